@@ -0,0 +1,8 @@
+package io.photondb;
+
+/** An exception thrown when an operation on a table fails. */
+public class PhotonDBException extends RuntimeException {
+    public PhotonDBException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,168 @@
+package io.photondb;
+
+import java.nio.ByteBuffer;
+import java.util.concurrent.atomic.AtomicInteger;
+
+/**
+ * A table that stores sorted key-value entries.
+ *
+ * <p>A table can be used by multiple threads, but must not be used after it is closed. Writes are
+ * assigned LSNs by the table, and reads see all writes that are done.
+ *
+ * <p>Keys and values can be passed as byte arrays, which are copied, or as direct {@link
+ * ByteBuffer}s, which are accessed in place between their positions and limits.
+ */
+public final class Table implements AutoCloseable {
+    static {
+        System.loadLibrary("photondb_jni");
+    }
+
+    private long handle;
+    private final AtomicInteger openIterators = new AtomicInteger();
+
+    private Table(long handle) {
+        this.handle = handle;
+    }
+
+    /** Opens a table in the path. */
+    public static Table open(String path) {
+        return new Table(openTable(path));
+    }
+
+    /** Returns the value of the key, or {@code null} if it is not found. */
+    public byte[] get(byte[] key) {
+        return get(handle(), key);
+    }
+
+    /**
+     * Copies the value of the key to the remaining space of the value buffer, and returns the
+     * length of the value, or -1 if it is not found.
+     *
+     * <p>If the value is longer than the remaining space, only a prefix of it is copied. The limit
+     * of the value buffer is set to the end of the copied value.
+     */
+    public int get(ByteBuffer key, ByteBuffer value) {
+        checkDirect(key);
+        checkDirect(value);
+        int len =
+                getDirect(
+                        handle(),
+                        key,
+                        key.position(),
+                        key.remaining(),
+                        value,
+                        value.position(),
+                        value.remaining());
+        if (len >= 0) {
+            value.limit(value.position() + Math.min(len, value.remaining()));
+        }
+        return len;
+    }
+
+    /** Puts a key-value entry to the table. */
+    public void put(byte[] key, byte[] value) {
+        put(handle(), key, value);
+    }
+
+    /** Puts a key-value entry to the table from direct buffers. */
+    public void put(ByteBuffer key, ByteBuffer value) {
+        checkDirect(key);
+        checkDirect(value);
+        putDirect(
+                handle(),
+                key,
+                key.position(),
+                key.remaining(),
+                value,
+                value.position(),
+                value.remaining());
+    }
+
+    /** Deletes the key from the table. */
+    public void delete(byte[] key) {
+        delete(handle(), key);
+    }
+
+    /** Deletes the key in a direct buffer from the table. */
+    public void delete(ByteBuffer key) {
+        checkDirect(key);
+        deleteDirect(handle(), key, key.position(), key.remaining());
+    }
+
+    /** Writes a batch to the table. */
+    public void write(WriteBatch batch) {
+        write(handle(), batch.handle());
+    }
+
+    /**
+     * Returns an iterator over the table, which is not positioned until it is sought.
+     *
+     * <p>The iterator must be closed before the table.
+     */
+    public TableIterator newIterator() {
+        TableIterator iter = new TableIterator(this, handle());
+        openIterators.incrementAndGet();
+        return iter;
+    }
+
+    void iteratorClosed() {
+        openIterators.decrementAndGet();
+    }
+
+    /** Closes the table. */
+    @Override
+    public void close() {
+        if (openIterators.get() > 0) {
+            throw new IllegalStateException("iterators must be closed before the table");
+        }
+        if (handle != 0) {
+            close(handle);
+            handle = 0;
+        }
+    }
+
+    private long handle() {
+        if (handle == 0) {
+            throw new IllegalStateException("the table is closed");
+        }
+        return handle;
+    }
+
+    private static void checkDirect(ByteBuffer buf) {
+        if (!buf.isDirect()) {
+            throw new IllegalArgumentException("the buffer must be direct");
+        }
+    }
+
+    private static native long openTable(String path);
+
+    private static native void close(long handle);
+
+    private static native byte[] get(long handle, byte[] key);
+
+    private static native int getDirect(
+            long handle,
+            ByteBuffer key,
+            int keyPos,
+            int keyLen,
+            ByteBuffer value,
+            int valuePos,
+            int valueLen);
+
+    private static native void put(long handle, byte[] key, byte[] value);
+
+    private static native void putDirect(
+            long handle,
+            ByteBuffer key,
+            int keyPos,
+            int keyLen,
+            ByteBuffer value,
+            int valuePos,
+            int valueLen);
+
+    private static native void delete(long handle, byte[] key);
+
+    private static native void deleteDirect(long handle, ByteBuffer key, int keyPos, int keyLen);
+
+    private static native void write(long handle, long batchHandle);
+}
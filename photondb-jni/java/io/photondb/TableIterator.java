@@ -0,0 +1,78 @@
+package io.photondb;
+
+/**
+ * An iterator over the entries in a table, in the order of keys.
+ *
+ * <p>The iterator reads the table in chunks, so it does not pin the table between calls, and may
+ * observe writes that are done during the iteration. An iterator is not thread-safe, and must be
+ * closed before the table.
+ */
+public final class TableIterator implements AutoCloseable {
+    private final Table table;
+    private long handle;
+
+    TableIterator(Table table, long tableHandle) {
+        this.table = table;
+        this.handle = create(tableHandle);
+    }
+
+    /** Positions the iterator at the first entry in the table. */
+    public void seekToFirst() {
+        seek(new byte[0]);
+    }
+
+    /** Positions the iterator at the first entry that is at or after the target. */
+    public void seek(byte[] target) {
+        seek(handle(), target);
+    }
+
+    /** Returns true if the iterator is positioned at an entry. */
+    public boolean isValid() {
+        return isValid(handle());
+    }
+
+    /** Moves the iterator to the next entry. */
+    public void next() {
+        next(handle());
+    }
+
+    /** Returns the key of the current entry. */
+    public byte[] key() {
+        return key(handle());
+    }
+
+    /** Returns the value of the current entry. */
+    public byte[] value() {
+        return value(handle());
+    }
+
+    @Override
+    public void close() {
+        if (handle != 0) {
+            dispose(handle);
+            handle = 0;
+            table.iteratorClosed();
+        }
+    }
+
+    private long handle() {
+        if (handle == 0) {
+            throw new IllegalStateException("the iterator is closed");
+        }
+        return handle;
+    }
+
+    private static native long create(long tableHandle);
+
+    private static native void dispose(long handle);
+
+    private static native void seek(long handle, byte[] target);
+
+    private static native boolean isValid(long handle);
+
+    private static native void next(long handle);
+
+    private static native byte[] key(long handle);
+
+    private static native byte[] value(long handle);
+}
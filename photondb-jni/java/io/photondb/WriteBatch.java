@@ -0,0 +1,61 @@
+package io.photondb;
+
+/**
+ * A batch of writes to a table, which share an LSN assigned when the batch is written.
+ *
+ * <p>A batch is not thread-safe.
+ */
+public final class WriteBatch implements AutoCloseable {
+    private long handle;
+
+    public WriteBatch() {
+        this.handle = create();
+    }
+
+    /** Adds a put of the key-value entry to the batch. */
+    public void put(byte[] key, byte[] value) {
+        put(handle(), key, value);
+    }
+
+    /** Adds a delete of the key to the batch. */
+    public void delete(byte[] key) {
+        delete(handle(), key);
+    }
+
+    /** Removes all writes from the batch. */
+    public void clear() {
+        clear(handle());
+    }
+
+    /** Returns the number of writes in the batch. */
+    public int count() {
+        return count(handle());
+    }
+
+    @Override
+    public void close() {
+        if (handle != 0) {
+            dispose(handle);
+            handle = 0;
+        }
+    }
+
+    long handle() {
+        if (handle == 0) {
+            throw new IllegalStateException("the batch is closed");
+        }
+        return handle;
+    }
+
+    private static native long create();
+
+    private static native void dispose(long handle);
+
+    private static native void put(long handle, byte[] key, byte[] value);
+
+    private static native void delete(long handle, byte[] key);
+
+    private static native void clear(long handle);
+
+    private static native int count(long handle);
+}
@@ -0,0 +1,468 @@
+//! JNI bindings for PhotonDB.
+//!
+//! This is the native side of the Java API in the `java` directory, which
+//! loads the library as `photondb_jni`. Tables, write batches and iterators
+//! are owned by boxes that are passed to Java as handles, and are freed when
+//! the Java objects are closed.
+//!
+//! Keys and values can be passed as byte arrays, which are copied, or as
+//! direct `ByteBuffer`s, which are read and written in place. The Java side
+//! passes the position and the remaining length of a direct buffer, so the
+//! native side never calls back into Java to access the buffer.
+//!
+//! Writes are assigned LSNs by the table, and reads see all writes that are
+//! done.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use jni::{
+    errors::Error as JniError,
+    objects::{JByteBuffer, JClass, JString},
+    sys::{jboolean, jbyteArray, jint, jlong, JNI_FALSE, JNI_TRUE},
+    JNIEnv,
+};
+use photondb::{std::Table, TableOptions, WriteBatch};
+
+/// The class of the exceptions thrown by the native methods.
+const EXCEPTION_CLASS: &str = "io/photondb/PhotonDBException";
+
+/// The number of entries that an iterator reads from the table at a time.
+const ITERATOR_CHUNK_SIZE: usize = 256;
+
+enum Error {
+    Jni(JniError),
+    Db(photondb::Error),
+    InvalidArgument(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jni(err) => write!(f, "JNI error: {err}"),
+            Self::Db(err) => write!(f, "{err}"),
+            Self::InvalidArgument(msg) => write!(f, "InvalidArgument {msg}"),
+        }
+    }
+}
+
+impl From<JniError> for Error {
+    fn from(err: JniError) -> Self {
+        Self::Jni(err)
+    }
+}
+
+impl From<photondb::Error> for Error {
+    fn from(err: photondb::Error) -> Self {
+        Self::Db(err)
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Runs the body of a native method, and throws an exception with `default`
+/// returned if it fails.
+///
+/// Panics are caught, since they must not unwind into the JVM.
+fn run<T>(env: &JNIEnv<'_>, default: T, f: impl FnOnce() -> Result<T>) -> T {
+    let err = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(v)) => return v,
+        // The exception is already pending.
+        Ok(Err(Error::Jni(JniError::JavaException))) => return default,
+        Ok(Err(err)) => err.to_string(),
+        Err(_) => "native method panicked".to_owned(),
+    };
+    let _ = env.throw_new(EXCEPTION_CLASS, err);
+    default
+}
+
+/// Returns the slice of a direct buffer in `[pos, pos + len)`.
+fn direct_slice<'e>(
+    env: &'e JNIEnv<'_>,
+    buf: JByteBuffer<'_>,
+    pos: jint,
+    len: jint,
+) -> Result<&'e mut [u8]> {
+    let buf = env.get_direct_buffer_address(buf)?;
+    let (pos, len) = (pos as usize, len as usize);
+    buf.get_mut(pos..pos + len).ok_or_else(|| {
+        Error::InvalidArgument(format!("range {pos}..{} is out of bounds", pos + len))
+    })
+}
+
+/// Returns the table of a handle.
+///
+/// # Safety
+///
+/// The handle must be returned by `open` and not closed.
+unsafe fn table<'a>(handle: jlong) -> &'a Table {
+    &*(handle as *const Table)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_openTable(
+    env: JNIEnv,
+    _: JClass,
+    path: JString,
+) -> jlong {
+    run(&env, 0, || {
+        let path = String::from(env.get_string(path)?);
+        let table = Table::open(path, TableOptions::default())?;
+        Ok(Box::into_raw(Box::new(table)) as jlong)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_close(env: JNIEnv, _: JClass, handle: jlong) {
+    run(&env, (), || {
+        let table = unsafe { Box::from_raw(handle as *mut Table) };
+        // The Java side closes iterators before the table, so this is the only
+        // reference to the table.
+        let _ = (*table).close();
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_get(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: jbyteArray,
+) -> jbyteArray {
+    run(&env, ptr::null_mut(), || {
+        let table = unsafe { table(handle) };
+        let key = env.convert_byte_array(key)?;
+        let guard = table.pin();
+        match guard.get(&key, u64::MAX)? {
+            Some(value) => Ok(env.byte_array_from_slice(value)?),
+            None => Ok(ptr::null_mut()),
+        }
+    })
+}
+
+/// Copies the value of the key to the value buffer, and returns the length of
+/// the value, or -1 if the key is not found.
+///
+/// If the value is longer than the buffer, only a prefix of it is copied.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_io_photondb_Table_getDirect(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: JByteBuffer,
+    key_pos: jint,
+    key_len: jint,
+    value: JByteBuffer,
+    value_pos: jint,
+    value_len: jint,
+) -> jint {
+    run(&env, -1, || {
+        let table = unsafe { table(handle) };
+        let key = direct_slice(&env, key, key_pos, key_len)?;
+        let buf = direct_slice(&env, value, value_pos, value_len)?;
+        // The value is copied from the page to the buffer without an
+        // intermediate copy.
+        let guard = table.pin();
+        match guard.get(key, u64::MAX)? {
+            Some(value) => {
+                let len = value.len().min(buf.len());
+                buf[..len].copy_from_slice(&value[..len]);
+                Ok(value.len() as jint)
+            }
+            None => Ok(-1),
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_put(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    run(&env, (), || {
+        let table = unsafe { table(handle) };
+        let key = env.convert_byte_array(key)?;
+        let value = env.convert_byte_array(value)?;
+        table.put_sequenced(&key, &value)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_io_photondb_Table_putDirect(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: JByteBuffer,
+    key_pos: jint,
+    key_len: jint,
+    value: JByteBuffer,
+    value_pos: jint,
+    value_len: jint,
+) {
+    run(&env, (), || {
+        let table = unsafe { table(handle) };
+        let key = direct_slice(&env, key, key_pos, key_len)?;
+        let value = direct_slice(&env, value, value_pos, value_len)?;
+        table.put_sequenced(key, value)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_delete(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: jbyteArray,
+) {
+    run(&env, (), || {
+        let table = unsafe { table(handle) };
+        let key = env.convert_byte_array(key)?;
+        table.delete_sequenced(&key)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_deleteDirect(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: JByteBuffer,
+    key_pos: jint,
+    key_len: jint,
+) {
+    run(&env, (), || {
+        let table = unsafe { table(handle) };
+        let key = direct_slice(&env, key, key_pos, key_len)?;
+        table.delete_sequenced(key)?;
+        Ok(())
+    })
+}
+
+/// Writes a batch with an LSN assigned by the table.
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_Table_write(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    batch_handle: jlong,
+) {
+    run(&env, (), || {
+        let table = unsafe { table(handle) };
+        let batch = unsafe { &*(batch_handle as *const Batch) };
+        let lsn = table.next_lsn();
+        let mut inner = WriteBatch::new();
+        for (key, value) in &batch.0 {
+            match value {
+                Some(value) => inner.put(key, lsn, value),
+                None => inner.delete(key, lsn),
+            }
+        }
+        table.write_batch(&inner)?;
+        Ok(())
+    })
+}
+
+/// The writes of a batch, which share an LSN assigned when the batch is
+/// written.
+#[derive(Default)]
+struct Batch(Vec<(Vec<u8>, Option<Vec<u8>>)>);
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_create(_: JNIEnv, _: JClass) -> jlong {
+    Box::into_raw(Box::<Batch>::default()) as jlong
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_dispose(_: JNIEnv, _: JClass, handle: jlong) {
+    drop(unsafe { Box::from_raw(handle as *mut Batch) });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_put(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    run(&env, (), || {
+        let batch = unsafe { &mut *(handle as *mut Batch) };
+        let key = env.convert_byte_array(key)?;
+        let value = env.convert_byte_array(value)?;
+        batch.0.push((key, Some(value)));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_delete(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    key: jbyteArray,
+) {
+    run(&env, (), || {
+        let batch = unsafe { &mut *(handle as *mut Batch) };
+        let key = env.convert_byte_array(key)?;
+        batch.0.push((key, None));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_clear(_: JNIEnv, _: JClass, handle: jlong) {
+    let batch = unsafe { &mut *(handle as *mut Batch) };
+    batch.0.clear();
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_WriteBatch_count(
+    _: JNIEnv,
+    _: JClass,
+    handle: jlong,
+) -> jint {
+    let batch = unsafe { &*(handle as *const Batch) };
+    batch.0.len() as jint
+}
+
+/// An iterator that reads the table in chunks, so that it does not pin the
+/// table between calls from Java.
+struct Iter {
+    table: Table,
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+    next_start: Option<Vec<u8>>,
+}
+
+impl Iter {
+    fn seek(&mut self, target: Vec<u8>) -> Result<()> {
+        self.entries.clear();
+        self.next_start = Some(target);
+        self.read_chunk()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.entries.pop_front();
+        if self.entries.is_empty() {
+            self.read_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> Result<()> {
+        let Some(start) = self.next_start.take() else {
+            return Ok(());
+        };
+        let guard = self.table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&start)?;
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            if self.entries.len() == ITERATOR_CHUNK_SIZE {
+                self.next_start = Some(key.to_vec());
+                break;
+            }
+            self.entries.push_back((key.to_vec(), value.to_vec()));
+            cursor.next()?;
+        }
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_create(
+    _: JNIEnv,
+    _: JClass,
+    table_handle: jlong,
+) -> jlong {
+    let table = unsafe { table(table_handle) }.clone();
+    let iter = Iter {
+        table,
+        entries: VecDeque::new(),
+        next_start: None,
+    };
+    Box::into_raw(Box::new(iter)) as jlong
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_dispose(_: JNIEnv, _: JClass, handle: jlong) {
+    drop(unsafe { Box::from_raw(handle as *mut Iter) });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_seek(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+    target: jbyteArray,
+) {
+    run(&env, (), || {
+        let iter = unsafe { &mut *(handle as *mut Iter) };
+        iter.seek(env.convert_byte_array(target)?)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_isValid(
+    _: JNIEnv,
+    _: JClass,
+    handle: jlong,
+) -> jboolean {
+    let iter = unsafe { &*(handle as *const Iter) };
+    if iter.entries.is_empty() {
+        JNI_FALSE
+    } else {
+        JNI_TRUE
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_next(env: JNIEnv, _: JClass, handle: jlong) {
+    run(&env, (), || {
+        let iter = unsafe { &mut *(handle as *mut Iter) };
+        iter.next()
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_key(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+) -> jbyteArray {
+    run(&env, ptr::null_mut(), || {
+        let iter = unsafe { &*(handle as *const Iter) };
+        let (key, _) = iter
+            .entries
+            .front()
+            .ok_or_else(|| Error::InvalidArgument("iterator is not valid".to_owned()))?;
+        Ok(env.byte_array_from_slice(key)?)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_photondb_TableIterator_value(
+    env: JNIEnv,
+    _: JClass,
+    handle: jlong,
+) -> jbyteArray {
+    run(&env, ptr::null_mut(), || {
+        let iter = unsafe { &*(handle as *const Iter) };
+        let (_, value) = iter
+            .entries
+            .front()
+            .ok_or_else(|| Error::InvalidArgument("iterator is not valid".to_owned()))?;
+        Ok(env.byte_array_from_slice(value)?)
+    })
+}
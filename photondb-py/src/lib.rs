@@ -0,0 +1,299 @@
+//! Python bindings for PhotonDB.
+//!
+//! The bindings are built into a `photondb` Python module with
+//! [maturin](https://github.com/PyO3/maturin):
+//!
+//! ```text
+//! cd photondb-py && maturin develop
+//! ```
+//!
+//! Keys and values can be any bytes-like objects, and are returned as
+//! `bytes`:
+//!
+//! ```python
+//! import photondb
+//!
+//! with photondb.Table("/tmp/photondb") as table:
+//!     table.put(b"key", b"value")
+//!     assert table.get(b"key") == b"value"
+//!     batch = photondb.WriteBatch()
+//!     batch.put(b"a", bytearray(b"1"))
+//!     batch.delete(b"key")
+//!     table.write(batch)
+//!     for key, value in table.iterate(start=b"a"):
+//!         print(key, value)
+//! ```
+//!
+//! Writes without an LSN are assigned one by the table, and reads without an
+//! LSN see all writes that are done. Operations release the GIL while they
+//! run.
+
+use std::{collections::VecDeque, path::PathBuf};
+
+use photondb::TableOptions;
+use pyo3::{
+    buffer::PyBuffer,
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+create_exception!(photondb, PhotonDBError, PyException);
+
+/// The number of entries that an iterator reads from the table at a time.
+const ITERATOR_CHUNK_SIZE: usize = 256;
+
+/// A table that can be used as a context manager, which closes the table on
+/// exit.
+#[pyclass]
+struct Table {
+    inner: Option<photondb::std::Table>,
+}
+
+#[pymethods]
+impl Table {
+    /// Opens a table in the path.
+    #[new]
+    fn new(py: Python<'_>, path: PathBuf) -> PyResult<Self> {
+        let table = py
+            .allow_threads(|| photondb::std::Table::open(path, TableOptions::default()))
+            .map_err(error)?;
+        Ok(Self { inner: Some(table) })
+    }
+
+    /// Gets the value of the key, or `None` if it is not found.
+    fn get(&self, py: Python<'_>, key: &PyAny, lsn: Option<u64>) -> PyResult<Option<PyObject>> {
+        let table = self.table()?;
+        let key = bytes_like(key)?;
+        let value = py
+            .allow_threads(|| table.get(&key, lsn.unwrap_or(u64::MAX)))
+            .map_err(error)?;
+        Ok(value.map(|v| PyBytes::new(py, &v).into()))
+    }
+
+    /// Puts a key-value entry to the table, and returns the LSN of the write.
+    fn put(&self, py: Python<'_>, key: &PyAny, value: &PyAny, lsn: Option<u64>) -> PyResult<u64> {
+        let table = self.table()?;
+        let (key, value) = (bytes_like(key)?, bytes_like(value)?);
+        py.allow_threads(|| match lsn {
+            Some(lsn) => table.put(&key, lsn, &value).map(|_| lsn),
+            None => table.put_sequenced(&key, &value),
+        })
+        .map_err(error)
+    }
+
+    /// Deletes the key from the table, and returns the LSN of the write.
+    fn delete(&self, py: Python<'_>, key: &PyAny, lsn: Option<u64>) -> PyResult<u64> {
+        let table = self.table()?;
+        let key = bytes_like(key)?;
+        py.allow_threads(|| match lsn {
+            Some(lsn) => table.delete(&key, lsn).map(|_| lsn),
+            None => table.delete_sequenced(&key),
+        })
+        .map_err(error)
+    }
+
+    /// Writes a batch to the table, and returns the LSN of the writes that are
+    /// added without one.
+    fn write(&self, py: Python<'_>, batch: &WriteBatch) -> PyResult<u64> {
+        let table = self.table()?;
+        let lsn = table.next_lsn();
+        let mut inner = photondb::WriteBatch::new();
+        for (key, op_lsn, value) in &batch.ops {
+            let op_lsn = op_lsn.unwrap_or(lsn);
+            match value {
+                Some(value) => inner.put(key, op_lsn, value),
+                None => inner.delete(key, op_lsn),
+            }
+        }
+        py.allow_threads(|| table.write_batch(&inner))
+            .map_err(error)?;
+        Ok(lsn)
+    }
+
+    /// Returns an iterator over the `(key, value)` pairs in `[start, end)`.
+    ///
+    /// The iterator reads the table in chunks, so it does not pin the table
+    /// between steps, and may observe writes that are done during the
+    /// iteration.
+    fn iterate(
+        slf: &PyCell<Self>,
+        start: Option<&PyAny>,
+        end: Option<&PyAny>,
+        lsn: Option<u64>,
+    ) -> PyResult<TableIterator> {
+        slf.borrow().table()?;
+        Ok(TableIterator {
+            table: slf.into(),
+            next_start: Some(start.map(bytes_like).transpose()?.unwrap_or_default()),
+            end: end.map(bytes_like).transpose()?,
+            lsn: lsn.unwrap_or(u64::MAX),
+            entries: VecDeque::new(),
+        })
+    }
+
+    /// Closes the table.
+    ///
+    /// Operations on a closed table raise `ValueError`.
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        if let Some(table) = self.inner.take() {
+            // Iterators only refer to this object, so this is the only
+            // reference to the table.
+            py.allow_threads(|| {
+                let _ = table.close();
+            });
+        }
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python<'_>,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
+
+impl Table {
+    fn table(&self) -> PyResult<&photondb::std::Table> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("the table is closed"))
+    }
+}
+
+/// A batch of writes, which is written to a table with `Table.write`.
+#[pyclass]
+#[derive(Default)]
+struct WriteBatch {
+    ops: Vec<(Vec<u8>, Option<u64>, Option<Vec<u8>>)>,
+}
+
+#[pymethods]
+impl WriteBatch {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a put of the key-value entry to the batch.
+    fn put(&mut self, key: &PyAny, value: &PyAny, lsn: Option<u64>) -> PyResult<()> {
+        self.ops
+            .push((bytes_like(key)?, lsn, Some(bytes_like(value)?)));
+        Ok(())
+    }
+
+    /// Adds a delete of the key to the batch.
+    fn delete(&mut self, key: &PyAny, lsn: Option<u64>) -> PyResult<()> {
+        self.ops.push((bytes_like(key)?, lsn, None));
+        Ok(())
+    }
+
+    /// Removes all writes from the batch.
+    fn clear(&mut self) {
+        self.ops.clear();
+    }
+
+    fn __len__(&self) -> usize {
+        self.ops.len()
+    }
+}
+
+/// An iterator over the entries in a range of a table.
+#[pyclass]
+struct TableIterator {
+    table: Py<Table>,
+    next_start: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+    lsn: u64,
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+}
+
+#[pymethods]
+impl TableIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<(PyObject, PyObject)>> {
+        if self.entries.is_empty() {
+            self.read_chunk(py)?;
+        }
+        Ok(self.entries.pop_front().map(|(key, value)| {
+            (
+                PyBytes::new(py, &key).into(),
+                PyBytes::new(py, &value).into(),
+            )
+        }))
+    }
+}
+
+impl TableIterator {
+    /// Reads the next chunk of entries from the table.
+    fn read_chunk(&mut self, py: Python<'_>) -> PyResult<()> {
+        let Some(start) = self.next_start.take() else {
+            return Ok(());
+        };
+        let table = self.table.borrow(py);
+        let table = table.table()?;
+        let (end, lsn) = (self.end.as_deref(), self.lsn);
+        let (entries, next_start) = py
+            .allow_threads(|| -> photondb::Result<_> {
+                let guard = table.pin();
+                let mut cursor = guard.cursor(lsn);
+                cursor.seek(&start)?;
+                let mut entries = Vec::new();
+                while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+                    if end.map_or(false, |end| key >= end) {
+                        break;
+                    }
+                    if entries.len() == ITERATOR_CHUNK_SIZE {
+                        return Ok((entries, Some(key.to_vec())));
+                    }
+                    entries.push((key.to_vec(), value.to_vec()));
+                    cursor.next()?;
+                }
+                Ok((entries, None))
+            })
+            .map_err(error)?;
+        self.entries.extend(entries);
+        self.next_start = next_start;
+        Ok(())
+    }
+}
+
+/// Copies the bytes of a bytes-like object.
+fn bytes_like(obj: &PyAny) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = obj.downcast::<PyBytes>() {
+        return Ok(bytes.as_bytes().to_vec());
+    }
+    PyBuffer::<u8>::get(obj)?.to_vec(obj.py())
+}
+
+fn error(err: photondb::Error) -> PyErr {
+    match err {
+        photondb::Error::InvalidArgument(msg) => PyValueError::new_err(msg),
+        photondb::Error::Io(err) => err.into(),
+        err => PhotonDBError::new_err(err.to_string()),
+    }
+}
+
+/// The `photondb` Python module.
+#[pymodule]
+#[pyo3(name = "photondb")]
+fn photondb_py(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Table>()?;
+    m.add_class::<WriteBatch>()?;
+    m.add_class::<TableIterator>()?;
+    m.add("PhotonDBError", py.get_type::<PhotonDBError>())?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! A tool to trigger maintenance on a running table.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use photondb::{Error, MaintenanceCommand};
+
+use crate::Result;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Submit maintenance commands to a running table")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The commands to run in order: flush, consolidate or reclaim.
+    #[arg(required = true)]
+    commands: Vec<MaintenanceCommand>,
+}
+
+/// Submits the commands through the admin file of the table.
+///
+/// The table must be opened with `admin_poll_period` set, and it logs the
+/// results of the commands.
+pub(crate) fn run(args: Args) -> Result<()> {
+    if !args.db.is_dir() {
+        return Err(Error::InvalidArgument(format!(
+            "{} is not a db data folder",
+            args.db.display()
+        )));
+    }
+    if let Err(err) = MaintenanceCommand::submit(&args.db, &args.commands) {
+        if matches!(err, Error::Busy) {
+            eprintln!("Commands submitted before are not picked up by the table yet");
+        }
+        return Err(err);
+    }
+    println!("Submitted {} commands", args.commands.len());
+    Ok(())
+}
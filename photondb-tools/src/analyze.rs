@@ -0,0 +1,104 @@
+//! A tool to analyze the space used by a table.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use photondb::SizeDistribution;
+
+use crate::{inspect::open_table, Result};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Report the space used by a table")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+}
+
+/// Reads all pages of the table and prints the space they use.
+pub(crate) async fn run(args: Args) -> Result<()> {
+    let table = open_table(&args.db).await?;
+    let report = table.analyze_space().await?;
+
+    let uncompressed_bytes: u64 = report.files.iter().map(|f| f.uncompressed_live_bytes).sum();
+    println!(
+        "Files: {}, file bytes: {}, live bytes: {}, dead bytes: {}, compression ratio: {:.2}",
+        report.files.len(),
+        report.file_bytes(),
+        report.live_bytes(),
+        report.dead_bytes(),
+        ratio(uncompressed_bytes, report.live_bytes()),
+    );
+    if report.num_unreadable_pages > 0 {
+        println!("Unreadable pages: {}", report.num_unreadable_pages);
+    }
+
+    println!();
+    println!(
+        "{:<20} {:>10} {:>12} {:>14} {:>14} {:>8}",
+        "kind", "pages", "items", "disk bytes", "raw bytes", "ratio"
+    );
+    for space in &report.page_kinds {
+        println!(
+            "{:<20} {:>10} {:>12} {:>14} {:>14} {:>8.2}",
+            space.kind,
+            space.num_pages,
+            space.num_items,
+            space.disk_bytes,
+            space.uncompressed_bytes,
+            ratio(space.uncompressed_bytes, space.disk_bytes),
+        );
+    }
+
+    println!();
+    println!(
+        "{:<10} {:>14} {:>14} {:>14} {:>8} {:>12}",
+        "file", "size", "live bytes", "dead bytes", "ratio", "compression"
+    );
+    for file in &report.files {
+        println!(
+            "{:<10} {:>14} {:>14} {:>14} {:>8.2} {:>12?}",
+            file.file_id,
+            file.file_size,
+            file.live_bytes,
+            file.dead_bytes,
+            ratio(file.uncompressed_live_bytes, file.live_bytes),
+            file.compression,
+        );
+    }
+
+    println!();
+    print_distribution("Key sizes", &report.key_sizes);
+    print_distribution("Value sizes", &report.value_sizes);
+    Ok(())
+}
+
+fn print_distribution(name: &str, dist: &SizeDistribution) {
+    println!(
+        "{name}: count {}, avg {:.1}, max {}",
+        dist.count,
+        ratio(dist.total, dist.count),
+        dist.max
+    );
+    for (i, &count) in dist.buckets.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let range = match i {
+            0 => "[0, 1)".to_owned(),
+            i if i + 1 == dist.buckets.len() => format!("[{}, inf)", 1u64 << (i - 1)),
+            i => format!("[{}, {})", 1u64 << (i - 1), 1u64 << i),
+        };
+        println!(
+            "  {range:<24} {count:>12} {:>6.2}%",
+            ratio(count * 100, dist.count)
+        );
+    }
+}
+
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
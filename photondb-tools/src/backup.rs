@@ -0,0 +1,527 @@
+//! Tools to manage backups of a table.
+//!
+//! A backup directory keeps the files of all backups in `files/`, and a
+//! `BACKUP_<id>` meta file that lists the files of each backup. Files are
+//! stored under their names, sizes and checksums, so backups share identical
+//! files.
+//!
+//! Page files are immutable once written, so a backup reuses the page files
+//! of its parent backup without reading them again, and only copies new page
+//! files and the manifest. Each meta file lists all files of its backup, so
+//! backups in a chain can be restored and pruned independently.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use photondb::Error;
+
+use crate::{inspect::lock_table, Result};
+
+/// The directory in the backup directory that keeps the files of backups.
+const FILES_DIR: &str = "files";
+const META_PREFIX: &str = "BACKUP_";
+const PAGE_FILE_PREFIX: &str = "map_";
+const LOCK_FILE_NAME: &str = "LOCK";
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Create, verify, restore and prune backups of a table")]
+pub(crate) struct Args {
+    #[command(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    Create(CreateArgs),
+    List(ListArgs),
+    Verify(VerifyArgs),
+    Restore(RestoreArgs),
+    Prune(PruneArgs),
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Back up a table")]
+struct CreateArgs {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// Path of the backup directory.
+    backup_dir: PathBuf,
+
+    /// Copies all files instead of reusing page files of the latest backup.
+    #[arg(long)]
+    full: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "List backups")]
+struct ListArgs {
+    /// Path of the backup directory.
+    backup_dir: PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Verify the sizes and checksums of backup files")]
+struct VerifyArgs {
+    /// Path of the backup directory.
+    backup_dir: PathBuf,
+
+    /// The backup to verify, all backups are verified if it is not given.
+    #[arg(long)]
+    id: Option<u64>,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Restore a backup to an empty db data folder")]
+struct RestoreArgs {
+    /// Path of the backup directory.
+    backup_dir: PathBuf,
+
+    /// Path of db data folder to restore to, which must be empty.
+    db: PathBuf,
+
+    /// The backup to restore, the latest backup is restored if it is not
+    /// given.
+    #[arg(long)]
+    id: Option<u64>,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Delete backups out of the retention")]
+struct PruneArgs {
+    /// Path of the backup directory.
+    backup_dir: PathBuf,
+
+    /// Keeps the latest N backups.
+    #[arg(long)]
+    keep_last: Option<usize>,
+
+    /// Keeps backups created in the last D days.
+    #[arg(long)]
+    keep_days: Option<u64>,
+}
+
+pub(crate) async fn run(args: Args) -> Result<()> {
+    match args.cmd {
+        Command::Create(args) => create(args).await,
+        Command::List(args) => list(args),
+        Command::Verify(args) => verify(args),
+        Command::Restore(args) => restore(args).await,
+        Command::Prune(args) => prune(args),
+    }
+}
+
+/// A file in a backup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BackupFile {
+    name: String,
+    size: u64,
+    checksum: u32,
+}
+
+impl BackupFile {
+    /// The name of the file in the files directory.
+    fn stored_name(&self) -> String {
+        format!("{}_{}_{:08x}", self.name, self.size, self.checksum)
+    }
+}
+
+/// The meta of a backup.
+///
+/// It is written as lines of `parent <id|none>`, `created <unix_secs>` and
+/// `file <name> <size> <checksum>`.
+#[derive(Clone, Debug)]
+struct BackupMeta {
+    id: u64,
+    parent: Option<u64>,
+    created: i64,
+    files: Vec<BackupFile>,
+}
+
+impl BackupMeta {
+    fn encode(&self) -> String {
+        let parent = self
+            .parent
+            .map_or_else(|| "none".to_owned(), |id| id.to_string());
+        let mut s = format!("parent {parent}\ncreated {}\n", self.created);
+        for file in &self.files {
+            s.push_str(&format!(
+                "file {} {} {:08x}\n",
+                file.name, file.size, file.checksum
+            ));
+        }
+        s
+    }
+
+    fn decode(id: u64, content: &str) -> Option<Self> {
+        let mut meta = Self {
+            id,
+            parent: None,
+            created: 0,
+            files: Vec::new(),
+        };
+        for line in content.lines() {
+            let fields: Vec<_> = line.split(' ').collect();
+            match fields[..] {
+                ["parent", "none"] => meta.parent = None,
+                ["parent", parent] => meta.parent = Some(parent.parse().ok()?),
+                ["created", created] => meta.created = created.parse().ok()?,
+                ["file", name, size, checksum] => meta.files.push(BackupFile {
+                    name: name.to_owned(),
+                    size: size.parse().ok()?,
+                    checksum: u32::from_str_radix(checksum, 16).ok()?,
+                }),
+                _ => return None,
+            }
+        }
+        Some(meta)
+    }
+
+    fn size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Reads the metas of all backups, ordered by ids.
+fn read_metas(backup_dir: &Path) -> Result<Vec<BackupMeta>> {
+    let mut metas = Vec::new();
+    if !backup_dir.exists() {
+        return Ok(metas);
+    }
+    for entry in fs::read_dir(backup_dir)? {
+        let name = entry?.file_name();
+        let Some(id) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(META_PREFIX))
+            .and_then(|id| id.parse().ok())
+        else {
+            continue;
+        };
+        let path = backup_dir.join(&name);
+        let content = fs::read_to_string(&path)?;
+        let meta = BackupMeta::decode(id, &content).ok_or_else(|| {
+            Error::InvalidArgument(format!("invalid backup meta {}", path.display()))
+        })?;
+        metas.push(meta);
+    }
+    metas.sort_unstable_by_key(|meta| meta.id);
+    Ok(metas)
+}
+
+fn find_meta(metas: Vec<BackupMeta>, id: Option<u64>) -> Result<BackupMeta> {
+    let meta = match id {
+        Some(id) => metas.into_iter().find(|meta| meta.id == id),
+        None => metas.into_iter().last(),
+    };
+    meta.ok_or_else(|| match id {
+        Some(id) => Error::InvalidArgument(format!("backup {id} is not found")),
+        None => Error::InvalidArgument("no backup is found".to_owned()),
+    })
+}
+
+/// Copies the file from `src` to `dst`, and returns the size and checksum of
+/// the copied content.
+fn copy_file(src: &Path, dst: &Path) -> Result<(u64, u32)> {
+    let mut writer = File::create(dst)?;
+    let result = read_file(src, &mut writer)?;
+    writer.sync_all()?;
+    Ok(result)
+}
+
+/// Returns the size and checksum of the file.
+fn checksum_file(path: &Path) -> Result<(u64, u32)> {
+    read_file(path, &mut io::sink())
+}
+
+fn read_file<W: Write>(path: &Path, writer: &mut W) -> Result<(u64, u32)> {
+    let mut reader = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 1 << 20];
+    let mut size = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    Ok((size, hasher.finalize()))
+}
+
+/// Writes the content to the path atomically.
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Copies the files of the table to a new backup.
+///
+/// The table is opened during the backup, so it can not be modified by
+/// others, and no page files are reclaimed.
+async fn create(args: CreateArgs) -> Result<()> {
+    let table = lock_table(&args.db).await?;
+    let files_dir = args.backup_dir.join(FILES_DIR);
+    fs::create_dir_all(&files_dir)?;
+    let metas = read_metas(&args.backup_dir)?;
+    let id = metas.last().map_or(1, |meta| meta.id + 1);
+    let parent = if args.full { None } else { metas.last() };
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&args.db)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            if name != LOCK_FILE_NAME {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort_unstable();
+
+    let mut files = Vec::with_capacity(names.len());
+    let (mut num_copied, mut num_reused) = (0, 0);
+    for name in names {
+        let path = args.db.join(&name);
+        let size = fs::metadata(&path)?.len();
+        if name.starts_with(PAGE_FILE_PREFIX) {
+            let reused = parent.and_then(|parent| {
+                parent
+                    .files
+                    .iter()
+                    .find(|f| f.name == name && f.size == size)
+            });
+            if let Some(file) = reused {
+                if files_dir.join(file.stored_name()).exists() {
+                    files.push(file.clone());
+                    num_reused += 1;
+                    continue;
+                }
+            }
+        }
+        let tmp = files_dir.join(format!("{name}.tmp"));
+        let (size, checksum) = copy_file(&path, &tmp)?;
+        let file = BackupFile {
+            name,
+            size,
+            checksum,
+        };
+        fs::rename(&tmp, files_dir.join(file.stored_name()))?;
+        files.push(file);
+        num_copied += 1;
+    }
+
+    let meta = BackupMeta {
+        id,
+        parent: parent.map(|parent| parent.id),
+        created: Utc::now().timestamp(),
+        files,
+    };
+    write_atomic(
+        &args.backup_dir.join(format!("{META_PREFIX}{id}")),
+        &meta.encode(),
+    )?;
+    let _ = table.close().await;
+    println!(
+        "Created backup {id} with {} bytes, {num_copied} files copied, {num_reused} files reused",
+        meta.size()
+    );
+    Ok(())
+}
+
+fn list(args: ListArgs) -> Result<()> {
+    println!(
+        "{:<8} {:<8} {:<20} {:>8} {:>14}",
+        "id", "parent", "created", "files", "size"
+    );
+    for meta in read_metas(&args.backup_dir)? {
+        let parent = meta
+            .parent
+            .map_or_else(|| "-".to_owned(), |id| id.to_string());
+        let created = DateTime::<Utc>::from_timestamp(meta.created, 0).map_or_else(
+            || meta.created.to_string(),
+            |t| t.format("%F %T").to_string(),
+        );
+        println!(
+            "{:<8} {parent:<8} {created:<20} {:>8} {:>14}",
+            meta.id,
+            meta.files.len(),
+            meta.size()
+        );
+    }
+    Ok(())
+}
+
+/// Verifies the files of backups.
+///
+/// Exits with 1 if any file is missing or corrupted.
+fn verify(args: VerifyArgs) -> Result<()> {
+    let mut metas = read_metas(&args.backup_dir)?;
+    if let Some(id) = args.id {
+        metas = vec![find_meta(metas, Some(id))?];
+    }
+    let files_dir = args.backup_dir.join(FILES_DIR);
+    let mut verified = HashSet::new();
+    let mut ok = true;
+    for meta in &metas {
+        let mut num_bad_files = 0;
+        for file in &meta.files {
+            let stored_name = file.stored_name();
+            if verified.contains(&stored_name) {
+                continue;
+            }
+            match checksum_file(&files_dir.join(&stored_name)) {
+                Ok((size, checksum)) if size == file.size && checksum == file.checksum => {
+                    verified.insert(stored_name);
+                }
+                Ok(_) => {
+                    println!("Backup {}: file {} is corrupted", meta.id, file.name);
+                    num_bad_files += 1;
+                }
+                Err(err) => {
+                    println!(
+                        "Backup {}: file {} is unreadable: {err}",
+                        meta.id, file.name
+                    );
+                    num_bad_files += 1;
+                }
+            }
+        }
+        if num_bad_files == 0 {
+            println!("Backup {}: ok", meta.id);
+        } else {
+            ok = false;
+        }
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Copies the files of a backup to the db data folder, and checks that the
+/// restored table can be opened.
+async fn restore(args: RestoreArgs) -> Result<()> {
+    let meta = find_meta(read_metas(&args.backup_dir)?, args.id)?;
+    if args.db.exists() && fs::read_dir(&args.db)?.next().is_some() {
+        return Err(Error::InvalidArgument(format!(
+            "{} is not empty",
+            args.db.display()
+        )));
+    }
+    fs::create_dir_all(&args.db)?;
+    let files_dir = args.backup_dir.join(FILES_DIR);
+    for file in &meta.files {
+        let (size, checksum) = copy_file(
+            &files_dir.join(file.stored_name()),
+            &args.db.join(&file.name),
+        )?;
+        if size != file.size || checksum != file.checksum {
+            return Err(Error::InvalidArgument(format!(
+                "file {} of backup {} is corrupted",
+                file.name, meta.id
+            )));
+        }
+    }
+    let table = lock_table(&args.db).await?;
+    let _ = table.close().await;
+    println!("Restored backup {} to {}", meta.id, args.db.display());
+    Ok(())
+}
+
+/// Deletes backups that are neither in the latest `keep_last` backups nor
+/// created in the last `keep_days` days, and then deletes the files that are
+/// not used by the remaining backups.
+fn prune(args: PruneArgs) -> Result<()> {
+    if args.keep_last.is_none() && args.keep_days.is_none() {
+        return Err(Error::InvalidArgument(
+            "at least one of --keep-last and --keep-days is required".to_owned(),
+        ));
+    }
+    let metas = read_metas(&args.backup_dir)?;
+    let keep_since = args
+        .keep_days
+        .map(|days| Utc::now().timestamp() - (days * 24 * 60 * 60) as i64);
+    let num_kept_by_count = args.keep_last.unwrap_or(0).min(metas.len());
+    let first_kept_by_count = metas.len() - num_kept_by_count;
+
+    let mut used = HashSet::new();
+    for (i, meta) in metas.iter().enumerate() {
+        let keep = i >= first_kept_by_count || keep_since.map_or(false, |t| meta.created >= t);
+        if keep {
+            used.extend(meta.files.iter().map(|f| f.stored_name()));
+        } else {
+            fs::remove_file(args.backup_dir.join(format!("{META_PREFIX}{}", meta.id)))?;
+            println!("Deleted backup {}", meta.id);
+        }
+    }
+
+    let files_dir = args.backup_dir.join(FILES_DIR);
+    if !files_dir.exists() {
+        return Ok(());
+    }
+    let mut num_bytes = 0;
+    for entry in fs::read_dir(&files_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_str().map_or(false, |name| used.contains(name)) {
+            continue;
+        }
+        num_bytes += entry.metadata()?.len();
+        fs::remove_file(entry.path())?;
+    }
+    println!("Deleted {num_bytes} bytes of unused files");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_meta() {
+        let meta = BackupMeta {
+            id: 2,
+            parent: Some(1),
+            created: 1_600_000_000,
+            files: vec![
+                BackupFile {
+                    name: "CURRENT".to_owned(),
+                    size: 10,
+                    checksum: 0xabcd,
+                },
+                BackupFile {
+                    name: "map_1".to_owned(),
+                    size: 4096,
+                    checksum: 0xffff_ffff,
+                },
+            ],
+        };
+        let decoded = BackupMeta::decode(2, &meta.encode()).unwrap();
+        assert_eq!(decoded.parent, Some(1));
+        assert_eq!(decoded.created, meta.created);
+        assert_eq!(decoded.files, meta.files);
+        assert_eq!(decoded.size(), 4106);
+        assert_eq!(meta.files[0].stored_name(), "CURRENT_10_0000abcd");
+
+        let meta = BackupMeta::decode(1, "parent none\ncreated 1\n").unwrap();
+        assert_eq!(meta.parent, None);
+        assert!(meta.files.is_empty());
+        assert!(BackupMeta::decode(1, "file map_1 x 0").is_none());
+    }
+}
@@ -66,6 +66,16 @@ pub(crate) struct Args {
     #[arg(long, default_value_t = 90)]
     read_write_percent: u32,
 
+    /// Number of entries to read after each seek in the seekrandom workload
+    /// and the scans of ycsb workloads.
+    #[arg(long, default_value_t = 10)]
+    seek_nexts: u64,
+
+    /// Maximum number of operations per second of each thread.
+    /// 0 means no limit.
+    #[arg(long, default_value_t = 0)]
+    ops_per_sec: u64,
+
     /// Number of concurrent threads to run.
     #[arg(short, long, default_value_t = 1)]
     threads: u64,
@@ -168,6 +178,14 @@ enum BenchmarkType {
     ReadRandom,
     UpdateRandom,
     ReadRandomWriteRandom,
+    ReadWhileWriting,
+    SeekRandom,
+    YcsbA,
+    YcsbB,
+    YcsbC,
+    YcsbD,
+    YcsbE,
+    YcsbF,
     Flush,
     WaitForReclaiming,
 }
@@ -179,6 +197,37 @@ impl BenchmarkType {
             BenchmarkType::Flush | BenchmarkType::WaitForReclaiming
         )
     }
+
+    /// Returns the percentages of reads, updates, scans and
+    /// read-modify-writes of a ycsb workload.
+    ///
+    /// Inserts are run as updates of random keys.
+    pub(crate) fn ycsb_mix(&self) -> Option<YcsbMix> {
+        let (read, update, scan, rmw) = match self {
+            BenchmarkType::YcsbA => (50, 50, 0, 0),
+            BenchmarkType::YcsbB => (95, 5, 0, 0),
+            BenchmarkType::YcsbC => (100, 0, 0, 0),
+            BenchmarkType::YcsbD => (95, 5, 0, 0),
+            BenchmarkType::YcsbE => (0, 5, 95, 0),
+            BenchmarkType::YcsbF => (50, 0, 0, 50),
+            _ => return None,
+        };
+        Some(YcsbMix {
+            read,
+            update,
+            scan,
+            rmw,
+        })
+    }
+}
+
+/// The percentages of operations in a ycsb workload.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct YcsbMix {
+    read: u32,
+    update: u32,
+    scan: u32,
+    rmw: u32,
 }
 
 impl From<&str> for BenchmarkType {
@@ -190,6 +239,14 @@ impl From<&str> for BenchmarkType {
             "readrandom" => BenchmarkType::ReadRandom,
             "updaterandom" => BenchmarkType::UpdateRandom,
             "readrandomwriterandom" => BenchmarkType::ReadRandomWriteRandom,
+            "readwhilewriting" => BenchmarkType::ReadWhileWriting,
+            "seekrandom" => BenchmarkType::SeekRandom,
+            "ycsba" => BenchmarkType::YcsbA,
+            "ycsbb" => BenchmarkType::YcsbB,
+            "ycsbc" => BenchmarkType::YcsbC,
+            "ycsbd" => BenchmarkType::YcsbD,
+            "ycsbe" => BenchmarkType::YcsbE,
+            "ycsbf" => BenchmarkType::YcsbF,
             "flush" => BenchmarkType::Flush,
             "waitforreclaiming" => BenchmarkType::WaitForReclaiming,
             _ => panic!("invalid benchmark type"),
@@ -16,6 +16,11 @@ pub(crate) trait Store<E>: std::fmt::Debug + Clone + Sync + Send + 'static {
 
     async fn get(&self, key: &[u8], lsn: u64) -> Result<Option<Vec<u8>>>;
 
+    /// Seeks to the key and reads at most `limit` entries from there.
+    ///
+    /// Returns the number of entries and bytes read.
+    async fn seek(&self, key: &[u8], lsn: u64, limit: usize) -> Result<(usize, usize)>;
+
     async fn flush(&self);
 
     async fn wait_for_reclaiming(&self);
@@ -61,6 +61,22 @@ where
         Ok(r)
     }
 
+    async fn seek(&self, key: &[u8], lsn: u64, limit: usize) -> Result<(usize, usize)> {
+        let guard = self.table.pin();
+        let mut cursor = guard.cursor(lsn);
+        cursor.seek(key).await.expect("seek fail");
+        let (mut entries, mut bytes) = (0, 0);
+        while entries < limit {
+            let (Some(k), Some(v)) = (cursor.key(), cursor.value()) else {
+                break;
+            };
+            entries += 1;
+            bytes += k.len() + v.len();
+            cursor.next().await.expect("next fail");
+        }
+        Ok((entries, bytes))
+    }
+
     async fn flush(&self) {
         self.table.flush(&FlushOptions::default()).await;
     }
@@ -361,6 +361,12 @@ impl<S: Store<E>, E: Env> Stats<S, E> {
         }
     }
 
+    /// Starts timing the next operation from now, so that the time waiting
+    /// for the rate limiter is not counted in its latency.
+    pub(super) fn start_operation(&mut self) {
+        self.last_op_finish = Some(Instant::now());
+    }
+
     pub(super) fn add_msg(&mut self, msg: &str) {
         if msg.is_empty() {
             return;
@@ -447,7 +453,7 @@ impl<S: Store<E>, E: Env> Stats<S, E> {
 fn display_hist(hists: &HashMap<OpType, Histogram<u64>>, avg: f64) {
     for (op, hist) in hists {
         println!(
-            "Percentiles_{:12?} : P50: {} ms, P75: {} ms, P99: {} ms, P99.9: {} ms, P99.99: {} ms, Min: {} ms, Max: {} ms, AVG: {} ms",
+            "Percentiles_{:12?} : P50: {} micros, P75: {} micros, P99: {} micros, P99.9: {} micros, P99.99: {} micros, Min: {} micros, Max: {} micros, AVG: {} micros",
             op,
             hist.value_at_quantile(0.50),
             hist.value_at_quantile(0.75),
@@ -466,6 +472,7 @@ pub enum OpType {
     Write,
     Read,
     Update,
+    Seek,
 }
 
 #[derive(Clone)]
@@ -518,6 +525,40 @@ impl Future for Barrier {
     }
 }
 
+/// Limits the rate of operations of a task.
+#[derive(Clone)]
+pub(super) struct RateLimiter {
+    interval: Option<Duration>,
+    next: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter that allows `ops_per_sec` operations per second.
+    /// 0 means no limit.
+    pub(super) fn new(ops_per_sec: u64) -> Self {
+        let interval = (ops_per_sec > 0).then(|| Duration::from_nanos(1_000_000_000 / ops_per_sec));
+        Self {
+            interval,
+            next: Instant::now(),
+        }
+    }
+
+    /// Blocks until the next operation is allowed.
+    ///
+    /// Returns true if the rate is limited.
+    pub(super) fn wait(&mut self) -> bool {
+        let Some(interval) = self.interval else {
+            return false;
+        };
+        let now = Instant::now();
+        if self.next > now {
+            std::thread::sleep(self.next - now);
+        }
+        self.next = self.next.max(now) + interval;
+        true
+    }
+}
+
 pub(super) struct Until {
     want_cnt: u64,
     done_cnt: u64,
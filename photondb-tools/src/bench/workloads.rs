@@ -2,15 +2,19 @@ use std::{
     cell::RefCell,
     marker::PhantomData,
     rc::Rc,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use photondb::{env::Env, TableStats};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use regex::Regex;
 
 use super::{util::*, *};
-use crate::bench::{Args, BenchOperation, BenchmarkType, Result};
+use crate::bench::{Args, BenchOperation, BenchmarkType, Result, YcsbMix};
 
 pub(super) struct Workloads<S: Store<E>, E: Env> {
     ctx: WorkloadContext,
@@ -140,12 +144,20 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
 
         let thread_num = self.config.threads;
         assert!(thread_num > 0);
+        let read_while_writing = matches!(op.benchmark_type, BenchmarkType::ReadWhileWriting);
+        // The last thread writes until all the other threads finish reading.
+        assert!(
+            !read_while_writing || thread_num > 1,
+            "readwhilewriting needs at least 2 threads"
+        );
+        let running_readers = Arc::new(AtomicU64::new(thread_num - 1));
         let barrier = Barrier::new(thread_num);
         let mut handles = Vec::with_capacity(thread_num as usize);
         let mut ctxs = Vec::with_capacity(thread_num as usize);
         for tid in 0..thread_num as u32 {
             let seed_offset = self.ctx.total_task_offset;
             self.ctx.total_task_offset += 1;
+            let is_writer = read_while_writing && tid as u64 == thread_num - 1;
             let task_ctx = TaskCtx {
                 config: self.config.to_owned(),
                 table: self.table.as_ref().unwrap().clone(),
@@ -157,9 +169,15 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
                 _barrier: barrier.clone(),
                 op: op.to_owned(),
                 seed: self.config.seed_base + seed_offset,
+                limiter: RateLimiter::new(self.config.ops_per_sec),
+                is_writer,
+                running_readers: running_readers.clone(),
                 _mark: PhantomData,
             };
-            ctxs.push(task_ctx.to_owned());
+            // The stats of the writer are not reported.
+            if !is_writer {
+                ctxs.push(task_ctx.to_owned());
+            }
 
             let handle = self.env.spawn_background(async move {
                 // FIXME: await barrier seems let all task joined...
@@ -177,6 +195,22 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
                     BenchmarkType::ReadRandomWriteRandom => {
                         Self::do_read_random_write_random(&mut task_ctx).await
                     }
+                    BenchmarkType::ReadWhileWriting if task_ctx.is_writer => {
+                        Self::do_write_while_reading(&mut task_ctx).await
+                    }
+                    BenchmarkType::ReadWhileWriting => {
+                        Self::do_read_random(&mut task_ctx).await;
+                        task_ctx.running_readers.fetch_sub(1, Ordering::Release);
+                    }
+                    BenchmarkType::SeekRandom => Self::do_seek_random(&mut task_ctx).await,
+                    typ @ (BenchmarkType::YcsbA
+                    | BenchmarkType::YcsbB
+                    | BenchmarkType::YcsbC
+                    | BenchmarkType::YcsbD
+                    | BenchmarkType::YcsbE
+                    | BenchmarkType::YcsbF) => {
+                        Self::do_ycsb(&mut task_ctx, typ.ycsb_mix().unwrap()).await
+                    }
                     _ => unimplemented!(),
                 }
                 task_ctx.stats.as_ref().borrow_mut().stop();
@@ -249,6 +283,7 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
             ctx.config.value_size,
         );
         for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
             let mut key = vec![0u8; ctx.config.key_size as usize];
             key_gen.generate_key(&mut key);
             let value = value_gen.generate_value();
@@ -285,6 +320,7 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
         let mut founds = 0;
 
         for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
             let mut key = vec![0u8; ctx.config.key_size as usize];
             key_gen.generate_key(&mut key);
             reads += 1;
@@ -329,6 +365,7 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
         );
 
         for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
             let mut bytes = 0;
             let mut key = vec![0u8; ctx.config.key_size as usize];
             key_gen.generate_key(&mut key);
@@ -378,6 +415,7 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
         let mut read_weight = 0;
         let mut write_weight = 0;
         for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
             if read_weight == 0 && write_weight == 0 {
                 read_weight = cfg.read_write_percent;
                 write_weight = 100 - read_weight;
@@ -427,6 +465,157 @@ impl<S: Store<E>, E: Env> Workloads<S, E> {
         );
         ctx.stats.borrow_mut().add_msg(&msg);
     }
+
+    async fn do_write_while_reading(ctx: &mut TaskCtx<S, E>) {
+        let table = ctx.table.clone();
+        let mut key_gen = KeyGenerator::new(
+            GenMode::Random,
+            ctx.config.key_size,
+            ctx.config.num,
+            ctx.seed,
+            ctx.config.key_rand_dist,
+        );
+        let mut value_gen = ValueGenerator::new(
+            ctx.config.value_size_distribution_type,
+            ctx.config.value_size,
+        );
+        while ctx.running_readers.load(Ordering::Acquire) > 0 {
+            ctx.throttle();
+            let mut key = vec![0u8; ctx.config.key_size as usize];
+            key_gen.generate_key(&mut key);
+            let value = value_gen.generate_value();
+            table.put(&key, 0, value).await.unwrap();
+
+            photonio::task::yield_now().await;
+        }
+    }
+
+    async fn do_seek_random(ctx: &mut TaskCtx<S, E>) {
+        let table = ctx.table.clone();
+        let cfg = ctx.config.to_owned();
+        let op_cnt = if cfg.reads >= 0 {
+            cfg.reads as u64
+        } else {
+            cfg.num
+        };
+
+        let mut key_gen = KeyGenerator::new(
+            GenMode::Random,
+            ctx.config.key_size,
+            ctx.config.num,
+            ctx.seed,
+            ctx.config.key_rand_dist,
+        );
+
+        let mut seeks = 0;
+        let mut founds = 0;
+
+        for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
+            let mut key = vec![0u8; ctx.config.key_size as usize];
+            key_gen.generate_key(&mut key);
+            let (entries, bytes) = table
+                .seek(&key, 0, cfg.seek_nexts as usize)
+                .await
+                .expect("seek fail");
+            seeks += 1;
+            if entries > 0 {
+                founds += 1;
+            }
+            ctx.stats
+                .borrow_mut()
+                .finish_operation(OpType::Seek, 1, 0, bytes as u64);
+        }
+        let msg = format!("(seeks:{seeks} founds:{founds})");
+        ctx.stats.borrow_mut().add_msg(&msg);
+    }
+
+    async fn do_ycsb(ctx: &mut TaskCtx<S, E>, mix: YcsbMix) {
+        let table = ctx.table.clone();
+        let cfg = ctx.config.to_owned();
+        let op_cnt = if cfg.read_writes >= 0 {
+            cfg.read_writes as u64
+        } else {
+            cfg.num
+        };
+
+        let mut rng = SmallRng::seed_from_u64(!ctx.seed);
+        let mut key_gen = KeyGenerator::new(
+            GenMode::Random,
+            ctx.config.key_size,
+            ctx.config.num,
+            ctx.seed,
+            ctx.config.key_rand_dist,
+        );
+        let mut val_gen = ValueGenerator::new(
+            ctx.config.value_size_distribution_type,
+            ctx.config.value_size,
+        );
+
+        let (mut reads, mut updates, mut scans, mut rmws) = (0, 0, 0, 0);
+        let mut founds = 0;
+
+        for _ in Until::new(op_cnt, cfg.duration) {
+            ctx.throttle();
+            let mut key = vec![0u8; ctx.config.key_size as usize];
+            key_gen.generate_key(&mut key);
+
+            let dice = rng.gen_range(0..100);
+            if dice < mix.read {
+                reads += 1;
+                if let Some(v) = table.get(&key, 0).await.expect("get key fail") {
+                    founds += 1;
+                    let bytes = key.len() + v.len() + std::mem::size_of::<u64>();
+                    ctx.stats
+                        .borrow_mut()
+                        .finish_operation(OpType::Read, 1, 0, bytes as u64);
+                } else {
+                    ctx.stats
+                        .borrow_mut()
+                        .finish_operation(OpType::Read, 0, 1, 0);
+                }
+            } else if dice < mix.read + mix.update {
+                updates += 1;
+                let value = val_gen.generate_value();
+                table.put(&key, 0, value).await.expect("put fail");
+                let bytes = key.len() + value.len() + std::mem::size_of::<u64>();
+                ctx.stats
+                    .borrow_mut()
+                    .finish_operation(OpType::Write, 1, 0, bytes as u64);
+            } else if dice < mix.read + mix.update + mix.scan {
+                scans += 1;
+                let (entries, bytes) = table
+                    .seek(&key, 0, cfg.seek_nexts as usize)
+                    .await
+                    .expect("seek fail");
+                if entries > 0 {
+                    founds += 1;
+                }
+                ctx.stats
+                    .borrow_mut()
+                    .finish_operation(OpType::Seek, 1, 0, bytes as u64);
+            } else {
+                debug_assert!(dice < mix.read + mix.update + mix.scan + mix.rmw);
+                rmws += 1;
+                let mut bytes = 0;
+                if let Some(ov) = table.get(&key, 0).await.expect("read of rmw fail") {
+                    founds += 1;
+                    bytes += key.len() + ov.len() + std::mem::size_of::<u64>();
+                }
+                let value = val_gen.generate_value();
+                table.put(&key, 0, value).await.expect("put of rmw fail");
+                bytes += key.len() + value.len() + std::mem::size_of::<u64>();
+                ctx.stats
+                    .borrow_mut()
+                    .finish_operation(OpType::Update, 1, 0, bytes as u64);
+            }
+
+            photonio::task::yield_now().await;
+        }
+        let msg =
+            format!("(reads:{reads} updates:{updates} scans:{scans} rmws:{rmws} founds:{founds})");
+        ctx.stats.borrow_mut().add_msg(&msg);
+    }
 }
 
 #[derive(Clone)]
@@ -437,9 +626,23 @@ pub(crate) struct TaskCtx<S: Store<E>, E: Env> {
     table: S,
     config: Arc<Args>,
     seed: u64,
+    limiter: RateLimiter,
+    // Whether this task is the writer of the readwhilewriting workload.
+    is_writer: bool,
+    // The number of reader tasks that are still running.
+    running_readers: Arc<AtomicU64>,
     _mark: PhantomData<E>,
 }
 
+impl<S: Store<E>, E: Env> TaskCtx<S, E> {
+    /// Waits for the rate limiter before an operation.
+    fn throttle(&mut self) {
+        if self.limiter.wait() {
+            self.stats.borrow_mut().start_operation();
+        }
+    }
+}
+
 unsafe impl<S: Store<E>, E: Env> Sync for TaskCtx<S, E> {}
 
 unsafe impl<S: Store<E>, E: Env> Send for TaskCtx<S, E> {}
@@ -0,0 +1,124 @@
+//! A tool to compare the entries of two tables.
+
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+
+use crate::{
+    inspect::{open_table, print_entry, Format, READ_LSN},
+    Result,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Compare the entries of two tables")]
+pub(crate) struct Args {
+    /// Path of the first db data folder.
+    a: PathBuf,
+
+    /// Path of the second db data folder.
+    b: PathBuf,
+
+    /// The maximum number of differences to print.
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = Format::Escaped)]
+    format: Format,
+}
+
+#[derive(Default)]
+struct DiffStats {
+    num_keys: u64,
+    only_in_a: u64,
+    only_in_b: u64,
+    different: u64,
+}
+
+impl DiffStats {
+    fn num_diffs(&self) -> u64 {
+        self.only_in_a + self.only_in_b + self.different
+    }
+}
+
+/// Scans both tables in key order and prints the entries that differ.
+///
+/// Entries only in the first table or with a different value are prefixed
+/// with `-`, and entries only in the second table or with a different value
+/// are prefixed with `+`. Exits with 1 if any difference is found.
+pub(crate) async fn run(args: Args) -> Result<()> {
+    let a = open_table(&args.a).await?;
+    let b = open_table(&args.b).await?;
+    let limit = args.limit.unwrap_or(usize::MAX) as u64;
+    let mut stdout = io::stdout().lock();
+    let mut stats = DiffStats::default();
+    {
+        let (guard_a, guard_b) = (a.pin(), b.pin());
+        let mut cursor_a = guard_a.cursor(READ_LSN);
+        let mut cursor_b = guard_b.cursor(READ_LSN);
+        cursor_a.seek(&[]).await?;
+        cursor_b.seek(&[]).await?;
+        while stats.num_diffs() < limit {
+            let entry_a = cursor_a.key().zip(cursor_a.value());
+            let entry_b = cursor_b.key().zip(cursor_b.value());
+            let order = match (entry_a, entry_b) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((key_a, _)), Some((key_b, _))) => key_a.cmp(key_b),
+            };
+            stats.num_keys += 1;
+            match (order, entry_a, entry_b) {
+                (Ordering::Less, Some((key, value)), _) => {
+                    stats.only_in_a += 1;
+                    print_diff(&mut stdout, '-', args.format, key, value)?;
+                }
+                (Ordering::Greater, _, Some((key, value))) => {
+                    stats.only_in_b += 1;
+                    print_diff(&mut stdout, '+', args.format, key, value)?;
+                }
+                (Ordering::Equal, Some((key, value_a)), Some((_, value_b))) => {
+                    if value_a != value_b {
+                        stats.different += 1;
+                        print_diff(&mut stdout, '-', args.format, key, value_a)?;
+                        print_diff(&mut stdout, '+', args.format, key, value_b)?;
+                    }
+                }
+                _ => unreachable!(),
+            }
+            if order.is_le() {
+                cursor_a.next().await?;
+            }
+            if order.is_ge() {
+                cursor_b.next().await?;
+            }
+        }
+    }
+    eprintln!(
+        "Compared {} keys: {} only in {}, {} only in {}, {} different",
+        stats.num_keys,
+        stats.only_in_a,
+        args.a.display(),
+        stats.only_in_b,
+        args.b.display(),
+        stats.different,
+    );
+    if stats.num_diffs() > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_diff<W: Write>(
+    w: &mut W,
+    sign: char,
+    format: Format,
+    key: &[u8],
+    value: &[u8],
+) -> Result<()> {
+    write!(w, "{sign} ")?;
+    print_entry(w, format, key, value)
+}
@@ -0,0 +1,97 @@
+//! A tool to export entries of a table for analytics.
+
+use std::{
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use photondb::{ByteEncoding, Error, ExportFormat, ExportOptions};
+
+use crate::{
+    inspect::{open_table, parse_key},
+    Result,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Export entries in a key range as JSON lines or CSV")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The key range to export, as `start..end`, `start..` or `..end`, where
+    /// the start is inclusive and the end is exclusive.
+    ///
+    /// The range is split at the first `..`. All entries are exported if it
+    /// is not specified.
+    #[arg(long)]
+    range: Option<String>,
+
+    /// Parses the keys of the range as hex instead of escaped strings.
+    #[arg(long)]
+    hex_keys: bool,
+
+    /// The format of exported entries: jsonl or csv.
+    #[arg(long, default_value = "jsonl")]
+    format: ExportFormat,
+
+    /// The encoding of exported keys: hex, base64 or utf8-lossy.
+    #[arg(long, default_value = "hex")]
+    key_encoding: ByteEncoding,
+
+    /// The encoding of exported values: hex, base64 or utf8-lossy.
+    #[arg(long, default_value = "hex")]
+    value_encoding: ByteEncoding,
+}
+
+/// Streams the entries in the range to stdout.
+pub(crate) async fn run(args: Args) -> Result<()> {
+    let (start, end) = match &args.range {
+        Some(range) => parse_range(range, args.hex_keys)?,
+        None => (Vec::new(), None),
+    };
+    let table = open_table(&args.db).await?;
+    let mut opts = ExportOptions::default();
+    opts.format = args.format;
+    opts.key_encoding = args.key_encoding;
+    opts.value_encoding = args.value_encoding;
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let num_entries = table
+        .export(&start, end.as_deref(), &opts, &mut stdout)
+        .await?;
+    stdout.flush()?;
+    eprintln!("Exported {num_entries} entries");
+    Ok(())
+}
+
+fn parse_range(range: &str, hex: bool) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let Some((start, end)) = range.split_once("..") else {
+        return Err(Error::InvalidArgument(format!("invalid range {range:?}")));
+    };
+    let start = parse_key(start, hex)?;
+    let end = if end.is_empty() {
+        None
+    } else {
+        Some(parse_key(end, hex)?)
+    };
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(
+            parse_range("a..b", false).unwrap(),
+            (b"a".to_vec(), Some(b"b".to_vec()))
+        );
+        assert_eq!(parse_range("0a..", true).unwrap(), (vec![0x0a], None));
+        assert_eq!(
+            parse_range("..\\x00", false).unwrap(),
+            (Vec::new(), Some(vec![0]))
+        );
+        assert!(parse_range("a", false).is_err());
+    }
+}
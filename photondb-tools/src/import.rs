@@ -0,0 +1,144 @@
+//! A tool to import entries from another database.
+//!
+//! Sources are behind the `rocksdb` and `sled` features, since they link
+//! their own storage engines.
+
+use std::{path::PathBuf, time::Instant};
+
+use clap::{Parser, ValueEnum};
+use log::info;
+use photondb::{std::Table, Error, TableOptions, WriteBatch};
+
+use crate::Result;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub(crate) enum Source {
+    /// A RocksDB database, read with the default column family.
+    Rocksdb,
+    /// A sled database, read with the default tree.
+    Sled,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Import entries from a RocksDB or sled database")]
+pub(crate) struct Args {
+    /// The kind of the source database.
+    #[arg(long, value_enum)]
+    from: Source,
+
+    /// Path of the source database.
+    src: PathBuf,
+
+    /// Path of db data folder to import into.
+    dst: PathBuf,
+
+    /// The number of entries written in a batch.
+    #[arg(long, default_value_t = 1024, value_parser = clap::value_parser!(u64).range(1..))]
+    batch_size: u64,
+}
+
+/// Streams entries from the source in key order, and writes them to the
+/// table in batches.
+///
+/// All entries are written with the same LSN, which is larger than all LSNs
+/// in the table, so they can be read like any other write.
+pub(crate) fn run(args: Args) -> Result<()> {
+    let start = Instant::now();
+    let table = Table::open(&args.dst, TableOptions::default())?;
+    let mut loader = Loader {
+        table: &table,
+        lsn: table.latest_lsn() + 1,
+        batch: WriteBatch::new(),
+        batch_size: args.batch_size as usize,
+        num_entries: 0,
+        num_bytes: 0,
+    };
+    match args.from {
+        Source::Rocksdb => scan_rocksdb(&args, &mut loader)?,
+        Source::Sled => scan_sled(&args, &mut loader)?,
+    }
+    loader.flush()?;
+    let (num_entries, num_bytes) = (loader.num_entries, loader.num_bytes);
+    // Write buffers are flushed when the table is closed.
+    let _ = table.close();
+    info!(
+        "Imported {num_entries} entries ({num_bytes} bytes) from {} in {:?}",
+        args.src.display(),
+        start.elapsed()
+    );
+    Ok(())
+}
+
+/// Writes entries to the table in batches.
+struct Loader<'a> {
+    table: &'a Table,
+    lsn: u64,
+    batch: WriteBatch,
+    batch_size: usize,
+    num_entries: u64,
+    num_bytes: u64,
+}
+
+impl<'a> Loader<'a> {
+    fn add(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.batch.put(key, self.lsn, value);
+        self.num_entries += 1;
+        self.num_bytes += (key.len() + value.len()) as u64;
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.batch.is_empty() {
+            self.table.write_batch(&self.batch)?;
+            self.batch.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "rocksdb", feature = "sled"))]
+fn source_error(err: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Io(std::io::Error::new(std::io::ErrorKind::Other, err))
+}
+
+#[cfg(not(all(feature = "rocksdb", feature = "sled")))]
+fn unsupported(name: &str) -> Error {
+    Error::InvalidArgument(format!(
+        "photondb is built without the {name} feature to import from {name}"
+    ))
+}
+
+#[cfg(feature = "rocksdb")]
+fn scan_rocksdb(args: &Args, loader: &mut Loader<'_>) -> Result<()> {
+    use rocksdb::{IteratorMode, Options, DB};
+
+    let db = DB::open_for_read_only(&Options::default(), &args.src, false).map_err(source_error)?;
+    for entry in db.iterator(IteratorMode::Start) {
+        let (key, value) = entry.map_err(source_error)?;
+        loader.add(&key, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "rocksdb"))]
+fn scan_rocksdb(_: &Args, _: &mut Loader<'_>) -> Result<()> {
+    Err(unsupported("rocksdb"))
+}
+
+#[cfg(feature = "sled")]
+fn scan_sled(args: &Args, loader: &mut Loader<'_>) -> Result<()> {
+    let db = sled::open(&args.src).map_err(source_error)?;
+    for entry in db.iter() {
+        let (key, value) = entry.map_err(source_error)?;
+        loader.add(&key, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sled"))]
+fn scan_sled(_: &Args, _: &mut Loader<'_>) -> Result<()> {
+    Err(unsupported("sled"))
+}
@@ -0,0 +1,296 @@
+//! Tools to inspect the contents of a table.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, ValueEnum};
+use photondb::{
+    env::{self, Env},
+    Error, Table, TableOptions,
+};
+
+use crate::Result;
+
+/// The LSN to read the latest version of entries.
+pub(crate) const READ_LSN: u64 = u64::MAX;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub(crate) enum Format {
+    /// Lowercase hex digits.
+    Hex,
+    /// Quoted strings with non-printable bytes escaped as `\xNN`.
+    Escaped,
+    /// A JSON object per entry, with bytes other than printable ASCII
+    /// characters escaped as `\u00NN`.
+    Json,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Print entries in a key range")]
+pub(crate) struct ScanArgs {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The first key to print (inclusive).
+    #[arg(long)]
+    start: Option<String>,
+
+    /// The key to stop at (exclusive).
+    #[arg(long)]
+    end: Option<String>,
+
+    /// The maximum number of entries to print.
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Parses `start` and `end` as hex instead of escaped strings.
+    #[arg(long)]
+    hex_keys: bool,
+
+    #[arg(long, value_enum, default_value_t = Format::Escaped)]
+    format: Format,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Print the value of a key")]
+pub(crate) struct GetArgs {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The key to get.
+    key: String,
+
+    /// Parses the key as hex instead of an escaped string.
+    #[arg(long)]
+    hex_keys: bool,
+
+    #[arg(long, value_enum, default_value_t = Format::Escaped)]
+    format: Format,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Print all entries or pages")]
+pub(crate) struct DumpArgs {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// Prints the structure of pages instead of entries.
+    #[arg(long)]
+    pages: bool,
+
+    #[arg(long, value_enum, default_value_t = Format::Escaped)]
+    format: Format,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Print the delta chain and raw bytes of a page")]
+pub(crate) struct PageArgs {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The id of the page.
+    #[arg(long)]
+    id: u64,
+}
+
+pub(crate) async fn scan(args: ScanArgs) -> Result<()> {
+    let start = match &args.start {
+        Some(key) => parse_key(key, args.hex_keys)?,
+        None => Vec::new(),
+    };
+    let end = match &args.end {
+        Some(key) => Some(parse_key(key, args.hex_keys)?),
+        None => None,
+    };
+    let table = open_table(&args.db).await?;
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let mut stdout = io::stdout().lock();
+    let guard = table.pin();
+    let mut cursor = guard.cursor(READ_LSN);
+    cursor.seek(&start).await?;
+    let mut count = 0;
+    while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+        if count == limit || end.as_ref().map_or(false, |end| key >= end.as_slice()) {
+            break;
+        }
+        print_entry(&mut stdout, args.format, key, value)?;
+        count += 1;
+        cursor.next().await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn get(args: GetArgs) -> Result<()> {
+    let key = parse_key(&args.key, args.hex_keys)?;
+    let table = open_table(&args.db).await?;
+    match table.get(&key, READ_LSN).await? {
+        Some(value) => print_entry(&mut io::stdout().lock(), args.format, &key, &value)?,
+        None => {
+            eprintln!("Key not found");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn dump(args: DumpArgs) -> Result<()> {
+    let table = open_table(&args.db).await?;
+    let mut stdout = io::stdout().lock();
+    if args.pages {
+        return table.dump(&mut stdout).await;
+    }
+    let guard = table.pin();
+    let mut cursor = guard.cursor(READ_LSN);
+    cursor.seek(&[]).await?;
+    while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+        print_entry(&mut stdout, args.format, key, value)?;
+        cursor.next().await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn page(args: PageArgs) -> Result<()> {
+    let table = open_table(&args.db).await?;
+    table.dump_page(args.id, &mut io::stdout().lock()).await
+}
+
+/// Opens an existing table for inspection.
+///
+/// The table is opened read-only, so nothing in the directory is modified,
+/// even if the table is opened by another process.
+pub(crate) async fn open_table(path: &Path) -> Result<Table> {
+    let mut options = TableOptions::default();
+    options.page_store.read_only = true;
+    open_with(path, options).await
+}
+
+/// Opens an existing table and locks it, so that it can't be modified by
+/// others while it is open.
+///
+/// The table is opened without reclaiming space or flushing on shutdown, so
+/// that no page is rewritten.
+pub(crate) async fn lock_table(path: &Path) -> Result<Table> {
+    let mut options = TableOptions::default();
+    options.page_store.disable_space_reclaiming = true;
+    options.page_store.avoid_flush_during_shutdown = true;
+    open_with(path, options).await
+}
+
+async fn open_with(path: &Path, options: TableOptions) -> Result<Table> {
+    if !path.is_dir() {
+        return Err(Error::InvalidArgument(format!(
+            "{} is not a db data folder",
+            path.display()
+        )));
+    }
+    env::Photon
+        .spawn_background(Table::open(path.to_owned(), options))
+        .await
+}
+
+/// Parses a key from a hex string or an escaped string.
+///
+/// Escaped strings can contain `\xNN` for arbitrary bytes, and `\\` and `\"`
+/// for a backslash and a quote, which is the same as the escaped format.
+pub(crate) fn parse_key(s: &str, hex: bool) -> Result<Vec<u8>> {
+    let invalid = || Error::InvalidArgument(format!("invalid key {s:?}"));
+    if hex {
+        if s.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        return (0..s.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(s.get(i..i + 2).ok_or_else(invalid)?, 16).map_err(|_| invalid())
+            })
+            .collect();
+    }
+    let mut key = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            key.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'\\') => key.push(b'\\'),
+            Some(b'"') => key.push(b'"'),
+            Some(b'x') => {
+                let digits = [
+                    bytes.next().ok_or_else(invalid)?,
+                    bytes.next().ok_or_else(invalid)?,
+                ];
+                let digits = std::str::from_utf8(&digits).map_err(|_| invalid())?;
+                key.push(u8::from_str_radix(digits, 16).map_err(|_| invalid())?);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(key)
+}
+
+pub(crate) fn print_entry<W: Write>(
+    w: &mut W,
+    format: Format,
+    key: &[u8],
+    value: &[u8],
+) -> Result<()> {
+    match format {
+        Format::Hex => writeln!(w, "{} {}", hex(key), hex(value))?,
+        Format::Escaped => writeln!(w, "\"{}\" \"{}\"", escape(key), escape(value))?,
+        Format::Json => writeln!(
+            w,
+            "{{\"key\":\"{}\",\"value\":\"{}\"}}",
+            escape_json(key),
+            escape_json(value)
+        )?,
+    }
+    Ok(())
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'"' => "\\\"".to_owned(),
+            b'\\' => "\\\\".to_owned(),
+            0x20..=0x7e => (b as char).to_string(),
+            _ => format!("\\x{b:02x}"),
+        })
+        .collect()
+}
+
+fn escape_json(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'"' => "\\\"".to_owned(),
+            b'\\' => "\\\\".to_owned(),
+            0x20..=0x7e => (b as char).to_string(),
+            _ => format!("\\u{b:04x}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_printed_keys() {
+        let key = b"a\"b\\c\x00\xff d";
+        assert_eq!(parse_key(&escape(key), false).unwrap(), key);
+        assert_eq!(parse_key(&hex(key), true).unwrap(), key);
+        assert_eq!(escape_json(b"a\"\x01\xff"), "a\\\"\\u0001\\u00ff");
+        assert!(parse_key("\\x1", false).is_err());
+        assert!(parse_key("\\n", false).is_err());
+        assert!(parse_key("abc", true).is_err());
+        assert!(parse_key("zz", true).is_err());
+    }
+}
@@ -4,6 +4,28 @@ mod stress;
 
 mod bench;
 
+mod inspect;
+
+mod verify;
+
+mod repair;
+
+mod replay;
+
+mod analyze;
+
+mod import;
+
+mod backup;
+
+mod admin;
+
+mod top;
+
+mod diff;
+
+mod export;
+
 use clap::{Parser, Subcommand};
 pub(crate) use photondb::Result;
 
@@ -17,7 +39,23 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     Stress(stress::Args),
+    CrashTest(stress::crash::Args),
+    Simulate(stress::sim::Args),
     Bench(bench::Args),
+    Scan(inspect::ScanArgs),
+    Get(inspect::GetArgs),
+    Dump(inspect::DumpArgs),
+    Page(inspect::PageArgs),
+    Verify(verify::Args),
+    Repair(repair::Args),
+    Replay(replay::Args),
+    Analyze(analyze::Args),
+    Import(import::Args),
+    Backup(backup::Args),
+    Admin(admin::Args),
+    Top(top::Args),
+    Diff(diff::Args),
+    Export(export::Args),
 }
 
 #[photonio::main]
@@ -27,7 +65,23 @@ async fn main() -> Result<()> {
     let args = Args::parse();
     match args.cmd {
         Commands::Stress(args) => stress::run(args).await?,
+        Commands::CrashTest(args) => stress::crash::run(args).await?,
+        Commands::Simulate(args) => stress::sim::run(args)?,
         Commands::Bench(args) => bench::run(args).await.unwrap(),
+        Commands::Scan(args) => inspect::scan(args).await?,
+        Commands::Get(args) => inspect::get(args).await?,
+        Commands::Dump(args) => inspect::dump(args).await?,
+        Commands::Page(args) => inspect::page(args).await?,
+        Commands::Verify(args) => verify::run(args).await?,
+        Commands::Repair(args) => repair::run(args).await?,
+        Commands::Replay(args) => replay::run(args)?,
+        Commands::Analyze(args) => analyze::run(args).await?,
+        Commands::Import(args) => import::run(args)?,
+        Commands::Backup(args) => backup::run(args).await?,
+        Commands::Admin(args) => admin::run(args)?,
+        Commands::Top(args) => top::run(args)?,
+        Commands::Diff(args) => diff::run(args).await?,
+        Commands::Export(args) => export::run(args).await?,
     }
     Ok(())
 }
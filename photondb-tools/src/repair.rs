@@ -0,0 +1,64 @@
+//! A tool to repair a corrupted table.
+
+use std::{fmt::Write, path::PathBuf};
+
+use clap::Parser;
+use photondb::{
+    env::{self, Env},
+    Error, Table, TableOptions,
+};
+
+use crate::{inspect::hex, Result};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Rebuild the manifest and quarantine unreadable pages")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+}
+
+/// Repairs the table and prints the report as JSON.
+///
+/// Exits with 1 if any unreadable page can not be repaired.
+pub(crate) async fn run(args: Args) -> Result<()> {
+    if !args.db.is_dir() {
+        return Err(Error::InvalidArgument(format!(
+            "{} is not a db data folder",
+            args.db.display()
+        )));
+    }
+    let mut options = TableOptions::default();
+    options.page_store.disable_space_reclaiming = true;
+    let report = env::Photon
+        .spawn_background(Table::repair(args.db.clone(), options))
+        .await?;
+
+    let mut lost_ranges = String::new();
+    for (i, range) in report.lost_ranges.iter().enumerate() {
+        let end = range
+            .end
+            .as_ref()
+            .map_or_else(|| "null".to_owned(), |end| format!("\"{}\"", hex(end)));
+        let sep = if i > 0 { "," } else { "" };
+        write!(
+            lost_ranges,
+            "{sep}{{\"page_id\":{},\"start\":\"{}\",\"end\":{end}}}",
+            range.page_id,
+            hex(&range.start)
+        )
+        .unwrap();
+    }
+    println!(
+        "{{\"ok\":{},\"num_files\":{},\"quarantined_files\":{:?},\"quarantined_pages\":{:?},\
+         \"unrepaired_pages\":{:?},\"lost_ranges\":[{lost_ranges}]}}",
+        report.is_ok(),
+        report.num_files,
+        report.quarantined_files,
+        report.quarantined_pages,
+        report.unrepaired_pages
+    );
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
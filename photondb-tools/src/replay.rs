@@ -0,0 +1,192 @@
+//! A tool to replay a captured workload against a table.
+//!
+//! Workloads are captured from a live table with
+//! [`photondb::raw::Table::start_workload_capture`].
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    sync::mpsc::{sync_channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use hdrhistogram::Histogram;
+use photondb::{std::Table, TableOptions, WorkloadOp, WorkloadOpKind};
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+use crate::Result;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Replay a captured workload against a table")]
+pub(crate) struct Args {
+    /// Path of the workload trace.
+    trace: PathBuf,
+
+    /// Path of db data folder to replay against.
+    #[arg(long)]
+    db: PathBuf,
+
+    /// Replays at this multiple of the original speed, 0 replays as fast as
+    /// possible.
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// The number of replay threads. Operations on the same key are replayed
+    /// by the same thread in the captured order.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: u64,
+}
+
+/// The latencies of replayed operations.
+struct Report {
+    get: Histogram<u64>,
+    put: Histogram<u64>,
+    delete: Histogram<u64>,
+    /// How far the replay falls behind the schedule at most.
+    max_lag: Duration,
+}
+
+impl Report {
+    fn new() -> Self {
+        let hist = || Histogram::new_with_bounds(1, 60_000_000, 3).unwrap();
+        Self {
+            get: hist(),
+            put: hist(),
+            delete: hist(),
+            max_lag: Duration::ZERO,
+        }
+    }
+
+    fn hist(&mut self, kind: WorkloadOpKind) -> &mut Histogram<u64> {
+        match kind {
+            WorkloadOpKind::Get => &mut self.get,
+            WorkloadOpKind::Put => &mut self.put,
+            WorkloadOpKind::Delete => &mut self.delete,
+        }
+    }
+
+    fn merge(&mut self, other: &Report) {
+        self.get.add(&other.get).unwrap();
+        self.put.add(&other.put).unwrap();
+        self.delete.add(&other.delete).unwrap();
+        self.max_lag = self.max_lag.max(other.max_lag);
+    }
+
+    fn len(&self) -> u64 {
+        self.get.len() + self.put.len() + self.delete.len()
+    }
+}
+
+/// Replays the operations in the trace, and prints the latencies.
+///
+/// Each operation is replayed at its offset from the first operation in the
+/// trace, divided by the speed. Writes are replayed with LSNs assigned by the
+/// table, and values of the captured sizes are filled with random bytes.
+pub(crate) fn run(args: Args) -> Result<()> {
+    let trace = BufReader::new(File::open(&args.trace)?);
+    let table = Table::open(&args.db, TableOptions::default())?;
+    let start = Instant::now();
+    let mut senders = Vec::new();
+    let mut handles = Vec::new();
+    for tid in 0..args.threads {
+        let (tx, rx) = sync_channel(1024);
+        let table = table.clone();
+        let speed = args.speed;
+        senders.push(tx);
+        handles.push(thread::spawn(move || replay(table, rx, start, speed, tid)));
+    }
+
+    let mut base_ts = None;
+    for line in trace.lines() {
+        let op = WorkloadOp::parse(&line?)?;
+        let base_ts = *base_ts.get_or_insert(op.timestamp_us);
+        let offset = Duration::from_micros(op.timestamp_us.saturating_sub(base_ts));
+        let mut hasher = DefaultHasher::new();
+        op.key.hash(&mut hasher);
+        let tid = hasher.finish() % args.threads;
+        if senders[tid as usize].send((offset, op)).is_err() {
+            // The thread fails, and the error is returned when it is joined.
+            break;
+        }
+    }
+    drop(senders);
+
+    let mut report = Report::new();
+    for handle in handles {
+        report.merge(&handle.join().unwrap()?);
+    }
+    // All other references are dropped with the threads.
+    let _ = table.close();
+    let elapsed = start.elapsed();
+    println!(
+        "Replayed {} ops in {elapsed:?} ({:.0} ops/sec), max lag {:?}",
+        report.len(),
+        report.len() as f64 / elapsed.as_secs_f64(),
+        report.max_lag
+    );
+    for (name, hist) in [
+        ("get", &report.get),
+        ("put", &report.put),
+        ("delete", &report.delete),
+    ] {
+        if hist.is_empty() {
+            continue;
+        }
+        println!(
+            "{name}: count {}, p50 {}us, p99 {}us, max {}us",
+            hist.len(),
+            hist.value_at_quantile(0.5),
+            hist.value_at_quantile(0.99),
+            hist.max()
+        );
+    }
+    Ok(())
+}
+
+fn replay(
+    table: Table,
+    ops: Receiver<(Duration, WorkloadOp)>,
+    start: Instant,
+    speed: f64,
+    tid: u64,
+) -> Result<Report> {
+    let mut rng = SmallRng::seed_from_u64(tid);
+    let mut value = Vec::new();
+    let mut report = Report::new();
+    for (offset, op) in ops {
+        if speed > 0.0 {
+            let target = start + offset.div_f64(speed);
+            let now = Instant::now();
+            if target > now {
+                thread::sleep(target - now);
+            } else {
+                report.max_lag = report.max_lag.max(now - target);
+            }
+        }
+        if value.len() < op.value_size {
+            let len = value.len();
+            value.resize(op.value_size, 0);
+            rng.fill_bytes(&mut value[len..]);
+        }
+        let begin = Instant::now();
+        match op.kind {
+            WorkloadOpKind::Get => {
+                table.get(&op.key, u64::MAX)?;
+            }
+            WorkloadOpKind::Put => {
+                table.put_sequenced(&op.key, &value[..op.value_size])?;
+            }
+            WorkloadOpKind::Delete => {
+                table.delete_sequenced(&op.key)?;
+            }
+        }
+        let latency = begin.elapsed().as_micros() as u64;
+        report.hist(op.kind).saturating_record(latency.max(1));
+    }
+    Ok(report)
+}
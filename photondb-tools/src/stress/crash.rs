@@ -0,0 +1,360 @@
+//! A tool used to test the recovery of tables from crashes.
+//!
+//! Each round opens the table, verifies it against a model of the written
+//! entries, and runs random writes with periodic flushes until the
+//! environment crashes at a random time. Crashes are either process kills
+//! that discard all I/O after the crash, or power cuts that also drop the data
+//! that is not synced.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use clap::Parser;
+use log::{error, info};
+use photondb::{
+    env::{Env, Faulty, Photon},
+    raw::Table,
+    FlushOptions, TableOptions,
+};
+use rand::{
+    rngs::{OsRng, SmallRng},
+    Rng, RngCore, SeedableRng,
+};
+
+use super::{fill_bytes, Timer};
+use crate::Result;
+
+/// The size of the key and the value id at the start of each value.
+const VALUE_HEADER_SIZE: usize = 16;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Start crash testing")]
+pub(crate) struct Args {
+    /// Sets the path of db to test
+    #[clap(long, required = true)]
+    db: PathBuf,
+
+    /// Sets the random seed
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// How many times to crash and recover the DB
+    #[clap(long, default_value_t = 20)]
+    rounds: u64,
+
+    /// Sets the number of write tasks
+    #[clap(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: u64,
+
+    /// Sets the number of distinct keys
+    #[clap(long, default_value_t = 10000)]
+    num_keys: u64,
+
+    /// Sets the value size
+    #[clap(long, default_value_t = 100)]
+    value_size: usize,
+
+    /// The percentage of writes that are deletes
+    #[clap(long, default_value_t = 10)]
+    delete_percent: u32,
+
+    /// How long a round runs at most before crashing, in milliseconds
+    #[clap(long, default_value_t = 3000)]
+    max_round_millis: u64,
+
+    /// How often to flush the DB, in milliseconds
+    #[clap(long, default_value_t = 200)]
+    flush_period_millis: u64,
+
+    /// How likely is a crash a power cut instead of a process kill
+    #[clap(long, default_value_t = 0.5)]
+    power_cut_probability: f64,
+
+    /// Sets the write buffer capacity, small buffers make flushes and
+    /// reclamations more frequent
+    #[clap(long, default_value_t = 1 << 20)]
+    write_buffer_capacity: u32,
+}
+
+/// The values of a key that can be read after a crash.
+#[derive(Clone, Debug, Default)]
+struct KeyModel {
+    /// The id of the value that is durable, or `None` if the key is absent.
+    durable: Option<u64>,
+    /// The ids of the values written after the durable one, `None` for deletes.
+    pending: Vec<Option<u64>>,
+    /// The number of pending writes that are acknowledged.
+    acked: usize,
+}
+
+struct Job {
+    stop: AtomicBool,
+    args: Args,
+    env: Faulty<Photon>,
+    table: Table<Faulty<Photon>>,
+    timer: Timer,
+    model: Arc<Mutex<Vec<KeyModel>>>,
+    next_value_id: Arc<AtomicU64>,
+}
+
+pub(crate) async fn run(args: Args) -> Result<()> {
+    if args.value_size < VALUE_HEADER_SIZE {
+        error!(
+            "Value size must be at least {VALUE_HEADER_SIZE}, but get {}",
+            args.value_size
+        );
+        std::process::exit(-1);
+    }
+    if args.num_keys < args.threads {
+        error!(
+            "Number of keys must be at least the number of threads {}, but get {}",
+            args.threads, args.num_keys
+        );
+        std::process::exit(-1);
+    }
+    if args.db.exists() {
+        if let Err(err) = std::fs::remove_dir_all(&args.db) {
+            error!("Destroy DB {}: {err:?}", args.db.display());
+            std::process::abort();
+        }
+    }
+
+    let base_seed = args.seed.unwrap_or_else(|| OsRng.next_u64());
+    info!("Start crash testing with base seed {base_seed}");
+    let mut rng = SmallRng::seed_from_u64(base_seed);
+    let model = Arc::new(Mutex::new(vec![
+        KeyModel::default();
+        args.num_keys as usize
+    ]));
+    let next_value_id = Arc::new(AtomicU64::new(0));
+
+    for round in 0..args.rounds {
+        let env = Faulty::new(Photon);
+        let table = env
+            .spawn_background(Table::open(env.clone(), args.db.clone(), options(&args)))
+            .await?;
+        verify(&table, &model, round).await?;
+
+        let job = Arc::new(Job {
+            stop: AtomicBool::new(false),
+            args: args.clone(),
+            env: env.clone(),
+            table: table.clone(),
+            timer: Timer::default(),
+            model: model.clone(),
+            next_value_id: next_value_id.clone(),
+        });
+        let seed = rng.gen::<u64>();
+        let mut handles = (0..args.threads)
+            .map(|tid| env.spawn_background(write_task(job.clone(), tid, seed.wrapping_add(tid))))
+            .collect::<Vec<_>>();
+        handles.push(env.spawn_background(flush_task(job.clone())));
+
+        let crash_after = rng.gen_range(1..=args.max_round_millis);
+        for _ in 0..crash_after {
+            std::thread::sleep(Duration::from_millis(1));
+            job.timer.next_round();
+        }
+        if rng.gen::<f64>() < args.power_cut_probability {
+            info!("Round {round}: cut power after {crash_after} ms");
+            env.power_cut()?;
+        } else {
+            info!("Round {round}: crash after {crash_after} ms");
+            env.crash();
+        }
+
+        job.timer.close();
+        job.stop.store(true, Ordering::SeqCst);
+        env.spawn_background(async move {
+            for handle in handles {
+                handle.await;
+            }
+        })
+        .await;
+        // The table is dropped without closing, as if the process exited.
+        drop(job);
+        drop(table);
+    }
+
+    let env = Faulty::new(Photon);
+    let table = env
+        .spawn_background(Table::open(env.clone(), args.db.clone(), options(&args)))
+        .await?;
+    verify(&table, &model, args.rounds).await?;
+    info!("Pass {} rounds of crash testing", args.rounds);
+    Ok(())
+}
+
+fn options(args: &Args) -> TableOptions {
+    let mut options = TableOptions::default();
+    options.page_store.write_buffer_capacity = args.write_buffer_capacity;
+    options
+}
+
+async fn write_task(job: Arc<Job>, tid: u64, seed: u64) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    // Each key is written by one task, so the order of writes to a key is
+    // the order in the model.
+    let num_owned_keys = (job.args.num_keys - tid - 1) / job.args.threads + 1;
+    let mut value = vec![0u8; job.args.value_size];
+    while !job.stop.load(Ordering::Relaxed) {
+        let k = tid + rng.gen_range(0..num_owned_keys) * job.args.threads;
+        let key = encode_key(k);
+        let value_id = if rng.gen_range(0..100) < job.args.delete_percent {
+            None
+        } else {
+            Some(job.next_value_id.fetch_add(1, Ordering::Relaxed))
+        };
+        job.model.lock().expect("Poisoned")[k as usize]
+            .pending
+            .push(value_id);
+        let res = match value_id {
+            Some(id) => {
+                encode_value(k, id, &mut value);
+                job.table.put_sequenced(&key, &value).await
+            }
+            None => job.table.delete_sequenced(&key).await,
+        };
+        if let Err(err) = res {
+            if job.env.is_crashed() {
+                break;
+            }
+            error!("Write to DB: {err:?}");
+            std::process::abort();
+        }
+        job.model.lock().expect("Poisoned")[k as usize].acked += 1;
+        photonio::task::yield_now().await;
+    }
+}
+
+async fn flush_task(job: Arc<Job>) {
+    let period = Duration::from_millis(job.args.flush_period_millis);
+    while !job.stop.load(Ordering::Relaxed) {
+        job.timer.sleep(period).await;
+        if job.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        // Writes acknowledged before the flush are durable after it.
+        let acked: Vec<_> = job
+            .model
+            .lock()
+            .expect("Poisoned")
+            .iter()
+            .map(|key| key.acked)
+            .collect();
+        job.table.flush(&FlushOptions::default()).await;
+        if job.env.is_crashed() {
+            break;
+        }
+        let mut model = job.model.lock().expect("Poisoned");
+        for (key, acked) in model.iter_mut().zip(acked) {
+            if acked > 0 {
+                key.durable = key.pending[acked - 1];
+                key.pending.drain(..acked);
+                key.acked -= acked;
+            }
+        }
+    }
+}
+
+/// Verifies the table against the model, and resets the model to the
+/// entries in the table.
+async fn verify(
+    table: &Table<Faulty<Photon>>,
+    model: &Mutex<Vec<KeyModel>>,
+    round: u64,
+) -> Result<()> {
+    let report = table.verify().await?;
+    if !report.is_ok() {
+        fail(round, format!("invalid tree: {:?}", report.violations));
+    }
+    let report = table.verify_checksums().await?;
+    if !report.is_ok() {
+        fail(
+            round,
+            format!("corrupted pages: {:?}", report.corrupted_pages),
+        );
+    }
+
+    let mut scanned = BTreeMap::new();
+    {
+        let guard = table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&[]).await?;
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            scanned.insert(key.to_vec(), value.to_vec());
+            cursor.next().await?;
+        }
+    }
+
+    let num_entries = scanned.len();
+    let mut keys = std::mem::take(&mut *model.lock().expect("Poisoned"));
+    for (k, expect) in keys.iter_mut().enumerate() {
+        let k = k as u64;
+        let key = encode_key(k);
+        let value = table.get(&key, u64::MAX).await?;
+        if value != scanned.remove(&key) {
+            fail(round, format!("get and scan of key {k} disagree"));
+        }
+        let value_id = match value {
+            Some(value) => match decode_value(k, &value) {
+                Some(id) => Some(id),
+                None => fail(round, format!("invalid value of key {k}: {value:?}")),
+            },
+            None => None,
+        };
+        if value_id != expect.durable && !expect.pending.contains(&value_id) {
+            fail(
+                round,
+                format!("unexpected value {value_id:?} of key {k}, expect {expect:?}"),
+            );
+        }
+        *expect = KeyModel {
+            durable: value_id,
+            ..Default::default()
+        };
+    }
+    if let Some(key) = scanned.keys().next() {
+        fail(round, format!("unexpected key {key:?}"));
+    }
+    *model.lock().expect("Poisoned") = keys;
+    info!("Round {round}: verify {num_entries} entries");
+    Ok(())
+}
+
+fn fail(round: u64, msg: String) -> ! {
+    error!("Round {round}: {msg}");
+    std::process::exit(1);
+}
+
+fn encode_key(k: u64) -> Vec<u8> {
+    format!("{k:016x}").into_bytes()
+}
+
+/// Encodes a value that can be checked against its key and id.
+fn encode_value(k: u64, id: u64, value: &mut [u8]) {
+    value[..8].copy_from_slice(&k.to_be_bytes());
+    value[8..VALUE_HEADER_SIZE].copy_from_slice(&id.to_be_bytes());
+    fill_bytes(
+        &mut SmallRng::seed_from_u64(id),
+        &mut value[VALUE_HEADER_SIZE..],
+    );
+}
+
+/// Decodes the id of a value, or returns `None` if the value is invalid.
+fn decode_value(k: u64, value: &[u8]) -> Option<u64> {
+    if value.len() < VALUE_HEADER_SIZE || value[..8] != k.to_be_bytes() {
+        return None;
+    }
+    let id = u64::from_be_bytes(value[8..VALUE_HEADER_SIZE].try_into().unwrap());
+    let mut expect = vec![0u8; value.len()];
+    encode_value(k, id, &mut expect);
+    (expect == value).then_some(id)
+}
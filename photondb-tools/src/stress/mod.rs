@@ -1,5 +1,8 @@
 //! A tool used to perform stress testing.
 
+pub(crate) mod crash;
+pub(crate) mod sim;
+
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
@@ -0,0 +1,171 @@
+//! A tool used to run workloads in a deterministic simulation.
+//!
+//! Each seed runs concurrent writes on a [`Sim`] environment, then reopens
+//! the table and checks it against the writes. A failed seed takes the same
+//! schedule when it runs again, so it can be reproduced with `--seed`.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use log::{error, info};
+use photondb::{
+    env::{Env, Sim, SimOptions},
+    raw::Table,
+    TableOptions,
+};
+use rand::{
+    rngs::{OsRng, SmallRng},
+    Rng, RngCore, SeedableRng,
+};
+
+use crate::Result;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Start simulation testing")]
+pub(crate) struct Args {
+    /// Sets the path of db to test
+    #[clap(long, required = true)]
+    db: PathBuf,
+
+    /// Sets the first seed to run
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// How many seeds to run, starting from the first one
+    #[clap(long, default_value_t = 1)]
+    seeds: u64,
+
+    /// Sets the number of write tasks
+    #[clap(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: u64,
+
+    /// Sets the number of writes of each task
+    #[clap(long, default_value_t = 1000)]
+    num_writes: u64,
+
+    /// Sets the number of distinct keys
+    #[clap(long, default_value_t = 1000)]
+    num_keys: u64,
+
+    /// The percentage of writes that are deletes
+    #[clap(long, default_value_t = 10)]
+    delete_percent: u32,
+
+    /// The maximum latency of a file operation, in microseconds
+    #[clap(long, default_value_t = 100)]
+    max_io_latency_micros: u64,
+
+    /// Sets the write buffer capacity, small buffers make flushes and
+    /// reclamations more frequent
+    #[clap(long, default_value_t = 64 << 10)]
+    write_buffer_capacity: u32,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    if args.num_keys < args.threads {
+        error!(
+            "Number of keys must be at least the number of threads {}, but get {}",
+            args.threads, args.num_keys
+        );
+        std::process::exit(-1);
+    }
+    let first_seed = args.seed.unwrap_or_else(|| OsRng.next_u64());
+    for seed in (0..args.seeds).map(|i| first_seed.wrapping_add(i)) {
+        if args.db.exists() {
+            std::fs::remove_dir_all(&args.db)?;
+        }
+        let env = Sim::new(SimOptions {
+            seed,
+            max_io_latency: Duration::from_micros(args.max_io_latency_micros),
+            ..Default::default()
+        });
+        env.run(simulate(&env, &args))?;
+        info!("Seed {seed}: pass in {:?} of simulated time", env.now());
+    }
+    Ok(())
+}
+
+async fn simulate(env: &Sim, args: &Args) -> Result<()> {
+    let seed = env.seed();
+    let mut options = TableOptions::default();
+    options.page_store.write_buffer_capacity = args.write_buffer_capacity;
+
+    let table = Table::open(env.clone(), &args.db, options.clone()).await?;
+    let handles: Vec<_> = (0..args.threads)
+        .map(|tid| {
+            let rng = SmallRng::seed_from_u64(seed.wrapping_add(tid));
+            env.spawn_background(write_task(table.clone(), args.clone(), seed, tid, rng))
+        })
+        .collect();
+    let mut model = BTreeMap::new();
+    for handle in handles {
+        model.extend(handle.await?);
+    }
+    if table.close().await.is_err() {
+        fail(seed, "table is still referenced after writes".to_owned());
+    }
+
+    // Checks that all writes are recovered.
+    let table = Table::open(env.clone(), &args.db, options).await?;
+    let report = table.verify().await?;
+    if !report.is_ok() {
+        fail(seed, format!("invalid tree: {:?}", report.violations));
+    }
+    for (k, expect) in model {
+        let value = table.get(&encode_key(k), u64::MAX).await?;
+        if value != expect {
+            fail(
+                seed,
+                format!("recovered {value:?} of key {k}, expect {expect:?}"),
+            );
+        }
+    }
+    if table.close().await.is_err() {
+        fail(seed, "table is still referenced after checks".to_owned());
+    }
+    Ok(())
+}
+
+/// Writes the keys owned by the task, and returns the last value of each
+/// key.
+async fn write_task(
+    table: Table<Sim>,
+    args: Args,
+    seed: u64,
+    tid: u64,
+    mut rng: SmallRng,
+) -> Result<BTreeMap<u64, Option<Vec<u8>>>> {
+    // Each key is written by one task, so the last write to a key is known.
+    let num_owned_keys = (args.num_keys - tid - 1) / args.threads + 1;
+    let mut model = BTreeMap::new();
+    for i in 0..args.num_writes {
+        let k = tid + rng.gen_range(0..num_owned_keys) * args.threads;
+        let key = encode_key(k);
+        let value = if rng.gen_range(0..100) < args.delete_percent {
+            table.delete_sequenced(&key).await?;
+            None
+        } else {
+            let value = [key.as_slice(), &i.to_be_bytes()].concat();
+            table.put_sequenced(&key, &value).await?;
+            Some(value)
+        };
+        let read = table.get(&key, u64::MAX).await?;
+        if read != value {
+            fail(
+                seed,
+                format!("read {read:?} of key {k} after writing {value:?}"),
+            );
+        }
+        model.insert(k, value);
+    }
+    Ok(model)
+}
+
+fn fail(seed: u64, msg: String) -> ! {
+    error!("Seed {seed}: {msg}");
+    std::process::exit(1);
+}
+
+fn encode_key(k: u64) -> Vec<u8> {
+    format!("{k:016x}").into_bytes()
+}
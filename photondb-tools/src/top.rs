@@ -0,0 +1,152 @@
+//! A terminal UI to watch the live stats of a running table.
+//!
+//! The UI is behind the `tui` feature, since it takes over the terminal.
+//! Without the feature, the stats are printed in batches instead.
+
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use photondb::{Histogram, LiveStats};
+
+use crate::Result;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Watch the live stats of a running table")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+
+    /// The interval to refresh the stats in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    interval_ms: u64,
+}
+
+/// Renders the latest stats published by the table, until `q` is pressed.
+#[cfg(feature = "tui")]
+pub(crate) fn run(args: Args) -> Result<()> {
+    use std::{
+        io::{self, Write},
+        time::Duration,
+    };
+
+    use crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyModifiers},
+        execute, queue,
+        style::Print,
+        terminal::{self, ClearType},
+    };
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = (|| -> Result<()> {
+        loop {
+            let mut lines = render(&args, LiveStats::read(&args.db)?);
+            lines.push(String::new());
+            lines.push("Press q to quit".to_owned());
+            queue!(stdout, terminal::Clear(ClearType::All))?;
+            for (row, line) in lines.iter().enumerate() {
+                queue!(stdout, cursor::MoveTo(0, row as u16), Print(line))?;
+            }
+            stdout.flush()?;
+            if event::poll(Duration::from_millis(args.interval_ms))? {
+                if let Event::Key(key) = event::read()? {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc || ctrl_c {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    })();
+    // Restores the terminal even if the stats can not be read.
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// Prints the latest stats published by the table in every interval.
+#[cfg(not(feature = "tui"))]
+pub(crate) fn run(args: Args) -> Result<()> {
+    loop {
+        for line in render(&args, LiveStats::read(&args.db)?) {
+            println!("{line}");
+        }
+        println!();
+        std::thread::sleep(std::time::Duration::from_millis(args.interval_ms));
+    }
+}
+
+/// Renders the stats as lines of text.
+fn render(args: &Args, stats: Option<LiveStats>) -> Vec<String> {
+    let mut lines = vec![format!("photondb top - {}", args.db.display())];
+    let Some(stats) = stats else {
+        lines.push(String::new());
+        lines.push("Waiting for the stats of a table opened with admin_poll_period".to_owned());
+        return lines;
+    };
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let secs = stats.period.as_secs_f64();
+    lines.push(format!(
+        "Updated {:.1}s ago, over the last {secs:.1}s",
+        now_ms.saturating_sub(stats.time_ms) as f64 / 1000.0
+    ));
+    lines.push(String::new());
+    lines.push(format!(
+        "{:<8} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "op", "ops/s", "avg (us)", "p50 (us)", "p99 (us)", "p99.9 (us)"
+    ));
+    for (name, hist) in [
+        ("get", &stats.latency.get),
+        ("put", &stats.latency.put),
+        ("delete", &stats.latency.delete),
+        ("scan", &stats.latency.scan),
+    ] {
+        lines.push(format_latency(name, hist, secs));
+    }
+
+    let lookups = stats.page_cache_hits + stats.page_cache_misses;
+    let hit_rate = if lookups == 0 {
+        0.0
+    } else {
+        stats.page_cache_hits as f64 * 100.0 / lookups as f64
+    };
+    lines.push(String::new());
+    lines.push(format!(
+        "Page cache: {hit_rate:.2}% hits, {:.0} lookups/s",
+        per_sec(lookups, secs)
+    ));
+    lines.push(format!(
+        "Maintenance debt: {} pages to consolidate, {} dirty bytes, {} garbage bytes",
+        stats.debt.pending_consolidations, stats.debt.dirty_bytes, stats.debt.garbage_bytes
+    ));
+    lines
+}
+
+fn format_latency(name: &str, hist: &Histogram, secs: f64) -> String {
+    format!(
+        "{name:<8} {:>12.0} {:>12.1} {:>12} {:>12} {:>12}",
+        per_sec(hist.count, secs),
+        hist.avg(),
+        hist.percentile(0.5),
+        hist.percentile(0.99),
+        hist.percentile(0.999),
+    )
+}
+
+fn per_sec(count: u64, secs: f64) -> f64 {
+    if secs > 0.0 {
+        count as f64 / secs
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,83 @@
+//! A tool to verify the integrity of a table.
+
+use std::{fmt::Write, path::PathBuf};
+
+use clap::Parser;
+use photondb::{ChecksumReport, VerifyReport};
+
+use crate::{inspect::open_table, Result};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Verify the checksums of pages and the structure of the tree")]
+pub(crate) struct Args {
+    /// Path of db data folder.
+    db: PathBuf,
+}
+
+/// Verifies the table and prints the report as JSON.
+///
+/// Exits with 1 if any corrupted page or tree violation is found.
+pub(crate) async fn run(args: Args) -> Result<()> {
+    let table = open_table(&args.db).await?;
+    let checksums = table.verify_checksums().await?;
+    let tree = table.verify().await?;
+    let ok = checksums.is_ok() && tree.is_ok();
+    println!(
+        "{{\"ok\":{ok},\"checksums\":{},\"tree\":{}}}",
+        checksums_json(&checksums),
+        tree_json(&tree)
+    );
+    if !ok {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn checksums_json(report: &ChecksumReport) -> String {
+    let mut pages = String::new();
+    for (i, page) in report.corrupted_pages.iter().enumerate() {
+        let page_id = page
+            .page_id
+            .map_or_else(|| "null".to_owned(), |id| id.to_string());
+        let sep = if i > 0 { "," } else { "" };
+        write!(
+            pages,
+            "{sep}{{\"file_id\":{},\"page_addr\":{},\"page_id\":{page_id}}}",
+            page.file_id, page.page_addr
+        )
+        .unwrap();
+    }
+    format!(
+        "{{\"ok\":{},\"num_files\":{},\"num_pages\":{},\"num_bytes\":{},\
+         \"corrupted_pages\":[{pages}]}}",
+        report.is_ok(),
+        report.num_files,
+        report.num_pages,
+        report.num_bytes
+    )
+}
+
+fn tree_json(report: &VerifyReport) -> String {
+    let mut violations = String::new();
+    for (i, v) in report.violations.iter().enumerate() {
+        let sep = if i > 0 { "," } else { "" };
+        write!(
+            violations,
+            "{sep}{{\"page_id\":{},\"kind\":\"{:?}\"}}",
+            v.page_id, v.kind
+        )
+        .unwrap();
+    }
+    format!(
+        "{{\"ok\":{},\"height\":{},\"num_leaf_pages\":{},\"num_inner_pages\":{},\
+         \"num_delta_pages\":{},\"num_pending_smos\":{},\"num_unreachable_pages\":{},\
+         \"violations\":[{violations}]}}",
+        report.is_ok(),
+        report.height,
+        report.num_leaf_pages,
+        report.num_inner_pages,
+        report.num_delta_pages,
+        report.num_pending_smos,
+        report.num_unreachable_pages
+    )
+}
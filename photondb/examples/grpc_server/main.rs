@@ -0,0 +1,305 @@
+//! An example that serves a table over a simple gRPC KV service.
+//!
+//! The service is described in `kv.proto` next to this file. Run the server
+//! with:
+//!
+//! ```text
+//! cargo run --example grpc_server --features tokio -- <path> [<addr>]
+//! ```
+//!
+//! and try it with a gRPC client, for example:
+//!
+//! ```text
+//! grpcurl -plaintext -proto kv.proto -d '{"key": "aw==", "value": "dg=="}' \
+//!     127.0.0.1:50051 kv.Kv/Put
+//! ```
+//!
+//! The table runs with the [`Tokio`] environment, so requests are served
+//! concurrently on the runtime of the server without blocking it, and the
+//! table is shared by cloning the reference. The server stops on Ctrl-C and
+//! closes the table after in-flight requests are done.
+
+use std::{convert::Infallible, future::Future};
+
+use photondb::{env::Tokio, raw::Table, Error, TableOptions, WriteBatch};
+use prost::Message;
+use tonic::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{empty_body, http, Body, BoxFuture, Context, Poll, Service, StdError},
+    server::{Grpc, NamedService, UnaryService},
+    transport::Server,
+    Request, Response, Status,
+};
+
+/// The number of entries returned by a scan if the request has no limit.
+const DEFAULT_SCAN_LIMIT: usize = 100;
+/// The maximum number of entries returned by a scan.
+const MAX_SCAN_LIMIT: usize = 1000;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "photondb-grpc".to_owned());
+    let addr = args
+        .next()
+        .unwrap_or_else(|| "127.0.0.1:50051".to_owned())
+        .parse()?;
+
+    let table = Table::open(Tokio, &path, TableOptions::default()).await?;
+
+    println!("Serving {path} on {addr}");
+    Server::builder()
+        .add_service(KvService {
+            table: table.clone(),
+        })
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    // The service is dropped with the server, so this is the last reference.
+    if table.close().await.is_err() {
+        eprintln!("The table is still referenced");
+    }
+    Ok(())
+}
+
+/// Implements the `kv.Kv` service with a table.
+#[derive(Clone)]
+struct KvService {
+    table: Table<Tokio>,
+}
+
+impl KvService {
+    async fn get(&self, req: GetRequest) -> Result<GetResponse, Status> {
+        // Reads with the largest LSN see all writes that are done.
+        let value = self.table.get(&req.key, u64::MAX).await.map_err(status)?;
+        Ok(GetResponse { value })
+    }
+
+    async fn put(&self, req: PutRequest) -> Result<PutResponse, Status> {
+        self.table
+            .put_sequenced(&req.key, &req.value)
+            .await
+            .map_err(status)?;
+        Ok(PutResponse {})
+    }
+
+    async fn delete(&self, req: DeleteRequest) -> Result<DeleteResponse, Status> {
+        self.table
+            .delete_sequenced(&req.key)
+            .await
+            .map_err(status)?;
+        Ok(DeleteResponse {})
+    }
+
+    async fn scan(&self, req: ScanRequest) -> Result<ScanResponse, Status> {
+        let limit = match req.limit as usize {
+            0 => DEFAULT_SCAN_LIMIT,
+            limit => limit.min(MAX_SCAN_LIMIT),
+        };
+        // A cursor pins the table while it is alive, so it is not kept across
+        // requests. Clients continue the scan from the returned key instead.
+        let guard = self.table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&req.start).await.map_err(status)?;
+        let mut resp = ScanResponse::default();
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            if req.end.as_ref().map_or(false, |end| key >= end.as_slice()) {
+                break;
+            }
+            if resp.entries.len() == limit {
+                resp.next_start = Some(key.to_vec());
+                break;
+            }
+            resp.entries.push(KeyValue {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            });
+            cursor.next().await.map_err(status)?;
+        }
+        Ok(resp)
+    }
+
+    async fn batch(&self, req: BatchRequest) -> Result<BatchResponse, Status> {
+        // Writes in a batch share one LSN, so the last write of a key wins.
+        let lsn = self.table.next_lsn();
+        let mut batch = WriteBatch::new();
+        for op in &req.ops {
+            match &op.value {
+                Some(value) => batch.put(&op.key, lsn, value),
+                None => batch.delete(&op.key, lsn),
+            }
+        }
+        self.table.write_batch(&batch).await.map_err(status)?;
+        Ok(BatchResponse {})
+    }
+
+    async fn stats(&self, _: StatsRequest) -> Result<StatsResponse, Status> {
+        let json = self.table.debug_state_json().await.map_err(status)?;
+        Ok(StatsResponse { json })
+    }
+}
+
+impl<B> Service<http::Request<B>> for KvService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let kv = self.clone();
+        Box::pin(async move {
+            let resp = match req.uri().path() {
+                "/kv.Kv/Get" => unary(req, |r| async move { kv.get(r).await }).await,
+                "/kv.Kv/Put" => unary(req, |r| async move { kv.put(r).await }).await,
+                "/kv.Kv/Delete" => unary(req, |r| async move { kv.delete(r).await }).await,
+                "/kv.Kv/Scan" => unary(req, |r| async move { kv.scan(r).await }).await,
+                "/kv.Kv/Batch" => unary(req, |r| async move { kv.batch(r).await }).await,
+                "/kv.Kv/Stats" => unary(req, |r| async move { kv.stats(r).await }).await,
+                _ => http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", tonic::Code::Unimplemented as i32)
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap(),
+            };
+            Ok(resp)
+        })
+    }
+}
+
+impl NamedService for KvService {
+    const NAME: &'static str = "kv.Kv";
+}
+
+/// Serves a unary call with the handler.
+async fn unary<B, Req, Resp, F, Fut>(req: http::Request<B>, handler: F) -> http::Response<BoxBody>
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+    Req: Message + Default + 'static,
+    Resp: Message + 'static,
+    F: FnOnce(Req) -> Fut,
+    Fut: Future<Output = Result<Resp, Status>> + Send + 'static,
+{
+    struct Handler<F>(Option<F>);
+
+    impl<Req, Resp, F, Fut> UnaryService<Req> for Handler<F>
+    where
+        F: FnOnce(Req) -> Fut,
+        Fut: Future<Output = Result<Resp, Status>> + Send + 'static,
+    {
+        type Response = Resp;
+        type Future = BoxFuture<Response<Resp>, Status>;
+
+        fn call(&mut self, req: Request<Req>) -> Self::Future {
+            let handler = self.0.take().expect("a unary call is handled once");
+            let fut = handler(req.into_inner());
+            Box::pin(async move { fut.await.map(Response::new) })
+        }
+    }
+
+    let mut grpc = Grpc::new(ProstCodec::<Resp, Req>::default());
+    grpc.unary(Handler(Some(handler)), req).await
+}
+
+fn status(err: Error) -> Status {
+    match err {
+        Error::InvalidArgument(msg) => Status::invalid_argument(msg),
+        Error::Busy => Status::unavailable(err.to_string()),
+        err => Status::internal(err.to_string()),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GetRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GetResponse {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    value: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct PutRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct PutResponse {}
+
+#[derive(Clone, PartialEq, Message)]
+struct DeleteRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct DeleteResponse {}
+
+#[derive(Clone, PartialEq, Message)]
+struct ScanRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    start: Vec<u8>,
+    #[prost(bytes = "vec", optional, tag = "2")]
+    end: Option<Vec<u8>>,
+    #[prost(uint32, tag = "3")]
+    limit: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct KeyValue {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ScanResponse {
+    #[prost(message, repeated, tag = "1")]
+    entries: Vec<KeyValue>,
+    #[prost(bytes = "vec", optional, tag = "2")]
+    next_start: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct BatchOp {
+    #[prost(bytes = "vec", tag = "1")]
+    key: Vec<u8>,
+    #[prost(bytes = "vec", optional, tag = "2")]
+    value: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct BatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    ops: Vec<BatchOp>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct BatchResponse {}
+
+#[derive(Clone, PartialEq, Message)]
+struct StatsRequest {}
+
+#[derive(Clone, PartialEq, Message)]
+struct StatsResponse {
+    #[prost(string, tag = "1")]
+    json: String,
+}
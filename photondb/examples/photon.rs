@@ -1,6 +1,15 @@
+//! PhotonIO is only available on Unix, so this example does nothing on other
+//! platforms. See `std.rs` for an example that runs everywhere.
+
+#[cfg(unix)]
 use photondb::{Result, Table, TableOptions};
+#[cfg(unix)]
 use tempfile::tempdir;
 
+#[cfg(not(unix))]
+fn main() {}
+
+#[cfg(unix)]
 #[photonio::main]
 async fn main() -> Result<()> {
     let path = tempdir().unwrap();
@@ -0,0 +1,178 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{ErrorKind, Result},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{channel::mpsc, StreamExt};
+use log::warn;
+use parking_lot::Mutex;
+
+use crate::env::{Directory, Env, PositionalReaderExt, SequentialWriter, SequentialWriterExt};
+
+/// The name of the audit log file in the table directory.
+pub(crate) const AUDIT_LOG_NAME: &str = "AUDIT";
+
+/// The name of the file that the audit log is rewritten to when opened.
+const AUDIT_LOG_TMP_NAME: &str = "AUDIT.tmp";
+
+thread_local! {
+    // The context of the current thread, recorded with audited operations.
+    static CONTEXT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A caller-provided context recorded with the audited operations.
+///
+/// The context is set per thread, and is recorded with the operations called
+/// on the thread, which is the calling thread for the synchronous APIs. It
+/// can describe who or what issues the operations, e.g. a user name or a
+/// ticket id.
+pub struct AuditContext;
+
+impl AuditContext {
+    /// Sets the context of the current thread.
+    pub fn set(context: impl Into<String>) {
+        CONTEXT.with(|c| *c.borrow_mut() = Some(context.into()));
+    }
+
+    /// Clears the context of the current thread.
+    pub fn clear() {
+        CONTEXT.with(|c| *c.borrow_mut() = None);
+    }
+
+    /// Returns the context of the current thread.
+    pub fn get() -> Option<String> {
+        CONTEXT.with(|c| c.borrow().clone())
+    }
+}
+
+/// An append-only log of destructive operations on a table.
+///
+/// Each operation is written as a line of
+/// `<timestamp_us> <op> <detail> context=<context>`, where the detail and the
+/// context are quoted.
+///
+/// The log is written through the environment of the table by a background
+/// task. Since environments only create files from scratch, the existing log
+/// is copied to a new file that replaces it when the log is opened.
+pub(crate) struct AuditLog<E: Env> {
+    lines: mpsc::UnboundedSender<String>,
+    task: Mutex<Option<E::JoinHandle<()>>>,
+}
+
+impl<E: Env> AuditLog<E> {
+    /// Opens the audit log in the directory, creating it if not exists.
+    pub(crate) async fn open(env: &E, dir: &Path) -> Result<Self> {
+        let path = dir.join(AUDIT_LOG_NAME);
+        let content = match env.metadata(&path).await {
+            Ok(metadata) => {
+                let mut buf = vec![0; metadata.len as usize];
+                let reader = env.open_positional_reader(&path).await?;
+                reader.read_exact_at(&mut buf, 0).await?;
+                buf
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let tmp_path = dir.join(AUDIT_LOG_TMP_NAME);
+        let mut writer = env.open_sequential_writer(&tmp_path).await?;
+        writer.write_all(&content).await?;
+        writer.sync_all().await?;
+        env.rename(&tmp_path, &path).await?;
+        env.open_dir(dir).await?.sync_all().await?;
+
+        let (lines, mut rx) = mpsc::unbounded::<String>();
+        let task = env.spawn_background(async move {
+            // Stops once the log is closed.
+            while let Some(line) = rx.next().await {
+                if let Err(err) = writer.write_all(line.as_bytes()).await {
+                    warn!("failed to write audit log: {err}");
+                }
+            }
+            if let Err(err) = writer.sync_all().await {
+                warn!("failed to sync audit log: {err}");
+            }
+        });
+        Ok(Self {
+            lines,
+            task: Mutex::new(Some(task)),
+        })
+    }
+
+    /// Closes the audit log after the recorded operations are written.
+    pub(crate) async fn close(&self) {
+        self.lines.close_channel();
+        let task = self.task.lock().take();
+        if let Some(task) = task {
+            task.await;
+        }
+    }
+
+    /// Records an operation with the context of the current thread.
+    ///
+    /// Failures are logged instead of failing the operation.
+    pub(crate) fn record(&self, op: &str, detail: &str) {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let context = AuditContext::get().unwrap_or_default();
+        let line = format!("{ts} {op} {detail:?} context={context:?}\n");
+        if self.lines.unbounded_send(line).is_err() {
+            warn!("failed to write audit log for {op}: the log is closed");
+        }
+    }
+}
+
+impl<E: Env> fmt::Debug for AuditLog<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLog").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::Mem;
+
+    #[test]
+    fn audit_log() {
+        let env = Mem::new();
+        env.run(async {
+            let dir = Path::new("/db");
+            env.create_dir_all(dir).await.unwrap();
+            let log = AuditLog::open(&env, dir).await.unwrap();
+            log.record("set_option", "a=1");
+            AuditContext::set("ticket \"1\"");
+            log.record("delete_range", "[a, b)");
+            AuditContext::clear();
+            assert_eq!(AuditContext::get(), None);
+            log.close().await;
+
+            // The log is appended to after reopening.
+            let log = AuditLog::open(&env, dir).await.unwrap();
+            log.record("set_option", "a=2");
+            log.close().await;
+
+            let path = dir.join(AUDIT_LOG_NAME);
+            let mut buf = vec![0; env.metadata(&path).await.unwrap().len as usize];
+            let reader = env.open_positional_reader(&path).await.unwrap();
+            reader.read_exact_at(&mut buf, 0).await.unwrap();
+            let content = String::from_utf8(buf).unwrap();
+            let lines: Vec<_> = content
+                .lines()
+                .map(|line| line.split_once(' ').unwrap().1)
+                .collect();
+            assert_eq!(
+                lines,
+                [
+                    r#"set_option "a=1" context="""#,
+                    r#"delete_range "[a, b)" context="ticket \"1\"""#,
+                    r#"set_option "a=2" context="""#,
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,267 @@
+//! Generates corrupted variants of a healthy table for tests.
+//!
+//! Each variant is a copy of the table with one kind of damage, so that tests
+//! can open it and assert that the damage is reported as an error instead of
+//! a panic. There is no write-ahead log in the engine, and the manifest is
+//! the only log that can be torn.
+
+use std::{fs, io, path::Path};
+
+use tempfile::{tempdir, TempDir};
+
+/// The size of the footer at the end of a page file, which consists of a
+/// magic number, two block handles, the compression and the checksum type.
+const FOOTER_SIZE: usize = 8 + 16 * 2 + 2;
+
+const PAGE_FILE_PREFIX: &str = "map_";
+const MANIFEST_FILE_PREFIX: &str = "MANIFEST_";
+
+/// A kind of damage applied to a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum CorruptionClass {
+    /// A bit is flipped in the footer of a page file.
+    FooterBitFlip,
+    /// A bit is flipped in the pages of a page file.
+    PageBitFlip,
+    /// A page file is truncated.
+    TruncatedPageFile,
+    /// A manifest file is truncated.
+    TruncatedManifest,
+    /// A page file is removed.
+    MissingPageFile,
+}
+
+impl CorruptionClass {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::FooterBitFlip,
+        Self::PageBitFlip,
+        Self::TruncatedPageFile,
+        Self::TruncatedManifest,
+        Self::MissingPageFile,
+    ];
+}
+
+/// A corrupted copy of a table.
+pub(crate) struct Variant {
+    pub(crate) class: CorruptionClass,
+    /// Describes the damage, like `map_1: flip bit 3 at 1024`.
+    pub(crate) name: String,
+    pub(crate) dir: TempDir,
+}
+
+/// Generates corrupted variants of the closed table at `src`.
+///
+/// The variants are generated systematically, so the same table always
+/// produces the same variants.
+pub(crate) fn generate(src: &Path) -> io::Result<Vec<Variant>> {
+    let mut variants = Vec::new();
+    for (name, len) in list_files(src, PAGE_FILE_PREFIX)? {
+        // Flips a different bit of each byte in the footer.
+        for i in 0..FOOTER_SIZE.min(len) {
+            let offset = len - FOOTER_SIZE.min(len) + i;
+            let bit = i % 8;
+            variants.push(corrupt(
+                src,
+                CorruptionClass::FooterBitFlip,
+                format!("{name}: flip bit {bit} at {offset}"),
+                |dir| flip_bit(&dir.join(&name), offset, bit),
+            )?);
+        }
+        // Pages are written at the beginning of the file, before the
+        // metadata of page groups.
+        for i in 0..8 {
+            let offset = len / 4 * i / 8;
+            variants.push(corrupt(
+                src,
+                CorruptionClass::PageBitFlip,
+                format!("{name}: flip bit {i} at {offset}"),
+                |dir| flip_bit(&dir.join(&name), offset, i),
+            )?);
+        }
+        for size in [0, len / 2, len.saturating_sub(1)] {
+            variants.push(corrupt(
+                src,
+                CorruptionClass::TruncatedPageFile,
+                format!("{name}: truncate to {size}"),
+                |dir| truncate(&dir.join(&name), size),
+            )?);
+        }
+        variants.push(corrupt(
+            src,
+            CorruptionClass::MissingPageFile,
+            format!("{name}: remove"),
+            |dir| fs::remove_file(dir.join(&name)),
+        )?);
+    }
+    for (name, len) in list_files(src, MANIFEST_FILE_PREFIX)? {
+        for size in [len / 2, len.saturating_sub(1)] {
+            variants.push(corrupt(
+                src,
+                CorruptionClass::TruncatedManifest,
+                format!("{name}: truncate to {size}"),
+                |dir| truncate(&dir.join(&name), size),
+            )?);
+        }
+    }
+    Ok(variants)
+}
+
+/// Returns the names and sizes of files with the prefix, in order of names.
+fn list_files(dir: &Path, prefix: &str) -> io::Result<Vec<(String, usize)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(prefix) {
+            files.push((name, entry.metadata()?.len() as usize));
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+fn corrupt(
+    src: &Path,
+    class: CorruptionClass,
+    name: String,
+    f: impl FnOnce(&Path) -> io::Result<()>,
+) -> io::Result<Variant> {
+    let dir = tempdir()?;
+    copy_dir(src, dir.path())?;
+    f(dir.path())?;
+    Ok(Variant { class, name, dir })
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), dst.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+fn flip_bit(path: &Path, offset: usize, bit: usize) -> io::Result<()> {
+    let mut content = fs::read(path)?;
+    content[offset] ^= 1 << bit;
+    fs::write(path, content)
+}
+
+fn truncate(path: &Path, size: usize) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(size as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, panic::AssertUnwindSafe};
+
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::{ChecksumType, Error, FlushOptions, PageStoreOptions, Result, Table, TableOptions};
+
+    const N: u64 = 100;
+
+    fn options() -> TableOptions {
+        TableOptions {
+            page_size: 128,
+            page_store: PageStoreOptions {
+                disable_space_reclaiming: true,
+                page_checksum_type: ChecksumType::CRC32,
+                avoid_flush_during_shutdown: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Opens the table and reads all entries and pages in it.
+    ///
+    /// Returns the number of entries read.
+    async fn check(path: &Path) -> Result<u64> {
+        let table = Table::open(path, options()).await?;
+        let result = read_all(&table).await;
+        let _ = table.close().await;
+        result
+    }
+
+    async fn read_all(table: &Table) -> Result<u64> {
+        let mut num_entries = 0;
+        {
+            let guard = table.pin();
+            let mut cursor = guard.cursor(u64::MAX);
+            cursor.seek(&[]).await?;
+            while cursor.key().is_some() {
+                num_entries += 1;
+                cursor.next().await?;
+            }
+        }
+        let report = table.verify_checksums().await?;
+        if !report.is_ok() {
+            return Err(Error::corrupted());
+        }
+        Ok(num_entries)
+    }
+
+    /// Creates a closed table with `N` entries flushed to page files.
+    async fn create() -> TempDir {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, options()).await.unwrap();
+        for i in 0..N {
+            let buf = i.to_be_bytes();
+            table.put(&buf, i, &buf).await.unwrap();
+        }
+        table.flush(&FlushOptions::default()).await;
+        table.close().await.unwrap();
+        path
+    }
+
+    #[photonio::test]
+    async fn corrupted_variants() {
+        let path = create().await;
+        assert_eq!(check(path.path()).await.unwrap(), N);
+
+        let variants = generate(path.path()).unwrap();
+        let mut detected = HashSet::new();
+        for variant in &variants {
+            match AssertUnwindSafe(check(variant.dir.path()))
+                .catch_unwind()
+                .await
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(_)) => {
+                    detected.insert(variant.class);
+                }
+                Err(_) => panic!("{} panics", variant.name),
+            }
+        }
+        for class in CorruptionClass::ALL {
+            assert!(detected.contains(&class), "{class:?} is not detected");
+        }
+    }
+
+    #[photonio::test]
+    async fn corrupted_page_context() {
+        let path = create().await;
+        let mut located = 0;
+        for variant in generate(path.path()).unwrap() {
+            if variant.class != CorruptionClass::PageBitFlip {
+                continue;
+            }
+            if let Err(Error::Corrupted {
+                file: Some(file),
+                offset,
+            }) = check(variant.dir.path()).await
+            {
+                assert!(variant.name.starts_with(&file), "{}", variant.name);
+                assert!(offset.is_some());
+                located += 1;
+            }
+        }
+        assert!(located > 0);
+    }
+}
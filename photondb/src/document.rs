@@ -0,0 +1,358 @@
+//! A typed document layer on top of a table.
+//!
+//! Documents are serializable values stored with a pluggable [`Codec`], and
+//! can be looked up by their primary keys or by secondary keys declared with
+//! [`Document::index_keys`].
+//!
+//! This module is behind the `document` feature.
+//!
+//! ```no_run
+//! use photondb::{
+//!     document::{Collection, Document},
+//!     env::Photon,
+//!     Table, TableOptions,
+//! };
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     id: u64,
+//!     email: String,
+//! }
+//!
+//! impl Document for User {
+//!     fn key(&self) -> Vec<u8> {
+//!         self.id.to_be_bytes().to_vec()
+//!     }
+//!
+//!     fn index_keys(&self) -> Vec<(&'static str, Vec<u8>)> {
+//!         vec![("email", self.email.as_bytes().to_vec())]
+//!     }
+//! }
+//!
+//! # async fn example() -> photondb::Result<()> {
+//! let table = Table::open("/tmp/users", TableOptions::default()).await?;
+//! let users = Collection::<Photon, User>::new((*table).clone(), "users")?;
+//! users
+//!     .put(&User {
+//!         id: 1,
+//!         email: "a@example.com".to_owned(),
+//!     })
+//!     .await?;
+//! let found = users.find("email", b"a@example.com").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{env::Env, raw, Error, Result, WriteBatch};
+
+/// A codec to encode documents to bytes and decode them back.
+pub trait Codec {
+    /// Encodes a value to bytes.
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>>;
+
+    /// Decodes a value from bytes.
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T>;
+}
+
+/// A compact codec with [bincode](https://crates.io/crates/bincode).
+///
+/// Fields are encoded without names, so the fields of a document type can
+/// not be changed once documents are stored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        bincode::serialize(value).map_err(|err| encode_error(&err))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        bincode::deserialize(bytes).map_err(|err| decode_error(&err))
+    }
+}
+
+/// A self-describing codec with [MessagePack](https://msgpack.org).
+///
+/// Fields are encoded with names, so fields can be added to a document type
+/// with `#[serde(default)]` after documents are stored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MsgPack;
+
+impl Codec for MsgPack {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(|err| encode_error(&err))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        rmp_serde::from_slice(bytes).map_err(|err| decode_error(&err))
+    }
+}
+
+fn encode_error(err: &dyn std::fmt::Display) -> Error {
+    Error::InvalidArgument(format!("failed to encode document: {err}"))
+}
+
+fn decode_error(err: &dyn std::fmt::Display) -> Error {
+    Error::InvalidArgument(format!("failed to decode document: {err}"))
+}
+
+/// A value stored in a [`Collection`].
+pub trait Document: Serialize + DeserializeOwned {
+    /// Returns the primary key of the document.
+    fn key(&self) -> Vec<u8>;
+
+    /// Returns the secondary keys of the document, as pairs of index names
+    /// and keys.
+    ///
+    /// A document can have any number of keys in an index, and different
+    /// documents can have the same key. Index names must not contain `\0`.
+    fn index_keys(&self) -> Vec<(&'static str, Vec<u8>)> {
+        Vec::new()
+    }
+}
+
+/// A collection of documents in a table.
+///
+/// Documents are stored under `<name>\0d<key>`, and each secondary key is
+/// stored as an empty entry under
+/// `<name>\0i<index>\0<key_len:u32><key><primary_key>`, so a table can hold
+/// multiple collections next to other entries.
+///
+/// A document and its secondary keys are written in a batch with the same
+/// LSN. Writes to the same document must not be concurrent, otherwise the
+/// secondary keys of the document may be left behind. Such keys are skipped
+/// by [`Collection::find`].
+pub struct Collection<E: Env, D, C = Bincode> {
+    table: raw::Table<E>,
+    name: Vec<u8>,
+    _marker: PhantomData<fn() -> (D, C)>,
+}
+
+impl<E: Env, D: Document, C: Codec> Collection<E, D, C> {
+    /// Creates a collection with the name in the table.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the name contains `\0`.
+    pub fn new(table: raw::Table<E>, name: &str) -> Result<Self> {
+        if name.contains('\0') {
+            return Err(Error::InvalidArgument(format!(
+                "invalid collection name {name:?}"
+            )));
+        }
+        Ok(Self {
+            table,
+            name: name.as_bytes().to_vec(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Gets the document with the primary key.
+    pub async fn get(&self, key: &[u8]) -> Result<Option<D>> {
+        match self.table.get(&self.doc_key(key), u64::MAX).await? {
+            Some(value) => Ok(Some(C::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Puts the document to the collection.
+    ///
+    /// This replaces the document with the same primary key and its secondary
+    /// keys.
+    pub async fn put(&self, doc: &D) -> Result<()> {
+        let key = doc.key();
+        let value = C::encode(doc)?;
+        let new_keys = self.entry_keys(doc, &key)?;
+        let old_keys = match self.get(&key).await? {
+            Some(old) => self.entry_keys(&old, &key)?,
+            None => Vec::new(),
+        };
+        let lsn = self.table.next_lsn();
+        let mut batch = WriteBatch::new();
+        for old_key in old_keys.iter().filter(|k| !new_keys.contains(k)) {
+            batch.delete(old_key, lsn);
+        }
+        for new_key in &new_keys {
+            batch.put(new_key, lsn, &[]);
+        }
+        batch.put(&self.doc_key(&key), lsn, &value);
+        self.table.write_batch(&batch).await
+    }
+
+    /// Deletes the document with the primary key.
+    ///
+    /// Returns true if the document exists.
+    pub async fn delete(&self, key: &[u8]) -> Result<bool> {
+        let Some(old) = self.get(key).await? else {
+            return Ok(false);
+        };
+        let lsn = self.table.next_lsn();
+        let mut batch = WriteBatch::new();
+        for old_key in self.entry_keys(&old, key)? {
+            batch.delete(&old_key, lsn);
+        }
+        batch.delete(&self.doc_key(key), lsn);
+        self.table.write_batch(&batch).await?;
+        Ok(true)
+    }
+
+    /// Finds the documents with the secondary key in the index.
+    ///
+    /// Documents are returned in the order of their primary keys.
+    pub async fn find(&self, index: &str, key: &[u8]) -> Result<Vec<D>> {
+        let prefix = self.index_prefix(index, key)?;
+        let mut doc_keys = Vec::new();
+        {
+            let guard = self.table.pin();
+            let mut cursor = guard.cursor(u64::MAX);
+            cursor.seek(&prefix).await?;
+            while let Some(index_key) = cursor.key() {
+                let Some(doc_key) = index_key.strip_prefix(prefix.as_slice()) else {
+                    break;
+                };
+                doc_keys.push(doc_key.to_vec());
+                cursor.next().await?;
+            }
+        }
+        let mut docs = Vec::with_capacity(doc_keys.len());
+        for doc_key in doc_keys {
+            // Skips secondary keys left behind by concurrent writes.
+            if let Some(doc) = self.get(&doc_key).await? {
+                if doc
+                    .index_keys()
+                    .iter()
+                    .any(|(name, k)| *name == index && k == key)
+                {
+                    docs.push(doc);
+                }
+            }
+        }
+        Ok(docs)
+    }
+
+    fn doc_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.name.len() + 2 + key.len());
+        buf.extend_from_slice(&self.name);
+        buf.extend_from_slice(b"\0d");
+        buf.extend_from_slice(key);
+        buf
+    }
+
+    fn index_prefix(&self, index: &str, key: &[u8]) -> Result<Vec<u8>> {
+        if index.contains('\0') {
+            return Err(Error::InvalidArgument(format!(
+                "invalid index name {index:?}"
+            )));
+        }
+        let mut buf = Vec::with_capacity(self.name.len() + index.len() + 7 + key.len());
+        buf.extend_from_slice(&self.name);
+        buf.extend_from_slice(b"\0i");
+        buf.extend_from_slice(index.as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        buf.extend_from_slice(key);
+        Ok(buf)
+    }
+
+    /// Returns the entry keys of the secondary keys of the document.
+    fn entry_keys(&self, doc: &D, doc_key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::new();
+        for (index, key) in doc.index_keys() {
+            let mut buf = self.index_prefix(index, &key)?;
+            buf.extend_from_slice(doc_key);
+            keys.push(buf);
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use serde::Deserialize;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{env::Photon, Table, TableOptions};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct User {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    impl Document for User {
+        fn key(&self) -> Vec<u8> {
+            self.id.to_be_bytes().to_vec()
+        }
+
+        fn index_keys(&self) -> Vec<(&'static str, Vec<u8>)> {
+            let mut keys = vec![("name", self.name.as_bytes().to_vec())];
+            for tag in &self.tags {
+                keys.push(("tag", tag.as_bytes().to_vec()));
+            }
+            keys
+        }
+    }
+
+    fn user(id: u64, name: &str, tags: &[&str]) -> User {
+        User {
+            id,
+            name: name.to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    async fn crud<C: Codec>() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, TableOptions::default()).await.unwrap();
+        let users = Collection::<Photon, User, C>::new((*table).clone(), "users").unwrap();
+
+        users.put(&user(1, "a", &["x", "y"])).await.unwrap();
+        users.put(&user(2, "b", &["x"])).await.unwrap();
+        assert_eq!(
+            users.get(&1u64.to_be_bytes()).await.unwrap(),
+            Some(user(1, "a", &["x", "y"]))
+        );
+        assert_eq!(users.find("tag", b"x").await.unwrap().len(), 2);
+        assert_eq!(
+            users.find("name", b"b").await.unwrap(),
+            vec![user(2, "b", &["x"])]
+        );
+        // A key in an index is not a prefix of another key.
+        assert!(users.find("name", b"").await.unwrap().is_empty());
+
+        // Replaces the secondary keys of a document.
+        users.put(&user(1, "c", &["y"])).await.unwrap();
+        assert!(users.find("name", b"a").await.unwrap().is_empty());
+        assert_eq!(
+            users.find("tag", b"x").await.unwrap(),
+            vec![user(2, "b", &["x"])]
+        );
+
+        assert!(users.delete(&2u64.to_be_bytes()).await.unwrap());
+        assert!(!users.delete(&2u64.to_be_bytes()).await.unwrap());
+        assert_eq!(users.get(&2u64.to_be_bytes()).await.unwrap(), None);
+        assert!(users.find("tag", b"x").await.unwrap().is_empty());
+
+        // Collections in the same table do not see each other.
+        let others = Collection::<Photon, User, C>::new((*table).clone(), "user").unwrap();
+        assert_eq!(others.get(&1u64.to_be_bytes()).await.unwrap(), None);
+        assert!(Collection::<Photon, User, C>::new((*table).clone(), "a\0b").is_err());
+        drop((users, others));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn bincode_crud() {
+        crud::<Bincode>().await;
+    }
+
+    #[photonio::test]
+    async fn msgpack_crud() {
+        crud::<MsgPack>().await;
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+
+use super::{Directory as _, SequentialWriter as _, *};
+
+/// An implementation of [`Env`] that simulates crashes on top of another
+/// [`Env`].
+///
+/// The environment tracks the files written through it. After
+/// [`Faulty::crash`], all modifications to the filesystem are discarded as if
+/// the process was killed, while the data written before is kept.
+/// [`Faulty::power_cut`] further drops the data that is not synced and the
+/// files that are not linked by syncing their directories, as if the machine
+/// lost power.
+///
+/// Renames and removals are treated as durable once they return. Files that
+/// are not written through the environment are never modified.
+#[derive(Clone, Debug)]
+pub struct Faulty<E> {
+    inner: E,
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    crashed: AtomicBool,
+    files: Mutex<HashMap<PathBuf, FileState>>,
+}
+
+#[derive(Debug, Default)]
+struct FileState {
+    len: u64,
+    synced_len: u64,
+    // Whether the directory is synced after the file is created.
+    linked: bool,
+}
+
+impl<E: Env> Faulty<E> {
+    /// Creates an environment on top of `inner`.
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            state: Arc::default(),
+        }
+    }
+
+    /// Simulates a crash of the process.
+    ///
+    /// All modifications to the filesystem after this are discarded.
+    pub fn crash(&self) {
+        self.state.crashed.store(true, Ordering::Release);
+    }
+
+    /// Simulates a power cut of the machine.
+    ///
+    /// This crashes the environment, truncates the files to the length of
+    /// their last sync, and removes the files that are not linked.
+    pub fn power_cut(&self) -> Result<()> {
+        self.crash();
+        let files = std::mem::take(&mut *self.state.files.lock());
+        for (path, file) in files {
+            let res = if !file.linked {
+                std::fs::remove_file(&path)
+            } else if file.len > file.synced_len {
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|f| f.set_len(file.synced_len))
+            } else {
+                Ok(())
+            };
+            match res {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the environment is crashed.
+    pub fn is_crashed(&self) -> bool {
+        self.state.crashed.load(Ordering::Acquire)
+    }
+}
+
+impl State {
+    fn is_crashed(&self) -> bool {
+        self.crashed.load(Ordering::Acquire)
+    }
+
+    fn update<F: FnOnce(&mut FileState)>(&self, path: &Path, f: F) {
+        if let Some(file) = self.files.lock().get_mut(path) {
+            f(file);
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Env> Env for Faulty<E> {
+    type PositionalReader = E::PositionalReader;
+    type SequentialWriter = SequentialWriter<E>;
+    type JoinHandle<T: Send> = E::JoinHandle<T>;
+    type Directory = Directory<E>;
+
+    async fn open_positional_reader<P>(&self, path: P) -> Result<Self::PositionalReader>
+    where
+        P: AsRef<Path> + Send,
+    {
+        self.inner.open_positional_reader(path).await
+    }
+
+    async fn open_sequential_writer<P>(&self, path: P) -> Result<Self::SequentialWriter>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let path = path.as_ref().to_owned();
+        if self.is_crashed() {
+            return Ok(SequentialWriter {
+                file: None,
+                path,
+                state: self.state.clone(),
+            });
+        }
+        // A file that already exists keeps its directory entry.
+        let linked = self
+            .state
+            .files
+            .lock()
+            .get(&path)
+            .map_or_else(|| path.exists(), |file| file.linked);
+        let file = self.inner.open_sequential_writer(&path).await?;
+        self.state.files.lock().insert(
+            path.clone(),
+            FileState {
+                linked,
+                ..Default::default()
+            },
+        );
+        Ok(SequentialWriter {
+            file: Some(file),
+            path,
+            state: self.state.clone(),
+        })
+    }
+
+    fn spawn_background<F>(&self, f: F) -> Self::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        self.inner.spawn_background(f)
+    }
+
+    async fn rename<P: AsRef<Path> + Send, Q: AsRef<Path> + Send>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        if self.is_crashed() {
+            return Ok(());
+        }
+        self.inner.rename(from.as_ref(), to.as_ref()).await?;
+        let mut files = self.state.files.lock();
+        match files.remove(from.as_ref()) {
+            Some(file) => files.insert(to.as_ref().to_owned(), file),
+            None => files.remove(to.as_ref()),
+        };
+        Ok(())
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        if self.is_crashed() {
+            return Ok(());
+        }
+        self.inner.remove_file(path.as_ref()).await?;
+        self.state.files.lock().remove(path.as_ref());
+        Ok(())
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        if self.is_crashed() {
+            return Ok(());
+        }
+        self.inner.create_dir_all(path).await
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        if self.is_crashed() {
+            return Ok(());
+        }
+        self.inner.remove_dir_all(path.as_ref()).await?;
+        self.state
+            .files
+            .lock()
+            .retain(|file, _| !file.starts_with(path.as_ref()));
+        Ok(())
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        self.inner.read_dir(path)
+    }
+
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock> {
+        self.inner.lock_file(path)
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
+        self.inner.metadata(path).await
+    }
+
+    async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
+        let path = path.as_ref().to_owned();
+        let dir = self.inner.open_dir(&path).await?;
+        Ok(Directory {
+            dir,
+            path,
+            state: self.state.clone(),
+        })
+    }
+
+    fn is_native(&self) -> bool {
+        self.inner.is_native()
+    }
+}
+
+pub struct SequentialWriter<E: Env> {
+    // The file is not opened if the environment is crashed.
+    file: Option<E::SequentialWriter>,
+    path: PathBuf,
+    state: Arc<State>,
+}
+
+#[async_trait]
+impl<E: Env> super::SequentialWriter for SequentialWriter<E> {
+    type Write<'a>
+        = impl Future<Output = Result<usize>> + 'a + Send
+    where
+        Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        async move {
+            let Some(file) = self.file.as_mut() else {
+                return Ok(buf.len());
+            };
+            if self.state.is_crashed() {
+                return Ok(buf.len());
+            }
+            let n = file.write(buf).await?;
+            self.state.update(&self.path, |f| f.len += n as u64);
+            Ok(n)
+        }
+    }
+
+    async fn sync_data(&mut self) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        if self.state.is_crashed() {
+            return Ok(());
+        }
+        file.sync_data().await?;
+        self.state.update(&self.path, |f| f.synced_len = f.len);
+        Ok(())
+    }
+
+    async fn sync_all(&mut self) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        if self.state.is_crashed() {
+            return Ok(());
+        }
+        file.sync_all().await?;
+        self.state.update(&self.path, |f| f.synced_len = f.len);
+        Ok(())
+    }
+
+    async fn truncate(&self, len: u64) -> Result<()> {
+        let Some(file) = self.file.as_ref() else {
+            return Ok(());
+        };
+        if self.state.is_crashed() {
+            return Ok(());
+        }
+        file.truncate(len).await?;
+        self.state.update(&self.path, |f| {
+            f.len = len;
+            f.synced_len = f.synced_len.min(len);
+        });
+        Ok(())
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        match &self.file {
+            Some(file) => file.direct_io_ify(),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct Directory<E: Env> {
+    dir: E::Directory,
+    path: PathBuf,
+    state: Arc<State>,
+}
+
+#[async_trait]
+impl<E: Env> super::Directory for Directory<E> {
+    async fn sync_all(&self) -> Result<()> {
+        if self.state.is_crashed() {
+            return Ok(());
+        }
+        self.dir.sync_all().await?;
+        for (path, file) in self.state.files.lock().iter_mut() {
+            if path.parent() == Some(self.path.as_path()) {
+                file.linked = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[photonio::test]
+    async fn power_cut() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = Faulty::new(Std);
+        let synced = dir.path().join("synced");
+        let unlinked = dir.path().join("unlinked");
+
+        let mut file = env.open_sequential_writer(&synced).await.unwrap();
+        file.write_all(b"synced").await.unwrap();
+        file.sync_all().await.unwrap();
+        let base = env.open_dir(dir.path()).await.unwrap();
+        base.sync_all().await.unwrap();
+        file.write_all(b" unsynced").await.unwrap();
+
+        let mut other = env.open_sequential_writer(&unlinked).await.unwrap();
+        other.write_all(b"unlinked").await.unwrap();
+        other.sync_all().await.unwrap();
+
+        env.power_cut().unwrap();
+        // Writes after the crash are discarded.
+        file.write_all(b" crashed").await.unwrap();
+        env.remove_file(&synced).await.unwrap();
+        assert!(env.is_crashed());
+
+        assert_eq!(std::fs::read(&synced).unwrap(), b"synced");
+        assert!(!unlinked.exists());
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+#[cfg(any(unix, windows))]
+use std::{io::Result, path::Path};
+
+/// An exclusive advisory lock of a file.
+///
+/// The lock is released when it is dropped, or by the operating system when
+/// the process exits, so a crashed process never leaves a stale lock.
+#[derive(Debug, Default)]
+pub struct FileLock {
+    _file: Option<File>,
+}
+
+impl FileLock {
+    /// Returns a lock that holds no file, for environments whose files are
+    /// not shared with other processes.
+    pub fn none() -> Self {
+        Self::default()
+    }
+}
+
+/// Locks the file with `flock` on Unix or `LockFileEx` on Windows, creating
+/// it if it doesn't exist.
+///
+/// Returns an error of [`std::io::ErrorKind::WouldBlock`] if the file is
+/// locked by others.
+#[cfg(any(unix, windows))]
+pub(in crate::env) fn os_lock_file(path: &Path) -> Result<FileLock> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(path)?;
+    lock_exclusive(&file)?;
+    Ok(FileLock { _file: Some(file) })
+}
+
+#[cfg(unix)]
+fn lock_exclusive(file: &File) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the file descriptor is valid while the file is open.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn lock_exclusive(file: &File) -> Result<()> {
+    use std::os::windows::io::AsRawHandle;
+
+    use windows_sys::Win32::{
+        Foundation::{ERROR_LOCK_VIOLATION, HANDLE},
+        Storage::FileSystem::{LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY},
+        System::IO::OVERLAPPED,
+    };
+
+    let flags = LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY;
+    // SAFETY: the handle is valid while the file is open, and the overlapped
+    // structure is only used during the call.
+    let locked = unsafe {
+        let mut overlapped: OVERLAPPED = std::mem::zeroed();
+        LockFileEx(
+            file.as_raw_handle() as HANDLE,
+            flags,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked == 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        return Err(err);
+    }
+    Ok(())
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::oneshot,
+    future::BoxFuture,
+    pin_mut,
+    task::{waker, ArcWake},
+};
+use parking_lot::{Mutex, RwLock};
+
+use super::*;
+
+/// The id of the future driven by [`Mem::run`].
+const MAIN_TASK: u64 = 0;
+
+/// An implementation of [`Env`] that keeps files in memory.
+///
+/// All tasks spawned through the environment run on the thread that calls
+/// [`Mem::run`], in the order they are woken. The environment doesn't use
+/// threads or the filesystem of the platform, so it works on targets without
+/// them, like `wasm32-wasi`. Table options that need background threads are
+/// not supported, and the directory of a table is not locked.
+///
+/// Files are shared by the clones of the environment, and are lost when the
+/// last clone is dropped. Syncs are no-ops.
+///
+/// The environment panics if no task can make progress, which means that the
+/// tasks are deadlocked or wait for something outside of the environment.
+///
+/// ```
+/// use photondb::{env::Mem, raw::Table, TableOptions};
+///
+/// let env = Mem::new();
+/// env.run(async {
+///     let table = Table::open(env.clone(), "/db", TableOptions::default())
+///         .await
+///         .unwrap();
+///     table.put(b"key", 1, b"value").await.unwrap();
+///     table.close().await.unwrap();
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct Mem {
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    fs: Mutex<Fs>,
+    tasks: Mutex<Tasks>,
+}
+
+#[derive(Default)]
+struct Fs {
+    files: HashMap<PathBuf, Arc<RwLock<Vec<u8>>>>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl Fs {
+    fn file(&self, path: &Path) -> Result<Arc<RwLock<Vec<u8>>>> {
+        self.files.get(path).cloned().ok_or_else(not_found)
+    }
+
+    /// Returns an error if the parent directory of the path doesn't exist.
+    fn check_parent(&self, path: &Path) -> Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !self.dirs.contains(parent) => {
+                Err(not_found())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tasks {
+    next_id: u64,
+    // Spawned tasks that are not being polled.
+    tasks: HashMap<u64, BoxFuture<'static, ()>>,
+    runnable: VecDeque<u64>,
+}
+
+impl Tasks {
+    fn wake(&mut self, id: u64) {
+        if !self.runnable.contains(&id) {
+            self.runnable.push_back(id);
+        }
+    }
+}
+
+fn not_found() -> Error {
+    Error::from(ErrorKind::NotFound)
+}
+
+impl Mem {
+    /// Creates an environment without files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs a future and the spawned tasks until the future completes.
+    ///
+    /// Tasks that are not completed when this returns are kept, and continue
+    /// in the next call.
+    pub fn run<F: Future>(&self, future: F) -> F::Output {
+        pin_mut!(future);
+        self.shared.tasks.lock().wake(MAIN_TASK);
+        loop {
+            let id = self
+                .shared
+                .tasks
+                .lock()
+                .runnable
+                .pop_front()
+                .expect("no task can make progress in memory environment");
+            let waker = waker(Arc::new(TaskWaker {
+                id,
+                shared: Arc::downgrade(&self.shared),
+            }));
+            let mut cx = Context::from_waker(&waker);
+            if id == MAIN_TASK {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                continue;
+            }
+            let task = self.shared.tasks.lock().tasks.remove(&id);
+            if let Some(mut task) = task {
+                if task.as_mut().poll(&mut cx).is_pending() {
+                    self.shared.tasks.lock().tasks.insert(id, task);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Mem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mem")
+            .field("files", &self.shared.fs.lock().files.len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Env for Mem {
+    type PositionalReader = PositionalReader;
+    type SequentialWriter = SequentialWriter;
+    type JoinHandle<T: Send> = JoinHandle<T>;
+    type Directory = Directory;
+
+    async fn open_positional_reader<P>(&self, path: P) -> Result<Self::PositionalReader>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let data = self.shared.fs.lock().file(path.as_ref())?;
+        Ok(PositionalReader(data))
+    }
+
+    async fn open_sequential_writer<P>(&self, path: P) -> Result<Self::SequentialWriter>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let path = path.as_ref();
+        let mut fs = self.shared.fs.lock();
+        fs.check_parent(path)?;
+        if fs.dirs.contains(path) {
+            return Err(ErrorKind::IsADirectory.into());
+        }
+        // Truncates the file if it exists, like `File::create`.
+        let data = fs.files.entry(path.to_owned()).or_default().clone();
+        data.write().clear();
+        Ok(SequentialWriter(data))
+    }
+
+    fn spawn_background<F>(&self, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let (tx, rx) = oneshot::channel();
+        let mut tasks = self.shared.tasks.lock();
+        tasks.next_id += 1;
+        let id = tasks.next_id;
+        tasks.tasks.insert(
+            id,
+            Box::pin(async move {
+                let _ = tx.send(f.await);
+            }),
+        );
+        tasks.wake(id);
+        JoinHandle(rx)
+    }
+
+    async fn rename<P: AsRef<Path> + Send, Q: AsRef<Path> + Send>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        let mut fs = self.shared.fs.lock();
+        fs.check_parent(to.as_ref())?;
+        let data = fs.files.remove(from.as_ref()).ok_or_else(not_found)?;
+        fs.files.insert(to.as_ref().to_owned(), data);
+        Ok(())
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        let mut fs = self.shared.fs.lock();
+        fs.files.remove(path.as_ref()).ok_or_else(not_found)?;
+        Ok(())
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        let mut fs = self.shared.fs.lock();
+        for dir in path.as_ref().ancestors() {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            if fs.files.contains_key(dir) {
+                return Err(ErrorKind::AlreadyExists.into());
+            }
+            fs.dirs.insert(dir.to_owned());
+        }
+        Ok(())
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut fs = self.shared.fs.lock();
+        if !fs.dirs.contains(path) {
+            return Err(not_found());
+        }
+        fs.dirs.retain(|dir| !dir.starts_with(path));
+        fs.files.retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let fs = self.shared.fs.lock();
+        if !fs.dirs.contains(path) {
+            return Err(not_found());
+        }
+        let mut entries: Vec<_> = fs
+            .dirs
+            .iter()
+            .chain(fs.files.keys())
+            .filter(|entry| entry.parent() == Some(path))
+            .cloned()
+            .collect();
+        entries.sort_unstable();
+        Ok(entries)
+    }
+
+    /// Files in memory are not shared with other processes, so they are not
+    /// locked.
+    fn lock_file<P: AsRef<Path>>(&self, _: P) -> Result<FileLock> {
+        Ok(FileLock::none())
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
+        let path = path.as_ref();
+        let fs = self.shared.fs.lock();
+        if fs.dirs.contains(path) {
+            return Ok(Metadata {
+                len: 0,
+                is_dir: true,
+            });
+        }
+        let data = fs.file(path)?;
+        let len = data.read().len() as u64;
+        Ok(Metadata { len, is_dir: false })
+    }
+
+    async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
+        let fs = self.shared.fs.lock();
+        if fs.files.contains_key(path.as_ref()) {
+            return Err(Error::new(ErrorKind::NotADirectory, "not a dir"));
+        }
+        if !fs.dirs.contains(path.as_ref()) {
+            return Err(not_found());
+        }
+        Ok(Directory)
+    }
+
+    fn is_native(&self) -> bool {
+        false
+    }
+}
+
+pub struct PositionalReader(Arc<RwLock<Vec<u8>>>);
+
+#[async_trait]
+impl super::PositionalReader for PositionalReader {
+    type ReadAt<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        async move {
+            let data = self.0.read();
+            let data = data.get(pos as usize..).unwrap_or_default();
+            let len = buf.len().min(data.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            Ok(len)
+        }
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct SequentialWriter(Arc<RwLock<Vec<u8>>>);
+
+#[async_trait]
+impl super::SequentialWriter for SequentialWriter {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        async move {
+            self.0.write().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    async fn sync_data(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn sync_all(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn truncate(&self, len: u64) -> Result<()> {
+        self.0.write().resize(len as usize, 0);
+        Ok(())
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct JoinHandle<T>(oneshot::Receiver<T>);
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|res| res.expect("the task is dropped"))
+    }
+}
+
+pub struct Directory;
+
+#[async_trait]
+impl super::Directory for Directory {
+    async fn sync_all(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    shared: Weak<Shared>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if let Some(shared) = arc_self.shared.upgrade() {
+            shared.tasks.lock().wake(arc_self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::PositionalReader as _, raw::Table, TableOptions};
+
+    #[test]
+    fn filesystem() {
+        let env = Mem::new();
+        env.run(async {
+            let dir = Path::new("/a/b");
+            assert!(env.open_sequential_writer(dir.join("f")).await.is_err());
+            env.create_dir_all(dir).await.unwrap();
+            let mut file = env.open_sequential_writer(dir.join("f")).await.unwrap();
+            file.write_all(b"hello").await.unwrap();
+            env.rename(dir.join("f"), dir.join("g")).await.unwrap();
+            assert_eq!(env.read_dir(dir).unwrap(), vec![dir.join("g")]);
+            assert_eq!(env.metadata(dir.join("g")).await.unwrap().len, 5);
+
+            let reader = env.open_positional_reader(dir.join("g")).await.unwrap();
+            let mut buf = [0; 8];
+            assert_eq!(reader.read_at(&mut buf, 1).await.unwrap(), 4);
+            assert_eq!(&buf[..4], b"ello");
+
+            env.remove_dir_all("/a").await.unwrap();
+            assert!(env.metadata(dir.join("g")).await.is_err());
+            assert!(env.read_dir("/a").is_err());
+        });
+    }
+
+    #[test]
+    fn table_operations() {
+        let env = Mem::new();
+        env.run(async {
+            let table = Table::open(env.clone(), "/db", TableOptions::default())
+                .await
+                .unwrap();
+            for i in 0..1000u64 {
+                let buf = i.to_be_bytes();
+                table.put(&buf, i, &buf).await.unwrap();
+            }
+            table.close().await.unwrap();
+
+            let table = Table::open(env.clone(), "/db", TableOptions::default())
+                .await
+                .unwrap();
+            for i in 0..1000u64 {
+                let buf = i.to_be_bytes();
+                assert_eq!(table.get(&buf, i).await.unwrap(), Some(buf.to_vec()));
+            }
+            table.close().await.unwrap();
+
+            // Background threads are not supported.
+            let options = TableOptions {
+                stats_dump_period: Some(std::time::Duration::from_secs(1)),
+                ..Default::default()
+            };
+            assert!(matches!(
+                Table::open(env.clone(), "/db", options).await,
+                Err(crate::Error::InvalidArgument(_))
+            ));
+        });
+    }
+}
@@ -1,16 +1,61 @@
 //! Environments for PhotonDB to interact with different runtimes and platforms.
-
-use std::{future::Future, io::Result, path::Path};
+//!
+//! The engine only spawns tasks and accesses files through [`Env`], so it does
+//! not depend on a specific runtime. [`Photon`] runs on PhotonIO and [`Std`]
+//! runs without a runtime. `Tokio` and `AsyncStd` run on Tokio and async-std,
+//! and are available with the `tokio` and `async-std` features.
+//!
+//! The environments backed by the filesystem are only available on Unix,
+//! except [`Std`], which also works on Windows. [`Mem`] keeps files in memory
+//! and runs tasks on the calling thread, so it also works on targets without
+//! threads or a filesystem, like `wasm32-wasi`.
+
+use std::{
+    future::Future,
+    io::Result,
+    path::{Path, PathBuf},
+};
 
 pub use async_trait::async_trait;
+#[cfg(unix)]
 pub use photonio::io::{Read, ReadAt, Write, WriteAt};
 
+#[cfg(any(unix, windows))]
 mod stdenv;
+#[cfg(any(unix, windows))]
 pub use stdenv::Std;
 
+#[cfg(unix)]
 mod photon;
+#[cfg(unix)]
 pub use photon::Photon;
 
+mod faulty;
+pub use faulty::Faulty;
+
+mod file_lock;
+#[cfg(any(unix, windows))]
+use file_lock::os_lock_file;
+pub use file_lock::FileLock;
+
+#[cfg(unix)]
+mod sim;
+#[cfg(unix)]
+pub use sim::{Sim, SimOptions};
+
+mod mem;
+pub use mem::Mem;
+
+#[cfg(all(unix, feature = "tokio"))]
+mod tokioenv;
+#[cfg(all(unix, feature = "tokio"))]
+pub use tokioenv::Tokio;
+
+#[cfg(all(unix, feature = "async-std"))]
+mod asyncstdenv;
+#[cfg(all(unix, feature = "async-std"))]
+pub use asyncstdenv::AsyncStd;
+
 /// Provides an environment to interact with a specific platform.
 #[async_trait]
 pub trait Env: Clone + Send + Sync + 'static {
@@ -59,10 +104,17 @@ pub trait Env: Clone + Send + Sync + 'static {
     /// See also [`std::fs::remove_dir_all`].
     async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()>;
 
-    /// Returns an iterator over the entries within a directory.
+    /// Returns the paths of the entries within a directory.
     /// See also [`std::fs::read_dir`].
     /// TODO: async iterator impl?
-    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<std::fs::ReadDir>;
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>>;
+
+    /// Locks a file exclusively, creating it if it doesn't exist.
+    ///
+    /// The lock is released when the returned [`FileLock`] is dropped, or when
+    /// the process exits. Returns an error of
+    /// [`std::io::ErrorKind::WouldBlock`] if the file is locked by others.
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock>;
 
     /// Given a path, query the file system to get information about a file,
     /// directory, etc.
@@ -71,6 +123,15 @@ pub trait Env: Clone + Send + Sync + 'static {
 
     /// Open the directory.
     async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory>;
+
+    /// Returns true if the environment runs on the operating system, with its
+    /// filesystem and threads.
+    ///
+    /// Features that need them, like the lock file of a store and the
+    /// background threads of a table, are unavailable in other environments.
+    fn is_native(&self) -> bool {
+        true
+    }
 }
 
 /// A reader that allows positional reads.
@@ -201,6 +262,14 @@ pub struct Metadata {
     pub is_dir: bool,
 }
 
+/// Returns the paths of the entries within a directory of the filesystem.
+#[cfg(any(unix, windows))]
+pub(in crate::env) fn read_dir_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect()
+}
+
 #[cfg(target_os = "linux")]
 pub(in crate::env) fn direct_io_ify(fd: i32) -> Result<()> {
     macro_rules! syscall {
@@ -219,7 +288,7 @@ pub(in crate::env) fn direct_io_ify(fd: i32) -> Result<()> {
     Ok(())
 }
 
-#[cfg(not(target_os = "linux"))]
+#[cfg(all(unix, not(target_os = "linux")))]
 pub(in crate::env) fn direct_io_ify(_: i32) -> Result<()> {
     Err(std::io::Error::new(
         std::io::ErrorKind::Unsupported,
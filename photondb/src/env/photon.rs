@@ -72,10 +72,14 @@ impl Env for Photon {
         std::fs::remove_dir_all(path) // TODO: async impl
     }
 
-    /// Returns an iterator over the entries within a directory.
+    /// Returns the paths of the entries within a directory.
     /// See also [`std::fs::read_dir`].
-    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<std::fs::ReadDir> {
-        std::fs::read_dir(path)
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        read_dir_paths(path.as_ref())
+    }
+
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock> {
+        os_lock_file(path.as_ref())
     }
 
     async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
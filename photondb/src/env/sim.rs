@@ -0,0 +1,530 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap},
+    fmt,
+    fs::File,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    os::unix::fs::FileExt,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures::{
+    channel::oneshot,
+    future::BoxFuture,
+    pin_mut,
+    task::{waker, ArcWake},
+};
+use parking_lot::Mutex;
+
+use super::*;
+
+/// The id of the future driven by [`Sim::run`].
+const MAIN_TASK: u64 = 0;
+
+/// Options for a [`Sim`] environment.
+#[derive(Clone, Debug)]
+pub struct SimOptions {
+    /// The seed that determines the schedule of tasks, the latency of file
+    /// operations, and the injected failures.
+    pub seed: u64,
+    /// The maximum latency of a file operation on the virtual clock.
+    ///
+    /// The latency of each operation is uniformly distributed in [0, max].
+    pub max_io_latency: Duration,
+    /// The probability that a file operation fails with an injected error
+    /// before it is performed.
+    pub io_error_probability: f64,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            max_io_latency: Duration::from_micros(100),
+            io_error_probability: 0.0,
+        }
+    }
+}
+
+/// An implementation of [`Env`] for deterministic simulation testing.
+///
+/// All tasks spawned through the environment run on the thread that calls
+/// [`Sim::run`], one poll at a time, in an order picked by a generator
+/// seeded with [`SimOptions::seed`]. File operations are performed with
+/// [`std::fs`] after a random latency on a virtual clock, and fail with
+/// [`SimOptions::io_error_probability`]. Time only advances when no task can
+/// make progress, so a run with the same seed takes the same schedule, and a
+/// failure found with a seed can be reproduced with it.
+///
+/// The environment panics if no task can make progress and no timer is
+/// pending, which means that the tasks are deadlocked.
+#[derive(Clone)]
+pub struct Sim {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    options: SimOptions,
+    state: Mutex<State>,
+}
+
+struct State {
+    rng: Rng,
+    now: Duration,
+    next_id: u64,
+    // Spawned tasks that are not being polled.
+    tasks: HashMap<u64, BoxFuture<'static, ()>>,
+    // Ordered so that picking a random task is deterministic.
+    runnable: BTreeSet<u64>,
+    timers: BinaryHeap<Reverse<(Duration, u64)>>,
+    timer_wakers: HashMap<u64, Waker>,
+}
+
+impl Sim {
+    /// Creates a simulation environment with the given options.
+    pub fn new(options: SimOptions) -> Self {
+        let state = State {
+            rng: Rng::new(options.seed),
+            now: Duration::ZERO,
+            next_id: MAIN_TASK + 1,
+            tasks: HashMap::new(),
+            runnable: BTreeSet::new(),
+            timers: BinaryHeap::new(),
+            timer_wakers: HashMap::new(),
+        };
+        Self {
+            shared: Arc::new(Shared {
+                options,
+                state: Mutex::new(state),
+            }),
+        }
+    }
+
+    /// Returns the seed of the simulation.
+    pub fn seed(&self) -> u64 {
+        self.shared.options.seed
+    }
+
+    /// Returns the time elapsed on the virtual clock.
+    pub fn now(&self) -> Duration {
+        self.shared.state.lock().now
+    }
+
+    /// Returns a future that completes after `duration` on the virtual clock.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        Sleep {
+            shared: self.shared.clone(),
+            deadline: self.now() + duration,
+            timer_id: None,
+        }
+    }
+
+    /// Runs a future and the spawned tasks until the future completes.
+    ///
+    /// Tasks that are not completed when this returns are kept, and continue
+    /// in the next call.
+    pub fn run<F: Future>(&self, future: F) -> F::Output {
+        let _reporter = SeedReporter(self.seed());
+        pin_mut!(future);
+        self.shared.state.lock().runnable.insert(MAIN_TASK);
+        loop {
+            let id = self.next_task();
+            let waker = waker(Arc::new(TaskWaker {
+                id,
+                shared: Arc::downgrade(&self.shared),
+            }));
+            let mut cx = Context::from_waker(&waker);
+            if id == MAIN_TASK {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                continue;
+            }
+            let task = self.shared.state.lock().tasks.remove(&id);
+            if let Some(mut task) = task {
+                if task.as_mut().poll(&mut cx).is_pending() {
+                    self.shared.state.lock().tasks.insert(id, task);
+                }
+            }
+        }
+    }
+
+    /// Picks the next task to poll, advancing the virtual clock if no task
+    /// is runnable.
+    fn next_task(&self) -> u64 {
+        loop {
+            let mut state = self.shared.state.lock();
+            if !state.runnable.is_empty() {
+                let index = state.rng.below(state.runnable.len() as u64) as usize;
+                let id = *state.runnable.iter().nth(index).unwrap();
+                state.runnable.remove(&id);
+                return id;
+            }
+            let Some(Reverse((deadline, timer_id))) = state.timers.pop() else {
+                panic!("no task can make progress in simulation");
+            };
+            state.now = state.now.max(deadline);
+            let waker = state.timer_wakers.remove(&timer_id);
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut state = self.shared.state.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.tasks.insert(id, Box::pin(future));
+        state.runnable.insert(id);
+    }
+
+    /// Performs a file operation after a random latency, or fails it.
+    async fn io<T, F>(&self, op: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send,
+    {
+        let (latency, fail) = {
+            let mut state = self.shared.state.lock();
+            let max = self.shared.options.max_io_latency.as_nanos() as u64;
+            let latency = Duration::from_nanos(state.rng.below(max.saturating_add(1)));
+            let fail = state.rng.chance(self.shared.options.io_error_probability);
+            (latency, fail)
+        };
+        self.sleep(latency).await;
+        if fail {
+            return Err(Error::new(ErrorKind::Other, "injected I/O error"));
+        }
+        op()
+    }
+}
+
+impl fmt::Debug for Sim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sim")
+            .field("options", &self.shared.options)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Env for Sim {
+    type PositionalReader = PositionalReader;
+    type SequentialWriter = SequentialWriter;
+    type JoinHandle<T: Send> = JoinHandle<T>;
+    type Directory = Directory;
+
+    async fn open_positional_reader<P>(&self, path: P) -> Result<Self::PositionalReader>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let file = self.io(|| File::open(path)).await?;
+        Ok(PositionalReader {
+            file,
+            sim: self.clone(),
+        })
+    }
+
+    async fn open_sequential_writer<P>(&self, path: P) -> Result<Self::SequentialWriter>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let file = self.io(|| File::create(path)).await?;
+        Ok(SequentialWriter {
+            file,
+            sim: self.clone(),
+        })
+    }
+
+    fn spawn_background<F>(&self, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.spawn(async move {
+            let _ = tx.send(f.await);
+        });
+        JoinHandle(rx)
+    }
+
+    async fn rename<P: AsRef<Path> + Send, Q: AsRef<Path> + Send>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        self.io(|| std::fs::rename(from, to)).await
+    }
+
+    async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        self.io(|| std::fs::remove_file(path)).await
+    }
+
+    async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        self.io(|| std::fs::create_dir_all(path)).await
+    }
+
+    async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        self.io(|| std::fs::remove_dir_all(path)).await
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        read_dir_paths(path.as_ref())
+    }
+
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock> {
+        os_lock_file(path.as_ref())
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
+        let raw_metadata = self.io(|| std::fs::metadata(path)).await?;
+        Ok(Metadata {
+            len: raw_metadata.len(),
+            is_dir: raw_metadata.is_dir(),
+        })
+    }
+
+    async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
+        let file = self.io(|| File::open(path)).await?;
+        Ok(Directory {
+            file,
+            sim: self.clone(),
+        })
+    }
+}
+
+pub struct PositionalReader {
+    file: File,
+    sim: Sim,
+}
+
+#[async_trait]
+impl super::PositionalReader for PositionalReader {
+    type ReadAt<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        self.sim.io(move || self.file.read_at(buf, pos))
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct SequentialWriter {
+    file: File,
+    sim: Sim,
+}
+
+#[async_trait]
+impl super::SequentialWriter for SequentialWriter {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        use std::io::Write as _;
+        let file = &mut self.file;
+        self.sim.io(move || file.write(buf))
+    }
+
+    async fn sync_data(&mut self) -> Result<()> {
+        let file = &self.file;
+        self.sim.io(|| file.sync_data()).await
+    }
+
+    async fn sync_all(&mut self) -> Result<()> {
+        let file = &self.file;
+        self.sim.io(|| file.sync_all()).await
+    }
+
+    async fn truncate(&self, len: u64) -> Result<()> {
+        self.sim.io(|| self.file.set_len(len)).await
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct JoinHandle<T>(oneshot::Receiver<T>);
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|res| res.expect("the task is dropped"))
+    }
+}
+
+pub struct Directory {
+    file: File,
+    sim: Sim,
+}
+
+#[async_trait]
+impl super::Directory for Directory {
+    async fn sync_all(&self) -> Result<()> {
+        self.sim.io(|| self.file.sync_all()).await
+    }
+}
+
+/// A future returned by [`Sim::sleep`].
+pub struct Sleep {
+    shared: Arc<Shared>,
+    deadline: Duration,
+    timer_id: Option<u64>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.shared.state.lock();
+        if state.now >= self.deadline {
+            return Poll::Ready(());
+        }
+        let timer_id = match self.timer_id {
+            Some(id) => id,
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.timers.push(Reverse((self.deadline, id)));
+                id
+            }
+        };
+        state.timer_wakers.insert(timer_id, cx.waker().clone());
+        drop(state);
+        self.timer_id = Some(timer_id);
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(timer_id) = self.timer_id {
+            self.shared.state.lock().timer_wakers.remove(&timer_id);
+        }
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    shared: Weak<Shared>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if let Some(shared) = arc_self.shared.upgrade() {
+            shared.state.lock().runnable.insert(arc_self.id);
+        }
+    }
+}
+
+/// Prints the seed if the simulation panics, so that it can be reproduced.
+struct SeedReporter(u64);
+
+impl Drop for SeedReporter {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!("simulation failed with seed {}", self.0);
+        }
+    }
+}
+
+/// A xorshift64* generator, which gives the same sequence on all platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in [0, n).
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Returns true with probability `p`.
+    fn chance(&mut self, p: f64) -> bool {
+        let x = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        x < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs tasks that record the order they are polled in.
+    fn trace(seed: u64) -> (Vec<u64>, Duration) {
+        let env = Sim::new(SimOptions {
+            seed,
+            ..Default::default()
+        });
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let env2 = env.clone();
+                let trace = trace.clone();
+                env.spawn_background(async move {
+                    for _ in 0..4 {
+                        trace.lock().push(i);
+                        env2.sleep(Duration::from_micros(i)).await;
+                    }
+                    i
+                })
+            })
+            .collect();
+        let outputs = env.run(futures::future::join_all(handles));
+        assert_eq!(outputs, vec![0, 1, 2, 3]);
+        let trace = trace.lock().clone();
+        (trace, env.now())
+    }
+
+    #[test]
+    fn deterministic_schedule() {
+        for seed in 0..8 {
+            assert_eq!(trace(seed), trace(seed));
+        }
+        assert!((0..8).any(|seed| trace(seed).0 != trace(0).0));
+    }
+
+    #[test]
+    fn inject_io_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = Sim::new(SimOptions {
+            io_error_probability: 1.0,
+            ..Default::default()
+        });
+        let res = env.run(env.open_sequential_writer(dir.path().join("file")));
+        assert!(res.is_err());
+        assert!(!dir.path().join("file").exists());
+    }
+
+    #[test]
+    #[should_panic(expected = "no task can make progress")]
+    fn detect_deadlock() {
+        let env = Sim::new(SimOptions::default());
+        env.run(futures::future::pending::<()>());
+    }
+}
@@ -1,8 +1,9 @@
+#[cfg(unix)]
+use std::{fs::rename as rename_file, os::fd::AsRawFd};
 use std::{
     fs::File,
     future::Future,
     io::Result,
-    os::fd::AsRawFd,
     pin::Pin,
     task::{Context, Poll},
     thread,
@@ -10,9 +11,15 @@ use std::{
 
 use futures::executor::block_on;
 
+#[cfg(windows)]
+use self::windows::rename as rename_file;
 use super::*;
 
 /// An implementation of [`Env`] based on [`std`].
+///
+/// On Windows, direct I/O is not supported, renames are written through to
+/// the disk before they return, and directories are not synced, since NTFS
+/// journals the changes of directories itself.
 #[derive(Clone, Debug)]
 pub struct Std;
 
@@ -54,7 +61,7 @@ impl Env for Std {
         from: P,
         to: Q,
     ) -> Result<()> {
-        std::fs::rename(from, to)
+        rename_file(from.as_ref(), to.as_ref())
     }
 
     /// An async version of [`std::fs::remove_file`].
@@ -72,10 +79,14 @@ impl Env for Std {
         std::fs::remove_dir_all(path)
     }
 
-    /// Returns an iterator over the entries within a directory.
-    /// See alos [`std::fs::read_dir`].
-    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<std::fs::ReadDir> {
-        std::fs::read_dir(path)
+    /// Returns the paths of the entries within a directory.
+    /// See also [`std::fs::read_dir`].
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        read_dir_paths(path.as_ref())
+    }
+
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock> {
+        os_lock_file(path.as_ref())
     }
 
     async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
@@ -86,6 +97,7 @@ impl Env for Std {
         })
     }
 
+    #[cfg(unix)]
     async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
         let file = File::open(path)?;
         if !file.metadata()?.is_dir() {
@@ -96,6 +108,18 @@ impl Env for Std {
         }
         Ok(Directory(file))
     }
+
+    #[cfg(windows)]
+    async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
+        // Directories can not be opened without backup semantics on Windows.
+        if !std::fs::metadata(path)?.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotADirectory,
+                "not a dir",
+            ));
+        }
+        Ok(Directory)
+    }
 }
 
 pub struct PositionalReader(File);
@@ -110,8 +134,16 @@ impl super::PositionalReader for PositionalReader {
         async move { self.0.read_at(buf, offset) }
     }
 
+    /// Reads with `seek_read`, which moves the cursor of the file, but readers
+    /// never read from the cursor.
+    #[cfg(windows)]
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], offset: u64) -> Self::ReadAt<'a> {
+        use std::os::windows::fs::FileExt;
+        async move { self.0.seek_read(buf, offset) }
+    }
+
     fn direct_io_ify(&self) -> Result<()> {
-        super::direct_io_ify(self.0.as_raw_fd())
+        direct_io_ify(&self.0)
     }
 }
 
@@ -139,10 +171,23 @@ impl super::SequentialWriter for SequentialWriter {
     }
 
     fn direct_io_ify(&self) -> Result<()> {
-        super::direct_io_ify(self.0.as_raw_fd())
+        direct_io_ify(&self.0)
     }
 }
 
+#[cfg(unix)]
+fn direct_io_ify(file: &File) -> Result<()> {
+    super::direct_io_ify(file.as_raw_fd())
+}
+
+#[cfg(windows)]
+fn direct_io_ify(_: &File) -> Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "enable direct io fail",
+    ))
+}
+
 pub struct JoinHandle<T> {
     handle: Option<thread::JoinHandle<T>>,
 }
@@ -159,11 +204,50 @@ impl<T> Future for JoinHandle<T> {
     }
 }
 
+#[cfg(unix)]
 pub struct Directory(File);
 
+#[cfg(unix)]
 #[async_trait]
 impl super::Directory for Directory {
     async fn sync_all(&self) -> Result<()> {
         self.0.sync_all()
     }
 }
+
+#[cfg(windows)]
+pub struct Directory;
+
+#[cfg(windows)]
+#[async_trait]
+impl super::Directory for Directory {
+    async fn sync_all(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{io::Result, os::windows::ffi::OsStrExt, path::Path};
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        MoveFileExW, MOVEFILE_REPLACE_EXISTING, MOVEFILE_WRITE_THROUGH,
+    };
+
+    /// Renames a file, replacing the destination if it exists, and returns
+    /// after the rename is flushed to the disk.
+    pub(super) fn rename(from: &Path, to: &Path) -> Result<()> {
+        let from = to_wide(from);
+        let to = to_wide(to);
+        let flags = MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH;
+        // SAFETY: Both paths are null-terminated wide strings.
+        if unsafe { MoveFileExW(from.as_ptr(), to.as_ptr(), flags) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn to_wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    fs::File,
+    future::Future,
+    io::{Result, Write as _},
+    os::unix::fs::FileExt,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use tokio::task;
+
+use super::*;
+
+/// An implementation of [`Env`] based on [Tokio].
+///
+/// Files are accessed with blocking I/O on the blocking thread pool of the
+/// runtime, so the environment must be used within a Tokio runtime. Buffers
+/// are copied to and from the pool, so direct I/O is not supported.
+///
+/// This is available with the `tokio` feature.
+///
+/// [Tokio]: https://crates.io/crates/tokio
+#[derive(Clone, Debug)]
+pub struct Tokio;
+
+#[async_trait]
+impl Env for Tokio {
+    type PositionalReader = PositionalReader;
+    type SequentialWriter = SequentialWriter;
+    type JoinHandle<T: Send> = JoinHandle<T>;
+    type Directory = Directory;
+
+    async fn open_positional_reader<P>(&self, path: P) -> Result<Self::PositionalReader>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let path = path.as_ref().to_owned();
+        let file = unblock(move || File::open(path)).await?;
+        Ok(PositionalReader(Arc::new(file)))
+    }
+
+    async fn open_sequential_writer<P>(&self, path: P) -> Result<Self::SequentialWriter>
+    where
+        P: AsRef<Path> + Send,
+    {
+        let path = path.as_ref().to_owned();
+        let file = unblock(move || File::create(path)).await?;
+        Ok(SequentialWriter(Arc::new(file)))
+    }
+
+    fn spawn_background<F>(&self, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        JoinHandle {
+            handle: task::spawn(f),
+        }
+    }
+
+    /// An async version of [`std::fs::rename`].
+    async fn rename<P: AsRef<Path> + Send, Q: AsRef<Path> + Send>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        tokio::fs::rename(from, to).await
+    }
+
+    /// An async version of [`std::fs::remove_file`].
+    async fn remove_file<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    /// An async version of [`std::fs::create_dir_all`].
+    async fn create_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    /// An async version of [`std::fs::remove_dir_all`].
+    async fn remove_dir_all<P: AsRef<Path> + Send>(&self, path: P) -> Result<()> {
+        tokio::fs::remove_dir_all(path).await
+    }
+
+    /// Returns the paths of the entries within a directory.
+    /// See also [`std::fs::read_dir`].
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        read_dir_paths(path.as_ref())
+    }
+
+    fn lock_file<P: AsRef<Path>>(&self, path: P) -> Result<FileLock> {
+        os_lock_file(path.as_ref())
+    }
+
+    async fn metadata<P: AsRef<Path> + Send>(&self, path: P) -> Result<Metadata> {
+        let raw_metadata = tokio::fs::metadata(path).await?;
+        Ok(Metadata {
+            len: raw_metadata.len(),
+            is_dir: raw_metadata.is_dir(),
+        })
+    }
+
+    async fn open_dir<P: AsRef<Path> + Send>(&self, path: P) -> Result<Self::Directory> {
+        let path = path.as_ref().to_owned();
+        let file = unblock(move || File::open(path)).await?;
+        if !file.metadata()?.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotADirectory,
+                "not a dir",
+            ));
+        }
+        Ok(Directory(Arc::new(file)))
+    }
+}
+
+/// Runs a blocking function on the blocking thread pool.
+async fn unblock<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    task::spawn_blocking(f).await?
+}
+
+pub struct PositionalReader(Arc<File>);
+
+#[async_trait]
+impl super::PositionalReader for PositionalReader {
+    type ReadAt<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        let file = self.0.clone();
+        let len = buf.len();
+        async move {
+            let (n, tmp) = unblock(move || {
+                let mut tmp = vec![0; len];
+                let n = file.read_at(&mut tmp, pos)?;
+                Ok((n, tmp))
+            })
+            .await?;
+            buf[..n].copy_from_slice(&tmp[..n]);
+            Ok(n)
+        }
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "direct io is not supported",
+        ))
+    }
+}
+
+pub struct SequentialWriter(Arc<File>);
+
+#[async_trait]
+impl super::SequentialWriter for SequentialWriter {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a + Send;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        let file = self.0.clone();
+        let tmp = buf.to_vec();
+        unblock(move || (&*file).write(&tmp))
+    }
+
+    async fn sync_data(&mut self) -> Result<()> {
+        let file = self.0.clone();
+        unblock(move || file.sync_data()).await
+    }
+
+    async fn sync_all(&mut self) -> Result<()> {
+        let file = self.0.clone();
+        unblock(move || file.sync_all()).await
+    }
+
+    async fn truncate(&self, len: u64) -> Result<()> {
+        let file = self.0.clone();
+        unblock(move || file.set_len(len)).await
+    }
+
+    fn direct_io_ify(&self) -> Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "direct io is not supported",
+        ))
+    }
+}
+
+pub struct JoinHandle<T> {
+    handle: task::JoinHandle<T>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.handle.poll_unpin(cx) {
+            Poll::Ready(Ok(v)) => Poll::Ready(v),
+            Poll::Ready(Err(e)) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Poll::Ready(Err(e)) => panic!("JoinHandle error: {:?}", e),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub struct Directory(Arc<File>);
+
+#[async_trait]
+impl super::Directory for Directory {
+    async fn sync_all(&self) -> Result<()> {
+        let file = self.0.clone();
+        unblock(move || file.sync_all()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{raw::Table, TableOptions};
+
+    #[test]
+    fn table_operations() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let table = Table::open(Tokio, dir.path(), TableOptions::default())
+                .await
+                .unwrap();
+            table.put(b"k", 1, b"v").await.unwrap();
+            assert_eq!(table.get(b"k", 1).await.unwrap(), Some(b"v".to_vec()));
+            table.close().await.unwrap();
+
+            let table = Table::open(Tokio, dir.path(), TableOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(table.get(b"k", 1).await.unwrap(), Some(b"v".to_vec()));
+            table.close().await.unwrap();
+        });
+    }
+}
@@ -1,27 +1,82 @@
 use thiserror::Error;
 
-use crate::page_store::Error as PageError;
+use crate::page_store::{page_file_name, Error as PageError};
 
 /// A list of possible errors returned by PhotonDB.
 #[derive(Error, Debug)]
 pub enum Error {
     /// Some data is corrupted.
-    #[error("Corrupted")]
-    Corrupted,
+    ///
+    /// The file and the offset of the corrupted data are set if they are
+    /// known, such as for pages read from page files.
+    #[error("Corrupted{}", location(.file, .offset))]
+    Corrupted {
+        /// The name of the corrupted file in the table directory.
+        file: Option<String>,
+        /// The offset of the corrupted data in the file.
+        offset: Option<u64>,
+    },
     /// Over Memory Limit(cache).
     #[error("MemoryLimit")]
     MemoryLimit,
     /// Put data is too large.
     #[error("TooLargeSize")]
     TooLargeSize,
+    /// The table is opened by another process.
+    #[error("Busy")]
+    Busy,
+    /// The table runs out of page ids.
+    #[error("Capacity")]
+    Capacity,
+    /// An operation exceeds its time or retry limit.
+    #[error("Timeout")]
+    Timeout,
+    /// A write is made to a table opened read-only.
+    #[error("ReadOnly")]
+    ReadOnly,
+    /// An argument is invalid.
+    #[error("InvalidArgument {0}")]
+    InvalidArgument(String),
+    /// An I/O error.
+    #[error("IO {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// Returns an error of corrupted data at an unknown location.
+    pub(crate) fn corrupted() -> Self {
+        Self::Corrupted {
+            file: None,
+            offset: None,
+        }
+    }
+}
+
+/// Formats the location of corrupted data.
+fn location(file: &Option<String>, offset: &Option<u64>) -> String {
+    match (file, offset) {
+        (Some(file), Some(offset)) => format!(" in {file} at offset {offset}"),
+        (Some(file), None) => format!(" in {file}"),
+        (None, Some(offset)) => format!(" at offset {offset}"),
+        (None, None) => String::new(),
+    }
 }
 
 impl From<PageError> for Error {
     fn from(err: PageError) -> Self {
         match err {
-            PageError::Corrupted => Self::Corrupted,
+            PageError::Corrupted => Self::corrupted(),
+            PageError::CorruptedPage { file_id, offset } => Self::Corrupted {
+                file: Some(page_file_name(file_id)),
+                offset: Some(offset),
+            },
             PageError::MemoryLimit => Self::MemoryLimit,
             PageError::TooLargeSize => Self::TooLargeSize,
+            PageError::Busy => Self::Busy,
+            PageError::Capacity => Self::Capacity,
+            PageError::Timeout => Self::Timeout,
+            PageError::ReadOnly => Self::ReadOnly,
+            PageError::Io(e) => Self::Io(e),
             e => unreachable!("unexpected error: {:?}", e),
         }
     }
@@ -0,0 +1,212 @@
+use std::{io::Write, str::FromStr};
+
+use crate::{util::json::Json, Error, Result};
+
+/// The format of exported entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A JSON object of `{"key":...,"value":...}` per line.
+    Jsonl,
+    /// A header line of `key,value`, followed by a line per entry.
+    ///
+    /// Fields are quoted if they contain commas, quotes or line breaks.
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            _ => Err(Error::InvalidArgument(format!(
+                "invalid export format {s:?}"
+            ))),
+        }
+    }
+}
+
+/// The encoding of exported keys or values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteEncoding {
+    /// Lowercase hex digits.
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+    /// UTF-8, with invalid sequences replaced by `U+FFFD`.
+    ///
+    /// This is lossy, so the bytes can not be decoded from the output.
+    Utf8Lossy,
+}
+
+impl ByteEncoding {
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Hex => bytes.iter().map(|b| format!("{b:02x}")).collect(),
+            Self::Base64 => base64(bytes),
+            Self::Utf8Lossy => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+impl FromStr for ByteEncoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            "utf8-lossy" => Ok(Self::Utf8Lossy),
+            _ => Err(Error::InvalidArgument(format!("invalid encoding {s:?}"))),
+        }
+    }
+}
+
+/// Options to configure exports of entries.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// The format of exported entries.
+    ///
+    /// Default: [`ExportFormat::Jsonl`]
+    pub format: ExportFormat,
+
+    /// The encoding of exported keys.
+    ///
+    /// Default: [`ByteEncoding::Hex`]
+    pub key_encoding: ByteEncoding,
+
+    /// The encoding of exported values.
+    ///
+    /// Default: [`ByteEncoding::Hex`]
+    pub value_encoding: ByteEncoding,
+
+    /// The maximum visible LSN of exported entries.
+    ///
+    /// Default: `u64::MAX`
+    pub max_lsn: u64,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Jsonl,
+            key_encoding: ByteEncoding::Hex,
+            value_encoding: ByteEncoding::Hex,
+            max_lsn: u64::MAX,
+        }
+    }
+}
+
+/// Writes entries to a writer in the format of the options.
+pub(crate) struct Exporter<'a, W: Write> {
+    opts: &'a ExportOptions,
+    w: &'a mut W,
+    num_entries: u64,
+}
+
+impl<'a, W: Write> Exporter<'a, W> {
+    /// Creates an exporter, which writes the header of the format if any.
+    pub(crate) fn new(opts: &'a ExportOptions, w: &'a mut W) -> Result<Self> {
+        if opts.format == ExportFormat::Csv {
+            writeln!(w, "key,value")?;
+        }
+        Ok(Self {
+            opts,
+            w,
+            num_entries: 0,
+        })
+    }
+
+    pub(crate) fn write(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let key = self.opts.key_encoding.encode(key);
+        let value = self.opts.value_encoding.encode(value);
+        match self.opts.format {
+            ExportFormat::Jsonl => {
+                let json = Json::Object(vec![("key", key.into()), ("value", value.into())]);
+                writeln!(self.w, "{json}")?;
+            }
+            ExportFormat::Csv => writeln!(self.w, "{},{}", csv_field(&key), csv_field(&value))?,
+        }
+        self.num_entries += 1;
+        Ok(())
+    }
+
+    pub(crate) fn num_entries(&self) -> u64 {
+        self.num_entries
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let mut buf = [0u8; 3];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_bytes() {
+        assert_eq!(ByteEncoding::Hex.encode(b"\x00\xffa"), "00ff61");
+        for (input, output) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(ByteEncoding::Base64.encode(input.as_bytes()), output);
+        }
+        assert_eq!(ByteEncoding::Utf8Lossy.encode(b"a\xffb"), "a\u{fffd}b");
+    }
+
+    #[test]
+    fn export_entries() {
+        let mut opts = ExportOptions {
+            format: ExportFormat::Csv,
+            key_encoding: ByteEncoding::Utf8Lossy,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let mut exporter = Exporter::new(&opts, &mut buf).unwrap();
+        exporter.write(b"a,\"b\"", b"1").unwrap();
+        exporter.write(b"c", b"2").unwrap();
+        assert_eq!(exporter.num_entries(), 2);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "key,value\n\"a,\"\"b\"\"\",31\nc,32\n"
+        );
+
+        opts.format = ExportFormat::Jsonl;
+        let mut buf = Vec::new();
+        let mut exporter = Exporter::new(&opts, &mut buf).unwrap();
+        exporter.write(b"a\"", b"1").unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"key\":\"a\\\"\",\"value\":\"31\"}\n"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Entry points for the fuzz targets in `fuzz/`.
+//!
+//! These are not part of the public API.
+
+use std::{future::Future, io, slice};
+
+use futures::executor::block_on;
+
+use crate::{
+    env::{async_trait, PositionalReader},
+    page::*,
+    page_store::decode_manifest,
+};
+
+/// Decodes a page from arbitrary bytes.
+///
+/// If the page is accepted by [`check_page`], all items in the page are read
+/// with the decoders that do not check bounds.
+pub fn decode_page(data: &[u8]) {
+    // Pages are aligned to 8 bytes in memory.
+    let mut aligned = vec![0u64; (data.len() + 7) / 8];
+    let buf = unsafe { slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, data.len()) };
+    buf.copy_from_slice(data);
+    let Some(page) = check_page(buf) else {
+        return;
+    };
+    if page.tier().is_leaf() && !page.kind().is_split() {
+        read_items(ValuePageRef::from(page));
+    } else {
+        read_items(IndexPageRef::from(page));
+    }
+}
+
+fn read_items<K: SortedPageKey, V: SortedPageValue>(page: SortedPageRef<'_, K, V>) {
+    for i in 0..page.len() {
+        let (key, _) = page.get(i).unwrap();
+        let _ = page.rank(&key);
+    }
+    SortedPageIter::new(page.clone()).for_each(drop);
+    if let Some((_, left, right)) = page.into_split_iter(0.5) {
+        left.chain(right).for_each(drop);
+    }
+}
+
+/// Decodes the records of a manifest file from arbitrary bytes.
+pub fn decode_manifest_file(data: &[u8]) {
+    let reader = BytesReader(data.to_vec());
+    let _ = block_on(decode_manifest(reader, data.len() as u64));
+}
+
+struct BytesReader(Vec<u8>);
+
+#[async_trait]
+impl PositionalReader for BytesReader {
+    type ReadAt<'a> = impl Future<Output = io::Result<usize>> + 'a + Send;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        async move {
+            let data = self.0.get(pos as usize..).unwrap_or_default();
+            let len = buf.len().min(data.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            Ok(len)
+        }
+    }
+
+    fn direct_io_ify(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+//! Order-preserving encodings of keys.
+//!
+//! Tables order keys bytewise, so composite keys built by concatenating
+//! `to_be_bytes` and raw strings are easy to get wrong: signed integers and
+//! floats do not sort by their big-endian bytes, and a variable-length
+//! component followed by another one sorts by the bytes of both. The encodings
+//! in this module order encoded keys the same way as the values they encode:
+//!
+//! - Unsigned integers are encoded in big-endian.
+//! - Signed integers are encoded in big-endian with the sign bit flipped.
+//! - Floats are ordered like [`f64::total_cmp`], so `-0.0` sorts before `0.0`
+//!   and NaNs sort at the ends.
+//! - Byte strings and strings escape zero bytes as `[0, 0xFF]` and are
+//!   terminated by `[0, 0]`, so that they can be followed by other components.
+//! - Tuples concatenate their components, and are ordered by the components
+//!   from left to right.
+//! - [`Reverse`] inverts the bytes of its component, which reverses its order.
+//!
+//! ```
+//! use std::cmp::Reverse;
+//!
+//! use photondb::keys;
+//!
+//! // Orders events by the user ascendingly and then the time descendingly.
+//! let a = keys::encode(&("alice", Reverse(20i64)));
+//! let b = keys::encode(&("alice", Reverse(10i64)));
+//! let c = keys::encode(&("bob", Reverse(30i64)));
+//! assert!(a < b && b < c);
+//!
+//! // A tuple of the leading components is a prefix of the whole key.
+//! assert!(a.starts_with(&keys::encode(&("alice",))));
+//!
+//! let (user, Reverse(time)): (String, Reverse<i64>) = keys::decode(&a).unwrap();
+//! assert_eq!((user.as_str(), time), ("alice", 20));
+//! ```
+
+use std::cmp::Reverse;
+
+const ESCAPE: u8 = 0xFF;
+const TERMINATOR: u8 = 0;
+
+/// Encodes a value to a key.
+pub fn encode<T: EncodeKey + ?Sized>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode_key(&mut buf);
+    buf
+}
+
+/// Decodes a value from a key encoded by [`encode`].
+///
+/// Returns [`None`] if the key is not an encoding of the value, including when
+/// bytes are left after the value.
+pub fn decode<T: DecodeKey>(buf: &[u8]) -> Option<T> {
+    let mut reader = KeyReader::new(buf);
+    let value = T::decode_key(&mut reader)?;
+    reader.is_empty().then_some(value)
+}
+
+/// A value that can be encoded as an order-preserving key component.
+pub trait EncodeKey {
+    /// Appends the encoding of the value to the buffer.
+    fn encode_key(&self, buf: &mut Vec<u8>);
+}
+
+/// A value that can be decoded from a key component written by
+/// [`EncodeKey::encode_key`].
+pub trait DecodeKey: Sized {
+    /// Reads the value from the reader, or returns [`None`] if the bytes are
+    /// not an encoding of the value.
+    fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self>;
+}
+
+/// Reads the components of a key.
+pub struct KeyReader<'a> {
+    buf: &'a [u8],
+    inverted: bool,
+}
+
+impl<'a> KeyReader<'a> {
+    /// Creates a reader over the bytes of a key.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            inverted: false,
+        }
+    }
+
+    /// Returns true if all bytes are read.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Reads a byte, which is inverted back inside a [`Reverse`] component.
+    pub fn read_byte(&mut self) -> Option<u8> {
+        let (&b, rest) = self.buf.split_first()?;
+        self.buf = rest;
+        Some(if self.inverted { !b } else { b })
+    }
+
+    /// Reads `N` bytes, which are inverted back inside a [`Reverse`]
+    /// component.
+    pub fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.buf.len() < N {
+            return None;
+        }
+        let (head, rest) = self.buf.split_at(N);
+        self.buf = rest;
+        let mut array: [u8; N] = head.try_into().unwrap();
+        if self.inverted {
+            array.iter_mut().for_each(|b| *b = !*b);
+        }
+        Some(array)
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_be_bytes());
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+                reader.read_array().map(<$t>::from_be_bytes)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key(&self, buf: &mut Vec<u8>) {
+                // Flipping the sign bit orders negative values first.
+                let v = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+                let v = <$u>::from_be_bytes(reader.read_array()?);
+                Some((v ^ (1 << (<$u>::BITS - 1))) as $t)
+            }
+        }
+    )*};
+}
+
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+macro_rules! impl_float {
+    ($($t:ty => $u:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key(&self, buf: &mut Vec<u8>) {
+                // Negative values are inverted to order larger magnitudes
+                // first, and positive values have the sign bit flipped to
+                // order after negative ones.
+                let bits = self.to_bits();
+                let v = if bits >> (<$u>::BITS - 1) == 1 {
+                    !bits
+                } else {
+                    bits ^ (1 << (<$u>::BITS - 1))
+                };
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+                let v = <$u>::from_be_bytes(reader.read_array()?);
+                let bits = if v >> (<$u>::BITS - 1) == 1 {
+                    v ^ (1 << (<$u>::BITS - 1))
+                } else {
+                    !v
+                };
+                Some(<$t>::from_bits(bits))
+            }
+        }
+    )*};
+}
+
+impl_float!(f32 => u32, f64 => u64);
+
+impl EncodeKey for bool {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl DecodeKey for bool {
+    fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+        match reader.read_byte()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl EncodeKey for [u8] {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        for &b in self {
+            buf.push(b);
+            if b == 0 {
+                buf.push(ESCAPE);
+            }
+        }
+        buf.extend_from_slice(&[0, TERMINATOR]);
+    }
+}
+
+impl EncodeKey for Vec<u8> {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        self.as_slice().encode_key(buf);
+    }
+}
+
+impl DecodeKey for Vec<u8> {
+    fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+        let mut bytes = Vec::new();
+        loop {
+            match reader.read_byte()? {
+                0 => match reader.read_byte()? {
+                    ESCAPE => bytes.push(0),
+                    TERMINATOR => return Some(bytes),
+                    _ => return None,
+                },
+                b => bytes.push(b),
+            }
+        }
+    }
+}
+
+impl EncodeKey for str {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        self.as_bytes().encode_key(buf);
+    }
+}
+
+impl EncodeKey for String {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        self.as_bytes().encode_key(buf);
+    }
+}
+
+impl DecodeKey for String {
+    fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+        String::from_utf8(Vec::decode_key(reader)?).ok()
+    }
+}
+
+impl<T: EncodeKey + ?Sized> EncodeKey for &T {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        (**self).encode_key(buf);
+    }
+}
+
+impl<T: EncodeKey> EncodeKey for Reverse<T> {
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        // Encodings are prefix-free, so inverting the bytes of one reverses
+        // the order of the component.
+        let start = buf.len();
+        self.0.encode_key(buf);
+        buf[start..].iter_mut().for_each(|b| *b = !*b);
+    }
+}
+
+impl<T: DecodeKey> DecodeKey for Reverse<T> {
+    fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+        reader.inverted = !reader.inverted;
+        let value = T::decode_key(reader);
+        reader.inverted = !reader.inverted;
+        value.map(Reverse)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: EncodeKey),+> EncodeKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_key(&self, buf: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_key(buf);)+
+            }
+        }
+
+        impl<$($name: DecodeKey),+> DecodeKey for ($($name,)+) {
+            fn decode_key(reader: &mut KeyReader<'_>) -> Option<Self> {
+                Some(($($name::decode_key(reader)?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use super::*;
+
+    /// Checks that the values are encoded in order and decoded back.
+    fn check_order<T: EncodeKey + DecodeKey + Debug + PartialEq>(values: &[T]) {
+        for pair in values.windows(2) {
+            assert!(
+                encode(&pair[0]) < encode(&pair[1]),
+                "{:?} < {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+        for value in values {
+            assert_eq!(decode::<T>(&encode(value)).as_ref(), Some(value));
+        }
+    }
+
+    #[test]
+    fn integers() {
+        check_order(&[0u8, 1, 0x7f, 0x80, u8::MAX]);
+        check_order(&[0u64, 1, 255, 256, u64::MAX]);
+        check_order(&[i32::MIN, -256, -1, 0, 1, 256, i32::MAX]);
+        check_order(&[i64::MIN, -1, 0, 1, i64::MAX]);
+        check_order(&[false, true]);
+    }
+
+    #[test]
+    fn floats() {
+        let values = [
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.5,
+            f64::MAX,
+            f64::INFINITY,
+        ];
+        for pair in values.windows(2) {
+            assert!(encode(&pair[0]) < encode(&pair[1]));
+        }
+        for v in values {
+            assert_eq!(decode::<f64>(&encode(&v)).unwrap().to_bits(), v.to_bits());
+        }
+        assert!(encode(&f64::INFINITY) < encode(&f64::NAN));
+        assert!(encode(&-1.0f32) < encode(&1.0f32));
+        assert_eq!(decode::<f32>(&encode(&-2.5f32)), Some(-2.5));
+    }
+
+    #[test]
+    fn strings() {
+        check_order(&[
+            Vec::new(),
+            vec![0],
+            vec![0, 0],
+            vec![0, 1],
+            vec![1],
+            vec![1, 0],
+            vec![0xff],
+        ]);
+        check_order(&[
+            "".to_owned(),
+            "a".to_owned(),
+            "ab".to_owned(),
+            "b".to_owned(),
+        ]);
+        assert_eq!(encode("ab"), encode(&"ab".to_owned()));
+        assert_eq!(decode::<String>(&encode(&[0xffu8][..])), None);
+    }
+
+    #[test]
+    fn tuples() {
+        // A shorter first component sorts first regardless of the rest.
+        check_order(&[
+            ("a".to_owned(), u64::MAX),
+            ("a\0".to_owned(), 0),
+            ("ab".to_owned(), 0),
+            ("ab".to_owned(), 1),
+            ("b".to_owned(), 0),
+        ]);
+        check_order(&[(-1i32, false, 2u8), (-1, true, 1), (0, false, 0)]);
+        let key = encode(&("users", 7u64, -3i16));
+        assert!(key.starts_with(&encode(&("users", 7u64))));
+        assert_eq!(
+            decode::<(String, u64, i16)>(&key),
+            Some(("users".to_owned(), 7, -3))
+        );
+        // Trailing and missing bytes are rejected.
+        assert_eq!(decode::<(String, u64)>(&key), None);
+        assert_eq!(decode::<(String, u64, i16)>(&key[..key.len() - 1]), None);
+    }
+
+    #[test]
+    fn reverse() {
+        check_order(&[Reverse(u32::MAX), Reverse(1), Reverse(0)]);
+        check_order(&[Reverse(1.0f64), Reverse(-1.0)]);
+        check_order(&[
+            Reverse(b"b".to_vec()),
+            Reverse(b"ab".to_vec()),
+            Reverse(b"a\0".to_vec()),
+            Reverse(b"a".to_vec()),
+            Reverse(Vec::new()),
+        ]);
+        check_order(&[
+            ("a".to_owned(), Reverse(("x".to_owned(), 2i8)), 0u8),
+            ("a".to_owned(), Reverse(("x".to_owned(), 1)), 1),
+            ("a".to_owned(), Reverse(("w".to_owned(), 9)), 0),
+            ("b".to_owned(), Reverse(("z".to_owned(), 0)), 0),
+        ]);
+        check_order(&[Reverse(Reverse(1u16)), Reverse(Reverse(2))]);
+    }
+}
@@ -0,0 +1,329 @@
+//! A portable container of a keyspace, which is the entries under a key
+//! prefix, such as the data of a tenant.
+//!
+//! A keyspace is exported with [`raw::Table::export_keyspace`] and imported
+//! with [`raw::Table::import_keyspace`]. Unlike page files, the container does
+//! not depend on the page layout or the endianness of the host, so it can be
+//! moved between tables of different versions and machines.
+//!
+//! A container starts with `PHOTONKS` and a `u32` version, followed by blocks
+//! of:
+//!
+//! | Field   | Type      | Description                                |
+//! |---------|-----------|--------------------------------------------|
+//! | kind    | `u8`      | 1 for the header, 2 for data, 3 the footer |
+//! | len     | `u32`     | The length of the payload                  |
+//! | crc32   | `u32`     | The checksum of the payload                |
+//! | payload | `[u8]`    | The block                                  |
+//!
+//! The header holds the prefix and the schema metadata of the keyspace. Data
+//! blocks hold the entries sorted by keys, with the prefix stripped from the
+//! keys. The footer holds the number of entries, so that a truncated container
+//! is detected. Integers are little-endian, and byte strings are prefixed with
+//! their `u32` lengths.
+//!
+//! [`raw::Table::export_keyspace`]: crate::raw::Table::export_keyspace
+//! [`raw::Table::import_keyspace`]: crate::raw::Table::import_keyspace
+
+use std::io::{ErrorKind, Read, Write};
+
+use crate::{Error, Result};
+
+const MAGIC: &[u8; 8] = b"PHOTONKS";
+const VERSION: u32 = 1;
+
+const KIND_HEADER: u8 = 1;
+const KIND_DATA: u8 = 2;
+const KIND_FOOTER: u8 = 3;
+
+/// The size of the data blocks to write.
+const BLOCK_SIZE: usize = 64 << 10;
+
+/// The description of an exported keyspace.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyspaceInfo {
+    /// The prefix of the keys in the keyspace.
+    pub prefix: Vec<u8>,
+    /// The schema metadata of the keyspace, which is opaque to the table.
+    pub schema: Vec<u8>,
+    /// The number of entries in the keyspace.
+    pub num_entries: u64,
+}
+
+/// Writes the entries of a keyspace to a container.
+pub(crate) struct KeyspaceWriter<W: Write> {
+    w: W,
+    block: Vec<u8>,
+    block_entries: u32,
+    num_entries: u64,
+}
+
+impl<W: Write> KeyspaceWriter<W> {
+    /// Creates a writer, which writes the header of the container.
+    pub(crate) fn new(mut w: W, prefix: &[u8], schema: &[u8]) -> Result<Self> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        let mut header = Vec::new();
+        put_bytes(&mut header, prefix);
+        put_bytes(&mut header, schema);
+        write_block(&mut w, KIND_HEADER, &header)?;
+        Ok(Self {
+            w,
+            block: Vec::new(),
+            block_entries: 0,
+            num_entries: 0,
+        })
+    }
+
+    /// Adds an entry, whose key is stripped of the prefix of the keyspace.
+    ///
+    /// Entries must be added in the order of keys.
+    pub(crate) fn add(&mut self, suffix: &[u8], value: &[u8]) -> Result<()> {
+        put_bytes(&mut self.block, suffix);
+        put_bytes(&mut self.block, value);
+        self.block_entries += 1;
+        self.num_entries += 1;
+        if self.block.len() >= BLOCK_SIZE {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the rest of the entries and the footer of the container.
+    ///
+    /// Returns the number of entries written.
+    pub(crate) fn finish(mut self) -> Result<u64> {
+        self.flush_block()?;
+        write_block(&mut self.w, KIND_FOOTER, &self.num_entries.to_le_bytes())?;
+        self.w.flush()?;
+        Ok(self.num_entries)
+    }
+
+    fn flush_block(&mut self) -> Result<()> {
+        if self.block_entries == 0 {
+            return Ok(());
+        }
+        let mut payload = Vec::with_capacity(4 + self.block.len());
+        payload.extend_from_slice(&self.block_entries.to_le_bytes());
+        payload.append(&mut self.block);
+        write_block(&mut self.w, KIND_DATA, &payload)?;
+        self.block_entries = 0;
+        Ok(())
+    }
+}
+
+/// Reads the entries of a keyspace from a container.
+pub(crate) struct KeyspaceReader<R: Read> {
+    r: R,
+    info: KeyspaceInfo,
+    last_suffix: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl<R: Read> KeyspaceReader<R> {
+    /// Creates a reader, which reads and checks the header of the container.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the reader is not a container of
+    /// a keyspace or its version is not supported.
+    pub(crate) fn new(mut r: R) -> Result<Self> {
+        let mut magic = [0; 12];
+        r.read_exact(&mut magic)?;
+        if &magic[..8] != MAGIC {
+            return Err(Error::InvalidArgument(
+                "not a keyspace container".to_owned(),
+            ));
+        }
+        let version = u32::from_le_bytes(magic[8..].try_into().unwrap());
+        if version != VERSION {
+            return Err(Error::InvalidArgument(format!(
+                "unsupported keyspace container version {version}"
+            )));
+        }
+        let Some((KIND_HEADER, header)) = read_block(&mut r)? else {
+            return Err(Error::corrupted());
+        };
+        let mut buf = header.as_slice();
+        let info = KeyspaceInfo {
+            prefix: get_bytes(&mut buf)?.to_vec(),
+            schema: get_bytes(&mut buf)?.to_vec(),
+            num_entries: 0,
+        };
+        Ok(Self {
+            r,
+            info,
+            last_suffix: None,
+            done: false,
+        })
+    }
+
+    /// Returns the description of the keyspace, with the number of entries
+    /// read so far.
+    pub(crate) fn info(&self) -> &KeyspaceInfo {
+        &self.info
+    }
+
+    /// Reads the entries of the next data block, whose keys are stripped of
+    /// the prefix of the keyspace.
+    ///
+    /// Returns [`None`] after the footer, and [`Error::Corrupted`] if a block
+    /// fails the checksum, the entries are out of order, or the container is
+    /// truncated.
+    pub(crate) fn next_block(&mut self) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>> {
+        if self.done {
+            return Ok(None);
+        }
+        match read_block(&mut self.r)? {
+            Some((KIND_DATA, payload)) => {
+                let mut buf = payload.as_slice();
+                let count = get_u32(&mut buf)?;
+                let mut entries = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let suffix = get_bytes(&mut buf)?;
+                    let value = get_bytes(&mut buf)?;
+                    if self.last_suffix.as_deref() >= Some(suffix) {
+                        return Err(Error::corrupted());
+                    }
+                    self.last_suffix = Some(suffix.to_vec());
+                    entries.push((suffix.to_vec(), value.to_vec()));
+                }
+                if !buf.is_empty() {
+                    return Err(Error::corrupted());
+                }
+                self.info.num_entries += count as u64;
+                Ok(Some(entries))
+            }
+            Some((KIND_FOOTER, payload)) => {
+                let mut buf = payload.as_slice();
+                if get_u64(&mut buf)? != self.info.num_entries {
+                    return Err(Error::corrupted());
+                }
+                self.done = true;
+                Ok(None)
+            }
+            _ => Err(Error::corrupted()),
+        }
+    }
+}
+
+fn write_block<W: Write>(w: &mut W, kind: u8, payload: &[u8]) -> Result<()> {
+    w.write_all(&[kind])?;
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(&crc32fast::hash(payload).to_le_bytes())?;
+    w.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads a block, or returns [`None`] at the end of the reader.
+fn read_block<R: Read>(r: &mut R) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut header = [0; 9];
+    match r.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let kind = header[0];
+    let len = u32::from_le_bytes(header[1..5].try_into().unwrap());
+    let crc = u32::from_le_bytes(header[5..9].try_into().unwrap());
+    let mut payload = vec![0; len as usize];
+    match r.read_exact(&mut payload) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Err(Error::corrupted()),
+        Err(err) => return Err(err.into()),
+    }
+    if crc32fast::hash(&payload) != crc {
+        return Err(Error::corrupted());
+    }
+    Ok(Some((kind, payload)))
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn get_slice<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(Error::corrupted());
+    }
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+fn get_u32(buf: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(get_slice(buf, 4)?.try_into().unwrap()))
+}
+
+fn get_u64(buf: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(get_slice(buf, 8)?.try_into().unwrap()))
+}
+
+fn get_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = get_u32(buf)? as usize;
+    get_slice(buf, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(n: u64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut w = KeyspaceWriter::new(&mut buf, b"t/", b"schema").unwrap();
+        for i in 0..n {
+            w.add(&i.to_be_bytes(), &[i as u8; 100]).unwrap();
+        }
+        assert_eq!(w.finish().unwrap(), n);
+        buf
+    }
+
+    fn import(buf: &[u8]) -> Result<(KeyspaceInfo, Vec<(Vec<u8>, Vec<u8>)>)> {
+        let mut r = KeyspaceReader::new(buf)?;
+        let mut entries = Vec::new();
+        while let Some(block) = r.next_block()? {
+            entries.extend(block);
+        }
+        Ok((r.info().clone(), entries))
+    }
+
+    #[test]
+    fn export_and_import() {
+        // The entries span multiple data blocks.
+        let n = 2000;
+        let buf = export(n);
+        let (info, entries) = import(&buf).unwrap();
+        assert_eq!(
+            info,
+            KeyspaceInfo {
+                prefix: b"t/".to_vec(),
+                schema: b"schema".to_vec(),
+                num_entries: n,
+            }
+        );
+        for (i, (key, value)) in entries.into_iter().enumerate() {
+            assert_eq!(key, (i as u64).to_be_bytes());
+            assert_eq!(value, [i as u8; 100]);
+        }
+
+        let (info, entries) = import(&export(0)).unwrap();
+        assert_eq!(info.num_entries, 0);
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn corrupted() {
+        let buf = export(2000);
+        assert!(matches!(
+            import(b"PHOTONRS\x01\x00\x00\x00"),
+            Err(Error::InvalidArgument(_))
+        ));
+        // A flipped byte fails the checksum.
+        let mut flipped = buf.clone();
+        flipped[buf.len() / 2] ^= 1;
+        assert!(matches!(import(&flipped), Err(Error::Corrupted { .. })));
+        // A truncated container misses the footer or a torn block.
+        for len in [buf.len() - 1, buf.len() - 17] {
+            assert!(matches!(import(&buf[..len]), Err(Error::Corrupted { .. })));
+        }
+    }
+}
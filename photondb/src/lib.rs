@@ -18,7 +18,13 @@
 //!   with the [PhotonIO] runtime.
 //!
 //! The [`Photon`] APIs are the default and are re-exported to the top-level
-//! module for convenience.
+//! module for convenience. The raw APIs can also run on Tokio or async-std
+//! with the environments in [`env`], behind the `tokio` and `async-std`
+//! features.
+//!
+//! The [`Photon`] APIs are only available on Unix, and the [`Std`] APIs are
+//! available on Unix and Windows. On other targets like `wasm32-wasi`, the raw
+//! APIs run with the in-memory [`env::Mem`] environment.
 //!
 //! [`Raw`]: crate::raw
 //! [`Std`]: crate::std
@@ -34,27 +40,78 @@
 )]
 
 pub mod env;
+pub mod keys;
 pub mod raw;
+pub use raw::{BulkLoadOptions, LiveStats, MaintenanceCommand, MaintenanceDebt, TableStats};
+#[cfg(any(unix, windows))]
 pub mod std;
 
+#[cfg(unix)]
 pub mod photon;
-pub use photon::{Table, TableStats};
+#[cfg(unix)]
+pub use photon::Table;
+
+#[cfg(any(unix, windows))]
+pub mod sled_compat;
 
 mod error;
 pub use error::{Error, Result};
 
 mod tree;
-pub use tree::{Options as TableOptions, PageIter, ReadOptions, TreeStats, WriteOptions};
+pub use tree::{
+    decode_timestamped_key, decode_timestamped_value, encode_timestamped_key, Histogram,
+    LatencyStats, LevelShape, LiveSnapshots, LostRange, Options as TableOptions, PageIter,
+    PreparedToken, ReadOptions, RepairReport, TreeShape, TreeStats, VerifyReport, Violation,
+    ViolationKind, WriteBatch, WriteOptions, FILL_FACTOR_BUCKETS,
+};
+
+mod perf_context;
+pub use perf_context::PerfContext;
+
+mod audit;
+pub use audit::AuditContext;
+
+mod workload;
+pub use workload::{WorkloadOp, WorkloadOpKind};
+
+mod export;
+pub use export::{ByteEncoding, ExportFormat, ExportOptions};
+
+mod keyspace;
+pub use keyspace::KeyspaceInfo;
+
+mod replication;
+pub use replication::{
+    ReplicatedOp, ReplicationDecoder, ReplicationEncoder, ReplicationRecord, ReplicationStream,
+};
+
+mod listener;
+pub use listener::{
+    BackgroundErrorEvent, ConsolidationEvent, EventListener, FlushBeginEvent, FlushEvent,
+    ReclaimEvent, SplitEvent, StallEvent, StallReason,
+};
 
 mod page_store;
 pub use page_store::{
-    ChecksumType, Compression, FlushOptions, Options as PageStoreOptions, StoreStats,
+    ChecksumReport, ChecksumType, Compression, CorruptedPage, FileSpace, FlushOptions,
+    Options as PageStoreOptions, PageKindSpace, SizeDistribution, SpaceReport, StoreStats,
+    SIZE_BUCKETS,
 };
 
 mod page;
 mod util;
 
-#[cfg(test)]
+#[cfg(feature = "document")]
+pub mod document;
+
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzz;
+
+#[cfg(all(test, unix))]
+mod corruption;
+
+#[cfg(all(test, unix))]
 mod tests {
     use rand::random;
     use tempfile::tempdir;
@@ -64,12 +121,33 @@ mod tests {
 
     const OPTIONS: TableOptions = TableOptions {
         page_size: 128,
+        split_fill_factor: 0.5,
+        consolidation_fill_factor: 1.0,
         page_chain_length: 4,
+        max_consolidation_pages: 0,
+        auto_tune_consolidation: false,
+        min_page_chain_length: 2,
+        max_page_chain_length: 16,
+        min_consolidation_fill_factor: 0.5,
+        hot_page_sample_interval: 64,
+        hot_page_threshold: 8,
+        linear_search_threshold: 16,
+        event_listener: None,
+        slow_op_threshold: None,
+        stats_dump_period: None,
+        admin_poll_period: None,
+        audit_log: false,
+        buffered_write_delay: None,
+        buffered_write_size: 64 << 10,
         page_store: PageStoreOptions {
             write_buffer_capacity: 1 << 20,
             max_write_buffers: 8,
+            max_deferred_bytes: 4 << 20,
+            epoch_advance_attempts: 8,
             use_direct_io: false,
             disable_space_reclaiming: false,
+            max_background_flushes: 1,
+            reclaim_in_background: true,
             max_space_amplification_percent: 10,
             space_used_high: u64::MAX,
             file_base_size: 1 << 20,
@@ -82,6 +160,8 @@ mod tests {
             compression_on_cold_compact: Compression::ZSTD,
             page_checksum_type: ChecksumType::CRC32,
             avoid_flush_during_shutdown: false,
+            force_unlock: false,
+            read_only: false,
         },
     };
 
@@ -96,6 +176,16 @@ mod tests {
         assert_eq!(value, expect.map(|v| v.to_be_bytes().to_vec()));
     }
 
+    /// Waits for the duration without blocking the runtime.
+    async fn sleep(duration: std::time::Duration) {
+        let (tx, rx) = futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            let _ = tx.send(());
+        });
+        rx.await.unwrap();
+    }
+
     #[photonio::test]
     async fn crud() {
         let path = tempdir().unwrap();
@@ -121,42 +211,1983 @@ mod tests {
         }
         assert_eq!(i, N);
 
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        assert!(report.height > 1);
+
+        let shape = table.describe_tree().await.unwrap();
+        assert_eq!(shape.height, report.height);
+        assert_eq!(
+            shape.num_pages(),
+            report.num_leaf_pages + report.num_inner_pages
+        );
+        let leaves = shape.levels.last().unwrap();
+        assert_eq!(leaves.num_pages, report.num_leaf_pages);
+        assert_eq!(leaves.fill_factors.iter().sum::<usize>(), leaves.num_pages);
+        assert_eq!(leaves.chain_lengths.iter().sum::<usize>(), leaves.num_pages);
+
         table.close().await.unwrap();
     }
 
     #[photonio::test]
-    async fn random_crud() {
+    async fn cursor() {
         let path = tempdir().unwrap();
         let table = Table::open(&path, OPTIONS).await.unwrap();
-        const N: u64 = 1 << 12;
-        for _ in 0..N {
-            let i = random();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+
+        // Deletes even keys and overwrites odd keys through the cursor.
+        {
+            let guard = table.pin();
+            let mut cursor = guard.cursor(N);
+            cursor.seek(&[]).await.unwrap();
+            let mut i = 0u64;
+            while let Some(key) = cursor.key() {
+                assert_eq!(key, &i.to_be_bytes());
+                assert_eq!(cursor.value(), Some(i.to_be_bytes().as_slice()));
+                if i % 2 == 0 {
+                    cursor.delete(N).await.unwrap();
+                } else {
+                    cursor.put(N, &(i + N).to_be_bytes()).await.unwrap();
+                }
+                cursor.next().await.unwrap();
+                i += 1;
+            }
+            assert_eq!(i, N);
+        }
+        for i in 0..N {
+            let expect = if i % 2 == 0 { None } else { Some(i + N) };
+            must_get(&table, i, N, expect).await;
+        }
+
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn cursor_next_n() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
             must_put(&table, i, i).await;
+        }
+        // Overwrites some keys, so that pages have multiple versions of them.
+        for i in (0..N).step_by(3) {
+            let key = i.to_be_bytes();
+            table
+                .put(&key, N + i, &(N + i).to_be_bytes())
+                .await
+                .unwrap();
+        }
+
+        {
+            let guard = table.pin();
+            let mut cursor = guard.cursor(u64::MAX);
+            cursor.seek(&[]).await.unwrap();
+            let mut entries = Vec::new();
+            while cursor.next_n(100, &mut entries).await.unwrap() > 0 {}
+            assert!(cursor.key().is_none());
+            assert_eq!(entries.len(), N as usize);
+            for (i, (key, value)) in (0..N).zip(entries) {
+                let expect = if i % 3 == 0 { N + i } else { i };
+                assert_eq!(key, i.to_be_bytes());
+                assert_eq!(value, expect.to_be_bytes());
+            }
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn cursor_refresh() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..3 {
+            must_put(&table, i, 1).await;
+        }
+
+        let guard = table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&[]).await.unwrap();
+        assert_eq!(cursor.key(), Some(0u64.to_be_bytes().as_slice()));
+        table.put(&0u64.to_be_bytes(), 2, b"x").await.unwrap();
+        table.delete(&1u64.to_be_bytes(), 2).await.unwrap();
+        // The loaded page is not changed by the writes.
+        assert_eq!(cursor.value(), Some(0u64.to_be_bytes().as_slice()));
+
+        cursor.refresh().await.unwrap();
+        assert_eq!(cursor.key(), Some(0u64.to_be_bytes().as_slice()));
+        assert_eq!(cursor.value(), Some(b"x".as_slice()));
+        cursor.next().await.unwrap();
+        assert_eq!(cursor.key(), Some(2u64.to_be_bytes().as_slice()));
+
+        // The cursor stays at the end after it is refreshed.
+        cursor.next().await.unwrap();
+        cursor.refresh().await.unwrap();
+        assert_eq!(cursor.key(), None);
+        drop(guard);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn write_batch() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        let mut batch = WriteBatch::new();
+        for i in 0..N {
+            let buf = i.to_be_bytes();
+            batch.put(&buf, i, &buf);
+        }
+        table.write_batch(&batch).await.unwrap();
+        for i in 0..N {
             must_get(&table, i, i, Some(i)).await;
         }
+
+        batch.clear();
+        for i in (0..N).step_by(2) {
+            batch.delete(&i.to_be_bytes(), N);
+        }
+        let writes = table.stats().tree.success.write;
+        table.write_batch(&batch).await.unwrap();
+        assert_eq!(table.stats().tree.success.write - writes, N / 2);
+        for i in 0..N {
+            let expect = if i % 2 == 0 { None } else { Some(i) };
+            must_get(&table, i, N, expect).await;
+        }
+
+        // A batch larger than a write buffer is written in groups.
+        batch.clear();
+        let value = vec![1; 32 << 10];
+        let num_items = OPTIONS.page_store.write_buffer_capacity as usize / value.len() * 2;
+        for i in 0..num_items as u64 {
+            batch.put(&i.to_be_bytes(), N + 1, &value);
+        }
+        table.write_batch(&batch).await.unwrap();
+        for i in 0..num_items as u64 {
+            let got = table.get(&i.to_be_bytes(), N + 1).await.unwrap();
+            assert_eq!(got.as_deref(), Some(value.as_slice()));
+        }
+
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
         table.close().await.unwrap();
     }
 
     #[photonio::test]
-    async fn concurrent_crud() {
+    async fn snapshot() {
         let path = tempdir().unwrap();
         let table = Table::open(&path, OPTIONS).await.unwrap();
-        let mut tasks = Vec::new();
-        for _ in 0..4 {
-            let table = table.clone();
-            let handle = photonio::task::spawn(async move {
-                const N: u64 = 1 << 10;
-                for _ in 0..N {
-                    let i = random();
-                    must_put(&table, i, i).await;
-                    must_get(&table, i, i, Some(i)).await;
+        must_put(&table, 0, 1).await;
+        let snapshot = table.snapshot(1);
+        table.set_safe_lsn(u64::MAX);
+        // Overwrites the key to consolidate the page many times.
+        for lsn in 2..32 {
+            must_put(&table, 0, lsn).await;
+        }
+        assert!(table.stats().tree.success.consolidate_page > 0);
+        let value = snapshot.get(&0u64.to_be_bytes()).await.unwrap();
+        assert_eq!(value, Some(0u64.to_be_bytes().to_vec()));
+        must_get(&table, 0, 1, Some(0)).await;
+        drop(snapshot);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn live_snapshots() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert_eq!(table.live_snapshots(), LiveSnapshots::default());
+        let old = table.snapshot(1);
+        let new = table.snapshot(2);
+        let live = table.live_snapshots();
+        assert_eq!(live.count, 2);
+        assert_eq!(live.oldest_lsn, Some(1));
+        assert!(live.oldest_age.is_some());
+        drop(old);
+        let live = table.live_snapshots();
+        assert_eq!(live.count, 1);
+        assert_eq!(live.oldest_lsn, Some(2));
+        drop(new);
+        assert_eq!(table.live_snapshots().count, 0);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn sequenced_writes() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        must_put(&table, 0, 10).await;
+        assert_eq!(table.latest_lsn(), 10);
+        let lsn = table.put_sequenced(&[1], &[1]).await.unwrap();
+        assert_eq!(lsn, 11);
+        assert_eq!(table.get(&[1], lsn).await.unwrap(), Some(vec![1]));
+        let lsn = table.delete_sequenced(&[1]).await.unwrap();
+        assert_eq!(lsn, 12);
+        assert_eq!(table.get(&[1], lsn).await.unwrap(), None);
+        assert_eq!(table.latest_lsn(), 12);
+        table.close().await.unwrap();
+
+        // LSNs keep increasing after the table is reopened.
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert_eq!(table.latest_lsn(), 12);
+        assert_eq!(table.durable_lsn(), 12);
+        let lsn = table.delete_sequenced(&[0]).await.unwrap();
+        assert_eq!(lsn, 13);
+        assert_eq!(table.get(&[0], lsn).await.unwrap(), None);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn timestamped_writes() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        table.put_at(b"a", 1, 1, b"a1").await.unwrap();
+        table.put_at(b"a", 3, 2, b"a3").await.unwrap();
+        table.put_at(b"a\0", 2, 3, b"a\0").await.unwrap();
+        table.delete_at(b"a", 5, 4).await.unwrap();
+        table.put_at(b"ab", 1, 5, b"ab1").await.unwrap();
+
+        assert_eq!(table.get_at(b"a", 0, 5).await.unwrap(), None);
+        assert_eq!(
+            table.get_at(b"a", 2, 5).await.unwrap(),
+            Some(b"a1".to_vec())
+        );
+        assert_eq!(
+            table.get_at(b"a", 4, 5).await.unwrap(),
+            Some(b"a3".to_vec())
+        );
+        assert_eq!(table.get_at(b"a", 6, 5).await.unwrap(), None);
+        assert_eq!(
+            table.get_at(b"a", 6, 3).await.unwrap(),
+            Some(b"a3".to_vec())
+        );
+        assert_eq!(
+            table.get_at(b"a\0", 9, 5).await.unwrap(),
+            Some(b"a\0".to_vec())
+        );
+        assert_eq!(table.get_at(b"ab", 0, 5).await.unwrap(), None);
+
+        // Range scans go in the order of user keys.
+        let guard = table.pin();
+        let mut pages = guard.pages();
+        let mut keys = Vec::new();
+        while let Some(page) = pages.next().await.unwrap() {
+            for (k, _) in page {
+                keys.push(decode_timestamped_key(k).unwrap());
+            }
+        }
+        assert_eq!(
+            keys,
+            vec![
+                (b"a".to_vec(), 5),
+                (b"a".to_vec(), 3),
+                (b"a".to_vec(), 1),
+                (b"a\0".to_vec(), 2),
+                (b"ab".to_vec(), 1),
+            ]
+        );
+        drop(guard);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn multi_get() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in (0..N).step_by(2) {
+            must_put(&table, i, i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+
+        let keys: Vec<_> = (0..N).rev().map(|i| i.to_be_bytes()).collect();
+        let keys: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let values = table.multi_get(&keys, N).await.unwrap();
+        for (key, value) in keys.iter().zip(values) {
+            let i = u64::from_be_bytes((*key).try_into().unwrap());
+            let expect = (i % 2 == 0).then(|| i.to_be_bytes().to_vec());
+            assert_eq!(value, expect);
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn prefetch() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            page_store: PageStoreOptions {
+                cache_capacity: 1 << 20,
+                ..OPTIONS.page_store
+            },
+            ..OPTIONS
+        };
+        const N: u64 = 1 << 10;
+        let table = Table::open(&path, opts.clone()).await.unwrap();
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.close().await.unwrap();
+
+        // The cache is empty after the table is reopened.
+        let table = Table::open(&path, opts).await.unwrap();
+        let keys: Vec<_> = (0..N).step_by(97).map(|i| i.to_be_bytes()).collect();
+        let keys: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        table.prefetch(&keys).await.unwrap();
+        let start = 256u64.to_be_bytes();
+        let end = 512u64.to_be_bytes();
+        table.prefetch_range(&start, Some(&end)).await.unwrap();
+
+        let misses = table.stats().store.page_cache.lookup_miss;
+        for i in (0..N).step_by(97).chain(256..512) {
+            must_get(&table, i, u64::MAX, Some(i)).await;
+        }
+        assert_eq!(table.stats().store.page_cache.lookup_miss, misses);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn parallel_scan() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 12;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        // The tree has enough inner pages to split the keys into 4 ranges.
+        let ranges = table.scan_ranges(4).await.unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert!(ranges.windows(2).all(|w| w[0].1.as_ref() == Some(&w[1].0)));
+        // Ranges are taken from inner pages without visiting the leaf pages.
+        let guard = table.pin();
+        let mut pages = guard.pages();
+        let mut num_leaves = 0;
+        while pages.next().await.unwrap().is_some() {
+            num_leaves += 1;
+        }
+        drop(pages);
+        drop(guard);
+        PerfContext::enable();
+        let ranges = table.scan_ranges(usize::MAX).await.unwrap();
+        let ctx = PerfContext::get();
+        PerfContext::disable();
+        assert!(ranges.len() > 4 && ranges.len() <= num_leaves);
+        assert!(ctx.pages_visited < num_leaves as u64);
+        for shards in [1, 4, 1024] {
+            let keys = std::sync::Mutex::new(Vec::new());
+            table
+                .parallel_scan(shards, N, |k, v| {
+                    assert_eq!(k, v);
+                    keys.lock().unwrap().push(k.to_vec());
+                })
+                .await
+                .unwrap();
+            let mut keys = keys.into_inner().unwrap();
+            keys.sort();
+            let expect: Vec<_> = (0..N).map(|i| i.to_be_bytes().to_vec()).collect();
+            assert_eq!(keys, expect, "{shards}");
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn scan_without_filling_cache() {
+        let path = tempdir().unwrap();
+        const N: u64 = 1 << 10;
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.close().await.unwrap();
+
+        // The cache is empty after the table is reopened.
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let scan = |fill_cache: bool| {
+            let table = &table;
+            async move {
+                let opts = ReadOptions {
+                    fill_cache,
+                    ..Default::default()
+                };
+                let guard = table.pin();
+                let mut pages = guard.pages_with_options(opts);
+                let (mut num_pages, mut num_entries) = (0, 0);
+                while let Some(page) = pages.next().await.unwrap() {
+                    num_pages += 1;
+                    num_entries += page.count();
                 }
-            });
-            tasks.push(handle);
+                assert_eq!(num_entries as u64, N);
+                num_pages
+            }
+        };
+        scan(false).await;
+        // Leaf pages are not in the cache after the first scan, so they are
+        // all inserted by the second one.
+        let inserts = table.stats().store.page_cache.insert;
+        let num_pages = scan(true).await;
+        assert!(table.stats().store.page_cache.insert - inserts >= num_pages);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn guard_batch() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 8;
+        let guard = table.pin();
+        for i in 0..N {
+            let key = i.to_be_bytes();
+            guard.put(&key, i, &key).await.unwrap();
         }
-        for task in tasks {
-            task.await.unwrap();
+        for i in (0..N).step_by(2) {
+            guard.delete(&i.to_be_bytes(), N).await.unwrap();
+        }
+        let mut batch = WriteBatch::new();
+        batch.put(&N.to_be_bytes(), N, b"batch");
+        guard.write_batch(&batch).await.unwrap();
+
+        let keys: Vec<_> = (0..=N).map(|i| i.to_be_bytes()).collect();
+        let keys: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+        let values = guard.multi_get(&keys, N).await.unwrap();
+        for (i, value) in (0..N).zip(&values) {
+            let expect = (i % 2 == 1).then(|| i.to_be_bytes());
+            assert_eq!(*value, expect.as_ref().map(|v| v.as_slice()));
+        }
+        assert_eq!(values[N as usize], Some(b"batch".as_slice()));
+        assert_eq!(guard.get(&1u64.to_be_bytes(), 0).await.unwrap(), None);
+        drop(guard);
+
+        must_get(&table, 1, N, Some(1)).await;
+        must_get(&table, 2, N, None).await;
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn dump() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 8;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.delete(&0u64.to_be_bytes(), N).await.unwrap();
+
+        let mut buf = Vec::new();
+        table.dump(&mut buf).await.unwrap();
+        let dump = String::from_utf8(buf).unwrap();
+        let shape = table.describe_tree().await.unwrap();
+        let num_pages = dump.lines().filter(|l| l.starts_with("page ")).count();
+        assert_eq!(num_pages, shape.num_pages());
+        let entry = format!("entry key={} lsn={N} kind=delete", "00".repeat(8));
+        assert!(dump.lines().any(|l| l.trim() == entry), "{dump}");
+        for line in dump.lines() {
+            assert!(line.split_whitespace().skip(1).all(|f| f.contains('=')));
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn open_with_invalid_options() {
+        let path = tempdir().unwrap();
+        let options = TableOptions {
+            page_chain_length: 0,
+            ..OPTIONS
+        };
+        assert!(matches!(
+            Table::open(&path, options).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert!(table.set_option("page_chain_length", "0").is_err());
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn dump_page() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..4u64 {
+            must_put(&table, i, i).await;
+        }
+
+        let mut buf = Vec::new();
+        table.dump_page(1, &mut buf).await.unwrap();
+        let dump = String::from_utf8(buf).unwrap();
+        let head = dump.lines().next().unwrap();
+        assert!(head.starts_with("page id=1 "), "{dump}");
+        let chain_len: usize = head
+            .split_whitespace()
+            .find_map(|f| f.strip_prefix("chain_len="))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let count = |prefix: &str| dump.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!(count("  delta "), chain_len);
+        assert_eq!(count("    header "), chain_len);
+        assert_eq!(count("    raw offset=0 "), chain_len);
+        assert!(count("    entry ") + count("    index ") > 0, "{dump}");
+
+        assert!(matches!(
+            table.dump_page(u64::MAX, &mut Vec::new()).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn export() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..100u64 {
+            must_put(&table, i, i).await;
+        }
+        let mut opts = ExportOptions {
+            format: ExportFormat::Csv,
+            value_encoding: ByteEncoding::Base64,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let start = 10u64.to_be_bytes();
+        let end = 20u64.to_be_bytes();
+        let n = table
+            .export(&start, Some(&end), &opts, &mut buf)
+            .await
+            .unwrap();
+        assert_eq!(n, 10);
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "key,value");
+        assert_eq!(lines[1], "000000000000000a,AAAAAAAAAAo=");
+
+        // Entries written after the LSN are not exported.
+        opts.max_lsn = 49;
+        let n = table
+            .export(&[], None, &opts, &mut std::io::sink())
+            .await
+            .unwrap();
+        assert_eq!(n, 50);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn apply_with_index() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert_eq!(table.applied_index().await.unwrap(), 0);
+        for index in 1..=10u64 {
+            let mut batch = WriteBatch::new();
+            batch.put(&index.to_be_bytes(), index, &index.to_be_bytes());
+            batch.delete(&(index - 1).to_be_bytes(), index);
+            table.apply_with_index(&batch, index).await.unwrap();
+            assert_eq!(table.applied_index().await.unwrap(), index);
+        }
+        table.close().await.unwrap();
+
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert_eq!(table.applied_index().await.unwrap(), 10);
+        must_get(&table, 10, u64::MAX, Some(10)).await;
+        must_get(&table, 9, u64::MAX, None).await;
+        // Applying an entry again is idempotent.
+        let mut batch = WriteBatch::new();
+        batch.put(&10u64.to_be_bytes(), 10, &10u64.to_be_bytes());
+        table.apply_with_index(&batch, 10).await.unwrap();
+        assert_eq!(table.applied_index().await.unwrap(), 10);
+        // The index is not an entry of the table.
+        table.clear(11).await.unwrap();
+        assert_eq!(table.applied_index().await.unwrap(), 10);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn two_phase_commit() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let mut tokens = Vec::new();
+        for i in 0..3u64 {
+            let mut batch = WriteBatch::new();
+            batch.put(&i.to_be_bytes(), 1, &i.to_be_bytes());
+            tokens.push(table.prepare(&batch).await.unwrap());
+            must_get(&table, i, u64::MAX, None).await;
+        }
+        assert_eq!(table.prepared().await.unwrap(), tokens);
+        table.commit(tokens[0]).await.unwrap();
+        table.rollback(tokens[1]).await.unwrap();
+        assert!(matches!(
+            table.commit(tokens[1]).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        must_get(&table, 0, u64::MAX, Some(0)).await;
+        must_get(&table, 1, u64::MAX, None).await;
+        table.close().await.unwrap();
+
+        // Prepared batches survive restarts, and ids are not reused.
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert_eq!(table.prepared().await.unwrap(), vec![tokens[2]]);
+        let token = table.prepare(&WriteBatch::new()).await.unwrap();
+        assert!(token.id() > tokens[2].id());
+        table.commit(tokens[2]).await.unwrap();
+        table.rollback(token).await.unwrap();
+        must_get(&table, 2, u64::MAX, Some(2)).await;
+        assert!(table.prepared().await.unwrap().is_empty());
+        table.close().await.unwrap();
+
+        // Prepared batches are durable once prepared, and are not entries of
+        // the table.
+        let opts = TableOptions {
+            page_store: PageStoreOptions {
+                avoid_flush_during_shutdown: true,
+                ..OPTIONS.page_store
+            },
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts.clone()).await.unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(&3u64.to_be_bytes(), 1, &3u64.to_be_bytes());
+        let token = table.prepare(&batch).await.unwrap();
+        table.clear(u64::MAX).await.unwrap();
+        table.close().await.unwrap();
+        let table = Table::open(&path, opts).await.unwrap();
+        assert_eq!(table.prepared().await.unwrap(), vec![token]);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn keyspace() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..100u64 {
+            for prefix in [b"a/", b"b/"] {
+                let key = [prefix.as_slice(), &i.to_be_bytes()].concat();
+                table.put(&key, 1, &i.to_be_bytes()).await.unwrap();
+            }
+        }
+        let mut buf = Vec::new();
+        let num_entries = table
+            .export_keyspace(b"a/", b"v1", u64::MAX, &mut buf)
+            .await
+            .unwrap();
+        assert_eq!(num_entries, 100);
+
+        // Imports the keyspace under another prefix.
+        let info = table
+            .import_keyspace(buf.as_slice(), Some(b"c/"), 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            info,
+            KeyspaceInfo {
+                prefix: b"a/".to_vec(),
+                schema: b"v1".to_vec(),
+                num_entries: 100,
+            }
+        );
+        for i in 0..100u64 {
+            let key = [b"c/".as_slice(), &i.to_be_bytes()].concat();
+            let value = table.get(&key, u64::MAX).await.unwrap();
+            assert_eq!(value, Some(i.to_be_bytes().to_vec()));
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn bulk_load() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let n = 10000u64;
+        // Loads keys in a scrambled order, with half of them loaded twice.
+        let entries: Vec<_> = (0..n)
+            .map(|i| i * 7919 % n)
+            .chain(0..n / 2)
+            .enumerate()
+            .map(|(seq, i)| (i.to_be_bytes().to_vec(), seq.to_be_bytes().to_vec()))
+            .collect();
+        let expect: std::collections::BTreeMap<_, _> = entries.iter().cloned().collect();
+        let mut opts = BulkLoadOptions::default();
+        opts.memory_limit = 16 << 10;
+        opts.batch_size = 100;
+        let num_entries = table.bulk_load(entries, 1, &opts).await.unwrap();
+        assert_eq!(num_entries, n);
+        {
+            let guard = table.pin();
+            let mut cursor = guard.cursor(1);
+            cursor.seek(&[]).await.unwrap();
+            for (key, value) in &expect {
+                assert_eq!(cursor.key(), Some(key.as_slice()));
+                assert_eq!(cursor.value(), Some(value.as_slice()));
+                cursor.next().await.unwrap();
+            }
+            assert_eq!(cursor.key(), None);
+        }
+        // Runs are removed after the load.
+        let runs = std::fs::read_dir(&path)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".run")
+            })
+            .count();
+        assert_eq!(runs, 0);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn replication() {
+        let primary_path = tempdir().unwrap();
+        let replica_path = tempdir().unwrap();
+        let primary = Table::open(&primary_path, OPTIONS).await.unwrap();
+        let replica = Table::open(&replica_path, OPTIONS).await.unwrap();
+        let mut stream = primary.subscribe_replication(1024);
+        for i in 0..100u64 {
+            must_put(&primary, i, i).await;
+        }
+        primary.delete(&1u64.to_be_bytes(), 100).await.unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(&2u64.to_be_bytes(), 101, b"2");
+        batch.delete(&3u64.to_be_bytes(), 101);
+        primary.write_batch(&batch).await.unwrap();
+        primary
+            .delete_range(&10u64.to_be_bytes(), &20u64.to_be_bytes(), 102)
+            .await
+            .unwrap();
+        primary.mark_replication_watermark(102);
+
+        // Ships the records through an encoded stream.
+        let mut encoder = ReplicationEncoder::new(Vec::new()).unwrap();
+        while let Some(record) = stream.try_recv() {
+            encoder.write(&record).unwrap();
+        }
+        assert!(!stream.is_lagged());
+        let buf = encoder.into_inner();
+        let mut decoder = ReplicationDecoder::new(buf.as_slice()).unwrap();
+        while let Some(record) = decoder.next().unwrap() {
+            replica.apply_replicated(&record).await.unwrap();
+        }
+        assert_eq!(replica.replicated_watermark(), 102);
+        assert!(replica.next_lsn() > 102);
+        for i in 0..100u64 {
+            let key = i.to_be_bytes();
+            assert_eq!(
+                replica.get(&key, 102).await.unwrap(),
+                primary.get(&key, 102).await.unwrap(),
+            );
+        }
+        assert_eq!(
+            replica.get(&2u64.to_be_bytes(), 101).await.unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(replica.get(&15u64.to_be_bytes(), 102).await.unwrap(), None);
+
+        drop(stream);
+        primary.close().await.unwrap();
+        replica.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn replicate_cursor_writes() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..2 {
+            must_put(&table, i, 1).await;
+        }
+        let mut stream = table.subscribe_replication(16);
+
+        let guard = table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&[]).await.unwrap();
+        cursor.put(2, b"x").await.unwrap();
+        cursor.next().await.unwrap();
+        cursor.delete(3).await.unwrap();
+        drop(guard);
+
+        assert_eq!(
+            stream.try_recv(),
+            Some(ReplicationRecord::Batch(vec![ReplicatedOp::Put {
+                key: 0u64.to_be_bytes().to_vec(),
+                lsn: 2,
+                value: b"x".to_vec(),
+            }]))
+        );
+        assert_eq!(
+            stream.try_recv(),
+            Some(ReplicationRecord::Batch(vec![ReplicatedOp::Delete {
+                key: 1u64.to_be_bytes().to_vec(),
+                lsn: 3,
+            }]))
+        );
+        assert_eq!(stream.try_recv(), None);
+        drop(stream);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn delete_range() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        let (start, end) = (N / 4, N / 2);
+        table
+            .delete_range(&start.to_be_bytes(), &end.to_be_bytes(), N)
+            .await
+            .unwrap();
+        for i in 0..N {
+            must_get(&table, i, N - 1, Some(i)).await;
+            let expect = (i < start || i >= end).then_some(i);
+            must_get(&table, i, N, expect).await;
+        }
+        // Puts after the range delete are visible.
+        must_put(&table, start, N + 1).await;
+        must_get(&table, start, N + 1, Some(start)).await;
+
+        // Writes more deltas to consolidate pages in the range.
+        for _ in 0..4 {
+            for i in start + 1..end {
+                table.delete(&i.to_be_bytes(), N + 2).await.unwrap();
+            }
+        }
+        assert!(table.stats().tree.success.consolidate_page > 0);
+        for i in 0..N {
+            must_get(&table, i, N - 1, Some(i)).await;
+            let expect = (i <= start || i >= end).then_some(i);
+            must_get(&table, i, N + 2, expect).await;
+        }
+        let guard = table.pin();
+        let mut pages = guard.pages();
+        let mut keys = Vec::new();
+        while let Some(page) = pages.next().await.unwrap() {
+            for (k, _) in page {
+                keys.push(u64::from_be_bytes(k.try_into().unwrap()));
+            }
+        }
+        let expect: Vec<_> = (0..N).filter(|&i| i <= start || i >= end).collect();
+        assert_eq!(keys, expect);
+        drop(guard);
+
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn clear() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.clear(N).await.unwrap();
+        for i in 0..N {
+            must_get(&table, i, N - 1, Some(i)).await;
+            must_get(&table, i, N, None).await;
+        }
+        // Puts after the clear are visible.
+        must_put(&table, 1, N + 1).await;
+        {
+            let guard = table.pin();
+            let mut cursor = guard.cursor(N + 1);
+            cursor.seek(&[]).await.unwrap();
+            assert_eq!(cursor.key(), Some(1u64.to_be_bytes().as_slice()));
+            cursor.next().await.unwrap();
+            assert_eq!(cursor.key(), None);
+        }
+
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn durable_lsn() {
+        use std::sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        };
+
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 1..=10 {
+            must_put(&table, i, i).await;
+        }
+        assert_eq!(table.durable_lsn(), 0);
+        let durable = Arc::new(AtomicU64::new(0));
+        for lsn in [10, 11] {
+            let durable = durable.clone();
+            table.on_durable(lsn, move |lsn| durable.store(lsn, Ordering::Relaxed));
+        }
+        table.flush(&FlushOptions::default()).await;
+        assert_eq!(table.flushed_lsn(), 10);
+        assert_eq!(table.durable_lsn(), 10);
+        assert_eq!(durable.load(Ordering::Relaxed), 10);
+
+        // Writes at 11 are not flushed yet.
+        must_put(&table, 11, 11).await;
+        table.on_durable(5, |lsn| assert_eq!(lsn, 10));
+        assert_eq!(table.durable_lsn(), 10);
+        table.flush(&FlushOptions::default()).await;
+        assert_eq!(table.durable_lsn(), 11);
+        assert_eq!(durable.load(Ordering::Relaxed), 11);
+
+        // Writes with smaller LSNs are not durable until they are flushed.
+        must_put(&table, 12, 3).await;
+        assert_eq!(table.durable_lsn(), 2);
+        durable.store(0, Ordering::Relaxed);
+        {
+            let durable = durable.clone();
+            table.on_durable(3, move |lsn| durable.store(lsn, Ordering::Relaxed));
+        }
+        assert_eq!(durable.load(Ordering::Relaxed), 0);
+        table.flush(&FlushOptions::default()).await;
+        assert_eq!(table.durable_lsn(), 11);
+        assert_eq!(durable.load(Ordering::Relaxed), 11);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn random_crud() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 12;
+        for _ in 0..N {
+            let i = random();
+            must_put(&table, i, i).await;
+            must_get(&table, i, i, Some(i)).await;
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn concurrent_crud() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let mut tasks = Vec::new();
+        for _ in 0..4 {
+            let table = table.clone();
+            let handle = photonio::task::spawn(async move {
+                const N: u64 = 1 << 10;
+                for _ in 0..N {
+                    let i = random();
+                    must_put(&table, i, i).await;
+                    must_get(&table, i, i, Some(i)).await;
+                }
+            });
+            tasks.push(handle);
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        table.close().await.unwrap();
+    }
+
+    #[test]
+    fn simulate_concurrent_crud() {
+        for seed in 0..4 {
+            let path = tempdir().unwrap();
+            let env = env::Sim::new(env::SimOptions {
+                seed,
+                ..Default::default()
+            });
+            env.run(async {
+                let table = raw::Table::open(env.clone(), &path, OPTIONS).await.unwrap();
+                let mut tasks = Vec::new();
+                for t in 0..4u64 {
+                    let table = table.clone();
+                    let handle = env.spawn_background(async move {
+                        const N: u64 = 1 << 8;
+                        for i in 0..N {
+                            let buf = (i * 4 + t).to_be_bytes();
+                            table.put(&buf, i, &buf).await.unwrap();
+                            let value = table.get(&buf, i).await.unwrap();
+                            assert_eq!(value, Some(buf.to_vec()));
+                        }
+                    });
+                    tasks.push(handle);
+                }
+                for task in tasks {
+                    task.await;
+                }
+                let report = table.verify().await.unwrap();
+                assert!(report.is_ok(), "{report:?}");
+                table.close().await.unwrap();
+            });
+        }
+    }
+
+    #[photonio::test]
+    async fn tree_stats() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        let stats = table.stats().tree;
+        assert!(stats.height > 1);
+        assert_eq!(stats.write_retries.histogram.iter().sum::<u64>(), N);
+        assert!(stats.chain.count >= N);
+        assert!(stats.chain.max_len > 0);
+        assert!(stats.chain.avg_len() <= stats.chain.max_len as f64);
+        // All keys and values are 8 bytes, which fall into the [8, 16) bucket.
+        let leaf = &stats.leaf;
+        assert!(leaf.num_entries.count > 0);
+        assert_eq!(leaf.key_size.buckets[4], leaf.key_size.count);
+        assert_eq!(leaf.value_size.avg(), 8.0);
+        assert_eq!(leaf.key_size.sum, leaf.num_entries.sum * 8);
+        let page_table = table.stats().store.page_table;
+        assert!(page_table.allocated_ids > stats.success.split_page);
+        assert!(page_table.allocated_ids < page_table.capacity);
+        assert!(page_table.resident_bytes > 0);
+        let memory = table.stats().store.memory;
+        assert_eq!(memory.page_table, page_table.resident_bytes);
+        assert!(memory.write_buffers >= OPTIONS.page_store.write_buffer_capacity as u64);
+        assert!(memory.total() > memory.write_buffers);
+
+        table.flush(&FlushOptions::default()).await;
+        let io = table.stats().store.io;
+        assert!(io.page_file.write_bytes > 0);
+        assert!(io.page_file.syncs > 0);
+        assert!(io.manifest.writes > 0);
+        assert!(io.manifest.syncs > 0);
+
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        let latency = table.stats().tree.latency;
+        assert_eq!(latency.put.count, N);
+        assert_eq!(latency.get.count, N);
+        assert_eq!(latency.delete.count, 0);
+        assert!(latency.get.percentile(0.5) <= latency.get.percentile(0.999));
+        assert_eq!(latency.delete.percentile(0.99), 0);
+        let contention = table.stats().tree.contention;
+        assert_eq!(contention.put.count, N);
+        assert_eq!(contention.get.count, N);
+        assert_eq!(contention.get.failed_cas, 0);
+        // Operations through a guard are recorded one by one.
+        let guard = table.pin();
+        let buf = N.to_be_bytes();
+        guard.put(&buf, N, &buf).await.unwrap();
+        for i in 0..N {
+            guard.get(&i.to_be_bytes(), i).await.unwrap();
+        }
+        drop(guard);
+        let contention = table.stats().tree.contention;
+        assert_eq!(contention.put.count, N + 1);
+        assert_eq!(contention.get.count, N * 2);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn maintenance_debt() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..256 {
+            must_put(&table, i, i).await;
+        }
+        let debt = table.maintenance_debt().await.unwrap();
+        assert!(debt.dirty_bytes > 0);
+        assert_eq!(debt.garbage_bytes, 0);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn run_maintenance() {
+        let path = tempdir().unwrap();
+        let mut opts = OPTIONS;
+        // Reclaims manually only.
+        opts.page_store.disable_space_reclaiming = true;
+        let table = Table::open(&path, opts).await.unwrap();
+        let delta_pages = |shape: TreeShape| -> usize {
+            shape.levels.iter().map(|level| level.num_delta_pages).sum()
+        };
+        for i in 0..256 {
+            must_put(&table, i, i).await;
+        }
+        let before = delta_pages(table.describe_tree().await.unwrap());
+        table
+            .run_maintenance(MaintenanceCommand::Consolidate)
+            .await
+            .unwrap();
+        assert!(delta_pages(table.describe_tree().await.unwrap()) < before);
+
+        table
+            .run_maintenance(MaintenanceCommand::Flush)
+            .await
+            .unwrap();
+        assert_eq!(table.maintenance_debt().await.unwrap().dirty_bytes, 0);
+
+        // Overwrites the entries to make obsolete pages in the flushed file.
+        for i in 0..256 {
+            must_put(&table, i, 256 + i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+        let before = table.maintenance_debt().await.unwrap().garbage_bytes;
+        table
+            .run_maintenance(MaintenanceCommand::Reclaim)
+            .await
+            .unwrap();
+        assert!(table.maintenance_debt().await.unwrap().garbage_bytes < before);
+        for i in 0..256 {
+            must_get(&table, i, u64::MAX, Some(i)).await;
+        }
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn admin_poll_period() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            admin_poll_period: Some(std::time::Duration::from_millis(1)),
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        for i in 0..64 {
+            must_put(&table, i, i).await;
+        }
+        MaintenanceCommand::submit(&path, &[MaintenanceCommand::Flush]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        // The live stats are published in the same period.
+        let mut stats = None;
+        for _ in 0..100 {
+            stats = LiveStats::read(&path).unwrap();
+            if stats.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(stats.unwrap().time_ms > 0);
+        // Closing stops the poller, even if the command is not done.
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn buffered_writes() {
+        let path = tempdir().unwrap();
+        // Two writes of 8-byte keys and values fill up a buffer.
+        let opts = TableOptions {
+            buffered_write_delay: Some(std::time::Duration::from_secs(3600)),
+            buffered_write_size: 64,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        let put = |i: u64| {
+            let table = &table;
+            async move {
+                let buf = i.to_be_bytes();
+                table.put_buffered(&buf, i, &buf).await.unwrap();
+            }
+        };
+        put(1).await;
+        must_get(&table, 1, u64::MAX, None).await;
+        put(2).await;
+        must_get(&table, 1, u64::MAX, Some(1)).await;
+        must_get(&table, 2, u64::MAX, Some(2)).await;
+
+        put(3).await;
+        table.delete_buffered(&1u64.to_be_bytes(), 4).await.unwrap();
+        must_get(&table, 3, u64::MAX, None).await;
+        table.flush_buffered_writes().await.unwrap();
+        must_get(&table, 1, u64::MAX, None).await;
+        must_get(&table, 3, u64::MAX, Some(3)).await;
+
+        // Closing writes the rest of the buffered writes.
+        put(5).await;
+        table.close().await.unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        must_get(&table, 5, u64::MAX, Some(5)).await;
+        table.close().await.unwrap();
+
+        // Buffered writes are written in the background after the delay.
+        let opts = TableOptions {
+            buffered_write_delay: Some(std::time::Duration::from_millis(1)),
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        table
+            .put_buffered(&6u64.to_be_bytes(), 6, &6u64.to_be_bytes())
+            .await
+            .unwrap();
+        let mut value = None;
+        for _ in 0..100 {
+            value = table.get(&6u64.to_be_bytes(), u64::MAX).await.unwrap();
+            if value.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(value, Some(6u64.to_be_bytes().to_vec()));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn buffered_write_errors() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct Errors(Mutex<Vec<&'static str>>);
+
+        impl EventListener for Errors {
+            fn on_background_error(&self, event: &BackgroundErrorEvent) {
+                self.0.lock().unwrap().push(event.job);
+            }
+        }
+
+        let path = tempdir().unwrap();
+        let errors = Arc::new(Errors::default());
+        let opts = TableOptions {
+            event_listener: Some(errors.clone()),
+            buffered_write_delay: Some(std::time::Duration::from_millis(1)),
+            buffered_write_size: 4 << 20,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        // The value doesn't fit in a write buffer, so the write fails in the
+        // background.
+        let value = vec![0; OPTIONS.page_store.write_buffer_capacity as usize * 2];
+        table.put_buffered(b"k", 1, &value).await.unwrap();
+        for _ in 0..100 {
+            if !errors.0.lock().unwrap().is_empty() {
+                break;
+            }
+            sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(*errors.0.lock().unwrap(), ["buffered_write"]);
+        assert!(matches!(
+            table.put_buffered(b"k", 2, b"v").await,
+            Err(Error::TooLargeSize)
+        ));
+        // The error is returned once, and later writes go on.
+        table.put_buffered(b"k", 2, b"v").await.unwrap();
+        table.flush_buffered_writes().await.unwrap();
+        assert_eq!(
+            table.get(b"k", u64::MAX).await.unwrap(),
+            Some(b"v".to_vec())
+        );
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn background_parallelism() {
+        const N: u64 = 1 << 14;
+        for (flushes, reclaim_in_background) in [(0, false), (4, true)] {
+            let path = tempdir().unwrap();
+            let opts = TableOptions {
+                page_store: PageStoreOptions {
+                    max_background_flushes: flushes,
+                    reclaim_in_background,
+                    ..OPTIONS.page_store
+                },
+                ..OPTIONS
+            };
+            let table = Table::open(&path, opts).await.unwrap();
+            for i in 0..N {
+                must_put(&table, i, i).await;
+            }
+            if flushes == 0 {
+                // The filled write buffers are flushed by the writes.
+                assert!(table.stats().store.jobs.flush_write_bytes > 0);
+            }
+            table
+                .run_maintenance(MaintenanceCommand::Flush)
+                .await
+                .unwrap();
+            table
+                .run_maintenance(MaintenanceCommand::Reclaim)
+                .await
+                .unwrap();
+            for i in 0..N {
+                must_get(&table, i, u64::MAX, Some(i)).await;
+            }
+            table.close().await.unwrap();
+
+            let table = Table::open(&path, OPTIONS).await.unwrap();
+            for i in 0..N {
+                must_get(&table, i, u64::MAX, Some(i)).await;
+            }
+            table.close().await.unwrap();
+        }
+    }
+
+    #[photonio::test]
+    async fn debug_state_json() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..256 {
+            must_put(&table, i, i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+        let state = table.debug_state_json().await.unwrap();
+        assert!(state.starts_with(r#"{"options":{"page_size":128,"#));
+        assert!(state.contains(r#""files":[{"file_id":"#));
+        assert!(state.ends_with('}'));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn op_limits() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let key = 1u64.to_be_bytes();
+
+        // Operations without conflicts don't retry.
+        let wopts = WriteOptions {
+            max_retries: Some(0),
+            ..Default::default()
+        };
+        table.put_with_options(&key, 1, &key, &wopts).await.unwrap();
+        let ropts = ReadOptions {
+            max_retries: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            table.get_with_options(&key, &ropts).await.unwrap(),
+            Some(key.to_vec())
+        );
+
+        // Operations exceed a zero timeout before reading any page.
+        let wopts = WriteOptions {
+            timeout: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(matches!(
+            table.delete_with_options(&key, 2, &wopts).await,
+            Err(Error::Timeout)
+        ));
+        let ropts = ReadOptions {
+            timeout: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(matches!(
+            table.get_with_options(&key, &ropts).await,
+            Err(Error::Timeout)
+        ));
+        must_get(&table, 1, 2, Some(1)).await;
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn set_option() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        table.set_option("page_chain_length", "8").unwrap();
+        table.set_option("max_consolidation_pages", "2").unwrap();
+        table.set_option("slow_op_threshold_ms", "0").unwrap();
+        table.set_option("cache_capacity", "1024").unwrap();
+        table.set_option("max_deferred_bytes", "4096").unwrap();
+        assert!(matches!(
+            table.set_option("page_size", "256"),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            table.set_option("page_chain_length", "-1"),
+            Err(Error::InvalidArgument(_))
+        ));
+        // Invalid values are rejected by the validation of the options.
+        for (name, value) in [("page_chain_length", "0"), ("max_consolidation_pages", "1")] {
+            assert!(matches!(
+                table.set_option(name, value),
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+        for i in 0..256 {
+            must_put(&table, i, i).await;
+        }
+        for i in 0..256 {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        let state = table.debug_state_json().await.unwrap();
+        assert!(state.contains(r#""page_chain_length":8,"#));
+        assert!(state.contains(r#""cache_capacity":1024,"#));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn access_trace() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        must_put(&table, 0, 1).await;
+        let trace_path = path.path().join("access.trace");
+        table.start_access_trace(&trace_path).unwrap();
+        must_put(&table, 1, 2).await;
+        must_get(&table, 1, 2, Some(1)).await;
+        table.stop_access_trace().unwrap();
+        must_get(&table, 0, 2, Some(0)).await;
+
+        let trace = std::fs::read_to_string(&trace_path).unwrap();
+        let ops: Vec<_> = trace
+            .lines()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(ops, ["put", "get"]);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn workload_capture() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        must_put(&table, 0, 1).await;
+        let trace_path = path.path().join("workload.trace");
+        table.start_workload_capture(&trace_path).unwrap();
+        must_put(&table, 1, 2).await;
+        must_get(&table, 1, 2, Some(1)).await;
+        must_get(&table, 2, 2, None).await;
+        let mut batch = WriteBatch::new();
+        batch.delete(&0u64.to_be_bytes(), 3);
+        table.write_batch(&batch).await.unwrap();
+        table.stop_workload_capture().unwrap();
+        must_get(&table, 0, 3, None).await;
+
+        let trace = std::fs::read_to_string(&trace_path).unwrap();
+        let ops: Vec<_> = trace
+            .lines()
+            .map(|line| {
+                let op = WorkloadOp::parse(line).unwrap();
+                (op.kind, op.key, op.value_size)
+            })
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                (WorkloadOpKind::Put, 1u64.to_be_bytes().to_vec(), 8),
+                (WorkloadOpKind::Get, 1u64.to_be_bytes().to_vec(), 8),
+                (WorkloadOpKind::Get, 2u64.to_be_bytes().to_vec(), 0),
+                (WorkloadOpKind::Delete, 0u64.to_be_bytes().to_vec(), 0),
+            ]
+        );
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn audit_log() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            audit_log: true,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts.clone()).await.unwrap();
+        AuditContext::set("test");
+        table.delete_range(b"a", b"b", 1).await.unwrap();
+        table.set_option("page_chain_length", "8").unwrap();
+        AuditContext::clear();
+        table.close().await.unwrap();
+
+        let log = std::fs::read_to_string(path.path().join("AUDIT")).unwrap();
+        let ops: Vec<_> = log
+            .lines()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(ops, ["delete_range", "set_option"]);
+        assert!(log.lines().all(|line| line.ends_with(r#"context="test""#)));
+
+        // The store is closed if the audit log fails to open.
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        must_put(&table, 1, 2).await;
+        table.close().await.unwrap();
+        let log_path = path.path().join("AUDIT");
+        std::fs::remove_file(&log_path).unwrap();
+        std::fs::create_dir(&log_path).unwrap();
+        assert!(Table::open(&path, opts.clone()).await.is_err());
+        std::fs::remove_dir(&log_path).unwrap();
+        let table = Table::open(&path, opts).await.unwrap();
+        must_get(&table, 1, 2, Some(1)).await;
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn verify_checksums() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..100 {
+            must_put(&table, i, i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+        let report = table.verify_checksums().await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.num_files, 1);
+        assert!(report.num_pages > 0);
+
+        // Corrupts the pages at the beginning of the file.
+        let file = std::fs::read_dir(path.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| {
+                path.file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .starts_with("map_")
+            })
+            .unwrap();
+        let mut content = std::fs::read(&file).unwrap();
+        let len = content.len() / 4;
+        content[..len].fill(0xff);
+        std::fs::write(&file, content).unwrap();
+        let report = table.verify_checksums().await.unwrap();
+        assert!(!report.is_ok());
+        assert!(report
+            .corrupted_pages
+            .iter()
+            .all(|page| page.page_id.is_some()));
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn analyze_space() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 100;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+        let report = table.analyze_space().await.unwrap();
+        assert_eq!(report.num_unreadable_pages, 0);
+        assert_eq!(report.files.len(), 1);
+        assert!(report.live_bytes() > 0);
+        assert!(report.live_bytes() + report.dead_bytes() <= report.file_bytes());
+        let leaf_data = report
+            .page_kinds
+            .iter()
+            .find(|space| space.kind == "leaf_data")
+            .unwrap();
+        assert!(leaf_data.num_items >= N);
+        // Keys and values are 8 bytes, which fall in the 4-th bucket.
+        assert!(report.key_sizes.count >= N);
+        assert_eq!(report.key_sizes.buckets[4], report.key_sizes.count);
+        assert_eq!(report.value_sizes.max, 8);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn repair() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        // The root is a leaf page with a chain of three pages.
+        must_put(&table, 0, 1).await;
+        must_put(&table, 1, 2).await;
+        table.close().await.unwrap();
+
+        // Corrupts the pages at the beginning of the file and loses the manifest.
+        let file = std::fs::read_dir(path.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| {
+                path.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("map_")
+            })
+            .unwrap();
+        let mut content = std::fs::read(&file).unwrap();
+        let len = content.len() / 4;
+        content[..len].fill(0xff);
+        std::fs::write(&file, content).unwrap();
+        std::fs::remove_file(path.path().join("CURRENT")).unwrap();
+
+        let report = Table::repair(&path, OPTIONS).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.num_files, 1);
+        assert!(report.quarantined_files.is_empty());
+        assert_eq!(report.quarantined_pages.len(), 1);
+        assert_eq!(report.lost_ranges.len(), 1);
+        assert!(report.lost_ranges[0].start.is_empty());
+        assert_eq!(report.lost_ranges[0].end, None);
+        assert!(path.path().join("quarantine").is_dir());
+
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert!(table.verify().await.unwrap().is_ok());
+        assert!(table.verify_checksums().await.unwrap().is_ok());
+        must_get(&table, 0, 3, None).await;
+        must_put(&table, 0, 3).await;
+        must_get(&table, 0, 3, Some(0)).await;
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn event_listener() {
+        use std::sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        };
+
+        #[derive(Debug, Default)]
+        struct Counter {
+            splits: AtomicU64,
+            consolidations: AtomicU64,
+            flush_begins: AtomicU64,
+            flushes: AtomicU64,
+        }
+
+        impl EventListener for Counter {
+            fn on_page_split(&self, event: &SplitEvent) {
+                assert_ne!(event.page_id, event.right_page_id);
+                assert!(event.right_page_size < event.page_size);
+                self.splits.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_page_consolidated(&self, event: &ConsolidationEvent) {
+                assert!(event.num_pages > 1);
+                self.consolidations.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_flush_begin(&self, _: &FlushBeginEvent) {
+                self.flush_begins.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_flush_completed(&self, event: &FlushEvent) {
+                assert!(event.file_size > 0);
+                self.flushes.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let path = tempdir().unwrap();
+        let counter = Arc::new(Counter::default());
+        let opts = TableOptions {
+            event_listener: Some(counter.clone()),
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        let stats = table.stats().tree;
+        assert_eq!(
+            counter.splits.load(Ordering::Relaxed),
+            stats.success.split_page
+        );
+        assert_eq!(
+            counter.consolidations.load(Ordering::Relaxed),
+            stats.success.consolidate_page
+        );
+        // Write buffers are flushed on close.
+        table.close().await.unwrap();
+        let flushes = counter.flushes.load(Ordering::Relaxed);
+        assert!(flushes > 0);
+        assert_eq!(counter.flush_begins.load(Ordering::Relaxed), flushes);
+    }
+
+    #[photonio::test]
+    async fn slow_op_threshold() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            // Logs every operation, since the threshold is kept in microseconds.
+            slow_op_threshold: Some(std::time::Duration::from_nanos(1)),
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        const N: u64 = 1 << 8;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.flush(&FlushOptions::default()).await;
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        let guard = table.pin();
+        let mut pages = guard.pages();
+        let mut i = 0u64;
+        while let Some(page) = pages.next().await.unwrap() {
+            i += page.count() as u64;
+        }
+        assert_eq!(i, N);
+        drop(pages);
+        drop(guard);
+        // Puts, gets, and at least one scan of pages.
+        assert!(table.stats().tree.slow_ops > N * 2);
+
+        table.set_option("slow_op_threshold_ms", "60000").unwrap();
+        let slow_ops = table.stats().tree.slow_ops;
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        assert_eq!(table.stats().tree.slow_ops, slow_ops);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn stats_dump_period() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            stats_dump_period: Some(std::time::Duration::from_millis(1)),
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        for i in 0..64 {
+            must_put(&table, i, i).await;
+        }
+        while table.num_stats_dumps() < 2 {
+            sleep(std::time::Duration::from_millis(1)).await;
+        }
+        // Closing stops the dumper thread.
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn perf_context() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 8;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        PerfContext::enable();
+        must_get(&table, N / 2, N, Some(N / 2)).await;
+        let ctx = PerfContext::get();
+        assert!(ctx.pages_visited > 1);
+        assert!(ctx.deltas_read >= ctx.pages_visited);
+        assert!(ctx.key_comparisons > 0);
+        assert!(ctx.bytes_decoded > 0);
+        PerfContext::disable();
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn linear_search_threshold() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 8;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.close().await.unwrap();
+
+        // Binary searches all pages, and then searches all pages linearly.
+        let mut comparisons = Vec::new();
+        for threshold in [0, usize::MAX] {
+            let opts = TableOptions {
+                linear_search_threshold: threshold,
+                ..OPTIONS
+            };
+            let table = Table::open(&path, opts).await.unwrap();
+            PerfContext::enable();
+            for i in 0..N {
+                must_get(&table, i, N, Some(i)).await;
+            }
+            comparisons.push(PerfContext::get().key_comparisons);
+            PerfContext::disable();
+            table.close().await.unwrap();
+        }
+        assert!(comparisons[0] < comparisons[1], "{comparisons:?}");
+    }
+
+    #[photonio::test]
+    async fn hot_pages() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            hot_page_sample_interval: 1,
+            hot_page_threshold: 2,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        // Puts a few entries to build a delta chain without consolidation.
+        for i in 0..2 {
+            must_put(&table, i, i).await;
+        }
+        for _ in 0..4 {
+            must_get(&table, 0, 1, Some(0)).await;
+        }
+        let stats = table.stats().tree;
+        assert!(stats.hot_pages > 0);
+        assert!(stats.success.consolidate_page > 0);
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn partial_consolidation() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            page_chain_length: 8,
+            max_consolidation_pages: 2,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        // Deletes must shadow the puts in the pages that are not merged.
+        for i in 0..N {
+            table.delete(&i.to_be_bytes(), N + i).await.unwrap();
+        }
+        assert!(table.stats().tree.success.consolidate_page > 0);
+        for i in 0..N {
+            must_get(&table, i, N * 2, None).await;
+        }
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn auto_tune_consolidation() {
+        let path = tempdir().unwrap();
+        let opts = TableOptions {
+            auto_tune_consolidation: true,
+            min_page_chain_length: 2,
+            max_page_chain_length: 8,
+            ..OPTIONS
+        };
+        let table = Table::open(&path, opts).await.unwrap();
+        // Enough lookups for a few windows of the tuner.
+        const N: u64 = 1 << 10;
+        for round in 0..16 {
+            for i in 0..N {
+                must_put(&table, i, round * N + i).await;
+            }
+            for i in 0..N {
+                must_get(&table, i, round * N + i, Some(i)).await;
+            }
+        }
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn fill_factor() {
+        async fn count_splits(opts: TableOptions) -> u64 {
+            let path = tempdir().unwrap();
+            let table = Table::open(&path, opts).await.unwrap();
+            const N: u64 = 1 << 10;
+            for i in 0..N {
+                must_put(&table, i, i).await;
+            }
+            for i in 0..N {
+                must_get(&table, i, i, Some(i)).await;
+            }
+            let report = table.verify().await.unwrap();
+            assert!(report.is_ok(), "{report:?}");
+            let splits = table.stats().tree.success.split_page;
+            table.close().await.unwrap();
+            splits
+        }
+
+        let splits = count_splits(OPTIONS).await;
+        // Sequential writes leave the left pages full, so packing more items
+        // into them takes fewer splits.
+        let packed = count_splits(TableOptions {
+            split_fill_factor: 0.9,
+            ..OPTIONS
+        })
+        .await;
+        assert!(packed < splits, "{packed} >= {splits}");
+        // Leaving headroom in consolidated pages takes more splits.
+        let sparse = count_splits(TableOptions {
+            consolidation_fill_factor: 0.5,
+            ..OPTIONS
+        })
+        .await;
+        assert!(sparse > splits, "{sparse} <= {splits}");
+    }
+
+    #[photonio::test]
+    async fn lock_file() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        assert!(matches!(
+            Table::open(&path, OPTIONS).await,
+            Err(Error::Busy)
+        ));
+        table.close().await.unwrap();
+
+        // A lock file left by a closed or crashed table is not locked.
+        assert!(path.path().join("LOCK").exists());
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        table.close().await.unwrap();
+    }
+
+    #[photonio::test]
+    async fn read_only() {
+        let path = tempdir().unwrap();
+        let read_only = TableOptions {
+            page_store: PageStoreOptions {
+                read_only: true,
+                ..OPTIONS.page_store
+            },
+            ..OPTIONS
+        };
+        assert!(Table::open(&path, read_only.clone()).await.is_err());
+
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        table.close().await.unwrap();
+        let list_files = || {
+            let mut files: Vec<_> = std::fs::read_dir(&path)
+                .unwrap()
+                .map(|entry| {
+                    let entry = entry.unwrap();
+                    (entry.file_name(), entry.metadata().unwrap().len())
+                })
+                .collect();
+            files.sort();
+            files
+        };
+
+        // A read-only table doesn't lock the directory.
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        let reader = Table::open(&path, read_only.clone()).await.unwrap();
+        reader.close().await.unwrap();
+        table.close().await.unwrap();
+
+        let files = list_files();
+        let table = Table::open(&path, read_only).await.unwrap();
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
+        }
+        let guard = table.pin();
+        let mut cursor = guard.cursor(N);
+        cursor.seek(&[]).await.unwrap();
+        let mut count = 0;
+        while cursor.key().is_some() {
+            count += 1;
+            cursor.next().await.unwrap();
+        }
+        assert_eq!(count, N);
+        drop(cursor);
+        drop(guard);
+        assert!(matches!(
+            table.put(&N.to_be_bytes(), N, &[]).await,
+            Err(Error::ReadOnly)
+        ));
+        table.close().await.unwrap();
+        assert_eq!(list_files(), files);
+    }
+
+    #[photonio::test]
+    async fn recover_page_table() {
+        let path = tempdir().unwrap();
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        const N: u64 = 1 << 10;
+        for i in 0..N {
+            must_put(&table, i, i).await;
+        }
+        let splits = table.stats().tree.success.split_page;
+        assert!(splits > 0);
+        table.close().await.unwrap();
+
+        let table = Table::open(&path, OPTIONS).await.unwrap();
+        for i in 0..N {
+            must_get(&table, i, i, Some(i)).await;
         }
+        let report = table.verify().await.unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.num_unreachable_pages, 0);
         table.close().await.unwrap();
     }
 
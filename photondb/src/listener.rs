@@ -0,0 +1,146 @@
+use std::{fmt::Debug, time::Duration};
+
+/// A listener of lifecycle events on a table.
+///
+/// The callbacks are invoked synchronously on the thread that completes the
+/// event, which may be a user thread or a background job, so they should
+/// return quickly.
+pub trait EventListener: Debug + Send + Sync {
+    /// Called when a page is split.
+    fn on_page_split(&self, _event: &SplitEvent) {}
+
+    /// Called when a page is consolidated.
+    fn on_page_consolidated(&self, _event: &ConsolidationEvent) {}
+
+    /// Called before a write buffer is flushed.
+    fn on_flush_begin(&self, _event: &FlushBeginEvent) {}
+
+    /// Called after a write buffer is flushed and installed.
+    fn on_flush_completed(&self, _event: &FlushEvent) {}
+
+    /// Called after a set of files is compacted by space reclamation.
+    fn on_reclaim_completed(&self, _event: &ReclaimEvent) {}
+
+    /// Called after writes are stalled or slowed down and resumed.
+    fn on_write_stall(&self, _event: &StallEvent) {}
+
+    /// Called when a background job fails.
+    fn on_background_error(&self, _event: &BackgroundErrorEvent) {}
+}
+
+/// Information about a completed page split.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct SplitEvent {
+    /// The id of the split page.
+    pub page_id: u64,
+    /// The id of the new page that takes the right half.
+    pub right_page_id: u64,
+    /// The size of the split page.
+    pub page_size: usize,
+    /// The size of the new right page.
+    pub right_page_size: usize,
+    /// The time spent on the split.
+    pub duration: Duration,
+}
+
+/// Information about a completed page consolidation.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ConsolidationEvent {
+    /// The id of the consolidated page.
+    pub page_id: u64,
+    /// The number of pages in the chain that are merged.
+    pub num_pages: usize,
+    /// The total size of the merged pages.
+    pub input_size: usize,
+    /// The size of the new page.
+    pub output_size: usize,
+    /// The time spent on the consolidation.
+    pub duration: Duration,
+}
+
+/// Information about a flush to start.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct FlushBeginEvent {
+    /// The id of the write buffer to flush.
+    pub buffer_id: u32,
+}
+
+/// Information about a completed flush.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct FlushEvent {
+    /// The id of the flushed write buffer.
+    pub buffer_id: u32,
+    /// The id of the output file.
+    pub file_id: u32,
+    /// The size of the output file.
+    pub file_size: usize,
+    /// The number of active pages in the output file.
+    pub num_active_pages: usize,
+    /// The number of pages deallocated by the flush.
+    pub num_dealloc_pages: usize,
+    /// The time spent on the flush.
+    pub duration: Duration,
+}
+
+/// Information about a completed compaction of space reclamation.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ReclaimEvent {
+    /// The ids of the compacted files.
+    pub input_files: Vec<u32>,
+    /// The id of the output file.
+    pub file_id: u32,
+    /// The total size of the compacted files.
+    pub input_size: usize,
+    /// The size of the output file.
+    pub output_size: usize,
+    /// The time spent on the compaction.
+    pub duration: Duration,
+}
+
+/// Information about a write stall.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct StallEvent {
+    /// The reason of the stall.
+    pub reason: StallReason,
+    /// The time that writes are stalled.
+    pub duration: Duration,
+    /// The value of the resource that triggers the stall, e.g. the number of
+    /// write buffers.
+    pub current: u64,
+    /// The threshold of the resource that triggers the stall.
+    pub threshold: u64,
+}
+
+/// The reason of a write stall.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StallReason {
+    /// Writes are stopped until a write buffer is flushed, because the
+    /// number of write buffers reaches
+    /// [`PageStoreOptions::max_write_buffers`].
+    ///
+    /// [`PageStoreOptions::max_write_buffers`]: crate::PageStoreOptions::max_write_buffers
+    TooManyWriteBuffers,
+    /// A write is slowed down to advance the epoch, because the bytes of
+    /// released write buffers waiting for reclamation exceed
+    /// [`PageStoreOptions::max_deferred_bytes`].
+    ///
+    /// [`PageStoreOptions::max_deferred_bytes`]: crate::PageStoreOptions::max_deferred_bytes
+    TooManyDeferredBytes,
+}
+
+/// Information about a failed background job.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct BackgroundErrorEvent {
+    /// The name of the failed job, e.g. "flush" or "reclaim".
+    pub job: &'static str,
+    /// The description of the error.
+    pub error: String,
+}
@@ -246,6 +246,23 @@ impl<'a> PageRef<'a> {
             PagePtr::new(ptr, buf.len()).into()
         }
     }
+
+    /// Creates an immutable page reference from a byte slice that may be
+    /// corrupted.
+    ///
+    /// Returns [`None`] if the slice is not aligned to 8 bytes, the slice is
+    /// shorter than [`PAGE_HEADER_LEN`], or the page has invalid flags.
+    pub(crate) fn try_new(buf: &'a [u8]) -> Option<Self> {
+        if !buf.as_ptr().is_aligned_to(8) || buf.len() < PAGE_HEADER_LEN {
+            return None;
+        }
+        let page = Self::new(buf);
+        if page.flags().is_valid() {
+            Some(page)
+        } else {
+            None
+        }
+    }
 }
 
 impl<'a> Deref for PageRef<'a> {
@@ -321,11 +338,13 @@ impl From<u8> for PageTier {
 pub(crate) enum PageKind {
     Data = PAGE_KIND_DATA,
     Split = PAGE_KIND_SPLIT,
+    RangeDelete = PAGE_KIND_RANGE_DELETE,
 }
 
 const PAGE_KIND_MASK: u8 = 0b0000_1110;
 const PAGE_KIND_DATA: u8 = 0b0000_0000;
 const PAGE_KIND_SPLIT: u8 = 0b0000_0010;
+const PAGE_KIND_RANGE_DELETE: u8 = 0b0000_0100;
 
 impl PageKind {
     pub(crate) fn is_data(&self) -> bool {
@@ -335,6 +354,10 @@ impl PageKind {
     pub(crate) fn is_split(&self) -> bool {
         self == &Self::Split
     }
+
+    pub(crate) fn is_range_delete(&self) -> bool {
+        self == &Self::RangeDelete
+    }
 }
 
 impl From<u8> for PageKind {
@@ -342,6 +365,7 @@ impl From<u8> for PageKind {
         match value & PAGE_KIND_MASK {
             PAGE_KIND_DATA => Self::Data,
             PAGE_KIND_SPLIT => Self::Split,
+            PAGE_KIND_RANGE_DELETE => Self::RangeDelete,
             _ => unreachable!(),
         }
     }
@@ -361,6 +385,14 @@ impl PageFlags {
     fn kind(&self) -> PageKind {
         self.0.into()
     }
+
+    fn is_valid(&self) -> bool {
+        self.0 & !(PAGE_TIER_MASK | PAGE_KIND_MASK) == 0
+            && matches!(
+                self.0 & PAGE_KIND_MASK,
+                PAGE_KIND_DATA | PAGE_KIND_SPLIT | PAGE_KIND_RANGE_DELETE
+            )
+    }
 }
 
 /// Builds a page with basic information.
@@ -470,6 +502,12 @@ mod tests {
             assert!(page.tier().is_inner());
             assert!(page.kind().is_split());
         }
+        {
+            let builder = PageBuilder::new(PageTier::Leaf, PageKind::RangeDelete);
+            builder.build(&mut page);
+            assert!(page.tier().is_leaf());
+            assert!(page.kind().is_range_delete());
+        }
 
         assert_eq!(page.epoch(), 0);
         page.set_epoch(1);
@@ -18,6 +18,18 @@ pub(crate) trait Codec {
     ///
     /// The decoder must have enough data to decode the object.
     unsafe fn decode_from(decoder: &mut Decoder) -> Self;
+
+    /// Decodes an object from the decoder that may contain invalid data.
+    ///
+    /// Returns [`None`] if the decoder does not have enough data or the data
+    /// is invalid.
+    ///
+    /// # Safety
+    ///
+    /// The data of the decoder must outlive the returned object.
+    unsafe fn try_decode_from(decoder: &mut Decoder) -> Option<Self>
+    where
+        Self: Sized;
 }
 
 // An unsafe, little-endian encoder.
@@ -95,6 +107,20 @@ macro_rules! get_int {
     };
 }
 
+macro_rules! try_get_int {
+    ($name:ident, $get:ident, $t:ty) => {
+        pub(super) fn $name(&mut self) -> Option<$t> {
+            unsafe {
+                if mem::size_of::<$t>() <= self.remaining() {
+                    Some(self.$get())
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
 impl Decoder {
     pub(super) fn new(buf: &[u8]) -> Self {
         Self {
@@ -131,4 +157,16 @@ impl Decoder {
         let cursor = self.take(len);
         slice::from_raw_parts(cursor, len)
     }
+
+    try_get_int!(try_get_u8, get_u8, u8);
+    try_get_int!(try_get_u32, get_u32, u32);
+    try_get_int!(try_get_u64, get_u64, u64);
+
+    pub(super) unsafe fn try_get_slice<'a>(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len <= self.remaining() {
+            Some(self.get_slice(len))
+        } else {
+            None
+        }
+    }
 }
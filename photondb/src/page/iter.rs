@@ -263,6 +263,28 @@ where
     }
 }
 
+impl<I> MergingIter<I>
+where
+    I: Iterator,
+    OrderedIter<I>: Iterator<Item = I::Item> + Ord,
+{
+    /// Appends up to `n` next items to `items`, and returns the number of
+    /// items appended.
+    ///
+    /// If there is only one iterator, which is the case of a consolidated
+    /// page, the items are taken from it without adjusting the heap per item.
+    pub(crate) fn next_n(&mut self, n: usize, items: &mut Vec<I::Item>) -> usize {
+        let len = items.len();
+        if self.heap.len() == 1 {
+            let mut iter = self.heap.peek_mut().unwrap();
+            items.extend(iter.0.by_ref().take(n));
+        } else {
+            items.extend(self.by_ref().take(n));
+        }
+        items.len() - len
+    }
+}
+
 impl<I> RewindableIterator for MergingIter<I>
 where
     I: Iterator,
@@ -416,4 +438,24 @@ mod tests {
         assert_eq!(iter.next(), Some((7, "d")));
         assert_eq!(iter.next(), Some((8, "c")));
     }
+
+    #[test]
+    fn merging_iter_next_n() {
+        let input = [vec![(1, "a"), (3, "a")], vec![(2, "b")]];
+        for num_iters in 1..=input.len() {
+            let mut builder = MergingIterBuilder::new();
+            for slice in &input[..num_iters] {
+                builder.add(SliceIter::new(slice));
+            }
+            let mut iter = builder.build();
+            let mut expect: Vec<_> = input[..num_iters].concat();
+            expect.sort();
+
+            let mut items = Vec::new();
+            assert_eq!(iter.next_n(2, &mut items), 2);
+            assert_eq!(iter.next_n(2, &mut items), expect.len() - 2);
+            assert_eq!(iter.next_n(2, &mut items), 0);
+            assert_eq!(items, expect);
+        }
+    }
 }
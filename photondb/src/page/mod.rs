@@ -15,11 +15,35 @@ pub(crate) use base_page::{PageBuf, PageInfo, PageKind, PageRef, PageTier};
 mod sorted_page;
 pub(crate) use sorted_page::{
     SortedPageBuilder, SortedPageIter, SortedPageKey, SortedPageRef, SortedPageValue,
+    LINEAR_SEARCH_THRESHOLD,
 };
 
 pub(crate) type ValuePageRef<'a> = SortedPageRef<'a, Key<'a>, Value<'a>>;
 pub(crate) type IndexPageRef<'a> = SortedPageRef<'a, &'a [u8], Index>;
 
+/// Checks that a page that may be corrupted can be decoded.
+///
+/// Returns [`None`] if the header or any item of the page is invalid, so that
+/// pages read from disk can be rejected before they are decoded without
+/// bounds checks.
+pub(crate) fn check_page(buf: &[u8]) -> Option<PageRef<'_>> {
+    let page = PageRef::try_new(buf)?;
+    let len = match (page.tier(), page.kind()) {
+        (PageTier::Leaf, PageKind::Data | PageKind::RangeDelete) => {
+            ValuePageRef::try_new(page)?.len()
+        }
+        (PageTier::Inner, PageKind::Data) | (_, PageKind::Split) => {
+            IndexPageRef::try_new(page)?.len()
+        }
+        (PageTier::Inner, PageKind::RangeDelete) => return None,
+    };
+    // Delta pages other than data pages have exactly one item.
+    if !page.kind().is_data() && len != 1 {
+        return None;
+    }
+    Some(page)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use std::{
@@ -11,6 +11,25 @@ use super::{
     codec::*, data::*, ItemIter, PageBuf, PageBuilder, PageKind, PageRef, PageTier,
     RewindableIterator, SeekableIterator, SliceIter,
 };
+use crate::{perf_context, util::prefetch::prefetch_read};
+
+/// The default number of items below which pages are searched linearly
+/// instead of binary searched.
+///
+/// The comparisons of a linear scan are predictable, so it avoids the branch
+/// mispredictions of a binary search, which dominate the search in pages with
+/// a few items, such as pages of 64 bytes. Trees search pages with
+/// [`Options::linear_search_threshold`] instead.
+///
+/// [`Options::linear_search_threshold`]: crate::TableOptions::linear_search_threshold
+pub(crate) const LINEAR_SEARCH_THRESHOLD: usize = 16;
+
+/// Pages with at least this many items prefetch the probes of a binary search.
+///
+/// The offsets and items of such pages span many cache lines, so each probe
+/// of a search stalls on a cache miss without prefetching. Smaller pages are
+/// mostly in the cache after the first probes.
+pub(crate) const PREFETCH_SEARCH_THRESHOLD: usize = 128;
 
 /// Builds a sorted page from an iterator.
 pub(crate) struct SortedPageBuilder<I> {
@@ -136,6 +155,7 @@ pub(crate) struct SortedPageRef<'a, K, V> {
     page: PageRef<'a>,
     content: &'a [u8],
     offsets: &'a [u32],
+    linear_search_threshold: usize,
     _marker: PhantomData<(K, V)>,
 }
 
@@ -160,10 +180,53 @@ where
             page,
             content,
             offsets,
+            linear_search_threshold: LINEAR_SEARCH_THRESHOLD,
             _marker: PhantomData,
         }
     }
 
+    /// Searches the page linearly in [`Self::rank`] if it has fewer items than
+    /// the threshold, instead of [`LINEAR_SEARCH_THRESHOLD`].
+    pub(crate) fn with_linear_search_threshold(mut self, threshold: usize) -> Self {
+        self.linear_search_threshold = threshold;
+        self
+    }
+
+    /// Creates a [`SortedPageRef`] from a page that may be corrupted.
+    ///
+    /// Returns [`None`] if the offsets are out of order or out of the page, or
+    /// any item can not be decoded exactly.
+    pub(crate) fn try_new(page: PageRef<'a>) -> Option<Self> {
+        let content = page.content();
+        if !content.is_empty() {
+            // The first offset is also the size of offsets.
+            let size = u32::from_le_bytes(content.get(..4)?.try_into().unwrap()) as usize;
+            if size % mem::size_of::<u32>() != 0 || size > content.len() {
+                return None;
+            }
+        }
+        let page = Self::new(page);
+        let mut last_offset = 0;
+        for i in 0..page.len() {
+            let offset = page.item_offset(i).unwrap();
+            if offset < last_offset || offset > content.len() {
+                return None;
+            }
+            last_offset = offset;
+        }
+        for i in 0..page.len() {
+            let mut dec = Decoder::new(page.item(i).unwrap());
+            unsafe {
+                K::try_decode_from(&mut dec)?;
+                V::try_decode_from(&mut dec)?;
+                if dec.remaining() != 0 {
+                    return None;
+                }
+            }
+        }
+        Some(page)
+    }
+
     /// Returns the number of items in the page.
     pub(crate) fn len(&self) -> usize {
         self.offsets.len()
@@ -172,6 +235,7 @@ where
     /// Returns the item at the given index.
     pub(crate) fn get(&self, index: usize) -> Option<(K, V)> {
         if let Some(item) = self.item(index) {
+            perf_context::record(|ctx| ctx.bytes_decoded += item.len() as u64);
             let mut dec = Decoder::new(item);
             unsafe {
                 let k = K::decode_from(&mut dec);
@@ -190,30 +254,113 @@ where
     /// one of the matches could be returned. If the value is not found then
     /// [`Result::Err`] is returned, containing the index where a matching item
     /// could be inserted while maintaining sorted order.
+    ///
+    /// Pages with fewer items than the linear search threshold are searched
+    /// linearly, and others are binary searched. Binary searches in pages with
+    /// at least [`PREFETCH_SEARCH_THRESHOLD`] items prefetch the next probes.
     pub(crate) fn rank<Q: ?Sized>(&self, target: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        if self.len() < self.linear_search_threshold {
+            self.linear_rank(target)
+        } else {
+            self.binary_rank(target)
+        }
+    }
+
+    fn linear_rank<Q: ?Sized>(&self, target: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let mut comparisons = 0;
+        let mut rank = Err(self.len());
+        for i in 0..self.len() {
+            let key = self.key(i);
+            comparisons += 1;
+            match key.borrow().cmp(target) {
+                Ordering::Less => continue,
+                Ordering::Greater => rank = Err(i),
+                Ordering::Equal => rank = Ok(i),
+            }
+            break;
+        }
+        perf_context::record(|ctx| ctx.key_comparisons += comparisons);
+        rank
+    }
+
+    fn binary_rank<Q: ?Sized>(&self, target: &Q) -> Result<usize, usize>
     where
         K: Borrow<Q>,
         Q: Ord,
     {
         let mut left = 0;
         let mut right = self.len();
-        while left < right {
+        let mut comparisons = 0;
+        let prefetch = self.len() >= PREFETCH_SEARCH_THRESHOLD;
+        let rank = loop {
+            if left >= right {
+                break Err(left);
+            }
             let mid = (left + right) / 2;
-            let key = unsafe {
-                let item = self.item(mid).unwrap();
-                let mut dec = Decoder::new(item);
-                K::decode_from(&mut dec)
-            };
+            if prefetch {
+                self.prefetch_probes(left, mid, right);
+            }
+            let key = self.key(mid);
+            comparisons += 1;
             match key.borrow().cmp(target) {
                 Ordering::Less => left = mid + 1,
                 Ordering::Greater => right = mid,
-                Ordering::Equal => return Ok(mid),
+                Ordering::Equal => break Ok(mid),
+            }
+        };
+        perf_context::record(|ctx| ctx.key_comparisons += comparisons);
+        rank
+    }
+
+    /// Prefetches the next probes of a binary search in `[left, right)` that
+    /// probes `mid` now.
+    ///
+    /// Whichever way the comparison at `mid` goes, the next probe is the
+    /// middle of one of the halves. The items of both candidates are
+    /// prefetched, along with the offsets of the probes one step further.
+    /// The offsets of the candidates were prefetched by the previous step, so
+    /// locating their items rarely stalls.
+    #[inline(always)]
+    fn prefetch_probes(&self, left: usize, mid: usize, right: usize) {
+        let offsets = self.offsets.as_ptr();
+        for (l, r) in [(left, mid), (mid + 1, right)] {
+            if l >= r {
+                continue;
+            }
+            let next = (l + r) / 2;
+            if let Some(offset) = self.item_offset(next) {
+                prefetch_read(self.content.as_ptr().wrapping_add(offset));
             }
+            prefetch_read(offsets.wrapping_add((l + next) / 2));
+            prefetch_read(offsets.wrapping_add((next + 1 + r) / 2));
         }
-        Err(left)
     }
 
-    /// Finds a separator to split the page into two halves.
+    /// Decodes the key of the item at the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    fn key(&self, index: usize) -> K {
+        unsafe {
+            let item = self.item(index).unwrap();
+            let mut dec = Decoder::new(item);
+            K::decode_from(&mut dec)
+        }
+    }
+
+    /// Finds a separator to split the page into two parts.
+    ///
+    /// The left part takes about `fill_factor` of the items, which is clamped
+    /// to [0, 1].
     ///
     /// If a split separator is found, returns [`Option::Some`] with the split
     /// separator, an iterator over items before the separator, and another
@@ -221,13 +368,15 @@ where
     #[allow(clippy::type_complexity)]
     pub(crate) fn into_split_iter(
         self,
+        fill_factor: f64,
     ) -> Option<(
         K,
         SortedPageRangeIter<'a, K, V>,
         SortedPageRangeIter<'a, K, V>,
     )> {
         let len = self.len();
-        if let Some((mid, _)) = self.get(len / 2) {
+        let at = (len as f64 * fill_factor.clamp(0.0, 1.0)) as usize;
+        if let Some((mid, _)) = self.get(at.min(len.saturating_sub(1))) {
             let sep = mid.as_split_separator();
             let index = match self.rank(&sep) {
                 Ok(i) => i,
@@ -436,6 +585,11 @@ impl Codec for &[u8] {
         let len = dec.get_u32() as usize;
         dec.get_slice(len)
     }
+
+    unsafe fn try_decode_from(dec: &mut Decoder) -> Option<Self> {
+        let len = dec.try_get_u32()? as usize;
+        dec.try_get_slice(len)
+    }
 }
 
 impl SortedPageKey for &[u8] {
@@ -463,6 +617,12 @@ impl Codec for Key<'_> {
         let lsn = dec.get_u64();
         Self::new(raw, lsn)
     }
+
+    unsafe fn try_decode_from(dec: &mut Decoder) -> Option<Self> {
+        let raw = Codec::try_decode_from(dec)?;
+        let lsn = dec.try_get_u64()?;
+        Some(Self::new(raw, lsn))
+    }
 }
 
 impl SortedPageKey for Key<'_> {
@@ -506,6 +666,14 @@ impl Codec for Value<'_> {
             _ => unreachable!(),
         }
     }
+
+    unsafe fn try_decode_from(dec: &mut Decoder) -> Option<Self> {
+        match dec.try_get_u8()? {
+            VALUE_KIND_PUT => Some(Self::Put(dec.get_slice(dec.remaining()))),
+            VALUE_KIND_DELETE => Some(Self::Delete),
+            _ => None,
+        }
+    }
 }
 
 impl Codec for Index {
@@ -523,12 +691,21 @@ impl Codec for Index {
         let epoch = dec.get_u64();
         Self::new(id, epoch)
     }
+
+    unsafe fn try_decode_from(dec: &mut Decoder) -> Option<Self> {
+        let id = dec.try_get_u64()?;
+        let epoch = dec.try_get_u64()?;
+        Some(Self::new(id, epoch))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::page::tests::*;
+    use crate::{
+        page::{check_page, tests::*},
+        PerfContext,
+    };
 
     #[test]
     fn sorted_page() {
@@ -561,6 +738,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sorted_page_rank() {
+        // Covers pages searched linearly and binary searched.
+        let keys: Vec<[u8; 1]> = (0..LINEAR_SEARCH_THRESHOLD as u8 * 2)
+            .map(|i| [i * 2 + 1])
+            .collect();
+        for n in 0..=keys.len() {
+            let data = raw_slice(&keys[..n]);
+            let owned_page = OwnedSortedPage::from_slice(&data);
+            let page = owned_page.as_ref();
+            for target in 0..=(n as u8 * 2 + 1) {
+                let expect = data.binary_search_by(|(k, _)| k.cmp(&[target].as_slice()));
+                assert_eq!(page.rank([target].as_slice()), expect, "{n} {target}");
+                assert_eq!(page.linear_rank([target].as_slice()), expect);
+                assert_eq!(page.binary_rank([target].as_slice()), expect);
+            }
+        }
+    }
+
+    #[test]
+    fn sorted_page_rank_with_threshold() {
+        let keys: Vec<[u8; 1]> = (0..8).map(|i| [i * 2 + 1]).collect();
+        let data = raw_slice(&keys);
+        let owned_page = OwnedSortedPage::from_slice(&data);
+        // The last key takes one comparison per item with a linear search.
+        let comparisons = |threshold| {
+            let page = owned_page.as_ref().with_linear_search_threshold(threshold);
+            PerfContext::enable();
+            PerfContext::reset();
+            assert_eq!(page.rank([15].as_slice()), Ok(7));
+            let comparisons = PerfContext::get().key_comparisons;
+            PerfContext::disable();
+            comparisons
+        };
+        assert_eq!(comparisons(9), 8);
+        assert!(comparisons(8) < 8);
+        assert!(comparisons(0) < 8);
+    }
+
+    #[test]
+    fn sorted_page_rank_with_prefetch() {
+        let keys: Vec<[u8; 2]> = (0..PREFETCH_SEARCH_THRESHOLD as u16 * 2)
+            .map(|i| (i * 2 + 1).to_be_bytes())
+            .collect();
+        for n in [
+            PREFETCH_SEARCH_THRESHOLD - 1,
+            PREFETCH_SEARCH_THRESHOLD,
+            keys.len(),
+        ] {
+            let data: Vec<(&[u8], &[u8])> = keys[..n]
+                .iter()
+                .map(|k| (k.as_slice(), k.as_slice()))
+                .collect();
+            let owned_page = OwnedSortedPage::from_slice(&data);
+            let page = owned_page.as_ref();
+            for target in 0..=(n as u16 * 2 + 1) {
+                let target = target.to_be_bytes();
+                let expect = data.binary_search_by(|(k, _)| k.cmp(&target.as_slice()));
+                assert_eq!(page.rank(target.as_slice()), expect, "{n} {target:?}");
+            }
+        }
+    }
+
     #[test]
     fn sorted_page_split() {
         // The middle key is ([3], 2), but it should split at ([3], 3).
@@ -570,7 +810,7 @@ mod tests {
         let owned_page = OwnedSortedPage::from_slice(&data);
 
         let page = owned_page.as_ref();
-        let (split_key, mut left_iter, mut right_iter) = page.into_split_iter().unwrap();
+        let (split_key, mut left_iter, mut right_iter) = page.into_split_iter(0.5).unwrap();
         assert_eq!(split_key, Key::new(&[3], u64::MAX));
         for _ in 0..2 {
             for (a, b) in (&mut left_iter).zip(left_data.clone()) {
@@ -584,20 +824,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sorted_page_split_fill_factor() {
+        let data = raw_slice(&[[1], [2], [3], [4], [5]]);
+        let owned_page = OwnedSortedPage::from_slice(&data);
+        let page = owned_page.as_ref();
+        let (split_key, _, _) = page.clone().into_split_iter(0.2).unwrap();
+        assert_eq!(split_key, [2].as_slice());
+        let (split_key, _, _) = page.clone().into_split_iter(0.8).unwrap();
+        assert_eq!(split_key, [5].as_slice());
+        let (split_key, _, _) = page.clone().into_split_iter(1.0).unwrap();
+        assert_eq!(split_key, [5].as_slice());
+        assert!(page.into_split_iter(0.0).is_none());
+    }
+
     #[test]
     fn sorted_page_split_none() {
         {
             let data = raw_slice(&[[1]]);
             let owned_page = OwnedSortedPage::from_slice(&data);
-            assert!(owned_page.as_ref().into_split_iter().is_none());
+            assert!(owned_page.as_ref().into_split_iter(0.5).is_none());
         }
         {
             let data = key_slice(&[([1], 2), ([1], 1), ([3], 3)]);
             let owned_page = OwnedSortedPage::from_slice(&data);
-            assert!(owned_page.as_ref().into_split_iter().is_none());
+            assert!(owned_page.as_ref().into_split_iter(0.5).is_none());
         }
     }
 
+    #[test]
+    fn sorted_page_check() {
+        let data = [
+            (Key::new(b"a", 1), Value::Put(b"1")),
+            (Key::new(b"b", 1), Value::Delete),
+        ];
+        let builder =
+            SortedPageBuilder::new(PageTier::Leaf, PageKind::Data).with_iter(SliceIter::new(&data));
+        let mut buf = alloc_page(builder.size());
+        builder.build(&mut PageBuf::new(buf.as_mut()));
+        assert!(check_page(&buf).is_some());
+        // Truncated header and items.
+        assert!(check_page(&buf[..8]).is_none());
+        assert!(check_page(&buf[..buf.len() - 1]).is_none());
+        // Invalid value kind.
+        let last = buf.len() - 1;
+        buf[last] = 0xff;
+        assert!(check_page(&buf).is_none());
+        buf[last] = VALUE_KIND_DELETE;
+        assert!(check_page(&buf).is_some());
+        // Invalid flags.
+        let flags = buf[6];
+        buf[6] = 0xff;
+        assert!(check_page(&buf).is_none());
+        buf[6] = flags;
+        // The first offset is out of the page.
+        buf[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(check_page(&buf).is_none());
+    }
+
     #[test]
     fn sorted_page_iter() {
         let data = raw_slice(&[[1], [3], [5]]);
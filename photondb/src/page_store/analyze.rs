@@ -0,0 +1,197 @@
+use std::{collections::BTreeMap, io::ErrorKind};
+
+use super::*;
+use crate::page::{
+    check_page, IndexPageRef, PageKind, PageTier, SortedPageIter, Value, ValuePageRef,
+};
+
+/// The number of buckets in a [`SizeDistribution`].
+pub const SIZE_BUCKETS: usize = 33;
+
+/// A report of the space used by page files.
+#[derive(Clone, Debug, Default)]
+pub struct SpaceReport {
+    /// The space of each page file, ordered by file ids.
+    pub files: Vec<FileSpace>,
+    /// The space of active pages of each kind, ordered by kind names.
+    pub page_kinds: Vec<PageKindSpace>,
+    /// The sizes of keys in leaf data pages.
+    pub key_sizes: SizeDistribution,
+    /// The sizes of values in leaf data pages, excluding deletes.
+    pub value_sizes: SizeDistribution,
+    /// The number of active pages that fail to read or decode.
+    pub num_unreadable_pages: usize,
+}
+
+impl SpaceReport {
+    /// Returns the total size of page files.
+    pub fn file_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.file_size).sum()
+    }
+
+    /// Returns the total size of active pages in page files.
+    pub fn live_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.live_bytes).sum()
+    }
+
+    /// Returns the total size of obsolete pages in page files.
+    pub fn dead_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.dead_bytes).sum()
+    }
+}
+
+/// The space used by a page file.
+#[derive(Clone, Debug)]
+pub struct FileSpace {
+    /// The id of the file.
+    pub file_id: u32,
+    /// The size of the file.
+    pub file_size: u64,
+    /// The size of active pages in the file.
+    pub live_bytes: u64,
+    /// The size of obsolete pages in the file, which can be reclaimed.
+    pub dead_bytes: u64,
+    /// The size of active pages after decompression.
+    pub uncompressed_live_bytes: u64,
+    /// The compression of pages in the file.
+    pub compression: Compression,
+}
+
+/// The space used by active pages of a kind.
+#[derive(Clone, Debug, Default)]
+pub struct PageKindSpace {
+    /// The name of the kind, e.g. `leaf_data` or `inner_split`.
+    pub kind: &'static str,
+    /// The number of pages.
+    pub num_pages: u64,
+    /// The number of items in the pages.
+    pub num_items: u64,
+    /// The size of the pages in page files.
+    pub disk_bytes: u64,
+    /// The size of the pages after decompression.
+    pub uncompressed_bytes: u64,
+}
+
+/// A distribution of sizes.
+#[derive(Clone, Debug, Default)]
+pub struct SizeDistribution {
+    /// The number of sizes.
+    pub count: u64,
+    /// The sum of sizes.
+    pub total: u64,
+    /// The maximum size.
+    pub max: u64,
+    /// A histogram of sizes.
+    ///
+    /// The 0-th bucket counts sizes of 0, and the i-th bucket counts sizes in
+    /// [2^(i-1), 2^i). Sizes of 2^32 or larger are counted in the last bucket.
+    pub buckets: [u64; SIZE_BUCKETS],
+}
+
+impl SizeDistribution {
+    fn add(&mut self, size: usize) {
+        let size = size as u64;
+        self.count += 1;
+        self.total += size;
+        self.max = self.max.max(size);
+        let bucket = (u64::BITS - size.leading_zeros()) as usize;
+        self.buckets[bucket.min(SIZE_BUCKETS - 1)] += 1;
+    }
+}
+
+impl<E: Env> PageStore<E> {
+    /// Reads all active pages in page files to analyze the space they use.
+    ///
+    /// Pages in write buffers are not analyzed. The table should be quiescent,
+    /// since files reclaimed during analysis can not be read.
+    pub(crate) async fn analyze_space(&self) -> Result<SpaceReport> {
+        let version = self.version();
+        let mut report = SpaceReport::default();
+        let mut page_kinds = BTreeMap::new();
+        let mut files: Vec<_> = version.file_infos().values().collect();
+        files.sort_unstable_by_key(|info| info.meta().file_id);
+        for info in files {
+            let meta = info.meta();
+            let mut file = FileSpace {
+                file_id: meta.file_id,
+                file_size: meta.file_size as u64,
+                live_bytes: 0,
+                dead_bytes: 0,
+                uncompressed_live_bytes: 0,
+                compression: meta.compression,
+            };
+            let mut page_bytes = 0;
+            for (group_id, group_meta) in &meta.page_groups {
+                page_bytes += group_meta.total_page_size() as u64;
+                let Some(group) = version.page_groups().get(group_id) else {
+                    continue;
+                };
+                for page_addr in group.iter() {
+                    let Some(handle) = group.get_page_handle(page_addr) else {
+                        continue;
+                    };
+                    file.live_bytes += handle.size as u64;
+                    let buf = match self
+                        .page_files
+                        .read_file_page(meta.file_id, meta, handle)
+                        .await
+                    {
+                        Ok(buf) => buf,
+                        Err(err) if err.is_corrupted() => {
+                            report.num_unreadable_pages += 1;
+                            continue;
+                        }
+                        Err(Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                            report.num_unreadable_pages += 1;
+                            continue;
+                        }
+                        Err(err) => return Err(err),
+                    };
+                    let Some(page) = check_page(&buf) else {
+                        report.num_unreadable_pages += 1;
+                        continue;
+                    };
+                    file.uncompressed_live_bytes += buf.len() as u64;
+                    let kind = kind_name(page.tier(), page.kind());
+                    let space = page_kinds.entry(kind).or_insert_with(|| PageKindSpace {
+                        kind,
+                        ..Default::default()
+                    });
+                    space.num_pages += 1;
+                    space.disk_bytes += handle.size as u64;
+                    space.uncompressed_bytes += buf.len() as u64;
+                    if page.tier().is_leaf() && !page.kind().is_split() {
+                        let is_data = page.kind().is_data();
+                        let page = ValuePageRef::from(page);
+                        space.num_items += page.len() as u64;
+                        if is_data {
+                            for (key, value) in SortedPageIter::new(page) {
+                                report.key_sizes.add(key.raw.len());
+                                if let Value::Put(value) = value {
+                                    report.value_sizes.add(value.len());
+                                }
+                            }
+                        }
+                    } else {
+                        space.num_items += IndexPageRef::from(page).len() as u64;
+                    }
+                }
+            }
+            file.dead_bytes = page_bytes.saturating_sub(file.live_bytes);
+            report.files.push(file);
+        }
+        report.page_kinds = page_kinds.into_values().collect();
+        Ok(report)
+    }
+}
+
+fn kind_name(tier: PageTier, kind: PageKind) -> &'static str {
+    match (tier, kind) {
+        (PageTier::Leaf, PageKind::Data) => "leaf_data",
+        (PageTier::Leaf, PageKind::Split) => "leaf_split",
+        (PageTier::Leaf, PageKind::RangeDelete) => "leaf_range_delete",
+        (PageTier::Inner, PageKind::Data) => "inner_data",
+        (PageTier::Inner, PageKind::Split) => "inner_split",
+        (PageTier::Inner, PageKind::RangeDelete) => "inner_range_delete",
+    }
+}
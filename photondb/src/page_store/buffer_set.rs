@@ -1,6 +1,6 @@
 use std::{
     sync::{
-        atomic::{AtomicPtr, Ordering},
+        atomic::{AtomicPtr, AtomicU64, Ordering},
         Arc,
     },
     time::Instant,
@@ -14,18 +14,24 @@ use super::{
     write_buffer::ReleaseState,
     FlushOptions, Result, WriteBuffer,
 };
-use crate::util::notify::Notify;
+use crate::{
+    listener::{EventListener, StallEvent, StallReason},
+    util::{notify::Notify, task::yield_now},
+};
 
 pub(crate) struct BufferSet {
     buffer_capacity: u32,
     max_sealed_buffers: usize,
+    max_deferred_bytes: AtomicU64,
+    epoch_advance_attempts: usize,
 
     current: AtomicPtr<BufferSetVersion>,
 
     flush_notify: Notify,
     write_buffer_permits: buffer_permits::WriteBufferPermits,
 
-    stats: AtomicBufferSetStats,
+    stats: Arc<AtomicBufferSetStats>,
+    event_listener: Option<Arc<dyn EventListener>>,
 }
 
 pub(crate) struct BufferSetVersion {
@@ -69,13 +75,52 @@ impl BufferSet {
         BufferSet {
             buffer_capacity,
             max_sealed_buffers,
+            max_deferred_bytes: AtomicU64::new(u64::MAX),
+            epoch_advance_attempts: 0,
             current: AtomicPtr::new(raw),
             flush_notify: Notify::new(),
             write_buffer_permits,
-            stats: AtomicBufferSetStats::default(),
+            stats: Arc::default(),
+            event_listener: None,
         }
     }
 
+    /// Limits the bytes of released buffers waiting for reclamation.
+    ///
+    /// Once the limit is exceeded, the releasing thread will try to advance
+    /// the epoch at most `epoch_advance_attempts` times.
+    pub(crate) fn with_deferred_limit(
+        mut self,
+        max_deferred_bytes: u64,
+        epoch_advance_attempts: usize,
+    ) -> Self {
+        *self.max_deferred_bytes.get_mut() = max_deferred_bytes;
+        self.epoch_advance_attempts = epoch_advance_attempts;
+        self
+    }
+
+    /// Notifies the listener of write stalls.
+    pub(crate) fn with_event_listener(
+        mut self,
+        event_listener: Option<Arc<dyn EventListener>>,
+    ) -> Self {
+        self.event_listener = event_listener;
+        self
+    }
+
+    /// Changes the limit of the bytes of released buffers waiting for
+    /// reclamation.
+    pub(crate) fn set_max_deferred_bytes(&self, max_deferred_bytes: u64) {
+        self.max_deferred_bytes
+            .store(max_deferred_bytes, Ordering::Relaxed);
+    }
+
+    /// Returns the bytes of the write buffers in the current version.
+    pub(crate) fn memory_usage(&self) -> usize {
+        let current = self.current();
+        current.buffers_range.len() * self.buffer_capacity as usize
+    }
+
     #[inline]
     pub(crate) fn stats(&self) -> BufferSetStats {
         self.stats.snapshot()
@@ -199,7 +244,7 @@ impl BufferSet {
                     return;
                 }
             }
-            photonio::task::yield_now().await;
+            yield_now().await;
         }
     }
 
@@ -234,6 +279,9 @@ impl BufferSet {
         current: &BufferSetVersion,
         new: Box<BufferSetVersion>,
     ) -> Result<(), (Guard, &BufferSetVersion)> {
+        // The buffers removed from the version are freed along with it.
+        let released = (new.min_buffer_id() - current.min_buffer_id()) as u64;
+        let deferred_bytes = released * self.buffer_capacity as u64;
         let new = Box::into_raw(new);
         let current = current as *const _ as usize;
 
@@ -246,14 +294,21 @@ impl BufferSet {
             Ordering::Acquire,
         ) {
             Ok(_) => {
+                self.stats.deferred_versions.inc();
+                self.stats.deferred_bytes.add(deferred_bytes);
+                let stats = self.stats.clone();
                 guard.defer(move || {
                     // Safety: the backing memory is obtained from [`Box::into_raw`] and there no
                     // any references to the memory, which guarrantted by epoch based reclamation.
                     drop(unsafe { Box::from_raw(current as *mut BufferSetVersion) });
+                    stats.reclaimed_versions.inc();
+                    stats.reclaimed_bytes.add(deferred_bytes);
                 });
 
                 // Get the defer function executed as soon as possible.
                 guard.flush();
+                drop(guard);
+                self.try_reclaim_deferred();
                 Ok(())
             }
             Err(actual) => {
@@ -268,6 +323,39 @@ impl BufferSet {
         }
     }
 
+    /// Advances the epoch to reclaim the deferred versions if there are too
+    /// many bytes waiting for reclamation.
+    fn try_reclaim_deferred(&self) {
+        let max_deferred_bytes = self.max_deferred_bytes.load(Ordering::Relaxed);
+        let unreclaimed_bytes = self.stats.unreclaimed_bytes();
+        if unreclaimed_bytes <= max_deferred_bytes || self.epoch_advance_attempts == 0 {
+            return;
+        }
+        let start_at = Instant::now();
+        for _ in 0..self.epoch_advance_attempts {
+            self.stats.epoch_advances.inc();
+            // Each flush tries to advance the global epoch and collect the garbage
+            // of expired epochs.
+            buffer_set_guard::pin().flush();
+            if self.stats.unreclaimed_bytes() <= max_deferred_bytes {
+                break;
+            }
+        }
+        let duration = start_at.elapsed();
+        self.stats.slowdown_writes.inc();
+        self.stats
+            .slowdown_intervals_us
+            .add(duration.as_micros() as u64);
+        if let Some(listener) = &self.event_listener {
+            listener.on_write_stall(&StallEvent {
+                reason: StallReason::TooManyDeferredBytes,
+                duration,
+                current: unreclaimed_bytes,
+                threshold: max_deferred_bytes,
+            });
+        }
+    }
+
     /// Acquire the buffer id of the active buffer.
     ///
     /// if the active buffer is not installed, wait for it to be installed.
@@ -304,7 +392,7 @@ impl BufferSet {
                 .await
             {
                 // Maybe the new `WriteBuffer` is not installed yet.
-                photonio::task::yield_now().await;
+                yield_now().await;
             }
         }
     }
@@ -332,16 +420,26 @@ impl BufferSet {
     /// Install the corresponding successor of `group_id`.
     async fn install_successor(&self, group_id: u32) {
         if self.write_buffer_permits.try_acquire().is_none() {
+            let num_buffers = self.current().buffers_range.len() as u64;
             info!(
                 "Stalling writes because we have {} sealed write buffers (wait for flush)",
                 self.max_sealed_buffers
             );
             let start_at = Instant::now();
             self.write_buffer_permits.acquire().await;
+            let duration = start_at.elapsed();
             self.stats.stall_writes.inc();
             self.stats
                 .stall_intervals_ms
-                .add(start_at.elapsed().as_millis() as u64);
+                .add(duration.as_millis() as u64);
+            if let Some(listener) = &self.event_listener {
+                listener.on_write_stall(&StallEvent {
+                    reason: StallReason::TooManyWriteBuffers,
+                    duration,
+                    current: num_buffers,
+                    threshold: self.max_sealed_buffers as u64,
+                });
+            }
         }
 
         let write_buffer = WriteBuffer::with_capacity(group_id + 1, self.buffer_capacity);
@@ -363,7 +461,7 @@ impl BufferSet {
     pub(crate) async fn flush_active_buffer(&self, opts: &FlushOptions) {
         let buffer = {
             let current = self.current();
-            if current.current_buffer.is_empty() {
+            if current.current_buffer.is_empty() && !current.current_buffer.has_metadata() {
                 return;
             }
             current.current_buffer.clone()
@@ -426,6 +524,27 @@ impl BufferSetVersion {
         self.buffers_range.end
     }
 
+    /// Returns the bytes allocated in the buffers that are not released yet,
+    /// including the buffers being flushed.
+    pub(crate) fn dirty_bytes(&self) -> usize {
+        self.sealed_buffers
+            .iter()
+            .chain(Some(&self.current_buffer))
+            .map(|buf| buf.allocated_bytes())
+            .sum()
+    }
+
+    /// Returns the smallest LSN of the writes to the buffers that are not
+    /// flushed yet, or `u64::MAX` if there is none.
+    pub(crate) fn min_buffered_lsn(&self) -> u64 {
+        self.sealed_buffers
+            .iter()
+            .chain(Some(&self.current_buffer))
+            .map(|buf| buf.buffered_lsns().min())
+            .min()
+            .unwrap_or(u64::MAX)
+    }
+
     fn snapshot(&self) -> Vec<Arc<WriteBuffer>> {
         let mut buffers = self.sealed_buffers.clone();
         buffers.push(self.current_buffer.clone());
@@ -622,7 +741,7 @@ mod buffer_set_guard {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use std::sync::atomic::AtomicU32;
 
@@ -783,6 +902,35 @@ mod tests {
         assert_eq!(Arc::strong_count(&buf), 1);
     }
 
+    #[test]
+    fn buffer_set_deferred_reclaim_stats() {
+        let buffer_set = BufferSet::new(1, 1 << 10, 8).with_deferred_limit(0, 8);
+        let file_id = {
+            let current = buffer_set.current();
+            let buf = current.last_writer_buffer();
+            buf.seal().unwrap();
+            buf.group_id()
+        };
+
+        buffer_set.install(Arc::new(WriteBuffer::with_capacity(
+            file_id + 1,
+            buffer_set.buffer_capacity,
+        )));
+        buffer_set.release_until(file_id + 1);
+        let stats = buffer_set.stats();
+        assert_eq!(stats.deferred_versions, 2);
+        assert_eq!(stats.deferred_bytes, 1 << 10);
+        // Each release that has to advance the epoch slows down the writer.
+        assert_eq!(stats.slowdown_writes > 0, stats.epoch_advances > 0);
+
+        // Advance epoch until all deferred versions are reclaimed.
+        while buffer_set.stats().unreclaimed_bytes() > 0 {
+            buffer_set_guard::pin().flush();
+            std::thread::yield_now();
+        }
+        assert_eq!(buffer_set.stats().reclaimed_bytes, 1 << 10);
+    }
+
     #[photonio::test]
     async fn buffer_set_flush_active_buffer() {
         let buffer_set = BufferSet::new(1, 1 << 10, 8);
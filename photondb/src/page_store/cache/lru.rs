@@ -22,6 +22,7 @@ pub(crate) struct LRUCache<T: Clone> {
     shards: Vec<Mutex<LRUCacheShard<T>>>,
     shard_mask: u32,
     stats: Vec<Arc<AtomicCacheStats>>,
+    usages: Vec<Arc<AtomicUsize>>,
 }
 
 struct LRUCacheShard<T: Clone> {
@@ -99,15 +100,39 @@ impl<T: Clone> LRUCache<T> {
         let shard_mask = num_shards - 1;
         let mut shards = Vec::with_capacity(num_shards as usize);
         let mut stats = Vec::with_capacity(num_shards as usize);
+        let mut usages = Vec::with_capacity(num_shards as usize);
         for _ in 0..num_shards {
             let shard = LRUCacheShard::new(per_shard_cap, high_pri_ratio, low_pri_ratio);
             stats.push(shard.stats.clone());
+            usages.push(shard.usage.clone());
             shards.push(Mutex::new(shard));
         }
         Self {
             shards,
             shard_mask,
             stats,
+            usages,
+        }
+    }
+
+    /// Returns the total charge of the entries in the cache.
+    pub(crate) fn usage(&self) -> usize {
+        self.usages
+            .iter()
+            .map(|usage| usage.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Changes the capacity of the cache.
+    ///
+    /// Entries are evicted until the usage fits the new capacity, except the
+    /// ones that are still referenced.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let num_shards = self.shards.len();
+        let per_shard_cap = (capacity + (num_shards - 1)) / num_shards;
+        for shard in &self.shards {
+            let mut shard = shard.lock();
+            unsafe { shard.set_capacity(per_shard_cap) };
         }
     }
 
@@ -257,6 +282,14 @@ impl<T: Clone> LRUCacheShard<T> {
         }
     }
 
+    unsafe fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.lru_high_capacity = ((capacity as f64) * self.high_pri_ratio) as usize;
+        self.lru_low_capacity = ((capacity as f64) * self.low_pri_ratio) as usize;
+        self.maintain_priority_size();
+        self.evict_lru(0, CacheOption::default());
+    }
+
     unsafe fn insert(
         &mut self,
         key: u64,
@@ -1,6 +1,4 @@
 use std::{
-    fmt,
-    ops::{Deref, DerefMut},
     ptr,
     sync::atomic::{AtomicU8, Ordering},
 };
@@ -14,7 +12,7 @@ use bitflags::bitflags;
 use super::{page_txn::CachePriority, stats::CacheStats, CacheOption};
 use crate::{
     page_store::{Error, Result},
-    util::atomic::Counter,
+    util::{atomic::Counter, cache_padded::CachePadded},
 };
 
 pub(crate) mod clock;
@@ -387,56 +385,6 @@ impl AtomicCacheStats {
     }
 }
 
-#[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64"), repr(align(128)))]
-#[cfg_attr(
-    not(any(target_arch = "x86_64", target_arch = "aarch64")),
-    repr(align(64))
-)]
-#[derive(Default, PartialEq, Eq)]
-struct CachePadded<T> {
-    value: T,
-}
-
-#[allow(unsafe_code)]
-unsafe impl<T: Send> Send for CachePadded<T> {}
-
-#[allow(unsafe_code)]
-unsafe impl<T: Sync> Sync for CachePadded<T> {}
-
-impl<T> CachePadded<T> {
-    const fn new(t: T) -> CachePadded<T> {
-        CachePadded::<T> { value: t }
-    }
-}
-
-impl<T> Deref for CachePadded<T> {
-    type Target = T;
-
-    fn deref(&self) -> &T {
-        &self.value
-    }
-}
-
-impl<T> DerefMut for CachePadded<T> {
-    fn deref_mut(&mut self) -> &mut T {
-        &mut self.value
-    }
-}
-
-impl<T: fmt::Debug> fmt::Debug for CachePadded<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CachePadded")
-            .field("value", &self.value)
-            .finish()
-    }
-}
-
-impl<T> From<T> for CachePadded<T> {
-    fn from(t: T) -> Self {
-        CachePadded::new(t)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use ::std::thread;
@@ -482,6 +430,40 @@ mod tests {
         assert!(h.is_none());
     }
 
+    #[test]
+    fn test_lru_set_capacity() {
+        use super::lru::*;
+
+        let c = Arc::new(LRUCache::new(4, -1, 0.0, 0.0));
+        for n in 1..=4 {
+            let h = c
+                .insert(n, Some(vec![n]), 1, CacheOption::default())
+                .unwrap()
+                .unwrap();
+            drop(h);
+        }
+        let pinned = c.lookup(4).unwrap();
+
+        // The oldest entries are evicted, but the pinned one is kept.
+        c.set_capacity(2);
+        assert_eq!(c.usage(), 2);
+        assert!(c.lookup(1).is_none());
+        assert!(c.lookup(2).is_none());
+        assert!(c.lookup(3).is_some());
+        drop(pinned);
+        assert!(c.lookup(4).is_some());
+
+        c.set_capacity(4);
+        for n in 5..=6 {
+            let h = c
+                .insert(n, Some(vec![n]), 1, CacheOption::default())
+                .unwrap()
+                .unwrap();
+            drop(h);
+        }
+        assert!(c.lookup(3).is_some());
+    }
+
     #[test]
     fn test_lru_pri_op() {
         use super::lru::*;
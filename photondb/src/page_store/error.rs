@@ -6,14 +6,31 @@ pub(crate) enum Error {
     Again,
     #[error("Corrupted")]
     Corrupted,
+    #[error("Corrupted page in file {file_id} at offset {offset}")]
+    CorruptedPage { file_id: u32, offset: u64 },
     #[error("Invalid argument")]
     InvalidArgument,
     #[error("Memory Limit")]
     MemoryLimit,
     #[error("TooLarge put size")]
     TooLargeSize,
+    #[error("Busy")]
+    Busy,
+    #[error("Capacity")]
+    Capacity,
+    #[error("Timeout")]
+    Timeout,
+    #[error("ReadOnly")]
+    ReadOnly,
     #[error("IO {0}")]
     Io(#[from] std::io::Error),
 }
 
+impl Error {
+    /// Returns true if some data is corrupted.
+    pub(crate) fn is_corrupted(&self) -> bool {
+        matches!(self, Error::Corrupted | Error::CorruptedPage { .. })
+    }
+}
+
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
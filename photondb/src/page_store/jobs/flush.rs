@@ -1,12 +1,17 @@
 use std::{sync::Arc, time::Instant};
 
-use log::info;
+use futures::{future, lock::Mutex};
+use log::{error, info};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
     env::Env,
+    listener::{BackgroundErrorEvent, EventListener, FlushBeginEvent, FlushEvent},
     page_store::{stats::AtomicJobStats, *},
-    util::shutdown::{with_shutdown, Shutdown},
+    util::{
+        shutdown::{with_shutdown, Shutdown},
+        trace::instrument,
+    },
 };
 
 pub(crate) struct FlushCtx<E: Env> {
@@ -16,6 +21,16 @@ pub(crate) struct FlushCtx<E: Env> {
     version_owner: Arc<VersionOwner>,
     page_files: Arc<PageFiles<E>>,
     manifest: Arc<futures::lock::Mutex<Manifest<E>>>,
+    event_listener: Option<Arc<dyn EventListener>>,
+    watermark: Arc<LsnWatermark>,
+}
+
+/// A page file built from a write buffer, which is not installed yet.
+struct FlushedFile {
+    start_at: Instant,
+    dealloc_pages: Vec<u64>,
+    page_group: PageGroup,
+    file_info: FileInfo,
 }
 
 #[derive(Default)]
@@ -36,6 +51,7 @@ impl<E: Env> FlushCtx<E> {
         version_owner: Arc<VersionOwner>,
         page_files: Arc<PageFiles<E>>,
         manifest: Arc<futures::lock::Mutex<Manifest<E>>>,
+        event_listener: Option<Arc<dyn EventListener>>,
     ) -> Self {
         FlushCtx {
             options,
@@ -44,9 +60,16 @@ impl<E: Env> FlushCtx<E> {
             version_owner,
             page_files,
             manifest,
+            event_listener,
+            watermark: Arc::default(),
         }
     }
 
+    pub(crate) fn with_watermark(mut self, watermark: Arc<LsnWatermark>) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
     pub(crate) async fn run(mut self) {
         'OUTER: loop {
             let version = self.version_owner.current();
@@ -64,9 +87,24 @@ impl<E: Env> FlushCtx<E> {
                 }
             }
 
-            match self.flush(write_buffer.as_ref()).await {
+            // The following buffers that are also flushable are flushed together,
+            // up to the number of background flushes.
+            let mut write_buffers = vec![write_buffer];
+            while write_buffers.len() < self.options.max_background_flushes {
+                let group_id = write_buffers.last().unwrap().group_id() + 1;
+                let Some(next) = version.buffer_set.current().get(group_id).cloned() else {
+                    break;
+                };
+                if !next.is_flushable() {
+                    break;
+                }
+                write_buffers.push(next);
+            }
+
+            match self.flush_buffers(&write_buffers).await {
                 Ok(()) => {}
                 Err(err) => {
+                    self.on_error(&err);
                     todo!("flush write buffer: {err:?}");
                 }
             }
@@ -77,10 +115,30 @@ impl<E: Env> FlushCtx<E> {
         }
     }
 
+    fn on_error(&self, err: &Error) {
+        if let Some(listener) = &self.event_listener {
+            listener.on_background_error(&BackgroundErrorEvent {
+                job: "flush",
+                error: err.to_string(),
+            });
+        }
+    }
+
+    /// Flushes the write buffers that are ready to flush, in order.
+    async fn flush_ready(&self) -> Result<()> {
+        loop {
+            let write_buffer = self.version_owner.current().min_write_buffer();
+            if !write_buffer.is_flushable() {
+                return Ok(());
+            }
+            self.flush(write_buffer.as_ref()).await?;
+        }
+    }
+
     /// Flush write buffers when user try to shutdown a page store.
     ///
     /// Note: it assumes that there no any inflights writers during shutdown.
-    async fn flush_during_shutdown(self) {
+    async fn flush_during_shutdown(&self) {
         let buffers_range = { self.version_owner.current().buffers_range() };
         for id in buffers_range {
             let buffer = {
@@ -92,7 +150,7 @@ impl<E: Env> FlushCtx<E> {
                     .clone()
             };
             if !buffer.is_sealed() {
-                if buffer.is_empty() {
+                if buffer.is_empty() && !buffer.has_metadata() {
                     // skip empty buffer.
                     break;
                 }
@@ -111,10 +169,68 @@ impl<E: Env> FlushCtx<E> {
     }
 
     async fn flush_impl(&self, write_buffer: &WriteBuffer, wait: bool) -> Result<()> {
+        instrument!(
+            self.flush_impl_inner(write_buffer, wait),
+            "flush",
+            group_id = write_buffer.group_id()
+        )
+        .await
+    }
+
+    async fn flush_impl_inner(&self, write_buffer: &WriteBuffer, wait: bool) -> Result<()> {
+        let file = self.build_flushed_file(write_buffer).await?;
+        self.install_flushed_file(write_buffer, file, wait).await
+    }
+
+    /// Flushes consecutive write buffers, whose page files are built
+    /// concurrently and installed in order.
+    async fn flush_buffers(&self, write_buffers: &[Arc<WriteBuffer>]) -> Result<()> {
+        if let [write_buffer] = write_buffers {
+            return self.flush(write_buffer).await;
+        }
+        let files = future::try_join_all(write_buffers.iter().map(|write_buffer| {
+            instrument!(
+                self.build_flushed_file(write_buffer),
+                "flush",
+                group_id = write_buffer.group_id()
+            )
+        }))
+        .await?;
+        for (write_buffer, file) in write_buffers.iter().zip(files) {
+            self.install_flushed_file(write_buffer, file, true).await?;
+        }
+        Ok(())
+    }
+
+    async fn build_flushed_file(&self, write_buffer: &WriteBuffer) -> Result<FlushedFile> {
         let start_at = Instant::now();
-        let group_id = write_buffer.group_id();
+        if let Some(listener) = &self.event_listener {
+            listener.on_flush_begin(&FlushBeginEvent {
+                buffer_id: write_buffer.group_id(),
+            });
+        }
         let (dealloc_pages, page_group, file_info) = self.build_page_file(write_buffer).await?;
+        Ok(FlushedFile {
+            start_at,
+            dealloc_pages,
+            page_group,
+            file_info,
+        })
+    }
 
+    async fn install_flushed_file(
+        &self,
+        write_buffer: &WriteBuffer,
+        file: FlushedFile,
+        wait: bool,
+    ) -> Result<()> {
+        let FlushedFile {
+            start_at,
+            dealloc_pages,
+            page_group,
+            file_info,
+        } = file;
+        let group_id = write_buffer.group_id();
         let file_id = file_info.meta().file_id;
         let file_size = file_info.meta().file_size;
         info!(
@@ -125,10 +241,37 @@ impl<E: Env> FlushCtx<E> {
             start_at.elapsed().as_micros()
         );
 
-        self.save_and_install_version(page_group, file_info, dealloc_pages, wait)
+        let num_active_pages = page_group.num_active_pages();
+        let num_dealloc_pages = dealloc_pages.len();
+        let mut metadata = write_buffer.take_metadata();
+        if let Some(lsn) = self
+            .watermark
+            .flushed_lsn_with(write_buffer.buffered_lsns())
+        {
+            metadata.push(MetadataEdit {
+                key: FLUSHED_LSN_METADATA.to_owned(),
+                value: Some(lsn.to_be_bytes().to_vec()),
+            });
+        }
+        self.save_and_install_version(page_group, file_info, dealloc_pages, metadata, wait)
             .await?;
 
+        // The watermark is advanced before the flush is notified, so that
+        // waiters observe it.
+        let version = self.version_owner.current();
+        self.watermark
+            .on_flushed(write_buffer.buffered_lsns(), || version.min_buffered_lsn());
         write_buffer.on_flushed();
+        if let Some(listener) = &self.event_listener {
+            listener.on_flush_completed(&FlushEvent {
+                buffer_id: group_id,
+                file_id,
+                file_size,
+                num_active_pages,
+                num_dealloc_pages,
+                duration: start_at.elapsed(),
+            });
+        }
 
         Ok(())
     }
@@ -138,6 +281,7 @@ impl<E: Env> FlushCtx<E> {
         page_group: PageGroup,
         file_info: FileInfo,
         dealloc_pages: Vec<u64>,
+        metadata: Vec<MetadataEdit>,
         wait_new_buffer: bool,
     ) -> Result<()> {
         let mut manifest = self.manifest.lock().await;
@@ -155,7 +299,7 @@ impl<E: Env> FlushCtx<E> {
         }
         file_infos.insert(file_id, file_info);
 
-        let edit = make_flush_version_edit(file_id, &obsoleted_files);
+        let edit = make_flush_version_edit(file_id, &obsoleted_files, metadata);
         manifest
             .record_version_edit(edit, || version_snapshot(&version))
             .await?;
@@ -263,6 +407,53 @@ impl<E: Env> FlushCtx<E> {
     }
 }
 
+/// Flushes write buffers on the writers, which replaces the flush job if
+/// there are no background flushes.
+pub(crate) struct InlineFlusher<E: Env> {
+    ctx: Mutex<FlushCtx<E>>,
+}
+
+impl<E: Env> InlineFlusher<E> {
+    pub(crate) fn new(ctx: FlushCtx<E>) -> Self {
+        InlineFlusher {
+            ctx: Mutex::new(ctx),
+        }
+    }
+
+    /// Flushes the write buffers that are ready to flush.
+    ///
+    /// This returns immediately if another writer is flushing, which flushes
+    /// the ready buffers for it.
+    pub(crate) async fn flush_ready(&self) {
+        if let Some(ctx) = self.ctx.try_lock() {
+            Self::flush_ready_with(&ctx).await;
+        }
+    }
+
+    /// Flushes the write buffers that are ready to flush, and waits for other
+    /// writers that are flushing.
+    pub(crate) async fn flush_ready_and_wait(&self) {
+        let ctx = self.ctx.lock().await;
+        Self::flush_ready_with(&ctx).await;
+    }
+
+    async fn flush_ready_with(ctx: &FlushCtx<E>) {
+        // The buffer is flushed again by the next writer if this fails.
+        if let Err(err) = ctx.flush_ready().await {
+            error!("Flush write buffers: {err:?}");
+            ctx.on_error(&err);
+        }
+    }
+
+    /// Flushes the rest of the write buffers when the page store is closed.
+    pub(crate) async fn shutdown(&self) {
+        let ctx = self.ctx.lock().await;
+        if !ctx.options.avoid_flush_during_shutdown {
+            ctx.flush_during_shutdown().await;
+        }
+    }
+}
+
 impl std::fmt::Display for FlushPageStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -364,10 +555,15 @@ pub(super) fn version_snapshot(version: &Version) -> VersionEdit {
     };
     VersionEdit {
         file_stream: Some(stream),
+        metadata: Vec::new(),
     }
 }
 
-fn make_flush_version_edit(file_id: u32, obsoleted_files: &FxHashSet<u32>) -> VersionEdit {
+fn make_flush_version_edit(
+    file_id: u32,
+    obsoleted_files: &FxHashSet<u32>,
+    metadata: Vec<MetadataEdit>,
+) -> VersionEdit {
     let deleted_files = obsoleted_files.iter().cloned().collect();
     let new_files = vec![NewFile::from(file_id)];
     let stream = StreamEdit {
@@ -376,10 +572,11 @@ fn make_flush_version_edit(file_id: u32, obsoleted_files: &FxHashSet<u32>) -> Ve
     };
     VersionEdit {
         file_stream: Some(stream),
+        metadata,
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use std::{path::Path, sync::Arc};
 
@@ -413,6 +610,8 @@ mod tests {
             manifest: Arc::new(futures::lock::Mutex::new(
                 Manifest::open(Photon, base).await.unwrap(),
             )),
+            event_listener: None,
+            watermark: Arc::default(),
         }
     }
 
@@ -1,22 +1,31 @@
 use std::{sync::Arc, time::Instant};
 
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, Either},
+    pin_mut, StreamExt,
+};
 use log::{debug, error, info, trace};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
     env::Env,
+    listener::{BackgroundErrorEvent, EventListener, ReclaimEvent},
     page::PageRef,
     page_store::{
         page_file::{FileBuilder, FileMetaHolder, FileReader, PageGroupBuilder},
         stats::AtomicJobStats,
         strategy::ReclaimPickStrategy,
         version::{DeltaVersion, VersionOwner, VersionUpdateReason},
-        FileInfo, Manifest, NewFile, Options, PageFiles, PageGroup, Result, StrategyBuilder,
+        Error, FileInfo, Manifest, NewFile, Options, PageFiles, PageGroup, Result, StrategyBuilder,
         StreamEdit, Version, VersionEdit,
     },
     util::shutdown::{with_shutdown, Shutdown},
 };
 
+/// A request to reclaim all obsolete pages, which is answered once it is done.
+pub(crate) type ReclaimRequest = oneshot::Sender<Result<()>>;
+
 pub(crate) struct ReclaimCtx<E>
 where
     E: Env,
@@ -31,8 +40,10 @@ where
     manifest: Arc<futures::lock::Mutex<Manifest<E>>>,
 
     cleaned_files: FxHashSet<u32>,
+    requests: mpsc::UnboundedReceiver<ReclaimRequest>,
 
     job_stats: Arc<AtomicJobStats>,
+    event_listener: Option<Arc<dyn EventListener>>,
 }
 
 #[derive(Debug)]
@@ -89,7 +100,9 @@ where
         page_files: Arc<PageFiles<E>>,
         version_owner: Arc<VersionOwner>,
         manifest: Arc<futures::lock::Mutex<Manifest<E>>>,
+        requests: mpsc::UnboundedReceiver<ReclaimRequest>,
         job_stats: Arc<AtomicJobStats>,
+        event_listener: Option<Arc<dyn EventListener>>,
     ) -> Self {
         ReclaimCtx {
             options,
@@ -99,7 +112,9 @@ where
             version_owner,
             manifest,
             cleaned_files: FxHashSet::default(),
+            requests,
             job_stats,
+            event_listener,
         }
     }
 
@@ -109,9 +124,35 @@ where
                 self.reclaim(&version).await;
                 version.reclaimed();
             }
-            match with_shutdown(&mut self.shutdown, version.wait_next_version()).await {
-                Some(next_version) => version = next_version.refresh().unwrap_or(next_version),
-                None => break,
+            // Serves manual requests until the next version is installed.
+            loop {
+                let wakeup = {
+                    let next_version = version.wait_next_version();
+                    pin_mut!(next_version);
+                    let request = self.requests.next();
+                    let wakeup = future::select(next_version, request);
+                    with_shutdown(&mut self.shutdown, wakeup)
+                        .await
+                        .map(|either| match either {
+                            Either::Left((next_version, _)) => Either::Left(next_version),
+                            Either::Right((request, _)) => Either::Right(request),
+                        })
+                };
+                match wakeup {
+                    Some(Either::Left(next_version)) => {
+                        version = next_version.refresh().unwrap_or(next_version);
+                        break;
+                    }
+                    Some(Either::Right(Some(request))) => {
+                        let result = self.reclaim_all().await;
+                        if let Err(err) = &result {
+                            self.on_error(err);
+                        }
+                        let _ = request.send(result);
+                    }
+                    // The requests are closed once the store is dropped.
+                    Some(Either::Right(None)) | None => return,
+                }
             }
         }
     }
@@ -129,8 +170,66 @@ where
             .reclaim_files_by_strategy(&mut progress, version, &cleaned_files)
             .await
         {
-            error!("Reclaim files: {err:?}");
+            self.on_error(&err);
+        }
+    }
+
+    fn on_error(&self, err: &Error) {
+        error!("Reclaim files: {err:?}");
+        if let Some(listener) = &self.event_listener {
+            listener.on_background_error(&BackgroundErrorEvent {
+                job: "reclaim",
+                error: err.to_string(),
+            });
+        }
+    }
+
+    /// Compacts all files that contain obsolete pages, regardless of the space
+    /// usage and [`Options::disable_space_reclaiming`].
+    pub(crate) async fn reclaim_all(&mut self) -> Result<()> {
+        let version = self.version_owner.current();
+        let mut progress = ReclaimProgress::new(&self.options, &version, &self.cleaned_files);
+        let page_groups = version.page_groups();
+        let mut files: Vec<_> = version
+            .file_infos()
+            .iter()
+            .filter(|(id, _)| !self.cleaned_files.contains(id))
+            .filter_map(|(&id, info)| {
+                let mut has_obsolete_pages = false;
+                let mut active_size = 0;
+                for (group_id, meta) in &info.meta().page_groups {
+                    match page_groups.get(group_id) {
+                        Some(group) => {
+                            has_obsolete_pages |= group.num_active_pages() < meta.total_pages();
+                            active_size += group.effective_size();
+                        }
+                        None => has_obsolete_pages = true,
+                    }
+                }
+                has_obsolete_pages.then_some((id, active_size))
+            })
+            .collect();
+        files.sort_unstable();
+
+        let mut builder = ReclaimJobBuilder::new(self.options.file_base_size);
+        let mut jobs: Vec<_> = files
+            .into_iter()
+            .filter_map(|(id, active_size)| builder.add(id, active_size))
+            .collect();
+        jobs.extend(builder.finish());
+        info!("Reclaim all obsolete pages with {} compactions", jobs.len());
+        for job in jobs {
+            if self.shutdown.is_terminated() {
+                break;
+            }
+            match job {
+                ReclaimJob::Compact(victims) => {
+                    let version = self.version_owner.current();
+                    self.reclaim_files(&mut progress, &version, victims).await?;
+                }
+            }
         }
+        Ok(())
     }
 
     async fn reclaim_files_by_strategy(
@@ -279,6 +378,15 @@ where
                     free {free_size} bytes, free ratio {free_ratio:.4}, \
                     latest {elapsed} microseconds"
         );
+        if let Some(listener) = &self.event_listener {
+            listener.on_reclaim_completed(&ReclaimEvent {
+                input_files: victims,
+                file_id: new_file_id,
+                input_size,
+                output_size,
+                duration: start_at.elapsed(),
+            });
+        }
 
         Ok((page_groups, file_info))
     }
@@ -396,6 +504,15 @@ impl ReclaimJobBuilder {
         }
         None
     }
+
+    /// Returns a job of the remaining files, if any.
+    fn finish(&mut self) -> Option<ReclaimJob> {
+        if self.compact_files.is_empty() {
+            return None;
+        }
+        self.compact_size = 0;
+        Some(ReclaimJob::Compact(std::mem::take(&mut self.compact_files)))
+    }
 }
 
 impl ReclaimProgress {
@@ -498,7 +615,7 @@ impl CompactStats {
 
 /// Wait until the running reclaiming progress to finish.
 pub(crate) async fn wait_for_reclaiming(options: &Options, mut version: Arc<Version>) {
-    if options.disable_space_reclaiming {
+    if options.disable_space_reclaiming || !options.reclaim_in_background {
         return;
     }
 
@@ -552,10 +669,11 @@ fn make_compact_version_edit(
             new_files,
             deleted_files,
         }),
+        metadata: Vec::new(),
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use std::{collections::HashSet, path::Path};
 
@@ -598,7 +716,9 @@ mod tests {
             manifest,
             version_owner,
             cleaned_files: HashSet::default(),
+            requests: mpsc::unbounded().1,
             job_stats: Arc::default(),
+            event_listener: None,
         }
     }
 
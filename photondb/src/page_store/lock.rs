@@ -0,0 +1,76 @@
+use std::{io::ErrorKind, path::Path};
+
+use log::warn;
+
+use super::{Error, Result};
+use crate::env::{Env, FileLock};
+
+pub(crate) const LOCK_FILE_NAME: &str = "LOCK";
+
+/// A lock file that prevents a directory from being opened by more than one
+/// page store at the same time.
+///
+/// The file is locked with an advisory lock of the operating system through
+/// [`Env::lock_file`], which is released when the lock is dropped or the
+/// process exits. The file itself is left in the directory.
+pub(super) struct LockFile {
+    _lock: Option<FileLock>,
+}
+
+impl LockFile {
+    /// Returns a lock that holds nothing, for stores that don't modify the
+    /// directory.
+    pub(super) fn none() -> Self {
+        Self { _lock: None }
+    }
+
+    /// Acquires the lock of the directory.
+    ///
+    /// Returns [`Error::Busy`] if the directory is locked by others, unless
+    /// `force` is true, which opens the directory without the lock.
+    pub(super) fn acquire<E: Env>(env: &E, dir: &Path, force: bool) -> Result<Self> {
+        let path = dir.join(LOCK_FILE_NAME);
+        match env.lock_file(&path) {
+            Ok(lock) => Ok(Self { _lock: Some(lock) }),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                if !force {
+                    warn!("{path:?} is locked by another process");
+                    return Err(Error::Busy);
+                }
+                warn!("ignore the lock of {path:?} held by another process");
+                Ok(Self { _lock: None })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::env::Std;
+
+    #[test]
+    fn acquire() {
+        let dir = TempDir::new("lock_file").unwrap();
+        let lock = LockFile::acquire(&Std, dir.path(), false).unwrap();
+        assert!(matches!(
+            LockFile::acquire(&Std, dir.path(), false),
+            Err(Error::Busy)
+        ));
+        // The lock is held by others, which is ignored with `force`.
+        let forced = LockFile::acquire(&Std, dir.path(), true).unwrap();
+        drop(forced);
+        assert!(matches!(
+            LockFile::acquire(&Std, dir.path(), false),
+            Err(Error::Busy)
+        ));
+        drop(lock);
+
+        // The lock file is left, but it is not locked anymore.
+        assert!(dir.path().join(LOCK_FILE_NAME).exists());
+        let _lock = LockFile::acquire(&Std, dir.path(), false).unwrap();
+    }
+}
@@ -1,8 +1,12 @@
-use std::{io::ErrorKind, path::PathBuf};
+use std::{collections::BTreeMap, io::ErrorKind, path::PathBuf, sync::Arc, time::Instant};
 
 use prost::Message;
 
-use super::{meta::VersionEdit, Error};
+use super::{
+    meta::{MetadataEdit, VersionEdit},
+    stats::AtomicFileIoStats,
+    Error,
+};
 use crate::{env::*, page_store::Result};
 
 const CURRENT_FILE_NAME: &str = "CURRENT";
@@ -21,6 +25,11 @@ pub(crate) struct Manifest<E: Env> {
 
     current_file_num: Option<u32>,
     current_writer: Option<ManifestWriter<E::SequentialWriter>>,
+
+    /// The metadata entries recorded in the manifest.
+    metadata: BTreeMap<String, Vec<u8>>,
+
+    io_stats: Arc<AtomicFileIoStats>,
 }
 
 struct ManifestWriter<W> {
@@ -32,9 +41,33 @@ impl<E: Env> Manifest<E> {
     // Open manifest in specified folder.
     // it will reopen manifest by find CURRENT and do some cleanup.
     pub(crate) async fn open(env: E, base: impl Into<PathBuf>) -> Result<Self> {
-        let base = base.into();
+        let mut manifest = Self::new(env, base.into());
+        manifest.create_base_dir_if_not_exist().await?;
+        manifest.current_file_num = manifest.load_current().await?;
+        manifest.open_base_dir().await?;
+        manifest.cleanup_obsolete_files().await?;
+
+        Ok(manifest)
+    }
+
+    /// Opens the manifest in the folder without modifying the folder.
+    ///
+    /// Unlike [`Self::open`], it fails if there is no manifest, and the
+    /// manifest can't record version edits.
+    pub(crate) async fn open_read_only(env: E, base: impl Into<PathBuf>) -> Result<Self> {
+        let mut manifest = Self::new(env, base.into());
+        manifest.current_file_num = manifest.load_current().await?;
+        if manifest.current_file_num.is_none() {
+            return Err(Error::Io(std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("no manifest in {}", manifest.base.display()),
+            )));
+        }
+        Ok(manifest)
+    }
 
-        let mut manifest = Self {
+    fn new(env: E, base: PathBuf) -> Self {
+        Self {
             env,
             base,
             base_dir: None,
@@ -42,13 +75,19 @@ impl<E: Env> Manifest<E> {
             next_file_id: 0,
             current_file_num: Default::default(),
             current_writer: None,
-        };
-        manifest.create_base_dir_if_not_exist().await?;
-        manifest.current_file_num = manifest.load_current().await?;
-        manifest.open_base_dir().await?;
-        manifest.cleanup_obsolete_files().await?;
+            metadata: BTreeMap::new(),
+            io_stats: Arc::default(),
+        }
+    }
 
-        Ok(manifest)
+    /// Returns the I/O statistics of the manifest files.
+    pub(crate) fn io_stats(&self) -> Arc<AtomicFileIoStats> {
+        self.io_stats.clone()
+    }
+
+    /// Returns true if the file is a manifest file or the CURRENT file.
+    pub(super) fn is_manifest_file(file_name: &str) -> bool {
+        file_name == CURRENT_FILE_NAME || file_name.starts_with(&format!("{}_", MANIFEST_FILE_NAME))
     }
 
     async fn create_base_dir_if_not_exist(&self) -> Result<()> {
@@ -84,6 +123,25 @@ impl<E: Env> Manifest<E> {
         self.next_file_id
     }
 
+    /// Returns the metadata entries recorded in the manifest.
+    pub(crate) fn metadata(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.metadata
+    }
+
+    /// Applies the metadata edits of the versions listed from the manifest.
+    pub(super) fn recover_metadata(&mut self, versions: &[VersionEdit]) {
+        for edit in versions.iter().flat_map(|ve| &ve.metadata) {
+            self.apply_metadata_edit(edit.clone());
+        }
+    }
+
+    fn apply_metadata_edit(&mut self, edit: MetadataEdit) {
+        match edit.value {
+            Some(value) => self.metadata.insert(edit.key, value),
+            None => self.metadata.remove(&edit.key),
+        };
+    }
+
     // Record a new version_edit to manifest file.
     // it will rolling file when the file size over `max_file_size`.
     // so it need pass-in a `version_snapshot` to get current snapshot when it
@@ -93,6 +151,7 @@ impl<E: Env> Manifest<E> {
         ve: VersionEdit,
         version_snapshot: impl FnOnce() -> VersionEdit,
     ) -> Result<()> {
+        let metadata = ve.metadata.clone();
         let mut current = self.current_writer.take();
         let mut file_num = self.current_file_num.as_ref().unwrap_or(&0).to_owned();
 
@@ -116,7 +175,16 @@ impl<E: Env> Manifest<E> {
         let mut current = current.unwrap();
         let written = if rolled_path.is_some() {
             // TODO: remove new created file when write fail.
-            let base_snapshot = version_snapshot();
+            let mut base_snapshot = version_snapshot();
+            // The metadata is carried over to the new file.
+            base_snapshot.metadata = self
+                .metadata
+                .iter()
+                .map(|(key, value)| MetadataEdit {
+                    key: key.clone(),
+                    value: Some(value.clone()),
+                })
+                .collect();
             let base_written = VersionEditEncoder(base_snapshot)
                 .encode(&mut current.current_writer)
                 .await?;
@@ -135,13 +203,16 @@ impl<E: Env> Manifest<E> {
                 .encode(&mut current.current_writer)
                 .await?
         } as u64;
+        self.io_stats.on_write(written as usize);
 
+        let start = Instant::now();
         if rolled_path.is_some() {
             current
                 .current_writer
                 .sync_all()
                 .await
                 .expect("sync new manifest file fail");
+            self.io_stats.on_sync(start);
             self.set_current(file_num).await?;
             // TODO: notify cleaner previous manifest + size, so it can be delete when need.
             self.current_file_num = Some(file_num);
@@ -151,11 +222,15 @@ impl<E: Env> Manifest<E> {
                 .sync_data()
                 .await
                 .expect("sync manifest data fail");
+            self.io_stats.on_sync(start);
         }
 
         current.current_file_size += written;
 
         self.current_writer = Some(current);
+        for edit in metadata {
+            self.apply_metadata_edit(edit);
+        }
 
         Ok(())
     }
@@ -167,10 +242,14 @@ impl<E: Env> Manifest<E> {
             let path = self
                 .base
                 .join(format!("{}_{}", MANIFEST_FILE_NAME, current_file));
+            let len = self.env.metadata(&path).await?.len;
             let reader = self.env.open_positional_reader(path).await?;
-            let mut decoder = VersionEditDecoder::new(reader);
+            let mut decoder = VersionEditDecoder::new(reader, len);
             let mut ves = Vec::new();
+            let mut offset = 0;
             while let Some(ve) = decoder.next_record().await.map_err(|_| Error::Corrupted)? {
+                self.io_stats.on_read((decoder.offset - offset) as usize);
+                offset = decoder.offset;
                 ves.push(ve)
             }
             ves
@@ -193,6 +272,7 @@ impl<E: Env> Manifest<E> {
         curr_file_reader
             .read_exact_at(&mut file_num_bytes, 0)
             .await?;
+        self.io_stats.on_read(file_num_bytes.len());
         let file_num = u32::from_le_bytes(
             file_num_bytes[0..core::mem::size_of::<u32>()]
                 .try_into()
@@ -210,10 +290,13 @@ impl<E: Env> Manifest<E> {
             {
                 let mut tmp_file = self.env.open_sequential_writer(&tmp_path).await?;
                 tmp_file.write_all(&file_num.to_le_bytes()).await?;
+                self.io_stats.on_write(std::mem::size_of::<u32>());
+                let start = Instant::now();
                 tmp_file
                     .sync_all()
                     .await
                     .expect("sync tmp current file fail");
+                self.io_stats.on_sync(start);
             }
 
             match self
@@ -229,12 +312,14 @@ impl<E: Env> Manifest<E> {
                 }
             }?;
         }
+        let start = Instant::now();
         self.base_dir
             .as_ref()
             .unwrap()
             .sync_all()
             .await
             .expect("sync base folder fail");
+        self.io_stats.on_sync(start);
         Ok(())
     }
 
@@ -254,11 +339,10 @@ impl<E: Env> Manifest<E> {
         }
 
         let mut wait_remove_paths = Vec::new();
-        for path in self.env.read_dir(&self.base)? {
-            let file_path = path.unwrap().path();
+        for file_path in self.env.read_dir(&self.base)? {
             if let Some(ext) = file_path.extension() {
                 if ext.to_str().unwrap() == TEMPFILE_SUFFIX {
-                    wait_remove_paths.push(file_path.to_owned());
+                    wait_remove_paths.push(file_path);
                     continue;
                 }
             }
@@ -266,7 +350,7 @@ impl<E: Env> Manifest<E> {
                 file_path.file_name().unwrap().to_str().unwrap(),
                 self.current_file_num,
             ) {
-                wait_remove_paths.push(file_path.to_owned());
+                wait_remove_paths.push(file_path);
             }
         }
 
@@ -302,11 +386,17 @@ impl VersionEditEncoder {
 struct VersionEditDecoder<R: PositionalReader> {
     reader: R,
     offset: u64,
+    // The length of the file, which bounds the length of records.
+    len: u64,
 }
 
 impl<R: PositionalReader> VersionEditDecoder<R> {
-    fn new(reader: R) -> Self {
-        Self { reader, offset: 0 }
+    fn new(reader: R, len: u64) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            len,
+        }
     }
     async fn next_record(&mut self) -> Result<Option<VersionEdit>> {
         let mut offset = self.offset;
@@ -328,6 +418,9 @@ impl<R: PositionalReader> VersionEditDecoder<R> {
             )
         };
         offset += core::mem::size_of::<u64>() as u64;
+        if len > self.len.saturating_sub(offset) {
+            return Err(Error::Corrupted);
+        }
         let ve = {
             let mut ve_bytes = vec![0u8; len as usize];
             self.reader
@@ -340,7 +433,21 @@ impl<R: PositionalReader> VersionEditDecoder<R> {
     }
 }
 
-#[cfg(test)]
+/// Decodes all records from a manifest file of `len` bytes.
+#[cfg(feature = "fuzzing")]
+pub(crate) async fn decode_manifest<R: PositionalReader>(
+    reader: R,
+    len: u64,
+) -> Result<Vec<VersionEdit>> {
+    let mut decoder = VersionEditDecoder::new(reader, len);
+    let mut ves = Vec::new();
+    while let Some(ve) = decoder.next_record().await? {
+        ves.push(ve);
+    }
+    Ok(ves)
+}
+
+#[cfg(all(test, unix))]
 mod tests {
     use super::*;
     use crate::page_store::{NewFile, StreamEdit};
@@ -368,6 +475,7 @@ mod tests {
                             new_files: new_files(vec![2, 3]),
                             deleted_files: vec![1],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -381,6 +489,7 @@ mod tests {
                             new_files: new_files(vec![2, 3]),
                             deleted_files: vec![1],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -393,6 +502,7 @@ mod tests {
                             new_files: new_files(vec![2, 3]),
                             deleted_files: vec![1],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -434,6 +544,7 @@ mod tests {
                 new_files: vec![],
                 deleted_files: vec![],
             }),
+            metadata: Vec::new(),
         }));
 
         let ve_snapshot = || {
@@ -466,6 +577,7 @@ mod tests {
                     new_files: new_files(vec![0]),
                     deleted_files: vec![],
                 }),
+                metadata: Vec::new(),
             };
             manifest
                 .record_version_edit(ve.to_owned(), ve_snapshot)
@@ -481,6 +593,7 @@ mod tests {
                         new_files: new_files(vec![i]),
                         deleted_files: vec![r],
                     }),
+                    metadata: Vec::new(),
                 };
                 manifest
                     .record_version_edit(ve.to_owned(), ve_snapshot)
@@ -498,6 +611,7 @@ mod tests {
 
             let mut recover_ver = VersionEdit {
                 file_stream: Some(StreamEdit::default()),
+                metadata: Vec::new(),
             };
             for ve in versions {
                 let recover_ver = recover_ver.file_stream.as_mut().unwrap();
@@ -518,6 +632,7 @@ mod tests {
                     new_files: new_files(vec![1]),
                     deleted_files: vec![],
                 }),
+                metadata: Vec::new(),
             };
             manifest2
                 .record_version_edit(ve.to_owned(), ve_snapshot)
@@ -544,6 +659,7 @@ mod tests {
                             new_files: new_files(vec![2, 3]),
                             deleted_files: vec![1],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -556,6 +672,7 @@ mod tests {
                             new_files: new_files(vec![4]),
                             deleted_files: vec![],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -568,6 +685,7 @@ mod tests {
                             new_files: new_files(vec![5]),
                             deleted_files: vec![],
                         }),
+                        metadata: Vec::new(),
                     },
                     version_snapshot,
                 )
@@ -581,4 +699,43 @@ mod tests {
             assert_eq!(versions.len(), 4);
         }
     }
+
+    #[photonio::test]
+    fn test_metadata_after_roll() {
+        let env = crate::env::Photon;
+        let base = tempdir::TempDir::new("curr_test_metadata").unwrap();
+        let edit = |key: &str, value: Option<&[u8]>| VersionEdit {
+            file_stream: None,
+            metadata: vec![MetadataEdit {
+                key: key.into(),
+                value: value.map(|v| v.to_vec()),
+            }],
+        };
+
+        {
+            let mut manifest = Manifest::open(env.to_owned(), base.as_ref()).await.unwrap();
+            manifest.max_file_size = 1;
+            for ve in [
+                edit("a", Some(b"1")),
+                edit("b", Some(b"2")),
+                edit("a", None),
+                edit("b", Some(b"3")),
+            ] {
+                manifest
+                    .record_version_edit(ve, VersionEdit::default)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // Each edit rolls the manifest, so the entries are carried over.
+        let mut manifest = Manifest::open(env, base.as_ref()).await.unwrap();
+        let versions = manifest.list_versions().await.unwrap();
+        assert_eq!(versions.len(), 2);
+        manifest.recover_metadata(&versions);
+        assert_eq!(
+            manifest.metadata().iter().collect::<Vec<_>>(),
+            [(&"b".to_string(), &b"3".to_vec())]
+        );
+    }
 }
@@ -22,12 +22,26 @@ pub(crate) struct StreamEdit {
     pub deleted_files: Vec<u32>,
 }
 
+/// An edit of an entry in the metadata of the store.
+#[allow(unreachable_pub)]
+#[derive(Clone, PartialEq, Eq, Message)]
+pub(crate) struct MetadataEdit {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    /// The new value of the entry, or `None` if the entry is removed.
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub value: Option<Vec<u8>>,
+}
+
 #[allow(unreachable_pub)]
 #[derive(Clone, PartialEq, Message)]
 pub(crate) struct VersionEdit {
     /// A set of map files.
     #[prost(message, tag = "1")]
     pub file_stream: Option<StreamEdit>,
+    /// The edits of the metadata, which are applied in order.
+    #[prost(message, repeated, tag = "2")]
+    pub metadata: Vec<MetadataEdit>,
 }
 
 mod convert {
@@ -67,6 +81,16 @@ mod tests {
                 new_files,
                 deleted_files: vec![1, 2, 3],
             }),
+            metadata: vec![
+                MetadataEdit {
+                    key: "a".into(),
+                    value: Some(vec![1]),
+                },
+                MetadataEdit {
+                    key: "b".into(),
+                    value: None,
+                },
+            ],
         };
 
         let payload = edit.encode_to_vec();
@@ -1,39 +1,61 @@
-use std::{fmt, mem, path::Path, sync::Arc};
+use std::{collections::BTreeMap, fmt, mem, path::Path, sync::Arc};
 
-use crate::{env::Env, util::shutdown::ShutdownNotifier};
+use crate::{
+    env::Env,
+    listener::EventListener,
+    util::{json::Json, shutdown::ShutdownNotifier, trace::instrument},
+};
 
 mod error;
 pub(crate) use error::{Error, Result};
 
 mod page_txn;
-use futures::lock::Mutex;
-pub(crate) use page_txn::{CacheOption, Guard};
+use futures::{
+    channel::{mpsc, oneshot},
+    lock::Mutex,
+};
+pub(crate) use page_txn::{CacheOption, Guard, ReadStats};
 
 mod page_table;
 use page_table::PageTable;
 pub(crate) use page_table::{MIN_ID, NAN_ID};
 
 mod meta;
-pub(crate) use meta::{NewFile, StreamEdit, VersionEdit};
+pub(crate) use meta::{MetadataEdit, NewFile, StreamEdit, VersionEdit};
 
 mod version;
 use version::{DeltaVersion, Version, VersionOwner, VersionUpdateReason};
 
 mod jobs;
-use jobs::{cleanup::CleanupCtx, flush::FlushCtx, reclaim::ReclaimCtx};
+use jobs::{
+    cleanup::CleanupCtx,
+    flush::{FlushCtx, InlineFlusher},
+    reclaim::{ReclaimCtx, ReclaimRequest},
+};
 
 mod write_buffer;
 pub(crate) use write_buffer::{RecordRef, WriteBuffer};
 
 mod buffer_set;
+use buffer_set::BufferSet;
+
+mod watermark;
+pub(crate) use watermark::{
+    BufferedLsns, DurableCallback, LsnWatermark, PendingWrite, FLUSHED_LSN_METADATA,
+};
 
 mod manifest;
+#[cfg(feature = "fuzzing")]
+pub(crate) use manifest::decode_manifest;
 pub(crate) use manifest::Manifest;
 
 mod page_file;
-pub(crate) use page_file::{FileInfo, PageFiles, PageGroup};
+pub(crate) use page_file::{page_file_name, FileInfo, PageFiles, PageGroup};
 
 mod recover;
+
+mod lock;
+use lock::LockFile;
 mod strategy;
 pub(crate) use strategy::{MinDeclineRateStrategyBuilder, StrategyBuilder};
 
@@ -43,11 +65,20 @@ pub(crate) use cache::{clock::ClockCache, lru::LRUCache, Cache, CacheEntry, Cach
 
 mod stats;
 pub use page_file::{ChecksumType, Compression};
+pub(crate) use stats::CacheStats;
 pub use stats::StoreStats;
 
+mod verify;
+pub use verify::{ChecksumReport, CorruptedPage};
+
+mod analyze;
+pub use analyze::{FileSpace, PageKindSpace, SizeDistribution, SpaceReport, SIZE_BUCKETS};
+
+mod repair;
+
 use self::{
     jobs::wait_for_reclaiming,
-    stats::{AtomicJobStats, AtomicWritebufStats},
+    stats::{AtomicFileIoStats, AtomicJobStats, AtomicWritebufStats, IoStats, MemoryStats},
 };
 
 /// Options to configure a page store.
@@ -67,6 +98,23 @@ pub struct Options {
     /// Default: 8
     pub max_write_buffers: usize,
 
+    /// The max bytes of released write buffers that are waiting for the
+    /// epoch-based reclamation.
+    ///
+    /// Released write buffers are freed only after all threads have left the
+    /// epoch in which they are released. If the deferred bytes exceed this
+    /// limit, the releasing thread will try to advance the epoch and reclaim
+    /// them eagerly.
+    ///
+    /// Default: 256MB
+    pub max_deferred_bytes: u64,
+
+    /// The max number of attempts to advance the epoch once the deferred
+    /// bytes exceed `max_deferred_bytes`.
+    ///
+    /// Default: 8
+    pub epoch_advance_attempts: usize,
+
     /// If true, use O_DIRECT to read/write page files.
     ///
     /// Default: false
@@ -77,6 +125,29 @@ pub struct Options {
     /// Default: false
     pub disable_space_reclaiming: bool,
 
+    /// The max number of write buffers that are flushed together in the
+    /// background.
+    ///
+    /// There is one background flush job. It takes up to this number of
+    /// consecutive write buffers that are ready to flush, builds their page
+    /// files concurrently, and installs the files in order.
+    ///
+    /// If this is zero, there is no background flush, and write buffers are
+    /// flushed by the writes that fill them. This requires at least two write
+    /// buffers.
+    ///
+    /// Default: 1
+    pub max_background_flushes: usize,
+
+    /// If true, space is reclaimed by a background job.
+    ///
+    /// Otherwise, space is only reclaimed by manual requests, which run on the
+    /// caller. There is at most one reclaim job, since the files to compact
+    /// are picked in order.
+    ///
+    /// Default: true
+    pub reclaim_in_background: bool,
+
     /// The max percentage of the space amplification.
     ///
     /// The space amplification is defined as the amount (in percentage) of
@@ -159,6 +230,33 @@ pub struct Options {
     ///
     /// Default: false
     pub avoid_flush_during_shutdown: bool,
+
+    /// Opens the store even if the directory is locked.
+    ///
+    /// A store locks its directory while it is open, and opening a locked
+    /// directory fails with [`Error::Busy`]. The lock is released by the
+    /// operating system when the process exits, so a crashed process doesn't
+    /// leave it behind. This is only a last resort to open a directory whose
+    /// lock is held by a process that can't be stopped, and it is unsafe to
+    /// open the same directory from multiple processes with this.
+    ///
+    /// [`Error::Busy`]: crate::Error::Busy
+    ///
+    /// Default: false
+    pub force_unlock: bool,
+
+    /// Opens the store without modifying its directory.
+    ///
+    /// The directory is not locked, and no files are created, written, or
+    /// removed in it, so the store must exist. Pages are read as of the open,
+    /// and files removed by a store that writes the directory at the same
+    /// time fail the reads. Writes to the store fail with
+    /// [`Error::ReadOnly`].
+    ///
+    /// [`Error::ReadOnly`]: crate::Error::ReadOnly
+    ///
+    /// Default: false
+    pub read_only: bool,
 }
 
 impl Default for Options {
@@ -166,8 +264,12 @@ impl Default for Options {
         Self {
             write_buffer_capacity: 128 << 20,
             max_write_buffers: 8,
+            max_deferred_bytes: 256 << 20,
+            epoch_advance_attempts: 8,
             use_direct_io: false,
             disable_space_reclaiming: false,
+            max_background_flushes: 1,
+            reclaim_in_background: true,
             max_space_amplification_percent: 100,
             space_used_high: u64::MAX,
             file_base_size: 64 << 20,
@@ -180,10 +282,71 @@ impl Default for Options {
             compression_on_cold_compact: Compression::ZSTD,
             page_checksum_type: ChecksumType::NONE,
             avoid_flush_during_shutdown: false,
+            force_unlock: false,
+            read_only: false,
         }
     }
 }
 
+impl Options {
+    pub(crate) fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("write_buffer_capacity", self.write_buffer_capacity.into()),
+            ("max_write_buffers", self.max_write_buffers.into()),
+            ("max_deferred_bytes", self.max_deferred_bytes.into()),
+            ("epoch_advance_attempts", self.epoch_advance_attempts.into()),
+            ("use_direct_io", self.use_direct_io.into()),
+            (
+                "disable_space_reclaiming",
+                self.disable_space_reclaiming.into(),
+            ),
+            ("max_background_flushes", self.max_background_flushes.into()),
+            ("reclaim_in_background", self.reclaim_in_background.into()),
+            (
+                "max_space_amplification_percent",
+                self.max_space_amplification_percent.into(),
+            ),
+            ("space_used_high", self.space_used_high.into()),
+            ("file_base_size", self.file_base_size.into()),
+            ("cache_capacity", self.cache_capacity.into()),
+            (
+                "cache_estimated_entry_charge",
+                self.cache_estimated_entry_charge.into(),
+            ),
+            (
+                "cache_file_reader_capacity",
+                self.cache_file_reader_capacity.into(),
+            ),
+            (
+                "cache_strict_capacity_limit",
+                self.cache_strict_capacity_limit.into(),
+            ),
+            (
+                "prepopulate_cache_on_flush",
+                self.prepopulate_cache_on_flush.into(),
+            ),
+            (
+                "compression_on_flush",
+                format!("{:?}", self.compression_on_flush).into(),
+            ),
+            (
+                "compression_on_cold_compact",
+                format!("{:?}", self.compression_on_cold_compact).into(),
+            ),
+            (
+                "page_checksum_type",
+                format!("{:?}", self.page_checksum_type).into(),
+            ),
+            (
+                "avoid_flush_during_shutdown",
+                self.avoid_flush_during_shutdown.into(),
+            ),
+            ("force_unlock", self.force_unlock.into()),
+            ("read_only", self.read_only.into()),
+        ])
+    }
+}
+
 /// Options that control manual flush operations.
 #[derive(Clone, Debug)]
 pub struct FlushOptions {
@@ -208,6 +371,46 @@ impl Default for FlushOptions {
     }
 }
 
+/// A reader of the statistics of a page store.
+pub(crate) struct StatsReader<E: Env> {
+    table: PageTable,
+    version_owner: Arc<VersionOwner>,
+    page_files: Arc<PageFiles<E>>,
+    job_stats: Arc<AtomicJobStats>,
+    writebuf_stats: Arc<AtomicWritebufStats>,
+    manifest_io_stats: Arc<AtomicFileIoStats>,
+}
+
+impl<E: Env> StatsReader<E> {
+    pub(crate) fn stats(&self) -> StoreStats {
+        let (page_cache, file_reader_cache) = self.page_files.stats();
+        let writebuf = self.writebuf_stats.snapshot();
+        let jobs = self.job_stats.snapshot();
+        let buffer_set = self.version_owner.current().buffer_set.stats();
+        let page_table = self.table.stats();
+        let io = IoStats {
+            page_file: self.page_files.io_stats(),
+            manifest: self.manifest_io_stats.snapshot(),
+        };
+        let memory = MemoryStats {
+            page_cache: self.page_files.cache_usage() as u64,
+            write_buffers: self.version_owner.current().buffer_set.memory_usage() as u64,
+            deferred_write_buffers: buffer_set.unreclaimed_bytes(),
+            page_table: page_table.resident_bytes,
+        };
+        StoreStats {
+            page_cache,
+            file_reader_cache,
+            writebuf,
+            jobs,
+            buffer_set,
+            page_table,
+            io,
+            memory,
+        }
+    }
+}
+
 pub(crate) struct PageStore<E: Env> {
     options: Options,
     #[allow(unused)]
@@ -220,32 +423,70 @@ pub(crate) struct PageStore<E: Env> {
 
     job_stats: Arc<AtomicJobStats>,
     writebuf_stats: Arc<AtomicWritebufStats>,
+    manifest_io_stats: Arc<AtomicFileIoStats>,
 
     jobs: Vec<E::JoinHandle<()>>,
     shutdown: ShutdownNotifier,
+    event_listener: Option<Arc<dyn EventListener>>,
+    reclaim_requests: mpsc::UnboundedSender<ReclaimRequest>,
+    inline_flusher: Option<Arc<InlineFlusher<E>>>,
+    inline_reclaimer: Option<Mutex<ReclaimCtx<E>>>,
+    watermark: Arc<LsnWatermark>,
+    /// The metadata entries, including the ones not flushed yet.
+    metadata: parking_lot::Mutex<BTreeMap<String, Vec<u8>>>,
+
+    _lock: LockFile,
 }
 
 impl<E: Env> PageStore<E> {
-    pub(crate) async fn open<P>(env: E, path: P, options: Options) -> Result<Self>
+    pub(crate) async fn open<P>(
+        env: E,
+        path: P,
+        options: Options,
+        event_listener: Option<Arc<dyn EventListener>>,
+    ) -> Result<Self>
     where
         P: AsRef<Path>,
     {
+        let lock = if options.read_only {
+            // Nothing in the directory is modified, so others can't be broken.
+            LockFile::none()
+        } else {
+            match env.create_dir_all(path.as_ref()).await {
+                Err(err) if err.kind() != std::io::ErrorKind::AlreadyExists => {
+                    return Err(err.into())
+                }
+                _ => {}
+            }
+            LockFile::acquire(&env, path.as_ref(), options.force_unlock)?
+        };
         let (next_page_file_id, manifest, table, page_files, delta) =
-            Self::recover(env.to_owned(), path, &options).await?;
+            instrument!(Self::recover(env.to_owned(), path, &options), "recover").await?;
 
-        let version = Version::new(
-            options.write_buffer_capacity,
+        let buffer_set = BufferSet::new(
             next_page_file_id,
+            options.write_buffer_capacity,
             options.max_write_buffers,
-            delta,
-        );
+        )
+        .with_deferred_limit(options.max_deferred_bytes, options.epoch_advance_attempts)
+        .with_event_listener(event_listener.clone());
+        let version = Version::with_buffer_set(next_page_file_id, Arc::new(buffer_set), delta);
 
         let version_owner = Arc::new(VersionOwner::new(version));
+        let manifest_io_stats = manifest.io_stats();
+        // The LSNs of the flushed writes are recovered from the manifest.
+        let watermark = Arc::new(LsnWatermark::default());
+        if let Some(value) = manifest.metadata().get(FLUSHED_LSN_METADATA) {
+            let value = value.as_slice().try_into().map_err(|_| Error::Corrupted)?;
+            watermark.advance(u64::from_be_bytes(value));
+        }
+        let metadata = parking_lot::Mutex::new(manifest.metadata().clone());
         let manifest = Arc::new(futures::lock::Mutex::new(manifest));
         let page_files = Arc::new(page_files);
         let shutdown = ShutdownNotifier::new();
         let job_stats = Arc::default();
         let writebuf_stats = Arc::default();
+        let (reclaim_requests, reclaim_receiver) = mpsc::unbounded();
 
         let mut store = PageStore {
             options,
@@ -256,14 +497,38 @@ impl<E: Env> PageStore<E> {
             manifest,
             job_stats,
             writebuf_stats,
+            manifest_io_stats,
             jobs: Vec::new(),
             shutdown,
+            event_listener,
+            reclaim_requests,
+            inline_flusher: None,
+            inline_reclaimer: None,
+            watermark,
+            metadata,
+            _lock: lock,
         };
 
-        // Spawn background jobs.
-        store.spawn_flush_job();
+        // A read-only store has nothing to flush or reclaim.
+        if store.options.read_only {
+            return Ok(store);
+        }
+
+        // Spawn background jobs, or run them on the callers if there are no
+        // background jobs of the kind.
+        if store.options.max_background_flushes > 0 {
+            store.spawn_flush_job();
+        } else {
+            let flusher = InlineFlusher::new(store.new_flush_ctx());
+            store.inline_flusher = Some(Arc::new(flusher));
+        }
         store.spawn_cleanup_job();
-        store.spawn_reclaim_job();
+        if store.options.reclaim_in_background {
+            store.spawn_reclaim_job(reclaim_receiver);
+        } else {
+            let reclaimer = store.new_reclaim_ctx(reclaim_receiver);
+            store.inline_reclaimer = Some(Mutex::new(reclaimer));
+        }
 
         Ok(store)
     }
@@ -276,19 +541,24 @@ impl<E: Env> PageStore<E> {
             self.page_files.clone(),
             self.writebuf_stats.clone(),
         )
+        .with_flusher(self.inline_flusher.clone())
+        .with_watermark(self.watermark.clone())
+        .with_read_only(self.options.read_only)
     }
 
     pub(crate) fn stats(&self) -> StoreStats {
-        let (page_cache, file_reader_cache) = self.page_files.stats();
-        let writebuf = self.writebuf_stats.snapshot();
-        let jobs = self.job_stats.snapshot();
-        let buffer_set = self.version().buffer_set.stats();
-        StoreStats {
-            page_cache,
-            file_reader_cache,
-            writebuf,
-            jobs,
-            buffer_set,
+        self.stats_reader().stats()
+    }
+
+    /// Returns a reader of the statistics that doesn't keep the store open.
+    pub(crate) fn stats_reader(&self) -> StatsReader<E> {
+        StatsReader {
+            table: self.table.clone(),
+            version_owner: self.version_owner.clone(),
+            page_files: self.page_files.clone(),
+            job_stats: self.job_stats.clone(),
+            writebuf_stats: self.writebuf_stats.clone(),
+            manifest_io_stats: self.manifest_io_stats.clone(),
         }
     }
 
@@ -298,12 +568,190 @@ impl<E: Env> PageStore<E> {
         for job in jobs {
             job.await;
         }
+        if let Some(flusher) = self.inline_flusher.take() {
+            flusher.shutdown().await;
+        }
+    }
+
+    /// Changes the capacity of the page cache.
+    pub(crate) fn set_cache_capacity(&self, capacity: usize) {
+        self.page_files.set_cache_capacity(capacity);
+    }
+
+    /// Changes the max bytes of released write buffers that are waiting for
+    /// reclamation.
+    pub(crate) fn set_max_deferred_bytes(&self, max_deferred_bytes: u64) {
+        self.version()
+            .buffer_set
+            .set_max_deferred_bytes(max_deferred_bytes);
+    }
+
+    /// Returns the watermark of the LSNs flushed to page files.
+    pub(crate) fn watermark(&self) -> &LsnWatermark {
+        &self.watermark
+    }
+
+    /// Returns the LSN that the writes at or below it are durable.
+    pub(crate) fn durable_lsn(&self) -> u64 {
+        let version = self.version();
+        self.watermark.durable_lsn(|| version.min_buffered_lsn())
+    }
+
+    /// Registers a callback that is invoked once writes at or below the LSN
+    /// are durable.
+    pub(crate) fn on_durable(&self, lsn: u64, callback: DurableCallback) {
+        let version = self.version();
+        self.watermark
+            .register(lsn, callback, || version.min_buffered_lsn());
+    }
+
+    /// Returns the value of a metadata entry.
+    ///
+    /// Metadata entries are kept outside of the pages, and are recorded to the
+    /// manifest when the write buffers they are attached to are flushed.
+    pub(crate) fn metadata(&self, key: &str) -> Option<Vec<u8>> {
+        self.metadata.lock().get(key).cloned()
+    }
+
+    /// Returns the metadata entries whose keys start with the prefix, in the
+    /// order of the keys.
+    pub(crate) fn metadata_with_prefix(&self, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        let metadata = self.metadata.lock();
+        metadata
+            .range(prefix.to_owned()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Sets a metadata entry, or removes it if the value is `None`.
+    ///
+    /// The edit is attached to the active write buffer, so it becomes durable
+    /// once the writes made before it are durable, and never before them.
+    pub(crate) async fn put_metadata(&self, key: &str, value: Option<Vec<u8>>) -> Result<()> {
+        self.attach_metadata(key, value).await?;
+        Ok(())
+    }
+
+    /// Like [`Self::put_metadata`], but returns after the edit is durable.
+    ///
+    /// This flushes the active write buffer.
+    pub(crate) async fn put_metadata_durably(
+        &self,
+        key: &str,
+        value: Option<Vec<u8>>,
+    ) -> Result<()> {
+        let buffer = self.attach_metadata(key, value).await?;
+        self.flush(&FlushOptions::default()).await;
+        // The buffer may have been sealed by others, and is flushed before the
+        // active one.
+        buffer.wait_flushed().await;
+        Ok(())
+    }
+
+    /// Attaches a metadata edit to the active write buffer, and returns the
+    /// buffer.
+    ///
+    /// Returns [`Error::ReadOnly`] if the store is read-only.
+    async fn attach_metadata(&self, key: &str, value: Option<Vec<u8>>) -> Result<Arc<WriteBuffer>> {
+        if self.options.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let edit = MetadataEdit {
+            key: key.to_owned(),
+            value,
+        };
+        let version = self.version();
+        let buffer_set = &version.buffer_set;
+        loop {
+            let group_id = buffer_set.acquire_active_buffer_id().await;
+            let buffers = buffer_set.current();
+            let Some(buffer) = buffers.get(group_id) else {
+                continue;
+            };
+            // The lock keeps the entries in the order of the edits attached.
+            let mut metadata = self.metadata.lock();
+            if buffer.add_metadata(edit.clone()).is_err() {
+                continue;
+            }
+            match edit.value {
+                Some(value) => metadata.insert(edit.key, value),
+                None => metadata.remove(&edit.key),
+            };
+            return Ok(buffer.clone());
+        }
+    }
+
+    /// Returns the bytes of pages in write buffers that are not flushed yet.
+    pub(crate) fn dirty_bytes(&self) -> u64 {
+        self.version().buffer_set.current().dirty_bytes() as u64
+    }
+
+    /// Returns the bytes of obsolete pages in page files, which can be
+    /// reclaimed by compactions.
+    pub(crate) fn garbage_bytes(&self) -> u64 {
+        let version = self.version();
+        let used_size: usize = version
+            .file_infos()
+            .values()
+            .map(|info| info.meta().file_size)
+            .sum();
+        let effective_size: usize = version
+            .page_groups()
+            .values()
+            .map(PageGroup::effective_size)
+            .sum();
+        used_size.saturating_sub(effective_size) as u64
+    }
+
+    /// Returns the state of page files for debugging.
+    pub(crate) fn files_json(&self) -> Json {
+        let version = self.version();
+        let mut files: Vec<_> = version.file_infos().values().collect();
+        files.sort_unstable_by_key(|info| info.meta().file_id);
+        let files = files
+            .into_iter()
+            .map(|info| {
+                let meta = info.meta();
+                let mut groups: Vec<_> = meta.page_groups.keys().copied().collect();
+                groups.sort_unstable();
+                let effective_size: usize = groups
+                    .iter()
+                    .filter_map(|id| version.page_groups().get(id))
+                    .map(PageGroup::effective_size)
+                    .sum();
+                Json::Object(vec![
+                    ("file_id", meta.file_id.into()),
+                    ("file_size", meta.file_size.into()),
+                    ("effective_size", effective_size.into()),
+                    ("page_groups", groups.into()),
+                    ("up1", info.up1().into()),
+                    ("up2", info.up2().into()),
+                ])
+            })
+            .collect();
+        Json::Array(files)
     }
 
     /// Flush the active write buffer if it is not empty.
-    #[inline]
+    ///
+    /// If there is no background flush, the write buffers are flushed here,
+    /// regardless of [`FlushOptions::wait`].
     pub(crate) async fn flush(&self, opts: &FlushOptions) {
-        self.version().buffer_set.flush_active_buffer(opts).await
+        let version = self.version();
+        let buffer_set = &version.buffer_set;
+        let Some(flusher) = &self.inline_flusher else {
+            return buffer_set.flush_active_buffer(opts).await;
+        };
+        // Flushes the sealed buffers first, so that the active buffer can be
+        // sealed without stalling.
+        flusher.flush_ready_and_wait().await;
+        let opts = FlushOptions {
+            wait: false,
+            ..opts.clone()
+        };
+        buffer_set.flush_active_buffer(&opts).await;
+        flusher.flush_ready_and_wait().await;
     }
 
     /// Wait all pending reclaiming to finish.
@@ -312,20 +760,42 @@ impl<E: Env> PageStore<E> {
         wait_for_reclaiming(&self.options, self.version()).await;
     }
 
+    /// Compacts all page files that contain obsolete pages, and waits until it
+    /// is done.
+    ///
+    /// This runs in the reclaim job, even if space reclaiming is disabled.
+    pub(crate) async fn reclaim_all(&self) -> Result<()> {
+        if let Some(reclaimer) = &self.inline_reclaimer {
+            return reclaimer.lock().await.reclaim_all().await;
+        }
+        let (tx, rx) = oneshot::channel();
+        if self.reclaim_requests.unbounded_send(tx).is_err() {
+            return Ok(());
+        }
+        // The request is dropped if the store is shutting down.
+        rx.await.unwrap_or(Ok(()))
+    }
+
     #[inline]
     fn version(&self) -> Arc<Version> {
         self.version_owner.current()
     }
 
-    fn spawn_flush_job(&mut self) {
-        let job = FlushCtx::new(
+    fn new_flush_ctx(&self) -> FlushCtx<E> {
+        FlushCtx::new(
             self.options.clone(),
             self.shutdown.subscribe(),
             self.job_stats.clone(),
             self.version_owner.clone(),
             self.page_files.clone(),
             self.manifest.clone(),
-        );
+            self.event_listener.clone(),
+        )
+        .with_watermark(self.watermark.clone())
+    }
+
+    fn spawn_flush_job(&mut self) {
+        let job = self.new_flush_ctx();
         let handle = self.env.spawn_background(job.run());
         self.jobs.push(handle);
     }
@@ -336,17 +806,23 @@ impl<E: Env> PageStore<E> {
         self.jobs.push(handle);
     }
 
-    fn spawn_reclaim_job(&mut self) {
+    fn new_reclaim_ctx(&self, requests: mpsc::UnboundedReceiver<ReclaimRequest>) -> ReclaimCtx<E> {
         let strategy_builder = Box::new(MinDeclineRateStrategyBuilder);
-        let job = ReclaimCtx::new(
+        ReclaimCtx::new(
             self.options.clone(),
             self.shutdown.subscribe(),
             strategy_builder,
             self.page_files.clone(),
             self.version_owner.clone(),
             self.manifest.clone(),
+            requests,
             self.job_stats.clone(),
-        );
+            self.event_listener.clone(),
+        )
+    }
+
+    fn spawn_reclaim_job(&mut self, requests: mpsc::UnboundedReceiver<ReclaimRequest>) {
+        let job = self.new_reclaim_ctx(requests);
         let handle = self.env.spawn_background(job.run(self.version()));
         self.jobs.push(handle);
     }
@@ -52,7 +52,9 @@ pub(crate) fn decompress_len(
         }
         Compression::ZSTD => {
             let raw_len = u64::from_le_bytes(
-                input[..std::mem::size_of::<u64>()]
+                input
+                    .get(..std::mem::size_of::<u64>())
+                    .ok_or(Error::Corrupted)?
                     .try_into()
                     .map_err(|_| Error::Corrupted)?,
             );
@@ -1,4 +1,4 @@
-use std::{alloc::Layout, collections::BTreeMap, marker::PhantomData, sync::Arc};
+use std::{alloc::Layout, collections::BTreeMap, marker::PhantomData, sync::Arc, time::Instant};
 
 use super::{
     checksum,
@@ -8,7 +8,7 @@ use super::{
 use crate::{
     env::{Directory, Env, SequentialWriter, SequentialWriterExt},
     page::PageInfo,
-    page_store::{Error, Result},
+    page_store::{stats::AtomicFileIoStats, Error, Result},
 };
 
 pub(crate) struct CommonFileBuilder {
@@ -270,7 +270,11 @@ impl IndexBlock {
                     .map_err(|_| Error::Corrupted)?,
             );
             if let Some((addr, last_offset, meta, next)) = last_offset {
-                let size = (offset - last_offset) as usize;
+                // Pages are encoded in the order of addresses and offsets.
+                if page_addr <= addr {
+                    return Err(Error::Corrupted);
+                }
+                let size = offset.checked_sub(last_offset).ok_or(Error::Corrupted)? as usize;
                 page_offsets.insert(addr, (last_offset, PageInfo::from_raw(meta, next, size)));
             }
             last_offset = Some((page_addr, offset, meta, next));
@@ -285,8 +289,15 @@ impl IndexBlock {
                 .try_into()
                 .map_err(|_| Error::Corrupted)?,
         ));
+        // Offsets of pages are stored in `u32`.
+        if meta_page_table.unwrap() > u32::MAX as u64 {
+            return Err(Error::Corrupted);
+        }
         if let Some((addr, offset, meta, next)) = last_offset {
-            let size = (meta_page_table.unwrap() - offset) as usize;
+            let size = meta_page_table
+                .unwrap()
+                .checked_sub(offset)
+                .ok_or(Error::Corrupted)? as usize;
             page_offsets.insert(addr, (offset, PageInfo::from_raw(meta, next, size)));
         }
         Ok(Self {
@@ -310,6 +321,7 @@ impl IndexBlock {
 pub(super) struct BufferedWriter<'a, E: Env> {
     file: E::SequentialWriter,
     base_dir: &'a E::Directory,
+    io_stats: &'a AtomicFileIoStats,
 
     use_direct: bool,
 
@@ -329,11 +341,13 @@ impl<'a, E: Env> BufferedWriter<'a, E> {
         use_direct: bool,
         align_size: usize,
         base_dir: &'a E::Directory,
+        io_stats: &'a AtomicFileIoStats,
     ) -> Self {
         let buffer = AlignBuffer::new(io_batch_size, align_size);
         Self {
             file,
             base_dir,
+            io_stats,
             use_direct,
             next_page_offset: 0,
             actual_data_size: 0,
@@ -399,6 +413,7 @@ impl<'a, E: Env> BufferedWriter<'a, E> {
         self.file
             .write_all(&self.buffer.as_bytes()[..self.buf_pos])
             .await?;
+        self.io_stats.on_write(self.buf_pos);
         self.buf_pos = 0;
         Ok(())
     }
@@ -409,8 +424,12 @@ impl<'a, E: Env> BufferedWriter<'a, E> {
             self.file.truncate(self.actual_data_size as u64).await?;
         }
         // panic when sync fail, https://wiki.postgresql.org/wiki/Fsync_Errors
+        let start = Instant::now();
         self.file.sync_all().await.expect("sync file fail");
+        self.io_stats.on_sync(start);
+        let start = Instant::now();
         self.base_dir.sync_all().await.expect("sync base dir fail");
+        self.io_stats.on_sync(start);
         Ok(())
     }
 
@@ -492,7 +511,7 @@ pub(crate) fn is_block_aligned_ptr(p: *const u8, align: usize) -> bool {
     p.is_aligned_to(align)
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
 
     use super::*;
@@ -516,12 +535,23 @@ mod tests {
                 .open_sequential_writer(path1.to_owned())
                 .await
                 .expect("open file_id: {file_id}'s file fail");
-            let mut bw1 =
-                BufferedWriter::<crate::env::Photon>::new(file1, 4096 + 1, use_direct, 512, &base);
+            let io_stats = AtomicFileIoStats::default();
+            let mut bw1 = BufferedWriter::<crate::env::Photon>::new(
+                file1,
+                4096 + 1,
+                use_direct,
+                512,
+                &base,
+                &io_stats,
+            );
             bw1.write(&[1].repeat(10)).await.unwrap(); // only fill buffer
             bw1.write(&[2].repeat(4096 * 2 + 1)).await.unwrap(); // trigger flush
             bw1.write(&[3].repeat(4096 * 2 + 1)).await.unwrap(); // trigger flush
             bw1.flush_and_sync().await.unwrap(); // flush again
+            let stats = io_stats.snapshot();
+            assert!(stats.writes > 1);
+            assert!(stats.write_bytes >= 10 + (4096 * 2 + 1) * 2);
+            assert_eq!(stats.syncs, 2);
         }
         {
             let file2 = env
@@ -1,7 +1,9 @@
+use std::sync::Arc;
+
 use super::file_builder::*;
 use crate::{
     env::{PositionalReader, PositionalReaderExt},
-    page_store::Result,
+    page_store::{stats::AtomicFileIoStats, Error, Result},
     util::atomic::Counter,
 };
 
@@ -11,28 +13,43 @@ pub(crate) struct FileReader<R: PositionalReader> {
     pub(super) align_size: usize,
     pub(super) file_size: usize,
     read_bytes: Counter,
+    io_stats: Arc<AtomicFileIoStats>,
 }
 
 impl<R: PositionalReader> FileReader<R> {
     /// Open page reader.
-    pub(super) fn from(reader: R, use_direct: bool, align_size: usize, file_size: usize) -> Self {
+    pub(super) fn from(
+        reader: R,
+        use_direct: bool,
+        align_size: usize,
+        file_size: usize,
+        io_stats: Arc<AtomicFileIoStats>,
+    ) -> Self {
         Self {
             reader,
             use_direct,
             align_size,
             file_size,
             read_bytes: Counter::new(0),
+            io_stats,
         }
     }
 
     /// Reads the exact number of bytes from the page specified by `offset`.
+    ///
+    /// Returns [`Error::Corrupted`] if the range exceeds the end of the file.
     pub(crate) async fn read_exact_at(&self, buf: &mut [u8], req_offset: u64) -> Result<()> {
         if buf.is_empty() {
             return Ok(());
         }
+        match req_offset.checked_add(buf.len() as u64) {
+            Some(end) if end <= self.file_size as u64 => {}
+            _ => return Err(Error::Corrupted),
+        }
         if !self.use_direct {
             self.reader.read_exact_at(buf, req_offset).await?;
             self.read_bytes.add(buf.len() as u64);
+            self.io_stats.on_read(buf.len());
             return Ok(());
         }
 
@@ -46,6 +63,7 @@ impl<R: PositionalReader> FileReader<R> {
 
         self.inner_read_exact_at(&self.reader, read_buf, align_offset as u64)
             .await?;
+        self.io_stats.on_read(align_buf_size);
 
         buf.copy_from_slice(&read_buf[offset_ahead..offset_ahead + buf.len()]);
         self.read_bytes.add(buf.len() as u64);
@@ -80,6 +98,11 @@ impl<R: PositionalReader> FileReader<R> {
     }
 
     pub(crate) async fn read_block(&self, block_handle: BlockHandle) -> Result<Vec<u8>> {
+        // Checks the range before allocating, since the handle may be corrupted.
+        match block_handle.offset.checked_add(block_handle.length) {
+            Some(end) if end <= self.file_size as u64 => {}
+            _ => return Err(Error::Corrupted),
+        }
         let mut buf = vec![0u8; block_handle.length as usize];
         self.read_exact_at(&mut buf, block_handle.offset).await?;
         Ok(buf)
@@ -16,7 +16,7 @@ use super::{
 use crate::{
     env::Env,
     page::PageInfo,
-    page_store::{Error, Result},
+    page_store::{stats::AtomicFileIoStats, Error, Result},
 };
 
 /// Builder for file.
@@ -78,16 +78,25 @@ pub(crate) struct Footer {
 }
 
 impl<'a, E: Env> FileBuilder<'a, E> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         file_id: u32,
         base_dir: &'a E::Directory,
+        io_stats: &'a AtomicFileIoStats,
         file: E::SequentialWriter,
         use_direct: bool,
         block_size: usize,
         compression: Compression,
         checksum: ChecksumType,
     ) -> Self {
-        let writer = BufferedWriter::new(file, IO_BUFFER_SIZE, use_direct, block_size, base_dir);
+        let writer = BufferedWriter::new(
+            file,
+            IO_BUFFER_SIZE,
+            use_direct,
+            block_size,
+            base_dir,
+            io_stats,
+        );
         Self {
             file_id,
             writer,
@@ -333,8 +342,18 @@ impl Footer {
         let end = idx + BlockHandle::encoded_size();
         let dealloc_pages_handle = BlockHandle::decode(&bytes[idx..end])?;
 
+        // Only a single method can be used by a file.
         let compression = Compression::from_bits(bytes[end]).ok_or(Error::Corrupted)?;
+        if !matches!(
+            compression,
+            Compression::NONE | Compression::SNAPPY | Compression::ZSTD
+        ) {
+            return Err(Error::Corrupted);
+        }
         let checksum_type = ChecksumType::from_bits(bytes[end + 1]).ok_or(Error::Corrupted)?;
+        if !matches!(checksum_type, ChecksumType::NONE | ChecksumType::CRC32) {
+            return Err(Error::Corrupted);
+        }
 
         Ok(Self {
             magic,
@@ -346,7 +365,7 @@ impl Footer {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use super::*;
     use crate::env::Env;
@@ -7,7 +7,7 @@ mod file_reader;
 pub(crate) use file_reader::FileReader;
 
 mod types;
-pub(crate) use facade::PageFiles;
+pub(crate) use facade::{page_file_name, PageFiles};
 pub(crate) use types::{FileInfo, PageGroup, PageGroupMeta};
 
 mod map_file_builder;
@@ -34,7 +34,10 @@ pub(crate) mod constant {
 }
 
 pub(crate) mod facade {
-    use std::{path::PathBuf, sync::Arc};
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
 
     use super::{
         cache::FileReaderCache,
@@ -45,10 +48,10 @@ pub(crate) mod facade {
     };
     use crate::{
         env::{Env, PositionalReader, SequentialWriter},
-        page::PageRef,
+        page::check_page,
         page_store::{
             page_txn::{CacheOption, CachePriority},
-            stats::CacheStats,
+            stats::{AtomicFileIoStats, CacheStats, FileIoStats},
             Cache, CacheEntry, Error, LRUCache, Result,
         },
         PageStoreOptions,
@@ -56,6 +59,11 @@ pub(crate) mod facade {
 
     pub(crate) const FILE_PREFIX: &str = "map";
 
+    /// Returns the name of the page file in the table directory.
+    pub(crate) fn page_file_name(file_id: u32) -> String {
+        format!("{}_{file_id}", FILE_PREFIX)
+    }
+
     /// The facade for page_file module.
     /// it hides the detail about disk location for caller(after it be created).
     pub(crate) struct PageFiles<E: Env> {
@@ -68,6 +76,7 @@ pub(crate) mod facade {
 
         reader_cache: cache::FileReaderCache<E>,
         page_cache: Arc<LRUCache<Vec<u8>>>,
+        io_stats: Arc<AtomicFileIoStats>,
     }
 
     impl<E: Env> PageFiles<E> {
@@ -92,6 +101,7 @@ pub(crate) mod facade {
                 prepopulate_cache_on_flush,
                 reader_cache,
                 page_cache,
+                io_stats: Arc::default(),
             })
         }
 
@@ -103,12 +113,13 @@ pub(crate) mod facade {
             checksum: ChecksumType,
         ) -> Result<FileBuilder<E>> {
             // TODO: switch to env in suitable time.
-            let path = self.base.join(format!("{}_{file_id}", FILE_PREFIX));
+            let path = self.base.join(page_file_name(file_id));
             let writer = self.env.open_sequential_writer(path.to_owned()).await?;
             let use_direct = self.use_direct && writer.direct_io_ify().is_ok();
             Ok(FileBuilder::new(
                 file_id,
                 &self.base_dir,
+                &self.io_stats,
                 writer,
                 use_direct,
                 DEFAULT_BLOCK_SIZE,
@@ -131,11 +142,12 @@ pub(crate) mod facade {
 
             let buf = self.read_file_page(file_id, file_meta, handle).await?;
 
-            let is_inner = {
-                let page =
-                    PageRef::new(unsafe { std::slice::from_raw_parts(buf.as_ptr(), buf.len()) });
-                page.tier().is_inner()
-            };
+            // Corrupted pages are rejected before they are cached and decoded.
+            let page = check_page(&buf).ok_or(Error::CorruptedPage {
+                file_id,
+                offset: handle.offset as u64,
+            })?;
+            let is_inner = page.tier().is_inner();
             hint = hint.set_priority(if is_inner {
                 CachePriority::High
             } else {
@@ -147,6 +159,14 @@ pub(crate) mod facade {
             Ok((cache_entry.unwrap(), false))
         }
 
+        /// Returns the cached page of the address, if it is in the page cache.
+        pub(crate) fn lookup_page(
+            &self,
+            addr: u64,
+        ) -> Option<CacheEntry<Vec<u8>, LRUCache<Vec<u8>>>> {
+            self.page_cache.lookup(addr)
+        }
+
         pub(crate) async fn read_file_page(
             &self,
             file_id: u32,
@@ -155,11 +175,19 @@ pub(crate) mod facade {
         ) -> Result<Vec<u8>> {
             const CHECKSUM_LEN: usize = std::mem::size_of::<u32>();
 
+            let corrupted = || Error::CorruptedPage {
+                file_id,
+                offset: handle.offset as u64,
+            };
+            if handle.offset as usize + handle.size as usize > file_meta.file_size {
+                return Err(corrupted());
+            }
             let reader = self.open_page_reader(file_id, file_meta.block_size).await?;
 
             let mut buf = vec![0u8; handle.size as usize]; // TODO: aligned buffer pool
             self.read_file_page_from_reader(&reader, file_meta, handle, &mut buf)
-                .await?;
+                .await
+                .map_err(|err| if err.is_corrupted() { corrupted() } else { err })?;
             Ok(buf)
         }
 
@@ -175,6 +203,9 @@ pub(crate) mod facade {
             reader.read_exact_at(output, handle.offset as u64).await?;
 
             if file_meta.checksum_type != ChecksumType::NONE {
+                if output.len() < CHECKSUM_LEN {
+                    return Err(Error::Corrupted);
+                }
                 let checksum = u32::from_le_bytes(
                     output[output.len() - CHECKSUM_LEN..output.len()]
                         .try_into()
@@ -216,6 +247,7 @@ pub(crate) mod facade {
                         use_direct,
                         block_size,
                         file_size as usize,
+                        self.io_stats.clone(),
                     )))
                 })
                 .await
@@ -228,6 +260,7 @@ pub(crate) mod facade {
                 true,
                 DEFAULT_BLOCK_SIZE,
                 file_size as usize,
+                self.io_stats.clone(),
             ));
             FileMetaHolder::read(file_id, page_file_reader).await
         }
@@ -250,8 +283,18 @@ pub(crate) mod facade {
             }
         }
 
+        /// Moves the file to the directory, so that it is no longer read.
+        pub(crate) async fn quarantine_file(&self, file_id: u32, dir: &Path) -> Result<()> {
+            let name = page_file_name(file_id);
+            self.env
+                .rename(self.base.join(&name), dir.join(&name))
+                .await?;
+            self.reader_cache.invalidate(file_id);
+            Ok(())
+        }
+
         async fn remove_file(&self, file_id: u32) {
-            let path = self.base.join(format!("{}_{file_id}", FILE_PREFIX));
+            let path = self.base.join(page_file_name(file_id));
             let _ = self.env.remove_file(&path).await;
         }
 
@@ -281,23 +324,22 @@ pub(crate) mod facade {
         }
 
         pub(crate) fn list_files(&self) -> Result<Vec<u32>> {
-            let prefix = format!("{}_", FILE_PREFIX).into_bytes();
+            let prefix = format!("{}_", FILE_PREFIX);
             self.list_files_with_prefix(&prefix)
         }
 
-        fn list_files_with_prefix(&self, prefix: &[u8]) -> Result<Vec<u32>> {
-            use std::os::unix::ffi::OsStrExt;
-
+        fn list_files_with_prefix(&self, prefix: &str) -> Result<Vec<u32>> {
             let dir = self.env.read_dir(&self.base)?;
             let mut files = Vec::default();
             for entry in dir {
-                let file_name = entry?.file_name();
-                let bytes = file_name.as_bytes();
-                if !bytes.starts_with(prefix) {
+                let Some(file_name) = entry.file_name() else {
                     continue;
-                }
-                if let Ok(file_id) = String::from_utf8_lossy(&bytes[prefix.len()..]).parse::<u32>()
-                {
+                };
+                let file_name = file_name.to_string_lossy();
+                let Some(suffix) = file_name.strip_prefix(prefix) else {
+                    continue;
+                };
+                if let Ok(file_id) = suffix.parse::<u32>() {
                     files.push(file_id);
                 }
             }
@@ -305,14 +347,26 @@ pub(crate) mod facade {
             Ok(files)
         }
 
+        pub(crate) fn cache_usage(&self) -> usize {
+            self.page_cache.usage()
+        }
+
+        pub(crate) fn set_cache_capacity(&self, capacity: usize) {
+            self.page_cache.set_capacity(capacity);
+        }
+
         pub(crate) fn stats(&self) -> (CacheStats, CacheStats) {
             let page_cache = self.page_cache.stats();
             let table_cache = self.reader_cache.stats();
             (page_cache, table_cache)
         }
+
+        pub(crate) fn io_stats(&self) -> FileIoStats {
+            self.io_stats.snapshot()
+        }
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, unix))]
     mod tests {
         use tempdir::TempDir;
 
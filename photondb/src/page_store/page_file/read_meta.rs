@@ -3,6 +3,7 @@ use std::{collections::BTreeMap, sync::Arc};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use super::{
+    constant::FILE_MAGIC,
     file_builder::IndexBlock,
     file_reader::FileReader,
     map_file_builder::{Footer, PageIndex},
@@ -38,6 +39,10 @@ impl FileMetaHolder {
         let mut offset = 0;
         for page_index in &page_indexes {
             let index_block = Self::read_page_group_index_block(&reader, page_index).await?;
+            // The page table is followed by the index block.
+            if index_block.meta_page_table.unwrap() > page_index.data_handle.offset {
+                return Err(Error::Corrupted);
+            }
             let (indexes, offsets) = index_block.as_meta_file_cached(page_index.data_handle);
             let file_meta =
                 PageGroupMeta::new(page_index.file_id, file_id, offset, indexes, offsets);
@@ -104,7 +109,11 @@ impl FileMetaHolder {
         let footer_offset = (file_size - Footer::encoded_size()) as u64;
         let mut buf = vec![0u8; Footer::encoded_size() as usize];
         reader.read_exact_at(&mut buf, footer_offset).await?;
-        Footer::decode(&buf)
+        let footer = Footer::decode(&buf)?;
+        if footer.magic != FILE_MAGIC {
+            return Err(Error::Corrupted);
+        }
+        Ok(footer)
     }
 
     /// Read [`PageIndex`] of the corresponding file, according to the file
@@ -116,6 +125,9 @@ impl FileMetaHolder {
         const RECORD_SIZE: usize = PageIndex::encoded_size();
 
         let handle = footer.page_index_handle;
+        if handle.length as usize % RECORD_SIZE != 0 {
+            return Err(Error::Corrupted);
+        }
         let mut buf = reader.read_block(handle).await?;
         let mut buf = buf.as_mut_slice();
         let mut indexes = Vec::default();
@@ -134,6 +146,9 @@ impl FileMetaHolder {
         footer: &Footer,
     ) -> Result<Vec<u64>> {
         let handle = footer.dealloc_pages_handle;
+        if handle.length as usize % core::mem::size_of::<u64>() != 0 {
+            return Err(Error::Corrupted);
+        }
         let mut buf = reader.read_block(handle).await?;
         let mut buf = buf.as_mut_slice();
         let mut dealloc_pages = Vec::default();
@@ -128,7 +128,7 @@ impl PageGroup {
         &self.meta
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, unix))]
     pub(crate) fn is_page_active(&self, page_addr: u64) -> bool {
         self.meta
             .get_page_handle(page_addr)
@@ -1,16 +1,27 @@
 use std::{
-    mem::MaybeUninit,
+    mem::{size_of, MaybeUninit},
     ptr::null_mut,
     sync::{
-        atomic::{fence, AtomicPtr, AtomicU64, Ordering},
+        atomic::{fence, AtomicI64, AtomicPtr, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
 };
 
+use crossbeam_epoch as epoch;
+
+use super::stats::PageTableStats;
+use crate::util::cache_padded::CachePadded;
+
 pub(crate) const NAN_ID: u64 = 0;
 pub(crate) const MIN_ID: u64 = 1;
 pub(crate) const MAX_ID: u64 = L2_FANOUT - 1;
 
+/// A tag of the slots of free ids.
+///
+/// A free slot stores the next id in the free list with this tag, which
+/// distinguishes it from the address of a live page.
+const FREE_TAG: u64 = 1 << 63;
+
 /// Builds a new [`PageTable`] from existing mappings.
 #[derive(Default)]
 pub(crate) struct PageTableBuilder {
@@ -30,15 +41,22 @@ impl PageTableBuilder {
 
     pub(crate) fn build(mut self) -> PageTable {
         let mut free = NAN_ID;
+        let mut free_len = 0;
         // We prefer smaller ids so we scan backward to build the free list.
         for id in (MIN_ID..=self.max_id).rev() {
             if self.inner.index(id).load(Ordering::Relaxed) == 0 {
-                self.inner.index(id).store(free, Ordering::Relaxed);
+                self.inner
+                    .index(id)
+                    .store(free | FREE_TAG, Ordering::Relaxed);
                 free = id;
+                free_len += 1;
             }
         }
-        self.inner.free = AtomicU64::new(free);
-        self.inner.next = AtomicU64::new(self.max_id + 1);
+        self.inner.free[0] = CachePadded::new(FreeList {
+            head: AtomicU64::new(free),
+            len: AtomicI64::new(free_len),
+        });
+        self.inner.next = CachePadded::new(AtomicU64::new(self.max_id + 1));
         // Make sure all writes are visible before we publish the table.
         fence(Ordering::SeqCst);
         PageTable {
@@ -48,6 +66,11 @@ impl PageTableBuilder {
 }
 
 /// A table that maps page ids to page addresses.
+///
+/// The table is a three-level radix tree that addresses up to [`MAX_ID`]
+/// pages. Only the top-level arrays are allocated upfront, and the lower
+/// levels are installed with CAS on first access, so the table grows with the
+/// number of pages without blocking readers.
 #[derive(Clone, Default)]
 pub(crate) struct PageTable {
     inner: Arc<Inner>,
@@ -74,8 +97,65 @@ impl PageTable {
         self.inner.alloc()
     }
 
+    /// Returns an iterator over the ids and addresses of live pages.
+    ///
+    /// The iterator doesn't provide a consistent view of the table if it is
+    /// updated concurrently.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let next_id = self.inner.next.load(Ordering::Acquire).min(MAX_ID);
+        (MIN_ID..next_id).filter_map(|id| {
+            let addr = self.get(id);
+            (addr != 0 && addr & FREE_TAG == 0).then_some((id, addr))
+        })
+    }
+
+    /// Returns the address of the page with the given id, if it is live.
+    pub(crate) fn get_live(&self, id: u64) -> Option<u64> {
+        let next_id = self.inner.next.load(Ordering::Acquire).min(MAX_ID);
+        if !(MIN_ID..next_id).contains(&id) {
+            return None;
+        }
+        let addr = self.get(id);
+        (addr != 0 && addr & FREE_TAG == 0).then_some(addr)
+    }
+
+    /// Uses up all page ids that have not been allocated.
+    #[cfg(all(test, unix))]
+    pub(super) fn exhaust_ids(&self) {
+        self.inner.next.store(MAX_ID, Ordering::Relaxed);
+    }
+
+    /// Returns the statistics of the table.
+    pub(crate) fn stats(&self) -> PageTableStats {
+        let inner = &self.inner;
+        let next_id = inner.next.load(Ordering::Relaxed).min(MAX_ID);
+        let free_ids = inner
+            .free
+            .iter()
+            .map(|list| list.len.load(Ordering::Relaxed))
+            .fold(0i64, i64::saturating_add)
+            .max(0) as u64;
+        let used_ids = next_id - MIN_ID;
+        PageTableStats {
+            capacity: MAX_ID - MIN_ID,
+            allocated_ids: used_ids.saturating_sub(free_ids),
+            free_ids,
+            resident_bytes: inner.resident_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the page id to the free list.
+    ///
+    /// The id is not reused until all threads pinned at the time have been
+    /// unpinned, so concurrent allocations can not observe a recycled id.
+    ///
+    /// # Safety
+    ///
+    /// The id must not be reachable from the tree anymore.
     pub(crate) unsafe fn dealloc(&self, id: u64) {
-        self.inner.dealloc(id)
+        let inner = self.inner.clone();
+        let guard = epoch::pin();
+        guard.defer(move || inner.dealloc(id));
     }
 }
 
@@ -87,10 +167,24 @@ struct Inner {
     // Level 2: [L1_FANOUT, L2_FANOUT)
     l2: Box<L2<L2_LEN>>,
     // The next id to allocate.
-    next: AtomicU64,
-    // The head of the free list.
-    // The list uses epoch-based reclamation to prevent the ABA problem.
-    free: AtomicU64,
+    next: CachePadded<AtomicU64>,
+    // The free lists.
+    // The free ids are striped across threads to reduce contention on the
+    // heads. The lists use epoch-based reclamation to prevent the ABA problem.
+    free: [CachePadded<FreeList>; FREE_LIST_SHARDS],
+    // The bytes of the installed levels.
+    resident_bytes: AtomicU64,
+}
+
+#[derive(Default)]
+struct FreeList {
+    // The head of the list.
+    head: AtomicU64,
+    // The approximate number of ids in the list.
+    //
+    // The length is updated after the head, so it may be negative for a while
+    // if an id is popped before the push of it is counted.
+    len: AtomicI64,
 }
 
 impl Default for Inner {
@@ -99,35 +193,43 @@ impl Default for Inner {
             l0: Box::default(),
             l1: Box::default(),
             l2: Box::default(),
-            next: AtomicU64::new(MIN_ID),
-            free: AtomicU64::new(NAN_ID),
+            next: CachePadded::new(AtomicU64::new(MIN_ID)),
+            // NAN_ID is zero.
+            free: Default::default(),
+            resident_bytes: AtomicU64::new(
+                (size_of::<L0<L0_LEN>>() + size_of::<L1<L1_LEN>>() + size_of::<L2<L2_LEN>>())
+                    as u64,
+            ),
         }
     }
 }
 
 impl Inner {
     fn index(&self, index: u64) -> &AtomicU64 {
+        let resident = &self.resident_bytes;
         if index < L0_FANOUT {
-            self.l0.index(index)
+            self.l0.index(index, resident)
         } else if index < L1_FANOUT {
-            self.l1.index(index - L0_FANOUT)
+            self.l1.index(index - L0_FANOUT, resident)
         } else if index < L2_FANOUT {
-            self.l2.index(index - L1_FANOUT)
+            self.l2.index(index - L1_FANOUT, resident)
         } else {
             unreachable!()
         }
     }
 
     fn alloc(&self) -> Option<u64> {
-        let mut id = self.free.load(Ordering::Acquire);
-        while id != NAN_ID {
-            let next = self.index(id).load(Ordering::Acquire);
-            match self
-                .free
-                .compare_exchange(id, next, Ordering::AcqRel, Ordering::Acquire)
-            {
-                Ok(_) => break,
-                Err(actual) => id = actual,
+        // Ids are pushed back to the free list after all pinned threads are
+        // unpinned, so the head can not be popped and pushed again before the
+        // CAS below, which prevents the ABA problem.
+        let _guard = epoch::pin();
+        // Tries the free list of this thread first, and then the others.
+        let shard = current_shard();
+        let mut id = NAN_ID;
+        for i in 0..FREE_LIST_SHARDS {
+            id = self.pop_free((shard + i) % FREE_LIST_SHARDS);
+            if id != NAN_ID {
+                break;
             }
         }
         if id == NAN_ID {
@@ -143,21 +245,58 @@ impl Inner {
         }
     }
 
+    fn pop_free(&self, shard: usize) -> u64 {
+        let free = &self.free[shard];
+        let mut id = free.head.load(Ordering::Acquire);
+        while id != NAN_ID {
+            let next = self.index(id).load(Ordering::Acquire) & !FREE_TAG;
+            match free
+                .head
+                .compare_exchange(id, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    free.len.fetch_sub(1, Ordering::Relaxed);
+                    break;
+                }
+                Err(actual) => id = actual,
+            }
+        }
+        id
+    }
+
     fn dealloc(&self, id: u64) {
-        let mut next = self.free.load(Ordering::Acquire);
+        let free = &self.free[current_shard()];
+        let mut next = free.head.load(Ordering::Acquire);
         loop {
-            self.index(id).store(next, Ordering::Release);
-            match self
-                .free
+            self.index(id).store(next | FREE_TAG, Ordering::Release);
+            match free
+                .head
                 .compare_exchange(next, id, Ordering::AcqRel, Ordering::Acquire)
             {
-                Ok(_) => break,
+                Ok(_) => {
+                    free.len.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
                 Err(actual) => next = actual,
             }
         }
     }
 }
 
+/// The number of free lists in a page table.
+const FREE_LIST_SHARDS: usize = 8;
+
+/// Returns the free list shard of the current thread.
+///
+/// Threads are assigned to shards in a round-robin manner.
+fn current_shard() -> usize {
+    static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % FREE_LIST_SHARDS;
+    }
+    SHARD.with(|shard| *shard)
+}
+
 struct L0<const N: usize>([AtomicU64; N]);
 
 impl<const N: usize> Default for L0<N> {
@@ -167,7 +306,7 @@ impl<const N: usize> Default for L0<N> {
 }
 
 impl<const N: usize> L0<N> {
-    fn index(&self, index: u64) -> &AtomicU64 {
+    fn index(&self, index: u64, _: &AtomicU64) -> &AtomicU64 {
         &self.0[index as usize]
     }
 }
@@ -196,30 +335,35 @@ macro_rules! define_level {
         }
 
         impl<const N: usize> $level<N> {
-            fn index(&self, index: u64) -> &AtomicU64 {
+            fn index(&self, index: u64, resident: &AtomicU64) -> &AtomicU64 {
                 let i = index / $fanout;
                 let j = index % $fanout;
                 let p = self.0[i as usize].load(Ordering::Relaxed);
                 let child = unsafe {
                     p.as_ref()
-                        .unwrap_or_else(|| self.install_or_acquire_child(i as usize))
+                        .unwrap_or_else(|| self.install_or_acquire_child(i as usize, resident))
                 };
-                child.index(j)
+                child.index(j, resident)
             }
 
             #[cold]
-            fn install_or_acquire_child(&self, index: usize) -> &$child {
+            fn install_or_acquire_child(&self, index: usize, resident: &AtomicU64) -> &$child {
                 let mut child = Box::into_raw(Box::default());
-                if let Err(current) = self.0[index].compare_exchange(
+                match self.0[index].compare_exchange(
                     null_mut(),
                     child,
                     Ordering::AcqRel,
                     Ordering::Acquire,
                 ) {
-                    unsafe {
-                        drop(Box::from_raw(child));
+                    Ok(_) => {
+                        resident.fetch_add(size_of::<$child>() as u64, Ordering::Relaxed);
+                    }
+                    Err(current) => {
+                        unsafe {
+                            drop(Box::from_raw(child));
+                        }
+                        child = current;
                     }
-                    child = current;
                 }
                 unsafe { &*child }
             }
@@ -248,13 +392,147 @@ mod tests {
         unsafe {
             assert_eq!(table.alloc(), Some(1));
             assert_eq!(table.alloc(), Some(2));
+        }
+        table.inner.dealloc(1);
+        table.inner.dealloc(2);
+        unsafe {
+            assert_eq!(table.alloc(), Some(2));
+            assert_eq!(table.alloc(), Some(1));
+        }
+    }
+
+    #[test]
+    fn alloc_concurrent() {
+        let table = PageTable::default();
+        let handles: Vec<_> = (0..FREE_LIST_SHARDS)
+            .map(|_| {
+                let table = table.clone();
+                std::thread::spawn(move || {
+                    (0..1000)
+                        .map(|_| unsafe { table.alloc() }.unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut ids: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), FREE_LIST_SHARDS * 1000);
+        assert_eq!(ids.last(), Some(&(FREE_LIST_SHARDS as u64 * 1000)));
+    }
+
+    #[test]
+    fn stats_with_negative_free_len() {
+        let table = PageTable::default();
+        unsafe {
+            assert_eq!(table.alloc(), Some(1));
+        }
+        // An id is popped before its push is counted.
+        table.inner.free[0].len.fetch_sub(1, Ordering::Relaxed);
+        let stats = table.stats();
+        assert_eq!(stats.free_ids, 0);
+        assert_eq!(stats.allocated_ids, 1);
+    }
+
+    #[test]
+    fn dealloc_deferred() {
+        let table = PageTable::default();
+        unsafe {
+            assert_eq!(table.alloc(), Some(1));
+            let guard = epoch::pin();
             table.dealloc(1);
-            table.dealloc(2);
+            // The id is not recycled while the thread is pinned.
             assert_eq!(table.alloc(), Some(2));
+            drop(guard);
+        }
+        let mut recycled = false;
+        for _ in 0..1000 {
+            epoch::pin().flush();
+            if table
+                .inner
+                .free
+                .iter()
+                .any(|f| f.head.load(Ordering::Acquire) == 1)
+            {
+                recycled = true;
+                break;
+            }
+        }
+        assert!(recycled);
+        unsafe {
             assert_eq!(table.alloc(), Some(1));
         }
     }
 
+    #[test]
+    fn alloc_grow() {
+        let table = PageTable::default();
+        table.inner.next.store(L0_FANOUT - 1, Ordering::Relaxed);
+        unsafe {
+            assert_eq!(table.alloc(), Some(L0_FANOUT - 1));
+            // The next id lives in a lower level that is not installed yet.
+            assert_eq!(table.alloc(), Some(L0_FANOUT));
+        }
+        table.set(L0_FANOUT, 1);
+        assert_eq!(table.get(L0_FANOUT), 1);
+        assert_eq!(table.get(L0_FANOUT + 1), 0);
+
+        table.inner.next.store(MAX_ID, Ordering::Relaxed);
+        unsafe {
+            assert_eq!(table.alloc(), None);
+        }
+    }
+
+    #[test]
+    fn iter() {
+        let table = PageTable::default();
+        unsafe {
+            for id in 1..=4 {
+                assert_eq!(table.alloc(), Some(id));
+                table.set(id, id << 32);
+            }
+        }
+        table.inner.dealloc(1);
+        table.inner.dealloc(3);
+        let pages: Vec<_> = table.iter().collect();
+        assert_eq!(pages, [(2, 2 << 32), (4, 4 << 32)]);
+        unsafe {
+            assert_eq!(table.alloc(), Some(3));
+        }
+        assert_eq!(table.get(1), FREE_TAG);
+        assert_eq!(table.iter().count(), 2);
+    }
+
+    #[test]
+    fn stats() {
+        let table = PageTable::default();
+        let stats = table.stats();
+        assert_eq!(stats.allocated_ids, 0);
+        assert_eq!(stats.free_ids, 0);
+        let resident_bytes = stats.resident_bytes;
+
+        unsafe {
+            assert_eq!(table.alloc(), Some(1));
+            assert_eq!(table.alloc(), Some(2));
+        }
+        table.inner.dealloc(1);
+        let stats = table.stats();
+        assert_eq!(stats.allocated_ids, 1);
+        assert_eq!(stats.free_ids, 1);
+        assert_eq!(stats.resident_bytes, resident_bytes);
+
+        // Accessing an id in the next level installs a child.
+        table.set(L0_FANOUT, 1);
+        let stats = table.stats();
+        assert_eq!(
+            stats.resident_bytes,
+            resident_bytes + size_of::<L0<FANOUT>>() as u64
+        );
+    }
+
     #[test]
     fn index() {
         let table = PageTable::default();
@@ -285,7 +563,9 @@ mod tests {
             assert_eq!(table.alloc(), Some(2));
             assert_eq!(table.alloc(), Some(4));
             assert_eq!(table.alloc(), Some(6));
-            table.dealloc(2);
+        }
+        table.inner.dealloc(2);
+        unsafe {
             assert_eq!(table.alloc(), Some(2));
         }
     }
@@ -1,20 +1,24 @@
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use bitflags::bitflags;
+use log::error;
 
 use super::{
     cache::CacheToken,
     stats::AtomicWritebufStats,
     version::Version,
     write_buffer::{RecordHeader, ReleaseState},
-    CacheEntry, Error, LRUCache, PageFiles, PageTable, Result, WriteBuffer, NAN_ID,
+    CacheEntry, Error, InlineFlusher, LRUCache, LsnWatermark, PageFiles, PageTable, Result,
+    WriteBuffer, NAN_ID,
 };
 use crate::{
     env::Env,
-    page::{PageBuf, PageInfo, PageRef},
+    page::{check_page, PageBuf, PageInfo, PageRef},
+    util::atomic::Counter,
 };
 
 bitflags! {
@@ -30,6 +34,10 @@ pub struct CacheOption: u8 {
     const LOW_PRI = 0b00000010;
 
     const BOTTOM_PRI = 0b00000100;
+
+    /// NoFill: read from cache first and read disk without refilling cache when cache miss.
+    /// It's normally be used by scans that read lots of pages only once, so that they don't evict the working set.
+    const NO_FILL = 0b00001000;
 }
 }
 
@@ -76,6 +84,15 @@ impl CacheOption {
         self.set(CacheOption::REFILL_COLD_WHEN_NOT_FULL, v);
         self
     }
+
+    pub(crate) fn fill_cache(&self) -> bool {
+        !self.contains(CacheOption::NO_FILL)
+    }
+
+    pub(crate) fn set_fill_cache(mut self, v: bool) -> Self {
+        self.set(CacheOption::NO_FILL, !v);
+        self
+    }
 }
 
 pub(crate) enum CachePriority {
@@ -86,6 +103,75 @@ pub(crate) enum CachePriority {
 
 type CacheEntryGuard = CacheEntry<Vec<u8>, LRUCache<Vec<u8>>>;
 
+/// The number of cache entries that a guard holds without allocations.
+///
+/// A point lookup reads a page per level of the tree, plus the deltas of the
+/// leaf page, which rarely exceeds this.
+const INLINE_CACHE_GUARDS: usize = 8;
+
+/// The cache entries held by a guard.
+///
+/// The first entries are stored inline, so that a point lookup doesn't
+/// allocate. The rest are spilled to the heap.
+#[derive(Default)]
+struct CacheGuards {
+    inline: [Option<CacheEntryGuard>; INLINE_CACHE_GUARDS],
+    len: usize,
+    spilled: Vec<CacheEntryGuard>,
+}
+
+impl CacheGuards {
+    /// Holds the entry until the guard is dropped, and returns a reference to
+    /// it.
+    fn push(&mut self, entry: CacheEntryGuard) -> &CacheEntryGuard {
+        if self.len < INLINE_CACHE_GUARDS {
+            let slot = &mut self.inline[self.len];
+            self.len += 1;
+            return slot.insert(entry);
+        }
+        self.spilled.push(entry);
+        self.spilled.last().unwrap()
+    }
+}
+
+/// The pages read from files without filling the cache, which are held by a
+/// guard until all the readers of them are done.
+#[derive(Default)]
+struct UncachedPages {
+    pages: Vec<Vec<u8>>,
+    // The number of readers that read pages without filling the cache.
+    readers: usize,
+}
+
+/// Statistics of page reads through a guard.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ReadStats {
+    /// The number of pages read from files.
+    pub(crate) file_reads: u64,
+    /// The number of page reads that miss the page cache.
+    pub(crate) cache_misses: u64,
+    /// The time spent on page reads that miss the page cache.
+    pub(crate) io_wait: Duration,
+}
+
+impl ReadStats {
+    /// Returns the reads since the `earlier` statistics.
+    pub(crate) fn since(&self, earlier: &ReadStats) -> ReadStats {
+        ReadStats {
+            file_reads: self.file_reads.saturating_sub(earlier.file_reads),
+            cache_misses: self.cache_misses.saturating_sub(earlier.cache_misses),
+            io_wait: self.io_wait.saturating_sub(earlier.io_wait),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AtomicReadStats {
+    file_reads: Counter,
+    cache_misses: Counter,
+    io_wait_us: Counter,
+}
+
 pub(crate) struct Guard<E: Env>
 where
     Self: Send,
@@ -93,8 +179,13 @@ where
     version: Arc<Version>,
     page_table: PageTable,
     page_files: Arc<PageFiles<E>>,
-    cache_guards: Mutex<Vec<CacheEntryGuard>>,
+    cache_guards: Mutex<CacheGuards>,
+    uncached_pages: Mutex<UncachedPages>,
     writebuf_stats: Arc<AtomicWritebufStats>,
+    read_stats: AtomicReadStats,
+    flusher: Option<Arc<InlineFlusher<E>>>,
+    watermark: Arc<LsnWatermark>,
+    read_only: bool,
 }
 
 impl<E: Env> Guard<E> {
@@ -109,11 +200,49 @@ impl<E: Env> Guard<E> {
             page_table,
             page_files,
             cache_guards: Mutex::default(),
+            uncached_pages: Mutex::default(),
             writebuf_stats,
+            read_stats: AtomicReadStats::default(),
+            flusher: None,
+            watermark: Arc::default(),
+            read_only: false,
         }
     }
 
+    /// Flushes the write buffers that are ready to flush before transactions
+    /// begin, if there is no background flush.
+    pub(crate) fn with_flusher(mut self, flusher: Option<Arc<InlineFlusher<E>>>) -> Self {
+        self.flusher = flusher;
+        self
+    }
+
+    /// Tracks the writes in progress with the watermark.
+    pub(crate) fn with_watermark(mut self, watermark: Arc<LsnWatermark>) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
+    /// Fails the transactions that allocate or deallocate pages with
+    /// [`Error::ReadOnly`].
+    pub(crate) fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Returns the watermark of the LSNs flushed to page files.
+    pub(crate) fn watermark(&self) -> &LsnWatermark {
+        &self.watermark
+    }
+
+    /// Returns true if pages can't be modified.
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub(crate) async fn begin(&self) -> PageTxn<E> {
+        if let Some(flusher) = &self.flusher {
+            flusher.flush_ready().await;
+        }
         let buffer_id = self.version.buffer_set.acquire_active_buffer_id().await;
         PageTxn {
             guard: self,
@@ -121,6 +250,7 @@ impl<E: Env> Guard<E> {
             hold_write_guard: false,
             records: HashMap::default(),
             page_ids: Vec::default(),
+            lsns: None,
         }
     }
 
@@ -138,6 +268,23 @@ impl<E: Env> Guard<E> {
         self.page_table.get(id)
     }
 
+    /// Returns the address of the corresponding page, if it is live.
+    ///
+    /// Unlike [`Self::page_addr`], this accepts any page id.
+    pub(crate) fn live_page_addr(&self, id: u64) -> Option<u64> {
+        self.page_table.get_live(id)
+    }
+
+    /// Returns an iterator over the live pages in the page table.
+    ///
+    /// Each item is the id, the address, and the information of the head of
+    /// a page chain.
+    pub(crate) fn iter_pages(&self) -> impl Iterator<Item = Result<(u64, u64, PageInfo)>> + '_ {
+        self.page_table
+            .iter()
+            .map(|(id, addr)| Ok((id, addr, self.read_page_info(addr)?)))
+    }
+
     pub(crate) fn read_page_info(&self, addr: u64) -> Result<PageInfo> {
         let logical_id = (addr >> 32) as u32;
         if let Some(buf) = self.version.get(logical_id) {
@@ -146,17 +293,75 @@ impl<E: Env> Guard<E> {
             return Ok(page.info());
         }
 
+        // A page that can not be found means that the metadata of page files
+        // is corrupted, so it is reported instead of panicking.
         let Some(file_info) = self.version.page_groups().get(&logical_id) else {
-            panic!("File {logical_id} (addr {addr}) is not exists");
+            error!("File {logical_id} (addr {addr}) is not exists");
+            return Err(Error::Corrupted);
         };
 
         let Some(page_info) = file_info.get_page_info(addr) else {
-            panic!("The addr {addr} is not belongs to the target file");
+            error!("The addr {addr} is not belongs to the target file");
+            return Err(Error::Corrupted);
         };
 
         Ok(page_info)
     }
 
+    /// Returns true if the page at the address is in a write buffer or a page
+    /// file of the current version, so that it can be read without panics.
+    pub(crate) fn contains_page(&self, addr: u64) -> bool {
+        let logical_id = (addr >> 32) as u32;
+        if self.version.get(logical_id).is_some() {
+            return true;
+        }
+        self.version
+            .page_groups()
+            .get(&logical_id)
+            .filter(|group| {
+                self.version
+                    .file_infos()
+                    .contains_key(&group.meta().file_id)
+            })
+            .map_or(false, |group| group.get_page_handle(addr).is_some())
+    }
+
+    /// Registers a reader that reads pages without filling the cache.
+    pub(crate) fn add_uncached_reader(&self) {
+        self.uncached_pages.lock().expect("Poisoned").readers += 1;
+    }
+
+    /// Unregisters a reader added by [`Guard::add_uncached_reader`].
+    pub(crate) fn remove_uncached_reader(&self) {
+        self.uncached_pages.lock().expect("Poisoned").readers -= 1;
+    }
+
+    /// Releases the pages read without filling the cache if the caller is the
+    /// only reader of them.
+    ///
+    /// Otherwise, the pages are kept until the guard is dropped, since they
+    /// may be used by other readers.
+    ///
+    /// # Safety
+    ///
+    /// The caller must be a registered reader, and must not use the pages it
+    /// read without filling the cache anymore.
+    pub(crate) unsafe fn release_uncached_pages(&self) {
+        let mut uncached = self.uncached_pages.lock().expect("Poisoned");
+        if uncached.readers == 1 {
+            uncached.pages.clear();
+        }
+    }
+
+    /// Returns the statistics of page reads through this guard.
+    pub(crate) fn read_stats(&self) -> ReadStats {
+        ReadStats {
+            file_reads: self.read_stats.file_reads.get(),
+            cache_misses: self.read_stats.cache_misses.get(),
+            io_wait: Duration::from_micros(self.read_stats.io_wait_us.get()),
+        }
+    }
+
     pub(crate) async fn read_page(
         &self,
         addr: u64,
@@ -171,26 +376,66 @@ impl<E: Env> Guard<E> {
         self.writebuf_stats.read_in_file.inc();
 
         let Some(page_group) = self.version.page_groups().get(&logical_id) else {
-            panic!("File {logical_id} (addr {addr}) is not exists");
+            error!("File {logical_id} (addr {addr}) is not exists");
+            return Err(Error::Corrupted);
         };
 
         let physical_id = page_group.meta().file_id;
         let Some(file_info) = self.version.file_infos().get(&physical_id) else {
-            panic!("Target file {physical_id} is missing");
+            error!("Target file {physical_id} is missing");
+            return Err(Error::Corrupted);
         };
         let Some(handle) = page_group.get_page_handle(addr) else {
-            panic!("The addr {addr} is not belongs to the target file {physical_id:?}");
+            error!("The addr {addr} is not belongs to the target file {physical_id:?}");
+            return Err(Error::Corrupted);
+        };
+
+        let start_at = Instant::now();
+        let read = if hint.fill_cache() {
+            let read = self
+                .page_files
+                .read_page(physical_id, file_info.meta(), addr, handle, hint)
+                .await?;
+            Some(read)
+        } else {
+            self.page_files.lookup_page(addr).map(|entry| (entry, true))
         };
+        self.read_stats.file_reads.inc();
+        let Some((entry, hit)) = read else {
+            let buf = self
+                .page_files
+                .read_file_page(physical_id, file_info.meta(), handle)
+                .await?;
+            let page = check_page(&buf).ok_or(Error::CorruptedPage {
+                file_id: physical_id,
+                offset: handle.offset as u64,
+            })?;
+            self.read_stats.cache_misses.inc();
+            let io_wait = start_at.elapsed().as_micros() as u64;
+            self.read_stats.io_wait_us.add(io_wait);
+            self.writebuf_stats.read_file_bytes.add(buf.len() as u64);
+            if !page.tier().is_leaf() {
+                self.writebuf_stats.miss_inner.inc();
+            }
 
-        let (entry, hit) = self
-            .page_files
-            .read_page(physical_id, file_info.meta(), addr, handle, hint)
-            .await?;
+            let mut uncached = self.uncached_pages.lock().expect("Poisoned");
+            uncached.pages.push(buf);
+            let page = uncached.pages.last().unwrap();
+            let page = PageRef::new(unsafe {
+                // Safety: the buffer is not moved or dropped until it is released by its
+                // reader, or the guard is dropped.
+                std::slice::from_raw_parts(page.as_ptr(), page.len())
+            });
+            return Ok((page, None));
+        };
+        if !hit {
+            self.read_stats.cache_misses.inc();
+            let io_wait = start_at.elapsed().as_micros() as u64;
+            self.read_stats.io_wait_us.add(io_wait);
+        }
 
         let mut owned_pages = self.cache_guards.lock().expect("Poisoned");
-        owned_pages.push(entry);
-
-        let last_guard = owned_pages.last().unwrap();
+        let last_guard = owned_pages.push(entry);
         let page = last_guard.value();
         if !hit {
             self.writebuf_stats.read_file_bytes.add(page.len() as u64);
@@ -233,6 +478,7 @@ where
     hold_write_guard: bool,
     records: HashMap<u64 /* page addr */, &'a mut RecordHeader>,
     page_ids: Vec<u64>,
+    lsns: Option<(u64, u64)>,
 }
 
 impl<'a, E: Env> PageTxn<'a, E> {
@@ -254,22 +500,30 @@ impl<'a, E: Env> PageTxn<'a, E> {
     ///
     /// If the transaction aborts, the inserted page will be deleted.
     ///
+    /// Returns [`Error::Capacity`] if page ids are exhausted.
+    ///
     /// # Panics
     ///
     /// Panics if `addr` is not allocated by this transaction.
-    pub(crate) fn insert_page(&mut self, addr: u64) -> u64 {
+    pub(crate) fn insert_page(&mut self, addr: u64) -> Result<u64> {
         let header = self.records.get_mut(&addr).expect("no such pages");
         if header.is_tombstone() {
             panic!("insert page with tombstone");
         }
 
         // TODO: safety conditions
-        let page_id = unsafe { self.guard.page_table.alloc() }.expect("page id is exhausted");
+        let Some(page_id) = (unsafe { self.guard.page_table.alloc() }) else {
+            error!(
+                "page ids are exhausted: {:?}",
+                self.guard.page_table.stats()
+            );
+            return Err(Error::Capacity);
+        };
         self.guard.page_table.set(page_id, addr);
 
         header.set_page_id(page_id);
         self.page_ids.push(page_id);
-        page_id
+        Ok(page_id)
     }
 
     /// Updates the page address to `new_addr` if its current value is the same
@@ -361,6 +615,9 @@ impl<'a, E: Env> PageTxn<'a, E> {
     where
         F: FnOnce(&WriteBuffer, bool) -> Result<O>,
     {
+        if self.guard.read_only {
+            return Err(Error::ReadOnly);
+        }
         let is_first_op = !self.hold_write_guard;
         let result = {
             let buffer = self
@@ -388,11 +645,25 @@ impl<'a, E: Env> PageTxn<'a, E> {
         }
     }
 
+    /// Sets the range of LSNs of the writes in the transaction, which is
+    /// recorded with the write buffer of the allocated pages on commit.
+    pub(crate) fn set_lsns(&mut self, min_lsn: u64, max_lsn: u64) {
+        self.lsns = Some((min_lsn, max_lsn));
+    }
+
     /// Commits the transaction.
     pub(crate) fn commit(mut self) {
         self.page_ids.clear();
         if self.hold_write_guard {
             self.records.clear();
+            if let Some((min_lsn, max_lsn)) = self.lsns {
+                let buf = self
+                    .guard
+                    .version
+                    .get(self.buffer_id)
+                    .expect("The target write buffer must exists");
+                buf.buffered_lsns().record(min_lsn, max_lsn);
+            }
             self.drop_writer_guard();
             self.hold_write_guard = false;
         }
@@ -431,7 +702,7 @@ impl<'a, E: Env> Drop for PageTxn<'a, E> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use super::*;
     use crate::{
@@ -460,13 +731,40 @@ mod tests {
         let guard = Guard::new(version.clone(), page_table, files, Default::default());
         let mut page_txn = guard.begin().await;
         let (addr, _) = page_txn.alloc_page(123).await.unwrap();
-        let id = page_txn.insert_page(addr);
+        let id = page_txn.insert_page(addr).unwrap();
         let (new, _) = page_txn.alloc_page(123).await.unwrap();
         assert!(page_txn.update_page(id, addr, new).is_ok());
 
         assert_current_buffer_is_flushable(version);
     }
 
+    #[photonio::test]
+    async fn release_uncached_pages() {
+        let env = crate::env::Photon;
+        let base = tempdir::TempDir::new("test_release_uncached_pages").unwrap();
+        let files = Arc::new(
+            PageFiles::new(env, base.path(), &test_option())
+                .await
+                .unwrap(),
+        );
+        let version = new_version(512);
+        let page_table = PageTable::default();
+        let guard = Guard::new(version, page_table, files, Default::default());
+        let num_pages = || guard.uncached_pages.lock().unwrap().pages.len();
+        let read = || guard.uncached_pages.lock().unwrap().pages.push(vec![0; 8]);
+        guard.add_uncached_reader();
+        guard.add_uncached_reader();
+        read();
+        read();
+        // The pages may be used by the other reader.
+        unsafe { guard.release_uncached_pages() };
+        assert_eq!(num_pages(), 2);
+        guard.remove_uncached_reader();
+        unsafe { guard.release_uncached_pages() };
+        assert_eq!(num_pages(), 0);
+        guard.remove_uncached_reader();
+    }
+
     #[photonio::test]
     async fn page_txn_failed_update_page() {
         let env = crate::env::Photon;
@@ -484,7 +782,7 @@ mod tests {
         // insert old page.
         let mut page_txn = guard.begin().await;
         let (addr, _) = page_txn.alloc_page(123).await.unwrap();
-        let id = page_txn.insert_page(addr);
+        let id = page_txn.insert_page(addr).unwrap();
         let (new, _) = page_txn.alloc_page(123).await.unwrap();
         assert!(page_txn.update_page(id, addr, new).is_ok());
 
@@ -528,7 +826,7 @@ mod tests {
         let guard = Guard::new(version.clone(), page_table, files, Default::default());
         let mut page_txn = guard.begin().await;
         let (addr, _) = page_txn.alloc_page(123).await.unwrap();
-        let id = page_txn.insert_page(addr);
+        let id = page_txn.insert_page(addr).unwrap();
         let (new, _) = page_txn.alloc_page(123).await.unwrap();
         assert!(page_txn
             .replace_page(id, addr, new, &[1, 2, 3])
@@ -604,13 +902,41 @@ mod tests {
         );
         let mut page_txn = guard.begin().await;
         let (addr, _) = page_txn.alloc_page(123).await.unwrap();
-        let id = page_txn.insert_page(addr);
+        let id = page_txn.insert_page(addr).unwrap();
         page_txn.commit();
 
         assert_eq!(page_table.get(id), addr);
         assert_current_buffer_is_flushable(version);
     }
 
+    #[photonio::test]
+    async fn page_txn_insert_page_exhausted() {
+        let env = crate::env::Photon;
+        let base = tempdir::TempDir::new("test_page_insert_page_exhausted").unwrap();
+        let files = Arc::new(
+            PageFiles::new(env, base.path(), &test_option())
+                .await
+                .unwrap(),
+        );
+
+        let version = new_version(512);
+        let page_table = PageTable::default();
+        page_table.exhaust_ids();
+        let guard = Guard::new(
+            version.clone(),
+            page_table.clone(),
+            files,
+            Default::default(),
+        );
+        let mut page_txn = guard.begin().await;
+        let (addr, _) = page_txn.alloc_page(123).await.unwrap();
+        assert!(matches!(page_txn.insert_page(addr), Err(Error::Capacity)));
+        drop(page_txn);
+
+        assert_eq!(page_table.iter().count(), 0);
+        assert_current_buffer_is_flushable(version);
+    }
+
     fn assert_current_buffer_is_flushable(version: Arc<Version>) {
         let current = version.buffer_set.current();
         let buf = current.last_writer_buffer();
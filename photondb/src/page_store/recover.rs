@@ -7,9 +7,9 @@ use log::debug;
 use rustc_hash::FxHashMap;
 
 use super::{
-    page_table::{PageTable, PageTableBuilder},
+    page_table::{PageTable, PageTableBuilder, MAX_ID, MIN_ID},
     version::DeltaVersion,
-    FileInfo, NewFile, PageFiles, PageGroup, PageStore, Result, VersionEdit,
+    Error, FileInfo, NewFile, PageFiles, PageGroup, PageStore, Result, VersionEdit,
 };
 use crate::{env::Env, page_store::Manifest};
 
@@ -46,8 +46,13 @@ impl<E: Env> PageStore<E> {
         PageFiles<E>,
         DeltaVersion,
     )> {
-        let mut manifest = Manifest::open(env.to_owned(), path.as_ref()).await?;
+        let mut manifest = if options.read_only {
+            Manifest::open_read_only(env.to_owned(), path.as_ref()).await?
+        } else {
+            Manifest::open(env.to_owned(), path.as_ref()).await?
+        };
         let versions = manifest.list_versions().await?;
+        manifest.recover_metadata(&versions);
         let summary = Self::apply_version_edits(versions);
         debug!("Recover with file summary {summary:?}");
 
@@ -57,7 +62,9 @@ impl<E: Env> PageStore<E> {
         Self::recover_page_groups(&mut builder, &summary.active_files).await?;
         let (page_groups, file_infos, page_table) = builder.build();
 
-        Self::delete_unreferenced_page_files(&page_files, &summary).await?;
+        if !options.read_only {
+            Self::delete_unreferenced_page_files(&page_files, &summary).await?;
+        }
 
         let next_file_id = summary.next_file_id();
         manifest.reset_next_file_id(summary.next_file_id());
@@ -168,8 +175,20 @@ impl<'a, E: Env> FileInfoBuilder<'a, E> {
             .insert(file.id, FileInfo::new(file.up1, file.up2, file_meta));
 
         // 3. recover page table.
-        for (_, page_table) in meta_reader.page_tables {
+        // Each page file records the ids of the pages it contains, which is an
+        // incremental checkpoint of the page table. Files are replayed in
+        // order, and the latest address of a page wins.
+        for (group_id, page_table) in meta_reader.page_tables {
+            let group_meta = &meta_reader.page_groups[&group_id];
             for (page_addr, page_id) in page_table {
+                // Rejects entries that can not come from this group, since a
+                // corrupted page table would otherwise point to nowhere.
+                if !(MIN_ID..MAX_ID).contains(&page_id)
+                    || (page_addr >> 32) as u32 != group_id
+                    || group_meta.get_page_handle(page_addr).is_none()
+                {
+                    return Err(Error::Corrupted);
+                }
                 if self.page_table_builder.get(page_id) < page_addr {
                     self.page_table_builder.set(page_id, page_addr);
                 }
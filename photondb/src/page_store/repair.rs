@@ -0,0 +1,67 @@
+use std::io::ErrorKind;
+
+use log::warn;
+
+use super::*;
+
+/// The directory in the table directory that keeps the quarantined files.
+const QUARANTINE_DIR: &str = "quarantine";
+
+impl<E: Env> PageStore<E> {
+    /// Rebuilds the manifest from the page files in the path.
+    ///
+    /// The previous manifest files and the page files whose metadata can not
+    /// be read are moved to the quarantine directory. The remaining page files
+    /// are recorded in a new manifest.
+    ///
+    /// Returns the ids of the recorded files and the quarantined files.
+    pub(crate) async fn rebuild_manifest(
+        env: E,
+        path: &Path,
+        options: &Options,
+    ) -> Result<(Vec<u32>, Vec<u32>)> {
+        let quarantine = path.join(QUARANTINE_DIR);
+        match env.create_dir_all(&quarantine).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err.into()),
+        }
+        for entry in env.read_dir(path)? {
+            let Some(name) = entry.file_name() else {
+                continue;
+            };
+            if Manifest::<E>::is_manifest_file(&name.to_string_lossy()) {
+                env.rename(&entry, quarantine.join(name)).await?;
+            }
+        }
+
+        let page_files = PageFiles::new(env.to_owned(), path, options).await?;
+        let mut files = page_files.list_files()?;
+        files.sort_unstable();
+        let mut recorded = Vec::new();
+        let mut quarantined = Vec::new();
+        for file_id in files {
+            match page_files.read_file_meta(file_id).await {
+                Ok(_) => recorded.push(file_id),
+                Err(err) => {
+                    warn!("Quarantine page file {file_id}: {err}");
+                    page_files.quarantine_file(file_id, &quarantine).await?;
+                    quarantined.push(file_id);
+                }
+            }
+        }
+
+        let mut manifest = Manifest::open(env, path).await?;
+        let edit = VersionEdit {
+            file_stream: Some(StreamEdit {
+                new_files: recorded.iter().copied().map(NewFile::from).collect(),
+                deleted_files: Vec::new(),
+            }),
+            metadata: Vec::new(),
+        };
+        manifest
+            .record_version_edit(VersionEdit::default(), || edit)
+            .await?;
+        Ok((recorded, quarantined))
+    }
+}
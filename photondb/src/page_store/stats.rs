@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Instant};
 
 use crate::util::atomic::Counter;
 
@@ -15,6 +15,12 @@ pub struct StoreStats {
     pub jobs: JobStats,
     /// Statistics of buffer set.
     pub buffer_set: BufferSetStats,
+    /// Statistics of page table.
+    pub page_table: PageTableStats,
+    /// Statistics of file I/O.
+    pub io: IoStats,
+    /// Statistics of memory usage.
+    pub memory: MemoryStats,
 }
 
 impl StoreStats {
@@ -26,6 +32,9 @@ impl StoreStats {
             writebuf: self.writebuf.sub(&o.writebuf),
             jobs: self.jobs.sub(&o.jobs),
             buffer_set: self.buffer_set.sub(&o.buffer_set),
+            page_table: self.page_table,
+            io: self.io.sub(&o.io),
+            memory: self.memory,
         }
     }
 }
@@ -58,6 +67,9 @@ impl Display for StoreStats {
             self.file_reader_cache.recommendation,
         )?;
         self.buffer_set.fmt(f)?;
+        self.page_table.fmt(f)?;
+        self.memory.fmt(f)?;
+        self.io.fmt(f)?;
         self.jobs.fmt(f)
     }
 }
@@ -231,6 +243,22 @@ pub struct BufferSetStats {
     pub stall_writes: u64,
     /// The total interval of stalling writes.
     pub stall_intervals_ms: u64,
+    /// The total number of writes slowed down to reclaim deferred write
+    /// buffers.
+    pub slowdown_writes: u64,
+    /// The total interval of slowed down writes in microseconds.
+    pub slowdown_intervals_us: u64,
+    /// The total number of buffer set versions deferred to reclaim.
+    pub deferred_versions: u64,
+    /// The total number of deferred buffer set versions reclaimed.
+    pub reclaimed_versions: u64,
+    /// The total bytes of write buffers deferred to reclaim.
+    pub deferred_bytes: u64,
+    /// The total bytes of deferred write buffers reclaimed.
+    pub reclaimed_bytes: u64,
+    /// The total number of attempts to advance the epoch because of too many
+    /// deferred bytes.
+    pub epoch_advances: u64,
 }
 
 impl BufferSetStats {
@@ -238,16 +266,43 @@ impl BufferSetStats {
         BufferSetStats {
             stall_writes: self.stall_writes.wrapping_sub(o.stall_writes),
             stall_intervals_ms: self.stall_intervals_ms.wrapping_sub(o.stall_intervals_ms),
+            slowdown_writes: self.slowdown_writes.wrapping_sub(o.slowdown_writes),
+            slowdown_intervals_us: self
+                .slowdown_intervals_us
+                .wrapping_sub(o.slowdown_intervals_us),
+            deferred_versions: self.deferred_versions.wrapping_sub(o.deferred_versions),
+            reclaimed_versions: self.reclaimed_versions.wrapping_sub(o.reclaimed_versions),
+            deferred_bytes: self.deferred_bytes.wrapping_sub(o.deferred_bytes),
+            reclaimed_bytes: self.reclaimed_bytes.wrapping_sub(o.reclaimed_bytes),
+            epoch_advances: self.epoch_advances.wrapping_sub(o.epoch_advances),
         }
     }
+
+    /// Returns the bytes of write buffers waiting for reclamation.
+    pub fn unreclaimed_bytes(&self) -> u64 {
+        self.deferred_bytes.saturating_sub(self.reclaimed_bytes)
+    }
 }
 
 impl Display for BufferSetStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
-            "BufferSet: stall_writes: {} stall_intervals_ms: {}",
-            self.stall_writes, self.stall_intervals_ms,
+            "BufferSet: stall_writes: {} stall_intervals_ms: {} slowdown_writes: {} slowdown_intervals_us: {}",
+            self.stall_writes,
+            self.stall_intervals_ms,
+            self.slowdown_writes,
+            self.slowdown_intervals_us,
+        )?;
+        writeln!(
+            f,
+            "BufferSetReclaim: deferred_versions: {} reclaimed_versions: {} deferred_bytes: {} reclaimed_bytes: {} unreclaimed_bytes: {} epoch_advances: {}",
+            self.deferred_versions,
+            self.reclaimed_versions,
+            self.deferred_bytes,
+            self.reclaimed_bytes,
+            self.unreclaimed_bytes(),
+            self.epoch_advances,
         )
     }
 }
@@ -256,6 +311,13 @@ impl Display for BufferSetStats {
 pub(crate) struct AtomicBufferSetStats {
     pub(crate) stall_writes: Counter,
     pub(crate) stall_intervals_ms: Counter,
+    pub(crate) slowdown_writes: Counter,
+    pub(crate) slowdown_intervals_us: Counter,
+    pub(crate) deferred_versions: Counter,
+    pub(crate) reclaimed_versions: Counter,
+    pub(crate) deferred_bytes: Counter,
+    pub(crate) reclaimed_bytes: Counter,
+    pub(crate) epoch_advances: Counter,
 }
 
 impl AtomicBufferSetStats {
@@ -263,6 +325,186 @@ impl AtomicBufferSetStats {
         BufferSetStats {
             stall_writes: self.stall_writes.get(),
             stall_intervals_ms: self.stall_intervals_ms.get(),
+            slowdown_writes: self.slowdown_writes.get(),
+            slowdown_intervals_us: self.slowdown_intervals_us.get(),
+            deferred_versions: self.deferred_versions.get(),
+            reclaimed_versions: self.reclaimed_versions.get(),
+            deferred_bytes: self.deferred_bytes.get(),
+            reclaimed_bytes: self.reclaimed_bytes.get(),
+            epoch_advances: self.epoch_advances.get(),
+        }
+    }
+
+    pub(crate) fn unreclaimed_bytes(&self) -> u64 {
+        self.deferred_bytes
+            .get()
+            .saturating_sub(self.reclaimed_bytes.get())
+    }
+}
+
+/// Statistics of page table.
+///
+/// These are gauges of the current state of the table.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PageTableStats {
+    /// The max number of page ids that can be allocated.
+    pub capacity: u64,
+    /// The number of page ids in use.
+    pub allocated_ids: u64,
+    /// The number of page ids in the free lists.
+    pub free_ids: u64,
+    /// The bytes of memory used by the table.
+    pub resident_bytes: u64,
+}
+
+impl Display for PageTableStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "PageTable: capacity: {} allocated_ids: {} free_ids: {} resident_bytes: {}",
+            self.capacity, self.allocated_ids, self.free_ids, self.resident_bytes,
+        )
+    }
+}
+
+/// Statistics of memory usage, by the type of subsystems.
+///
+/// These are gauges of the current state of the store.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MemoryStats {
+    /// The bytes of pages charged to the page cache.
+    pub page_cache: u64,
+    /// The bytes of write buffers in use, which hold the delta pages that are
+    /// not flushed yet.
+    pub write_buffers: u64,
+    /// The bytes of released write buffers waiting for reclamation.
+    pub deferred_write_buffers: u64,
+    /// The bytes of memory used by the page table.
+    pub page_table: u64,
+}
+
+impl MemoryStats {
+    /// Returns the total bytes of memory.
+    pub fn total(&self) -> u64 {
+        self.page_cache + self.write_buffers + self.deferred_write_buffers + self.page_table
+    }
+}
+
+impl Display for MemoryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Memory: total: {} page_cache: {} write_buffers: {} deferred_write_buffers: {} page_table: {}",
+            self.total(),
+            self.page_cache,
+            self.write_buffers,
+            self.deferred_write_buffers,
+            self.page_table,
+        )
+    }
+}
+
+/// Statistics of file I/O, by the type of files.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct IoStats {
+    /// Statistics of page files.
+    pub page_file: FileIoStats,
+    /// Statistics of manifest files, including the `CURRENT` file.
+    pub manifest: FileIoStats,
+}
+
+impl IoStats {
+    pub(crate) fn sub(&self, o: &Self) -> Self {
+        IoStats {
+            page_file: self.page_file.sub(&o.page_file),
+            manifest: self.manifest.sub(&o.manifest),
+        }
+    }
+}
+
+impl Display for IoStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, stats) in [("PageFile", &self.page_file), ("Manifest", &self.manifest)] {
+            writeln!(
+                f,
+                "{name}Io: reads: {} read_bytes: {} writes: {} write_bytes: {} syncs: {} sync_micros: {}",
+                stats.reads,
+                stats.read_bytes,
+                stats.writes,
+                stats.write_bytes,
+                stats.syncs,
+                stats.sync_micros,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of I/O on a type of files.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FileIoStats {
+    /// The number of reads.
+    pub reads: u64,
+    /// The total bytes read.
+    pub read_bytes: u64,
+    /// The number of writes.
+    pub writes: u64,
+    /// The total bytes written.
+    pub write_bytes: u64,
+    /// The number of syncs of files and directories.
+    pub syncs: u64,
+    /// The total time spent on syncs in microseconds.
+    pub sync_micros: u64,
+}
+
+impl FileIoStats {
+    pub(crate) fn sub(&self, o: &Self) -> Self {
+        FileIoStats {
+            reads: self.reads.wrapping_sub(o.reads),
+            read_bytes: self.read_bytes.wrapping_sub(o.read_bytes),
+            writes: self.writes.wrapping_sub(o.writes),
+            write_bytes: self.write_bytes.wrapping_sub(o.write_bytes),
+            syncs: self.syncs.wrapping_sub(o.syncs),
+            sync_micros: self.sync_micros.wrapping_sub(o.sync_micros),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct AtomicFileIoStats {
+    reads: Counter,
+    read_bytes: Counter,
+    writes: Counter,
+    write_bytes: Counter,
+    syncs: Counter,
+    sync_micros: Counter,
+}
+
+impl AtomicFileIoStats {
+    pub(crate) fn on_read(&self, bytes: usize) {
+        self.reads.inc();
+        self.read_bytes.add(bytes as u64);
+    }
+
+    pub(crate) fn on_write(&self, bytes: usize) {
+        self.writes.inc();
+        self.write_bytes.add(bytes as u64);
+    }
+
+    /// Records a sync started at `start`.
+    pub(crate) fn on_sync(&self, start: Instant) {
+        self.syncs.inc();
+        self.sync_micros.add(start.elapsed().as_micros() as u64);
+    }
+
+    pub(crate) fn snapshot(&self) -> FileIoStats {
+        FileIoStats {
+            reads: self.reads.get(),
+            read_bytes: self.read_bytes.get(),
+            writes: self.writes.get(),
+            write_bytes: self.write_bytes.get(),
+            syncs: self.syncs.get(),
+            sync_micros: self.sync_micros.get(),
         }
     }
 }
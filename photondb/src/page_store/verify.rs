@@ -0,0 +1,99 @@
+use std::io::ErrorKind;
+
+use super::*;
+use crate::page::check_page;
+
+/// A report produced by verifying the checksums of pages in page files.
+#[derive(Clone, Debug, Default)]
+pub struct ChecksumReport {
+    /// The number of page files verified.
+    pub num_files: usize,
+    /// The number of pages verified.
+    pub num_pages: usize,
+    /// The number of bytes of pages verified.
+    pub num_bytes: u64,
+    /// The pages that fail to verify.
+    pub corrupted_pages: Vec<CorruptedPage>,
+}
+
+impl ChecksumReport {
+    /// Returns true if no corrupted page is found.
+    pub fn is_ok(&self) -> bool {
+        self.corrupted_pages.is_empty()
+    }
+}
+
+/// A page that fails to verify.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptedPage {
+    /// The id of the file that contains the page.
+    pub file_id: u32,
+    /// The address of the page.
+    pub page_addr: u64,
+    /// The id of the page, or [`None`] if it is not recorded in the file.
+    pub page_id: Option<u64>,
+}
+
+impl<E: Env> PageStore<E> {
+    /// Reads all active pages in page files to verify their checksums.
+    ///
+    /// Pages that fail to verify, decompress or decode, or that are truncated,
+    /// are collected into the returned report. The table should be quiescent,
+    /// since files reclaimed during verification can not be read.
+    pub(crate) async fn verify_checksums(&self) -> Result<ChecksumReport> {
+        let version = self.version();
+        let mut report = ChecksumReport::default();
+        let mut files: Vec<_> = version.file_infos().values().collect();
+        files.sort_unstable_by_key(|info| info.meta().file_id);
+        for info in files {
+            let meta = info.meta();
+            report.num_files += 1;
+            let mut groups: Vec<_> = meta
+                .page_groups
+                .keys()
+                .filter_map(|id| version.page_groups().get(id))
+                .collect();
+            groups.sort_unstable_by_key(|group| group.meta().group_id);
+            let mut corrupted_addrs = Vec::new();
+            for group in groups {
+                for page_addr in group.iter() {
+                    let Some(handle) = group.get_page_handle(page_addr) else {
+                        continue;
+                    };
+                    report.num_pages += 1;
+                    report.num_bytes += handle.size as u64;
+                    match self
+                        .page_files
+                        .read_file_page(meta.file_id, meta, handle)
+                        .await
+                    {
+                        Ok(buf) if check_page(&buf).is_some() => {}
+                        Ok(_) => corrupted_addrs.push(page_addr),
+                        Err(err) if err.is_corrupted() => corrupted_addrs.push(page_addr),
+                        Err(Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                            corrupted_addrs.push(page_addr)
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            if corrupted_addrs.is_empty() {
+                continue;
+            }
+            let file_meta = self.page_files.read_file_meta(meta.file_id).await?;
+            for page_addr in corrupted_addrs {
+                let page_id = file_meta
+                    .page_tables
+                    .get(&((page_addr >> 32) as u32))
+                    .and_then(|table| table.get(&page_addr))
+                    .copied();
+                report.corrupted_pages.push(CorruptedPage {
+                    file_id: meta.file_id,
+                    page_addr,
+                    page_id,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
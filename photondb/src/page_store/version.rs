@@ -247,6 +247,12 @@ impl Version {
         }
     }
 
+    /// Returns the smallest LSN of the writes to the write buffers that are
+    /// not flushed yet, or `u64::MAX` if there is none.
+    pub(crate) fn min_buffered_lsn(&self) -> u64 {
+        self.buffer_set.current().min_buffered_lsn()
+    }
+
     pub(crate) fn min_write_buffer(&self) -> Arc<WriteBuffer> {
         let current = self.buffer_set.current();
         current
@@ -0,0 +1,428 @@
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use parking_lot::Mutex;
+
+/// The metadata entry that stores the flushed LSN, so that LSNs assigned
+/// after a restart are larger than all LSNs written before.
+pub(crate) const FLUSHED_LSN_METADATA: &str = "flushed_lsn";
+
+/// A callback invoked with the durable LSN once it reaches a given LSN.
+pub(crate) type DurableCallback = Box<dyn FnOnce(u64) + Send>;
+
+/// The number of slots for the writes in progress.
+const NUM_SLOTS: usize = 256;
+
+/// The LSN of an empty slot, or of a write buffer without writes.
+const NO_LSN: u64 = u64::MAX;
+
+/// Tracks the LSNs of the writes that are flushed to page files.
+///
+/// A write is pending from when it begins until the write buffer it is
+/// recorded to is flushed. The durable LSN is the largest LSN of the flushed
+/// writes, capped below the smallest LSN of the pending writes. It goes
+/// backwards if a write with a smaller LSN begins after it advances.
+///
+/// Writes only touch atomics: a write in progress holds a slot with its LSN,
+/// and the range of LSNs recorded to a write buffer is kept in the
+/// [`BufferedLsns`] of the buffer. The durable LSN is computed from them when
+/// it is queried or a write buffer is flushed.
+pub(crate) struct LsnWatermark {
+    /// The largest LSN of the flushed writes.
+    flushed: AtomicU64,
+    /// The LSNs of the writes in progress.
+    slots: Box<[AtomicU64]>,
+    /// The LSNs of the writes in progress when all slots are taken, with the
+    /// number of writes of each.
+    overflow: Mutex<BTreeMap<u64, usize>>,
+    callbacks: Mutex<BTreeMap<u64, Vec<DurableCallback>>>,
+}
+
+impl Default for LsnWatermark {
+    fn default() -> Self {
+        LsnWatermark {
+            flushed: AtomicU64::new(0),
+            slots: (0..NUM_SLOTS).map(|_| AtomicU64::new(NO_LSN)).collect(),
+            overflow: Mutex::default(),
+            callbacks: Mutex::default(),
+        }
+    }
+}
+
+/// The range of LSNs of the writes recorded to a write buffer that is not
+/// flushed yet.
+pub(crate) struct BufferedLsns {
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for BufferedLsns {
+    fn default() -> Self {
+        BufferedLsns {
+            min: AtomicU64::new(NO_LSN),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl BufferedLsns {
+    /// Records the range of LSNs of a write to the buffer.
+    ///
+    /// This must be called before the writer guard of the buffer is released,
+    /// so that the range is complete once the buffer is flushable.
+    pub(crate) fn record(&self, min_lsn: u64, max_lsn: u64) {
+        self.max.fetch_max(max_lsn, Ordering::SeqCst);
+        self.min.fetch_min(min_lsn, Ordering::SeqCst);
+    }
+
+    /// Returns the smallest LSN of the writes, or `u64::MAX` if there is none
+    /// or the buffer is flushed.
+    pub(crate) fn min(&self) -> u64 {
+        self.min.load(Ordering::SeqCst)
+    }
+
+    /// Returns the largest LSN of the writes, or `None` if there is none or
+    /// the buffer is flushed.
+    fn max(&self) -> Option<u64> {
+        if self.min() == NO_LSN {
+            None
+        } else {
+            Some(self.max.load(Ordering::SeqCst))
+        }
+    }
+}
+
+/// A write in progress, which is pending until it is dropped.
+pub(crate) struct PendingWrite<'a> {
+    watermark: &'a LsnWatermark,
+    slot: Option<usize>,
+    lsn: u64,
+}
+
+impl PendingWrite<'_> {
+    pub(crate) fn lsn(&self) -> u64 {
+        self.lsn
+    }
+}
+
+impl Drop for PendingWrite<'_> {
+    fn drop(&mut self) {
+        // The write may be aborted, which is not recorded to a write buffer.
+        // Callbacks waiting for it are invoked on the next flush.
+        self.watermark.unpend(self.slot, self.lsn);
+    }
+}
+
+impl LsnWatermark {
+    /// Returns the largest LSN of the flushed writes.
+    pub(crate) fn flushed_lsn(&self) -> u64 {
+        self.flushed.load(Ordering::SeqCst)
+    }
+
+    /// Returns the LSN that the writes at or below it are durable.
+    ///
+    /// `min_buffered` returns the smallest LSN of the write buffers that are
+    /// not flushed yet. It is called after the writes in progress are read,
+    /// so that a write recorded meanwhile is seen by either of them.
+    pub(crate) fn durable_lsn(&self, min_buffered: impl FnOnce() -> u64) -> u64 {
+        let pending = self.min_pending();
+        let buffered = min_buffered();
+        let flushed = self.flushed_lsn();
+        match pending.min(buffered) {
+            NO_LSN => flushed,
+            pending => flushed.min(pending.saturating_sub(1)),
+        }
+    }
+
+    /// Begins a write with the LSN, which must be kept until the write is
+    /// recorded to a write buffer.
+    pub(crate) fn begin_write(&self, lsn: u64) -> PendingWrite<'_> {
+        PendingWrite {
+            watermark: self,
+            slot: self.pend(lsn),
+            lsn,
+        }
+    }
+
+    /// Begins a write with the LSN returned by `f`.
+    ///
+    /// A slot is taken before the LSN is assigned, so that increasing LSNs are
+    /// pending before they are visible to others. The slot holds the LSN
+    /// after the flushed one meanwhile, which is not larger than the assigned
+    /// one unless it is assigned below the flushed LSN.
+    pub(crate) fn begin_write_with(&self, f: impl FnOnce() -> u64) -> PendingWrite<'_> {
+        let reserved = self.flushed_lsn().saturating_add(1);
+        let slot = self.pend(reserved);
+        let lsn = f();
+        match slot {
+            Some(index) => self.slots[index].store(lsn, Ordering::SeqCst),
+            None => {
+                let mut overflow = self.overflow.lock();
+                remove_overflow(&mut overflow, reserved);
+                *overflow.entry(lsn).or_default() += 1;
+            }
+        }
+        PendingWrite {
+            watermark: self,
+            slot,
+            lsn,
+        }
+    }
+
+    /// Returns the flushed LSN once the write buffer is flushed, or `None` if
+    /// no write is recorded to it.
+    pub(crate) fn flushed_lsn_with(&self, buffered: &BufferedLsns) -> Option<u64> {
+        let max_lsn = buffered.max()?;
+        Some(self.flushed_lsn().max(max_lsn))
+    }
+
+    /// Advances the watermark after the write buffer is flushed, and invokes
+    /// the callbacks whose LSNs are durable.
+    ///
+    /// `min_buffered` is the same as [`Self::durable_lsn`].
+    pub(crate) fn on_flushed(&self, buffered: &BufferedLsns, min_buffered: impl FnOnce() -> u64) {
+        if let Some(max_lsn) = buffered.max() {
+            // The flushed LSN covers the writes before they are removed from
+            // the buffer.
+            self.flushed.fetch_max(max_lsn, Ordering::SeqCst);
+            buffered.min.store(NO_LSN, Ordering::SeqCst);
+        }
+        self.notify(min_buffered);
+    }
+
+    /// Advances the flushed LSN, e.g. to the LSN recovered from the manifest.
+    pub(crate) fn advance(&self, flushed: u64) {
+        self.flushed.fetch_max(flushed, Ordering::SeqCst);
+    }
+
+    /// Registers a callback that is invoked once writes at or below the LSN
+    /// are durable.
+    ///
+    /// The callback is invoked immediately if the LSN is durable already.
+    /// Otherwise, it is invoked when a write buffer is flushed.
+    pub(crate) fn register(
+        &self,
+        lsn: u64,
+        callback: DurableCallback,
+        min_buffered: impl FnOnce() -> u64,
+    ) {
+        // The lock is held until the callback is added, so that it is not
+        // missed by the flushes that complete meanwhile.
+        let mut callbacks = self.callbacks.lock();
+        let durable = self.durable_lsn(min_buffered);
+        if lsn <= durable {
+            drop(callbacks);
+            callback(durable);
+            return;
+        }
+        callbacks.entry(lsn).or_default().push(callback);
+    }
+
+    /// Invokes the callbacks whose LSNs are durable.
+    fn notify(&self, min_buffered: impl FnOnce() -> u64) {
+        let mut callbacks = self.callbacks.lock();
+        if callbacks.is_empty() {
+            return;
+        }
+        let durable = self.durable_lsn(min_buffered);
+        let ready = {
+            let rest = callbacks.split_off(&(durable.saturating_add(1)));
+            std::mem::replace(&mut *callbacks, rest)
+        };
+        drop(callbacks);
+        for callback in ready.into_values().flatten() {
+            callback(durable);
+        }
+    }
+
+    /// Takes a slot for a write in progress, or records it to the overflow
+    /// if all slots are taken.
+    fn pend(&self, lsn: u64) -> Option<usize> {
+        let start = current_slot();
+        for i in 0..NUM_SLOTS {
+            let index = (start + i) % NUM_SLOTS;
+            let slot = &self.slots[index];
+            if slot.load(Ordering::Relaxed) == NO_LSN
+                && slot
+                    .compare_exchange(NO_LSN, lsn, Ordering::SeqCst, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return Some(index);
+            }
+        }
+        *self.overflow.lock().entry(lsn).or_default() += 1;
+        None
+    }
+
+    fn unpend(&self, slot: Option<usize>, lsn: u64) {
+        match slot {
+            Some(index) => self.slots[index].store(NO_LSN, Ordering::SeqCst),
+            None => remove_overflow(&mut self.overflow.lock(), lsn),
+        }
+    }
+
+    /// Returns the smallest LSN of the writes in progress.
+    fn min_pending(&self) -> u64 {
+        let pending = self
+            .slots
+            .iter()
+            .map(|slot| slot.load(Ordering::SeqCst))
+            .min()
+            .unwrap_or(NO_LSN);
+        let overflow = self.overflow.lock();
+        match overflow.keys().next() {
+            Some(&lsn) => pending.min(lsn),
+            None => pending,
+        }
+    }
+}
+
+fn remove_overflow(overflow: &mut BTreeMap<u64, usize>, lsn: u64) {
+    if let Some(count) = overflow.get_mut(&lsn) {
+        *count -= 1;
+        if *count == 0 {
+            overflow.remove(&lsn);
+        }
+    }
+}
+
+/// Returns the slot that the writes of the current thread start to probe.
+///
+/// Threads are assigned to slots in a round-robin manner.
+fn current_slot() -> usize {
+    static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static SLOT: usize = NEXT_SLOT.fetch_add(1, Ordering::Relaxed) % NUM_SLOTS;
+    }
+    SLOT.with(|slot| *slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn register(
+        watermark: &LsnWatermark,
+        buffers: &[BufferedLsns],
+        fired: &Arc<Mutex<Vec<(u64, u64)>>>,
+        lsn: u64,
+    ) {
+        let fired = fired.clone();
+        watermark.register(
+            lsn,
+            Box::new(move |durable| fired.lock().push((lsn, durable))),
+            || min_buffered(buffers),
+        );
+    }
+
+    fn min_buffered(buffers: &[BufferedLsns]) -> u64 {
+        buffers.iter().map(|b| b.min()).min().unwrap_or(NO_LSN)
+    }
+
+    fn durable_lsn(watermark: &LsnWatermark, buffers: &[BufferedLsns]) -> u64 {
+        watermark.durable_lsn(|| min_buffered(buffers))
+    }
+
+    fn on_flushed(watermark: &LsnWatermark, buffers: &[BufferedLsns], index: usize) {
+        watermark.on_flushed(&buffers[index], || min_buffered(buffers));
+    }
+
+    #[test]
+    fn advance_and_register() {
+        let watermark = LsnWatermark::default();
+        let buffers: [BufferedLsns; 4] = Default::default();
+        let fired = Arc::default();
+        for lsn in [1, 3, 5] {
+            register(&watermark, &buffers, &fired, lsn);
+        }
+
+        // Writes at 2 are not flushed yet.
+        buffers[1].record(1, 1);
+        buffers[2].record(2, 2);
+        buffers[3].record(3, 4);
+        assert_eq!(watermark.flushed_lsn_with(&buffers[2]), Some(2));
+        assert_eq!(watermark.flushed_lsn_with(&buffers[0]), None);
+        on_flushed(&watermark, &buffers, 1);
+        on_flushed(&watermark, &buffers, 3);
+        assert_eq!(watermark.flushed_lsn(), 4);
+        assert_eq!(durable_lsn(&watermark, &buffers), 1);
+        assert_eq!(*fired.lock(), [(1, 1)]);
+
+        on_flushed(&watermark, &buffers, 2);
+        assert_eq!(watermark.flushed_lsn(), 4);
+        assert_eq!(durable_lsn(&watermark, &buffers), 4);
+        assert_eq!(*fired.lock(), [(1, 1), (3, 4)]);
+        watermark.register(2, Box::new(|durable| assert_eq!(durable, 4)), || {
+            min_buffered(&buffers)
+        });
+        assert_eq!(fired.lock().len(), 2);
+    }
+
+    #[test]
+    fn out_of_order_writes() {
+        let watermark = LsnWatermark::default();
+        let buffers: [BufferedLsns; 4] = Default::default();
+        let fired = Arc::default();
+        let mut next_lsn = 2;
+        let mut assign = || {
+            next_lsn += 1;
+            next_lsn
+        };
+
+        // The write at 4 is flushed before the write at 3 is recorded.
+        let write3 = watermark.begin_write_with(&mut assign);
+        let write4 = watermark.begin_write_with(&mut assign);
+        assert_eq!((write3.lsn(), write4.lsn()), (3, 4));
+        register(&watermark, &buffers, &fired, 3);
+        buffers[1].record(4, 4);
+        drop(write4);
+        on_flushed(&watermark, &buffers, 1);
+        assert_eq!(watermark.flushed_lsn(), 4);
+        assert_eq!(durable_lsn(&watermark, &buffers), 2);
+        assert!(fired.lock().is_empty());
+
+        buffers[2].record(3, 3);
+        drop(write3);
+        assert!(fired.lock().is_empty());
+        on_flushed(&watermark, &buffers, 2);
+        assert_eq!(*fired.lock(), [(3, 4)]);
+
+        // A write below the durable LSN is pending until it is flushed.
+        let write1 = watermark.begin_write(1);
+        assert_eq!(durable_lsn(&watermark, &buffers), 0);
+        register(&watermark, &buffers, &fired, 1);
+        buffers[3].record(1, 1);
+        drop(write1);
+        assert_eq!(fired.lock().len(), 1);
+        on_flushed(&watermark, &buffers, 3);
+        assert_eq!(*fired.lock(), [(3, 4), (1, 4)]);
+    }
+
+    #[test]
+    fn overflow_slots() {
+        let watermark = LsnWatermark::default();
+        watermark.advance(10);
+        let mut writes: Vec<_> = (0..NUM_SLOTS as u64)
+            .map(|i| watermark.begin_write(100 + i))
+            .collect();
+        assert!(writes.iter().all(|w| w.slot.is_some()));
+
+        // Writes beyond the slots are still pending.
+        let write5 = watermark.begin_write_with(|| 5);
+        assert_eq!(write5.slot, None);
+        assert_eq!(watermark.durable_lsn(|| NO_LSN), 4);
+        drop(write5);
+        assert_eq!(watermark.durable_lsn(|| NO_LSN), 10);
+        writes.truncate(1);
+        let write6 = watermark.begin_write(6);
+        assert!(write6.slot.is_some());
+        assert_eq!(watermark.durable_lsn(|| NO_LSN), 5);
+        drop(writes);
+        drop(write6);
+        assert_eq!(watermark.durable_lsn(|| NO_LSN), 10);
+    }
+}
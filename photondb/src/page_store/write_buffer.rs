@@ -6,8 +6,9 @@ use std::{
 
 use bitflags::bitflags;
 use log::info;
+use parking_lot::Mutex;
 
-use super::Result;
+use super::{BufferedLsns, MetadataEdit, Result};
 use crate::{
     page::{PageBuf, PageRef},
     page_store::Error,
@@ -28,6 +29,12 @@ where
 
     /// A latch for flushed notify.
     flush_latch: Latch,
+
+    /// The metadata edits recorded when the buffer is flushed.
+    metadata: Mutex<Vec<MetadataEdit>>,
+
+    /// The range of LSNs of the writes to the buffer.
+    lsns: BufferedLsns,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -105,6 +112,8 @@ impl WriteBuffer {
             buf_size,
             buffer_state: AtomicU64::new(default_state.apply()),
             flush_latch: Latch::new(1),
+            metadata: Mutex::default(),
+            lsns: BufferedLsns::default(),
         }
     }
 
@@ -128,6 +137,12 @@ impl WriteBuffer {
         self.buffer_state().allocated == 0
     }
 
+    /// Returns the bytes allocated in the buffer.
+    #[inline]
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.buffer_state().allocated as usize
+    }
+
     #[inline]
     pub(crate) async fn wait_flushed(&self) {
         self.flush_latch.wait().await;
@@ -138,6 +153,37 @@ impl WriteBuffer {
         self.flush_latch.count_down();
     }
 
+    /// Attaches a metadata edit to the buffer, which is recorded to the
+    /// manifest when the buffer is flushed.
+    ///
+    /// `Err(Error::Again)` is returned if the buffer has been sealed.
+    pub(crate) fn add_metadata(&self, edit: MetadataEdit) -> Result<()> {
+        let mut metadata = self.metadata.lock();
+        // The edits are taken after the buffer is sealed, so it is checked
+        // with the lock held.
+        if self.is_sealed() {
+            return Err(Error::Again);
+        }
+        metadata.push(edit);
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn has_metadata(&self) -> bool {
+        !self.metadata.lock().is_empty()
+    }
+
+    /// Returns the range of LSNs of the writes to the buffer.
+    #[inline]
+    pub(crate) fn buffered_lsns(&self) -> &BufferedLsns {
+        &self.lsns
+    }
+
+    /// Takes the metadata edits attached to the buffer.
+    pub(crate) fn take_metadata(&self) -> Vec<MetadataEdit> {
+        std::mem::take(&mut *self.metadata.lock())
+    }
+
     /// Allocate pages and record dealloc pages in one batch. This operation
     /// will acquire a writer guard.
     #[cfg(test)]
@@ -0,0 +1,114 @@
+use std::{cell::Cell, fmt};
+
+thread_local! {
+    // The context of the current thread, or `None` if recording is disabled.
+    static CONTEXT: Cell<Option<PerfContext>> = const { Cell::new(None) };
+}
+
+/// Fine-grained counters of the operations executed on a thread.
+///
+/// Recording is disabled by default and can be enabled per thread with
+/// [`PerfContext::enable`]. The counters are recorded on the thread that
+/// runs an operation, which is the calling thread for the synchronous APIs.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfContext {
+    /// The number of pages visited.
+    pub pages_visited: u64,
+    /// The number of delta pages read in page chains.
+    pub deltas_read: u64,
+    /// The number of key comparisons in binary searches within pages.
+    pub key_comparisons: u64,
+    /// The number of bytes of items decoded from pages.
+    pub bytes_decoded: u64,
+}
+
+impl PerfContext {
+    /// Enables recording on the current thread.
+    ///
+    /// This keeps the counters if recording is already enabled.
+    pub fn enable() {
+        CONTEXT.with(|c| c.set(Some(c.get().unwrap_or_default())));
+    }
+
+    /// Disables recording on the current thread and clears the counters.
+    pub fn disable() {
+        CONTEXT.with(|c| c.set(None));
+    }
+
+    /// Returns true if recording is enabled on the current thread.
+    pub fn is_enabled() -> bool {
+        CONTEXT.with(|c| c.get().is_some())
+    }
+
+    /// Returns the counters recorded on the current thread.
+    ///
+    /// Returns zero counters if recording is disabled.
+    pub fn get() -> PerfContext {
+        CONTEXT.with(|c| c.get().unwrap_or_default())
+    }
+
+    /// Resets the counters on the current thread.
+    pub fn reset() {
+        CONTEXT.with(|c| {
+            if c.get().is_some() {
+                c.set(Some(PerfContext::default()));
+            }
+        });
+    }
+}
+
+impl fmt::Display for PerfContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PerfContext: pages_visited: {}, \
+                deltas_read: {}, \
+                key_comparisons: {}, \
+                bytes_decoded: {}",
+            self.pages_visited, self.deltas_read, self.key_comparisons, self.bytes_decoded
+        )
+    }
+}
+
+/// Updates the context of the current thread if recording is enabled.
+#[inline]
+pub(crate) fn record(f: impl FnOnce(&mut PerfContext)) {
+    CONTEXT.with(|c| {
+        if let Some(mut ctx) = c.get() {
+            f(&mut ctx);
+            c.set(Some(ctx));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perf_context() {
+        record(|ctx| ctx.pages_visited += 1);
+        assert!(!PerfContext::is_enabled());
+        assert_eq!(PerfContext::get(), PerfContext::default());
+
+        PerfContext::enable();
+        record(|ctx| ctx.pages_visited += 1);
+        PerfContext::enable();
+        record(|ctx| ctx.bytes_decoded += 8);
+        let ctx = PerfContext::get();
+        assert_eq!(ctx.pages_visited, 1);
+        assert_eq!(ctx.bytes_decoded, 8);
+
+        // Counters are not shared across threads.
+        std::thread::spawn(|| assert!(!PerfContext::is_enabled()))
+            .join()
+            .unwrap();
+
+        PerfContext::reset();
+        assert!(PerfContext::is_enabled());
+        assert_eq!(PerfContext::get(), PerfContext::default());
+        PerfContext::disable();
+        assert!(!PerfContext::is_enabled());
+    }
+}
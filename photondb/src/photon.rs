@@ -5,8 +5,8 @@
 
 use std::{ops::Deref, path::Path};
 
-pub use crate::raw::TableStats;
-use crate::{env::Photon, raw, Result, TableOptions};
+pub use crate::raw::{LiveStats, MaintenanceCommand, MaintenanceDebt, TableStats};
+use crate::{env::Photon, raw, RepairReport, Result, TableOptions};
 
 /// A reference to a latch-free, log-structured table that stores sorted
 /// key-value entries.
@@ -25,6 +25,14 @@ impl Table {
         Ok(Self(table))
     }
 
+    /// Repairs a corrupted table in the path.
+    ///
+    /// This is the same as [`raw::Table::repair`] with the [`Photon`]
+    /// environment.
+    pub async fn repair<P: AsRef<Path>>(path: P, options: TableOptions) -> Result<RepairReport> {
+        raw::Table::repair(Photon, path, options).await
+    }
+
     /// Closes the table if this is the only reference to it.
     ///
     /// This is the same as [`raw::Table::close`] with the [`Photon`]
@@ -47,3 +55,9 @@ pub type Guard<'a> = raw::Guard<'a, Photon>;
 
 /// An iterator over pages in a table.
 pub type Pages<'a, 't> = raw::Pages<'a, 't, Photon>;
+
+/// A consistent view of a table at an LSN.
+pub type Snapshot = raw::Snapshot<Photon>;
+
+/// A cursor over entries in a table.
+pub type Cursor<'a, 't> = raw::Cursor<'a, 't, Photon>;
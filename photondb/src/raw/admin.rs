@@ -0,0 +1,497 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Weak,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{channel::mpsc as async_mpsc, StreamExt};
+use log::{error, info, warn};
+use parking_lot::Mutex;
+
+use super::{MaintenanceDebt, TableStats};
+use crate::{
+    env::Env,
+    page_store::PageStore,
+    tree::{Histogram, LatencyStats, Tree, HISTOGRAM_BUCKETS},
+    Error, Result,
+};
+
+/// The name of the admin file in the table directory.
+const ADMIN_FILE_NAME: &str = "ADMIN";
+
+/// The name of the file that the live stats are published to.
+const STATS_FILE_NAME: &str = "STATS";
+
+/// A maintenance command that can be triggered on a running table.
+///
+/// Commands are run by [`Table::run_maintenance`], or submitted to a table in
+/// another process with [`MaintenanceCommand::submit`].
+///
+/// [`Table::run_maintenance`]: super::Table::run_maintenance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaintenanceCommand {
+    /// Flushes the active write buffer.
+    Flush,
+    /// Consolidates all pages that have delta pages.
+    Consolidate,
+    /// Compacts all page files that contain obsolete pages.
+    Reclaim,
+}
+
+impl MaintenanceCommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Flush => "flush",
+            Self::Consolidate => "consolidate",
+            Self::Reclaim => "reclaim",
+        }
+    }
+
+    /// Submits commands to the table in the directory.
+    ///
+    /// The commands are written to an admin file in the directory, which is
+    /// polled by the table if [`Options::admin_poll_period`] is set. The
+    /// commands are run in order in the background, and their results are
+    /// logged.
+    ///
+    /// Returns [`Error::Busy`] if the commands submitted before are not
+    /// picked up yet.
+    ///
+    /// The file is written on the filesystem of the operating system, which
+    /// is the only one that a table polls for commands.
+    ///
+    /// [`Options::admin_poll_period`]: crate::TableOptions::admin_poll_period
+    pub fn submit<P: AsRef<Path>>(dir: P, commands: &[MaintenanceCommand]) -> Result<()> {
+        let dir = dir.as_ref();
+        let mut content = String::new();
+        for cmd in commands {
+            content.push_str(cmd.as_str());
+            content.push('\n');
+        }
+        let tmp = dir.join(format!("{ADMIN_FILE_NAME}.{}.tmp", std::process::id()));
+        fs::write(&tmp, content)?;
+        // Links the complete file to the admin file, which fails if the admin
+        // file exists, so the table never reads a partial file.
+        let result = fs::hard_link(&tmp, dir.join(ADMIN_FILE_NAME));
+        fs::remove_file(&tmp)?;
+        match result {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Err(Error::Busy),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MaintenanceCommand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "flush" => Ok(Self::Flush),
+            "consolidate" => Ok(Self::Consolidate),
+            "reclaim" => Ok(Self::Reclaim),
+            _ => Err(Error::InvalidArgument(format!(
+                "invalid maintenance command {s:?}"
+            ))),
+        }
+    }
+}
+
+/// Runs a maintenance command on the tree and the store.
+pub(super) async fn run_command<E: Env>(
+    tree: &Tree,
+    store: &PageStore<E>,
+    cmd: MaintenanceCommand,
+) -> Result<()> {
+    match cmd {
+        MaintenanceCommand::Flush => store.flush(&Default::default()).await,
+        MaintenanceCommand::Consolidate => {
+            let ids = tree.begin(store.guard()).chained_pages()?;
+            let mut count = 0;
+            for id in ids {
+                // Each page is consolidated in a separate transaction, so the
+                // pass doesn't keep an old version of the store.
+                if tree.begin(store.guard()).consolidate_page_by_id(id).await? {
+                    count += 1;
+                }
+            }
+            info!("Consolidate {count} pages");
+        }
+        MaintenanceCommand::Reclaim => store.reclaim_all().await?,
+    }
+    Ok(())
+}
+
+/// Statistics of a running table, published to the table directory.
+///
+/// A table opened with [`Options::admin_poll_period`] publishes the stats in
+/// that period, so that they can be watched from another process with
+/// [`LiveStats::read`].
+///
+/// [`Options::admin_poll_period`]: crate::TableOptions::admin_poll_period
+#[non_exhaustive]
+#[derive(Clone, Debug, Default)]
+pub struct LiveStats {
+    /// The time the stats are published, in milliseconds since the Unix
+    /// epoch.
+    pub time_ms: u64,
+    /// The period covered by the latencies and cache lookups below.
+    pub period: Duration,
+    /// The latencies of operations in the period.
+    pub latency: LatencyStats,
+    /// The number of page cache hits in the period.
+    pub page_cache_hits: u64,
+    /// The number of page cache misses in the period.
+    pub page_cache_misses: u64,
+    /// The maintenance debt when the stats are published.
+    pub debt: MaintenanceDebt,
+}
+
+impl LiveStats {
+    /// Reads the latest stats published by the table in the directory.
+    ///
+    /// Returns [`None`] if no stats are published, and [`Error::Corrupted`]
+    /// if the stats can not be decoded.
+    pub fn read<P: AsRef<Path>>(dir: P) -> Result<Option<Self>> {
+        match fs::read_to_string(dir.as_ref().join(STATS_FILE_NAME)) {
+            Ok(content) => Self::decode(&content)
+                .map(Some)
+                .ok_or_else(Error::corrupted),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Publishes the stats to the directory, replacing the previous ones.
+    fn publish(&self, dir: &Path) -> io::Result<()> {
+        let tmp = dir.join(format!("{STATS_FILE_NAME}.tmp"));
+        fs::write(&tmp, self.encode())?;
+        fs::rename(&tmp, dir.join(STATS_FILE_NAME))
+    }
+
+    /// Encodes the stats as lines of a name followed by numbers.
+    fn encode(&self) -> String {
+        let mut s = format!(
+            "time_ms {}\nperiod_ms {}\n",
+            self.time_ms,
+            self.period.as_millis()
+        );
+        for (name, hist) in self.histograms() {
+            s += &format!("{name} {} {}", hist.count, hist.sum);
+            for n in hist.buckets {
+                s += &format!(" {n}");
+            }
+            s.push('\n');
+        }
+        s += &format!(
+            "page_cache {} {}\ndebt {} {} {}\n",
+            self.page_cache_hits,
+            self.page_cache_misses,
+            self.debt.pending_consolidations,
+            self.debt.dirty_bytes,
+            self.debt.garbage_bytes
+        );
+        s
+    }
+
+    fn decode(content: &str) -> Option<Self> {
+        let mut stats = Self::default();
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let values = fields
+                .map(|v| v.parse().ok())
+                .collect::<Option<Vec<u64>>>()?;
+            match (name, values.as_slice()) {
+                ("time_ms", &[v]) => stats.time_ms = v,
+                ("period_ms", &[v]) => stats.period = Duration::from_millis(v),
+                ("page_cache", &[hits, misses]) => {
+                    stats.page_cache_hits = hits;
+                    stats.page_cache_misses = misses;
+                }
+                ("debt", &[pending, dirty, garbage]) => {
+                    stats.debt.pending_consolidations = pending;
+                    stats.debt.dirty_bytes = dirty;
+                    stats.debt.garbage_bytes = garbage;
+                }
+                (name, &[count, sum, ref buckets @ ..]) if buckets.len() == HISTOGRAM_BUCKETS => {
+                    let hist = match name {
+                        "get" => &mut stats.latency.get,
+                        "put" => &mut stats.latency.put,
+                        "delete" => &mut stats.latency.delete,
+                        "scan" => &mut stats.latency.scan,
+                        _ => return None,
+                    };
+                    hist.count = count;
+                    hist.sum = sum;
+                    hist.buckets.copy_from_slice(buckets);
+                }
+                _ => return None,
+            }
+        }
+        Some(stats)
+    }
+
+    fn histograms(&self) -> [(&'static str, &Histogram); 4] {
+        [
+            ("get", &self.latency.get),
+            ("put", &self.latency.put),
+            ("delete", &self.latency.delete),
+            ("scan", &self.latency.scan),
+        ]
+    }
+}
+
+/// Collects the live stats of a table with the deltas since the last time.
+struct StatsPublisher {
+    last: TableStats,
+    last_time: Instant,
+}
+
+impl StatsPublisher {
+    fn new() -> Self {
+        Self {
+            last: TableStats::default(),
+            last_time: Instant::now(),
+        }
+    }
+
+    fn publish<E: Env>(&mut self, dir: &Path, tree: &Tree, store: &PageStore<E>) -> Result<()> {
+        let stats = TableStats {
+            tree: tree.stats(),
+            store: store.stats(),
+        };
+        let delta = stats.sub(&self.last);
+        let now = Instant::now();
+        let live = LiveStats {
+            time_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            period: now - self.last_time,
+            latency: delta.tree.latency,
+            page_cache_hits: delta.store.page_cache.lookup_hit,
+            page_cache_misses: delta.store.page_cache.lookup_miss,
+            debt: MaintenanceDebt::of(tree, store)?,
+        };
+        live.publish(dir)?;
+        self.last = stats;
+        self.last_time = now;
+        Ok(())
+    }
+}
+
+/// Polls the admin file in the table directory, and runs the commands in it.
+///
+/// A background thread polls the file and publishes the live stats, and the
+/// commands are sent to a task spawned in the environment of the table. The
+/// thread and the task only hold the store while using it, so they don't
+/// keep the table open.
+///
+/// The files are accessed with [`std::fs`] on the thread, as another process
+/// shares them through the filesystem, so the poller is only spawned in a
+/// native environment.
+pub(super) struct AdminPoller<E: Env> {
+    stop: Mutex<Option<Sender<()>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    task: Mutex<Option<E::JoinHandle<()>>>,
+}
+
+impl<E: Env> AdminPoller<E> {
+    /// Spawns a poller for the table in the directory.
+    ///
+    /// Returns an [`io::ErrorKind::Unsupported`] error if the environment is
+    /// not native.
+    pub(super) fn spawn(
+        env: &E,
+        dir: &Path,
+        period: Duration,
+        tree: Arc<Tree>,
+        store: Weak<PageStore<E>>,
+    ) -> io::Result<Self> {
+        if !env.is_native() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "admin files are only polled in a native environment",
+            ));
+        }
+        let (tx, mut rx) = async_mpsc::unbounded::<MaintenanceCommand>();
+        let (task_tree, task_store) = (tree.clone(), store.clone());
+        let task = env.spawn_background(async move {
+            while let Some(cmd) = rx.next().await {
+                let Some(store) = task_store.upgrade() else {
+                    break;
+                };
+                match run_command(&task_tree, &store, cmd).await {
+                    Ok(()) => info!("Run maintenance command {cmd}"),
+                    Err(err) => error!("Run maintenance command {cmd}: {err}"),
+                }
+            }
+        });
+
+        let (stop, stop_rx) = mpsc::channel::<()>();
+        let dir = dir.to_owned();
+        let thread = thread::Builder::new()
+            .name("photondb-admin".into())
+            .spawn(move || {
+                let path = dir.join(ADMIN_FILE_NAME);
+                let mut publisher = StatsPublisher::new();
+                // Stops once the sender is dropped.
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(period) {
+                    for cmd in take_commands(&path) {
+                        if tx.unbounded_send(cmd).is_err() {
+                            return;
+                        }
+                    }
+                    let Some(store) = store.upgrade() else {
+                        return;
+                    };
+                    if let Err(err) = publisher.publish(&dir, &tree, &store) {
+                        warn!("Publish live stats: {err}");
+                    }
+                }
+            })?;
+        Ok(Self {
+            stop: Mutex::new(Some(stop)),
+            thread: Mutex::new(Some(thread)),
+            task: Mutex::new(Some(task)),
+        })
+    }
+
+    /// Stops polling, and waits for the running command to finish.
+    pub(super) async fn stop(&self) {
+        self.stop.lock().take();
+        if let Some(thread) = self.thread.lock().take() {
+            let _ = thread.join();
+        }
+        // The task is done once the thread drops the sender.
+        let task = self.task.lock().take();
+        if let Some(task) = task {
+            task.await;
+        }
+    }
+}
+
+impl<E: Env> fmt::Debug for AdminPoller<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminPoller").finish()
+    }
+}
+
+/// Takes the commands in the admin file, if it exists.
+///
+/// The file is renamed before it is read, so a file submitted later is not
+/// removed by mistake.
+fn take_commands(path: &Path) -> Vec<MaintenanceCommand> {
+    let taken = PathBuf::from(format!("{}.taken", path.display()));
+    match fs::rename(path, &taken) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            warn!("Take admin file {}: {err}", path.display());
+            return Vec::new();
+        }
+    }
+    let content = fs::read_to_string(&taken);
+    let _ = fs::remove_file(&taken);
+    let content = match content {
+        Ok(content) => content,
+        Err(err) => {
+            warn!("Read admin file {}: {err}", path.display());
+            return Vec::new();
+        }
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.parse() {
+            Ok(cmd) => Some(cmd),
+            Err(err) => {
+                warn!("Skip admin command: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::Mem, raw::Table, TableOptions};
+
+    #[test]
+    fn submit_and_take_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ADMIN_FILE_NAME);
+        assert!(take_commands(&path).is_empty());
+
+        let commands = [MaintenanceCommand::Flush, MaintenanceCommand::Reclaim];
+        MaintenanceCommand::submit(dir.path(), &commands).unwrap();
+        assert!(matches!(
+            MaintenanceCommand::submit(dir.path(), &commands),
+            Err(Error::Busy)
+        ));
+        assert_eq!(take_commands(&path), commands);
+        assert!(!path.exists());
+
+        fs::write(&path, "consolidate\n\nunknown\n").unwrap();
+        assert_eq!(take_commands(&path), [MaintenanceCommand::Consolidate]);
+        assert!("reclaim".parse::<MaintenanceCommand>().is_ok());
+        assert!("gc".parse::<MaintenanceCommand>().is_err());
+    }
+
+    #[test]
+    fn encode_and_decode_live_stats() {
+        let mut stats = LiveStats {
+            time_ms: 1,
+            period: Duration::from_millis(2),
+            page_cache_hits: 3,
+            page_cache_misses: 4,
+            ..Default::default()
+        };
+        stats.latency.put.count = 5;
+        stats.latency.put.sum = 6;
+        stats.latency.put.buckets[HISTOGRAM_BUCKETS - 1] = 7;
+        stats.debt.garbage_bytes = 8;
+
+        let decoded = LiveStats::decode(&stats.encode()).unwrap();
+        assert_eq!(decoded.encode(), stats.encode());
+        assert_eq!(decoded.latency.put.buckets, stats.latency.put.buckets);
+        assert_eq!(decoded.debt.garbage_bytes, 8);
+        assert!(LiveStats::decode("put 1 2 3").is_none());
+        assert!(LiveStats::decode("unknown 1").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(LiveStats::read(dir.path()).unwrap().is_none());
+        stats.publish(dir.path()).unwrap();
+        assert_eq!(LiveStats::read(dir.path()).unwrap().unwrap().time_ms, 1);
+    }
+
+    #[test]
+    fn native_env_only() {
+        let env = Mem::new();
+        env.run(async {
+            let options = TableOptions {
+                admin_poll_period: Some(Duration::from_secs(1)),
+                ..Default::default()
+            };
+            assert!(matches!(
+                Table::open(env.clone(), "/table", options).await,
+                Err(Error::InvalidArgument(_))
+            ));
+        });
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    cell::Cell,
+    fmt, io, mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use futures::{channel::mpsc as async_mpsc, StreamExt};
+use log::error;
+use parking_lot::Mutex;
+
+use super::Table;
+use crate::{
+    env::Env, util::cache_padded::CachePadded, BackgroundErrorEvent, Error, EventListener, Result,
+    WriteBatch,
+};
+
+/// The number of buffers of buffered writes.
+const NUM_BUFFERS: usize = 16;
+
+/// The approximate size of the metadata of a write in a buffer.
+const WRITE_OVERHEAD: usize = 16;
+
+/// Buffers writes per thread, so that small writes are written as batches.
+///
+/// Threads are assigned to buffers in a round-robin manner, so a buffer is
+/// rarely shared by threads.
+pub(super) struct WriteBuffers {
+    buffers: Box<[CachePadded<Mutex<Buffer>>]>,
+    max_size: usize,
+    // The first error of writes in the background that is not returned yet.
+    error: Mutex<Option<Error>>,
+}
+
+#[derive(Default)]
+struct Buffer {
+    batch: WriteBatch,
+    size: usize,
+}
+
+impl WriteBuffers {
+    pub(super) fn new(max_size: usize) -> Self {
+        Self {
+            buffers: (0..NUM_BUFFERS).map(|_| CachePadded::default()).collect(),
+            max_size,
+            error: Mutex::new(None),
+        }
+    }
+
+    /// Adds a write to the buffer of the current thread.
+    ///
+    /// Returns the writes in the buffer if it is full, which must be written
+    /// by the caller.
+    pub(super) fn add(&self, key: &[u8], value: Option<&[u8]>, lsn: u64) -> Option<WriteBatch> {
+        let mut buffer = self.buffers[current_buffer()].lock();
+        match value {
+            Some(value) => buffer.batch.put(key, lsn, value),
+            None => buffer.batch.delete(key, lsn),
+        }
+        buffer.size += key.len() + value.map_or(0, |v| v.len()) + WRITE_OVERHEAD;
+        if buffer.size < self.max_size {
+            return None;
+        }
+        buffer.size = 0;
+        Some(mem::take(&mut buffer.batch))
+    }
+
+    /// Takes the writes in all buffers.
+    pub(super) fn take_all(&self) -> Vec<WriteBatch> {
+        self.buffers
+            .iter()
+            .filter_map(|buffer| {
+                let mut buffer = buffer.lock();
+                buffer.size = 0;
+                let batch = mem::take(&mut buffer.batch);
+                (!batch.is_empty()).then_some(batch)
+            })
+            .collect()
+    }
+
+    /// Records an error of writes in the background, which is returned by
+    /// [`WriteBuffers::take_error`] later.
+    ///
+    /// Only the first error is kept until it is taken.
+    pub(super) fn set_error(&self, err: Error) {
+        self.error.lock().get_or_insert(err);
+    }
+
+    /// Takes the recorded error of writes in the background, if any.
+    pub(super) fn take_error(&self) -> Result<()> {
+        match self.error.lock().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the buffer of the current thread.
+fn current_buffer() -> usize {
+    static NEXT_BUFFER: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static BUFFER: Cell<Option<usize>> = Cell::new(None);
+    }
+    BUFFER.with(|buffer| match buffer.get() {
+        Some(i) => i,
+        None => {
+            let i = NEXT_BUFFER.fetch_add(1, Ordering::Relaxed) % NUM_BUFFERS;
+            buffer.set(Some(i));
+            i
+        }
+    })
+}
+
+/// Writes the buffered writes of a table in the background, which bounds the
+/// time that writes stay in the buffers.
+///
+/// A background thread ticks in the delay, and the writes are written by a
+/// task spawned in the environment of the table. The task holds a reference
+/// to the table until it is stopped, and writes the rest of the buffered
+/// writes before it exits. Errors of the writes are recorded in the buffers
+/// and reported to the event listener, since the writers have returned.
+pub(super) struct BufferFlusher<E: Env> {
+    stop: Mutex<Option<Sender<()>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    task: Mutex<Option<E::JoinHandle<()>>>,
+}
+
+impl<E: Env> BufferFlusher<E> {
+    /// Spawns a flusher for the table, which must not hold the flusher
+    /// itself.
+    pub(super) fn spawn(
+        env: &E,
+        table: Table<E>,
+        buffers: Arc<WriteBuffers>,
+        delay: Duration,
+        listener: Option<Arc<dyn EventListener>>,
+    ) -> io::Result<Self> {
+        let (tx, mut rx) = async_mpsc::unbounded::<()>();
+        let task = env.spawn_background(async move {
+            loop {
+                let done = rx.next().await.is_none();
+                for batch in buffers.take_all() {
+                    if let Err(err) = table.write_batch(&batch).await {
+                        error!("Write {} buffered writes: {err}", batch.len());
+                        if let Some(listener) = &listener {
+                            listener.on_background_error(&BackgroundErrorEvent {
+                                job: "buffered_write",
+                                error: err.to_string(),
+                            });
+                        }
+                        buffers.set_error(err);
+                    }
+                }
+                if done {
+                    break;
+                }
+            }
+            // Releases the table before the task is done, so that it can be
+            // closed.
+            drop(table);
+        });
+
+        let (stop, stop_rx) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("photondb-buffer".into())
+            .spawn(move || {
+                // Stops once the sender is dropped.
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(delay) {
+                    if tx.unbounded_send(()).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Self {
+            stop: Mutex::new(Some(stop)),
+            thread: Mutex::new(Some(thread)),
+            task: Mutex::new(Some(task)),
+        })
+    }
+
+    /// Stops the flusher, and waits for the rest of the buffered writes to be
+    /// written.
+    pub(super) async fn stop(&self) {
+        self.stop.lock().take();
+        if let Some(thread) = self.thread.lock().take() {
+            let _ = thread.join();
+        }
+        // The task is done once the thread drops the sender.
+        let task = self.task.lock().take();
+        if let Some(task) = task {
+            task.await;
+        }
+    }
+}
+
+impl<E: Env> Drop for BufferFlusher<E> {
+    fn drop(&mut self) {
+        // The task exits on its own once the thread drops the sender.
+        self.stop.lock().take();
+    }
+}
+
+impl<E: Env> fmt::Debug for BufferFlusher<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferFlusher").finish()
+    }
+}
+
+impl fmt::Debug for WriteBuffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteBuffers")
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_take() {
+        let buffers = WriteBuffers::new(64);
+        assert!(buffers.add(b"a", Some(b"1"), 1).is_none());
+        assert!(buffers.add(b"b", None, 2).is_none());
+        let batch = buffers.add(&[0; 32], Some(b"2"), 3).unwrap();
+        assert_eq!(batch.len(), 3);
+        assert!(buffers.take_all().is_empty());
+
+        // Writes of other threads go to their own buffers.
+        let buffers = Arc::new(buffers);
+        assert!(buffers.add(b"c", Some(b"3"), 4).is_none());
+        thread::spawn({
+            let buffers = buffers.clone();
+            move || assert!(buffers.add(b"d", None, 5).is_none())
+        })
+        .join()
+        .unwrap();
+        let batches = buffers.take_all();
+        assert_eq!(batches.iter().map(|b| b.len()).sum::<usize>(), 2);
+
+        // Only the first error is kept, and it is taken once.
+        buffers.set_error(Error::TooLargeSize);
+        buffers.set_error(Error::Timeout);
+        assert!(matches!(buffers.take_error(), Err(Error::TooLargeSize)));
+        assert!(buffers.take_error().is_ok());
+    }
+}
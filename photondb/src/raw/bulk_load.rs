@@ -0,0 +1,334 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    mem,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+    vec,
+};
+
+use crate::{
+    env::{Env, PositionalReaderExt, SequentialWriterExt},
+    Error, Result,
+};
+
+/// The size of the buffer to write or read a run at a time.
+const IO_BUFFER_SIZE: usize = 1 << 20;
+
+/// Distinguishes the runs of concurrent bulk loads in the same directory.
+static NEXT_SORTER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Options to bulk load entries with [`super::Table::bulk_load`].
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct BulkLoadOptions {
+    /// The approximate size of the entries to sort in memory.
+    ///
+    /// Entries are sorted in runs of this size, which are spilled to files and
+    /// merged when all entries are added.
+    ///
+    /// Default: 64 MiB
+    pub memory_limit: usize,
+
+    /// The directory to spill runs to.
+    ///
+    /// If this is `None`, runs are spilled to the directory of the table.
+    ///
+    /// Default: None
+    pub spill_dir: Option<PathBuf>,
+
+    /// The number of entries to write in a batch.
+    ///
+    /// Default: 1024
+    pub batch_size: usize,
+}
+
+impl Default for BulkLoadOptions {
+    fn default() -> Self {
+        Self {
+            memory_limit: 64 << 20,
+            spill_dir: None,
+            batch_size: 1024,
+        }
+    }
+}
+
+/// Sorts entries with an external merge sort, which spills sorted runs to
+/// files through the environment.
+///
+/// If a key is added more than once, the last entry wins.
+pub(crate) struct ExternalSorter<E: Env> {
+    env: E,
+    dir: PathBuf,
+    id: u64,
+    memory_limit: usize,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    entries_size: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl<E: Env> ExternalSorter<E> {
+    pub(crate) fn new(env: E, dir: PathBuf, memory_limit: usize) -> Self {
+        Self {
+            env,
+            dir,
+            id: NEXT_SORTER_ID.fetch_add(1, AtomicOrdering::Relaxed),
+            memory_limit,
+            entries: Vec::new(),
+            entries_size: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Adds an entry, which spills a run if the memory limit is reached.
+    pub(crate) async fn add(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.entries_size += key.len() + value.len();
+        self.entries.push((key, value));
+        if self.entries_size >= self.memory_limit {
+            self.spill().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of runs spilled so far.
+    pub(crate) fn num_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    async fn spill(&mut self) -> Result<()> {
+        sort_entries(&mut self.entries);
+        let path = self
+            .dir
+            .join(format!("bulk_load_{}_{}.run", self.id, self.runs.len()));
+        let mut writer = self.env.open_sequential_writer(&path).await?;
+        // Records the run first, so that it is removed even if it is torn.
+        self.runs.push(path);
+        let mut buf = Vec::with_capacity(IO_BUFFER_SIZE);
+        for (key, value) in self.entries.drain(..) {
+            buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&key);
+            buf.extend_from_slice(&value);
+            if buf.len() >= IO_BUFFER_SIZE {
+                writer.write_all(&buf).await?;
+                buf.clear();
+            }
+        }
+        writer.write_all(&buf).await?;
+        self.entries_size = 0;
+        Ok(())
+    }
+
+    /// Returns the entries sorted by keys, which are merged from the runs and
+    /// the entries in memory.
+    pub(crate) async fn sorted(&mut self) -> Result<SortedEntries<E>> {
+        sort_entries(&mut self.entries);
+        let mut runs = Vec::with_capacity(self.runs.len());
+        for path in &self.runs {
+            let len = self.env.metadata(path).await?.len;
+            let reader = self.env.open_positional_reader(path).await?;
+            runs.push(RunReader {
+                reader,
+                len,
+                pos: 0,
+                buf: Vec::new(),
+                offset: 0,
+            });
+        }
+        let mut sorted = SortedEntries {
+            runs,
+            entries: mem::take(&mut self.entries).into_iter(),
+            heap: BinaryHeap::new(),
+        };
+        for source in 0..=sorted.runs.len() {
+            sorted.refill(source).await?;
+        }
+        Ok(sorted)
+    }
+
+    /// Removes the spilled runs.
+    pub(crate) async fn remove_runs(&mut self) -> Result<()> {
+        for path in self.runs.drain(..) {
+            self.env.remove_file(&path).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sorts the entries by keys, and keeps the last entry of each key.
+fn sort_entries(entries: &mut Vec<(Vec<u8>, Vec<u8>)>) {
+    // The sort is stable, so the last entry of a key is the last one.
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut deduped: Vec<(Vec<u8>, Vec<u8>)> = Vec::with_capacity(entries.len());
+    for entry in entries.drain(..) {
+        match deduped.last_mut() {
+            Some(last) if last.0 == entry.0 => *last = entry,
+            _ => deduped.push(entry),
+        }
+    }
+    *entries = deduped;
+}
+
+/// The sorted entries of an [`ExternalSorter`].
+pub(crate) struct SortedEntries<E: Env> {
+    runs: Vec<RunReader<E>>,
+    entries: vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    heap: BinaryHeap<HeapEntry>,
+}
+
+impl<E: Env> SortedEntries<E> {
+    /// Returns the next entry.
+    pub(crate) async fn next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let Some(top) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.refill(top.source).await?;
+        // Entries of the same key from older sources are overwritten.
+        while let Some(entry) = self.heap.peek() {
+            if entry.key != top.key {
+                break;
+            }
+            let source = entry.source;
+            self.heap.pop();
+            self.refill(source).await?;
+        }
+        Ok(Some((top.key, top.value)))
+    }
+
+    /// Pushes the next entry of the source to the heap.
+    ///
+    /// Sources are ordered by the time their entries are added, and the
+    /// entries in memory are the latest.
+    async fn refill(&mut self, source: usize) -> Result<()> {
+        let entry = match self.runs.get_mut(source) {
+            Some(run) => run.next().await?,
+            None => self.entries.next(),
+        };
+        if let Some((key, value)) = entry {
+            self.heap.push(HeapEntry { key, value, source });
+        }
+        Ok(())
+    }
+}
+
+struct HeapEntry {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    source: usize,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    /// Orders the smallest key first, and the latest source first for the
+    /// same key.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .cmp(&self.key)
+            .then_with(|| self.source.cmp(&other.source))
+    }
+}
+
+/// Reads the entries of a run.
+struct RunReader<E: Env> {
+    reader: E::PositionalReader,
+    len: u64,
+    pos: u64,
+    buf: Vec<u8>,
+    offset: usize,
+}
+
+impl<E: Env> RunReader<E> {
+    async fn next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        if !self.fill(8).await? {
+            return Ok(None);
+        }
+        let header = &self.buf[self.offset..self.offset + 8];
+        let key_len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let value_len = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        if !self.fill(8 + key_len + value_len).await? {
+            return Err(Error::corrupted());
+        }
+        let start = self.offset + 8;
+        let key = self.buf[start..start + key_len].to_vec();
+        let value = self.buf[start + key_len..start + key_len + value_len].to_vec();
+        self.offset = start + key_len + value_len;
+        Ok(Some((key, value)))
+    }
+
+    /// Fills the buffer with at least `n` bytes after the offset.
+    ///
+    /// Returns false if the run ends before any bytes, and
+    /// [`Error::Corrupted`] if it ends before `n` bytes.
+    async fn fill(&mut self, n: usize) -> Result<bool> {
+        if self.buf.len() - self.offset >= n {
+            return Ok(true);
+        }
+        self.buf.drain(..self.offset);
+        self.offset = 0;
+        if self.buf.is_empty() && self.pos == self.len {
+            return Ok(false);
+        }
+        let want = n.max(IO_BUFFER_SIZE) - self.buf.len();
+        let want = want.min((self.len - self.pos) as usize);
+        let start = self.buf.len();
+        self.buf.resize(start + want, 0);
+        self.reader
+            .read_exact_at(&mut self.buf[start..], self.pos)
+            .await?;
+        self.pos += want as u64;
+        if self.buf.len() < n {
+            return Err(Error::corrupted());
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::Mem;
+
+    #[test]
+    fn external_sort() {
+        let env = Mem::new();
+        env.run(async {
+            let dir = PathBuf::from("/sort");
+            env.create_dir_all(&dir).await.unwrap();
+            let mut sorter = ExternalSorter::new(env.clone(), dir.clone(), 1 << 10);
+            // Adds keys in a scrambled order, with each key added twice.
+            let n = 1000u32;
+            for round in 0..2u32 {
+                for i in 0..n {
+                    let key = (i * 7919 % n).to_be_bytes().to_vec();
+                    sorter.add(key, round.to_be_bytes().to_vec()).await.unwrap();
+                }
+            }
+            assert!(sorter.num_runs() > 1);
+
+            let mut sorted = sorter.sorted().await.unwrap();
+            for i in 0..n {
+                let (key, value) = sorted.next().await.unwrap().unwrap();
+                assert_eq!(key, i.to_be_bytes());
+                assert_eq!(value, 1u32.to_be_bytes());
+            }
+            assert!(sorted.next().await.unwrap().is_none());
+            drop(sorted);
+            sorter.remove_runs().await.unwrap();
+            assert!(env.read_dir(&dir).unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,179 @@
+use ::metrics::{absolute_counter, gauge};
+
+use super::TableStats;
+
+/// Reports the stats of a table through the `metrics` facade.
+///
+/// Cumulative stats are reported as absolute counters, and the current state
+/// of the table, such as the tree height and the memory usage, is reported as
+/// gauges. All metrics have a `table` label with the path of the table, so
+/// that tables in the same process are reported separately.
+pub(super) fn report(stats: &TableStats, table: &str) {
+    let labels = [("table", table.to_owned())];
+    let tree = &stats.tree;
+    absolute_counter!("photondb_tree_reads_total", tree.success.read, &labels);
+    absolute_counter!("photondb_tree_writes_total", tree.success.write, &labels);
+    absolute_counter!(
+        "photondb_tree_read_bytes_total",
+        tree.success.read_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_tree_write_bytes_total",
+        tree.success.write_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_tree_split_pages_total",
+        tree.success.split_page,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_tree_consolidate_pages_total",
+        tree.success.consolidate_page,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_tree_read_conflicts_total",
+        tree.conflict.read,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_tree_write_conflicts_total",
+        tree.conflict.write,
+        &labels
+    );
+    absolute_counter!("photondb_tree_failed_cas_total", tree.failed_cas, &labels);
+    absolute_counter!("photondb_tree_slow_ops_total", tree.slow_ops, &labels);
+    gauge!("photondb_tree_height", tree.height as f64, &labels);
+    gauge!("photondb_tree_hot_pages", tree.hot_pages as f64, &labels);
+
+    let store = &stats.store;
+    absolute_counter!(
+        "photondb_page_cache_hits_total",
+        store.page_cache.lookup_hit,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_page_cache_misses_total",
+        store.page_cache.lookup_miss,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_write_stalls_total",
+        store.buffer_set.stall_writes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_write_stall_millis_total",
+        store.buffer_set.stall_intervals_ms,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_write_slowdowns_total",
+        store.buffer_set.slowdown_writes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_flush_write_bytes_total",
+        store.jobs.flush_write_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_compact_write_bytes_total",
+        store.jobs.compact_write_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_page_file_read_bytes_total",
+        store.io.page_file.read_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_page_file_write_bytes_total",
+        store.io.page_file.write_bytes,
+        &labels
+    );
+    absolute_counter!(
+        "photondb_page_file_syncs_total",
+        store.io.page_file.syncs,
+        &labels
+    );
+    gauge!(
+        "photondb_page_table_allocated_ids",
+        store.page_table.allocated_ids as f64,
+        &labels
+    );
+    gauge!(
+        "photondb_memory_bytes",
+        store.memory.total() as f64,
+        &labels
+    );
+    gauge!(
+        "photondb_memory_page_cache_bytes",
+        store.memory.page_cache as f64,
+        &labels
+    );
+    gauge!(
+        "photondb_memory_write_buffers_bytes",
+        store.memory.write_buffers as f64,
+        &labels
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ::metrics::{Counter, Gauge, Histogram, Key, KeyName, Recorder, SharedString, Unit};
+
+    use super::*;
+
+    /// A recorder that keeps the keys of the registered metrics.
+    struct KeyRecorder(Arc<Mutex<Vec<Key>>>);
+
+    impl Recorder for KeyRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key) -> Counter {
+            self.0.lock().unwrap().push(key.clone());
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key) -> Gauge {
+            self.0.lock().unwrap().push(key.clone());
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key) -> Histogram {
+            self.0.lock().unwrap().push(key.clone());
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn report_with_table_label() {
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        ::metrics::set_boxed_recorder(Box::new(KeyRecorder(keys.clone()))).unwrap();
+        let stats = TableStats::default();
+        report(&stats, "a");
+        report(&stats, "b");
+
+        let keys = keys.lock().unwrap();
+        let table_of = |key: &Key| {
+            key.labels()
+                .find(|label| label.key() == "table")
+                .map(|label| label.value().to_owned())
+        };
+        for table in ["a", "b"] {
+            assert!(keys.iter().any(|key| {
+                key.name() == "photondb_tree_reads_total" && table_of(key).as_deref() == Some(table)
+            }));
+        }
+        assert!(keys.iter().all(|key| table_of(key).is_some()));
+    }
+}
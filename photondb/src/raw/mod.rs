@@ -1,7 +1,20 @@
 //! Raw PhotonDB APIs that can can run with different environments.
 
 mod table;
-pub use table::{Guard, Pages, Table, TableStats};
+pub use table::{Cursor, Guard, MaintenanceDebt, Pages, Snapshot, Table, TableStats};
+
+mod bulk_load;
+pub use bulk_load::BulkLoadOptions;
+
+mod buffered_writes;
+
+mod admin;
+pub use admin::{LiveStats, MaintenanceCommand};
+
+mod stats_dumper;
+
+#[cfg(feature = "metrics")]
+mod metrics;
 
 #[cfg(test)]
 mod tree_test {
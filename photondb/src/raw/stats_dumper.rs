@@ -0,0 +1,80 @@
+use std::{
+    io,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::info;
+
+use super::TableStats;
+use crate::{env::Env, page_store::StatsReader, tree::Tree, util::atomic::Counter};
+
+/// A background thread that logs the stats of a table periodically.
+///
+/// Each dump contains the deltas since the last dump. With the `metrics`
+/// feature, the stats are also reported through the `metrics` facade, labeled
+/// with the path of the table. The thread is stopped when the dumper is
+/// dropped.
+#[derive(Debug)]
+pub(super) struct StatsDumper {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    dumps: Arc<Counter>,
+}
+
+impl StatsDumper {
+    pub(super) fn spawn<E: Env>(
+        period: Duration,
+        tree: Arc<Tree>,
+        store: StatsReader<E>,
+        table: String,
+    ) -> io::Result<Self> {
+        let (stop, rx) = mpsc::channel::<()>();
+        let dumps = Arc::new(Counter::default());
+        let thread_dumps = dumps.clone();
+        let handle = thread::Builder::new()
+            .name("photondb-stats".into())
+            .spawn(move || {
+                let mut last = TableStats::default();
+                // Stops once the sender is dropped.
+                while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(period) {
+                    let stats = TableStats {
+                        tree: tree.stats(),
+                        store: store.stats(),
+                    };
+                    info!(
+                        "Stats of {table} in the last {period:?}:\n{}",
+                        stats.sub(&last)
+                    );
+                    #[cfg(feature = "metrics")]
+                    super::metrics::report(&stats, &table);
+                    last = stats;
+                    thread_dumps.inc();
+                }
+            })?;
+        Ok(Self {
+            stop: Some(stop),
+            handle: Some(handle),
+            dumps,
+        })
+    }
+
+    /// Returns the number of dumps so far.
+    #[cfg(all(test, unix))]
+    pub(super) fn num_dumps(&self) -> u64 {
+        self.dumps.get()
+    }
+}
+
+impl Drop for StatsDumper {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
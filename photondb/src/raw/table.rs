@@ -1,34 +1,182 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    io::{Read, Write},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+use log::info;
+use parking_lot::Mutex;
 
+use super::{
+    admin::{self, AdminPoller},
+    buffered_writes::{BufferFlusher, WriteBuffers},
+    bulk_load::{BulkLoadOptions, ExternalSorter},
+    stats_dumper::StatsDumper,
+    MaintenanceCommand,
+};
 use crate::{
+    audit::AuditLog,
     env::Env,
+    export::Exporter,
+    keyspace::{KeyspaceInfo, KeyspaceReader, KeyspaceWriter},
     page::{Key, Value},
-    page_store::{FlushOptions, PageStore, StoreStats},
+    page_store::{
+        CacheStats, ChecksumReport, FlushOptions, PageStore, PendingWrite, SpaceReport, StoreStats,
+    },
+    replication::{ReplicatedOp, ReplicationRecord, ReplicationStream, Replicator},
     tree::*,
-    Result,
+    util::{json::Json, trace::instrument},
+    workload::{WorkloadOpKind, WorkloadRecorder},
+    Error, ExportOptions, Result,
 };
 
+/// The metadata entry that stores the index applied by
+/// [`Table::apply_with_index`].
+const APPLIED_INDEX_METADATA: &str = "applied_index";
+
+/// The prefix of the metadata entries that store the batches prepared by
+/// [`Table::prepare`], followed by the hexadecimal ids of the batches.
+const PREPARED_METADATA_PREFIX: &str = "prepared.";
+
+/// The metadata entry that stores the id of the next batch to prepare, so
+/// that ids are not reused across restarts.
+const NEXT_PREPARED_ID_METADATA: &str = "next_prepared_id";
+
+/// The number of entries a range scan reads before it re-pins the table.
+const SCAN_BATCH_SIZE: usize = 1024;
+
 /// A reference to a latch-free, log-structured table that stores sorted
 /// key-value entries.
 ///
 /// The reference is thread-safe and cheap to clone.
 #[derive(Clone, Debug)]
 pub struct Table<E: Env> {
+    env: E,
+    path: Arc<Path>,
+    options: Arc<Mutex<Options>>,
     tree: Arc<Tree>,
     store: Arc<PageStore<E>>,
+    stats_dumper: Option<Arc<StatsDumper>>,
+    admin: Option<Arc<AdminPoller<E>>>,
+    audit_log: Option<Arc<AuditLog<E>>>,
+    workload: Arc<WorkloadRecorder>,
+    replicator: Arc<Replicator>,
+    next_prepared_id: Arc<futures::lock::Mutex<u64>>,
+    write_buffers: Option<Arc<WriteBuffers>>,
+    buffer_flusher: Option<Arc<BufferFlusher<E>>>,
 }
 
 impl<E: Env> Table<E> {
     /// Opens a table in the path with the given options.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the options enable background
+    /// threads in an environment that is not [native](Env::is_native).
+    ///
+    /// With [`PageStoreOptions::read_only`], an existing table is opened
+    /// without modifying its directory, and writes to it fail with
+    /// [`Error::ReadOnly`].
+    ///
+    /// [`PageStoreOptions::read_only`]: crate::PageStoreOptions::read_only
     pub async fn open<P: AsRef<Path>>(env: E, path: P, options: Options) -> Result<Self> {
+        options.validate()?;
+        if !env.is_native() {
+            // These features run on threads of the operating system.
+            let threaded = [
+                options.stats_dump_period.map(|_| "stats_dump_period"),
+                options.admin_poll_period.map(|_| "admin_poll_period"),
+                options.buffered_write_delay.map(|_| "buffered_write_delay"),
+            ];
+            if let Some(name) = threaded.into_iter().flatten().next() {
+                return Err(Error::InvalidArgument(format!(
+                    "{name} is not supported by the environment"
+                )));
+            }
+        }
         let tree = Arc::new(Tree::new(options.clone()));
-        let store = PageStore::open(env, path, options.page_store).await?;
-        let txn = tree.begin(store.guard());
-        txn.init().await?;
-        Ok(Self {
+        let store = PageStore::open(
+            env.clone(),
+            path.as_ref(),
+            options.page_store.clone(),
+            options.event_listener.clone(),
+        )
+        .await?;
+        let mut table = Self {
+            env,
+            path: path.as_ref().into(),
+            options: Arc::new(Mutex::new(options.clone())),
             tree,
             store: Arc::new(store),
-        })
+            stats_dumper: None,
+            admin: None,
+            audit_log: None,
+            workload: Arc::default(),
+            replicator: Arc::default(),
+            next_prepared_id: Arc::default(),
+            write_buffers: None,
+            buffer_flusher: None,
+        };
+        if let Err(err) = table.start(&options).await {
+            // Closes the store and the parts that have started.
+            let _ = table.close().await;
+            return Err(err);
+        }
+        Ok(table)
+    }
+
+    /// Recovers the state of the table and starts the background parts.
+    async fn start(&mut self, options: &Options) -> Result<()> {
+        if options.audit_log {
+            let log = AuditLog::open(&self.env, &self.path).await?;
+            self.audit_log = Some(Arc::new(log));
+        }
+        if !options.page_store.read_only {
+            self.begin().init().await?;
+        } else if !self.begin().is_initialized() {
+            return Err(Error::InvalidArgument(format!(
+                "{} has no tree to read",
+                self.path.display()
+            )));
+        }
+        // LSNs assigned by the table continue from the flushed writes.
+        self.tree.observe_lsn(self.store.watermark().flushed_lsn());
+        if let Some(value) = self.store.metadata(NEXT_PREPARED_ID_METADATA) {
+            let id = u64::from_be_bytes(value.try_into().map_err(|_| Error::corrupted())?);
+            self.next_prepared_id = Arc::new(futures::lock::Mutex::new(id));
+        }
+        if let Some(period) = options.stats_dump_period {
+            let dumper = StatsDumper::spawn(
+                period,
+                self.tree.clone(),
+                self.store.stats_reader(),
+                self.path.display().to_string(),
+            )?;
+            self.stats_dumper = Some(Arc::new(dumper));
+        }
+        if let Some(period) = options.admin_poll_period {
+            let admin = AdminPoller::spawn(
+                &self.env,
+                &self.path,
+                period,
+                self.tree.clone(),
+                Arc::downgrade(&self.store),
+            )?;
+            self.admin = Some(Arc::new(admin));
+        }
+        if let Some(delay) = options.buffered_write_delay {
+            let buffers = Arc::new(WriteBuffers::new(options.buffered_write_size));
+            self.write_buffers = Some(buffers.clone());
+            // The flusher holds a clone of the table without itself.
+            let flusher = BufferFlusher::spawn(
+                &self.env,
+                self.clone(),
+                buffers,
+                delay,
+                options.event_listener.clone(),
+            )?;
+            self.buffer_flusher = Some(Arc::new(flusher));
+        }
+        Ok(())
     }
 
     /// Closes the table if this is the only reference to it.
@@ -36,18 +184,54 @@ impl<E: Env> Table<E> {
     /// If this is not the only reference, returns [`Result::Err`] with this
     /// reference.
     pub async fn close(self) -> Result<(), Self> {
+        // Admin commands and the buffer flusher hold the store while running,
+        // so they are stopped first if this is the last reference.
+        if let Some(admin) = &self.admin {
+            if Arc::strong_count(admin) == 1 {
+                admin.stop().await;
+            }
+        }
+        if let Some(flusher) = &self.buffer_flusher {
+            if Arc::strong_count(flusher) == 1 {
+                flusher.stop().await;
+            }
+        }
         match Arc::try_unwrap(self.store) {
             Ok(store) => {
+                // Stops dumping stats before the store is closed.
+                drop(self.stats_dumper);
+                if let Some(log) = &self.audit_log {
+                    log.close().await;
+                }
                 store.close().await;
                 Ok(())
             }
             Err(store) => Err(Self {
+                env: self.env,
+                path: self.path,
+                options: self.options,
                 tree: self.tree,
                 store,
+                stats_dumper: self.stats_dumper,
+                admin: self.admin,
+                audit_log: self.audit_log,
+                workload: self.workload,
+                replicator: self.replicator,
+                next_prepared_id: self.next_prepared_id,
+                write_buffers: self.write_buffers,
+                buffer_flusher: self.buffer_flusher,
             }),
         }
     }
 
+    /// Returns the number of stats dumps so far.
+    #[cfg(all(test, unix))]
+    pub(crate) fn num_stats_dumps(&self) -> u64 {
+        self.stats_dumper
+            .as_ref()
+            .map_or(0, |dumper| dumper.num_dumps())
+    }
+
     /// Begins a tree transaction.
     fn begin(&self) -> TreeTxn<'_, E> {
         self.tree.begin(self.store.guard())
@@ -60,27 +244,1132 @@ impl<E: Env> Table<E> {
 
     /// Gets the value corresponding to the key.
     pub async fn get(&self, key: &[u8], lsn: u64) -> Result<Option<Vec<u8>>> {
-        let key = Key::new(key, lsn);
         let txn = self.begin();
-        let value = txn.get(key).await?;
+        let value = self.get_in(&txn, key, lsn).await?;
+        Ok(value.map(|v| v.to_vec()))
+    }
+
+    /// Gets the value corresponding to the key with the options.
+    ///
+    /// This is the same as [`Table::get`] at [`ReadOptions::max_lsn`], except
+    /// that it returns [`Error::Timeout`] once it exceeds the limits in the
+    /// options.
+    pub async fn get_with_options(
+        &self,
+        key: &[u8],
+        opts: &ReadOptions,
+    ) -> Result<Option<Vec<u8>>> {
+        let txn = self.begin().with_limits(opts.limits());
+        let value = self.get_in(&txn, key, opts.max_lsn).await?;
         Ok(value.map(|v| v.to_vec()))
     }
 
+    async fn get_in<'t>(
+        &self,
+        txn: &'t TreeTxn<'_, E>,
+        key: &[u8],
+        lsn: u64,
+    ) -> Result<Option<&'t [u8]>> {
+        let key = Key::new(key, lsn);
+        let value = instrument!(txn.get(key), "get", lsn).await?;
+        self.capture(WorkloadOpKind::Get, key.raw, value.map_or(0, |v| v.len()));
+        Ok(value)
+    }
+
+    /// Gets the values corresponding to the keys.
+    ///
+    /// This is more efficient than multiple [`Table::get`] calls, since keys in
+    /// the same page share one lookup of the page.
+    pub async fn multi_get(&self, keys: &[&[u8]], lsn: u64) -> Result<Vec<Option<Vec<u8>>>> {
+        let txn = self.begin();
+        let values = self.multi_get_in(&txn, keys, lsn).await?;
+        Ok(values.into_iter().map(|v| v.map(|v| v.to_vec())).collect())
+    }
+
+    async fn multi_get_in<'t>(
+        &self,
+        txn: &'t TreeTxn<'_, E>,
+        keys: &[&[u8]],
+        lsn: u64,
+    ) -> Result<Vec<Option<&'t [u8]>>> {
+        let keys: Vec<_> = keys.iter().map(|key| Key::new(key, lsn)).collect();
+        let values = instrument!(txn.multi_get(&keys), "multi_get", lsn, len = keys.len()).await?;
+        for (key, value) in keys.iter().zip(&values) {
+            self.capture(WorkloadOpKind::Get, key.raw, value.map_or(0, |v| v.len()));
+        }
+        Ok(values)
+    }
+
+    /// Scans the entries visible to the LSN in `shards` key ranges of the
+    /// table concurrently, and calls `f` on each entry.
+    ///
+    /// The key space is split at the keys of the upper inner pages, so the
+    /// ranges hold roughly the same number of pages without reading any leaf
+    /// page. Entries of a range are passed to `f` in order, but entries of
+    /// different ranges are interleaved. The ranges are scanned concurrently in
+    /// the current task, which overlaps their page reads;
+    /// [`crate::std::Table::parallel_scan`] scans them on threads instead.
+    pub async fn parallel_scan<F>(&self, shards: usize, lsn: u64, f: F) -> Result<()>
+    where
+        F: Fn(&[u8], &[u8]),
+    {
+        let ranges = self.scan_ranges(shards).await?;
+        let scans = ranges
+            .iter()
+            .map(|(start, end)| self.scan_range(start, end.as_deref(), lsn, &f));
+        futures::future::try_join_all(scans).await?;
+        Ok(())
+    }
+
+    /// Splits the key space into up to `n` ranges to scan in parallel.
+    pub(crate) async fn scan_ranges(&self, n: usize) -> Result<Vec<(Vec<u8>, Option<Vec<u8>>)>> {
+        let txn = self.begin();
+        let keys = txn.partition_keys(n.max(1)).await?;
+        let starts = std::iter::once(Vec::new()).chain(keys.iter().cloned());
+        let ends = keys.into_iter().map(Some).chain(std::iter::once(None));
+        Ok(starts.zip(ends).collect())
+    }
+
+    /// Scans the entries in `[start, end)` visible to the LSN, or in
+    /// `[start, ..)` if `end` is `None`, and calls `f` on each entry.
+    ///
+    /// The table is re-pinned every [`SCAN_BATCH_SIZE`] entries, so that a
+    /// long scan doesn't hold the resources of the table.
+    pub(crate) async fn scan_range<F>(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        lsn: u64,
+        f: &F,
+    ) -> Result<()>
+    where
+        F: Fn(&[u8], &[u8]),
+    {
+        let mut next = start.to_vec();
+        loop {
+            let txn = self.begin();
+            let mut cursor = TreeCursor::new(&txn, lsn);
+            cursor.seek(&next).await?;
+            let mut entries = Vec::with_capacity(SCAN_BATCH_SIZE);
+            let n = cursor.next_n(SCAN_BATCH_SIZE, &mut entries).await?;
+            for &(key, value) in &entries {
+                if end.map_or(false, |end| key >= end) {
+                    return Ok(());
+                }
+                f(key, value);
+            }
+            match entries.last() {
+                // Continues right after the last key.
+                Some(&(key, _)) if n == SCAN_BATCH_SIZE => {
+                    next.clear();
+                    next.extend_from_slice(key);
+                    next.push(0);
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Reads the pages that contain the keys into the cache.
+    ///
+    /// This is a hint for later reads of the keys, such as [`Table::get`], so
+    /// that they don't wait for I/O. The pages are read concurrently, so a
+    /// request handler can run this alongside other work before it reads the
+    /// keys.
+    pub async fn prefetch(&self, keys: &[&[u8]]) -> Result<()> {
+        let txn = self.begin();
+        instrument!(txn.prefetch(keys), "prefetch", len = keys.len()).await?;
+        Ok(())
+    }
+
+    /// Reads the pages in `[start, end)` into the cache, or in `[start, ..)`
+    /// if `end` is `None`.
+    ///
+    /// This is a hint for later scans of the range. See [`Table::prefetch`]
+    /// for more details.
+    pub async fn prefetch_range(&self, start: &[u8], end: Option<&[u8]>) -> Result<()> {
+        let txn = self.begin();
+        instrument!(txn.prefetch_range(start, end), "prefetch_range").await?;
+        Ok(())
+    }
+
     /// Puts a key-value entry to the table.
     pub async fn put(&self, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
+        self.put_in(&self.begin(), key, lsn, value).await
+    }
+
+    /// Puts a key-value entry to the table with the options.
+    ///
+    /// This is the same as [`Table::put`], except that it returns
+    /// [`Error::Timeout`] once it exceeds the limits in the options. The entry
+    /// is not written then.
+    pub async fn put_with_options(
+        &self,
+        key: &[u8],
+        lsn: u64,
+        value: &[u8],
+        opts: &WriteOptions,
+    ) -> Result<()> {
+        let txn = self.begin().with_limits(opts.limits());
+        self.put_in(&txn, key, lsn, value).await
+    }
+
+    async fn put_in(&self, txn: &TreeTxn<'_, E>, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
         let key = Key::new(key, lsn);
         let value = Value::Put(value);
-        let txn = self.begin();
-        txn.write(key, value).await?;
+        instrument!(txn.write(key, value), "put", lsn).await?;
+        self.on_written(key, value);
         Ok(())
     }
 
     /// Deletes the entry corresponding to the key from the table.
     pub async fn delete(&self, key: &[u8], lsn: u64) -> Result<()> {
+        self.delete_in(&self.begin(), key, lsn).await
+    }
+
+    /// Deletes the entry corresponding to the key from the table with the
+    /// options.
+    ///
+    /// This is the same as [`Table::delete`], except that it returns
+    /// [`Error::Timeout`] once it exceeds the limits in the options. The entry
+    /// is not deleted then.
+    pub async fn delete_with_options(
+        &self,
+        key: &[u8],
+        lsn: u64,
+        opts: &WriteOptions,
+    ) -> Result<()> {
+        let txn = self.begin().with_limits(opts.limits());
+        self.delete_in(&txn, key, lsn).await
+    }
+
+    async fn delete_in(&self, txn: &TreeTxn<'_, E>, key: &[u8], lsn: u64) -> Result<()> {
         let key = Key::new(key, lsn);
         let value = Value::Delete;
+        instrument!(txn.write(key, value), "delete", lsn).await?;
+        self.on_written(key, value);
+        Ok(())
+    }
+
+    /// Puts a key-value entry to the table with a buffered write.
+    ///
+    /// If [`Options::buffered_write_delay`] is set, the entry is added to a
+    /// buffer of the current thread, and is not visible to reads until the
+    /// buffer is written as a batch. A buffer is written by the thread that
+    /// fills it up to [`Options::buffered_write_size`], or in the background
+    /// once the delay elapses, or by [`Table::flush_buffered_writes`].
+    ///
+    /// A write in the background that fails is reported to
+    /// [`Options::event_listener`] as a `buffered_write` background error, and
+    /// the error is returned by the next call to this,
+    /// [`Table::delete_buffered`] or [`Table::flush_buffered_writes`]. The
+    /// entries of the failed batch are not written.
+    ///
+    /// If the delay is not set, this is the same as [`Table::put`].
+    pub async fn put_buffered(&self, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
+        let Some(buffers) = &self.write_buffers else {
+            return self.put(key, lsn, value).await;
+        };
+        buffers.take_error()?;
+        if let Some(batch) = buffers.add(key, Some(value), lsn) {
+            self.write_batch(&batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the entry corresponding to the key from the table with a
+    /// buffered write.
+    ///
+    /// See [`Table::put_buffered`] for more details.
+    pub async fn delete_buffered(&self, key: &[u8], lsn: u64) -> Result<()> {
+        let Some(buffers) = &self.write_buffers else {
+            return self.delete(key, lsn).await;
+        };
+        buffers.take_error()?;
+        if let Some(batch) = buffers.add(key, None, lsn) {
+            self.write_batch(&batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered writes of all threads, so that they are visible
+    /// to reads.
+    ///
+    /// Returns the error of a failed write in the background first, if it is
+    /// not returned yet.
+    pub async fn flush_buffered_writes(&self) -> Result<()> {
+        if let Some(buffers) = &self.write_buffers {
+            buffers.take_error()?;
+            for batch in buffers.take_all() {
+                self.write_batch(&batch).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the entries in `[start, end)` from the table.
+    ///
+    /// This writes a range tombstone to each page in the range instead of
+    /// deleting the entries one by one, so deleting a large range is cheap.
+    pub async fn delete_range(&self, start: &[u8], end: &[u8], lsn: u64) -> Result<()> {
+        let txn = self.begin();
+        instrument!(txn.delete_range(start, Some(end), lsn), "delete_range", lsn).await?;
+        self.audit("delete_range", || {
+            format!("start={start:?} end={end:?} lsn={lsn}")
+        });
+        self.replicate(|| {
+            vec![ReplicatedOp::DeleteRange {
+                start: start.to_vec(),
+                end: end.to_vec(),
+                lsn,
+            }]
+        });
+        Ok(())
+    }
+
+    /// Deletes all entries from the table.
+    ///
+    /// This writes a range tombstone over the whole key space like
+    /// [`Table::delete_range`], instead of replacing the tree. Reads with
+    /// smaller LSNs and active snapshots still see the entries.
+    ///
+    /// The space of the entries is not reclaimed when this returns. The
+    /// entries of a page are only dropped when the page is consolidated, by a
+    /// later write to it or by [`MaintenanceCommand::Consolidate`], and the
+    /// space of the obsolete pages is then reclaimed in the background. So a
+    /// cleared table that is not written again keeps its size until it is
+    /// consolidated.
+    pub async fn clear(&self, lsn: u64) -> Result<()> {
+        let txn = self.begin();
+        instrument!(txn.delete_range(&[], None, lsn), "clear", lsn).await?;
+        self.audit("clear", || format!("lsn={lsn}"));
+        self.replicate(|| vec![ReplicatedOp::Clear { lsn }]);
+        Ok(())
+    }
+
+    /// Writes a batch of entries to the table.
+    ///
+    /// Entries that go to the same page are written together, up to the page
+    /// size at a time.
+    ///
+    /// The batch is not atomic. If an error is returned, some of the entries
+    /// may have been written already, and the batch should be retried with
+    /// the same LSNs.
+    pub async fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        self.write_batch_in(&self.begin(), batch).await
+    }
+
+    async fn write_batch_in(&self, txn: &TreeTxn<'_, E>, batch: &WriteBatch) -> Result<()> {
+        let items = batch.items();
+        instrument!(txn.write_batch(&items), "write_batch", len = items.len()).await?;
+        for (key, value) in &items {
+            match value {
+                Value::Put(v) => self.capture(WorkloadOpKind::Put, key.raw, v.len()),
+                Value::Delete => self.capture(WorkloadOpKind::Delete, key.raw, 0),
+            }
+        }
+        self.replicate(|| {
+            items
+                .iter()
+                .map(|(key, value)| replicated_op(*key, *value))
+                .collect()
+        });
+        Ok(())
+    }
+
+    /// Loads entries in any order to the table with the LSN.
+    ///
+    /// Entries are sorted with an external merge sort before they are written
+    /// in batches, so the input can be larger than memory. Sorted runs are
+    /// spilled to files in [`BulkLoadOptions::spill_dir`] through the
+    /// environment of the table, and are removed when the load is done. If a
+    /// key is loaded more than once, the last entry wins.
+    ///
+    /// Returns the number of entries written.
+    pub async fn bulk_load<I>(&self, entries: I, lsn: u64, opts: &BulkLoadOptions) -> Result<u64>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let dir = opts
+            .spill_dir
+            .clone()
+            .unwrap_or_else(|| self.path.to_path_buf());
+        let mut sorter = ExternalSorter::new(self.env.clone(), dir, opts.memory_limit);
+        let result = self.bulk_load_sorted(&mut sorter, entries, lsn, opts).await;
+        let removed = sorter.remove_runs().await;
+        let num_entries = result?;
+        removed?;
+        self.audit("bulk_load", || {
+            format!("lsn={lsn} num_entries={num_entries}")
+        });
+        Ok(num_entries)
+    }
+
+    async fn bulk_load_sorted<I>(
+        &self,
+        sorter: &mut ExternalSorter<E>,
+        entries: I,
+        lsn: u64,
+        opts: &BulkLoadOptions,
+    ) -> Result<u64>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        for (key, value) in entries {
+            sorter.add(key, value).await?;
+        }
+        let mut sorted = sorter.sorted().await?;
+        let mut batch = WriteBatch::new();
+        let mut num_entries = 0;
+        while let Some((key, value)) = sorted.next().await? {
+            batch.put(&key, lsn, &value);
+            num_entries += 1;
+            if batch.len() >= opts.batch_size {
+                self.write_batch(&batch).await?;
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            self.write_batch(&batch).await?;
+        }
+        Ok(num_entries)
+    }
+
+    /// Gets the value of the latest version of the key at or before the
+    /// timestamp.
+    ///
+    /// Versions are written by [`Table::put_at`] and [`Table::delete_at`].
+    pub async fn get_at(&self, key: &[u8], ts: u64, lsn: u64) -> Result<Option<Vec<u8>>> {
+        let target = encode_timestamped_key(key, ts);
         let txn = self.begin();
-        txn.write(key, value).await?;
+        let mut cursor = TreeCursor::new(&txn, lsn);
+        cursor.seek(&target).await?;
+        let value = cursor
+            .current()
+            .and_then(|(raw, value)| match decode_timestamped_key(raw) {
+                Some((user_key, _)) if user_key == key => {
+                    decode_timestamped_value(value).map(|v| v.to_vec())
+                }
+                _ => None,
+            });
+        Ok(value)
+    }
+
+    /// Puts a version of the key with the timestamp to the table.
+    ///
+    /// The timestamp is stored as a suffix of the key, so versions of the same
+    /// key are ordered by timestamps descendingly and range scans still go in
+    /// the order of user keys. Keys returned by range scans can be decoded with
+    /// [`decode_timestamped_key`], and values with
+    /// [`decode_timestamped_value`].
+    pub async fn put_at(&self, key: &[u8], ts: u64, lsn: u64, value: &[u8]) -> Result<()> {
+        let key = encode_timestamped_key(key, ts);
+        let value = encode_timestamped_value(Some(value));
+        self.put(&key, lsn, &value).await
+    }
+
+    /// Deletes the key at the timestamp.
+    ///
+    /// This hides versions of the key before the timestamp from
+    /// [`Table::get_at`].
+    pub async fn delete_at(&self, key: &[u8], ts: u64, lsn: u64) -> Result<()> {
+        let key = encode_timestamped_key(key, ts);
+        let value = encode_timestamped_value(None);
+        self.put(&key, lsn, &value).await
+    }
+
+    /// Puts a key-value entry to the table with an LSN assigned by the table.
+    ///
+    /// Returns the assigned LSN, which is larger than all LSNs written before,
+    /// including the ones written before the table is reopened.
+    pub async fn put_sequenced(&self, key: &[u8], value: &[u8]) -> Result<u64> {
+        let pending = self.begin_sequenced();
+        self.put(key, pending.lsn(), value).await?;
+        Ok(pending.lsn())
+    }
+
+    /// Deletes the entry corresponding to the key from the table with an LSN
+    /// assigned by the table.
+    ///
+    /// Returns the assigned LSN.
+    pub async fn delete_sequenced(&self, key: &[u8]) -> Result<u64> {
+        let pending = self.begin_sequenced();
+        self.delete(key, pending.lsn()).await?;
+        Ok(pending.lsn())
+    }
+
+    /// Assigns an LSN for a write that is pending until it is durable, so
+    /// that writes with larger LSNs don't advance the durable LSN past it.
+    fn begin_sequenced(&self) -> PendingWrite<'_> {
+        self.store
+            .watermark()
+            .begin_write_with(|| self.tree.next_lsn())
+    }
+
+    /// Writes a batch as the entry at the index of a consensus log, such as a
+    /// Raft log, so that the table can be used as the state machine of the
+    /// log.
+    ///
+    /// The index is stored in the metadata of the table instead of a key, so
+    /// it is not visible to reads and scans. It is recorded after the batch,
+    /// and becomes durable with the write buffer that is active then. Write
+    /// buffers are flushed in order, so the index recovered after a crash
+    /// never goes beyond the batches recovered, but the batches after it may
+    /// be recovered too. Batches must be applied in the order of their
+    /// indexes, with LSNs that only depend on the entries, so that applying
+    /// them again after the recovered index is idempotent.
+    pub async fn apply_with_index(&self, batch: &WriteBatch, index: u64) -> Result<()> {
+        self.pin().write_batch(batch).await?;
+        self.store
+            .put_metadata(APPLIED_INDEX_METADATA, Some(index.to_be_bytes().to_vec()))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the latest index applied with [`Self::apply_with_index`], or 0
+    /// if none is applied.
+    pub async fn applied_index(&self) -> Result<u64> {
+        match self.store.metadata(APPLIED_INDEX_METADATA) {
+            Some(value) => {
+                let value = value.try_into().map_err(|_| Error::corrupted())?;
+                Ok(u64::from_be_bytes(value))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Prepares a batch for the first phase of a two-phase commit.
+    ///
+    /// The batch is stored in the metadata of the table, outside of the keys,
+    /// and its writes are not visible until it is committed with
+    /// [`Self::commit`] or rolled back with [`Self::rollback`]. The batch is
+    /// durable when this returns, which flushes the active write buffer, so
+    /// prepared batches survive crashes and can be listed with
+    /// [`Self::prepared`].
+    pub async fn prepare(&self, batch: &WriteBatch) -> Result<PreparedToken> {
+        // The lock keeps the recorded ids increasing.
+        let token = {
+            let mut next_id = self.next_prepared_id.lock().await;
+            let token = PreparedToken::from_id(*next_id);
+            *next_id += 1;
+            let value = next_id.to_be_bytes().to_vec();
+            self.store
+                .put_metadata(NEXT_PREPARED_ID_METADATA, Some(value))
+                .await?;
+            token
+        };
+        self.store
+            .put_metadata_durably(&prepared_metadata(token), Some(batch.encode()))
+            .await?;
+        self.audit("prepare", || {
+            format!("id={} len={}", token.id(), batch.len())
+        });
+        Ok(token)
+    }
+
+    /// Commits a prepared batch.
+    ///
+    /// The batch is written before the prepared one is removed, and write
+    /// buffers are flushed in order, so a batch that is still prepared after a
+    /// crash may have been written, in which case committing it again is
+    /// idempotent.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the batch is not prepared.
+    pub async fn commit(&self, token: PreparedToken) -> Result<()> {
+        let batch = self.prepared_batch(token).await?;
+        self.pin().write_batch(&batch).await?;
+        // The removal is recorded after the batch, so it is never durable
+        // before the batch.
+        self.store
+            .put_metadata(&prepared_metadata(token), None)
+            .await?;
+        self.audit("commit", || format!("id={}", token.id()));
+        Ok(())
+    }
+
+    /// Rolls back a prepared batch.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the batch is not prepared.
+    pub async fn rollback(&self, token: PreparedToken) -> Result<()> {
+        self.prepared_batch(token).await?;
+        self.store
+            .put_metadata(&prepared_metadata(token), None)
+            .await?;
+        self.audit("rollback", || format!("id={}", token.id()));
+        Ok(())
+    }
+
+    /// Returns the tokens of the batches that are prepared but not committed
+    /// or rolled back, in the order they are prepared.
+    pub async fn prepared(&self) -> Result<Vec<PreparedToken>> {
+        self.store
+            .metadata_with_prefix(PREPARED_METADATA_PREFIX)
+            .into_iter()
+            .map(|(key, _)| {
+                let id = &key[PREPARED_METADATA_PREFIX.len()..];
+                let id = u64::from_str_radix(id, 16).map_err(|_| Error::corrupted())?;
+                Ok(PreparedToken::from_id(id))
+            })
+            .collect()
+    }
+
+    async fn prepared_batch(&self, token: PreparedToken) -> Result<WriteBatch> {
+        let value = self.store.metadata(&prepared_metadata(token));
+        let value = value.ok_or_else(|| {
+            Error::InvalidArgument(format!("batch {} is not prepared", token.id()))
+        })?;
+        WriteBatch::decode(&value).ok_or_else(Error::corrupted)
+    }
+
+    /// Returns the latest LSN assigned by or written to the table.
+    pub fn latest_lsn(&self) -> u64 {
+        self.tree.latest_lsn()
+    }
+
+    /// Returns the largest LSN of the writes that are flushed to page files.
+    ///
+    /// Some writes with smaller LSNs may not be flushed yet, see
+    /// [`Table::durable_lsn`] for the LSN that all writes below are durable.
+    pub fn flushed_lsn(&self) -> u64 {
+        self.store.watermark().flushed_lsn()
+    }
+
+    /// Returns the LSN that the writes at or below it are durable.
+    ///
+    /// Writes are durable once their write buffers are flushed to page files,
+    /// so the LSN advances as flushes complete. It stays below the writes in
+    /// progress, and goes backwards if a write begins with an LSN at or below
+    /// it. Writes with LSNs from [`Table::next_lsn`] are only covered once
+    /// they begin. This starts at the LSN of the writes flushed before the
+    /// table is opened.
+    pub fn durable_lsn(&self) -> u64 {
+        self.store.durable_lsn()
+    }
+
+    /// Registers a callback that is invoked with the durable LSN once the
+    /// writes at or below `lsn` are durable.
+    ///
+    /// The callback is invoked immediately if the LSN is durable already.
+    /// Otherwise, it is invoked on the job that completes the flush, so it
+    /// should return quickly. Callbacks that are still pending when the table
+    /// is closed are dropped without being invoked.
+    pub fn on_durable<F>(&self, lsn: u64, f: F)
+    where
+        F: FnOnce(u64) + Send + 'static,
+    {
+        self.store.on_durable(lsn, Box::new(f));
+    }
+
+    /// Assigns an LSN that is larger than all LSNs written before.
+    ///
+    /// This is the LSN used by [`Table::put_sequenced`], which can be used to
+    /// write a [`WriteBatch`] in the same sequence.
+    pub fn next_lsn(&self) -> u64 {
+        self.tree.next_lsn()
+    }
+
+    /// Verifies the structure of the table.
+    ///
+    /// This walks through all pages in the table and checks the invariants of
+    /// the tree. Structure modifications that are in progress are counted in
+    /// the report, so the table should be quiescent to get an exact result.
+    pub async fn verify(&self) -> Result<VerifyReport> {
+        let txn = self.begin();
+        Ok(txn.verify().await?)
+    }
+
+    /// Verifies the checksums of all pages in the page files of the table.
+    ///
+    /// This reads every active page in the page files, so it can be slow on
+    /// large tables. Pages in write buffers are not verified. The table should
+    /// be quiescent, since files reclaimed during verification can not be
+    /// read.
+    pub async fn verify_checksums(&self) -> Result<ChecksumReport> {
+        Ok(self.store.verify_checksums().await?)
+    }
+
+    /// Analyzes the space used by the page files of the table.
+    ///
+    /// This reports the live and dead bytes of each page file, the space of
+    /// pages of each kind before and after compression, and the sizes of keys
+    /// and values. It reads every active page in the page files, so it can be
+    /// slow on large tables. Pages in write buffers are not analyzed. The
+    /// table should be quiescent, since files reclaimed during analysis can not
+    /// be read.
+    pub async fn analyze_space(&self) -> Result<SpaceReport> {
+        Ok(self.store.analyze_space().await?)
+    }
+
+    /// Repairs a corrupted table in the path.
+    ///
+    /// This rebuilds the manifest from the page files in the path, and
+    /// replaces the leaf pages that can not be read with empty pages. The
+    /// previous manifest files and the page files that can not be read are
+    /// moved to the `quarantine` directory in the path. The key ranges of the
+    /// replaced pages are lost, and are returned in the report. The metadata
+    /// recorded in the previous manifest, like the applied index, is lost too.
+    ///
+    /// The table must not be opened by others during repairing.
+    pub async fn repair<P: AsRef<Path>>(env: E, path: P, options: Options) -> Result<RepairReport> {
+        let path = path.as_ref();
+        let (files, quarantined_files) =
+            PageStore::rebuild_manifest(env.clone(), path, &options.page_store).await?;
+        let mut report = RepairReport {
+            num_files: files.len(),
+            quarantined_files,
+            ..Default::default()
+        };
+        let table = Self::open(env, path, options).await?;
+        table.begin().repair(&mut report).await?;
+        table.audit("repair", || {
+            format!(
+                "quarantined_files={:?} quarantined_pages={:?} unrepaired_pages={:?}",
+                report.quarantined_files, report.quarantined_pages, report.unrepaired_pages
+            )
+        });
+        table.flush(&FlushOptions::default()).await;
+        // This is the only reference to the table.
+        let _ = table.close().await;
+        Ok(report)
+    }
+
+    /// Describes the shape of the table.
+    ///
+    /// This walks through all pages in the table and returns the height, the
+    /// page counts, the fill factors and the delta chain lengths of each level.
+    pub async fn describe_tree(&self) -> Result<TreeShape> {
+        let txn = self.begin();
+        Ok(txn.describe().await?)
+    }
+
+    /// Dumps the structure of the table to the writer.
+    ///
+    /// This writes the id, epoch, range and delta chain of every page in the
+    /// table, one record per line. It is intended for debugging, and the
+    /// output can be large.
+    pub async fn dump<W: Write>(&self, w: &mut W) -> Result<()> {
+        let txn = self.begin();
+        Ok(txn.dump(w).await?)
+    }
+
+    /// Dumps a single page of the table to the writer.
+    ///
+    /// This writes the delta chain of the page like [`Table::dump`], together
+    /// with the header and the raw bytes of each delta page. It is intended
+    /// for examining a page reported by [`Table::verify_checksums`] or
+    /// [`Table::verify`].
+    ///
+    /// Returns [`Error::InvalidArgument`] if the page is not found.
+    pub async fn dump_page<W: Write>(&self, id: u64, w: &mut W) -> Result<()> {
+        let txn = self.begin();
+        if txn.dump_page(id, w).await? {
+            Ok(())
+        } else {
+            Err(Error::InvalidArgument(format!("page {id} is not found")))
+        }
+    }
+
+    /// Exports the entries in `[start, end)` to the writer.
+    ///
+    /// Entries are streamed in key order with the format and the encodings of
+    /// the options, and `None` as `end` exports all entries after `start`.
+    ///
+    /// Returns the number of entries exported.
+    pub async fn export<W: Write>(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        opts: &ExportOptions,
+        w: &mut W,
+    ) -> Result<u64> {
+        let mut exporter = Exporter::new(opts, w)?;
+        let guard = self.pin();
+        let mut cursor = guard.cursor(opts.max_lsn);
+        cursor.seek(start).await?;
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            if end.map_or(false, |end| key >= end) {
+                break;
+            }
+            exporter.write(key, value)?;
+            cursor.next().await?;
+        }
+        Ok(exporter.num_entries())
+    }
+
+    /// Exports the keyspace of the entries under the prefix to a portable
+    /// container.
+    ///
+    /// The container is versioned, and holds the entries in checksummed blocks
+    /// that don't depend on the page layout or the endianness of the host, so
+    /// it can move a keyspace, such as the data of a tenant, between tables.
+    /// The schema is opaque metadata that is stored with the entries, and is
+    /// returned by [`Table::import_keyspace`].
+    ///
+    /// Returns the number of entries exported.
+    pub async fn export_keyspace<W: Write>(
+        &self,
+        prefix: &[u8],
+        schema: &[u8],
+        lsn: u64,
+        w: W,
+    ) -> Result<u64> {
+        let mut writer = KeyspaceWriter::new(w, prefix, schema)?;
+        let guard = self.pin();
+        let mut cursor = guard.cursor(lsn);
+        cursor.seek(prefix).await?;
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            let Some(suffix) = key.strip_prefix(prefix) else {
+                break;
+            };
+            writer.add(suffix, value)?;
+            cursor.next().await?;
+        }
+        let num_entries = writer.finish()?;
+        self.audit("export_keyspace", || {
+            format!("prefix={prefix:?} lsn={lsn} num_entries={num_entries}")
+        });
+        Ok(num_entries)
+    }
+
+    /// Imports a keyspace from a container written by
+    /// [`Table::export_keyspace`].
+    ///
+    /// Entries are written with the LSN under `prefix`, or under the prefix of
+    /// the exported keyspace if it is `None`. Existing entries under the prefix
+    /// are not removed. Each data block is verified before it is written, so a
+    /// corrupted container may be imported partially, and importing it again
+    /// with the same LSN is idempotent.
+    ///
+    /// Returns the description of the keyspace, with the exported prefix.
+    pub async fn import_keyspace<R: Read>(
+        &self,
+        r: R,
+        prefix: Option<&[u8]>,
+        lsn: u64,
+    ) -> Result<KeyspaceInfo> {
+        let mut reader = KeyspaceReader::new(r)?;
+        let prefix = prefix.map_or_else(|| reader.info().prefix.clone(), |p| p.to_vec());
+        while let Some(entries) = reader.next_block()? {
+            let mut batch = WriteBatch::new();
+            for (suffix, value) in entries {
+                batch.put(&[prefix.as_slice(), &suffix].concat(), lsn, &value);
+            }
+            self.write_batch(&batch).await?;
+        }
+        let info = reader.info().clone();
+        self.audit("import_keyspace", || {
+            format!(
+                "prefix={prefix:?} lsn={lsn} num_entries={}",
+                info.num_entries
+            )
+        });
+        Ok(info)
+    }
+
+    /// Starts recording the accesses to the table to a trace file in the path.
+    ///
+    /// Each get, write, and scan step is written as a line of
+    /// `<timestamp_us> <op> <key_hash> <page_ids> <hit|miss>`, which can be
+    /// used for offline cache simulations. The file is truncated if it exists,
+    /// and a previous trace file is closed.
+    pub fn start_access_trace<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(self.tree.start_access_trace(path.as_ref())?)
+    }
+
+    /// Stops recording the accesses to the table and flushes the trace file.
+    pub fn stop_access_trace(&self) -> Result<()> {
+        Ok(self.tree.stop_access_trace()?)
+    }
+
+    /// Starts capturing the workload of the table to a trace file in the path.
+    ///
+    /// Each successful get, put, and delete is written as a line of
+    /// `<timestamp_us> <op> <key_hex> <value_size>`, including those in multi
+    /// gets and write batches. See [`crate::WorkloadOp`] to parse the trace
+    /// for replaying. The file is truncated if it exists, and a previous trace
+    /// file is closed.
+    pub fn start_workload_capture<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(self.workload.start(path.as_ref())?)
+    }
+
+    /// Stops capturing the workload of the table and flushes the trace file.
+    pub fn stop_workload_capture(&self) -> Result<()> {
+        Ok(self.workload.stop()?)
+    }
+
+    /// Returns a stream of the writes to the table from now on.
+    ///
+    /// Each successful put, delete, range delete, and write batch is published
+    /// as a [`ReplicationRecord::Batch`], followed by the watermarks marked
+    /// with [`Self::mark_replication_watermark`]. Writes that run
+    /// concurrently may be published in any order, so a replica must only
+    /// rely on the LSNs of the writes and the watermarks.
+    ///
+    /// The stream buffers at most `capacity` records. Writes never wait for
+    /// the stream: if it falls behind by more than `capacity` records, it is
+    /// disconnected and [`ReplicationStream::is_lagged`] returns true.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn subscribe_replication(&self, capacity: usize) -> ReplicationStream {
+        self.replicator.subscribe(capacity)
+    }
+
+    /// Publishes a watermark to the replication streams, which marks that all
+    /// writes at or below the LSN are published.
+    ///
+    /// The caller must ensure that no write at or below the LSN is in
+    /// progress.
+    pub fn mark_replication_watermark(&self, lsn: u64) {
+        self.replicate_record(|| ReplicationRecord::Watermark(lsn));
+    }
+
+    /// Applies a record from the replication stream of a primary table.
+    ///
+    /// The writes keep the LSNs assigned by the primary, and the latest LSN
+    /// of this table is advanced past them, so that LSNs assigned locally
+    /// later don't go backwards. The record is also published to the
+    /// replication streams of this table, so replicas can be chained.
+    pub async fn apply_replicated(&self, record: &ReplicationRecord) -> Result<()> {
+        match record {
+            ReplicationRecord::Batch(ops) => {
+                let mut batch = WriteBatch::new();
+                let mut ranges = Vec::new();
+                for op in ops {
+                    match op {
+                        ReplicatedOp::Put { key, lsn, value } => batch.put(key, *lsn, value),
+                        ReplicatedOp::Delete { key, lsn } => batch.delete(key, *lsn),
+                        ReplicatedOp::DeleteRange { start, end, lsn } => {
+                            ranges.push((start.as_slice(), Some(end.as_slice()), *lsn))
+                        }
+                        ReplicatedOp::Clear { lsn } => ranges.push((&[], None, *lsn)),
+                    }
+                }
+                let txn = self.begin();
+                let items = batch.items();
+                if !items.is_empty() {
+                    txn.write_batch(&items).await?;
+                }
+                for (start, end, lsn) in ranges {
+                    txn.delete_range(start, end, lsn).await?;
+                }
+                if let Some(lsn) = ops.iter().map(ReplicatedOp::lsn).max() {
+                    self.tree.observe_lsn(lsn);
+                }
+            }
+            ReplicationRecord::Watermark(lsn) => self.replicator.advance_watermark(*lsn),
+        }
+        self.replicate_record(|| record.clone());
+        Ok(())
+    }
+
+    /// Returns the latest watermark applied with [`Self::apply_replicated`].
+    ///
+    /// All writes at or below the watermark are applied, so reads at it see
+    /// the same entries as on the primary.
+    pub fn replicated_watermark(&self) -> u64 {
+        self.replicator.watermark()
+    }
+
+    /// Changes an option of the table without reopening it.
+    ///
+    /// The following options can be changed, where the value is parsed from a
+    /// string:
+    ///
+    /// - `page_chain_length`
+    /// - `max_consolidation_pages`
+    /// - `slow_op_threshold_ms`: 0 disables logging slow operations.
+    /// - `cache_capacity`
+    /// - `max_deferred_bytes`
+    ///
+    /// Returns [`Error::InvalidArgument`] if the option can not be changed or
+    /// the options with the value are not [valid](Options::validate).
+    pub fn set_option(&self, name: &str, value: &str) -> Result<()> {
+        let mut options = self.options.lock();
+        let mut new = options.clone();
+        match name {
+            "page_chain_length" | "max_consolidation_pages" | "slow_op_threshold_ms" => {
+                new.set(name, value)?
+            }
+            "cache_capacity" | "max_deferred_bytes" => new.set_page_store(name, value)?,
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "option {name} can not be changed"
+                )))
+            }
+        }
+        new.validate()?;
+        let dynamic = self.tree.dynamic_options();
+        let old = match name {
+            "page_chain_length" => {
+                dynamic.set_page_chain_length(new.page_chain_length);
+                options.page_chain_length.to_string()
+            }
+            "max_consolidation_pages" => {
+                dynamic.set_max_consolidation_pages(new.max_consolidation_pages);
+                options.max_consolidation_pages.to_string()
+            }
+            "slow_op_threshold_ms" => {
+                dynamic.set_slow_op_threshold(new.slow_op_threshold);
+                let old = options.slow_op_threshold;
+                old.map_or(0, |d| d.as_millis()).to_string()
+            }
+            "cache_capacity" => {
+                self.store.set_cache_capacity(new.page_store.cache_capacity);
+                options.page_store.cache_capacity.to_string()
+            }
+            "max_deferred_bytes" => {
+                self.store
+                    .set_max_deferred_bytes(new.page_store.max_deferred_bytes);
+                options.page_store.max_deferred_bytes.to_string()
+            }
+            _ => unreachable!(),
+        };
+        *options = new;
+        info!("Change option {name} from {old} to {value}");
+        self.audit("set_option", || format!("{name}: {old} -> {value}"));
+        Ok(())
+    }
+
+    /// Records an operation to the workload trace if capture is started.
+    #[inline]
+    /// Captures and replicates a write of a single key.
+    fn on_written(&self, key: Key<'_>, value: Value<'_>) {
+        let kind = match value {
+            Value::Put(_) => WorkloadOpKind::Put,
+            Value::Delete => WorkloadOpKind::Delete,
+        };
+        self.capture(kind, key.raw, value.len());
+        self.replicate(|| vec![replicated_op(key, value)]);
+    }
+
+    fn capture(&self, kind: WorkloadOpKind, key: &[u8], value_size: usize) {
+        if self.workload.is_enabled() {
+            self.workload.record(kind, key, value_size);
+        }
+    }
+
+    /// Publishes writes to the replication streams if there is any.
+    #[inline]
+    fn replicate(&self, ops: impl FnOnce() -> Vec<ReplicatedOp>) {
+        self.replicate_record(|| ReplicationRecord::Batch(ops()));
+    }
+
+    #[inline]
+    fn replicate_record(&self, record: impl FnOnce() -> ReplicationRecord) {
+        if self.replicator.is_enabled() {
+            self.replicator.publish(record());
+        }
+    }
+
+    /// Records an operation to the audit log if it is enabled.
+    fn audit(&self, op: &str, detail: impl FnOnce() -> String) {
+        if let Some(log) = &self.audit_log {
+            log.record(op, &detail());
+        }
+    }
+
+    /// Returns the state of the table as a JSON string for debugging.
+    ///
+    /// The state includes the options in effect, a summary of the stats, the
+    /// shape of the tree, the cache stats, and the list of page files. The
+    /// format is not stable and may change between versions. This walks
+    /// through all pages in the table, so it should not be called frequently.
+    pub async fn debug_state_json(&self) -> Result<String> {
+        let shape = self.describe_tree().await?;
+        let debt = self.maintenance_debt().await?;
+        let TableStats { tree, store } = self.stats();
+        let levels = shape
+            .levels
+            .iter()
+            .map(|level| {
+                Json::Object(vec![
+                    ("num_pages", level.num_pages.into()),
+                    ("num_delta_pages", level.num_delta_pages.into()),
+                    ("total_size", level.total_size.into()),
+                ])
+            })
+            .collect();
+        let cache = |stats: &CacheStats| {
+            Json::Object(vec![
+                ("lookup_hit", stats.lookup_hit.into()),
+                ("lookup_miss", stats.lookup_miss.into()),
+                ("insert", stats.insert.into()),
+                ("active_evict", stats.active_evict.into()),
+                ("passive_evict", stats.passive_evict.into()),
+            ])
+        };
+        let state = Json::Object(vec![
+            ("options", self.options.lock().to_json()),
+            ("latest_lsn", self.latest_lsn().into()),
+            ("safe_lsn", self.safe_lsn().into()),
+            (
+                "stats",
+                Json::Object(vec![
+                    ("reads", tree.success.read.into()),
+                    ("writes", tree.success.write.into()),
+                    ("split_pages", tree.success.split_page.into()),
+                    ("consolidate_pages", tree.success.consolidate_page.into()),
+                    ("failed_cas", tree.failed_cas.into()),
+                    ("hot_pages", tree.hot_pages.into()),
+                    ("slow_ops", tree.slow_ops.into()),
+                    ("max_chain_len", tree.chain.max_len.into()),
+                    ("stall_writes", store.buffer_set.stall_writes.into()),
+                    ("flush_write_bytes", store.jobs.flush_write_bytes.into()),
+                    ("compact_write_bytes", store.jobs.compact_write_bytes.into()),
+                    ("allocated_page_ids", store.page_table.allocated_ids.into()),
+                    ("pending_consolidations", debt.pending_consolidations.into()),
+                    ("dirty_bytes", debt.dirty_bytes.into()),
+                    ("garbage_bytes", debt.garbage_bytes.into()),
+                ]),
+            ),
+            (
+                "tree",
+                Json::Object(vec![
+                    ("height", shape.height.into()),
+                    ("num_pages", shape.num_pages().into()),
+                    ("levels", Json::Array(levels)),
+                ]),
+            ),
+            (
+                "cache",
+                Json::Object(vec![
+                    ("page_cache", cache(&store.page_cache)),
+                    ("file_reader_cache", cache(&store.file_reader_cache)),
+                ]),
+            ),
+            ("files", self.store.files_json()),
+        ]);
+        Ok(state.to_string())
+    }
+
+    /// Returns the amount of maintenance work that is not done yet.
+    ///
+    /// This reads the info of every page in the table to count the pages to
+    /// consolidate, so it should not be called frequently.
+    pub async fn maintenance_debt(&self) -> Result<MaintenanceDebt> {
+        MaintenanceDebt::of(&self.tree, &self.store)
+    }
+
+    /// Runs a maintenance command, and waits until it is done.
+    ///
+    /// Maintenance work is usually triggered by writes and the space usage.
+    /// This allows running it at a chosen time instead, e.g. during off-peak
+    /// hours:
+    ///
+    /// - [`MaintenanceCommand::Flush`] flushes the active write buffer.
+    /// - [`MaintenanceCommand::Consolidate`] consolidates every page with delta
+    ///   pages, regardless of the chain length.
+    /// - [`MaintenanceCommand::Reclaim`] compacts every page file with obsolete
+    ///   pages, regardless of the space usage, even if space reclaiming is
+    ///   disabled.
+    ///
+    /// Commands can also be submitted from another process with
+    /// [`MaintenanceCommand::submit`].
+    pub async fn run_maintenance(&self, cmd: MaintenanceCommand) -> Result<()> {
+        admin::run_command(&self.tree, &self.store, cmd).await?;
+        info!("Run maintenance command {cmd}");
         Ok(())
     }
 
@@ -104,11 +1393,31 @@ impl<E: Env> Table<E> {
     ///
     /// The safe LSN must be increasing, so updating it with a smaller value has
     /// no effect. When the safe LSN is advanced, the table will gradually drop
-    /// entries that are not visible to the LSN anymore.
+    /// entries that are not visible to the LSN anymore, except those visible to
+    /// active snapshots.
     pub fn set_safe_lsn(&self, lsn: u64) {
         self.tree.set_safe_lsn(lsn);
     }
 
+    /// Returns a snapshot that reads the table with the LSN.
+    ///
+    /// Entries visible to the snapshot are retained even if the safe LSN is
+    /// advanced beyond it, until the snapshot is dropped. The LSN should not
+    /// be smaller than the current safe LSN, otherwise some entries visible to
+    /// it may have been dropped already.
+    pub fn snapshot(&self, lsn: u64) -> Snapshot<E> {
+        Snapshot::new(self.clone(), lsn)
+    }
+
+    /// Returns a summary of the live snapshots of the table.
+    ///
+    /// The oldest snapshot keeps the entries visible to it from being
+    /// dropped, so a snapshot that is held for long blocks garbage
+    /// collection.
+    pub fn live_snapshots(&self) -> LiveSnapshots {
+        self.tree.live_snapshots()
+    }
+
     /// Flush all write buffer data.
     pub async fn flush(&self, opts: &FlushOptions) {
         self.store.flush(opts).await;
@@ -120,7 +1429,68 @@ impl<E: Env> Table<E> {
     }
 }
 
+fn prepared_metadata(token: PreparedToken) -> String {
+    // The ids are padded so that the entries are sorted by ids.
+    format!("{PREPARED_METADATA_PREFIX}{:016x}", token.id())
+}
+
+fn replicated_op(key: Key<'_>, value: Value<'_>) -> ReplicatedOp {
+    match value {
+        Value::Put(v) => ReplicatedOp::Put {
+            key: key.raw.to_vec(),
+            lsn: key.lsn,
+            value: v.to_vec(),
+        },
+        Value::Delete => ReplicatedOp::Delete {
+            key: key.raw.to_vec(),
+            lsn: key.lsn,
+        },
+    }
+}
+
+/// A consistent view of a table at an LSN.
+///
+/// The snapshot retains entries visible to its LSN until it is dropped.
+pub struct Snapshot<E: Env> {
+    table: Table<E>,
+    lsn: u64,
+    taken_at: Instant,
+}
+
+impl<E: Env> Snapshot<E> {
+    fn new(table: Table<E>, lsn: u64) -> Self {
+        let taken_at = table.tree.register_snapshot(lsn);
+        Self {
+            table,
+            lsn,
+            taken_at,
+        }
+    }
+
+    /// Returns the LSN of the snapshot.
+    pub fn lsn(&self) -> u64 {
+        self.lsn
+    }
+
+    /// Gets the value corresponding to the key in the snapshot.
+    pub async fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.table.get(key, self.lsn).await
+    }
+}
+
+impl<E: Env> Drop for Snapshot<E> {
+    fn drop(&mut self) {
+        self.table.tree.release_snapshot(self.lsn, self.taken_at);
+    }
+}
+
 /// A handle that holds some resources of a table for user operations.
+///
+/// Each operation on a [`Table`] pins and unpins the table on its own. A guard
+/// pins the table once for all operations done with it, which saves the cost
+/// of pinning for batches of small operations, such as point lookups. The
+/// resources pinned by a guard can't be released until it is dropped or
+/// [`Guard::repin`] is called, so it should not be held for long.
 pub struct Guard<'a, E: Env> {
     table: &'a Table<E>,
     txn: TreeTxn<'a, E>,
@@ -144,13 +1514,65 @@ impl<'a, E: Env> Guard<'a, E> {
     /// On success, if the value is found, returns [`Option::Some`] with the
     /// value; if the value is not found, returns [`Option::None`].
     pub async fn get(&self, key: &[u8], lsn: u64) -> Result<Option<&[u8]>> {
-        let key = Key::new(key, lsn);
-        Ok(self.txn.get(key).await?)
+        self.table.get_in(&self.txn, key, lsn).await
+    }
+
+    /// Gets the values corresponding to the keys.
+    ///
+    /// This is a version of [`Table::multi_get`] that returns the values
+    /// without copying them.
+    pub async fn multi_get(&self, keys: &[&[u8]], lsn: u64) -> Result<Vec<Option<&[u8]>>> {
+        self.table.multi_get_in(&self.txn, keys, lsn).await
+    }
+
+    /// Puts a key-value entry to the table.
+    ///
+    /// See [`Table::put`] for more details.
+    pub async fn put(&self, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
+        self.table.put_in(&self.txn, key, lsn, value).await
+    }
+
+    /// Deletes the entry corresponding to the key from the table.
+    ///
+    /// See [`Table::delete`] for more details.
+    pub async fn delete(&self, key: &[u8], lsn: u64) -> Result<()> {
+        self.table.delete_in(&self.txn, key, lsn).await
+    }
+
+    /// Writes a batch of entries to the table.
+    ///
+    /// See [`Table::write_batch`] for more details.
+    pub async fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        self.table.write_batch_in(&self.txn, batch).await
     }
 
     /// Returns an iterator over pages in the table.
     pub fn pages(&self) -> Pages<'_, 'a, E> {
-        Pages::new(&self.txn)
+        Pages::new(&self.txn, ReadOptions::default())
+    }
+
+    /// Returns an iterator over pages in the table with the options.
+    ///
+    /// Scans that read most of the table once, such as analytics, should set
+    /// [`ReadOptions::fill_cache`] to false, so that they don't evict the
+    /// pages of other reads from the page cache. Setting
+    /// [`ReadOptions::max_lsn`] to the LSN of a [`Snapshot`] scans a
+    /// consistent view of the table.
+    pub fn pages_with_options(&self, opts: ReadOptions) -> Pages<'_, 'a, E> {
+        Pages::new(&self.txn, opts)
+    }
+
+    /// Returns a cursor over entries visible to the LSN in the table.
+    ///
+    /// The cursor is not positioned until it is sought. It reads a page once
+    /// when it moves to the page, so writes to a page after that are not
+    /// observed until [`Cursor::refresh`] is called, even if they are visible
+    /// to the LSN. Writes to pages that the cursor has not moved to yet are
+    /// observed if they are visible to the LSN. To read a fixed view of the
+    /// table under concurrent writes, use the LSN of a [`Snapshot`] that is
+    /// taken before the writes are assigned larger LSNs.
+    pub fn cursor(&self, lsn: u64) -> Cursor<'_, 'a, E> {
+        Cursor::new(self.table, &self.txn, lsn)
     }
 }
 
@@ -160,15 +1582,138 @@ pub struct Pages<'a, 't: 'a, E: Env> {
 }
 
 impl<'a, 't: 'a, E: Env> Pages<'a, 't, E> {
-    fn new(txn: &'a TreeTxn<'t, E>) -> Self {
+    fn new(txn: &'a TreeTxn<'t, E>, opts: ReadOptions) -> Self {
         Self {
-            iter: TreeIter::new(txn, ReadOptions::default()),
+            iter: TreeIter::new(txn, opts),
         }
     }
 
     /// Returns the next page in the table.
     pub async fn next(&mut self) -> Result<Option<PageIter<'_>>> {
-        Ok(self.iter.next_page().await?)
+        Ok(instrument!(self.iter.next_page(), "next_page").await?)
+    }
+}
+
+/// A cursor over entries in a table.
+///
+/// Entries at the current position can be overwritten or deleted without
+/// another lookup from the root. Once the cursor moves past the last entry,
+/// it stays there until it is sought again.
+///
+/// See [`Guard::cursor`] for the entries that the cursor observes.
+pub struct Cursor<'a, 't: 'a, E: Env> {
+    table: &'t Table<E>,
+    cursor: TreeCursor<'a, 't, E>,
+}
+
+impl<'a, 't: 'a, E: Env> Cursor<'a, 't, E> {
+    fn new(table: &'t Table<E>, txn: &'a TreeTxn<'t, E>, lsn: u64) -> Self {
+        Self {
+            table,
+            cursor: TreeCursor::new(txn, lsn),
+        }
+    }
+
+    /// Returns the key of the current entry.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.cursor.current().map(|(k, _)| k)
+    }
+
+    /// Returns the value of the current entry.
+    pub fn value(&self) -> Option<&[u8]> {
+        self.cursor.current().map(|(_, v)| v)
+    }
+
+    /// Positions the cursor at the first entry that is at or after `target`.
+    pub async fn seek(&mut self, target: &[u8]) -> Result<()> {
+        Ok(instrument!(self.cursor.seek(target), "seek").await?)
+    }
+
+    /// Moves the cursor to the next entry.
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> Result<()> {
+        Ok(instrument!(self.cursor.next(), "next").await?)
+    }
+
+    /// Appends the current entry and the entries after it to `entries`, up
+    /// to `n` entries, and moves the cursor to the entry after the last
+    /// appended one.
+    ///
+    /// Returns the number of entries appended, which is zero if the cursor is
+    /// not positioned at an entry. This is cheaper than [`Cursor::next`] for
+    /// each entry, since the entries of a page are decoded in batches.
+    pub async fn next_n(
+        &mut self,
+        n: usize,
+        entries: &mut Vec<(&'a [u8], &'a [u8])>,
+    ) -> Result<usize> {
+        Ok(instrument!(self.cursor.next_n(n, entries), "next_n", n).await?)
+    }
+
+    /// Reloads the page of the current entry, so that the cursor observes
+    /// the writes done since it moved to the page.
+    ///
+    /// The cursor is positioned at the first entry that is at or after the
+    /// current key, which is the next entry if the current one is deleted.
+    /// It does nothing if the cursor is not positioned at an entry.
+    pub async fn refresh(&mut self) -> Result<()> {
+        Ok(instrument!(self.cursor.refresh(), "refresh").await?)
+    }
+
+    /// Overwrites the value of the current entry.
+    ///
+    /// The write is captured and replicated as [`Table::put`] does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is not positioned at an entry.
+    pub async fn put(&mut self, lsn: u64, value: &[u8]) -> Result<()> {
+        self.write(lsn, Value::Put(value)).await
+    }
+
+    /// Deletes the current entry.
+    ///
+    /// The cursor stays at the deleted entry until it is moved. The delete is
+    /// captured and replicated as [`Table::delete`] does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is not positioned at an entry.
+    pub async fn delete(&mut self, lsn: u64) -> Result<()> {
+        self.write(lsn, Value::Delete).await
+    }
+
+    async fn write(&mut self, lsn: u64, value: Value<'_>) -> Result<()> {
+        self.cursor.write(lsn, value).await?;
+        let (raw, _) = self
+            .cursor
+            .current()
+            .expect("cursor must be positioned at an entry");
+        self.table.on_written(Key::new(raw, lsn), value);
+        Ok(())
+    }
+}
+
+/// The amount of maintenance work that is not done yet in a table.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceDebt {
+    /// The number of pages whose delta chains should be consolidated.
+    pub pending_consolidations: u64,
+    /// The bytes of pages in write buffers that are not flushed yet.
+    pub dirty_bytes: u64,
+    /// The bytes of obsolete pages in page files that can be reclaimed.
+    pub garbage_bytes: u64,
+}
+
+impl MaintenanceDebt {
+    pub(super) fn of<E: Env>(tree: &Tree, store: &PageStore<E>) -> Result<Self> {
+        let txn = tree.begin(store.guard());
+        Ok(Self {
+            pending_consolidations: txn.pending_consolidations()?,
+            dirty_bytes: store.dirty_bytes(),
+            garbage_bytes: store.garbage_bytes(),
+        })
     }
 }
 
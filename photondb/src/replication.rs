@@ -0,0 +1,543 @@
+//! A replication stream of the writes to a table.
+//!
+//! A primary table publishes each write as a [`ReplicationRecord`] to the
+//! streams returned by [`raw::Table::subscribe_replication`], and a replica
+//! applies the records with [`raw::Table::apply_replicated`], which keeps the
+//! LSNs of the primary.
+//!
+//! Records can be shipped between processes with [`ReplicationEncoder`] and
+//! [`ReplicationDecoder`]. An encoded stream starts with a header of
+//! `PHOTONRS` and a `u32` version, followed by frames of:
+//!
+//! | Field   | Type      | Description                        |
+//! |---------|-----------|------------------------------------|
+//! | kind    | `u8`      | 1 for a batch, 2 for a watermark   |
+//! | len     | `u32`     | The length of the payload          |
+//! | crc32   | `u32`     | The checksum of the payload        |
+//! | payload | `[u8]`    | The record                         |
+//!
+//! Integers are little-endian. Frames of unknown kinds are skipped, so that
+//! new kinds of records can be added without breaking older replicas.
+//!
+//! Each stream buffers a bounded number of records. A stream that falls
+//! behind by more than its capacity is disconnected instead of stalling the
+//! writes or buffering without a limit: it ends after the buffered records,
+//! and [`ReplicationStream::is_lagged`] returns true. Since the records are
+//! not persisted, a stream can't be resumed from an LSN. A lagged replica
+//! must be rebuilt from an export of the primary taken after it subscribes
+//! again.
+//!
+//! [`raw::Table::subscribe_replication`]: crate::raw::Table::subscribe_replication
+//! [`raw::Table::apply_replicated`]: crate::raw::Table::apply_replicated
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{channel::mpsc, Stream, StreamExt};
+use parking_lot::Mutex;
+
+use crate::{Error, Result};
+
+const MAGIC: &[u8; 8] = b"PHOTONRS";
+const VERSION: u32 = 1;
+
+const KIND_BATCH: u8 = 1;
+const KIND_WATERMARK: u8 = 2;
+
+const OP_PUT: u8 = 1;
+const OP_DELETE: u8 = 2;
+const OP_DELETE_RANGE: u8 = 3;
+const OP_CLEAR: u8 = 4;
+
+/// A write in a replicated batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplicatedOp {
+    /// A put of a key-value entry.
+    Put {
+        /// The key of the entry.
+        key: Vec<u8>,
+        /// The LSN of the entry.
+        lsn: u64,
+        /// The value of the entry.
+        value: Vec<u8>,
+    },
+    /// A delete of a key.
+    Delete {
+        /// The key to delete.
+        key: Vec<u8>,
+        /// The LSN of the delete.
+        lsn: u64,
+    },
+    /// A delete of the keys in `[start, end)`.
+    DeleteRange {
+        /// The start of the range.
+        start: Vec<u8>,
+        /// The end of the range.
+        end: Vec<u8>,
+        /// The LSN of the delete.
+        lsn: u64,
+    },
+    /// A delete of all keys.
+    Clear {
+        /// The LSN of the delete.
+        lsn: u64,
+    },
+}
+
+impl ReplicatedOp {
+    /// Returns the LSN of the write.
+    pub fn lsn(&self) -> u64 {
+        match self {
+            Self::Put { lsn, .. }
+            | Self::Delete { lsn, .. }
+            | Self::DeleteRange { lsn, .. }
+            | Self::Clear { lsn } => *lsn,
+        }
+    }
+}
+
+/// A record in a replication stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplicationRecord {
+    /// The writes of an operation on the primary.
+    Batch(Vec<ReplicatedOp>),
+    /// All writes at or below the LSN are published before this record.
+    ///
+    /// Watermarks are published with
+    /// [`raw::Table::mark_replication_watermark`], since LSNs are assigned by
+    /// users. A replica can read at the latest watermark it applies.
+    ///
+    /// [`raw::Table::mark_replication_watermark`]: crate::raw::Table::mark_replication_watermark
+    Watermark(u64),
+}
+
+impl ReplicationRecord {
+    /// Returns the kind and the payload of the frame of the record.
+    fn encode(&self) -> (u8, Vec<u8>) {
+        let mut buf = Vec::new();
+        match self {
+            Self::Batch(ops) => {
+                buf.extend_from_slice(&(ops.len() as u32).to_le_bytes());
+                for op in ops {
+                    match op {
+                        ReplicatedOp::Put { key, lsn, value } => {
+                            buf.push(OP_PUT);
+                            buf.extend_from_slice(&lsn.to_le_bytes());
+                            put_bytes(&mut buf, key);
+                            put_bytes(&mut buf, value);
+                        }
+                        ReplicatedOp::Delete { key, lsn } => {
+                            buf.push(OP_DELETE);
+                            buf.extend_from_slice(&lsn.to_le_bytes());
+                            put_bytes(&mut buf, key);
+                        }
+                        ReplicatedOp::DeleteRange { start, end, lsn } => {
+                            buf.push(OP_DELETE_RANGE);
+                            buf.extend_from_slice(&lsn.to_le_bytes());
+                            put_bytes(&mut buf, start);
+                            put_bytes(&mut buf, end);
+                        }
+                        ReplicatedOp::Clear { lsn } => {
+                            buf.push(OP_CLEAR);
+                            buf.extend_from_slice(&lsn.to_le_bytes());
+                        }
+                    }
+                }
+                (KIND_BATCH, buf)
+            }
+            Self::Watermark(lsn) => {
+                buf.extend_from_slice(&lsn.to_le_bytes());
+                (KIND_WATERMARK, buf)
+            }
+        }
+    }
+
+    /// Decodes a record from the payload of a frame.
+    ///
+    /// Returns [`None`] if the kind is unknown.
+    fn decode(kind: u8, payload: &[u8]) -> Result<Option<Self>> {
+        let mut buf = payload;
+        let record = match kind {
+            KIND_BATCH => {
+                let count = get_u32(&mut buf)?;
+                let mut ops = Vec::new();
+                for _ in 0..count {
+                    let op = get_u8(&mut buf)?;
+                    let lsn = get_u64(&mut buf)?;
+                    let op = match op {
+                        OP_PUT => ReplicatedOp::Put {
+                            key: get_bytes(&mut buf)?,
+                            lsn,
+                            value: get_bytes(&mut buf)?,
+                        },
+                        OP_DELETE => ReplicatedOp::Delete {
+                            key: get_bytes(&mut buf)?,
+                            lsn,
+                        },
+                        OP_DELETE_RANGE => ReplicatedOp::DeleteRange {
+                            start: get_bytes(&mut buf)?,
+                            end: get_bytes(&mut buf)?,
+                            lsn,
+                        },
+                        OP_CLEAR => ReplicatedOp::Clear { lsn },
+                        _ => return Err(Error::corrupted()),
+                    };
+                    ops.push(op);
+                }
+                Self::Batch(ops)
+            }
+            KIND_WATERMARK => Self::Watermark(get_u64(&mut buf)?),
+            _ => return Ok(None),
+        };
+        if !buf.is_empty() {
+            return Err(Error::corrupted());
+        }
+        Ok(Some(record))
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn get_slice<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(Error::corrupted());
+    }
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+fn get_u8(buf: &mut &[u8]) -> Result<u8> {
+    Ok(get_slice(buf, 1)?[0])
+}
+
+fn get_u32(buf: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(get_slice(buf, 4)?.try_into().unwrap()))
+}
+
+fn get_u64(buf: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(get_slice(buf, 8)?.try_into().unwrap()))
+}
+
+fn get_bytes(buf: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = get_u32(buf)? as usize;
+    Ok(get_slice(buf, len)?.to_vec())
+}
+
+/// Encodes replication records to a writer.
+pub struct ReplicationEncoder<W: Write> {
+    w: W,
+}
+
+impl<W: Write> ReplicationEncoder<W> {
+    /// Creates an encoder, which writes the header of the stream.
+    pub fn new(mut w: W) -> Result<Self> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        Ok(Self { w })
+    }
+
+    /// Writes a record to the stream.
+    pub fn write(&mut self, record: &ReplicationRecord) -> Result<()> {
+        let (kind, payload) = record.encode();
+        self.w.write_all(&[kind])?;
+        self.w.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.w.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+        self.w.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+/// Decodes replication records from a reader.
+pub struct ReplicationDecoder<R: Read> {
+    r: R,
+}
+
+impl<R: Read> ReplicationDecoder<R> {
+    /// Creates a decoder, which reads and checks the header of the stream.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the stream is not a replication
+    /// stream or its version is not supported.
+    pub fn new(mut r: R) -> Result<Self> {
+        let mut header = [0; 12];
+        r.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(Error::InvalidArgument(
+                "not a replication stream".to_owned(),
+            ));
+        }
+        let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+        if version != VERSION {
+            return Err(Error::InvalidArgument(format!(
+                "unsupported replication stream version {version}"
+            )));
+        }
+        Ok(Self { r })
+    }
+
+    /// Reads the next record from the stream.
+    ///
+    /// Returns [`None`] at the end of the stream, and [`Error::Corrupted`] if
+    /// a frame is torn or fails the checksum.
+    pub fn next(&mut self) -> Result<Option<ReplicationRecord>> {
+        loop {
+            let mut header = [0; 9];
+            match self.r.read_exact(&mut header[..1]) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(err.into()),
+            }
+            read_frame(&mut self.r, &mut header[1..])?;
+            let kind = header[0];
+            let len = u32::from_le_bytes(header[1..5].try_into().unwrap());
+            let crc = u32::from_le_bytes(header[5..9].try_into().unwrap());
+            let mut payload = vec![0; len as usize];
+            read_frame(&mut self.r, &mut payload)?;
+            if crc32fast::hash(&payload) != crc {
+                return Err(Error::corrupted());
+            }
+            if let Some(record) = ReplicationRecord::decode(kind, &payload)? {
+                return Ok(Some(record));
+            }
+        }
+    }
+}
+
+/// Reads the rest of a frame, which is torn if the stream ends.
+fn read_frame<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<()> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(Error::corrupted()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A stream of the records published by a table.
+///
+/// The stream ends when the table is dropped, or when it falls behind by
+/// more than its capacity. See [`Self::is_lagged`] for the latter.
+#[derive(Debug)]
+pub struct ReplicationStream {
+    rx: mpsc::Receiver<ReplicationRecord>,
+    lagged: Arc<AtomicBool>,
+}
+
+impl ReplicationStream {
+    /// Receives the next record, waiting for it if necessary.
+    pub async fn recv(&mut self) -> Option<ReplicationRecord> {
+        self.rx.next().await
+    }
+
+    /// Receives the next record if there is one.
+    pub fn try_recv(&mut self) -> Option<ReplicationRecord> {
+        self.rx.try_next().ok().flatten()
+    }
+
+    /// Returns true if the stream is disconnected because its buffer is full.
+    ///
+    /// The records after the buffered ones are lost, so the replica must be
+    /// rebuilt once the stream ends.
+    pub fn is_lagged(&self) -> bool {
+        self.lagged.load(Ordering::Acquire)
+    }
+}
+
+impl Stream for ReplicationStream {
+    type Item = ReplicationRecord;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+/// Publishes the writes of a table to its replication streams.
+#[derive(Debug, Default)]
+pub(crate) struct Replicator {
+    enabled: AtomicBool,
+    subscribers: Mutex<Vec<Subscriber>>,
+    watermark: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    tx: mpsc::Sender<ReplicationRecord>,
+    lagged: Arc<AtomicBool>,
+}
+
+impl Replicator {
+    /// Returns a stream that buffers at most `capacity` records.
+    pub(crate) fn subscribe(&self, capacity: usize) -> ReplicationStream {
+        assert!(capacity > 0);
+        // The sender has a slot of its own besides the buffer of the channel.
+        let (tx, rx) = mpsc::channel(capacity - 1);
+        let lagged = Arc::new(AtomicBool::new(false));
+        let mut subscribers = self.subscribers.lock();
+        subscribers.push(Subscriber {
+            tx,
+            lagged: lagged.clone(),
+        });
+        self.enabled.store(true, Ordering::Release);
+        ReplicationStream { rx, lagged }
+    }
+
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Publishes a record to the streams that are not dropped.
+    ///
+    /// Streams whose buffers are full are disconnected and marked as lagged.
+    pub(crate) fn publish(&self, record: ReplicationRecord) {
+        let mut subscribers = self.subscribers.lock();
+        subscribers.retain_mut(|sub| match sub.tx.try_send(record.clone()) {
+            Ok(()) => true,
+            Err(err) => {
+                if err.is_full() {
+                    sub.lagged.store(true, Ordering::Release);
+                }
+                false
+            }
+        });
+        if subscribers.is_empty() {
+            self.enabled.store(false, Ordering::Release);
+        }
+    }
+
+    /// Returns the latest watermark applied from a primary.
+    pub(crate) fn watermark(&self) -> u64 {
+        self.watermark.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn advance_watermark(&self, lsn: u64) {
+        self.watermark.fetch_max(lsn, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<ReplicationRecord> {
+        vec![
+            ReplicationRecord::Batch(vec![
+                ReplicatedOp::Put {
+                    key: b"a".to_vec(),
+                    lsn: 1,
+                    value: b"1".to_vec(),
+                },
+                ReplicatedOp::Delete {
+                    key: b"b".to_vec(),
+                    lsn: 2,
+                },
+                ReplicatedOp::DeleteRange {
+                    start: b"c".to_vec(),
+                    end: b"d".to_vec(),
+                    lsn: 3,
+                },
+                ReplicatedOp::Clear { lsn: 3 },
+            ]),
+            ReplicationRecord::Watermark(3),
+        ]
+    }
+
+    fn encode(records: &[ReplicationRecord]) -> Vec<u8> {
+        let mut encoder = ReplicationEncoder::new(Vec::new()).unwrap();
+        for record in records {
+            encoder.write(record).unwrap();
+        }
+        encoder.into_inner()
+    }
+
+    fn decode(buf: &[u8]) -> Result<Vec<ReplicationRecord>> {
+        let mut decoder = ReplicationDecoder::new(buf)?;
+        let mut records = Vec::new();
+        while let Some(record) = decoder.next()? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let records = records();
+        let buf = encode(&records);
+        assert_eq!(decode(&buf).unwrap(), records);
+
+        // Skips frames of unknown kinds.
+        let mut with_unknown = buf.clone();
+        with_unknown.extend_from_slice(&[9, 1, 0, 0, 0]);
+        with_unknown.extend_from_slice(&crc32fast::hash(&[7]).to_le_bytes());
+        with_unknown.push(7);
+        with_unknown.extend_from_slice(&buf[12..]);
+        assert_eq!(decode(&with_unknown).unwrap().len(), 4);
+
+        assert!(matches!(
+            decode(&buf[..buf.len() - 1]),
+            Err(Error::Corrupted { .. })
+        ));
+        let mut flipped = buf.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(decode(&flipped), Err(Error::Corrupted { .. })));
+        assert!(matches!(
+            decode(b"NOTPHOTON\0\0\0"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn publish_to_streams() {
+        let replicator = Replicator::default();
+        assert!(!replicator.is_enabled());
+        let mut a = replicator.subscribe(8);
+        let b = replicator.subscribe(8);
+        assert!(replicator.is_enabled());
+        replicator.publish(ReplicationRecord::Watermark(1));
+        drop(b);
+        replicator.publish(ReplicationRecord::Watermark(2));
+        assert_eq!(a.try_recv(), Some(ReplicationRecord::Watermark(1)));
+        assert_eq!(a.try_recv(), Some(ReplicationRecord::Watermark(2)));
+        assert_eq!(a.try_recv(), None);
+        drop(a);
+        replicator.publish(ReplicationRecord::Watermark(3));
+        assert!(!replicator.is_enabled());
+    }
+
+    #[test]
+    fn disconnect_lagged_streams() {
+        let replicator = Replicator::default();
+        let mut a = replicator.subscribe(2);
+        let mut b = replicator.subscribe(4);
+        for lsn in 1..=3 {
+            replicator.publish(ReplicationRecord::Watermark(lsn));
+        }
+        assert!(a.is_lagged());
+        assert!(!b.is_lagged());
+        assert_eq!(a.try_recv(), Some(ReplicationRecord::Watermark(1)));
+        assert_eq!(a.try_recv(), Some(ReplicationRecord::Watermark(2)));
+        assert_eq!(a.try_recv(), None);
+        assert_eq!(futures::executor::block_on(a.recv()), None);
+
+        // The other stream keeps receiving records.
+        replicator.publish(ReplicationRecord::Watermark(4));
+        for lsn in 1..=4 {
+            assert_eq!(b.try_recv(), Some(ReplicationRecord::Watermark(lsn)));
+        }
+        assert!(replicator.is_enabled());
+    }
+}
@@ -0,0 +1,472 @@
+//! A compatibility layer with the API shape of [sled].
+//!
+//! This is a thin layer on top of the synchronous [`Table`] to ease migrating
+//! from sled. Trees are stored in the same table under different key
+//! prefixes, and the read-modify-write operations of trees, like
+//! [`Tree::insert`] and [`Tree::compare_and_swap`], are atomic with respect to
+//! each other. Entries written to the table directly are not protected.
+//!
+//! ```no_run
+//! use photondb::sled_compat::Db;
+//!
+//! # fn example() -> photondb::Result<()> {
+//! let db = Db::open("/tmp/db")?;
+//! db.insert(b"a", b"1".to_vec())?;
+//! assert_eq!(db.get(b"a")?, Some(b"1".to_vec()));
+//! let swapped = db.compare_and_swap(b"a", Some(b"1"), Some(b"2".to_vec()))?;
+//! assert!(swapped.is_ok());
+//! for entry in db.range(b"a".as_slice()..) {
+//!     let (key, value) = entry?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [sled]: https://crates.io/crates/sled
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Bound, Deref, RangeBounds},
+    path::Path,
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+
+use crate::{std::Table, MaintenanceCommand, Result, TableOptions, WriteBatch};
+
+/// The name of the tree that [`Db`] derefs to.
+const DEFAULT_TREE_NAME: &[u8] = b"__sled__default";
+
+/// The number of locks that serialize writes to the same keys.
+const NUM_LOCKS: usize = 64;
+
+/// The number of entries read from the table at a time by [`Iter`].
+const ITER_BATCH_SIZE: usize = 128;
+
+/// Keys and values, which are owned byte vectors instead of sled's inline
+/// vectors.
+pub type IVec = Vec<u8>;
+
+/// The result of [`Tree::compare_and_swap`].
+pub type CompareAndSwapResult = std::result::Result<(), CompareAndSwapError>;
+
+/// The error returned when the current value does not match the expected
+/// one in [`Tree::compare_and_swap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompareAndSwapError {
+    /// The current value of the key.
+    pub current: Option<IVec>,
+    /// The value that was proposed to set.
+    pub proposed: Option<IVec>,
+}
+
+impl fmt::Display for CompareAndSwapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Compare and swap conflict")
+    }
+}
+
+impl std::error::Error for CompareAndSwapError {}
+
+/// A database with a default tree and named trees.
+///
+/// The database derefs to the default tree.
+#[derive(Clone, Debug)]
+pub struct Db {
+    default: Tree,
+}
+
+impl Db {
+    /// Opens a database in the path with the default options.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_options(path, TableOptions::default())
+    }
+
+    /// Opens a database in the path with the options of the table.
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: TableOptions) -> Result<Self> {
+        let table = Table::open(path, options)?;
+        let locks = Arc::new((0..NUM_LOCKS).map(|_| Mutex::new(())).collect());
+        Ok(Self {
+            default: Tree::new(table, locks, DEFAULT_TREE_NAME),
+        })
+    }
+
+    /// Opens a tree with the name.
+    ///
+    /// Trees are created on demand, so this never fails for now.
+    pub fn open_tree<V: AsRef<[u8]>>(&self, name: V) -> Result<Tree> {
+        Ok(Tree::new(
+            self.default.table.clone(),
+            self.default.locks.clone(),
+            name.as_ref(),
+        ))
+    }
+
+    /// Removes all entries of the tree with the name.
+    ///
+    /// Returns true if the tree has any entry.
+    pub fn drop_tree<V: AsRef<[u8]>>(&self, name: V) -> Result<bool> {
+        let tree = self.open_tree(name)?;
+        let exists = tree.iter().next().transpose()?.is_some();
+        if exists {
+            let end = prefix_end(&tree.prefix);
+            let lsn = tree.table.next_lsn();
+            tree.table.delete_range(&tree.prefix, &end, lsn)?;
+        }
+        Ok(exists)
+    }
+
+    /// Returns the underlying table.
+    pub fn table(&self) -> &Table {
+        &self.default.table
+    }
+}
+
+impl Deref for Db {
+    type Target = Tree;
+
+    fn deref(&self) -> &Self::Target {
+        &self.default
+    }
+}
+
+/// A sorted map of keys and values in a [`Db`].
+#[derive(Clone)]
+pub struct Tree {
+    table: Table,
+    locks: Arc<Vec<Mutex<()>>>,
+    // The length and the name of the tree, which prefixes all keys in it.
+    prefix: Vec<u8>,
+}
+
+impl Tree {
+    fn new(table: Table, locks: Arc<Vec<Mutex<()>>>, name: &[u8]) -> Self {
+        let mut prefix = Vec::with_capacity(4 + name.len());
+        prefix.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        prefix.extend_from_slice(name);
+        Self {
+            table,
+            locks,
+            prefix,
+        }
+    }
+
+    /// Returns the name of the tree.
+    pub fn name(&self) -> IVec {
+        self.prefix[4..].to_vec()
+    }
+
+    /// Inserts a key to a value, and returns the previous value if any.
+    pub fn insert<K: AsRef<[u8]>, V: Into<IVec>>(&self, key: K, value: V) -> Result<Option<IVec>> {
+        let key = self.encode_key(key.as_ref());
+        let _lock = self.lock(&key);
+        let prev = self.table.get(&key, u64::MAX)?;
+        self.table.put(&key, self.table.next_lsn(), &value.into())?;
+        Ok(prev)
+    }
+
+    /// Returns the value of the key if any.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        self.table.get(&self.encode_key(key.as_ref()), u64::MAX)
+    }
+
+    /// Returns true if the tree contains a value for the key.
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Removes the value of the key, and returns it if any.
+    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        let key = self.encode_key(key.as_ref());
+        let _lock = self.lock(&key);
+        let prev = self.table.get(&key, u64::MAX)?;
+        if prev.is_some() {
+            self.table.delete(&key, self.table.next_lsn())?;
+        }
+        Ok(prev)
+    }
+
+    /// Sets the value of the key to `new` if the current value is `old`.
+    ///
+    /// `None` as `old` means that the key must not exist, and `None` as `new`
+    /// removes the key. Returns [`CompareAndSwapError`] in the inner result
+    /// if the current value does not match.
+    pub fn compare_and_swap<K, OV, NV>(
+        &self,
+        key: K,
+        old: Option<OV>,
+        new: Option<NV>,
+    ) -> Result<CompareAndSwapResult>
+    where
+        K: AsRef<[u8]>,
+        OV: AsRef<[u8]>,
+        NV: Into<IVec>,
+    {
+        let key = self.encode_key(key.as_ref());
+        let _lock = self.lock(&key);
+        let current = self.table.get(&key, u64::MAX)?;
+        let old: Option<&[u8]> = old.as_ref().map(AsRef::as_ref);
+        let new = new.map(Into::into);
+        if current.as_deref() != old {
+            return Ok(Err(CompareAndSwapError {
+                current,
+                proposed: new,
+            }));
+        }
+        let lsn = self.table.next_lsn();
+        match new {
+            Some(value) => self.table.put(&key, lsn, &value)?,
+            None => self.table.delete(&key, lsn)?,
+        }
+        Ok(Ok(()))
+    }
+
+    /// Applies the writes of the batch to the tree.
+    ///
+    /// Unlike sled, the batch is not atomic across pages of the table, and it
+    /// is not serialized with other writes to the same keys.
+    pub fn apply_batch(&self, batch: Batch) -> Result<()> {
+        let lsn = self.table.next_lsn();
+        let mut write_batch = WriteBatch::new();
+        for (key, value) in batch.writes {
+            let key = self.encode_key(&key);
+            match value {
+                Some(value) => write_batch.put(&key, lsn, &value),
+                None => write_batch.delete(&key, lsn),
+            }
+        }
+        self.table.write_batch(&write_batch)
+    }
+
+    /// Returns an iterator over all entries in the tree in key order.
+    pub fn iter(&self) -> Iter {
+        self.range::<&[u8], _>(..)
+    }
+
+    /// Returns an iterator over the entries in the range in key order.
+    pub fn range<K: AsRef<[u8]>, R: RangeBounds<K>>(&self, range: R) -> Iter {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.encode_key(key.as_ref()),
+            Bound::Excluded(key) => {
+                let mut start = self.encode_key(key.as_ref());
+                start.push(0);
+                start
+            }
+            Bound::Unbounded => self.prefix.clone(),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => Bound::Included(self.encode_key(key.as_ref())),
+            Bound::Excluded(key) => Bound::Excluded(self.encode_key(key.as_ref())),
+            Bound::Unbounded => Bound::Excluded(prefix_end(&self.prefix)),
+        };
+        Iter {
+            table: self.table.clone(),
+            prefix_len: self.prefix.len(),
+            next: Some(start),
+            end,
+            buf: VecDeque::new(),
+        }
+    }
+
+    /// Returns an iterator over the entries whose keys start with the prefix.
+    pub fn scan_prefix<P: AsRef<[u8]>>(&self, prefix: P) -> Iter {
+        let mut iter = self.range(prefix.as_ref()..);
+        iter.end = Bound::Excluded(prefix_end(&self.encode_key(prefix.as_ref())));
+        iter
+    }
+
+    /// Flushes the dirty pages of the table to page files.
+    pub fn flush(&self) -> Result<()> {
+        self.table.run_maintenance(MaintenanceCommand::Flush)
+    }
+
+    fn encode_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.prefix.len() + key.len());
+        buf.extend_from_slice(&self.prefix);
+        buf.extend_from_slice(key);
+        buf
+    }
+
+    fn lock(&self, key: &[u8]) -> parking_lot::MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.locks[hasher.finish() as usize % self.locks.len()].lock()
+    }
+}
+
+impl fmt::Debug for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tree")
+            .field("name", &String::from_utf8_lossy(&self.prefix[4..]))
+            .finish()
+    }
+}
+
+/// A batch of writes to apply to a [`Tree`].
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    writes: Vec<(IVec, Option<IVec>)>,
+}
+
+impl Batch {
+    /// Sets the key to the value.
+    pub fn insert<K: Into<IVec>, V: Into<IVec>>(&mut self, key: K, value: V) {
+        self.writes.push((key.into(), Some(value.into())));
+    }
+
+    /// Removes the key.
+    pub fn remove<K: Into<IVec>>(&mut self, key: K) {
+        self.writes.push((key.into(), None));
+    }
+}
+
+/// An iterator over entries in a [`Tree`].
+///
+/// Entries are read from the table in batches, so the iterator does not pin
+/// the table between batches, and it may see writes after it is created.
+pub struct Iter {
+    table: Table,
+    prefix_len: usize,
+    // The key to read the next batch from, or `None` if all entries are read.
+    next: Option<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    buf: VecDeque<(IVec, IVec)>,
+}
+
+impl Iter {
+    fn fill(&mut self) -> Result<()> {
+        let Some(start) = self.next.take() else {
+            return Ok(());
+        };
+        let guard = self.table.pin();
+        let mut cursor = guard.cursor(u64::MAX);
+        cursor.seek(&start)?;
+        while let (Some(key), Some(value)) = (cursor.key(), cursor.value()) {
+            let in_range = match &self.end {
+                Bound::Included(end) => key <= end.as_slice(),
+                Bound::Excluded(end) => key < end.as_slice(),
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                return Ok(());
+            }
+            if self.buf.len() == ITER_BATCH_SIZE {
+                self.next = Some(key.to_vec());
+                return Ok(());
+            }
+            self.buf
+                .push_back((key[self.prefix_len..].to_vec(), value.to_vec()));
+            cursor.next()?;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Iter {
+    type Item = Result<(IVec, IVec)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            if let Err(err) = self.fill() {
+                return Some(Err(err));
+            }
+        }
+        self.buf.pop_front().map(Ok)
+    }
+}
+
+/// Returns the smallest key that is larger than all keys with the prefix.
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    // Prefixes start with the lengths of tree names, so they can not be all
+    // 0xff.
+    unreachable!("prefix {prefix:?} has no end")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn collect(iter: Iter) -> Vec<(IVec, IVec)> {
+        iter.collect::<Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn tree_operations() {
+        let path = tempdir().unwrap();
+        let db = Db::open(&path).unwrap();
+        assert_eq!(db.insert(b"a", b"1".to_vec()).unwrap(), None);
+        assert_eq!(db.insert(b"a", b"2".to_vec()).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(b"a").unwrap(), Some(b"2".to_vec()));
+        assert!(db.contains_key(b"a").unwrap());
+        assert_eq!(db.remove(b"a").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.remove(b"a").unwrap(), None);
+
+        assert_eq!(
+            db.compare_and_swap(b"b", None::<&[u8]>, Some(b"1".to_vec()))
+                .unwrap(),
+            Ok(())
+        );
+        assert_eq!(
+            db.compare_and_swap(b"b", Some(b"2"), Some(b"3".to_vec()))
+                .unwrap(),
+            Err(CompareAndSwapError {
+                current: Some(b"1".to_vec()),
+                proposed: Some(b"3".to_vec()),
+            })
+        );
+        assert_eq!(
+            db.compare_and_swap(b"b", Some(b"1"), None::<IVec>).unwrap(),
+            Ok(())
+        );
+        assert_eq!(db.get(b"b").unwrap(), None);
+    }
+
+    #[test]
+    fn tree_ranges() {
+        let path = tempdir().unwrap();
+        let db = Db::open(&path).unwrap();
+        let tree = db.open_tree("t").unwrap();
+        let mut batch = Batch::default();
+        for i in 0..1000u32 {
+            batch.insert(i.to_be_bytes().to_vec(), i.to_le_bytes().to_vec());
+        }
+        tree.apply_batch(batch).unwrap();
+        db.insert(b"x", b"y".to_vec()).unwrap();
+
+        let entries = collect(tree.iter());
+        assert_eq!(entries.len(), 1000);
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(i, (k, v))| k == &(i as u32).to_be_bytes() && v == &(i as u32).to_le_bytes()));
+        let start = 10u32.to_be_bytes();
+        let end = 20u32.to_be_bytes();
+        assert_eq!(collect(tree.range(start..end)).len(), 10);
+        assert_eq!(collect(tree.range(start..=end)).len(), 11);
+        assert_eq!(collect(tree.scan_prefix([0, 0, 1])).len(), 256);
+        assert_eq!(collect(db.iter()), vec![(b"x".to_vec(), b"y".to_vec())]);
+
+        assert!(db.drop_tree("t").unwrap());
+        assert!(!db.drop_tree("t").unwrap());
+        assert!(collect(tree.iter()).is_empty());
+        assert_eq!(db.get(b"x").unwrap(), Some(b"y".to_vec()));
+    }
+
+    #[test]
+    fn key_prefix_end() {
+        assert_eq!(prefix_end(b"ab"), b"ac");
+        assert_eq!(prefix_end(&[1, 0xff]), [2]);
+    }
+}
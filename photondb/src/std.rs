@@ -11,6 +11,7 @@
 
 use std::{
     future::Future,
+    io::{Read, Write},
     ops::Deref,
     path::Path,
     pin::Pin,
@@ -19,7 +20,12 @@ use std::{
 
 use futures::task::noop_waker_ref;
 
-use crate::{env::Std, raw, PageIter, Result, TableOptions};
+use crate::{
+    env::Std, raw, BulkLoadOptions, ChecksumReport, ExportOptions, KeyspaceInfo,
+    MaintenanceCommand, MaintenanceDebt, PageIter, PreparedToken, ReadOptions, RepairReport,
+    ReplicationRecord, Result, SpaceReport, TableOptions, TreeShape, VerifyReport, WriteBatch,
+    WriteOptions,
+};
 
 /// A reference to a latch-free, log-structured table that stores sorted
 /// key-value entries.
@@ -38,6 +44,14 @@ impl Table {
         Ok(Self(table))
     }
 
+    /// Repairs a corrupted table in the path.
+    ///
+    /// This is a synchronous version of [`raw::Table::repair`] with the
+    /// [`Std`] environment.
+    pub fn repair<P: AsRef<Path>>(path: P, options: TableOptions) -> Result<RepairReport> {
+        poll(raw::Table::repair(Std, path, options))
+    }
+
     /// Closes the table if this is the only reference to it.
     ///
     /// This is a synchronous version of [`raw::Table::close`].
@@ -57,6 +71,58 @@ impl Table {
         poll(self.0.get(key, lsn))
     }
 
+    /// Gets the value corresponding to the key with the options.
+    ///
+    /// This is a synchronous version of [`raw::Table::get_with_options`].
+    pub fn get_with_options(&self, key: &[u8], opts: &ReadOptions) -> Result<Option<Vec<u8>>> {
+        poll(self.0.get_with_options(key, opts))
+    }
+
+    /// Gets the values corresponding to the keys.
+    ///
+    /// This is a synchronous version of [`raw::Table::multi_get`].
+    pub fn multi_get(&self, keys: &[&[u8]], lsn: u64) -> Result<Vec<Option<Vec<u8>>>> {
+        poll(self.0.multi_get(keys, lsn))
+    }
+
+    /// Reads the pages that contain the keys into the cache.
+    ///
+    /// This is a synchronous version of [`raw::Table::prefetch`].
+    pub fn prefetch(&self, keys: &[&[u8]]) -> Result<()> {
+        poll(self.0.prefetch(keys))
+    }
+
+    /// Reads the pages in the range into the cache.
+    ///
+    /// This is a synchronous version of [`raw::Table::prefetch_range`].
+    pub fn prefetch_range(&self, start: &[u8], end: Option<&[u8]>) -> Result<()> {
+        poll(self.0.prefetch_range(start, end))
+    }
+
+    /// Scans the entries visible to the LSN in `shards` key ranges of the
+    /// table on as many threads, and calls `f` on each entry.
+    ///
+    /// This is a multi-threaded version of [`raw::Table::parallel_scan`].
+    pub fn parallel_scan<F>(&self, shards: usize, lsn: u64, f: F) -> Result<()>
+    where
+        F: Fn(&[u8], &[u8]) + Sync,
+    {
+        let ranges = poll(self.0.scan_ranges(shards))?;
+        let f = &f;
+        std::thread::scope(|s| {
+            let handles: Vec<_> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    s.spawn(move || poll(self.0.scan_range(start, end.as_deref(), lsn, f)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("scan thread panicked"))
+                .collect()
+        })
+    }
+
     /// Puts a key-value entry to the table.
     ///
     /// This is a synchronous version of [`raw::Table::put`].
@@ -64,12 +130,281 @@ impl Table {
         poll(self.0.put(key, lsn, value))
     }
 
+    /// Puts a key-value entry to the table with the options.
+    ///
+    /// This is a synchronous version of [`raw::Table::put_with_options`].
+    pub fn put_with_options(
+        &self,
+        key: &[u8],
+        lsn: u64,
+        value: &[u8],
+        opts: &WriteOptions,
+    ) -> Result<()> {
+        poll(self.0.put_with_options(key, lsn, value, opts))
+    }
+
     /// Deletes the entry corresponding to the key from the table.
     ///
     /// This is a synchronous version of [`raw::Table::delete`].
     pub fn delete(&self, key: &[u8], lsn: u64) -> Result<()> {
         poll(self.0.delete(key, lsn))
     }
+
+    /// Deletes the entry corresponding to the key from the table with the
+    /// options.
+    ///
+    /// This is a synchronous version of [`raw::Table::delete_with_options`].
+    pub fn delete_with_options(&self, key: &[u8], lsn: u64, opts: &WriteOptions) -> Result<()> {
+        poll(self.0.delete_with_options(key, lsn, opts))
+    }
+
+    /// Loads entries in any order to the table with the LSN.
+    ///
+    /// This is a synchronous version of [`raw::Table::bulk_load`].
+    pub fn bulk_load<I>(&self, entries: I, lsn: u64, opts: &BulkLoadOptions) -> Result<u64>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        poll(self.0.bulk_load(entries, lsn, opts))
+    }
+
+    /// Gets the value of the latest version of the key at or before the
+    /// timestamp.
+    ///
+    /// This is a synchronous version of [`raw::Table::get_at`].
+    pub fn get_at(&self, key: &[u8], ts: u64, lsn: u64) -> Result<Option<Vec<u8>>> {
+        poll(self.0.get_at(key, ts, lsn))
+    }
+
+    /// Puts a version of the key with the timestamp to the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::put_at`].
+    pub fn put_at(&self, key: &[u8], ts: u64, lsn: u64, value: &[u8]) -> Result<()> {
+        poll(self.0.put_at(key, ts, lsn, value))
+    }
+
+    /// Deletes the key at the timestamp.
+    ///
+    /// This is a synchronous version of [`raw::Table::delete_at`].
+    pub fn delete_at(&self, key: &[u8], ts: u64, lsn: u64) -> Result<()> {
+        poll(self.0.delete_at(key, ts, lsn))
+    }
+
+    /// Deletes the entries in `[start, end)` from the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::delete_range`].
+    pub fn delete_range(&self, start: &[u8], end: &[u8], lsn: u64) -> Result<()> {
+        poll(self.0.delete_range(start, end, lsn))
+    }
+
+    /// Deletes all entries from the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::clear`].
+    pub fn clear(&self, lsn: u64) -> Result<()> {
+        poll(self.0.clear(lsn))
+    }
+
+    /// Puts a key-value entry to the table with an LSN assigned by the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::put_sequenced`].
+    pub fn put_sequenced(&self, key: &[u8], value: &[u8]) -> Result<u64> {
+        poll(self.0.put_sequenced(key, value))
+    }
+
+    /// Deletes the entry corresponding to the key from the table with an LSN
+    /// assigned by the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::delete_sequenced`].
+    pub fn delete_sequenced(&self, key: &[u8]) -> Result<u64> {
+        poll(self.0.delete_sequenced(key))
+    }
+
+    /// Puts a key-value entry to the table with a buffered write.
+    ///
+    /// This is a synchronous version of [`raw::Table::put_buffered`].
+    pub fn put_buffered(&self, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
+        poll(self.0.put_buffered(key, lsn, value))
+    }
+
+    /// Deletes the entry corresponding to the key from the table with a
+    /// buffered write.
+    ///
+    /// This is a synchronous version of [`raw::Table::delete_buffered`].
+    pub fn delete_buffered(&self, key: &[u8], lsn: u64) -> Result<()> {
+        poll(self.0.delete_buffered(key, lsn))
+    }
+
+    /// Writes the buffered writes of all threads.
+    ///
+    /// This is a synchronous version of [`raw::Table::flush_buffered_writes`].
+    pub fn flush_buffered_writes(&self) -> Result<()> {
+        poll(self.0.flush_buffered_writes())
+    }
+
+    /// Writes a batch of entries to the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::write_batch`].
+    pub fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        poll(self.0.write_batch(batch))
+    }
+
+    /// Returns a snapshot that reads the table with the LSN.
+    ///
+    /// This is a synchronous version of [`raw::Table::snapshot`].
+    pub fn snapshot(&self, lsn: u64) -> Snapshot {
+        Snapshot(self.0.snapshot(lsn))
+    }
+
+    /// Verifies the structure of the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::verify`].
+    pub fn verify(&self) -> Result<VerifyReport> {
+        poll(self.0.verify())
+    }
+
+    /// Verifies the checksums of all pages in the page files of the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::verify_checksums`].
+    pub fn verify_checksums(&self) -> Result<ChecksumReport> {
+        poll(self.0.verify_checksums())
+    }
+
+    /// Runs a maintenance command, and waits until it is done.
+    ///
+    /// This is a synchronous version of [`raw::Table::run_maintenance`].
+    pub fn run_maintenance(&self, cmd: MaintenanceCommand) -> Result<()> {
+        poll(self.0.run_maintenance(cmd))
+    }
+
+    /// Analyzes the space used by the page files of the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::analyze_space`].
+    pub fn analyze_space(&self) -> Result<SpaceReport> {
+        poll(self.0.analyze_space())
+    }
+
+    /// Returns the state of the table as a JSON string for debugging.
+    ///
+    /// This is a synchronous version of [`raw::Table::debug_state_json`].
+    pub fn debug_state_json(&self) -> Result<String> {
+        poll(self.0.debug_state_json())
+    }
+
+    /// Returns the amount of maintenance work that is not done yet.
+    ///
+    /// This is a synchronous version of [`raw::Table::maintenance_debt`].
+    pub fn maintenance_debt(&self) -> Result<MaintenanceDebt> {
+        poll(self.0.maintenance_debt())
+    }
+
+    /// Dumps the structure of the table to the writer.
+    ///
+    /// This is a synchronous version of [`raw::Table::dump`].
+    pub fn dump<W: Write>(&self, w: &mut W) -> Result<()> {
+        poll(self.0.dump(w))
+    }
+
+    /// Dumps a single page of the table to the writer.
+    ///
+    /// This is a synchronous version of [`raw::Table::dump_page`].
+    pub fn dump_page<W: Write>(&self, id: u64, w: &mut W) -> Result<()> {
+        poll(self.0.dump_page(id, w))
+    }
+
+    /// Exports the entries in `[start, end)` to the writer.
+    ///
+    /// This is a synchronous version of [`raw::Table::export`].
+    pub fn export<W: Write>(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        opts: &ExportOptions,
+        w: &mut W,
+    ) -> Result<u64> {
+        poll(self.0.export(start, end, opts, w))
+    }
+
+    /// Exports the keyspace of the entries under the prefix to a portable
+    /// container.
+    ///
+    /// This is a synchronous version of [`raw::Table::export_keyspace`].
+    pub fn export_keyspace<W: Write>(
+        &self,
+        prefix: &[u8],
+        schema: &[u8],
+        lsn: u64,
+        w: W,
+    ) -> Result<u64> {
+        poll(self.0.export_keyspace(prefix, schema, lsn, w))
+    }
+
+    /// Imports a keyspace from a container.
+    ///
+    /// This is a synchronous version of [`raw::Table::import_keyspace`].
+    pub fn import_keyspace<R: Read>(
+        &self,
+        r: R,
+        prefix: Option<&[u8]>,
+        lsn: u64,
+    ) -> Result<KeyspaceInfo> {
+        poll(self.0.import_keyspace(r, prefix, lsn))
+    }
+
+    /// Writes a batch as the entry at the index of a consensus log.
+    ///
+    /// This is a synchronous version of [`raw::Table::apply_with_index`].
+    pub fn apply_with_index(&self, batch: &WriteBatch, index: u64) -> Result<()> {
+        poll(self.0.apply_with_index(batch, index))
+    }
+
+    /// Returns the latest index applied with [`Self::apply_with_index`].
+    ///
+    /// This is a synchronous version of [`raw::Table::applied_index`].
+    pub fn applied_index(&self) -> Result<u64> {
+        poll(self.0.applied_index())
+    }
+
+    /// Prepares a batch for the first phase of a two-phase commit.
+    ///
+    /// This is a synchronous version of [`raw::Table::prepare`].
+    pub fn prepare(&self, batch: &WriteBatch) -> Result<PreparedToken> {
+        poll(self.0.prepare(batch))
+    }
+
+    /// Commits a prepared batch.
+    ///
+    /// This is a synchronous version of [`raw::Table::commit`].
+    pub fn commit(&self, token: PreparedToken) -> Result<()> {
+        poll(self.0.commit(token))
+    }
+
+    /// Rolls back a prepared batch.
+    ///
+    /// This is a synchronous version of [`raw::Table::rollback`].
+    pub fn rollback(&self, token: PreparedToken) -> Result<()> {
+        poll(self.0.rollback(token))
+    }
+
+    /// Returns the tokens of the batches that are prepared.
+    ///
+    /// This is a synchronous version of [`raw::Table::prepared`].
+    pub fn prepared(&self) -> Result<Vec<PreparedToken>> {
+        poll(self.0.prepared())
+    }
+
+    /// Applies a record from the replication stream of a primary table.
+    ///
+    /// This is a synchronous version of [`raw::Table::apply_replicated`].
+    pub fn apply_replicated(&self, record: &ReplicationRecord) -> Result<()> {
+        poll(self.0.apply_replicated(record))
+    }
+
+    /// Describes the shape of the table.
+    ///
+    /// This is a synchronous version of [`raw::Table::describe_tree`].
+    pub fn describe_tree(&self) -> Result<TreeShape> {
+        poll(self.0.describe_tree())
+    }
 }
 
 impl Deref for Table {
@@ -91,10 +426,50 @@ impl<'a> Guard<'a> {
         poll(self.0.get(key, lsn))
     }
 
+    /// Gets the values corresponding to the keys.
+    ///
+    /// This is a synchronous version of [`raw::Guard::multi_get`].
+    pub fn multi_get(&self, keys: &[&[u8]], lsn: u64) -> Result<Vec<Option<&[u8]>>> {
+        poll(self.0.multi_get(keys, lsn))
+    }
+
+    /// Puts a key-value entry to the table.
+    ///
+    /// This is a synchronous version of [`raw::Guard::put`].
+    pub fn put(&self, key: &[u8], lsn: u64, value: &[u8]) -> Result<()> {
+        poll(self.0.put(key, lsn, value))
+    }
+
+    /// Deletes the entry corresponding to the key from the table.
+    ///
+    /// This is a synchronous version of [`raw::Guard::delete`].
+    pub fn delete(&self, key: &[u8], lsn: u64) -> Result<()> {
+        poll(self.0.delete(key, lsn))
+    }
+
+    /// Writes a batch of entries to the table.
+    ///
+    /// This is a synchronous version of [`raw::Guard::write_batch`].
+    pub fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        poll(self.0.write_batch(batch))
+    }
+
     /// Returns an iterator over pages in the table.
     pub fn pages(&self) -> Pages<'_, 'a> {
         Pages(self.0.pages())
     }
+
+    /// Returns an iterator over pages in the table with the options.
+    ///
+    /// See [`raw::Guard::pages_with_options`] for more details.
+    pub fn pages_with_options(&self, opts: ReadOptions) -> Pages<'_, 'a> {
+        Pages(self.0.pages_with_options(opts))
+    }
+
+    /// Returns a cursor over entries visible to the LSN in the table.
+    pub fn cursor(&self, lsn: u64) -> Cursor<'_, 'a> {
+        Cursor(self.0.cursor(lsn))
+    }
 }
 
 impl<'a> Deref for Guard<'a> {
@@ -127,6 +502,82 @@ impl<'a, 't> Deref for Pages<'a, 't> {
     }
 }
 
+/// A consistent view of a table at an LSN.
+pub struct Snapshot(raw::Snapshot<Std>);
+
+impl Snapshot {
+    /// Gets the value corresponding to the key in the snapshot.
+    ///
+    /// This is a synchronous version of [`raw::Snapshot::get`].
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        poll(self.0.get(key))
+    }
+}
+
+impl Deref for Snapshot {
+    type Target = raw::Snapshot<Std>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A cursor over entries in a table.
+pub struct Cursor<'a, 't>(raw::Cursor<'a, 't, Std>);
+
+impl<'a, 't> Cursor<'a, 't> {
+    /// Positions the cursor at the first entry that is at or after `target`.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::seek`].
+    pub fn seek(&mut self, target: &[u8]) -> Result<()> {
+        poll(self.0.seek(target))
+    }
+
+    /// Moves the cursor to the next entry.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::next`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<()> {
+        poll(self.0.next())
+    }
+
+    /// Appends the current entry and the entries after it, up to `n` entries.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::next_n`].
+    pub fn next_n(&mut self, n: usize, entries: &mut Vec<(&'a [u8], &'a [u8])>) -> Result<usize> {
+        poll(self.0.next_n(n, entries))
+    }
+
+    /// Reloads the page of the current entry.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::refresh`].
+    pub fn refresh(&mut self) -> Result<()> {
+        poll(self.0.refresh())
+    }
+
+    /// Overwrites the value of the current entry.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::put`].
+    pub fn put(&mut self, lsn: u64, value: &[u8]) -> Result<()> {
+        poll(self.0.put(lsn, value))
+    }
+
+    /// Deletes the current entry.
+    ///
+    /// This is a synchronous version of [`raw::Cursor::delete`].
+    pub fn delete(&mut self, lsn: u64) -> Result<()> {
+        poll(self.0.delete(lsn))
+    }
+}
+
+impl<'a, 't> Deref for Cursor<'a, 't> {
+    type Target = raw::Cursor<'a, 't, Std>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 fn poll<F: Future>(mut future: F) -> F::Output {
     let cx = &mut Context::from_waker(noop_waker_ref());
     loop {
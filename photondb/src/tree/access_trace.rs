@@ -0,0 +1,124 @@
+use std::{
+    fs::File,
+    hash::Hasher,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+use rustc_hash::FxHasher;
+
+/// Records the accesses to a tree in a trace file.
+///
+/// Each access is written as a line of
+/// `<timestamp_us> <op> <key_hash> <page_ids> <hit|miss>`, where `page_ids`
+/// is a comma-separated list of the pages visited by the access, and `hit`
+/// means that no page was read from files. The key hash is 0 for accesses
+/// that are not on a single key.
+#[derive(Default)]
+pub(super) struct AccessTracer {
+    enabled: AtomicBool,
+    writer: Mutex<Option<BufWriter<File>>>,
+}
+
+impl AccessTracer {
+    /// Starts recording to the file in the path.
+    ///
+    /// The file is truncated if it exists. If recording has been started, the
+    /// previous file is closed.
+    pub(super) fn start(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = self.writer.lock();
+        if let Some(mut w) = writer.replace(BufWriter::new(file)) {
+            w.flush()?;
+        }
+        self.enabled.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Stops recording and flushes the trace file.
+    pub(super) fn stop(&self) -> io::Result<()> {
+        self.enabled.store(false, Ordering::Release);
+        match self.writer.lock().take() {
+            Some(mut w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub(super) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Records an access.
+    ///
+    /// Recording is stopped if the trace file fails to write.
+    pub(super) fn record(&self, op: &str, key: Option<&[u8]>, pages: &[u64], hit: bool) {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let key_hash = key.map_or(0, |key| {
+            let mut hasher = FxHasher::default();
+            hasher.write(key);
+            hasher.finish()
+        });
+        let mut line = format!("{ts} {op} {key_hash:016x} ");
+        for (i, id) in pages.iter().enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            line.push_str(&id.to_string());
+        }
+        line.push_str(if hit { " hit\n" } else { " miss\n" });
+
+        let mut writer = self.writer.lock();
+        if let Some(w) = writer.as_mut() {
+            if let Err(err) = w.write_all(line.as_bytes()) {
+                warn!("stop access tracing: {err}");
+                self.enabled.store(false, Ordering::Release);
+                writer.take();
+            }
+        }
+    }
+}
+
+/// The accesses of a transaction that are not recorded yet.
+#[derive(Default)]
+pub(super) struct PendingAccess {
+    /// The pages visited since the last record.
+    pub(super) pages: Vec<u64>,
+    /// The number of cache misses of the transaction at the last record.
+    pub(super) cache_misses: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_tracer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace");
+        let tracer = AccessTracer::default();
+        tracer.record("get", Some(b"a"), &[1], true);
+        assert!(!tracer.is_enabled());
+
+        tracer.start(&path).unwrap();
+        assert!(tracer.is_enabled());
+        tracer.record("get", Some(b"a"), &[1, 2], true);
+        tracer.record("scan", None, &[3], false);
+        tracer.stop().unwrap();
+        assert!(!tracer.is_enabled());
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Vec<&str>> = trace.lines().map(|l| l.split(' ').collect()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(&lines[0][1..], &["get", lines[0][2], "1,2", "hit"]);
+        assert_ne!(lines[0][2], "0000000000000000");
+        assert_eq!(&lines[1][1..], &["scan", "0000000000000000", "3", "miss"]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::page::{Key, Value};
+
+/// A batch of writes to a table.
+///
+/// Writes in a batch that go to the same leaf page are installed with one
+/// delta page, which keeps delta chains short under batched loads. The batch
+/// is not atomic, readers may observe part of it before it is done.
+#[derive(Clone, Debug, Default)]
+pub struct WriteBatch {
+    entries: Vec<(Vec<u8>, u64, Option<Vec<u8>>)>,
+}
+
+impl WriteBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a put of the key-value entry to the batch.
+    pub fn put(&mut self, key: &[u8], lsn: u64, value: &[u8]) {
+        self.entries.push((key.to_vec(), lsn, Some(value.to_vec())));
+    }
+
+    /// Adds a delete of the key to the batch.
+    pub fn delete(&mut self, key: &[u8], lsn: u64) {
+        self.entries.push((key.to_vec(), lsn, None));
+    }
+
+    /// Returns the number of writes in the batch.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the batch contains no writes.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all writes from the batch.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Encodes the writes in the batch to a buffer.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (key, lsn, value) in &self.entries {
+            buf.extend_from_slice(&lsn.to_le_bytes());
+            buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            buf.extend_from_slice(key);
+            match value {
+                Some(value) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(value);
+                }
+                None => buf.push(0),
+            }
+        }
+        buf
+    }
+
+    /// Decodes a batch from a buffer encoded by [`Self::encode`].
+    ///
+    /// Returns [`None`] if the buffer is malformed.
+    pub(crate) fn decode(mut buf: &[u8]) -> Option<Self> {
+        fn take<'a>(buf: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+            if buf.len() < len {
+                return None;
+            }
+            let (head, tail) = buf.split_at(len);
+            *buf = tail;
+            Some(head)
+        }
+        fn take_bytes(buf: &mut &[u8]) -> Option<Vec<u8>> {
+            let len = u32::from_le_bytes(take(buf, 4)?.try_into().unwrap());
+            take(buf, len as usize).map(|b| b.to_vec())
+        }
+
+        let mut batch = Self::new();
+        while !buf.is_empty() {
+            let lsn = u64::from_le_bytes(take(&mut buf, 8)?.try_into().unwrap());
+            let key = take_bytes(&mut buf)?;
+            let value = match take(&mut buf, 1)?[0] {
+                0 => None,
+                1 => Some(take_bytes(&mut buf)?),
+                _ => return None,
+            };
+            batch.entries.push((key, lsn, value));
+        }
+        Some(batch)
+    }
+
+    /// Returns the writes in the batch sorted by keys.
+    ///
+    /// If a key is written more than once with the same LSN, the last write
+    /// wins.
+    pub(crate) fn items(&self) -> Vec<(Key<'_>, Value<'_>)> {
+        let mut items: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, lsn, value)| {
+                let value = match value {
+                    Some(value) => Value::Put(value),
+                    None => Value::Delete,
+                };
+                (Key::new(key, *lsn), value)
+            })
+            .collect();
+        // The sort is stable, so the last write of the same key is the last one.
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut deduped: Vec<(Key<'_>, Value<'_>)> = Vec::with_capacity(items.len());
+        for item in items {
+            match deduped.last_mut() {
+                Some(last) if last.0 == item.0 => *last = item,
+                _ => deduped.push(item),
+            }
+        }
+        deduped
+    }
+}
+
+/// A token that identifies a batch prepared by [`crate::raw::Table::prepare`].
+///
+/// The id of the token is unique within a table, and can be persisted by a
+/// transaction coordinator to resolve the batch after a restart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PreparedToken(u64);
+
+impl PreparedToken {
+    /// Creates a token from the id of a prepared batch.
+    pub fn from_id(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Returns the id of the prepared batch.
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items() {
+        let mut batch = WriteBatch::new();
+        batch.put(&[2], 1, &[1]);
+        batch.put(&[1], 1, &[1]);
+        batch.delete(&[2], 2);
+        batch.put(&[1], 1, &[2]);
+        assert_eq!(batch.len(), 4);
+        assert_eq!(
+            batch.items(),
+            vec![
+                (Key::new(&[1], 1), Value::Put(&[2])),
+                (Key::new(&[2], 2), Value::Delete),
+                (Key::new(&[2], 1), Value::Put(&[1])),
+            ]
+        );
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let mut batch = WriteBatch::new();
+        batch.put(&[1], 1, &[1, 2]);
+        batch.delete(&[2], 2);
+        batch.put(&[], 3, &[]);
+        let buf = batch.encode();
+        let decoded = WriteBatch::decode(&buf).unwrap();
+        assert_eq!(decoded.entries, batch.entries);
+        assert!(WriteBatch::decode(&buf[..buf.len() - 1]).is_none());
+        assert!(WriteBatch::decode(&[]).unwrap().is_empty());
+    }
+}
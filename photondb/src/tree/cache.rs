@@ -0,0 +1,187 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use crossbeam_epoch::{Atomic, Owned};
+
+use crate::page::{Index, Range};
+
+/// A read-optimized copy of the consolidated root index.
+///
+/// Pages are immutable once they are installed, so the cache is valid as long
+/// as the root address remains the same. Any structure modification on the
+/// root installs a new address, which invalidates the cache.
+pub(super) struct RootCache {
+    addr: u64,
+    keys: Vec<Box<[u8]>>,
+    indexes: Vec<Index>,
+}
+
+impl RootCache {
+    pub(super) fn new<'a, I>(addr: u64, iter: I) -> Self
+    where
+        I: Iterator<Item = (&'a [u8], Index)>,
+    {
+        let (keys, indexes) = iter.map(|(k, v)| (Box::from(k), v)).unzip();
+        Self {
+            addr,
+            keys,
+            indexes,
+        }
+    }
+
+    /// Returns the address of the root this cache is built from.
+    pub(super) fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// Finds the child page that may contain the key.
+    ///
+    /// Returns the index and range of the child page.
+    pub(super) fn find_child(&self, key: &[u8]) -> Option<(Index, Range<'_>)> {
+        let i = match self.keys.binary_search_by(|k| k.as_ref().cmp(key)) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        let range = Range {
+            start: &self.keys[i],
+            end: self.keys.get(i + 1).map(|k| k.as_ref()),
+        };
+        Some((self.indexes[i], range))
+    }
+}
+
+/// A cached copy of the rightmost leaf page.
+///
+/// The rightmost leaf covers all keys from its range start, which doesn't
+/// change until the page is split. A split updates the page epoch, which
+/// invalidates the cache.
+pub(super) struct RightmostLeaf {
+    id: u64,
+    epoch: u64,
+    start: Box<[u8]>,
+}
+
+impl RightmostLeaf {
+    pub(super) fn new(id: u64, epoch: u64, start: &[u8]) -> Self {
+        Self {
+            id,
+            epoch,
+            start: Box::from(start),
+        }
+    }
+
+    /// Returns the id of the leaf page.
+    pub(super) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the epoch of the leaf page this cache is built from.
+    pub(super) fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the range of the leaf page.
+    pub(super) fn range(&self) -> Range<'_> {
+        Range {
+            start: &self.start,
+            end: None,
+        }
+    }
+
+    /// Returns true if the leaf page covers the key.
+    pub(super) fn covers(&self, key: &[u8]) -> bool {
+        key >= self.start.as_ref()
+    }
+}
+
+/// A cell that publishes the latest cache to readers.
+pub(super) struct CacheCell<T> {
+    inner: Atomic<Arc<T>>,
+}
+
+impl<T> Default for CacheCell<T> {
+    fn default() -> Self {
+        Self {
+            inner: Atomic::null(),
+        }
+    }
+}
+
+impl<T> CacheCell<T> {
+    /// Returns the current cache, if any.
+    pub(super) fn load(&self) -> Option<Arc<T>> {
+        let guard = crossbeam_epoch::pin();
+        let current = self.inner.load(Ordering::Acquire, &guard);
+        // Safety: the pointer is protected by the guard.
+        unsafe { current.as_ref() }.cloned()
+    }
+
+    /// Replaces the current cache.
+    pub(super) fn store(&self, cache: T) {
+        let guard = crossbeam_epoch::pin();
+        let former = self
+            .inner
+            .swap(Owned::new(Arc::new(cache)), Ordering::AcqRel, &guard);
+        if !former.is_null() {
+            // Safety: the former cache is unreachable from the cell now.
+            unsafe { guard.defer_destroy(former) };
+        }
+    }
+}
+
+impl<T> Drop for CacheCell<T> {
+    fn drop(&mut self) {
+        // Safety: no one else can access the cell now.
+        unsafe {
+            let guard = crossbeam_epoch::unprotected();
+            let current = self.inner.load(Ordering::Relaxed, guard);
+            if !current.is_null() {
+                drop(current.into_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_child() {
+        let data = [
+            ([].as_slice(), Index::new(1, 0)),
+            ([3].as_slice(), Index::new(3, 1)),
+            ([5].as_slice(), Index::new(5, 2)),
+        ];
+        let cache = RootCache::new(7, data.into_iter());
+        assert_eq!(cache.addr(), 7);
+        let (index, range) = cache.find_child(&[0]).unwrap();
+        assert_eq!(index, Index::new(1, 0));
+        assert_eq!(range.start, [].as_slice());
+        assert_eq!(range.end, Some([3].as_slice()));
+        let (index, range) = cache.find_child(&[3]).unwrap();
+        assert_eq!(index, Index::new(3, 1));
+        assert_eq!(range.end, Some([5].as_slice()));
+        let (index, range) = cache.find_child(&[9]).unwrap();
+        assert_eq!(index, Index::new(5, 2));
+        assert_eq!(range.end, None);
+
+        let cell = CacheCell::default();
+        assert!(cell.load().is_none());
+        cell.store(cache);
+        assert_eq!(cell.load().unwrap().addr(), 7);
+    }
+
+    #[test]
+    fn rightmost_leaf() {
+        let cache = RightmostLeaf::new(3, 1, &[5]);
+        assert_eq!(cache.id(), 3);
+        assert_eq!(cache.epoch(), 1);
+        assert!(!cache.covers(&[4]));
+        assert!(cache.covers(&[5]));
+        assert!(cache.covers(&[9, 0]));
+        let range = cache.range();
+        assert_eq!(range.start, [5].as_slice());
+        assert_eq!(range.end, None);
+    }
+}
@@ -0,0 +1,141 @@
+use super::*;
+
+/// A cursor over the entries in a tree.
+///
+/// The cursor remembers the leaf page of the current entry, so that mutations
+/// at the current position can go to the page without another lookup.
+pub(crate) struct TreeCursor<'a, 't: 'a, E: Env> {
+    txn: &'a TreeTxn<'t, E>,
+    read_lsn: u64,
+    view: Option<PageView<'a>>,
+    iter: Option<PageIter<'a>>,
+    current: Option<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a, 't: 'a, E: Env> TreeCursor<'a, 't, E> {
+    pub(crate) fn new(txn: &'a TreeTxn<'t, E>, read_lsn: u64) -> Self {
+        Self {
+            txn,
+            read_lsn,
+            view: None,
+            iter: None,
+            current: None,
+        }
+    }
+
+    /// Returns the entry at the current position.
+    pub(crate) fn current(&self) -> Option<(&'a [u8], &'a [u8])> {
+        self.current
+    }
+
+    /// Positions the cursor at the first entry that is at or after `target`.
+    pub(crate) async fn seek(&mut self, target: &[u8]) -> Result<()> {
+        self.load_leaf(target).await?;
+        self.advance().await
+    }
+
+    /// Moves the cursor to the next entry.
+    pub(crate) async fn next(&mut self) -> Result<()> {
+        self.advance().await
+    }
+
+    /// Appends the current entry and the entries after it to `entries`, up
+    /// to `n` entries, and moves the cursor to the entry after the last
+    /// appended one.
+    ///
+    /// Returns the number of entries appended.
+    pub(crate) async fn next_n(
+        &mut self,
+        n: usize,
+        entries: &mut Vec<(&'a [u8], &'a [u8])>,
+    ) -> Result<usize> {
+        let len = entries.len();
+        while entries.len() - len < n {
+            let Some(entry) = self.current else {
+                break;
+            };
+            entries.push(entry);
+            // Takes the rest of the current page without a lookup per entry.
+            if let Some(iter) = self.iter.as_mut() {
+                iter.next_n(n - (entries.len() - len), entries);
+            }
+            self.advance().await?;
+        }
+        Ok(entries.len() - len)
+    }
+
+    /// Reloads the page of the current entry, so that the cursor observes
+    /// the writes done since the page was loaded.
+    ///
+    /// The cursor is positioned at the first entry that is at or after the
+    /// current key. It does nothing if the cursor is not positioned at an
+    /// entry.
+    pub(crate) async fn refresh(&mut self) -> Result<()> {
+        let Some((key, _)) = self.current else {
+            return Ok(());
+        };
+        let key = key.to_vec();
+        self.seek(&key).await
+    }
+
+    /// Writes the key of the current entry with the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is not positioned at an entry.
+    pub(crate) async fn write(&mut self, lsn: u64, value: Value<'_>) -> Result<()> {
+        let (raw, _) = self.current.expect("cursor must be positioned at an entry");
+        let view = self.view.clone().expect("cursor must have a leaf page");
+        self.txn.write_at(view, Key::new(raw, lsn), value).await
+    }
+
+    async fn load_leaf(&mut self, target: &[u8]) -> Result<()> {
+        let (view, _) = self.txn.find_leaf(target).await?;
+        let mut iter = PageIter::new(self.txn.iter_page(&view).await?, self.read_lsn);
+        iter.seek(target);
+        self.view = Some(view);
+        self.iter = Some(iter);
+        Ok(())
+    }
+
+    async fn advance(&mut self) -> Result<()> {
+        loop {
+            if let Some(entry) = self.iter.as_mut().and_then(|iter| iter.next()) {
+                self.current = Some(entry);
+                return Ok(());
+            }
+            // Moves to the next leaf page if the current one is exhausted.
+            let end = self.view.as_ref().and_then(|v| v.range).and_then(|r| r.end);
+            match end {
+                Some(end) => self.load_leaf(end).await?,
+                None => {
+                    self.view = None;
+                    self.iter = None;
+                    self.current = None;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Writes the key-value pair to the given leaf page.
+    ///
+    /// This falls back to a normal write if the page range has changed.
+    async fn write_at(&self, view: PageView<'_>, key: Key<'_>, value: Value<'_>) -> Result<()> {
+        match self.write_page(view, &[(key, value)]).await {
+            Ok(_) => {
+                let bytes = key.len() + value.len();
+                self.tree.stats.success.write.inc();
+                self.tree.stats.success.write_bytes.add(bytes as u64);
+                Ok(())
+            }
+            Err(Error::Again) => {
+                self.tree.stats.conflict.write.inc();
+                self.write(key, value).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashSet, io::Write};
+
+use super::*;
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Dumps the structure of the tree to the writer.
+    ///
+    /// Pages are dumped in depth-first order from the root. Each line is a
+    /// record of space-separated `name=value` fields, and the records of a
+    /// page are indented under it:
+    ///
+    /// ```text
+    /// page id=1 addr=4294967296 tier=Inner epoch=0 chain_len=1 start= end=
+    ///   delta addr=4294967296 kind=Data size=64 len=2
+    ///     index key= id=2 epoch=0
+    ///     index key=0a id=3 epoch=0
+    /// page id=2 addr=4294967360 tier=Leaf epoch=1 chain_len=2 start= end=0a
+    ///   delta addr=4294967424 kind=Split size=32 key=0a id=3 epoch=0
+    ///   delta addr=4294967360 kind=Data size=64 len=1
+    ///     entry key=01 lsn=1 kind=put value=01
+    /// ```
+    ///
+    /// Keys and values are hex-encoded, and an empty `end` means the page
+    /// covers all keys after `start`. The right page of a pending split is
+    /// dumped after the left page, since it is not reachable from the parent
+    /// yet.
+    pub(crate) async fn dump<W: Write>(&self, w: &mut W) -> Result<()> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(ROOT_ID, Vec::new(), None)];
+        while let Some((id, start, end)) = stack.pop() {
+            let addr = self.guard.page_addr(id);
+            if addr == 0 || !visited.insert(id) {
+                continue;
+            }
+            let head = self.guard.read_page_info(addr)?;
+            writeln!(
+                w,
+                "page id={id} addr={addr} tier={:?} epoch={} chain_len={} start={} end={}",
+                head.tier(),
+                head.epoch(),
+                head.chain_len(),
+                Hex(&start),
+                Hex(end.as_deref().unwrap_or_default()),
+            )?;
+
+            let mut records = Vec::new();
+            let mut split = None;
+            self.walk_page(
+                addr,
+                |addr, page, _| {
+                    records.push(dump_delta(addr, page));
+                    if split.is_none() && page.kind() == PageKind::Split {
+                        let (key, index) = split_delta_from_page(page);
+                        split = Some((index.id, key.to_vec()));
+                    }
+                    false
+                },
+                CacheOption::default(),
+            )
+            .await?;
+            for record in records {
+                w.write_all(record.as_bytes())?;
+            }
+
+            // Pushes the right page of a pending split first, so that it is
+            // dumped after the children of this page.
+            let end = match split {
+                Some((right_id, split_key)) => {
+                    stack.push((right_id, split_key.clone(), end));
+                    Some(split_key)
+                }
+                None => end,
+            };
+            if head.tier().is_inner() {
+                let view = PageView {
+                    id,
+                    addr,
+                    page: head,
+                    range: None,
+                };
+                let iter = self.iter_page(&view).await?;
+                let children: Vec<_> = MergingInnerPageIter::new(iter).collect();
+                for (i, (start, index)) in children.iter().enumerate().rev() {
+                    let child_end = match children.get(i + 1) {
+                        Some((next, _)) => Some(next.to_vec()),
+                        None => end.clone(),
+                    };
+                    stack.push((index.id, start.to_vec(), child_end));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Dumps a single page to the writer.
+    ///
+    /// The page is dumped like in [`Self::dump`], and each delta page is
+    /// followed by its header and its raw bytes in hex:
+    ///
+    /// ```text
+    /// page id=2 addr=4294967360 tier=Leaf epoch=1 chain_len=1
+    ///   delta addr=4294967360 kind=Data size=48 len=1
+    ///     entry key=01 lsn=1 kind=put value=01
+    ///     header epoch=1 tier=Leaf kind=Data chain_len=1 chain_next=0
+    ///     raw offset=0 data=0100000000000001...
+    /// ```
+    ///
+    /// A delta page that can not be read is dumped with the error instead,
+    /// and the rest of the chain is still dumped.
+    ///
+    /// Returns false if the page is not found.
+    pub(crate) async fn dump_page<W: Write>(&self, id: u64, w: &mut W) -> Result<bool> {
+        let Some(addr) = self.guard.live_page_addr(id) else {
+            return Ok(false);
+        };
+        let head = self.guard.read_page_info(addr)?;
+        writeln!(
+            w,
+            "page id={id} addr={addr} tier={:?} epoch={} chain_len={}",
+            head.tier(),
+            head.epoch(),
+            head.chain_len(),
+        )?;
+        let mut next = addr;
+        while next != 0 {
+            let addr = next;
+            match self.guard.read_page(addr, CacheOption::default()).await {
+                Ok((page, _)) => {
+                    w.write_all(dump_delta(addr, page).as_bytes())?;
+                    writeln!(
+                        w,
+                        "    header epoch={} tier={:?} kind={:?} chain_len={} chain_next={}",
+                        page.epoch(),
+                        page.tier(),
+                        page.kind(),
+                        page.chain_len(),
+                        page.chain_next(),
+                    )?;
+                    for (i, chunk) in page.data().chunks(32).enumerate() {
+                        writeln!(w, "    raw offset={} data={}", i * 32, Hex(chunk))?;
+                    }
+                    next = page.chain_next();
+                }
+                Err(err) => {
+                    // The address of the next page is kept in the metadata
+                    // of the file, so the chain can still be followed.
+                    writeln!(w, "  delta addr={addr} error={err}")?;
+                    next = self.guard.read_page_info(addr)?.chain_next();
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Formats the records of a page in a delta chain.
+fn dump_delta(addr: u64, page: PageRef<'_>) -> String {
+    let mut s = format!(
+        "  delta addr={addr} kind={:?} size={}",
+        page.kind(),
+        page.size()
+    );
+    match (page.kind(), page.tier()) {
+        (PageKind::Split, _) => {
+            let (key, index) = split_delta_from_page(page);
+            s += &format!(" key={} id={} epoch={}\n", Hex(key), index.id, index.epoch);
+        }
+        (PageKind::RangeDelete, _) => {
+            let tombstone = RangeTombstone::from_page(page);
+            s += &format!(
+                " start={} end={} lsn={}\n",
+                Hex(tombstone.start),
+                Hex(tombstone.end.unwrap_or_default()),
+                tombstone.lsn
+            );
+        }
+        (PageKind::Data, PageTier::Leaf) => {
+            let page = ValuePageRef::from(page);
+            s += &format!(" len={}\n", page.len());
+            for (k, v) in SortedPageIter::new(page) {
+                s += &format!("    entry key={} lsn={}", Hex(k.raw), k.lsn);
+                match v {
+                    Value::Put(v) => s += &format!(" kind=put value={}\n", Hex(v)),
+                    Value::Delete => s += " kind=delete\n",
+                }
+            }
+        }
+        (PageKind::Data, PageTier::Inner) => {
+            let page = IndexPageRef::from(page);
+            s += &format!(" len={}\n", page.len());
+            for (k, index) in SortedPageIter::new(page) {
+                s += &format!(
+                    "    index key={} id={} epoch={}\n",
+                    Hex(k),
+                    index.id,
+                    index.epoch
+                );
+            }
+        }
+    }
+    s
+}
+
+/// Formats bytes in lowercase hex.
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+/// The number of slots to track hot pages.
+const NUM_SLOTS: usize = 1024;
+
+/// Tracks sampled accesses to pages to detect hot pages.
+///
+/// Pages are hashed into a fixed number of slots. A slot is taken over by
+/// another page on collision, so only pages accessed frequently enough can
+/// accumulate hits.
+pub(super) struct HotPages {
+    slots: Box<[Slot]>,
+    sample_interval: usize,
+    threshold: u32,
+}
+
+#[derive(Default)]
+struct Slot {
+    id: AtomicU64,
+    hits: AtomicU32,
+}
+
+impl HotPages {
+    pub(super) fn new(sample_interval: usize, threshold: usize) -> Self {
+        let slots = (0..NUM_SLOTS).map(|_| Slot::default()).collect();
+        Self {
+            slots,
+            sample_interval,
+            threshold: threshold.clamp(1, u32::MAX as usize) as u32,
+        }
+    }
+
+    /// Records an access to the page.
+    ///
+    /// Returns true if the page becomes hot. The hits of the page are reset
+    /// then, so that it is reported again only if it keeps being hot.
+    pub(super) fn access(&self, id: u64) -> bool {
+        if !self.sample() {
+            return false;
+        }
+        let slot = &self.slots[id as usize % self.slots.len()];
+        if slot.id.swap(id, Ordering::Relaxed) != id {
+            slot.hits.store(1, Ordering::Relaxed);
+            return false;
+        }
+        let hits = slot.hits.fetch_add(1, Ordering::Relaxed) + 1;
+        if hits >= self.threshold {
+            slot.hits.store(0, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Returns true if the current access should be sampled.
+    fn sample(&self) -> bool {
+        thread_local! {
+            static ACCESSES: Cell<usize> = Cell::new(0);
+        }
+        if self.sample_interval == 0 {
+            return false;
+        }
+        ACCESSES.with(|accesses| {
+            let n = accesses.get().wrapping_add(1);
+            accesses.set(n);
+            n % self.sample_interval == 0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access() {
+        let hot = HotPages::new(1, 3);
+        assert!(!hot.access(1));
+        assert!(!hot.access(1));
+        assert!(hot.access(1));
+        assert!(!hot.access(1));
+
+        // Collisions take over the slot.
+        assert!(!hot.access(1 + NUM_SLOTS as u64));
+        assert!(!hot.access(1));
+        assert!(!hot.access(1));
+        assert!(hot.access(1));
+
+        let disabled = HotPages::new(0, 1);
+        assert!(!disabled.access(1));
+    }
+}
@@ -1,35 +1,113 @@
 use std::{
-    fmt,
-    sync::atomic::{AtomicU64, Ordering},
+    fmt, mem,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
 
-use log::trace;
+use log::{trace, warn};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
 
-use crate::{env::Env, page::*, page_store::*};
+use crate::{
+    env::Env,
+    listener::{ConsolidationEvent, SplitEvent},
+    page::*,
+    page_store::*,
+    perf_context,
+    util::{
+        backoff::Backoff,
+        trace::{event, instrument},
+    },
+};
 
 mod page;
 pub use page::PageIter;
 use page::*;
 
 mod stats;
-use stats::AtomicStats;
-pub use stats::TreeStats;
+pub(crate) use stats::HISTOGRAM_BUCKETS;
+pub use stats::{Histogram, LatencyStats, TreeStats};
+use stats::{AtomicOpStats, AtomicStats, OpKind};
 
 mod options;
+use options::{DynamicOptions, OpLimits};
 pub use options::{Options, ReadOptions, WriteOptions};
 
+mod cache;
+use cache::{CacheCell, RightmostLeaf, RootCache};
+
+mod verify;
+pub use verify::{VerifyReport, Violation, ViolationKind};
+
+mod repair;
+pub use repair::{LostRange, RepairReport};
+
+mod shape;
+pub use shape::{LevelShape, TreeShape, FILL_FACTOR_BUCKETS};
+
+mod partition;
+
+mod cursor;
+pub(crate) use cursor::TreeCursor;
+
+mod batch;
+pub use batch::{PreparedToken, WriteBatch};
+
+mod hot;
+use hot::HotPages;
+
+mod tuner;
+use tuner::ConsolidationTuner;
+
+mod access_trace;
+use access_trace::{AccessTracer, PendingAccess};
+
+mod snapshot;
+pub use snapshot::LiveSnapshots;
+use snapshot::SnapshotList;
+
+mod dump;
+
+mod timestamp;
+pub(crate) use timestamp::encode_timestamped_value;
+pub use timestamp::{decode_timestamped_key, decode_timestamped_value, encode_timestamped_key};
+
 pub(crate) struct Tree {
     options: Options,
+    dynamic_options: DynamicOptions,
     stats: AtomicStats,
     safe_lsn: AtomicU64,
+    latest_lsn: AtomicU64,
+    root_cache: CacheCell<RootCache>,
+    rightmost_leaf: CacheCell<RightmostLeaf>,
+    hot_pages: HotPages,
+    tuner: ConsolidationTuner,
+    snapshots: SnapshotList,
+    access_tracer: AccessTracer,
 }
 
 impl Tree {
     pub(crate) fn new(options: Options) -> Self {
+        let hot_pages = HotPages::new(options.hot_page_sample_interval, options.hot_page_threshold);
+        let dynamic_options = DynamicOptions::new(&options);
+        let tuner = ConsolidationTuner::new(&options);
+        tuner.init(&dynamic_options);
         Self {
             options,
+            dynamic_options,
             stats: AtomicStats::default(),
             safe_lsn: AtomicU64::new(0),
+            latest_lsn: AtomicU64::new(0),
+            root_cache: CacheCell::default(),
+            rightmost_leaf: CacheCell::default(),
+            hot_pages,
+            tuner,
+            snapshots: SnapshotList::default(),
+            access_tracer: AccessTracer::default(),
         }
     }
 
@@ -41,6 +119,21 @@ impl Tree {
         self.stats.snapshot()
     }
 
+    /// Starts recording the accesses to the tree to the file in the path.
+    pub(crate) fn start_access_trace(&self, path: &Path) -> std::io::Result<()> {
+        self.access_tracer.start(path)
+    }
+
+    /// Stops recording the accesses to the tree.
+    pub(crate) fn stop_access_trace(&self) -> std::io::Result<()> {
+        self.access_tracer.stop()
+    }
+
+    /// Returns the options that can be changed while the tree is open.
+    pub(crate) fn dynamic_options(&self) -> &DynamicOptions {
+        &self.dynamic_options
+    }
+
     pub(crate) fn safe_lsn(&self) -> u64 {
         self.safe_lsn.load(Ordering::Acquire)
     }
@@ -61,6 +154,55 @@ impl Tree {
             }
         }
     }
+
+    /// Returns the latest LSN assigned or written to the tree.
+    pub(crate) fn latest_lsn(&self) -> u64 {
+        self.latest_lsn.load(Ordering::Acquire)
+    }
+
+    /// Assigns a new LSN that is larger than the latest one.
+    pub(crate) fn next_lsn(&self) -> u64 {
+        self.latest_lsn.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Advances the latest LSN if the given one is larger.
+    pub(crate) fn observe_lsn(&self, lsn: u64) {
+        self.latest_lsn.fetch_max(lsn, Ordering::AcqRel);
+    }
+
+    /// Registers a snapshot that reads with the LSN.
+    ///
+    /// Entries visible to the snapshot are retained until it is released.
+    /// Returns the time that the snapshot is taken.
+    pub(crate) fn register_snapshot(&self, lsn: u64) -> Instant {
+        self.snapshots.register(lsn)
+    }
+
+    /// Releases a snapshot registered with the LSN at the time.
+    pub(crate) fn release_snapshot(&self, lsn: u64, taken_at: Instant) {
+        self.snapshots.release(lsn, taken_at);
+    }
+
+    /// Returns a summary of the active snapshots.
+    pub(crate) fn live_snapshots(&self) -> LiveSnapshots {
+        self.snapshots.live()
+    }
+
+    /// Returns the number of active snapshots.
+    pub(crate) fn num_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns the LSN below which invisible entries can be dropped.
+    ///
+    /// This is the safe LSN, bounded by the oldest active snapshot.
+    pub(crate) fn gc_lsn(&self) -> u64 {
+        let safe_lsn = self.safe_lsn();
+        match self.snapshots.oldest() {
+            Some(lsn) => lsn.min(safe_lsn),
+            None => safe_lsn,
+        }
+    }
 }
 
 impl fmt::Debug for Tree {
@@ -68,6 +210,8 @@ impl fmt::Debug for Tree {
         f.debug_struct("Tree")
             .field("options", &self.options)
             .field("safe_lsn", &self.safe_lsn())
+            .field("latest_lsn", &self.latest_lsn())
+            .field("num_snapshots", &self.num_snapshots())
             .finish()
     }
 }
@@ -75,17 +219,64 @@ impl fmt::Debug for Tree {
 pub(crate) struct TreeTxn<'a, E: Env> {
     tree: &'a Tree,
     guard: Guard<E>,
+    // The root cache used by this transaction, loaded on the first descent.
+    root_cache: OnceCell<Option<Arc<RootCache>>>,
+    // The rightmost leaf cache used by this transaction, loaded on the first write.
+    rightmost_leaf: OnceCell<Option<Arc<RightmostLeaf>>>,
+    // The work done by this transaction, used to explain slow operations.
+    op_stats: AtomicOpStats,
+    // The time since which the pin of the page store is not attributed to an
+    // operation yet.
+    pinned_since: Mutex<Instant>,
+    // The accesses to record, if access tracing is enabled.
+    pending_access: Option<Mutex<PendingAccess>>,
+    // The limits of the operation of this transaction.
+    limits: OpLimits,
 }
 
 impl<'a, E: Env> TreeTxn<'a, E> {
     fn new(tree: &'a Tree, guard: Guard<E>) -> Self {
-        Self { tree, guard }
+        Self {
+            tree,
+            guard,
+            root_cache: OnceCell::new(),
+            rightmost_leaf: OnceCell::new(),
+            op_stats: AtomicOpStats::default(),
+            pinned_since: Mutex::new(Instant::now()),
+            pending_access: tree
+                .access_tracer
+                .is_enabled()
+                .then(|| Mutex::new(PendingAccess::default())),
+            limits: OpLimits::default(),
+        }
+    }
+
+    /// Limits the work of the operation of this transaction.
+    pub(crate) fn with_limits(mut self, limits: OpLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns [`Error::Timeout`] if the operation exceeds its limits.
+    fn check_limits(&self) -> Result<()> {
+        let limits = &self.limits;
+        let retries = self.op_stats.retries.get();
+        if limits.max_retries.map_or(false, |n| retries > n as u64)
+            || limits.deadline.map_or(false, |d| Instant::now() >= d)
+        {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the tree has a root.
+    pub(crate) fn is_initialized(&self) -> bool {
+        self.guard.page_addr(ROOT_ID) != 0
     }
 
     /// Initializes the tree if it is not initialized yet.
     pub(crate) async fn init(&self) -> Result<()> {
-        let addr = self.guard.page_addr(ROOT_ID);
-        if addr != 0 {
+        if self.is_initialized() {
             return Ok(());
         }
 
@@ -95,7 +286,7 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         let mut txn = self.guard.begin().await;
         let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
         builder.build(&mut new_page);
-        let root_id = txn.insert_page(new_addr);
+        let root_id = txn.insert_page(new_addr)?;
         assert_eq!(root_id, ROOT_ID);
         txn.commit();
 
@@ -104,8 +295,10 @@ impl<'a, E: Env> TreeTxn<'a, E> {
 
     /// Gets the value corresponding to the key.
     pub(crate) async fn get(&self, key: Key<'_>) -> Result<Option<&[u8]>> {
+        let start = self.start_op();
         let (view, _) = self.find_leaf(key.raw).await?;
         let value = self.find_value(&key, &view).await?;
+        self.access_page(view).await;
 
         let key_size = key.len() as u64;
         let value_size = value.map(|v| v.len()).unwrap_or_default() as u64;
@@ -114,22 +307,82 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             .success
             .read_bytes
             .add(key_size + value_size);
+        self.observe_latency("get", OpKind::Get, start);
+        self.trace_access("get", Some(key.raw));
 
         Ok(value)
     }
 
+    /// Gets the values corresponding to the keys.
+    ///
+    /// The keys are sorted first, so that keys in the same leaf page share one
+    /// descent of the tree. The delta chains of all the leaf pages are then
+    /// read concurrently before looking up the keys.
+    pub(crate) async fn multi_get(&self, keys: &[Key<'_>]) -> Result<Vec<Option<&[u8]>>> {
+        let start = self.start_op();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+        // Finds the leaf page of each group of keys.
+        let mut groups = Vec::new();
+        let mut rest = order.as_slice();
+        while let Some(&first) = rest.first() {
+            let (view, _) = self.find_leaf(keys[first].raw).await?;
+            let num_keys = match view.range.and_then(|r| r.end) {
+                Some(end) => rest.partition_point(|&i| keys[i].raw < end),
+                None => rest.len(),
+            };
+            let (group, next) = rest.split_at(num_keys);
+            groups.push((view, group));
+            rest = next;
+        }
+
+        let prefetches = groups.iter().map(|(view, _)| self.prefetch_page(view));
+        for result in futures::future::join_all(prefetches).await {
+            result?;
+        }
+
+        let mut values = vec![None; keys.len()];
+        let mut bytes = 0;
+        for (view, group) in groups {
+            for &i in group {
+                let value = self.find_value(&keys[i], &view).await?;
+                bytes += keys[i].len() + value.map(|v| v.len()).unwrap_or_default();
+                values[i] = value;
+            }
+            self.access_page(view).await;
+        }
+        self.tree.stats.success.read_bytes.add(bytes as u64);
+        self.observe_latency("multi_get", OpKind::Get, start);
+        self.trace_access("multi_get", None);
+        Ok(values)
+    }
+
     /// Writes the key-value pair to the tree.
     pub(crate) async fn write(&self, key: Key<'_>, value: Value<'_>) -> Result<()> {
+        let _pending = self.guard.watermark().begin_write(key.lsn);
+        let start = self.start_op();
         let bytes = key.len() + value.len();
+        let mut backoff = Backoff::new();
         loop {
             match self.try_write(key, value).await {
                 Ok(_) => {
                     self.tree.stats.success.write.inc();
                     self.tree.stats.success.write_bytes.add(bytes as u64);
+                    self.tree.stats.write_retries.observe(backoff.retries());
+                    let (op, kind) = match value {
+                        Value::Put(_) => ("put", OpKind::Put),
+                        Value::Delete => ("delete", OpKind::Delete),
+                    };
+                    self.observe_latency(op, kind, start);
+                    self.trace_access(op, Some(key.raw));
                     return Ok(());
                 }
                 Err(Error::Again) => {
                     self.tree.stats.conflict.write.inc();
+                    self.op_stats.retries.inc();
+                    self.check_limits()?;
+                    backoff.snooze().await;
                     continue;
                 }
                 Err(e) => return Err(e),
@@ -138,20 +391,214 @@ impl<'a, E: Env> TreeTxn<'a, E> {
     }
 
     async fn try_write(&self, key: Key<'_>, value: Value<'_>) -> Result<()> {
-        let (mut view, _) = self.find_leaf(key.raw).await?;
+        let view = self.find_write_leaf(key.raw).await?;
+        self.write_page(view, &[(key, value)]).await
+    }
+
+    /// Writes a batch of sorted key-value pairs to the tree.
+    ///
+    /// Pairs that go to the same leaf page are written with one delta page,
+    /// which is capped at the page size so that it fits in a write buffer.
+    ///
+    /// The batch is not atomic: if it fails, the groups written before the
+    /// failure are kept.
+    pub(crate) async fn write_batch(&self, items: &[(Key<'_>, Value<'_>)]) -> Result<()> {
+        debug_assert!(items.windows(2).all(|w| w[0].0 < w[1].0));
+        let min_lsn = items.iter().map(|(k, _)| k.lsn).min();
+        let _pending = min_lsn.map(|lsn| self.guard.watermark().begin_write(lsn));
+        let mut rest = items;
+        let mut backoff = Backoff::new();
+        while let Some((key, _)) = rest.first() {
+            let view = self.find_write_leaf(key.raw).await?;
+            // Groups the following pairs that are in the range of the page.
+            let num_items = match view.range.and_then(|r| r.end) {
+                Some(end) => rest.partition_point(|(k, _)| k.raw < end),
+                None => rest.len(),
+            };
+            let num_items = self.limit_batch_group(&rest[..num_items]);
+            let (group, next) = rest.split_at(num_items);
+            match self.write_page(view, group).await {
+                Ok(_) => {
+                    let bytes: usize = group.iter().map(|(k, v)| k.len() + v.len()).sum();
+                    self.tree.stats.success.write.add(group.len() as u64);
+                    self.tree.stats.success.write_bytes.add(bytes as u64);
+                    self.tree.stats.write_retries.observe(backoff.retries());
+                    backoff = Backoff::new();
+                    rest = next;
+                }
+                Err(Error::Again) => {
+                    self.tree.stats.conflict.write.inc();
+                    self.op_stats.retries.inc();
+                    self.check_limits()?;
+                    backoff.snooze().await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of leading items that fit in one delta page.
+    ///
+    /// At least one item is returned, so an item larger than the page size is
+    /// written alone as [`TreeTxn::write`] does.
+    fn limit_batch_group(&self, items: &[(Key<'_>, Value<'_>)]) -> usize {
+        let max_size = self.tree.options.page_size;
+        let mut size = 0;
+        let num_items = items
+            .iter()
+            .take_while(|(k, v)| {
+                size += k.len() + v.len();
+                size <= max_size
+            })
+            .count();
+        num_items.max(1)
+    }
+
+    /// Deletes the entries in `[start, end)` with range tombstones.
+    ///
+    /// A range tombstone delta is written to each leaf page that overlaps the
+    /// range, so the cost is proportional to the number of pages instead of
+    /// the number of entries in the range. If `end` is `None`, the range is
+    /// unbounded.
+    pub(crate) async fn delete_range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        lsn: u64,
+    ) -> Result<()> {
+        let _pending = self.guard.watermark().begin_write(lsn);
+        let mut next = start;
+        let mut backoff = Backoff::new();
+        while end.map_or(true, |end| next < end) {
+            let (view, _) = self.find_leaf(next).await?;
+            let page_end = view.range.and_then(|r| r.end);
+            // Clips the tombstone to the range of the page.
+            let tombstone_end = match (end, page_end) {
+                (Some(end), Some(page_end)) => Some(end.min(page_end)),
+                (end, None) => end,
+                (None, page_end) => page_end,
+            };
+            let tombstone = RangeTombstone::new(next, tombstone_end, lsn);
+            match self
+                .write_delta(view, PageKind::RangeDelete, &[tombstone.as_item()])
+                .await
+            {
+                Ok(_) => {
+                    self.tree.stats.success.write.inc();
+                    self.tree.stats.write_retries.observe(backoff.retries());
+                    backoff = Backoff::new();
+                    match page_end {
+                        Some(page_end) => next = page_end,
+                        None => break,
+                    }
+                }
+                Err(Error::Again) => {
+                    self.tree.stats.conflict.write.inc();
+                    self.op_stats.retries.inc();
+                    self.check_limits()?;
+                    backoff.snooze().await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of pages whose delta chains are long enough to be
+    /// consolidated.
+    ///
+    /// This reads the info of every page in the page table.
+    pub(crate) fn pending_consolidations(&self) -> Result<u64> {
+        let mut count = 0;
+        for page in self.guard.iter_pages() {
+            let (_, _, info) = page?;
+            if self.should_consolidate_page(&info) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the ids of pages that have delta pages.
+    ///
+    /// This reads the info of every page in the page table.
+    pub(crate) fn chained_pages(&self) -> Result<Vec<u64>> {
+        let mut ids = Vec::new();
+        for page in self.guard.iter_pages() {
+            let (id, _, info) = page?;
+            if info.chain_len() > 1 {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Consolidates the delta pages of the page, regardless of the chain
+    /// length.
+    ///
+    /// Returns true if the page is consolidated. Pages that are freed, have no
+    /// delta pages, or are modified concurrently are skipped.
+    pub(crate) async fn consolidate_page_by_id(&self, id: u64) -> Result<bool> {
+        if self.guard.live_page_addr(id).is_none() {
+            return Ok(false);
+        }
+        let view = self.page_view(id, None).await?;
+        if view.page.chain_len() <= 1 {
+            return Ok(false);
+        }
+        match self.consolidate_page(view).await {
+            Ok(_) => Ok(true),
+            Err(Error::Again) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Finds the leaf page to write the key.
+    async fn find_write_leaf(&self, key: &[u8]) -> Result<PageView<'_>> {
+        match self.find_rightmost_leaf(key).await? {
+            Some(view) => Ok(view),
+            None => Ok(self.find_leaf(key).await?.0),
+        }
+    }
 
+    /// Writes the sorted key-value pairs to the leaf page that covers them.
+    ///
+    /// Returns [`Error::Again`] if the page range has changed.
+    async fn write_page(&self, view: PageView<'_>, items: &[(Key<'_>, Value<'_>)]) -> Result<()> {
+        self.write_delta(view, PageKind::Data, items).await
+    }
+
+    /// Writes a delta page of the kind with the items to the leaf page.
+    ///
+    /// Returns [`Error::Again`] if the page range has changed.
+    async fn write_delta(
+        &self,
+        mut view: PageView<'_>,
+        kind: PageKind,
+        items: &[(Key<'_>, Value<'_>)],
+    ) -> Result<()> {
         // Try to split the page before every write to avoid starving the split
         // operation due to contentions.
-        if self.should_split_page(&view.page) && self.split_page(view.clone()).await.is_ok() {
-            return Err(Error::Again);
+        if self.should_split_page(&view.page) {
+            match self.split_page(view.clone()).await {
+                Ok(()) => return Err(Error::Again),
+                // The page can not grow further without new page ids.
+                Err(Error::Capacity) => return Err(Error::Capacity),
+                Err(_) => {}
+            }
         }
 
-        // Build a delta page with the given key-value pair.
-        let delta = (key, value);
-        let builder = SortedPageBuilder::new(PageTier::Leaf, PageKind::Data).with_item(delta);
+        // Build a delta page with the given key-value pairs.
+        let builder = SortedPageBuilder::new(PageTier::Leaf, kind).with_slice(items);
         let mut txn = self.guard.begin().await;
         let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
         builder.build(&mut new_page);
+        let lsns = items.iter().map(|(k, _)| k.lsn);
+        if let (Some(min_lsn), Some(max_lsn)) = (lsns.clone().min(), lsns.max()) {
+            txn.set_lsns(min_lsn, max_lsn);
+        }
 
         // Update the corresponding leaf page with the delta.
         loop {
@@ -166,6 +613,7 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 }
                 Err(None) => return Err(Error::Again),
                 Err(Some((_txn, addr))) => {
+                    self.on_failed_cas();
                     // The page has been updated by other transactions.
                     // We can keep retrying as long as the page epoch remains
                     // the same. However, this doesn't work for the root
@@ -183,6 +631,9 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 }
             }
         }
+        if let Some(lsn) = items.iter().map(|(k, _)| k.lsn).max() {
+            self.tree.observe_lsn(lsn);
+        }
 
         // Try to consolidate the page if it is too long.
         if self.should_consolidate_page(&view.page) {
@@ -191,10 +642,88 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         Ok(())
     }
 
+    /// Starts an operation of this transaction.
+    fn start_op(&self) -> OpStart {
+        self.op_stats.reset();
+        OpStart {
+            at: Instant::now(),
+            reads: self.guard.read_stats(),
+        }
+    }
+
+    /// Records the latency and the contention of an operation started at
+    /// `start`.
+    ///
+    /// The operation is logged with a breakdown of the work done by it if it
+    /// is slower than the slow operation threshold.
+    fn observe_latency(&self, op: &str, kind: OpKind, start: OpStart) {
+        let now = Instant::now();
+        let elapsed = now - start.at;
+        self.tree
+            .stats
+            .latency
+            .by_kind(kind)
+            .observe(elapsed.as_micros() as usize);
+        // The operation is attributed the pin since the last operation of this
+        // transaction, including the time between them.
+        let pinned_since = mem::replace(&mut *self.pinned_since.lock(), now);
+        self.tree
+            .stats
+            .contention
+            .observe(kind, &self.op_stats, now - pinned_since);
+        if !matches!(self.tree.dynamic_options.slow_op_threshold(), Some(t) if elapsed >= t) {
+            return;
+        }
+        self.tree.stats.slow_ops.inc();
+        let reads = self.guard.read_stats().since(&start.reads);
+        warn!(
+            "slow {op} lasted {elapsed:?}: pages_visited: {}, \
+                deltas_read: {}, \
+                max_chain_len: {}, \
+                failed_cas: {}, \
+                retries: {}, \
+                file_reads: {}, \
+                cache_misses: {}, \
+                io_wait: {:?}",
+            self.op_stats.pages_visited.get(),
+            self.op_stats.deltas_read.get(),
+            self.op_stats.max_chain_len.get(),
+            self.op_stats.failed_cas.get(),
+            self.op_stats.retries.get(),
+            reads.file_reads,
+            reads.cache_misses,
+            reads.io_wait
+        );
+    }
+
+    /// Records the pages visited since the last record if access tracing is
+    /// enabled.
+    fn trace_access(&self, op: &str, key: Option<&[u8]>) {
+        if let Some(pending) = &self.pending_access {
+            let mut pending = pending.lock();
+            let cache_misses = self.guard.read_stats().cache_misses;
+            let hit = cache_misses == pending.cache_misses;
+            pending.cache_misses = cache_misses;
+            self.tree.access_tracer.record(op, key, &pending.pages, hit);
+            pending.pages.clear();
+        }
+    }
+
+    fn on_failed_cas(&self) {
+        self.tree.stats.failed_cas.inc();
+        self.op_stats.failed_cas.inc();
+    }
+
     /// Returns a view to the page.
     async fn page_view<'g>(&'g self, id: u64, range: Option<Range<'g>>) -> Result<PageView<'g>> {
         let addr = self.guard.page_addr(id);
         let page = self.guard.read_page_info(addr)?;
+        self.op_stats.pages_visited.inc();
+        self.op_stats.max_chain_len.update(page.chain_len() as u64);
+        perf_context::record(|ctx| ctx.pages_visited += 1);
+        if let Some(pending) = &self.pending_access {
+            pending.lock().pages.push(id);
+        }
         Ok(PageView {
             id,
             addr,
@@ -211,10 +740,16 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             match self.try_find_leaf(key).await {
                 Ok((view, parent)) => {
                     self.tree.stats.success.read.inc();
+                    self.tree.stats.chain.observe(view.page.chain_len());
+                    self.tree
+                        .tuner
+                        .tick(&self.tree.stats, &self.tree.dynamic_options);
                     return Ok((view, parent));
                 }
                 Err(Error::Again) => {
                     self.tree.stats.conflict.read.inc();
+                    self.op_stats.retries.inc();
+                    self.check_limits()?;
                     continue;
                 }
                 Err(e) => return Err(e),
@@ -227,8 +762,9 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         let mut index = ROOT_INDEX;
         let mut range = ROOT_RANGE;
         let mut parent = None;
+        let mut height = 1;
         loop {
-            let view = self.page_view(index.id, Some(range)).await?;
+            let mut view = self.page_view(index.id, Some(range)).await?;
             // If the page epoch has changed, the page may not contain the data we expect
             // anymore. Try to reconcile pending conflicts and restart the operation.
             //
@@ -249,17 +785,43 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             // expect (between step 3 and 4). We use epoch to track the key range of a
             // logical page.
             if view.page.epoch() != index.epoch {
-                let _ = self.reconcile_page(view, parent).await;
-                return Err(Error::Again);
+                if !self.guard.is_read_only() {
+                    let _ = self.reconcile_page(view, parent).await;
+                    return Err(Error::Again);
+                }
+                // A read-only tree can't reconcile the split, so it follows the split to
+                // the page that contains the key instead. Nothing changes the tree, so
+                // the epoch can only change with a split.
+                if view.page.kind() != PageKind::Split {
+                    return Err(Error::Corrupted);
+                }
+                let (page, _) = self
+                    .guard
+                    .read_page(view.addr, CacheOption::default())
+                    .await?;
+                let (split_key, split_index) = split_delta_from_page(page);
+                if key >= split_key {
+                    index = split_index;
+                    range.start = split_key;
+                    continue;
+                }
+                range.end = Some(split_key);
+                view.range = Some(range);
             }
             if view.page.tier().is_leaf() {
+                self.tree.stats.height.update(height);
+                if range.end.is_none() && view.id != ROOT_ID {
+                    self.update_rightmost_leaf(&view, range.start);
+                }
                 return Ok((view, parent));
             }
             // Find the child page that may contain the key.
-            let (child_index, child_range) = self
-                .find_child(key, &view)
-                .await?
-                .expect("child page must exist");
+            let child = if view.id == ROOT_ID {
+                self.find_root_child(key, &view).await?
+            } else {
+                self.find_child(key, &view).await?
+            };
+            let (child_index, child_range) = child.expect("child page must exist");
             index = child_index;
             range.start = child_range.start;
             // If the child has no range end, use the current one instead.
@@ -267,6 +829,40 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 range.end = Some(end);
             }
             parent = Some(view);
+            height += 1;
+        }
+    }
+
+    /// Finds the rightmost leaf page if it covers the key.
+    ///
+    /// Sequential writes always go to the rightmost leaf page, so the leaf is
+    /// cached to skip the descent from the root.
+    async fn find_rightmost_leaf(&self, key: &[u8]) -> Result<Option<PageView<'_>>> {
+        let cache = self
+            .rightmost_leaf
+            .get_or_init(|| self.tree.rightmost_leaf.load());
+        let Some(cache) = cache.as_ref().filter(|c| c.covers(key)) else {
+            return Ok(None);
+        };
+        let view = self.page_view(cache.id(), Some(cache.range())).await?;
+        // The page range has changed if the page epoch is different.
+        if view.page.epoch() != cache.epoch() {
+            return Ok(None);
+        }
+        Ok(Some(view))
+    }
+
+    /// Updates the rightmost leaf cache if it is stale.
+    ///
+    /// The root is never cached because it is split without updating its
+    /// epoch.
+    fn update_rightmost_leaf(&self, view: &PageView<'_>, start: &[u8]) {
+        let epoch = view.page.epoch();
+        let cache = self.tree.rightmost_leaf.load();
+        if !matches!(cache, Some(c) if c.id() == view.id && c.epoch() == epoch) {
+            self.tree
+                .rightmost_leaf
+                .store(RightmostLeaf::new(view.id, epoch, start));
         }
     }
 
@@ -279,7 +875,10 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         F: FnMut(u64, PageRef<'g>, Option<CacheToken>) -> bool,
     {
         while addr != 0 {
+            self.check_limits()?;
             let (page, cache_token) = self.guard.read_page(addr, hint).await?;
+            self.op_stats.deltas_read.inc();
+            perf_context::record(|ctx| ctx.deltas_read += 1);
             if f(addr, page, cache_token) {
                 break;
             }
@@ -288,20 +887,97 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         Ok(())
     }
 
+    /// Reads the leaf pages of the keys into the cache.
+    ///
+    /// Returns the number of leaf pages read.
+    pub(crate) async fn prefetch(&self, keys: &[&[u8]]) -> Result<usize> {
+        let mut keys = keys.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        let mut views = Vec::new();
+        let mut rest = keys.as_slice();
+        while let Some(&first) = rest.first() {
+            let (view, _) = self.find_leaf(first).await?;
+            let num_keys = match view.range.and_then(|r| r.end) {
+                Some(end) => rest.partition_point(|&key| key < end),
+                None => rest.len(),
+            };
+            rest = &rest[num_keys..];
+            views.push(view);
+        }
+        self.prefetch_pages(&views).await?;
+        Ok(views.len())
+    }
+
+    /// Reads the leaf pages in `[start, end)` into the cache.
+    ///
+    /// Returns the number of leaf pages read.
+    pub(crate) async fn prefetch_range(&self, start: &[u8], end: Option<&[u8]>) -> Result<usize> {
+        let mut views = Vec::new();
+        let mut key = start;
+        loop {
+            let (view, _) = self.find_leaf(key).await?;
+            let next = view.range.and_then(|r| r.end);
+            views.push(view);
+            match next {
+                Some(next) if end.map_or(true, |end| next < end) => key = next,
+                _ => break,
+            }
+        }
+        self.prefetch_pages(&views).await?;
+        Ok(views.len())
+    }
+
+    /// Reads the delta chains of the pages concurrently.
+    async fn prefetch_pages(&self, views: &[PageView<'_>]) -> Result<()> {
+        let prefetches = views.iter().map(|view| self.prefetch_page(view));
+        for result in futures::future::join_all(prefetches).await {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Reads all the pages in the delta chain of the page.
+    ///
+    /// Pages read from files stay pinned by the guard, so later reads of the
+    /// chain don't need any I/O.
+    async fn prefetch_page(&self, view: &PageView<'_>) -> Result<()> {
+        self.walk_page(view.addr, |_, _, _| false, CacheOption::default())
+            .await
+    }
+
     /// Creates an iterator over the key-value pairs in the page.
     async fn iter_page<'g, K, V>(&'g self, view: &PageView<'g>) -> Result<MergingPageIter<'g, K, V>>
+    where
+        K: SortedPageKey,
+        V: SortedPageValue,
+    {
+        self.iter_page_with(view, CacheOption::default()).await
+    }
+
+    /// Creates an iterator over the key-value pairs in the page, and reads
+    /// the pages with the cache hint.
+    async fn iter_page_with<'g, K, V>(
+        &'g self,
+        view: &PageView<'g>,
+        hint: CacheOption,
+    ) -> Result<MergingPageIter<'g, K, V>>
     where
         K: SortedPageKey,
         V: SortedPageValue,
     {
         let mut builder = MergingIterBuilder::with_capacity(view.page.chain_len() as usize);
         let mut range_limit = None;
+        let mut tombstones = Vec::new();
         self.walk_page(
             view.addr,
             |_, page, _| {
                 match page.kind() {
                     PageKind::Data => {
-                        builder.add(SortedPageIter::from(page));
+                        builder.add(SortedPageIter::new(self.sorted_page(page)));
+                    }
+                    PageKind::RangeDelete => {
+                        tombstones.push(RangeTombstone::from_page(page));
                     }
                     PageKind::Split => {
                         // The split key we first encountered must be the smallest.
@@ -318,10 +994,21 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 }
                 false
             },
-            CacheOption::default(),
+            hint,
         )
         .await?;
-        Ok(MergingPageIter::new(builder.build(), range_limit))
+        Ok(MergingPageIter::new(builder.build(), range_limit).with_tombstones(tombstones))
+    }
+
+    /// Returns a reference to the sorted page, which is searched with the
+    /// options of the tree.
+    fn sorted_page<'p, K, V>(&self, page: PageRef<'p>) -> SortedPageRef<'p, K, V>
+    where
+        K: SortedPageKey,
+        V: SortedPageValue,
+    {
+        SortedPageRef::new(page)
+            .with_linear_search_threshold(self.tree.options.linear_search_threshold)
     }
 
     /// Finds the value corresponding to the key from the page.
@@ -331,26 +1018,38 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         view: &PageView<'g>,
     ) -> Result<Option<&'g [u8]>> {
         let mut value = None;
+        // The max LSN of visible range tombstones that cover the key.
+        let mut deleted_lsn = 0;
         self.walk_page(
             view.addr,
             |_, page, _| {
                 debug_assert!(page.tier().is_leaf());
-                // We only care about data pages here.
-                if page.kind().is_data() {
-                    let page = ValuePageRef::from(page);
-                    let index = match page.rank(key) {
-                        Ok(i) => i,
-                        Err(i) => i,
-                    };
-                    if let Some((k, v)) = page.get(index) {
-                        if k.raw == key.raw {
-                            debug_assert!(k.lsn <= key.lsn);
-                            if let Value::Put(v) = v {
-                                value = Some(v);
+                match page.kind() {
+                    PageKind::Data => {
+                        let page: ValuePageRef = self.sorted_page(page);
+                        let index = match page.rank(key) {
+                            Ok(i) => i,
+                            Err(i) => i,
+                        };
+                        if let Some((k, v)) = page.get(index) {
+                            if k.raw == key.raw {
+                                debug_assert!(k.lsn <= key.lsn);
+                                if let Value::Put(v) = v {
+                                    if k.lsn >= deleted_lsn {
+                                        value = Some(v);
+                                    }
+                                }
+                                return true;
                             }
-                            return true;
                         }
                     }
+                    PageKind::RangeDelete => {
+                        let tombstone = RangeTombstone::from_page(page);
+                        if tombstone.lsn <= key.lsn && tombstone.covers(key.raw) {
+                            deleted_lsn = deleted_lsn.max(tombstone.lsn);
+                        }
+                    }
+                    PageKind::Split => {}
                 }
                 false
             },
@@ -375,7 +1074,7 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 debug_assert!(page.tier().is_inner());
                 // We only care about data pages here.
                 if page.kind().is_data() {
-                    let page = IndexPageRef::from(page);
+                    let page: IndexPageRef = self.sorted_page(page);
                     // Find the two items that enclose the key.
                     let (left, right) = match page.rank(&key) {
                         // The `i` item is equal to the key, so the range is [i, i + 1).
@@ -402,6 +1101,30 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         Ok(child)
     }
 
+    /// Finds the child page that may contain the key from the root.
+    ///
+    /// This is the same as [`Self::find_child`], except that it looks up the
+    /// root cache first and refreshes the cache if it is stale.
+    async fn find_root_child<'g>(
+        &'g self,
+        key: &[u8],
+        view: &PageView<'g>,
+    ) -> Result<Option<(Index, Range<'g>)>> {
+        let cache = self.root_cache.get_or_init(|| self.tree.root_cache.load());
+        if let Some(cache) = cache.as_ref().filter(|c| c.addr() == view.addr) {
+            return Ok(cache.find_child(key));
+        }
+
+        // The cache of this transaction is stale. Refresh the shared one for
+        // subsequent transactions if no one else has done it yet.
+        if !matches!(self.tree.root_cache.load(), Some(c) if c.addr() == view.addr) {
+            let iter = self.iter_page(view).await?;
+            let iter = MergingInnerPageIter::new(iter);
+            self.tree.root_cache.store(RootCache::new(view.addr, iter));
+        }
+        self.find_child(key, view).await
+    }
+
     // Splits the page into two halves.
     async fn split_page(&self, view: PageView<'_>) -> Result<()> {
         // We can only split base data pages.
@@ -409,8 +1132,22 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             return Err(Error::InvalidArgument);
         }
         match view.page.tier() {
-            PageTier::Leaf => self.split_page_impl::<Key, Value>(view).await,
-            PageTier::Inner => self.split_page_impl::<&[u8], Index>(view).await,
+            PageTier::Leaf => {
+                instrument!(
+                    self.split_page_impl::<Key, Value>(view),
+                    "split_page",
+                    id = view.id
+                )
+                .await
+            }
+            PageTier::Inner => {
+                instrument!(
+                    self.split_page_impl::<&[u8], Index>(view),
+                    "split_page",
+                    id = view.id
+                )
+                .await
+            }
         }
     }
 
@@ -423,23 +1160,26 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             return self.split_root_impl::<K, V>(view).await;
         }
 
+        let start = Instant::now();
         let (page, _) = self
             .guard
             .read_page(view.addr, CacheOption::default())
             .await?;
         let page = SortedPageRef::<K, V>::from(page);
-        let Some((split_key, _, right_iter)) = page.into_split_iter() else {
+        let fill_factor = self.tree.options.split_fill_factor;
+        let Some((split_key, _, right_iter)) = page.into_split_iter(fill_factor) else {
             return Ok(());
         };
 
         let mut txn = self.guard.begin().await;
         // Build and insert the right page.
-        let right_id = {
+        let (right_id, right_size) = {
             let builder =
                 SortedPageBuilder::new(view.page.tier(), PageKind::Data).with_iter(right_iter);
-            let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
+            let size = builder.size();
+            let (new_addr, mut new_page) = txn.alloc_page(size).await?;
             builder.build(&mut new_page);
-            txn.insert_page(new_addr)
+            (txn.insert_page(new_addr)?, size)
         };
         // Build a delta page with the right index.
         let delta = (split_key.as_raw(), Index::new(right_id, 0));
@@ -455,11 +1195,13 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             .map(|_| {
                 trace!("split page {:?} with delta {:?}", view, delta);
                 self.tree.stats.success.split_page.inc();
+                self.on_page_split(&view, right_id, right_size, start);
                 view.addr = new_addr;
                 view.page = new_page.info();
             })
             .map_err(|_| {
                 self.tree.stats.conflict.split_page.inc();
+                self.on_failed_cas();
                 Error::Again
             })?;
 
@@ -475,12 +1217,14 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         assert_eq!(view.page.epoch(), 0);
         assert_eq!(view.page.chain_len(), 1);
 
+        let start = Instant::now();
         let (page, _) = self
             .guard
             .read_page(view.addr, CacheOption::default())
             .await?;
         let page = SortedPageRef::<K, V>::from(page);
-        let Some((split_key, left_iter, right_iter)) = page.into_split_iter() else {
+        let fill_factor = self.tree.options.split_fill_factor;
+        let Some((split_key, left_iter, right_iter)) = page.into_split_iter(fill_factor) else {
             return Ok(());
         };
 
@@ -491,15 +1235,16 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 SortedPageBuilder::new(view.page.tier(), PageKind::Data).with_iter(left_iter);
             let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
             builder.build(&mut new_page);
-            txn.insert_page(new_addr)
+            txn.insert_page(new_addr)?
         };
         // Build and insert the right page.
-        let right_id = {
+        let (right_id, right_size) = {
             let builder =
                 SortedPageBuilder::new(view.page.tier(), PageKind::Data).with_iter(right_iter);
-            let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
+            let size = builder.size();
+            let (new_addr, mut new_page) = txn.alloc_page(size).await?;
             builder.build(&mut new_page);
-            txn.insert_page(new_addr)
+            (txn.insert_page(new_addr)?, size)
         };
         // Build a delta page with the right index.
         let delta = [
@@ -515,17 +1260,32 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             .map(|_| {
                 trace!("split root {:?} with delta {:?}", view, delta);
                 self.tree.stats.success.split_page.inc();
+                self.on_page_split(&view, right_id, right_size, start);
             })
             .map_err(|_| {
                 self.tree.stats.conflict.split_page.inc();
+                self.on_failed_cas();
                 Error::Again
             })
     }
 
+    fn on_page_split(&self, view: &PageView<'_>, right_id: u64, right_size: usize, start: Instant) {
+        event!(right_id, right_size, "split page");
+        if let Some(listener) = &self.tree.options.event_listener {
+            listener.on_page_split(&SplitEvent {
+                page_id: view.id,
+                right_page_id: right_id,
+                page_size: view.page.size(),
+                right_page_size: right_size,
+                duration: start.elapsed(),
+            });
+        }
+    }
+
     /// Reconciles any conflicts on the page.
     async fn reconcile_page(&self, view: PageView<'_>, parent: Option<PageView<'_>>) -> Result<()> {
         let result = match view.page.kind() {
-            PageKind::Data => Ok(()),
+            PageKind::Data | PageKind::RangeDelete => Ok(()),
             PageKind::Split => {
                 if let Some(parent) = parent {
                     self.reconcile_split_page(view, parent).await
@@ -591,7 +1351,10 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                 parent.addr = new_addr;
                 parent.page = new_page.info();
             })
-            .map_err(|_| Error::Again)?;
+            .map_err(|_| {
+                self.on_failed_cas();
+                Error::Again
+            })?;
 
         // Try to consolidate the parent page if it is too long.
         if self.should_consolidate_page(&parent.page) {
@@ -604,13 +1367,25 @@ impl<'a, E: Env> TreeTxn<'a, E> {
     async fn consolidate_page<'g>(&'g self, view: PageView<'g>) -> Result<PageView<'g>> {
         match view.page.tier() {
             PageTier::Leaf => {
-                let safe_lsn = self.tree.safe_lsn();
-                self.consolidate_page_impl(view, |iter| MergingLeafPageIter::new(iter, safe_lsn))
-                    .await
+                let safe_lsn = self.tree.gc_lsn();
+                instrument!(
+                    self.consolidate_page_impl(view, |iter, partial| {
+                        // Deletes must shadow the versions in the pages that are not merged.
+                        MergingLeafPageIter::new(iter, safe_lsn).keep_deletes(partial)
+                    }),
+                    "consolidate_page",
+                    id = view.id,
+                    safe_lsn
+                )
+                .await
             }
             PageTier::Inner => {
-                self.consolidate_page_impl(view, MergingInnerPageIter::new)
-                    .await
+                instrument!(
+                    self.consolidate_page_impl(view, |iter, _| MergingInnerPageIter::new(iter)),
+                    "consolidate_page",
+                    id = view.id
+                )
+                .await
             }
         }
     }
@@ -621,14 +1396,15 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         f: F,
     ) -> Result<PageView<'g>>
     where
-        F: Fn(MergingPageIter<'g, K, V>) -> I,
+        F: Fn(MergingPageIter<'g, K, V>, bool) -> I,
         I: RewindableIterator<Item = (K, V)>,
         K: SortedPageKey,
         V: SortedPageValue,
     {
+        let start = Instant::now();
         // Collect information for this consolidation.
         let info = self.collect_consolidation_info(&view).await?;
-        let iter = f(info.iter);
+        let iter = f(info.iter, info.partial);
         let builder = SortedPageBuilder::new(view.page.tier(), PageKind::Data).with_iter(iter);
         let mut txn = self.guard.begin().await;
         let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
@@ -642,12 +1418,32 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             .map(|_| {
                 trace!("consolidate page {:?}", view);
                 self.tree.stats.success.consolidate_page.inc();
+                if view.page.tier().is_leaf() {
+                    let page = ValuePageRef::from(*new_page);
+                    self.tree.stats.leaf.observe(SortedPageIter::new(page));
+                }
                 view.addr = new_addr;
                 view.page = new_page.info();
+                event!(
+                    num_pages = info.page_addrs.len(),
+                    input_size = info.page_size,
+                    output_size = view.page.size(),
+                    "consolidate page"
+                );
+                if let Some(listener) = &self.tree.options.event_listener {
+                    listener.on_page_consolidated(&ConsolidationEvent {
+                        page_id: view.id,
+                        num_pages: info.page_addrs.len(),
+                        input_size: info.page_size,
+                        output_size: view.page.size(),
+                        duration: start.elapsed(),
+                    });
+                }
                 view
             })
             .map_err(|_| {
                 self.tree.stats.conflict.consolidate_page.inc();
+                self.on_failed_cas();
                 Error::Again
             })
     }
@@ -667,6 +1463,12 @@ impl<'a, E: Env> TreeTxn<'a, E> {
         let mut last_page = view.page.clone();
         let mut page_addrs = Vec::with_capacity(chain_len);
         let mut range_limit = None;
+        let mut tombstones = Vec::new();
+        let mut partial = false;
+        let max_pages = match self.tree.dynamic_options.max_consolidation_pages() {
+            0 => usize::MAX,
+            n => n,
+        };
         let opt = CacheOption::default().set_refill_cold_when_not_full(true);
         self.walk_page(
             view.addr,
@@ -676,20 +1478,36 @@ impl<'a, E: Env> TreeTxn<'a, E> {
                         // Inner pages can not do partial consolidations because of the
                         // placeholders. This is fine since inner pages
                         // doesn't consolidate as often as leaf pages.
+                        // Range tombstones must be merged with all the pages after them too.
                         if page.tier().is_leaf()
                             && builder.len() >= 2
                             && page_size < page.size() / 2
                             && range_limit.is_none()
+                            && tombstones.is_empty()
                             && !self.should_consolidate_page(&page.info())
                         {
+                            partial = true;
+                            return true;
+                        }
+                        // Stop at the limit unless there is a split delta, since pages after
+                        // the split delta must be merged to drop the moved items.
+                        if page.tier().is_leaf()
+                            && builder.len() >= max_pages
+                            && range_limit.is_none()
+                            && tombstones.is_empty()
+                        {
+                            partial = true;
                             return true;
                         }
                         if let Some(ctoken) = ctoken {
                             ctoken.return_cache_as_old();
                         }
-                        builder.add(SortedPageIter::from(page));
+                        builder.add(SortedPageIter::new(self.sorted_page(page)));
                         page_size += page.size();
                     }
+                    PageKind::RangeDelete => {
+                        tombstones.push(RangeTombstone::from_page(page));
+                    }
                     PageKind::Split => {
                         if range_limit.is_none() {
                             let (split_key, _) = split_delta_from_page(page);
@@ -704,11 +1522,13 @@ impl<'a, E: Env> TreeTxn<'a, E> {
             opt,
         )
         .await?;
-        let iter = MergingPageIter::new(builder.build(), range_limit);
+        let iter = MergingPageIter::new(builder.build(), range_limit).with_tombstones(tombstones);
         Ok(ConsolidationInfo {
             iter,
             last_page,
             page_addrs,
+            page_size,
+            partial,
         })
     }
 
@@ -716,25 +1536,46 @@ impl<'a, E: Env> TreeTxn<'a, E> {
     async fn consolidate_and_restructure_page<'g>(&'g self, mut view: PageView<'g>) -> Result<()> {
         view = self.consolidate_page(view).await?;
         // Try to split the page if it is too large.
-        if self.should_split_page(&view.page) {
+        let fill_factor = self.tree.dynamic_options.consolidation_fill_factor();
+        if self.should_split_page_with(&view.page, fill_factor) {
             let _ = self.split_page(view).await;
         }
         Ok(())
     }
 
+    /// Records an access to the page and restructures it if it is hot.
+    ///
+    /// Hot pages are consolidated once they have any delta, instead of waiting
+    /// for the chain length to exceed the limit.
+    async fn access_page(&self, view: PageView<'_>) {
+        if !self.tree.hot_pages.access(view.id) {
+            return;
+        }
+        if view.page.chain_len() > 1 || self.should_split_page(&view.page) {
+            self.tree.stats.hot_pages.inc();
+            let _ = self.consolidate_and_restructure_page(view).await;
+        }
+    }
+
     // Returns true if the page should be split.
     fn should_split_page(&self, page: &PageInfo) -> bool {
+        self.should_split_page_with(page, 1.0)
+    }
+
+    // Returns true if the page exceeds the fill factor of the page size.
+    fn should_split_page_with(&self, page: &PageInfo, fill_factor: f64) -> bool {
         let mut max_size = self.tree.options.page_size;
         if page.tier().is_inner() {
             // Adjust the page size for inner pages.
             max_size /= 2;
         }
+        let max_size = (max_size as f64 * fill_factor.clamp(0.0, 1.0)) as usize;
         page.size() > max_size && page.chain_next() == 0
     }
 
     // Returns true if the page should be consolidated.
     fn should_consolidate_page(&self, page: &PageInfo) -> bool {
-        let mut max_chain_len = self.tree.options.page_chain_length;
+        let mut max_chain_len = self.tree.dynamic_options.page_chain_length();
         if page.tier().is_inner() {
             // Adjust the chain length for inner pages.
             max_chain_len /= 2;
@@ -753,6 +1594,9 @@ pub(crate) struct TreeIter<'a, 't: 'a, E: Env> {
 
 impl<'a, 't: 'a, E: Env> TreeIter<'a, 't, E> {
     pub(crate) fn new(txn: &'a TreeTxn<'t, E>, options: ReadOptions) -> Self {
+        if !options.fill_cache {
+            txn.guard.add_uncached_reader();
+        }
         Self {
             txn,
             options,
@@ -761,12 +1605,24 @@ impl<'a, 't: 'a, E: Env> TreeIter<'a, 't, E> {
         }
     }
 
+    /// Returns the cache hint to read leaf pages.
+    ///
+    /// Inner pages are always cached, since they are shared by all reads.
+    fn leaf_hint(&self) -> CacheOption {
+        CacheOption::default().set_fill_cache(self.options.fill_cache)
+    }
+
     async fn seek(&mut self, target: &[u8]) -> Result<PageIter<'_>> {
         let (view, parent) = self.txn.find_leaf(target).await?;
-        let iter = self.txn.iter_page(&view).await?;
+        let iter = self.txn.iter_page_with(&view, self.leaf_hint()).await?;
         let mut leaf_iter = PageIter::new(iter, self.options.max_lsn);
         leaf_iter.seek(target);
-        if let Some(parent) = parent {
+        if self.txn.guard.is_read_only() && view.page.kind() == PageKind::Split {
+            // The parent of a split leaf may not know the split page in a
+            // read-only tree, so the scan restarts from the split key.
+            self.inner_iter = None;
+            self.inner_next = view.range.unwrap().end;
+        } else if let Some(parent) = parent {
             let iter = self.txn.iter_page(&parent).await?;
             let mut iter = MergingInnerPageIter::new(iter);
             if iter.seek(target) {
@@ -781,13 +1637,31 @@ impl<'a, 't: 'a, E: Env> TreeIter<'a, 't, E> {
         Ok(leaf_iter)
     }
 
+    /// Returns an iterator over the next leaf page.
+    ///
+    /// If pages are read without filling the cache, the pages read by the
+    /// last call are released, so that a long scan doesn't hold them all.
     pub(crate) async fn next_page(&mut self) -> Result<Option<PageIter<'_>>> {
+        if !self.options.fill_cache {
+            // Safety: the last page is not used anymore, since it borrows `self`.
+            unsafe { self.txn.guard.release_uncached_pages() };
+        }
+        let start = self.txn.start_op();
+        let txn = self.txn;
+        let page = self.next_page_impl().await?;
+        txn.observe_latency("scan", OpKind::Scan, start);
+        txn.trace_access("scan", None);
+        Ok(page)
+    }
+
+    async fn next_page_impl(&mut self) -> Result<Option<PageIter<'_>>> {
+        let hint = self.leaf_hint();
         let mut inner_next = self.inner_next.take();
         if let Some(inner_iter) = self.inner_iter.as_mut() {
             if let Some((start, index)) = inner_iter.next() {
                 let view = self.txn.page_view(index.id, None).await?;
                 if view.page.epoch() == index.epoch {
-                    let iter = self.txn.iter_page(&view).await?;
+                    let iter = self.txn.iter_page_with(&view, hint).await?;
                     self.inner_next = inner_next;
                     return Ok(Some(PageIter::new(iter, self.options.max_lsn)));
                 } else {
@@ -806,6 +1680,21 @@ impl<'a, 't: 'a, E: Env> TreeIter<'a, 't, E> {
     }
 }
 
+impl<'a, 't: 'a, E: Env> Drop for TreeIter<'a, 't, E> {
+    fn drop(&mut self) {
+        if !self.options.fill_cache {
+            self.txn.guard.remove_uncached_reader();
+        }
+    }
+}
+
+/// The start of an operation, used to observe the work done by it.
+#[derive(Clone, Copy)]
+struct OpStart {
+    at: Instant,
+    reads: ReadStats,
+}
+
 struct ConsolidationInfo<'a, K, V>
 where
     K: SortedPageKey,
@@ -814,6 +1703,9 @@ where
     iter: MergingPageIter<'a, K, V>,
     last_page: PageInfo,
     page_addrs: Vec<u64>,
+    page_size: usize,
+    // Whether some pages at the end of the chain are not merged.
+    partial: bool,
 }
 
 fn split_delta_from_page(page: PageRef<'_>) -> (&[u8], Index) {
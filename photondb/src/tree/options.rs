@@ -1,4 +1,25 @@
-use crate::PageStoreOptions;
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    listener::EventListener, page::LINEAR_SEARCH_THRESHOLD, util::json::Json, ChecksumType,
+    Compression, Error, PageStoreOptions, Result,
+};
+
+/// The minimal page size, which fits the header and a few small entries.
+const MIN_PAGE_SIZE: usize = 64;
+
+/// The prefix of environment variables that override options loaded from a
+/// file.
+const ENV_PREFIX: &str = "PHOTONDB_";
 
 /// Options to configure a table.
 #[non_exhaustive]
@@ -11,12 +32,168 @@ pub struct Options {
     /// Default: 8KB
     pub page_size: usize,
 
+    /// The fraction of items kept in the left page when a page is split.
+    ///
+    /// The rest of the items are moved to the new right page. A larger value
+    /// packs pages tighter for workloads that write keys in ascending order,
+    /// since the left page will not receive more writes.
+    ///
+    /// Default: 0.5
+    pub split_fill_factor: f64,
+
+    /// The target fill factor of pages rebuilt by consolidations.
+    ///
+    /// A consolidated page larger than `page_size * consolidation_fill_factor`
+    /// is split, which leaves headroom in pages for following writes. A value
+    /// of 1.0 only splits pages that exceed `page_size`.
+    ///
+    /// Default: 1.0
+    pub consolidation_fill_factor: f64,
+
     /// Approximate number of delta pages chained per page before it is
     /// consolidated.
     ///
     /// Default: 4
     pub page_chain_length: usize,
 
+    /// The max number of pages merged by a consolidation of a leaf page.
+    ///
+    /// If a leaf chain is longer than this, only the pages at the head of the
+    /// chain are merged into an intermediate page, and the rest are left for
+    /// later consolidations. This bounds the pause time of consolidating very
+    /// large pages. Zero means no limit, otherwise it must be at least 2.
+    ///
+    /// Default: 0
+    pub max_consolidation_pages: usize,
+
+    /// If true, adjusts `page_chain_length` and `consolidation_fill_factor`
+    /// to the workload while the table is open.
+    ///
+    /// Chains are shortened if lookups spend more work walking delta pages
+    /// than consolidations spend rebuilding pages, and lengthened otherwise.
+    /// Once the chain length reaches a bound, the fill factor is adjusted
+    /// between `min_consolidation_fill_factor` and
+    /// `consolidation_fill_factor` instead.
+    ///
+    /// Default: false
+    pub auto_tune_consolidation: bool,
+
+    /// The lower bound of `page_chain_length` with auto-tuning.
+    ///
+    /// Default: 2
+    pub min_page_chain_length: usize,
+
+    /// The upper bound of `page_chain_length` with auto-tuning.
+    ///
+    /// Default: 16
+    pub max_page_chain_length: usize,
+
+    /// The lower bound of `consolidation_fill_factor` with auto-tuning.
+    ///
+    /// Default: 0.5
+    pub min_consolidation_fill_factor: f64,
+
+    /// The interval to sample page accesses for hot page detection.
+    ///
+    /// One of every `hot_page_sample_interval` reads on each thread is
+    /// sampled. Zero disables hot page detection.
+    ///
+    /// Default: 64
+    pub hot_page_sample_interval: usize,
+
+    /// The number of sampled accesses for a page to be considered hot.
+    ///
+    /// Hot pages are consolidated or split before their delta chains reach
+    /// `page_chain_length`, to keep reads on them fast.
+    ///
+    /// Default: 8
+    pub hot_page_threshold: usize,
+
+    /// Pages with fewer items than this are searched linearly instead of
+    /// binary searched.
+    ///
+    /// A linear scan avoids the branch mispredictions of a binary search,
+    /// which dominate the search in pages with a few items. The best value
+    /// depends on the CPU. Zero always binary searches.
+    ///
+    /// Default: 16
+    pub linear_search_threshold: usize,
+
+    /// A listener to be notified of lifecycle events, such as page splits,
+    /// flushes and write stalls.
+    ///
+    /// Default: None
+    pub event_listener: Option<Arc<dyn EventListener>>,
+
+    /// Operations that take longer than this are logged with a breakdown of
+    /// their work, such as the pages visited and the time waiting for I/O.
+    ///
+    /// For range scans, each page read is an operation, and the breakdown
+    /// covers the scan so far.
+    ///
+    /// The threshold must be positive. Set it to None to disable logging, as
+    /// `slow_op_threshold_ms = 0` does.
+    ///
+    /// Default: None
+    pub slow_op_threshold: Option<Duration>,
+
+    /// If set, a background thread logs the stats of the table in this
+    /// period, with the deltas since the last dump.
+    ///
+    /// With the `metrics` feature, the stats are also reported through the
+    /// [`metrics`](https://docs.rs/metrics) facade in this period.
+    ///
+    /// Default: None
+    pub stats_dump_period: Option<Duration>,
+
+    /// If set, a background thread polls the admin file in the table
+    /// directory in this period, and runs the maintenance commands submitted
+    /// by [`MaintenanceCommand::submit`].
+    ///
+    /// The thread also publishes [`LiveStats`] to the table directory in this
+    /// period. This counts the pages to consolidate, so the period should not
+    /// be too short for large tables.
+    ///
+    /// The admin and stats files are accessed on the filesystem of the
+    /// operating system instead of through the environment of the table, so
+    /// this is only supported in a [native](crate::env::Env::is_native)
+    /// environment, and opening a table with it fails otherwise.
+    ///
+    /// [`MaintenanceCommand::submit`]: crate::MaintenanceCommand::submit
+    /// [`LiveStats`]: crate::LiveStats
+    ///
+    /// Default: None
+    pub admin_poll_period: Option<Duration>,
+
+    /// If true, destructive operations such as range deletions and option
+    /// changes are recorded to an append-only audit log in the table
+    /// directory, along with the [`AuditContext`] of the caller.
+    ///
+    /// [`AuditContext`]: crate::AuditContext
+    ///
+    /// Default: false
+    pub audit_log: bool,
+
+    /// If set, writes made by [`Table::put_buffered`] and
+    /// [`Table::delete_buffered`] are buffered per thread, and written as
+    /// batches at most this long after they are buffered.
+    ///
+    /// Grouping small writes into batches reduces the cost per write, at the
+    /// cost of buffered writes not being visible until they are written. If
+    /// this is `None`, those writes are written directly.
+    ///
+    /// [`Table::put_buffered`]: crate::raw::Table::put_buffered
+    /// [`Table::delete_buffered`]: crate::raw::Table::delete_buffered
+    ///
+    /// Default: None
+    pub buffered_write_delay: Option<Duration>,
+
+    /// The approximate size of writes buffered per thread before they are
+    /// written as a batch, if [`Self::buffered_write_delay`] is set.
+    ///
+    /// Default: 64KB
+    pub buffered_write_size: usize,
+
     /// Options for the underlying page store.
     pub page_store: PageStoreOptions,
 }
@@ -25,12 +202,474 @@ impl Default for Options {
     fn default() -> Self {
         Self {
             page_size: 8 << 10,
+            split_fill_factor: 0.5,
+            consolidation_fill_factor: 1.0,
             page_chain_length: 4,
+            max_consolidation_pages: 0,
+            auto_tune_consolidation: false,
+            min_page_chain_length: 2,
+            max_page_chain_length: 16,
+            min_consolidation_fill_factor: 0.5,
+            hot_page_sample_interval: 64,
+            hot_page_threshold: 8,
+            linear_search_threshold: LINEAR_SEARCH_THRESHOLD,
+            event_listener: None,
+            slow_op_threshold: None,
+            stats_dump_period: None,
+            admin_poll_period: None,
+            audit_log: false,
+            buffered_write_delay: None,
+            buffered_write_size: 64 << 10,
             page_store: PageStoreOptions::default(),
         }
     }
 }
 
+impl Options {
+    /// Checks that the options make sense together.
+    ///
+    /// This is called when a table is opened, so that invalid options are
+    /// rejected with a descriptive error instead of failing later in the tree
+    /// or the page store.
+    ///
+    /// Returns [`Error::InvalidArgument`] if any option is invalid.
+    pub fn validate(&self) -> Result<()> {
+        macro_rules! ensure {
+            ($cond:expr, $($arg:tt)+) => {
+                if !$cond {
+                    return Err(Error::InvalidArgument(format!($($arg)+)));
+                }
+            };
+        }
+        let is_fill_factor = |v: f64| v > 0.0 && v <= 1.0;
+        let store = &self.page_store;
+
+        ensure!(
+            self.page_size >= MIN_PAGE_SIZE,
+            "page_size {} is smaller than {MIN_PAGE_SIZE} bytes",
+            self.page_size
+        );
+        ensure!(
+            is_fill_factor(self.split_fill_factor),
+            "split_fill_factor {} is not in (0, 1]",
+            self.split_fill_factor
+        );
+        ensure!(
+            is_fill_factor(self.consolidation_fill_factor),
+            "consolidation_fill_factor {} is not in (0, 1]",
+            self.consolidation_fill_factor
+        );
+        ensure!(
+            self.page_chain_length > 0,
+            "page_chain_length must be positive"
+        );
+        ensure!(
+            self.max_consolidation_pages != 1,
+            "max_consolidation_pages must be 0 or at least 2"
+        );
+        ensure!(
+            self.min_page_chain_length > 0,
+            "min_page_chain_length must be positive"
+        );
+        ensure!(
+            self.min_page_chain_length <= self.max_page_chain_length,
+            "min_page_chain_length {} is larger than max_page_chain_length {}",
+            self.min_page_chain_length,
+            self.max_page_chain_length
+        );
+        ensure!(
+            is_fill_factor(self.min_consolidation_fill_factor)
+                && self.min_consolidation_fill_factor <= self.consolidation_fill_factor,
+            "min_consolidation_fill_factor {} is not in (0, consolidation_fill_factor]",
+            self.min_consolidation_fill_factor
+        );
+        ensure!(
+            self.hot_page_sample_interval == 0 || self.hot_page_threshold > 0,
+            "hot_page_threshold must be positive to detect hot pages"
+        );
+        ensure!(
+            self.slow_op_threshold != Some(Duration::ZERO),
+            "slow_op_threshold must be positive"
+        );
+        ensure!(
+            self.stats_dump_period != Some(Duration::ZERO),
+            "stats_dump_period must be positive"
+        );
+        ensure!(
+            self.admin_poll_period != Some(Duration::ZERO),
+            "admin_poll_period must be positive"
+        );
+        ensure!(
+            self.buffered_write_delay != Some(Duration::ZERO),
+            "buffered_write_delay must be positive"
+        );
+        for (name, enabled) in [
+            ("audit_log", self.audit_log),
+            ("admin_poll_period", self.admin_poll_period.is_some()),
+            ("buffered_write_delay", self.buffered_write_delay.is_some()),
+        ] {
+            ensure!(
+                !(store.read_only && enabled),
+                "{name} writes to the table, which is read-only"
+            );
+        }
+
+        let capacity = store.write_buffer_capacity as usize;
+        ensure!(
+            capacity.is_power_of_two(),
+            "write_buffer_capacity {capacity} is not a power of two"
+        );
+        ensure!(
+            capacity >= self.page_size,
+            "write_buffer_capacity {capacity} is smaller than page_size {}",
+            self.page_size
+        );
+        ensure!(
+            store.max_write_buffers > 0,
+            "max_write_buffers must be positive"
+        );
+        ensure!(
+            store.max_background_flushes > 0 || store.max_write_buffers > 1,
+            "max_write_buffers must be at least 2 without background flushes"
+        );
+        ensure!(store.file_base_size > 0, "file_base_size must be positive");
+        ensure!(
+            !store.cache_strict_capacity_limit || store.cache_capacity >= self.page_size,
+            "cache_capacity {} is smaller than page_size {} with a strict capacity limit",
+            store.cache_capacity,
+            self.page_size
+        );
+        for (name, compression) in [
+            ("compression_on_flush", store.compression_on_flush),
+            (
+                "compression_on_cold_compact",
+                store.compression_on_cold_compact,
+            ),
+        ] {
+            ensure!(
+                [Compression::NONE, Compression::SNAPPY, Compression::ZSTD].contains(&compression),
+                "{name} {compression:?} is not a single compression method"
+            );
+        }
+        ensure!(
+            [ChecksumType::NONE, ChecksumType::CRC32].contains(&store.page_checksum_type),
+            "page_checksum_type {:?} is not supported",
+            store.page_checksum_type
+        );
+        Ok(())
+    }
+
+    /// Loads options from a TOML file.
+    ///
+    /// Keys in the file are the names of the options. Durations are given in
+    /// milliseconds by keys with a `_ms` suffix, where 0 means none. Page
+    /// store options are in a `[page_store]` table, and compression and
+    /// checksum types are given by names such as `"zstd"` and `"crc32"`:
+    ///
+    /// ```toml
+    /// page_size = 16384
+    /// stats_dump_period_ms = 60000
+    ///
+    /// [page_store]
+    /// cache_capacity = 1073741824
+    /// compression_on_cold_compact = "zstd"
+    /// ```
+    ///
+    /// Options not in the file keep their default values. After the file is
+    /// loaded, each environment variable `PHOTONDB_<KEY>` overrides the option
+    /// of the key in upper case, such as `PHOTONDB_PAGE_SIZE` and
+    /// `PHOTONDB_PAGE_STORE_CACHE_CAPACITY`.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the file is not valid TOML, or if
+    /// the file or an environment variable with the prefix has an unknown key
+    /// or an invalid value.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut options = Self::from_toml(&content)?;
+        let vars = std::env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)));
+        options.apply_env(vars)?;
+        Ok(options)
+    }
+
+    fn from_toml(content: &str) -> Result<Self> {
+        let table = match content.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(Error::InvalidArgument("invalid config".to_owned())),
+            Err(err) => return Err(Error::InvalidArgument(format!("invalid config: {err}"))),
+        };
+        let mut options = Self::default();
+        for (name, value) in table {
+            match (name.as_str(), value) {
+                ("page_store", toml::Value::Table(table)) => {
+                    for (name, value) in table {
+                        options.set_page_store(&name, &toml_value(&name, value)?)?;
+                    }
+                }
+                ("page_store", value) => {
+                    return Err(invalid_option("page_store", &value.to_string()))
+                }
+                (name, value) => options.set(name, &toml_value(name, value)?)?,
+            }
+        }
+        Ok(options)
+    }
+
+    fn apply_env<I: IntoIterator<Item = (String, String)>>(&mut self, vars: I) -> Result<()> {
+        for (var, value) in vars {
+            let Some(name) = var.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let name = name.to_ascii_lowercase();
+            match name.strip_prefix("page_store_") {
+                Some(name) => self.set_page_store(name, &value)?,
+                None => self.set(&name, &value)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "page_size" => self.page_size = parse_option(name, value)?,
+            "split_fill_factor" => self.split_fill_factor = parse_option(name, value)?,
+            "consolidation_fill_factor" => {
+                self.consolidation_fill_factor = parse_option(name, value)?
+            }
+            "page_chain_length" => self.page_chain_length = parse_option(name, value)?,
+            "max_consolidation_pages" => self.max_consolidation_pages = parse_option(name, value)?,
+            "auto_tune_consolidation" => self.auto_tune_consolidation = parse_option(name, value)?,
+            "min_page_chain_length" => self.min_page_chain_length = parse_option(name, value)?,
+            "max_page_chain_length" => self.max_page_chain_length = parse_option(name, value)?,
+            "min_consolidation_fill_factor" => {
+                self.min_consolidation_fill_factor = parse_option(name, value)?
+            }
+            "hot_page_sample_interval" => {
+                self.hot_page_sample_interval = parse_option(name, value)?
+            }
+            "hot_page_threshold" => self.hot_page_threshold = parse_option(name, value)?,
+            "linear_search_threshold" => self.linear_search_threshold = parse_option(name, value)?,
+            "slow_op_threshold_ms" => self.slow_op_threshold = parse_millis(name, value)?,
+            "stats_dump_period_ms" => self.stats_dump_period = parse_millis(name, value)?,
+            "admin_poll_period_ms" => self.admin_poll_period = parse_millis(name, value)?,
+            "audit_log" => self.audit_log = parse_option(name, value)?,
+            "buffered_write_delay_ms" => self.buffered_write_delay = parse_millis(name, value)?,
+            "buffered_write_size" => self.buffered_write_size = parse_option(name, value)?,
+            _ => return Err(Error::InvalidArgument(format!("unknown option {name}"))),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_page_store(&mut self, name: &str, value: &str) -> Result<()> {
+        let opts = &mut self.page_store;
+        match name {
+            "write_buffer_capacity" => opts.write_buffer_capacity = parse_option(name, value)?,
+            "max_write_buffers" => opts.max_write_buffers = parse_option(name, value)?,
+            "max_deferred_bytes" => opts.max_deferred_bytes = parse_option(name, value)?,
+            "epoch_advance_attempts" => opts.epoch_advance_attempts = parse_option(name, value)?,
+            "use_direct_io" => opts.use_direct_io = parse_option(name, value)?,
+            "disable_space_reclaiming" => {
+                opts.disable_space_reclaiming = parse_option(name, value)?
+            }
+            "max_background_flushes" => opts.max_background_flushes = parse_option(name, value)?,
+            "reclaim_in_background" => opts.reclaim_in_background = parse_option(name, value)?,
+            "max_space_amplification_percent" => {
+                opts.max_space_amplification_percent = parse_option(name, value)?
+            }
+            "space_used_high" => opts.space_used_high = parse_option(name, value)?,
+            "file_base_size" => opts.file_base_size = parse_option(name, value)?,
+            "cache_capacity" => opts.cache_capacity = parse_option(name, value)?,
+            "cache_estimated_entry_charge" => {
+                opts.cache_estimated_entry_charge = parse_option(name, value)?
+            }
+            "cache_file_reader_capacity" => {
+                opts.cache_file_reader_capacity = parse_option(name, value)?
+            }
+            "cache_strict_capacity_limit" => {
+                opts.cache_strict_capacity_limit = parse_option(name, value)?
+            }
+            "prepopulate_cache_on_flush" => {
+                opts.prepopulate_cache_on_flush = parse_option(name, value)?
+            }
+            "compression_on_flush" => opts.compression_on_flush = parse_compression(name, value)?,
+            "compression_on_cold_compact" => {
+                opts.compression_on_cold_compact = parse_compression(name, value)?
+            }
+            "page_checksum_type" => {
+                opts.page_checksum_type = match value.to_ascii_lowercase().as_str() {
+                    "none" => ChecksumType::NONE,
+                    "crc32" => ChecksumType::CRC32,
+                    _ => return Err(invalid_option(name, value)),
+                }
+            }
+            "avoid_flush_during_shutdown" => {
+                opts.avoid_flush_during_shutdown = parse_option(name, value)?
+            }
+            "force_unlock" => opts.force_unlock = parse_option(name, value)?,
+            "read_only" => opts.read_only = parse_option(name, value)?,
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "unknown option page_store.{name}"
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_json(&self) -> Json {
+        let millis = |d: Option<Duration>| d.map(|d| d.as_millis() as u64);
+        Json::Object(vec![
+            ("page_size", self.page_size.into()),
+            ("split_fill_factor", self.split_fill_factor.into()),
+            (
+                "consolidation_fill_factor",
+                self.consolidation_fill_factor.into(),
+            ),
+            ("page_chain_length", self.page_chain_length.into()),
+            (
+                "max_consolidation_pages",
+                self.max_consolidation_pages.into(),
+            ),
+            (
+                "auto_tune_consolidation",
+                self.auto_tune_consolidation.into(),
+            ),
+            ("min_page_chain_length", self.min_page_chain_length.into()),
+            ("max_page_chain_length", self.max_page_chain_length.into()),
+            (
+                "min_consolidation_fill_factor",
+                self.min_consolidation_fill_factor.into(),
+            ),
+            (
+                "hot_page_sample_interval",
+                self.hot_page_sample_interval.into(),
+            ),
+            ("hot_page_threshold", self.hot_page_threshold.into()),
+            (
+                "linear_search_threshold",
+                self.linear_search_threshold.into(),
+            ),
+            ("event_listener", self.event_listener.is_some().into()),
+            (
+                "slow_op_threshold_ms",
+                millis(self.slow_op_threshold).into(),
+            ),
+            (
+                "stats_dump_period_ms",
+                millis(self.stats_dump_period).into(),
+            ),
+            (
+                "admin_poll_period_ms",
+                millis(self.admin_poll_period).into(),
+            ),
+            ("audit_log", self.audit_log.into()),
+            (
+                "buffered_write_delay_ms",
+                millis(self.buffered_write_delay).into(),
+            ),
+            ("buffered_write_size", self.buffered_write_size.into()),
+            ("page_store", self.page_store.to_json()),
+        ])
+    }
+}
+
+pub(crate) fn parse_option<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| invalid_option(name, value))
+}
+
+/// Parses a duration in milliseconds, where 0 means none.
+fn parse_millis(name: &str, value: &str) -> Result<Option<Duration>> {
+    match parse_option(name, value)? {
+        0 => Ok(None),
+        ms => Ok(Some(Duration::from_millis(ms))),
+    }
+}
+
+fn parse_compression(name: &str, value: &str) -> Result<Compression> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Ok(Compression::NONE),
+        "snappy" => Ok(Compression::SNAPPY),
+        "zstd" => Ok(Compression::ZSTD),
+        _ => Err(invalid_option(name, value)),
+    }
+}
+
+fn invalid_option(name: &str, value: &str) -> Error {
+    Error::InvalidArgument(format!("invalid value {value:?} of option {name}"))
+}
+
+/// Converts a TOML value to the string form of an option.
+fn toml_value(name: &str, value: toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Ok(value.to_string())
+        }
+        _ => Err(invalid_option(name, &value.to_string())),
+    }
+}
+
+/// Options of a tree that can be changed while it is open.
+#[derive(Debug)]
+pub(crate) struct DynamicOptions {
+    page_chain_length: AtomicUsize,
+    max_consolidation_pages: AtomicUsize,
+    // The bits of an `f64`.
+    consolidation_fill_factor: AtomicU64,
+    // In microseconds, or `u64::MAX` if slow operations are not logged.
+    slow_op_threshold: AtomicU64,
+}
+
+impl DynamicOptions {
+    pub(crate) fn new(options: &Options) -> Self {
+        let this = Self {
+            page_chain_length: AtomicUsize::new(options.page_chain_length),
+            max_consolidation_pages: AtomicUsize::new(options.max_consolidation_pages),
+            consolidation_fill_factor: AtomicU64::new(options.consolidation_fill_factor.to_bits()),
+            slow_op_threshold: AtomicU64::new(u64::MAX),
+        };
+        this.set_slow_op_threshold(options.slow_op_threshold);
+        this
+    }
+
+    pub(crate) fn page_chain_length(&self) -> usize {
+        self.page_chain_length.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_page_chain_length(&self, v: usize) {
+        self.page_chain_length.store(v, Ordering::Relaxed);
+    }
+
+    pub(crate) fn max_consolidation_pages(&self) -> usize {
+        self.max_consolidation_pages.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_max_consolidation_pages(&self, v: usize) {
+        self.max_consolidation_pages.store(v, Ordering::Relaxed);
+    }
+
+    pub(crate) fn consolidation_fill_factor(&self) -> f64 {
+        f64::from_bits(self.consolidation_fill_factor.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_consolidation_fill_factor(&self, v: f64) {
+        self.consolidation_fill_factor
+            .store(v.to_bits(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn slow_op_threshold(&self) -> Option<Duration> {
+        match self.slow_op_threshold.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    pub(crate) fn set_slow_op_threshold(&self, v: Option<Duration>) {
+        let us = v.map_or(u64::MAX, |d| (d.as_micros() as u64).min(u64::MAX - 1));
+        self.slow_op_threshold.store(us, Ordering::Relaxed);
+    }
+}
+
 /// Options to configure the behavior of reads.
 #[non_exhaustive]
 #[derive(Clone, Debug)]
@@ -44,8 +683,28 @@ pub struct ReadOptions {
 
     /// Whether to fill pages load from store to the page cache.
     ///
+    /// This only applies to leaf pages, since inner pages are shared by all
+    /// reads and are always cached.
+    ///
     /// Default: true
     pub fill_cache: bool,
+
+    /// The max number of times to retry the read on conflicts with
+    /// concurrent writes.
+    ///
+    /// The read returns [`Error::Timeout`] once it retries more than this.
+    ///
+    /// Default: None
+    pub max_retries: Option<u32>,
+
+    /// The time limit of the read.
+    ///
+    /// The read returns [`Error::Timeout`] once it runs longer than this. The
+    /// limit is checked between page reads and retries, so a read may overrun
+    /// it by one step, such as a slow I/O.
+    ///
+    /// Default: None
+    pub timeout: Option<Duration>,
 }
 
 impl Default for ReadOptions {
@@ -53,11 +712,176 @@ impl Default for ReadOptions {
         Self {
             max_lsn: u64::MAX,
             fill_cache: true,
+            max_retries: None,
+            timeout: None,
         }
     }
 }
 
+impl ReadOptions {
+    pub(crate) fn limits(&self) -> OpLimits {
+        OpLimits::new(self.timeout, self.max_retries)
+    }
+}
+
 /// Options to configure the behavior of writes.
 #[non_exhaustive]
-#[derive(Clone, Debug)]
-pub struct WriteOptions {}
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    /// The max number of times to retry the write on conflicts with
+    /// concurrent writes.
+    ///
+    /// The write returns [`Error::Timeout`] once it retries more than this.
+    ///
+    /// Default: None
+    pub max_retries: Option<u32>,
+
+    /// The time limit of the write.
+    ///
+    /// The write returns [`Error::Timeout`] once it runs longer than this.
+    /// The limit is checked between page reads and retries, so a write may
+    /// overrun it by one step, such as a slow I/O.
+    ///
+    /// Default: None
+    pub timeout: Option<Duration>,
+}
+
+impl WriteOptions {
+    pub(crate) fn limits(&self) -> OpLimits {
+        OpLimits::new(self.timeout, self.max_retries)
+    }
+}
+
+/// Limits of the work of an operation.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OpLimits {
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) max_retries: Option<u32>,
+}
+
+impl OpLimits {
+    fn new(timeout: Option<Duration>, max_retries: Option<u32>) -> Self {
+        Self {
+            deadline: timeout.and_then(|t| Instant::now().checked_add(t)),
+            max_retries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml() {
+        let options = Options::from_toml(
+            r#"
+            page_size = 16384
+            split_fill_factor = 0.9
+            stats_dump_period_ms = 1000
+            slow_op_threshold_ms = 0
+            linear_search_threshold = 4
+
+            [page_store]
+            cache_capacity = 1024
+            compression_on_flush = "zstd"
+            page_checksum_type = "CRC32"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(options.page_size, 16384);
+        assert_eq!(options.split_fill_factor, 0.9);
+        assert_eq!(options.stats_dump_period, Some(Duration::from_secs(1)));
+        assert_eq!(options.slow_op_threshold, None);
+        assert_eq!(options.linear_search_threshold, 4);
+        assert_eq!(options.page_chain_length, 4);
+        assert_eq!(options.page_store.cache_capacity, 1024);
+        assert_eq!(options.page_store.compression_on_flush, Compression::ZSTD);
+        assert_eq!(options.page_store.page_checksum_type, ChecksumType::CRC32);
+
+        for content in [
+            "page_size =",
+            "unknown = 1",
+            "page_size = -1",
+            "page_size = \"8KB\"",
+            "page_store = 1",
+            "[page_store]\nunknown = 1",
+            "[page_store]\ncompression_on_flush = \"lz4\"",
+        ] {
+            assert!(
+                matches!(Options::from_toml(content), Err(Error::InvalidArgument(_))),
+                "{content}"
+            );
+        }
+    }
+
+    #[test]
+    fn apply_env() {
+        let vars = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let mut options = Options::default();
+        options
+            .apply_env(vars(&[
+                ("HOME", "/root"),
+                ("PHOTONDB_PAGE_SIZE", "4096"),
+                ("PHOTONDB_PAGE_STORE_CACHE_CAPACITY", "2048"),
+            ]))
+            .unwrap();
+        assert_eq!(options.page_size, 4096);
+        assert_eq!(options.page_store.cache_capacity, 2048);
+        assert!(options
+            .apply_env(vars(&[("PHOTONDB_UNKNOWN", "1")]))
+            .is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photondb.toml");
+        fs::write(&path, "page_chain_length = 8").unwrap();
+        assert_eq!(Options::from_file(&path).unwrap().page_chain_length, 8);
+    }
+
+    #[test]
+    fn validate() {
+        Options::default().validate().unwrap();
+
+        let invalid: [fn(&mut Options); 18] = [
+            |o| o.page_size = 16,
+            |o| o.split_fill_factor = 0.0,
+            |o| o.consolidation_fill_factor = f64::NAN,
+            |o| o.page_chain_length = 0,
+            |o| o.max_consolidation_pages = 1,
+            |o| o.min_page_chain_length = 0,
+            |o| o.min_page_chain_length = 32,
+            |o| o.min_consolidation_fill_factor = 1.5,
+            |o| o.hot_page_threshold = 0,
+            |o| o.slow_op_threshold = Some(Duration::ZERO),
+            |o| o.stats_dump_period = Some(Duration::ZERO),
+            |o| o.buffered_write_delay = Some(Duration::ZERO),
+            |o| {
+                o.page_store.read_only = true;
+                o.audit_log = true;
+            },
+            |o| o.page_store.write_buffer_capacity = 3 << 20,
+            |o| o.page_store.write_buffer_capacity = 4 << 10,
+            |o| {
+                o.page_store.max_background_flushes = 0;
+                o.page_store.max_write_buffers = 1;
+            },
+            |o| {
+                o.page_store.cache_strict_capacity_limit = true;
+                o.page_store.cache_capacity = 1024;
+            },
+            |o| o.page_store.compression_on_flush = Compression::SNAPPY | Compression::ZSTD,
+        ];
+        for (i, f) in invalid.iter().enumerate() {
+            let mut options = Options::default();
+            f(&mut options);
+            assert!(
+                matches!(options.validate(), Err(Error::InvalidArgument(_))),
+                "{i}"
+            );
+        }
+    }
+}
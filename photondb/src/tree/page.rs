@@ -1,3 +1,5 @@
+use std::mem;
+
 use crate::{page::*, page_store::*};
 
 /// The root id is fixed to the minimal id in the page store.
@@ -15,19 +17,202 @@ pub(super) struct PageView<'a> {
     pub(super) range: Option<Range<'a>>,
 }
 
+/// A range tombstone in a leaf delta chain.
+///
+/// It deletes entries in `[start, end)` with LSNs smaller than its LSN. A
+/// range tombstone is stored as a delta page with one item, whose key is the
+/// range start with the LSN, and whose value is [`Value::Put`] with the range
+/// end, or [`Value::Delete`] if the range is unbounded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct RangeTombstone<'a> {
+    pub(super) start: &'a [u8],
+    pub(super) end: Option<&'a [u8]>,
+    pub(super) lsn: u64,
+}
+
+impl<'a> RangeTombstone<'a> {
+    pub(super) fn new(start: &'a [u8], end: Option<&'a [u8]>, lsn: u64) -> Self {
+        Self { start, end, lsn }
+    }
+
+    /// Decodes a range tombstone from a range delete delta page.
+    pub(super) fn from_page(page: PageRef<'a>) -> Self {
+        debug_assert!(page.kind().is_range_delete());
+        let page = ValuePageRef::from(page);
+        let (key, value) = page.get(0).expect("range delete page must have one item");
+        let end = match value {
+            Value::Put(end) => Some(end),
+            Value::Delete => None,
+        };
+        Self::new(key.raw, end, key.lsn)
+    }
+
+    /// Returns the item to store the range tombstone in a delta page.
+    pub(super) fn as_item(&self) -> (Key<'a>, Value<'a>) {
+        let value = match self.end {
+            Some(end) => Value::Put(end),
+            None => Value::Delete,
+        };
+        (Key::new(self.start, self.lsn), value)
+    }
+
+    /// Returns true if the raw key is in the range.
+    pub(super) fn covers(&self, raw: &[u8]) -> bool {
+        raw >= self.start && self.end.map_or(true, |end| raw < end)
+    }
+}
+
+/// An iterator that turns range tombstones into deletes of the covered keys.
+///
+/// For each tombstone that covers a key, a delete with the tombstone LSN is
+/// emitted before the versions of the key it deletes. The output can be
+/// treated as if the tombstones are expanded to point deletes.
+pub(super) struct RangeDeletingIter<'a> {
+    iter: MergingPageIter<'a, Key<'a>, Value<'a>>,
+    tombstones: Vec<RangeTombstone<'a>>,
+    last_raw: Option<&'a [u8]>,
+    // LSNs of tombstones that cover the last raw key and are not emitted yet,
+    // in ascending order.
+    pending_lsns: Vec<u64>,
+    peeked: Option<(Key<'a>, Value<'a>)>,
+}
+
+impl<'a> RangeDeletingIter<'a> {
+    pub(super) fn new(mut iter: MergingPageIter<'a, Key<'a>, Value<'a>>) -> Self {
+        let tombstones = mem::take(&mut iter.tombstones);
+        Self {
+            iter,
+            tombstones,
+            last_raw: None,
+            pending_lsns: Vec::new(),
+            peeked: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last_raw = None;
+        self.pending_lsns.clear();
+        self.peeked = None;
+    }
+
+    /// Appends up to `n` next items to `items`, and returns the number of
+    /// items appended.
+    fn next_n(&mut self, n: usize, items: &mut Vec<(Key<'a>, Value<'a>)>) -> usize {
+        if self.tombstones.is_empty() {
+            return self.iter.next_n(n, items);
+        }
+        let len = items.len();
+        items.extend(self.by_ref().take(n));
+        items.len() - len
+    }
+}
+
+impl<'a> Iterator for RangeDeletingIter<'a> {
+    type Item = (Key<'a>, Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.peeked.take().or_else(|| self.iter.next())?;
+        if self.tombstones.is_empty() {
+            return Some((k, v));
+        }
+        if self.last_raw != Some(k.raw) {
+            self.last_raw = Some(k.raw);
+            self.pending_lsns.clear();
+            for t in &self.tombstones {
+                if t.covers(k.raw) {
+                    self.pending_lsns.push(t.lsn);
+                }
+            }
+            self.pending_lsns.sort_unstable();
+            self.pending_lsns.dedup();
+        }
+        // Versions are ordered by LSNs descendingly, so a tombstone that deletes this
+        // version is emitted right before it.
+        while let Some(&lsn) = self.pending_lsns.last() {
+            if lsn > k.lsn {
+                self.pending_lsns.pop();
+                self.peeked = Some((k, v));
+                return Some((Key::new(k.raw, lsn), Value::Delete));
+            }
+            // A version with the same LSN overrides the tombstone.
+            if lsn == k.lsn {
+                self.pending_lsns.pop();
+                continue;
+            }
+            break;
+        }
+        Some((k, v))
+    }
+}
+
+impl<'a> RewindableIterator for RangeDeletingIter<'a> {
+    fn rewind(&mut self) {
+        self.iter.rewind();
+        self.reset();
+    }
+}
+
+impl<'a> SeekableIterator<Key<'_>> for RangeDeletingIter<'a> {
+    fn seek(&mut self, target: &Key<'_>) -> bool {
+        self.reset();
+        self.iter.seek(target)
+    }
+}
+
 /// An iterator over user entries in a page.
 pub struct PageIter<'a> {
-    iter: MergingPageIter<'a, Key<'a>, Value<'a>>,
+    iter: RangeDeletingIter<'a>,
     read_lsn: u64,
     last_raw: Option<&'a [u8]>,
+    // The items decoded by `next_n`, which is reused across calls.
+    batch: Vec<(Key<'a>, Value<'a>)>,
 }
 
 impl<'a> PageIter<'a> {
     pub(super) fn new(iter: MergingPageIter<'a, Key<'a>, Value<'a>>, read_lsn: u64) -> Self {
         Self {
-            iter,
+            iter: RangeDeletingIter::new(iter),
             read_lsn,
             last_raw: None,
+            batch: Vec::new(),
+        }
+    }
+
+    /// Appends up to `n` next entries to `entries`, and returns the number of
+    /// entries appended.
+    ///
+    /// This is cheaper than calling [`Iterator::next`] for each entry, since
+    /// the items of the page are decoded in batches.
+    pub fn next_n(&mut self, n: usize, entries: &mut Vec<(&'a [u8], &'a [u8])>) -> usize {
+        let len = entries.len();
+        let mut batch = mem::take(&mut self.batch);
+        while entries.len() - len < n {
+            // An item yields at most one entry, so this never exceeds `n`.
+            batch.clear();
+            if self.iter.next_n(n - (entries.len() - len), &mut batch) == 0 {
+                break;
+            }
+            for &(k, v) in &batch {
+                if let Some(entry) = self.visible(k, v) {
+                    entries.push(entry);
+                }
+            }
+        }
+        batch.clear();
+        self.batch = batch;
+        entries.len() - len
+    }
+
+    /// Returns the entry of the item if it is the latest visible version of
+    /// its key.
+    fn visible(&mut self, k: Key<'a>, v: Value<'a>) -> Option<(&'a [u8], &'a [u8])> {
+        if k.lsn > self.read_lsn || self.last_raw == Some(k.raw) {
+            return None;
+        }
+        self.last_raw = Some(k.raw);
+        match v {
+            Value::Put(value) => Some((k.raw, value)),
+            _ => None,
         }
     }
 
@@ -42,18 +227,9 @@ impl<'a> Iterator for PageIter<'a> {
     type Item = (&'a [u8], &'a [u8]);
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (k, v) in &mut self.iter {
-            if k.lsn > self.read_lsn {
-                continue;
-            }
-            if let Some(last) = self.last_raw {
-                if k.raw == last {
-                    continue;
-                }
-            }
-            self.last_raw = Some(k.raw);
-            if let Value::Put(value) = v {
-                return Some((k.raw, value));
+        while let Some((k, v)) = self.iter.next() {
+            if let Some(entry) = self.visible(k, v) {
+                return Some(entry);
             }
         }
         None
@@ -67,6 +243,7 @@ where
 {
     iter: MergingIter<SortedPageIter<'a, K, V>>,
     range_limit: Option<&'a [u8]>,
+    tombstones: Vec<RangeTombstone<'a>>,
 }
 
 impl<'a, K, V> MergingPageIter<'a, K, V>
@@ -78,7 +255,33 @@ where
         iter: MergingIter<SortedPageIter<'a, K, V>>,
         range_limit: Option<&'a [u8]>,
     ) -> Self {
-        Self { iter, range_limit }
+        Self {
+            iter,
+            range_limit,
+            tombstones: Vec::new(),
+        }
+    }
+
+    /// Attaches the range tombstones in the delta chain.
+    ///
+    /// The tombstones are applied by the leaf iterators built on this.
+    pub(super) fn with_tombstones(mut self, tombstones: Vec<RangeTombstone<'a>>) -> Self {
+        self.tombstones = tombstones;
+        self
+    }
+
+    /// Appends up to `n` next items to `items`, and returns the number of
+    /// items appended.
+    fn next_n(&mut self, n: usize, items: &mut Vec<(K, V)>) -> usize {
+        let len = items.len();
+        self.iter.next_n(n, items);
+        if let Some(limit) = self.range_limit {
+            // Items are sorted, so the items after the limit are all out of range.
+            if let Some(i) = items[len..].iter().position(|(k, _)| k.as_raw() >= limit) {
+                items.truncate(len + i);
+            }
+        }
+        items.len() - len
     }
 }
 
@@ -131,9 +334,13 @@ where
 }
 
 /// An iterator that merges multiple leaf delta pages for consolidation.
+///
+/// Range tombstones are expanded to point deletes of the merged keys, so they
+/// can be dropped after the consolidation.
 pub(super) struct MergingLeafPageIter<'a> {
-    iter: MergingPageIter<'a, Key<'a>, Value<'a>>,
+    iter: RangeDeletingIter<'a>,
     safe_lsn: u64,
+    keep_deletes: bool,
     last_raw: Option<&'a [u8]>,
     skip_same_raw: bool,
 }
@@ -141,12 +348,20 @@ pub(super) struct MergingLeafPageIter<'a> {
 impl<'a> MergingLeafPageIter<'a> {
     pub(super) fn new(iter: MergingPageIter<'a, Key<'a>, Value<'a>>, safe_lsn: u64) -> Self {
         Self {
-            iter,
+            iter: RangeDeletingIter::new(iter),
             safe_lsn,
+            keep_deletes: false,
             last_raw: None,
             skip_same_raw: false,
         }
     }
+
+    /// Keeps the deletes visible to the safe LSN, which is required if older
+    /// versions of the keys may be in pages that are not merged.
+    pub(super) fn keep_deletes(mut self, keep: bool) -> Self {
+        self.keep_deletes = keep;
+        self
+    }
 }
 
 impl<'a> Iterator for MergingLeafPageIter<'a> {
@@ -167,8 +382,8 @@ impl<'a> Iterator for MergingLeafPageIter<'a> {
                     // This is the oldest version visible to the safe LSN.
                     self.skip_same_raw = true;
                     match v {
-                        Value::Put(_) => return Some((k, v)),
-                        Value::Delete => continue,
+                        Value::Delete if !self.keep_deletes => continue,
+                        _ => return Some((k, v)),
                     }
                 }
             }
@@ -178,7 +393,7 @@ impl<'a> Iterator for MergingLeafPageIter<'a> {
             match v {
                 // If the latest version is a delete and all older versions are not visible to the
                 // safe LSN, we can skip all of them.
-                Value::Delete if k.lsn <= self.safe_lsn => {
+                Value::Delete if k.lsn <= self.safe_lsn && !self.keep_deletes => {
                     continue;
                 }
                 _ => return Some((k, v)),
@@ -399,6 +614,66 @@ mod tests {
             assert_eq!(iter.next(), Some(data[0]));
             assert_eq!(iter.next(), Some(data[1]));
         }
+
+        // Deletes are kept if some pages are not merged.
+        let lsn_expect = [
+            (2, vec![data[0], data[1], data[3], data[5]]),
+            (3, vec![data[0], data[3], data[5]]),
+        ];
+        for (lsn, expect) in lsn_expect {
+            let merging_iter = build_merging_iter([owned_page.as_iter()], None);
+            let iter = MergingLeafPageIter::new(merging_iter, lsn).keep_deletes(true);
+            assert_eq!(iter.collect::<Vec<_>>(), expect);
+        }
+    }
+
+    #[test]
+    fn range_deleting_iter() {
+        let data = vec![
+            (Key::new(&[1], 5), Value::Put(&[5])),
+            (Key::new(&[1], 1), Value::Put(&[1])),
+            (Key::new(&[3], 3), Value::Put(&[3])),
+            (Key::new(&[3], 2), Value::Put(&[2])),
+            (Key::new(&[5], 1), Value::Put(&[1])),
+        ];
+        let owned_page = OwnedSortedPage::from_slice(&data);
+        let tombstones = vec![
+            RangeTombstone::new(&[1], Some(&[4]), 3),
+            RangeTombstone::new(&[3], None, 4),
+        ];
+        let build =
+            || build_merging_iter([owned_page.as_iter()], None).with_tombstones(tombstones.clone());
+
+        let mut iter = RangeDeletingIter::new(build());
+        let expect = [
+            data[0],
+            (Key::new(&[1], 3), Value::Delete),
+            data[1],
+            (Key::new(&[3], 4), Value::Delete),
+            // A version with the same LSN overrides the tombstone.
+            data[2],
+            data[3],
+            (Key::new(&[5], 4), Value::Delete),
+            data[4],
+        ];
+        assert_eq!((&mut iter).collect::<Vec<_>>(), expect);
+        iter.rewind();
+        assert_eq!(iter.next(), Some(expect[0]));
+
+        let lsn_expect = [
+            (2, as_slice(&[([1], [1]), ([3], [2]), ([5], [1])])),
+            (3, as_slice(&[([3], [3]), ([5], [1])])),
+            (4, vec![]),
+            (5, as_slice(&[([1], [5])])),
+        ];
+        for (lsn, expect) in lsn_expect {
+            let iter = PageIter::new(build(), lsn);
+            assert_eq!(iter.collect::<Vec<_>>(), expect);
+        }
+
+        // Covered versions are dropped once the tombstones are visible to the safe LSN.
+        let iter = MergingLeafPageIter::new(build(), 4);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![data[0]]);
     }
 
     #[test]
@@ -0,0 +1,52 @@
+use super::*;
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Returns up to `n - 1` sorted keys that split the tree into up to `n`
+    /// ranges with roughly the same number of pages.
+    ///
+    /// The keys are the starts of the children of inner pages, taken from the
+    /// root down to the first level with at least `n` pages. Only inner pages
+    /// are read, except for the info of one page to tell that the children
+    /// are leaf pages. The keys only balance the ranges, so it is fine if the
+    /// tree changes after they are taken.
+    pub(crate) async fn partition_keys(&self, n: usize) -> Result<Vec<Vec<u8>>> {
+        let mut level = vec![self.page_view(ROOT_ID, None).await?];
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        while level.len() < n && level[0].page.tier().is_inner() {
+            let mut children = Vec::new();
+            keys.clear();
+            for view in &level {
+                let iter = self.iter_page(view).await?;
+                for (start, index) in MergingInnerPageIter::new(iter) {
+                    // The first child of a page starts at the start of the page.
+                    if !start.is_empty() && keys.last().map_or(true, |last| &last[..] < start) {
+                        keys.push(start.to_vec());
+                    }
+                    children.push(index.id);
+                }
+            }
+            if children.len() >= n {
+                break;
+            }
+            // All children are on the same level, so the first one tells if
+            // they are inner pages to expand.
+            let first = self.page_view(children[0], None).await?;
+            if first.page.tier().is_leaf() {
+                break;
+            }
+            level = vec![first];
+            for &id in &children[1..] {
+                level.push(self.page_view(id, None).await?);
+            }
+        }
+
+        // Picks evenly spaced keys if there are too many ranges.
+        let num_ranges = keys.len() + 1;
+        if num_ranges <= n {
+            return Ok(keys);
+        }
+        Ok((1..n)
+            .map(|i| keys[i * num_ranges / n - 1].clone())
+            .collect())
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashSet, io::ErrorKind};
+
+use super::*;
+
+/// A report produced by repairing a table.
+#[derive(Clone, Debug, Default)]
+pub struct RepairReport {
+    /// The number of page files recorded in the rebuilt manifest.
+    pub num_files: usize,
+    /// The ids of page files whose metadata can not be read.
+    ///
+    /// These files are moved to the `quarantine` directory in the table
+    /// directory.
+    pub quarantined_files: Vec<u32>,
+    /// The ids of pages that can not be read and are replaced with empty pages.
+    pub quarantined_pages: Vec<u64>,
+    /// The ids of pages that can not be read nor replaced.
+    ///
+    /// These are inner pages or pages that are not mapped, which can not be
+    /// replaced without losing the pages under them.
+    pub unrepaired_pages: Vec<u64>,
+    /// The key ranges of the pages that can not be read, whose entries are
+    /// lost.
+    pub lost_ranges: Vec<LostRange>,
+}
+
+impl RepairReport {
+    /// Returns true if all unreadable pages are replaced.
+    pub fn is_ok(&self) -> bool {
+        self.unrepaired_pages.is_empty()
+    }
+}
+
+/// A key range whose entries are lost.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LostRange {
+    /// The id of the page that covers the range.
+    pub page_id: u64,
+    /// The start of the range (inclusive).
+    pub start: Vec<u8>,
+    /// The end of the range (exclusive), or [`None`] if the range is unbounded.
+    pub end: Option<Vec<u8>>,
+}
+
+/// A page to repair and the range it covers.
+struct RepairNode {
+    id: u64,
+    start: Vec<u8>,
+    end: Option<Vec<u8>>,
+}
+
+/// Information of a readable page.
+struct RepairPage {
+    tier: PageTier,
+    split: Option<(Vec<u8>, Index)>,
+    children: Vec<(Vec<u8>, Index)>,
+}
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Replaces the pages that can not be read with empty pages.
+    ///
+    /// The tree is walked level by level from the root. Unreadable leaf pages
+    /// are replaced with empty leaf pages, and the ranges they cover are
+    /// recorded as lost. The table should be quiescent during repairing.
+    pub(crate) async fn repair(&self, report: &mut RepairReport) -> Result<()> {
+        let mut visited = HashSet::new();
+        let mut level = vec![RepairNode {
+            id: ROOT_ID,
+            start: Vec::new(),
+            end: None,
+        }];
+        while !level.is_empty() {
+            let mut level_tier = None;
+            let mut lost = Vec::new();
+            let mut next_level = Vec::new();
+            let mut i = 0;
+            while i < level.len() {
+                let node = &level[i];
+                i += 1;
+                if !visited.insert(node.id) {
+                    continue;
+                }
+                let Some(page) = self.repair_read_page(node.id).await? else {
+                    lost.push(i - 1);
+                    continue;
+                };
+                level_tier.get_or_insert(page.tier);
+
+                let end = page
+                    .split
+                    .as_ref()
+                    .map(|(split_key, _)| split_key.clone())
+                    .or_else(|| node.end.clone());
+                let mut children = page.children.into_iter().peekable();
+                while let Some((start, index)) = children.next() {
+                    let end = children
+                        .peek()
+                        .map(|(k, _)| k.clone())
+                        .or_else(|| end.clone());
+                    next_level.push(RepairNode {
+                        id: index.id,
+                        start,
+                        end,
+                    });
+                }
+                // The right page of a pending split may not be reachable from
+                // the parent yet.
+                if let Some((split_key, index)) = page.split {
+                    let right = RepairNode {
+                        id: index.id,
+                        start: split_key.clone(),
+                        end: node.end.clone(),
+                    };
+                    level[i - 1].end = Some(split_key);
+                    level.push(right);
+                }
+            }
+
+            for i in lost {
+                let node = &level[i];
+                report.lost_ranges.push(LostRange {
+                    page_id: node.id,
+                    start: node.start.clone(),
+                    end: node.end.clone(),
+                });
+                let addr = self.guard.page_addr(node.id);
+                let tier = if addr != 0 && self.guard.contains_page(addr) {
+                    Some(self.guard.read_page_info(addr)?.tier())
+                } else {
+                    level_tier
+                };
+                if addr != 0 && tier == Some(PageTier::Leaf) {
+                    self.replace_with_empty_leaf(node.id, addr).await?;
+                    report.quarantined_pages.push(node.id);
+                } else {
+                    report.unrepaired_pages.push(node.id);
+                }
+            }
+            level = next_level;
+        }
+        report.lost_ranges.sort_by(|a, b| a.start.cmp(&b.start));
+        Ok(())
+    }
+
+    /// Reads all pages in the chain of a page.
+    ///
+    /// Returns [`None`] if the page is not mapped or any page in the chain
+    /// can not be read.
+    async fn repair_read_page(&self, id: u64) -> Result<Option<RepairPage>> {
+        let addr = self.guard.page_addr(id);
+        if addr == 0 {
+            return Ok(None);
+        }
+        let mut next = addr;
+        while next != 0 {
+            if !self.guard.contains_page(next) {
+                return Ok(None);
+            }
+            next = self.guard.read_page_info(next)?.chain_next();
+        }
+
+        let mut split = None;
+        let walked = self
+            .walk_page(
+                addr,
+                |_, page, _| {
+                    if page.kind() == PageKind::Split && split.is_none() {
+                        let (split_key, index) = split_delta_from_page(page);
+                        split = Some((split_key.to_vec(), index));
+                    }
+                    false
+                },
+                CacheOption::default(),
+            )
+            .await;
+        match walked {
+            Ok(()) => {}
+            Err(err) if err.is_corrupted() => return Ok(None),
+            Err(Error::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        let head = self.guard.read_page_info(addr)?;
+        let mut children = Vec::new();
+        if head.tier().is_inner() {
+            let view = PageView {
+                id,
+                addr,
+                page: head.clone(),
+                range: None,
+            };
+            let iter = self.iter_page(&view).await?;
+            for (start, index) in MergingInnerPageIter::new(iter) {
+                children.push((start.to_vec(), index));
+            }
+        }
+        Ok(Some(RepairPage {
+            tier: head.tier(),
+            split,
+            children,
+        }))
+    }
+
+    /// Replaces the chain of a page with an empty leaf page.
+    async fn replace_with_empty_leaf(&self, id: u64, addr: u64) -> Result<()> {
+        let head = self.guard.read_page_info(addr)?;
+        let mut dealloc_addrs = Vec::new();
+        let mut next = addr;
+        while next != 0 && self.guard.contains_page(next) {
+            dealloc_addrs.push(next);
+            next = self.guard.read_page_info(next)?.chain_next();
+        }
+
+        let iter: ItemIter<(Key, Value)> = None.into();
+        let builder = SortedPageBuilder::new(PageTier::Leaf, PageKind::Data).with_iter(iter);
+        let mut txn = self.guard.begin().await;
+        let (new_addr, mut new_page) = txn.alloc_page(builder.size()).await?;
+        builder.build(&mut new_page);
+        new_page.set_epoch(head.epoch());
+        txn.replace_page(id, addr, new_addr, &dealloc_addrs).await
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// The number of buckets in a fill factor histogram.
+///
+/// Each bucket covers 10% of the page size, and the last bucket collects
+/// pages that are larger than the page size.
+pub const FILL_FACTOR_BUCKETS: usize = 11;
+
+/// The shape of a tree.
+#[derive(Clone, Debug, Default)]
+pub struct TreeShape {
+    /// The height of the tree.
+    pub height: usize,
+    /// The shape of each level, from the root to the leaves.
+    pub levels: Vec<LevelShape>,
+}
+
+impl TreeShape {
+    /// Returns the total number of pages in the tree.
+    pub fn num_pages(&self) -> usize {
+        self.levels.iter().map(|l| l.num_pages).sum()
+    }
+}
+
+/// The shape of a level in a tree.
+#[derive(Clone, Debug, Default)]
+pub struct LevelShape {
+    /// The number of pages in this level.
+    pub num_pages: usize,
+    /// The number of delta pages in this level.
+    pub num_delta_pages: usize,
+    /// The total size of pages in this level, including delta pages.
+    pub total_size: usize,
+    /// A histogram of page sizes relative to the page size.
+    ///
+    /// The i-th bucket counts pages that fill [i * 10%, (i + 1) * 10%) of the
+    /// page size.
+    pub fill_factors: [usize; FILL_FACTOR_BUCKETS],
+    /// A histogram of delta chain lengths.
+    ///
+    /// The i-th bucket counts pages with a chain length of i.
+    pub chain_lengths: Vec<usize>,
+}
+
+impl LevelShape {
+    fn observe(&mut self, size: usize, max_size: usize, chain_len: usize) {
+        self.num_pages += 1;
+        self.num_delta_pages += chain_len.saturating_sub(1);
+        self.total_size += size;
+        let bucket = size * 10 / max_size.max(1);
+        self.fill_factors[bucket.min(FILL_FACTOR_BUCKETS - 1)] += 1;
+        if self.chain_lengths.len() <= chain_len {
+            self.chain_lengths.resize(chain_len + 1, 0);
+        }
+        self.chain_lengths[chain_len] += 1;
+    }
+}
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Describes the shape of the tree.
+    ///
+    /// The tree is walked level by level from the root. Pages that are split
+    /// but not reconciled to parents yet are counted in their levels too.
+    pub(crate) async fn describe(&self) -> Result<TreeShape> {
+        let mut shape = TreeShape::default();
+        let mut visited = HashSet::new();
+        let mut level = vec![ROOT_ID];
+        while !level.is_empty() {
+            let mut level_shape = LevelShape::default();
+            let mut next_level = Vec::new();
+            let mut i = 0;
+            while i < level.len() {
+                let id = level[i];
+                i += 1;
+                let addr = self.guard.page_addr(id);
+                if addr == 0 || !visited.insert(id) {
+                    continue;
+                }
+                let head = self.guard.read_page_info(addr)?;
+                let mut max_size = self.tree.options.page_size;
+                if head.tier().is_inner() {
+                    // Adjust the page size for inner pages.
+                    max_size /= 2;
+                }
+
+                let mut size = 0;
+                let mut chain_len = 0;
+                let mut split = None;
+                self.walk_page(
+                    addr,
+                    |_, page, _| {
+                        size += page.size();
+                        chain_len += 1;
+                        if split.is_none() && page.kind() == PageKind::Split {
+                            let (_, index) = split_delta_from_page(page);
+                            split = Some(index.id);
+                        }
+                        false
+                    },
+                    CacheOption::default(),
+                )
+                .await?;
+                level_shape.observe(size, max_size, chain_len);
+
+                // The right page of a pending split is not reachable from the parent yet.
+                if let Some(right) = split {
+                    if level.get(i) != Some(&right) {
+                        level.insert(i, right);
+                    }
+                }
+                if head.tier().is_inner() {
+                    let view = PageView {
+                        id,
+                        addr,
+                        page: head,
+                        range: None,
+                    };
+                    let iter = self.iter_page(&view).await?;
+                    for (_, index) in MergingInnerPageIter::new(iter) {
+                        next_level.push(index.id);
+                    }
+                }
+            }
+            shape.height += 1;
+            shape.levels.push(level_shape);
+            level = next_level;
+        }
+        Ok(shape)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+/// A summary of the live snapshots of a table.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiveSnapshots {
+    /// The number of live snapshots.
+    pub count: usize,
+    /// The LSN of the oldest snapshot, which bounds the entries that can be
+    /// dropped.
+    pub oldest_lsn: Option<u64>,
+    /// The time since the longest held snapshot with the oldest LSN was
+    /// taken.
+    pub oldest_age: Option<Duration>,
+}
+
+/// A list of LSNs of active snapshots.
+///
+/// Entries visible to an active snapshot must be retained, so the oldest
+/// snapshot bounds the LSN that consolidations can drop entries below.
+#[derive(Default)]
+pub(super) struct SnapshotList {
+    /// The times that the snapshots of each LSN are taken.
+    lsns: Mutex<BTreeMap<u64, Vec<Instant>>>,
+}
+
+impl SnapshotList {
+    /// Registers a snapshot with the LSN.
+    ///
+    /// Returns the time that the snapshot is taken, which identifies it on
+    /// release.
+    pub(super) fn register(&self, lsn: u64) -> Instant {
+        let now = Instant::now();
+        self.lsns.lock().entry(lsn).or_default().push(now);
+        now
+    }
+
+    /// Releases a snapshot registered with the LSN at the time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no snapshot is registered with the LSN at the time.
+    pub(super) fn release(&self, lsn: u64, taken_at: Instant) {
+        let mut lsns = self.lsns.lock();
+        let times = lsns.get_mut(&lsn).expect("snapshot must be registered");
+        let index = times
+            .iter()
+            .position(|&t| t == taken_at)
+            .expect("snapshot must be registered");
+        times.swap_remove(index);
+        if times.is_empty() {
+            lsns.remove(&lsn);
+        }
+    }
+
+    /// Returns the LSN of the oldest active snapshot.
+    pub(super) fn oldest(&self) -> Option<u64> {
+        self.lsns.lock().keys().next().copied()
+    }
+
+    /// Returns the number of active snapshots.
+    pub(super) fn len(&self) -> usize {
+        self.lsns.lock().values().map(Vec::len).sum()
+    }
+
+    /// Returns a summary of the active snapshots.
+    pub(super) fn live(&self) -> LiveSnapshots {
+        let lsns = self.lsns.lock();
+        let oldest = lsns.iter().next();
+        LiveSnapshots {
+            count: lsns.values().map(Vec::len).sum(),
+            oldest_lsn: oldest.map(|(&lsn, _)| lsn),
+            oldest_age: oldest
+                .and_then(|(_, times)| times.iter().min())
+                .map(Instant::elapsed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_release() {
+        let list = SnapshotList::default();
+        assert_eq!(list.oldest(), None);
+        let t3 = list.register(3);
+        let t1 = list.register(1);
+        let t2 = list.register(1);
+        assert_eq!(list.oldest(), Some(1));
+        assert_eq!(list.len(), 3);
+        list.release(1, t1);
+        assert_eq!(list.oldest(), Some(1));
+        list.release(1, t2);
+        assert_eq!(list.oldest(), Some(3));
+        list.release(3, t3);
+        assert_eq!(list.oldest(), None);
+        assert_eq!(list.len(), 0);
+    }
+
+    #[test]
+    fn live() {
+        let list = SnapshotList::default();
+        assert_eq!(list.live(), LiveSnapshots::default());
+        let t2 = list.register(2);
+        let t1 = list.register(1);
+        let live = list.live();
+        assert_eq!(live.count, 2);
+        assert_eq!(live.oldest_lsn, Some(1));
+        assert!(live.oldest_age.unwrap() <= t1.elapsed());
+        list.release(1, t1);
+        let live = list.live();
+        assert_eq!(live.oldest_lsn, Some(2));
+        assert!(live.oldest_age.unwrap() >= t1.duration_since(t2));
+    }
+}
@@ -1,6 +1,9 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
-use crate::util::atomic::Counter;
+use crate::{
+    page::{Key, Value},
+    util::atomic::{Counter, MaxGauge},
+};
 
 /// Statistics of a tree.
 #[derive(Clone, Debug, Default)]
@@ -9,14 +12,43 @@ pub struct TreeStats {
     pub success: TxnStats,
     /// Statistics of conflicted transactions.
     pub conflict: TxnStats,
+    /// Statistics of delta chains observed by lookups.
+    pub chain: ChainStats,
+    /// The height of the tree observed by lookups, including the leaf level.
+    pub height: u64,
+    /// The number of failed CAS installs on the page table.
+    pub failed_cas: u64,
+    /// The number of hot pages restructured proactively.
+    pub hot_pages: u64,
+    /// The number of operations slower than the slow operation threshold.
+    pub slow_ops: u64,
+    /// Statistics of write retries.
+    pub write_retries: RetryStats,
+    /// Statistics of leaf pages built by consolidations.
+    pub leaf: LeafStats,
+    /// Statistics of operation latencies.
+    pub latency: LatencyStats,
+    /// Statistics of contention by the type of operations.
+    pub contention: ContentionStats,
 }
 
 impl TreeStats {
     /// Sub other stats to produce an new stats.
+    ///
+    /// Gauges like the tree height are taken from `self`.
     pub fn sub(&self, o: &TreeStats) -> TreeStats {
         Self {
             success: self.success.sub(&o.success),
             conflict: self.conflict.sub(&o.conflict),
+            chain: self.chain.sub(&o.chain),
+            height: self.height,
+            failed_cas: self.failed_cas.wrapping_sub(o.failed_cas),
+            hot_pages: self.hot_pages.wrapping_sub(o.hot_pages),
+            slow_ops: self.slow_ops.wrapping_sub(o.slow_ops),
+            write_retries: self.write_retries.sub(&o.write_retries),
+            leaf: self.leaf.sub(&o.leaf),
+            latency: self.latency.sub(&o.latency),
+            contention: self.contention.sub(&o.contention),
         }
     }
 }
@@ -52,7 +84,77 @@ impl Display for TreeStats {
             self.conflict.split_page,
             self.conflict.reconcile_page,
             self.conflict.consolidate_page
-        )
+        )?;
+        writeln!(
+            f,
+            "TreeStats_shape: height: {}, \
+                failed_cas: {}, \
+                hot_pages: {}, \
+                slow_ops: {}, \
+                avg_chain_len: {:.2}, \
+                max_chain_len: {}",
+            self.height,
+            self.failed_cas,
+            self.hot_pages,
+            self.slow_ops,
+            self.chain.avg_len(),
+            self.chain.max_len
+        )?;
+        writeln!(
+            f,
+            "TreeStats_write_retries: total: {}, histogram: {:?}",
+            self.write_retries.total, self.write_retries.histogram
+        )?;
+        writeln!(
+            f,
+            "TreeStats_leaf: count: {}, \
+                avg_entries: {:.2}, \
+                avg_key_size: {:.2}, \
+                avg_value_size: {:.2}",
+            self.leaf.num_entries.count,
+            self.leaf.num_entries.avg(),
+            self.leaf.key_size.avg(),
+            self.leaf.value_size.avg()
+        )?;
+        for (name, h) in [
+            ("get", &self.latency.get),
+            ("put", &self.latency.put),
+            ("delete", &self.latency.delete),
+            ("scan", &self.latency.scan),
+        ] {
+            writeln!(
+                f,
+                "TreeStats_latency_{name}: count: {}, \
+                    avg_us: {:.2}, \
+                    p50_us: {}, \
+                    p99_us: {}, \
+                    p999_us: {}",
+                h.count,
+                h.avg(),
+                h.percentile(0.5),
+                h.percentile(0.99),
+                h.percentile(0.999)
+            )?;
+        }
+        for (name, c) in [
+            ("get", &self.contention.get),
+            ("put", &self.contention.put),
+            ("delete", &self.contention.delete),
+            ("scan", &self.contention.scan),
+        ] {
+            writeln!(
+                f,
+                "TreeStats_contention_{name}: count: {}, \
+                    failed_cas: {}, \
+                    retries: {}, \
+                    avg_pin_us: {:.2}",
+                c.count,
+                c.failed_cas,
+                c.retries,
+                c.avg_pin_micros()
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -60,6 +162,15 @@ impl Display for TreeStats {
 pub(super) struct AtomicStats {
     pub(super) success: AtomicTxnStats,
     pub(super) conflict: AtomicTxnStats,
+    pub(super) chain: AtomicChainStats,
+    pub(super) height: MaxGauge,
+    pub(super) failed_cas: Counter,
+    pub(super) hot_pages: Counter,
+    pub(super) slow_ops: Counter,
+    pub(super) write_retries: AtomicRetryStats,
+    pub(super) leaf: AtomicLeafStats,
+    pub(super) latency: AtomicLatencyStats,
+    pub(super) contention: AtomicContentionStats,
 }
 
 impl AtomicStats {
@@ -67,6 +178,76 @@ impl AtomicStats {
         TreeStats {
             success: self.success.snapshot(),
             conflict: self.conflict.snapshot(),
+            chain: self.chain.snapshot(),
+            height: self.height.get(),
+            failed_cas: self.failed_cas.get(),
+            hot_pages: self.hot_pages.get(),
+            slow_ops: self.slow_ops.get(),
+            write_retries: self.write_retries.snapshot(),
+            leaf: self.leaf.snapshot(),
+            latency: self.latency.snapshot(),
+            contention: self.contention.snapshot(),
+        }
+    }
+}
+
+/// Statistics of delta chains.
+#[derive(Clone, Debug, Default)]
+pub struct ChainStats {
+    /// The number of chains observed.
+    pub count: u64,
+    /// The total length of chains observed.
+    pub total_len: u64,
+    /// The max length of chains observed.
+    pub max_len: u64,
+}
+
+impl ChainStats {
+    /// Returns the average length of chains observed.
+    pub fn avg_len(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_len as f64 / self.count as f64
+        }
+    }
+
+    pub(super) fn sub(&self, o: &ChainStats) -> ChainStats {
+        ChainStats {
+            count: self.count.wrapping_sub(o.count),
+            total_len: self.total_len.wrapping_sub(o.total_len),
+            max_len: self.max_len,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicChainStats {
+    count: Counter,
+    total_len: Counter,
+    max_len: MaxGauge,
+}
+
+impl AtomicChainStats {
+    pub(super) fn observe(&self, len: u8) {
+        self.count.inc();
+        self.total_len.add(len as u64);
+        self.max_len.update(len as u64);
+    }
+
+    pub(super) fn count(&self) -> u64 {
+        self.count.get()
+    }
+
+    pub(super) fn total_len(&self) -> u64 {
+        self.total_len.get()
+    }
+
+    pub(super) fn snapshot(&self) -> ChainStats {
+        ChainStats {
+            count: self.count.get(),
+            total_len: self.total_len.get(),
+            max_len: self.max_len.get(),
         }
     }
 }
@@ -125,3 +306,412 @@ impl TxnStats {
         }
     }
 }
+
+/// The number of buckets in a retry histogram.
+pub const RETRY_BUCKETS: usize = 8;
+
+/// Statistics of retries of conflicted operations.
+#[derive(Clone, Debug, Default)]
+pub struct RetryStats {
+    /// The total number of retries.
+    pub total: u64,
+    /// A histogram of retries per operation.
+    ///
+    /// The first bucket counts operations without retries, and the i-th bucket
+    /// counts operations retried [2^(i-1), 2^i) times. The last bucket also
+    /// counts operations retried more times.
+    pub histogram: [u64; RETRY_BUCKETS],
+}
+
+impl RetryStats {
+    pub(super) fn sub(&self, o: &RetryStats) -> RetryStats {
+        let mut histogram = self.histogram;
+        for (a, b) in histogram.iter_mut().zip(o.histogram) {
+            *a = a.wrapping_sub(b);
+        }
+        RetryStats {
+            total: self.total.wrapping_sub(o.total),
+            histogram,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicRetryStats {
+    total: Counter,
+    histogram: [Counter; RETRY_BUCKETS],
+}
+
+impl AtomicRetryStats {
+    pub(super) fn observe(&self, retries: u32) {
+        let bucket = (u32::BITS - retries.leading_zeros()) as usize;
+        self.total.add(retries as u64);
+        self.histogram[bucket.min(RETRY_BUCKETS - 1)].inc();
+    }
+
+    pub(super) fn snapshot(&self) -> RetryStats {
+        let mut histogram = [0; RETRY_BUCKETS];
+        for (a, b) in histogram.iter_mut().zip(&self.histogram) {
+            *a = b.get();
+        }
+        RetryStats {
+            total: self.total.get(),
+            histogram,
+        }
+    }
+}
+
+/// The number of buckets in a histogram.
+pub const HISTOGRAM_BUCKETS: usize = 24;
+
+/// A histogram of sizes or latencies with log2 buckets.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    /// The number of observations.
+    pub count: u64,
+    /// The sum of observations.
+    pub sum: u64,
+    /// The first bucket counts zero observations, and the i-th bucket counts
+    /// observations in [2^(i-1), 2^i). The last bucket also counts larger
+    /// observations.
+    pub buckets: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl Histogram {
+    /// Returns the average of observations.
+    pub fn avg(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.count as f64
+        }
+    }
+
+    /// Returns an upper bound of the given percentile of observations.
+    ///
+    /// The result is the exclusive upper bound of the bucket that contains the
+    /// percentile, so it overestimates the percentile by at most 2x. `q` is
+    /// clamped to [0, 1], and 0 is returned if there are no observations.
+    pub fn percentile(&self, q: f64) -> u64 {
+        let total: u64 = self.buckets.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+        let rank = ((total as f64 * q.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return if i == 0 { 0 } else { 1 << i };
+            }
+        }
+        1 << (HISTOGRAM_BUCKETS - 1)
+    }
+
+    pub(super) fn sub(&self, o: &Histogram) -> Histogram {
+        let mut buckets = self.buckets;
+        for (a, b) in buckets.iter_mut().zip(o.buckets) {
+            *a = a.wrapping_sub(b);
+        }
+        Histogram {
+            count: self.count.wrapping_sub(o.count),
+            sum: self.sum.wrapping_sub(o.sum),
+            buckets,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicHistogram {
+    count: Counter,
+    sum: Counter,
+    buckets: [Counter; HISTOGRAM_BUCKETS],
+}
+
+impl AtomicHistogram {
+    pub(super) fn observe(&self, size: usize) {
+        let bucket = (usize::BITS - size.leading_zeros()) as usize;
+        self.count.inc();
+        self.sum.add(size as u64);
+        self.buckets[bucket.min(HISTOGRAM_BUCKETS - 1)].inc();
+    }
+
+    pub(super) fn sum(&self) -> u64 {
+        self.sum.get()
+    }
+
+    pub(super) fn snapshot(&self) -> Histogram {
+        let mut buckets = [0; HISTOGRAM_BUCKETS];
+        for (a, b) in buckets.iter_mut().zip(&self.buckets) {
+            *a = b.get();
+        }
+        Histogram {
+            count: self.count.get(),
+            sum: self.sum.get(),
+            buckets,
+        }
+    }
+}
+
+/// Statistics of leaf pages.
+///
+/// These are collected from the leaf pages built by consolidations, which
+/// reflect the data distribution of a workload and help to choose a proper
+/// page size.
+#[derive(Clone, Debug, Default)]
+pub struct LeafStats {
+    /// A histogram of key sizes in leaf pages.
+    pub key_size: Histogram,
+    /// A histogram of value sizes in leaf pages, excluding tombstones.
+    pub value_size: Histogram,
+    /// A histogram of the number of entries per leaf page.
+    pub num_entries: Histogram,
+}
+
+impl LeafStats {
+    pub(super) fn sub(&self, o: &LeafStats) -> LeafStats {
+        LeafStats {
+            key_size: self.key_size.sub(&o.key_size),
+            value_size: self.value_size.sub(&o.value_size),
+            num_entries: self.num_entries.sub(&o.num_entries),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicLeafStats {
+    key_size: AtomicHistogram,
+    value_size: AtomicHistogram,
+    num_entries: AtomicHistogram,
+}
+
+impl AtomicLeafStats {
+    pub(super) fn observe<'a, I>(&self, iter: I)
+    where
+        I: Iterator<Item = (Key<'a>, Value<'a>)>,
+    {
+        let mut num_entries = 0;
+        for (k, v) in iter {
+            num_entries += 1;
+            self.key_size.observe(k.raw.len());
+            if let Value::Put(v) = v {
+                self.value_size.observe(v.len());
+            }
+        }
+        self.num_entries.observe(num_entries);
+    }
+
+    /// Returns the total number of entries in the observed pages.
+    pub(super) fn num_entries_sum(&self) -> u64 {
+        self.num_entries.sum()
+    }
+
+    pub(super) fn snapshot(&self) -> LeafStats {
+        LeafStats {
+            key_size: self.key_size.snapshot(),
+            value_size: self.value_size.snapshot(),
+            num_entries: self.num_entries.snapshot(),
+        }
+    }
+}
+
+/// Statistics of operation latencies in microseconds.
+///
+/// Latencies are measured inside the tree, from the start of an operation to
+/// its completion, including retries of conflicted writes.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyStats {
+    /// A histogram of point lookups, including each call of multi gets.
+    pub get: Histogram,
+    /// A histogram of puts.
+    pub put: Histogram,
+    /// A histogram of deletes.
+    pub delete: Histogram,
+    /// A histogram of reading the next page of range scans.
+    pub scan: Histogram,
+}
+
+impl LatencyStats {
+    pub(super) fn sub(&self, o: &LatencyStats) -> LatencyStats {
+        LatencyStats {
+            get: self.get.sub(&o.get),
+            put: self.put.sub(&o.put),
+            delete: self.delete.sub(&o.delete),
+            scan: self.scan.sub(&o.scan),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicLatencyStats {
+    pub(super) get: AtomicHistogram,
+    pub(super) put: AtomicHistogram,
+    pub(super) delete: AtomicHistogram,
+    pub(super) scan: AtomicHistogram,
+}
+
+impl AtomicLatencyStats {
+    pub(super) fn snapshot(&self) -> LatencyStats {
+        LatencyStats {
+            get: self.get.snapshot(),
+            put: self.put.snapshot(),
+            delete: self.delete.snapshot(),
+            scan: self.scan.snapshot(),
+        }
+    }
+
+    pub(super) fn by_kind(&self, kind: OpKind) -> &AtomicHistogram {
+        match kind {
+            OpKind::Get => &self.get,
+            OpKind::Put => &self.put,
+            OpKind::Delete => &self.delete,
+            OpKind::Scan => &self.scan,
+        }
+    }
+}
+
+/// The types of operations that stats are recorded by.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum OpKind {
+    Get,
+    Put,
+    Delete,
+    Scan,
+}
+
+/// Statistics of contention, by the type of operations.
+#[derive(Clone, Debug, Default)]
+pub struct ContentionStats {
+    /// Statistics of point lookups, including multi gets.
+    pub get: OpContentionStats,
+    /// Statistics of puts.
+    pub put: OpContentionStats,
+    /// Statistics of deletes.
+    pub delete: OpContentionStats,
+    /// Statistics of range scans.
+    pub scan: OpContentionStats,
+}
+
+impl ContentionStats {
+    pub(super) fn sub(&self, o: &ContentionStats) -> ContentionStats {
+        ContentionStats {
+            get: self.get.sub(&o.get),
+            put: self.put.sub(&o.put),
+            delete: self.delete.sub(&o.delete),
+            scan: self.scan.sub(&o.scan),
+        }
+    }
+}
+
+/// Statistics of contention of a type of operations.
+#[derive(Clone, Debug, Default)]
+pub struct OpContentionStats {
+    /// The number of operations.
+    pub count: u64,
+    /// The number of failed CAS on the page table.
+    pub failed_cas: u64,
+    /// The number of retries because of conflicts with other operations.
+    pub retries: u64,
+    /// The total time that the operations pin the page store.
+    ///
+    /// The pinned versions of the page store and their write buffers can not
+    /// be released until the operations finish. Operations through the same
+    /// guard are each attributed the pin since the last one.
+    pub pin_micros: u64,
+}
+
+impl OpContentionStats {
+    /// Returns the average time of an operation pinning the page store.
+    pub fn avg_pin_micros(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.pin_micros as f64 / self.count as f64
+        }
+    }
+
+    fn sub(&self, o: &OpContentionStats) -> OpContentionStats {
+        OpContentionStats {
+            count: self.count.wrapping_sub(o.count),
+            failed_cas: self.failed_cas.wrapping_sub(o.failed_cas),
+            retries: self.retries.wrapping_sub(o.retries),
+            pin_micros: self.pin_micros.wrapping_sub(o.pin_micros),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtomicContentionStats {
+    get: AtomicOpContentionStats,
+    put: AtomicOpContentionStats,
+    delete: AtomicOpContentionStats,
+    scan: AtomicOpContentionStats,
+}
+
+impl AtomicContentionStats {
+    /// Records the contention of a finished operation.
+    pub(super) fn observe(&self, kind: OpKind, op: &AtomicOpStats, pin: Duration) {
+        let stats = match kind {
+            OpKind::Get => &self.get,
+            OpKind::Put => &self.put,
+            OpKind::Delete => &self.delete,
+            OpKind::Scan => &self.scan,
+        };
+        stats.count.inc();
+        stats.failed_cas.add(op.failed_cas.get());
+        stats.retries.add(op.retries.get());
+        stats.pin_micros.add(pin.as_micros() as u64);
+    }
+
+    fn snapshot(&self) -> ContentionStats {
+        ContentionStats {
+            get: self.get.snapshot(),
+            put: self.put.snapshot(),
+            delete: self.delete.snapshot(),
+            scan: self.scan.snapshot(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AtomicOpContentionStats {
+    count: Counter,
+    failed_cas: Counter,
+    retries: Counter,
+    pin_micros: Counter,
+}
+
+impl AtomicOpContentionStats {
+    fn snapshot(&self) -> OpContentionStats {
+        OpContentionStats {
+            count: self.count.get(),
+            failed_cas: self.failed_cas.get(),
+            retries: self.retries.get(),
+            pin_micros: self.pin_micros.get(),
+        }
+    }
+}
+
+/// Counters of the work done by the current operation of a transaction.
+///
+/// These are used to explain slow operations and to attribute contention to
+/// the types of operations. They are reset when an operation starts, since a
+/// transaction may run many operations.
+#[derive(Default)]
+pub(super) struct AtomicOpStats {
+    pub(super) pages_visited: Counter,
+    pub(super) deltas_read: Counter,
+    pub(super) max_chain_len: MaxGauge,
+    pub(super) failed_cas: Counter,
+    pub(super) retries: Counter,
+}
+
+impl AtomicOpStats {
+    pub(super) fn reset(&self) {
+        self.pages_visited.reset();
+        self.deltas_read.reset();
+        self.max_chain_len.reset();
+        self.failed_cas.reset();
+        self.retries.reset();
+    }
+}
@@ -0,0 +1,126 @@
+//! Encodings of keys with user-defined timestamps.
+//!
+//! A timestamped key is encoded as the escaped user key followed by the
+//! bitwise-inverted timestamp in big-endian. Zero bytes in the user key are
+//! escaped as `[0, 0xFF]` and the user key is terminated by `[0, 0]`, so that
+//! encoded keys are ordered by the user key ascendingly and then the timestamp
+//! descendingly. This keeps range scans over timestamped keys in the user key
+//! order.
+
+use std::mem;
+
+const ESCAPE: u8 = 0xFF;
+const TERMINATOR: u8 = 0;
+
+const TOMBSTONE: u8 = 0;
+const PUT: u8 = 1;
+
+/// Encodes a user key with a timestamp.
+///
+/// This can be used to build the bounds of range scans over timestamped keys.
+pub fn encode_timestamped_key(key: &[u8], ts: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(key.len() + 2 + mem::size_of::<u64>());
+    for &b in key {
+        buf.push(b);
+        if b == 0 {
+            buf.push(ESCAPE);
+        }
+    }
+    buf.extend_from_slice(&[0, TERMINATOR]);
+    buf.extend_from_slice(&(!ts).to_be_bytes());
+    buf
+}
+
+/// Decodes a user key and its timestamp from a key written by
+/// [`put_at`] or [`delete_at`].
+///
+/// Returns [`None`] if the key is not encoded by [`encode_timestamped_key`].
+///
+/// [`put_at`]: crate::raw::Table::put_at
+/// [`delete_at`]: crate::raw::Table::delete_at
+pub fn decode_timestamped_key(buf: &[u8]) -> Option<(Vec<u8>, u64)> {
+    let mut key = Vec::with_capacity(buf.len());
+    let mut i = 0;
+    loop {
+        match *buf.get(i)? {
+            0 => match *buf.get(i + 1)? {
+                ESCAPE => key.push(0),
+                TERMINATOR => break,
+                _ => return None,
+            },
+            b => {
+                key.push(b);
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    let ts = buf[i + 2..].try_into().ok().map(u64::from_be_bytes)?;
+    Some((key, !ts))
+}
+
+/// Encodes a value of a timestamped key.
+///
+/// Deletes are encoded as tombstones, so that they hide older versions of the
+/// key.
+pub(crate) fn encode_timestamped_value(value: Option<&[u8]>) -> Vec<u8> {
+    match value {
+        Some(value) => {
+            let mut buf = Vec::with_capacity(value.len() + 1);
+            buf.push(PUT);
+            buf.extend_from_slice(value);
+            buf
+        }
+        None => vec![TOMBSTONE],
+    }
+}
+
+/// Decodes a value of a timestamped key returned by range scans.
+///
+/// Returns [`None`] if the value is a tombstone.
+pub fn decode_timestamped_value(buf: &[u8]) -> Option<&[u8]> {
+    match buf.split_first() {
+        Some((&PUT, value)) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_codec() {
+        let keys: [(&[u8], u64); 6] = [
+            (b"", 1),
+            (b"a", 3),
+            (b"a", 1),
+            (b"a\0", 2),
+            (b"a\0b", 0),
+            (b"ab", u64::MAX),
+        ];
+        let encoded: Vec<_> = keys
+            .iter()
+            .map(|(k, ts)| encode_timestamped_key(k, *ts))
+            .collect();
+        for (buf, (key, ts)) in encoded.iter().zip(keys) {
+            assert_eq!(decode_timestamped_key(buf), Some((key.to_vec(), ts)));
+        }
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(decode_timestamped_key(b"a"), None);
+        assert_eq!(decode_timestamped_key(b"a\0\0"), None);
+    }
+
+    #[test]
+    fn value_codec() {
+        assert_eq!(
+            decode_timestamped_value(&encode_timestamped_value(Some(b"v"))),
+            Some(b"v".as_slice())
+        );
+        assert_eq!(
+            decode_timestamped_value(&encode_timestamped_value(None)),
+            None
+        );
+    }
+}
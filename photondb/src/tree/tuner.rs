@@ -0,0 +1,217 @@
+use std::cell::Cell;
+
+use log::info;
+use parking_lot::Mutex;
+
+use super::{options::DynamicOptions, stats::AtomicStats, Options};
+
+/// One of every `TICK_INTERVAL` lookups on each thread ticks the tuner.
+const TICK_INTERVAL: usize = 256;
+
+/// The number of lookups in a window between two adjustments.
+const WINDOW_LOOKUPS: u64 = 1 << 14;
+
+/// The cost of reading a delta page in a lookup, relative to the cost of
+/// copying an entry in a consolidation.
+const DELTA_READ_COST: u64 = 8;
+
+/// The thresholds are adjusted only if one cost exceeds the other by this
+/// ratio, so that a balanced workload doesn't flip them back and forth.
+const IMBALANCE_RATIO: u64 = 2;
+
+/// The step to adjust the consolidation fill factor.
+const FILL_FACTOR_STEP: f64 = 0.05;
+
+/// Adjusts the consolidation thresholds of a tree to its workload.
+///
+/// The tuner weighs the read amplification of lookups, measured by the delta
+/// pages they walk through, against the cost of consolidations, measured by
+/// the entries of the pages they rebuild. If reads dominate, pages are
+/// consolidated after shorter chains; if consolidations dominate, longer
+/// chains are allowed. Once the chain length hits a bound, the fill factor of
+/// consolidated pages is adjusted instead, since smaller pages are cheaper to
+/// consolidate.
+pub(super) struct ConsolidationTuner {
+    enabled: bool,
+    chain_length: (usize, usize),
+    fill_factor: (f64, f64),
+    last: Mutex<Option<Sample>>,
+}
+
+/// Cumulative counters of a tree.
+#[derive(Clone, Copy, Debug, Default)]
+struct Sample {
+    lookups: u64,
+    delta_reads: u64,
+    consolidated_entries: u64,
+}
+
+impl ConsolidationTuner {
+    pub(super) fn new(options: &Options) -> Self {
+        Self {
+            enabled: options.auto_tune_consolidation,
+            chain_length: (options.min_page_chain_length, options.max_page_chain_length),
+            fill_factor: (
+                options.min_consolidation_fill_factor,
+                options.consolidation_fill_factor,
+            ),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Clamps the initial chain length into the bounds of the tuner.
+    pub(super) fn init(&self, options: &DynamicOptions) {
+        if self.enabled {
+            let (min, max) = self.chain_length;
+            options.set_page_chain_length(options.page_chain_length().clamp(min, max));
+        }
+    }
+
+    /// Records a lookup, and adjusts the thresholds in `options` once a
+    /// window of lookups has passed.
+    pub(super) fn tick(&self, stats: &AtomicStats, options: &DynamicOptions) {
+        thread_local! {
+            static LOOKUPS: Cell<usize> = Cell::new(0);
+        }
+        if !self.enabled {
+            return;
+        }
+        let sampled = LOOKUPS.with(|lookups| {
+            let n = lookups.get().wrapping_add(1);
+            lookups.set(n);
+            n % TICK_INTERVAL == 0
+        });
+        if !sampled {
+            return;
+        }
+        // Another thread is adjusting the thresholds.
+        let Some(mut last) = self.last.try_lock() else {
+            return;
+        };
+        let now = Sample {
+            lookups: stats.chain.count(),
+            delta_reads: stats.chain.total_len(),
+            consolidated_entries: stats.leaf.num_entries_sum(),
+        };
+        match *last {
+            Some(prev) if now.lookups.wrapping_sub(prev.lookups) < WINDOW_LOOKUPS => {}
+            Some(prev) => {
+                self.adjust(&now.sub(&prev), options);
+                *last = Some(now);
+            }
+            None => *last = Some(now),
+        }
+    }
+
+    /// Adjusts the thresholds by the counters of a window.
+    fn adjust(&self, window: &Sample, options: &DynamicOptions) {
+        let read_cost = window.delta_reads.saturating_mul(DELTA_READ_COST);
+        let consolidation_cost = window.consolidated_entries;
+        let chain_length = options.page_chain_length();
+        let fill_factor = options.consolidation_fill_factor();
+        let (min_len, max_len) = self.chain_length;
+        let (min_fill, max_fill) = self.fill_factor;
+        if read_cost > consolidation_cost.saturating_mul(IMBALANCE_RATIO) {
+            if chain_length > min_len {
+                options.set_page_chain_length(chain_length - 1);
+            } else if fill_factor < max_fill {
+                options
+                    .set_consolidation_fill_factor((fill_factor + FILL_FACTOR_STEP).min(max_fill));
+            } else {
+                return;
+            }
+        } else if consolidation_cost > read_cost.saturating_mul(IMBALANCE_RATIO) {
+            if chain_length < max_len {
+                options.set_page_chain_length(chain_length + 1);
+            } else if fill_factor > min_fill {
+                options
+                    .set_consolidation_fill_factor((fill_factor - FILL_FACTOR_STEP).max(min_fill));
+            } else {
+                return;
+            }
+        } else {
+            return;
+        }
+        info!(
+            "Tune consolidation with read cost {read_cost} and consolidation cost {consolidation_cost}: \
+             page_chain_length {chain_length} -> {}, consolidation_fill_factor {fill_factor:.2} -> {:.2}",
+            options.page_chain_length(),
+            options.consolidation_fill_factor(),
+        );
+    }
+}
+
+impl Sample {
+    fn sub(&self, o: &Sample) -> Sample {
+        Sample {
+            lookups: self.lookups.wrapping_sub(o.lookups),
+            delta_reads: self.delta_reads.wrapping_sub(o.delta_reads),
+            consolidated_entries: self
+                .consolidated_entries
+                .wrapping_sub(o.consolidated_entries),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuner() -> (ConsolidationTuner, DynamicOptions) {
+        let options = Options {
+            auto_tune_consolidation: true,
+            min_page_chain_length: 2,
+            max_page_chain_length: 4,
+            min_consolidation_fill_factor: 0.8,
+            consolidation_fill_factor: 0.9,
+            page_chain_length: 8,
+            ..Default::default()
+        };
+        let tuner = ConsolidationTuner::new(&options);
+        let dynamic = DynamicOptions::new(&options);
+        tuner.init(&dynamic);
+        (tuner, dynamic)
+    }
+
+    fn window(delta_reads: u64, consolidated_entries: u64) -> Sample {
+        Sample {
+            lookups: WINDOW_LOOKUPS,
+            delta_reads,
+            consolidated_entries,
+        }
+    }
+
+    #[test]
+    fn adjust() {
+        let (tuner, options) = tuner();
+        assert_eq!(options.page_chain_length(), 4);
+
+        // Consolidations dominate, so chains get longer up to the bound, and
+        // then consolidated pages get smaller.
+        let write_heavy = window(100, 10000);
+        tuner.adjust(&write_heavy, &options);
+        assert_eq!(options.page_chain_length(), 4);
+        assert!((options.consolidation_fill_factor() - 0.85).abs() < 1e-9);
+        tuner.adjust(&write_heavy, &options);
+        tuner.adjust(&write_heavy, &options);
+        assert_eq!(options.consolidation_fill_factor(), 0.8);
+
+        // A balanced workload changes nothing.
+        tuner.adjust(&window(1000, 10000), &options);
+        assert_eq!(options.page_chain_length(), 4);
+        assert_eq!(options.consolidation_fill_factor(), 0.8);
+
+        // Reads dominate, so chains get shorter down to the bound, and then
+        // consolidated pages get larger.
+        let read_heavy = window(10000, 100);
+        tuner.adjust(&read_heavy, &options);
+        tuner.adjust(&read_heavy, &options);
+        assert_eq!(options.page_chain_length(), 2);
+        assert_eq!(options.consolidation_fill_factor(), 0.8);
+        for _ in 0..3 {
+            tuner.adjust(&read_heavy, &options);
+        }
+        assert_eq!(options.page_chain_length(), 2);
+        assert_eq!(options.consolidation_fill_factor(), 0.9);
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// A report produced by verifying a tree.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    /// The height of the tree.
+    pub height: usize,
+    /// The number of leaf pages.
+    pub num_leaf_pages: usize,
+    /// The number of inner pages.
+    pub num_inner_pages: usize,
+    /// The number of delta pages, including split deltas.
+    pub num_delta_pages: usize,
+    /// The number of structure modifications that are not reconciled to
+    /// parents yet.
+    ///
+    /// These are expected if the tree is verified under concurrent writes.
+    pub num_pending_smos: usize,
+    /// The number of pages in the page table that are not reachable from the
+    /// root.
+    ///
+    /// These are expected if the tree is verified under concurrent writes,
+    /// since pages are mapped before they are linked to the tree.
+    pub num_unreachable_pages: usize,
+    /// The violations found in the tree.
+    pub violations: Vec<Violation>,
+}
+
+impl VerifyReport {
+    /// Returns true if no violation is found.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn violate(&mut self, page_id: u64, kind: ViolationKind) {
+        self.violations.push(Violation { page_id, kind });
+    }
+}
+
+/// A violation of the tree invariants.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// The id of the page that violates the invariant.
+    pub page_id: u64,
+    /// The kind of the violation.
+    pub kind: ViolationKind,
+}
+
+/// Kinds of tree invariant violations.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The page id is referenced but not mapped in the page table.
+    UnmappedPage,
+    /// The page id is referenced more than once.
+    DuplicatedPage,
+    /// Items in a page are not strictly ordered.
+    UnorderedItems,
+    /// An item is out of the key range of its page.
+    OutOfRange,
+    /// The page range doesn't start where the previous page range ends.
+    RangeGap,
+    /// The chain length recorded in a page is inconsistent with the chain.
+    ChainLength,
+    /// The epochs along a chain are not decreasing.
+    ChainEpoch,
+    /// A page has a different tier from its chain or its siblings.
+    TierMismatch,
+}
+
+/// A page to verify and the range it is expected to cover.
+struct VerifyNode {
+    id: u64,
+    epoch: Option<u64>,
+    start: Vec<u8>,
+    end: Option<Vec<u8>>,
+}
+
+/// Information of a verified page.
+struct VerifyPage {
+    tier: PageTier,
+    split: Option<(Vec<u8>, Index)>,
+    children: Vec<(Vec<u8>, Index)>,
+}
+
+impl<'a, E: Env> TreeTxn<'a, E> {
+    /// Verifies the invariants of the tree.
+    ///
+    /// The tree is walked level by level from the root. Violations are
+    /// collected into the returned report instead of aborting the
+    /// verification.
+    pub(crate) async fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let mut visited = HashSet::new();
+        let mut level = vec![VerifyNode {
+            id: ROOT_ID,
+            epoch: None,
+            start: Vec::new(),
+            end: None,
+        }];
+        while !level.is_empty() {
+            report.height += 1;
+            let mut level_tier = None;
+            let mut next_level = Vec::new();
+            let mut i = 0;
+            while i < level.len() {
+                let node = &level[i];
+                if !visited.insert(node.id) {
+                    report.violate(node.id, ViolationKind::DuplicatedPage);
+                    level.remove(i);
+                    continue;
+                }
+                let Some(page) = self.verify_page(node, &mut report).await? else {
+                    i += 1;
+                    continue;
+                };
+                if *level_tier.get_or_insert(page.tier) != page.tier {
+                    report.violate(node.id, ViolationKind::TierMismatch);
+                }
+                match page.tier {
+                    PageTier::Leaf => report.num_leaf_pages += 1,
+                    PageTier::Inner => report.num_inner_pages += 1,
+                }
+
+                // The end of this page is the split key if there is a pending split.
+                let end = page
+                    .split
+                    .as_ref()
+                    .map(|(split_key, _)| split_key.clone())
+                    .or_else(|| node.end.clone());
+                let mut children = page.children.into_iter().peekable();
+                while let Some((start, index)) = children.next() {
+                    let end = children
+                        .peek()
+                        .map(|(k, _)| k.clone())
+                        .or_else(|| end.clone());
+                    next_level.push(VerifyNode {
+                        id: index.id,
+                        epoch: Some(index.epoch),
+                        start,
+                        end,
+                    });
+                }
+
+                // The right page of a pending split is not reachable from the parent yet.
+                if let Some((split_key, index)) = page.split {
+                    let reconciled = level.get(i + 1).map(|n| n.id) == Some(index.id);
+                    if !reconciled {
+                        report.num_pending_smos += 1;
+                        let right = VerifyNode {
+                            id: index.id,
+                            epoch: Some(index.epoch),
+                            start: split_key.clone(),
+                            end: level[i].end.clone(),
+                        };
+                        level.insert(i + 1, right);
+                    }
+                    level[i].end = Some(split_key);
+                }
+                i += 1;
+            }
+
+            // Pages in the same level must cover the whole key space.
+            for pair in level.windows(2) {
+                if pair[0].end.as_ref() != Some(&pair[1].start) {
+                    report.violate(pair[1].id, ViolationKind::RangeGap);
+                }
+            }
+            level = next_level;
+        }
+
+        for page in self.guard.iter_pages() {
+            let (id, ..) = page?;
+            if !visited.contains(&id) {
+                report.num_unreachable_pages += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Verifies a single page and its chain.
+    ///
+    /// Returns [`None`] if the page can not be verified further.
+    async fn verify_page(
+        &self,
+        node: &VerifyNode,
+        report: &mut VerifyReport,
+    ) -> Result<Option<VerifyPage>> {
+        let addr = self.guard.page_addr(node.id);
+        if addr == 0 {
+            report.violate(node.id, ViolationKind::UnmappedPage);
+            return Ok(None);
+        }
+        let head = self.guard.read_page_info(addr)?;
+        if node.epoch.map_or(false, |epoch| epoch != head.epoch()) {
+            report.num_pending_smos += 1;
+        }
+
+        let mut kinds = Vec::new();
+        let mut split: Option<(Vec<u8>, Index)> = None;
+        let mut expect_len = head.chain_len();
+        let mut last_epoch = head.epoch();
+        let mut num_pages = 0;
+        self.walk_page(
+            addr,
+            |_, page, _| {
+                num_pages += 1;
+                if page.tier() != head.tier() {
+                    kinds.push(ViolationKind::TierMismatch);
+                }
+                // The chain length saturates at `u8::MAX`.
+                if expect_len != u8::MAX && page.chain_len() != expect_len {
+                    kinds.push(ViolationKind::ChainLength);
+                }
+                expect_len = match page.chain_len() {
+                    u8::MAX => u8::MAX,
+                    len => len.saturating_sub(1),
+                };
+                if page.epoch() > last_epoch {
+                    kinds.push(ViolationKind::ChainEpoch);
+                }
+                last_epoch = page.epoch();
+                match page.kind() {
+                    PageKind::Data => {
+                        let limit = split.as_ref().map(|(k, _)| k.as_slice());
+                        let out_of_range = |raw: &[u8]| {
+                            // Items at or after the split key are hidden by the split delta.
+                            let hidden = limit.map_or(false, |limit| raw >= limit);
+                            !hidden
+                                && (raw < node.start.as_slice()
+                                    || node.end.as_ref().map_or(false, |end| raw >= end))
+                        };
+                        match page.tier() {
+                            PageTier::Leaf => {
+                                let page = ValuePageRef::from(page);
+                                let mut last = None;
+                                for i in 0..page.len() {
+                                    let (k, _) = page.get(i).unwrap();
+                                    if last.map_or(false, |last| last >= k) {
+                                        kinds.push(ViolationKind::UnorderedItems);
+                                    }
+                                    if out_of_range(k.raw) {
+                                        kinds.push(ViolationKind::OutOfRange);
+                                    }
+                                    last = Some(k);
+                                }
+                            }
+                            PageTier::Inner => {
+                                let page = IndexPageRef::from(page);
+                                let mut last = None;
+                                for i in 0..page.len() {
+                                    let (k, index) = page.get(i).unwrap();
+                                    if last.map_or(false, |last| last >= k) {
+                                        kinds.push(ViolationKind::UnorderedItems);
+                                    }
+                                    // Placeholders mark range ends of children.
+                                    if index != NULL_INDEX && out_of_range(k) {
+                                        kinds.push(ViolationKind::OutOfRange);
+                                    }
+                                    last = Some(k);
+                                }
+                            }
+                        }
+                    }
+                    PageKind::Split => {
+                        if split.is_none() {
+                            let (split_key, index) = split_delta_from_page(page);
+                            split = Some((split_key.to_vec(), index));
+                        }
+                    }
+                    PageKind::RangeDelete => {
+                        let tombstone = RangeTombstone::from_page(page);
+                        let end_out_of_range = match (tombstone.end, node.end.as_ref()) {
+                            (Some(end), Some(node_end)) => end > node_end.as_slice(),
+                            (None, Some(_)) => true,
+                            (_, None) => false,
+                        };
+                        if tombstone.start < node.start.as_slice()
+                            || tombstone.end.map_or(false, |end| end <= tombstone.start)
+                            || end_out_of_range
+                        {
+                            kinds.push(ViolationKind::OutOfRange);
+                        }
+                    }
+                }
+                false
+            },
+            CacheOption::default(),
+        )
+        .await?;
+        // The last page in the chain must have a chain length of one.
+        if expect_len != 0 && expect_len != u8::MAX {
+            kinds.push(ViolationKind::ChainLength);
+        }
+        kinds.dedup();
+        for kind in kinds {
+            report.violate(node.id, kind);
+        }
+        report.num_delta_pages += num_pages - 1;
+
+        let mut children = Vec::new();
+        if head.tier().is_inner() {
+            let view = PageView {
+                id: node.id,
+                addr,
+                page: head.clone(),
+                range: None,
+            };
+            let iter = self.iter_page(&view).await?;
+            for (start, index) in MergingInnerPageIter::new(iter) {
+                children.push((start.to_vec(), index));
+            }
+        }
+        Ok(Some(VerifyPage {
+            tier: head.tier(),
+            split,
+            children,
+        }))
+    }
+}
@@ -20,6 +20,10 @@ impl Counter {
     pub(crate) fn add(&self, n: u64) -> u64 {
         self.0.fetch_add(n, Ordering::Relaxed)
     }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
 }
 
 impl Default for Counter {
@@ -27,3 +31,24 @@ impl Default for Counter {
         Self::new(0)
     }
 }
+
+/// An atomic gauge that keeps the maximum value with relaxed memory ordering.
+#[derive(Debug, Default)]
+pub(crate) struct MaxGauge(AtomicU64);
+
+impl MaxGauge {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn update(&self, value: u64) {
+        // Avoid the read-modify-write in the common case.
+        if value > self.get() {
+            self.0.fetch_max(value, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,69 @@
+use std::cell::Cell;
+
+/// Steps to spin before yielding.
+const SPIN_LIMIT: u32 = 6;
+
+/// An exponential backoff with jitter for retrying contended operations.
+///
+/// It spins for a random number of iterations within an exponentially
+/// increasing bound first, and yields the current task once the bound is
+/// large enough.
+#[derive(Debug, Default)]
+pub(crate) struct Backoff {
+    retries: u32,
+}
+
+impl Backoff {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of times it has backed off.
+    pub(crate) fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Backs off before the next retry.
+    pub(crate) async fn snooze(&mut self) {
+        if self.retries <= SPIN_LIMIT {
+            // Spins randomly in [bound / 2, bound] to avoid retrying in lockstep.
+            let bound = 1u32 << self.retries;
+            let spins = bound / 2 + jitter() % (bound / 2 + 1);
+            for _ in 0..spins {
+                std::hint::spin_loop();
+            }
+        } else {
+            super::task::yield_now().await;
+        }
+        self.retries = self.retries.saturating_add(1);
+    }
+}
+
+/// Returns a pseudo random number from a thread-local xorshift generator.
+fn jitter() -> u32 {
+    thread_local! {
+        static STATE: Cell<u32> = Cell::new(0x9e37_79b9);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        state.set(x);
+        x
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[photonio::test]
+    async fn snooze() {
+        let mut backoff = Backoff::new();
+        for i in 0..SPIN_LIMIT + 2 {
+            assert_eq!(backoff.retries(), i);
+            backoff.snooze().await;
+        }
+    }
+}
@@ -128,7 +128,7 @@ impl<'a> Drop for LatchWaiter<'a> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests {
     use std::sync::Arc;
 
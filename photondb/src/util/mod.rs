@@ -1,6 +1,12 @@
 pub(crate) mod atomic;
+pub(crate) mod backoff;
 pub(crate) mod bitmap;
+pub(crate) mod cache_padded;
+pub(crate) mod json;
 pub(crate) mod latch;
 pub(crate) mod linked_list;
 pub(crate) mod notify;
+pub(crate) mod prefetch;
 pub(crate) mod shutdown;
+pub(crate) mod task;
+pub(crate) mod trace;
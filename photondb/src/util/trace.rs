@@ -0,0 +1,37 @@
+//! Instrumentation with [`tracing`] if the `tracing` feature is enabled.
+//!
+//! The macros compile to nothing if the feature is disabled, so they can be
+//! used on hot paths.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+/// Instruments a future with a debug span.
+///
+/// The arguments after the future are passed to `tracing::debug_span!`. The
+/// span is created before the future, so its fields can borrow values that are
+/// moved into the future.
+macro_rules! instrument {
+    ($fut:expr, $($span:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        let fut = {
+            let span = tracing::debug_span!($($span)+);
+            tracing::Instrument::instrument($fut, span)
+        };
+        #[cfg(not(feature = "tracing"))]
+        let fut = $fut;
+        fut
+    }};
+}
+
+/// Records a debug event in the current span.
+///
+/// The arguments are passed to `tracing::debug!`.
+macro_rules! event {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)+);
+    };
+}
+
+pub(crate) use event;
+pub(crate) use instrument;
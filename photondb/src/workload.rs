@@ -0,0 +1,183 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+
+use crate::{Error, Result};
+
+/// The kind of a captured operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkloadOpKind {
+    /// A point read.
+    Get,
+    /// A write of a value.
+    Put,
+    /// A delete of a key.
+    Delete,
+}
+
+impl WorkloadOpKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::Put => "put",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// An operation captured in a workload trace.
+///
+/// Each operation is written as a line of
+/// `<timestamp_us> <get|put|delete> <key_hex> <value_size>`, where the value
+/// size of a get is the size of the value found, and 0 if the key is not
+/// found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkloadOp {
+    /// The time of the operation, in microseconds since the Unix epoch.
+    pub timestamp_us: u64,
+    /// The kind of the operation.
+    pub kind: WorkloadOpKind,
+    /// The key of the operation.
+    pub key: Vec<u8>,
+    /// The size of the value written or read.
+    pub value_size: usize,
+}
+
+impl WorkloadOp {
+    /// Parses an operation from a line of a workload trace.
+    pub fn parse(line: &str) -> Result<Self> {
+        let invalid = || Error::InvalidArgument(format!("invalid workload op {line:?}"));
+        let fields: Vec<_> = line.split(' ').collect();
+        let [ts, kind, key, value_size] = fields[..] else {
+            return Err(invalid());
+        };
+        let kind = match kind {
+            "get" => WorkloadOpKind::Get,
+            "put" => WorkloadOpKind::Put,
+            "delete" => WorkloadOpKind::Delete,
+            _ => return Err(invalid()),
+        };
+        if key.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        let key = (0..key.len())
+            .step_by(2)
+            .map(|i| {
+                key.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            timestamp_us: ts.parse().map_err(|_| invalid())?,
+            kind,
+            key,
+            value_size: value_size.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Records the operations on a table in a workload trace file.
+///
+/// See [`WorkloadOp`] for the format of the trace.
+#[derive(Debug, Default)]
+pub(crate) struct WorkloadRecorder {
+    enabled: AtomicBool,
+    writer: Mutex<Option<BufWriter<File>>>,
+}
+
+impl WorkloadRecorder {
+    /// Starts recording to the file in the path.
+    ///
+    /// The file is truncated if it exists. If recording has been started, the
+    /// previous file is closed.
+    pub(crate) fn start(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = self.writer.lock();
+        if let Some(mut w) = writer.replace(BufWriter::new(file)) {
+            w.flush()?;
+        }
+        self.enabled.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Stops recording and flushes the trace file.
+    pub(crate) fn stop(&self) -> io::Result<()> {
+        self.enabled.store(false, Ordering::Release);
+        match self.writer.lock().take() {
+            Some(mut w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Records an operation.
+    ///
+    /// Recording is stopped if the trace file fails to write.
+    pub(crate) fn record(&self, kind: WorkloadOpKind, key: &[u8], value_size: usize) {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let mut line = format!("{ts} {} ", kind.as_str());
+        for b in key {
+            line.push_str(&format!("{b:02x}"));
+        }
+        line.push_str(&format!(" {value_size}\n"));
+
+        let mut writer = self.writer.lock();
+        if let Some(w) = writer.as_mut() {
+            if let Err(err) = w.write_all(line.as_bytes()) {
+                warn!("stop workload capture: {err}");
+                self.enabled.store(false, Ordering::Release);
+                writer.take();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workload_recorder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("workload");
+        let recorder = WorkloadRecorder::default();
+        recorder.start(&path).unwrap();
+        recorder.record(WorkloadOpKind::Put, b"\x00a", 3);
+        recorder.record(WorkloadOpKind::Get, b"", 0);
+        recorder.stop().unwrap();
+        recorder.record(WorkloadOpKind::Delete, b"a", 0);
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        let ops: Vec<_> = trace
+            .lines()
+            .map(|line| WorkloadOp::parse(line).unwrap())
+            .collect();
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].kind, WorkloadOpKind::Put);
+        assert_eq!(ops[0].key, b"\x00a");
+        assert_eq!(ops[0].value_size, 3);
+        assert_eq!(ops[1].kind, WorkloadOpKind::Get);
+        assert_eq!(ops[1].key, b"");
+        assert!(ops[0].timestamp_us <= ops[1].timestamp_us);
+
+        assert!(WorkloadOp::parse("1 scan 00 0").is_err());
+        assert!(WorkloadOp::parse("1 get 0 0").is_err());
+        assert!(WorkloadOp::parse("1 get zz 0").is_err());
+        assert!(WorkloadOp::parse("1 get 00").is_err());
+    }
+}
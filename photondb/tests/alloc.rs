@@ -0,0 +1,92 @@
+//! Tests of the heap allocations of table operations.
+//!
+//! These are integration tests so that the counting allocator is only
+//! installed in this test binary.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use photondb::{std::Table, TableOptions};
+use tempfile::tempdir;
+
+/// Counts the allocations of each thread, so that background threads of the
+/// table don't interfere with the counts.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Returns the number of allocations of the current thread in `f`.
+fn count_allocs<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCS.with(|n| n.get());
+    let value = f();
+    let end = ALLOCS.with(|n| n.get());
+    (value, end - start)
+}
+
+fn options() -> TableOptions {
+    // The options are non-exhaustive outside of the crate.
+    let mut options = TableOptions::default();
+    options.page_size = 128;
+    options.page_store.cache_capacity = 8 << 20;
+    // Hot pages are consolidated by reads, which allocates.
+    options.hot_page_sample_interval = 0;
+    options
+}
+
+#[test]
+fn cached_get_without_allocations() {
+    const N: u64 = 1000;
+    let path = tempdir().unwrap();
+    let table = Table::open(&path, options()).unwrap();
+    for i in 0..N {
+        let buf = i.to_be_bytes();
+        table.put(&buf, i, &buf).unwrap();
+    }
+    table.close().unwrap();
+
+    // Reopens the table, so that pages are read from files through the cache.
+    let table = Table::open(&path, options()).unwrap();
+    for _ in 0..2 {
+        let guard = table.pin();
+        for i in 0..N {
+            let buf = i.to_be_bytes();
+            assert_eq!(guard.get(&buf, N).unwrap(), Some(&buf[..]));
+        }
+    }
+    let misses = table.stats().store.page_cache.lookup_miss;
+
+    for i in 0..N {
+        let buf = i.to_be_bytes();
+        let (found, allocs) = count_allocs(|| {
+            let guard = table.pin();
+            guard.get(&buf, N).unwrap() == Some(&buf[..])
+        });
+        assert!(found);
+        assert_eq!(allocs, 0, "get of key {i} allocates");
+    }
+    assert_eq!(table.stats().store.page_cache.lookup_miss, misses);
+    table.close().unwrap();
+}
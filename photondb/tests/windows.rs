@@ -0,0 +1,74 @@
+//! Tests of the storage on Windows with the [`Std`] environment.
+//!
+//! These are integration tests so that they only depend on the public APIs
+//! that are available on Windows, and can run without the unit tests, which
+//! rely on PhotonIO.
+//!
+//! [`Std`]: photondb::env::Std
+
+#![cfg(windows)]
+
+use photondb::{
+    env::{Env, Std},
+    std::Table,
+    Error, MaintenanceCommand, TableOptions,
+};
+use tempfile::tempdir;
+
+const N: u64 = 1000;
+
+fn options() -> TableOptions {
+    // The options are non-exhaustive outside of the crate.
+    let mut options = TableOptions::default();
+    options.page_size = 128;
+    options
+}
+
+#[test]
+fn reopen_table() {
+    let path = tempdir().unwrap();
+    let table = Table::open(&path, options()).unwrap();
+    for i in 0..N {
+        let buf = i.to_be_bytes();
+        table.put(&buf, i, &buf).unwrap();
+    }
+    table.run_maintenance(MaintenanceCommand::Flush).unwrap();
+    table.close().unwrap();
+
+    // Reopens twice to replace the manifest of the first reopen.
+    for _ in 0..2 {
+        let table = Table::open(&path, options()).unwrap();
+        for i in 0..N {
+            let buf = i.to_be_bytes();
+            assert_eq!(table.get(&buf, i).unwrap(), Some(buf.to_vec()));
+        }
+        assert!(table.verify().unwrap().is_ok());
+        table.close().unwrap();
+    }
+}
+
+#[test]
+fn lock_table() {
+    let path = tempdir().unwrap();
+    let table = Table::open(&path, options()).unwrap();
+    assert!(matches!(Table::open(&path, options()), Err(Error::Busy)));
+    table.close().unwrap();
+    let table = Table::open(&path, options()).unwrap();
+    table.close().unwrap();
+}
+
+#[test]
+fn rename_and_sync() {
+    let path = tempdir().unwrap();
+    let (from, to) = (path.path().join("from"), path.path().join("to"));
+    std::fs::write(&from, "new").unwrap();
+    std::fs::write(&to, "old").unwrap();
+    futures::executor::block_on(async {
+        Std.rename(&from, &to).await.unwrap();
+        let dir = Std.open_dir(path.path()).await.unwrap();
+        photondb::env::Directory::sync_all(&dir).await.unwrap();
+        assert!(Std.open_dir(&to).await.is_err());
+    });
+    assert!(!from.exists());
+    assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+}
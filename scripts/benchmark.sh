@@ -325,7 +325,7 @@ function run_fillseq {
 
   echo "Loading $num_keys keys sequentially"
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=fillseq \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=fillseq \
        $params_fillseq \
        --use-existing-db=0 \
        --threads=1 \
@@ -349,7 +349,7 @@ function run_bulkload {
   echo "Bulk loading $num_keys random keys"
   log_file_name=$output_dir/benchmark_bulkload_fillrandom.t${num_threads}.log
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=fillrandom \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=fillrandom \
        --use-existing-db=0 \
        $params_bulkload \
        --threads=${num_threads} \
@@ -374,7 +374,7 @@ function run_change {
   echo "Do $num_keys random $output_name"
   log_file_name="$output_dir/benchmark_${output_name}.t${num_threads}.s${syncval}.log"
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=$benchmarks \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=$benchmarks \
         $params_w \
        --use-existing-db=1 \
        --threads=$num_threads \
@@ -396,7 +396,7 @@ function run_readrandom {
   echo "Reading $num_keys random keys"
   log_file_name="${output_dir}/benchmark_readrandom.t${num_threads}.log"
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=readrandom \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=readrandom \
         $params_w \
        --use-existing-db=1 \
        --threads=$num_threads \
@@ -418,7 +418,7 @@ function run_readwhilewriting {
   echo "Read while writing $num_keys random keys"
   log_file_name="${output_dir}/benchmark_readwhilewriting.t${num_threads}.log"
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=readrandomwriterandom \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=readrandomwriterandom \
         $params_w \
         --use-existing-db=1 \
         --threads=$num_threads \
@@ -441,7 +441,7 @@ function run_waitforreclaiming {
   echo "Wait for reclaiming"
   log_file_name="${output_dir}/benchmark_waitforreclaiming.log"
   time_cmd=$( get_cmd $log_file_name.time )
-  cmd="$time_cmd ./target/release/photondb-tools bench --benchmarks=waitforreclaiming \
+  cmd="$time_cmd ./target/release/photondb bench --benchmarks=waitforreclaiming \
         $params_w \
         --use-existing-db=1 \
         --seed-base=$( date +%s ) \